@@ -0,0 +1,1164 @@
+use crate::matcher::matcher;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::str;
+use std::sync::{Arc, RwLock};
+
+/// The user clients are before they authenticate with AUTH.
+pub const DEFAULT_USER: &str = "default";
+
+#[doc(hidden)]
+const KEYSPACE: &str = "keyspace";
+#[doc(hidden)]
+const READ: &str = "read";
+#[doc(hidden)]
+const WRITE: &str = "write";
+#[doc(hidden)]
+const STRING: &str = "string";
+#[doc(hidden)]
+const LIST: &str = "list";
+#[doc(hidden)]
+const SET: &str = "set";
+#[doc(hidden)]
+const HASH: &str = "hash";
+#[doc(hidden)]
+const SORTEDSET: &str = "sortedset";
+#[doc(hidden)]
+const PUBSUB: &str = "pubsub";
+#[doc(hidden)]
+const ADMIN: &str = "admin";
+#[doc(hidden)]
+const CONNECTION: &str = "connection";
+#[doc(hidden)]
+const TRANSACTION: &str = "transaction";
+/// Every category, in the order ACL CAT lists them.
+pub const CATEGORIES: [&str; 12] = [
+    KEYSPACE,
+    READ,
+    WRITE,
+    STRING,
+    LIST,
+    SET,
+    HASH,
+    SORTEDSET,
+    PUBSUB,
+    ADMIN,
+    CONNECTION,
+    TRANSACTION,
+];
+#[doc(hidden)]
+const ALL: &str = "all";
+
+/// Which arguments of a command are keys, to check them against the key patterns of
+/// the user.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+enum KeySpec {
+    #[doc(hidden)]
+    NoKeys,
+    /// The first argument.
+    First,
+    /// The first two arguments, as in COPY and RENAME.
+    Pair,
+    /// Every argument, as in MGET.
+    All,
+    /// Every other argument from the first one, as in MSET.
+    Pairs,
+    /// The destination and then numkeys keys, as in ZUNIONSTORE.
+    Store,
+}
+
+/// The categories and the keys of every command.
+#[doc(hidden)]
+const COMMANDS: &[(&str, &[&str], KeySpec)] = &[
+    ("acl", &[ADMIN], KeySpec::NoKeys),
+    ("append", &[WRITE, STRING], KeySpec::First),
+    ("auth", &[CONNECTION], KeySpec::NoKeys),
+    ("bgrewriteaof", &[ADMIN], KeySpec::NoKeys),
+    ("bgsave", &[ADMIN], KeySpec::NoKeys),
+    ("close", &[CONNECTION], KeySpec::NoKeys),
+    ("config", &[ADMIN], KeySpec::NoKeys),
+    ("copy", &[KEYSPACE, WRITE], KeySpec::Pair),
+    ("dbsize", &[KEYSPACE, READ], KeySpec::NoKeys),
+    ("debug", &[ADMIN], KeySpec::NoKeys),
+    ("decrby", &[WRITE, STRING], KeySpec::First),
+    ("del", &[KEYSPACE, WRITE], KeySpec::All),
+    ("discard", &[TRANSACTION], KeySpec::NoKeys),
+    ("exec", &[TRANSACTION], KeySpec::NoKeys),
+    ("exists", &[KEYSPACE, READ], KeySpec::All),
+    ("expire", &[KEYSPACE, WRITE], KeySpec::First),
+    ("expireat", &[KEYSPACE, WRITE], KeySpec::First),
+    ("expiretime", &[KEYSPACE, READ], KeySpec::First),
+    ("flushall", &[KEYSPACE, WRITE], KeySpec::NoKeys),
+    ("flushdb", &[KEYSPACE, WRITE], KeySpec::NoKeys),
+    ("get", &[READ, STRING], KeySpec::First),
+    ("getdel", &[WRITE, STRING], KeySpec::First),
+    ("getex", &[WRITE, STRING], KeySpec::First),
+    ("getset", &[WRITE, STRING], KeySpec::First),
+    ("hdel", &[WRITE, HASH], KeySpec::First),
+    ("hello", &[CONNECTION], KeySpec::NoKeys),
+    ("hexists", &[READ, HASH], KeySpec::First),
+    ("hget", &[READ, HASH], KeySpec::First),
+    ("hgetall", &[READ, HASH], KeySpec::First),
+    ("hincrby", &[WRITE, HASH], KeySpec::First),
+    ("hincrbyfloat", &[WRITE, HASH], KeySpec::First),
+    ("hkeys", &[READ, HASH], KeySpec::First),
+    ("hlen", &[READ, HASH], KeySpec::First),
+    ("hmget", &[READ, HASH], KeySpec::First),
+    ("hrandfield", &[READ, HASH], KeySpec::First),
+    ("hscan", &[READ, HASH], KeySpec::First),
+    ("hset", &[WRITE, HASH], KeySpec::First),
+    ("hsetnx", &[WRITE, HASH], KeySpec::First),
+    ("hstrlen", &[READ, HASH], KeySpec::First),
+    ("hvals", &[READ, HASH], KeySpec::First),
+    ("incrby", &[WRITE, STRING], KeySpec::First),
+    ("info", &[ADMIN], KeySpec::NoKeys),
+    ("keys", &[KEYSPACE, READ], KeySpec::NoKeys),
+    ("lastsave", &[ADMIN], KeySpec::NoKeys),
+    ("lindex", &[READ, LIST], KeySpec::First),
+    ("llen", &[READ, LIST], KeySpec::First),
+    ("lpop", &[WRITE, LIST], KeySpec::First),
+    ("lpush", &[WRITE, LIST], KeySpec::First),
+    ("lpushx", &[WRITE, LIST], KeySpec::First),
+    ("lrange", &[READ, LIST], KeySpec::First),
+    ("lrem", &[WRITE, LIST], KeySpec::First),
+    ("lset", &[WRITE, LIST], KeySpec::First),
+    ("mget", &[READ, STRING], KeySpec::All),
+    ("monitor", &[ADMIN], KeySpec::NoKeys),
+    ("move", &[KEYSPACE, WRITE], KeySpec::First),
+    ("mset", &[WRITE, STRING], KeySpec::Pairs),
+    ("multi", &[TRANSACTION], KeySpec::NoKeys),
+    ("persist", &[KEYSPACE, WRITE], KeySpec::First),
+    ("pexpire", &[KEYSPACE, WRITE], KeySpec::First),
+    ("pexpireat", &[KEYSPACE, WRITE], KeySpec::First),
+    ("pexpiretime", &[KEYSPACE, READ], KeySpec::First),
+    ("ping", &[CONNECTION], KeySpec::NoKeys),
+    ("psetex", &[WRITE, STRING], KeySpec::First),
+    ("psubscribe", &[PUBSUB], KeySpec::NoKeys),
+    ("pttl", &[KEYSPACE, READ], KeySpec::First),
+    ("publish", &[PUBSUB], KeySpec::NoKeys),
+    ("pubsub", &[PUBSUB], KeySpec::NoKeys),
+    ("punsubscribe", &[PUBSUB], KeySpec::NoKeys),
+    ("quit", &[CONNECTION], KeySpec::NoKeys),
+    ("rename", &[KEYSPACE, WRITE], KeySpec::Pair),
+    ("reset", &[CONNECTION], KeySpec::NoKeys),
+    ("rpop", &[WRITE, LIST], KeySpec::First),
+    ("rpush", &[WRITE, LIST], KeySpec::First),
+    ("rpushx", &[WRITE, LIST], KeySpec::First),
+    ("sadd", &[WRITE, SET], KeySpec::First),
+    ("save", &[ADMIN], KeySpec::NoKeys),
+    ("scard", &[READ, SET], KeySpec::First),
+    ("select", &[CONNECTION], KeySpec::NoKeys),
+    ("set", &[WRITE, STRING], KeySpec::First),
+    ("setex", &[WRITE, STRING], KeySpec::First),
+    ("setnx", &[WRITE, STRING], KeySpec::First),
+    ("shutdown", &[ADMIN], KeySpec::NoKeys),
+    ("sismember", &[READ, SET], KeySpec::First),
+    ("smembers", &[READ, SET], KeySpec::First),
+    ("sort", &[KEYSPACE, READ], KeySpec::First),
+    ("spublish", &[PUBSUB], KeySpec::NoKeys),
+    ("srem", &[WRITE, SET], KeySpec::First),
+    ("ssubscribe", &[PUBSUB], KeySpec::NoKeys),
+    ("strlen", &[READ, STRING], KeySpec::First),
+    ("subscribe", &[PUBSUB], KeySpec::NoKeys),
+    ("sunsubscribe", &[PUBSUB], KeySpec::NoKeys),
+    ("swapdb", &[KEYSPACE, WRITE], KeySpec::NoKeys),
+    ("touch", &[KEYSPACE, READ], KeySpec::All),
+    ("ttl", &[KEYSPACE, READ], KeySpec::First),
+    ("type", &[KEYSPACE, READ], KeySpec::First),
+    ("unsubscribe", &[PUBSUB], KeySpec::NoKeys),
+    ("unwatch", &[TRANSACTION], KeySpec::NoKeys),
+    ("watch", &[TRANSACTION], KeySpec::All),
+    ("zadd", &[WRITE, SORTEDSET], KeySpec::First),
+    ("zcard", &[READ, SORTEDSET], KeySpec::First),
+    ("zcount", &[READ, SORTEDSET], KeySpec::First),
+    ("zdiffstore", &[WRITE, SORTEDSET], KeySpec::Store),
+    ("zincrby", &[WRITE, SORTEDSET], KeySpec::First),
+    ("zinterstore", &[WRITE, SORTEDSET], KeySpec::Store),
+    ("zpopmax", &[WRITE, SORTEDSET], KeySpec::First),
+    ("zpopmin", &[WRITE, SORTEDSET], KeySpec::First),
+    ("zrange", &[READ, SORTEDSET], KeySpec::First),
+    ("zrank", &[READ, SORTEDSET], KeySpec::First),
+    ("zrem", &[WRITE, SORTEDSET], KeySpec::First),
+    ("zremrangebylex", &[WRITE, SORTEDSET], KeySpec::First),
+    ("zremrangebyrank", &[WRITE, SORTEDSET], KeySpec::First),
+    ("zremrangebyscore", &[WRITE, SORTEDSET], KeySpec::First),
+    ("zrevrank", &[READ, SORTEDSET], KeySpec::First),
+    ("zscore", &[READ, SORTEDSET], KeySpec::First),
+    ("zunionstore", &[WRITE, SORTEDSET], KeySpec::Store),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum AclError {
+    InvalidRule(String, &'static str),
+    UnknownCategory(String),
+    AuthRequired,
+    NoCommandPermission(String, String),
+    NoKeyPermission,
+    NoChannelPermission,
+    DefaultUserRemoval,
+    NoAclFile,
+    AclFile(String),
+}
+
+/// A user of the server: whether it can log in, its passwords and what it's allowed
+/// to do.
+///
+/// Users are changed with rules, the same as ACL SETUSER takes:
+/// - on, off: enables or disables the user.
+/// - passwords: >password adds one and <password removes it. #hash and !hash do
+///   the same with the SHA-256 of the password, in hexadecimal.
+/// - nopass: any password is valid. resetpass removes every password and nopass.
+/// - ~pattern, allkeys, resetkeys: the keys the user can access, as glob patterns.
+/// - &pattern, allchannels, resetchannels: the channels the user can publish and
+///   subscribe to.
+/// - +command, -command, +@category, -@category: allows or denies a command or
+///   every command of a category. allcommands is +@all and nocommands is -@all.
+/// - reset: back to a new user: off, without passwords, keys, channels or commands.
+#[derive(Clone, Debug, PartialEq)]
+pub struct User {
+    #[doc(hidden)]
+    name: String,
+    #[doc(hidden)]
+    enabled: bool,
+    #[doc(hidden)]
+    nopass: bool,
+    #[doc(hidden)]
+    passwords: Vec<String>,
+    #[doc(hidden)]
+    commands: HashSet<&'static str>,
+    #[doc(hidden)]
+    command_rules: Vec<String>,
+    #[doc(hidden)]
+    keys: Vec<String>,
+    #[doc(hidden)]
+    channels: Vec<String>,
+}
+
+impl User {
+    /// Creates a new User that can't log in nor run any command.
+    pub fn new(name: &str) -> User {
+        User {
+            name: name.to_string(),
+            enabled: false,
+            nopass: false,
+            passwords: Vec::new(),
+            commands: HashSet::new(),
+            command_rules: vec![format!("-@{}", ALL)],
+            keys: Vec::new(),
+            channels: Vec::new(),
+        }
+    }
+
+    /// Applies rule to the user.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut user = User::new("alice");
+    /// user.apply("+@read")?;
+    /// user.apply("~cache:*")?;
+    ///
+    /// assert!(user.apply("+@unknown").is_err());
+    /// ```
+    pub fn apply(&mut self, rule: &str) -> Result<(), AclError> {
+        let invalid = |reason| AclError::InvalidRule(rule.to_string(), reason);
+
+        match rule.to_ascii_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => self.keys = vec!["*".to_string()],
+            "resetkeys" => self.keys.clear(),
+            "allchannels" => self.channels = vec!["*".to_string()],
+            "resetchannels" => self.channels.clear(),
+            "allcommands" => self.apply_command_rule(true, &format!("@{}", ALL))?,
+            "nocommands" => self.apply_command_rule(false, &format!("@{}", ALL))?,
+            "reset" => *self = User::new(&self.name),
+            _ => {
+                let (prefix, value) = rule.split_at(rule.chars().next().map_or(0, char::len_utf8));
+                match prefix {
+                    ">" => self.add_password(sha256_hex(value.as_bytes())),
+                    "<" => self.remove_password(&sha256_hex(value.as_bytes()), rule)?,
+                    "#" if is_password_hash(value) => self.add_password(value.to_string()),
+                    "#" => return Err(invalid("The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters")),
+                    "!" => self.remove_password(value, rule)?,
+                    "~" if !self.keys.iter().any(|pattern| pattern == value) => {
+                        self.keys.push(value.to_string())
+                    }
+                    "&" if !self.channels.iter().any(|pattern| pattern == value) => {
+                        self.channels.push(value.to_string())
+                    }
+                    "~" | "&" => {}
+                    "+" => self.apply_command_rule(true, &value.to_ascii_lowercase())?,
+                    "-" => self.apply_command_rule(false, &value.to_ascii_lowercase())?,
+                    _ => return Err(invalid("Syntax error")),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the user as a line of ACL LIST: its name and the rules that would
+    /// create it, which is also how it's saved to the aclfile.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut user = User::new("alice");
+    /// user.apply("on")?;
+    /// user.apply("~cache:*")?;
+    /// user.apply("+get")?;
+    ///
+    /// assert_eq!(user.describe(), "user alice on ~cache:* resetchannels -@all +get");
+    /// ```
+    pub fn describe(&self) -> String {
+        let mut rules = vec!["user".to_string(), self.name.clone()];
+        rules.extend(self.flags().iter().map(|flag| flag.to_string()));
+        rules.extend(self.passwords.iter().map(|hash| format!("#{}", hash)));
+        if !self.keys.is_empty() {
+            rules.push(self.keys());
+        }
+        if self.channels.is_empty() {
+            rules.push("resetchannels".to_string());
+        } else {
+            rules.push(self.channels());
+        }
+        rules.push(self.commands());
+
+        rules.join(" ")
+    }
+
+    /// Returns on or off, and nopass if the user has no password.
+    pub fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+        if self.nopass {
+            flags.push("nopass");
+        }
+        flags
+    }
+
+    /// Returns the SHA-256 of the passwords of the user.
+    pub fn passwords(&self) -> &[String] {
+        &self.passwords
+    }
+
+    /// Returns the rules that set the commands the user can run.
+    pub fn commands(&self) -> String {
+        self.command_rules.join(" ")
+    }
+
+    /// Returns the patterns of the keys the user can access.
+    pub fn keys(&self) -> String {
+        let keys: Vec<String> = self.keys.iter().map(|key| format!("~{}", key)).collect();
+        keys.join(" ")
+    }
+
+    /// Returns the patterns of the channels the user can access.
+    pub fn channels(&self) -> String {
+        let channels: Vec<String> = self
+            .channels
+            .iter()
+            .map(|channel| format!("&{}", channel))
+            .collect();
+        channels.join(" ")
+    }
+
+    /// Returns Ok if the user can run command: the command is allowed and every key
+    /// and channel it takes matches one of the patterns of the user. A command this
+    /// list doesn't know is never allowed, so a command added to the server is
+    /// denied until it is given its categories here.
+    pub fn can_run(&self, command: &[Vec<u8>]) -> Result<(), AclError> {
+        let name = str::from_utf8(&command[0]).unwrap_or_default();
+        let args = &command[1..];
+        if name == "acl"
+            && args
+                .first()
+                .is_some_and(|subcommand| subcommand.eq_ignore_ascii_case(b"whoami"))
+        {
+            return Ok(());
+        }
+        let keys = match COMMANDS.iter().find(|(command, _, _)| *command == name) {
+            Some((_, _, keys)) if self.commands.contains(name) => keys,
+            _ => {
+                return Err(AclError::NoCommandPermission(
+                    self.name.clone(),
+                    name.to_string(),
+                ))
+            }
+        };
+
+        let allowed = |patterns: &[String], name: &[u8]| {
+            patterns
+                .iter()
+                .any(|pattern| matcher(name, pattern.as_bytes()))
+        };
+        if !command_keys(*keys, args)
+            .iter()
+            .all(|key| allowed(&self.keys, key))
+        {
+            return Err(AclError::NoKeyPermission);
+        }
+        if !command_channels(name, args)
+            .iter()
+            .all(|channel| allowed(&self.channels, channel))
+        {
+            return Err(AclError::NoChannelPermission);
+        }
+        // A pattern could match channels the user can't access, so it has to be
+        // one of the patterns of the user, as it is.
+        if name == "psubscribe"
+            && !args.iter().all(|pattern| {
+                self.channels
+                    .iter()
+                    .any(|allowed| allowed == "*" || allowed.as_bytes() == &pattern[..])
+            })
+        {
+            return Err(AclError::NoChannelPermission);
+        }
+
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    fn add_password(&mut self, hash: String) {
+        self.nopass = false;
+        if !self.passwords.contains(&hash) {
+            self.passwords.push(hash);
+        }
+    }
+
+    #[doc(hidden)]
+    fn remove_password(&mut self, hash: &str, rule: &str) -> Result<(), AclError> {
+        match self.passwords.iter().position(|password| password == hash) {
+            Some(position) => {
+                self.passwords.remove(position);
+                Ok(())
+            }
+            None => Err(AclError::InvalidRule(
+                rule.to_string(),
+                "The password you are trying to remove from the user does not exist",
+            )),
+        }
+    }
+
+    /// Allows or denies the command or the category, if it starts with @. Allowing
+    /// or denying every command forgets the rules before it.
+    #[doc(hidden)]
+    fn apply_command_rule(&mut self, allow: bool, name: &str) -> Result<(), AclError> {
+        let commands: Vec<&'static str> = match name.strip_prefix('@') {
+            Some(ALL) => COMMANDS.iter().map(|(command, _, _)| *command).collect(),
+            Some(category) => commands_of(category)?,
+            None => match COMMANDS.iter().find(|(command, _, _)| *command == name) {
+                Some((command, _, _)) => vec![*command],
+                None => {
+                    return Err(AclError::InvalidRule(
+                        format!("{}{}", if allow { '+' } else { '-' }, name),
+                        "Unknown command",
+                    ))
+                }
+            },
+        };
+
+        for command in commands {
+            if allow {
+                self.commands.insert(command);
+            } else {
+                self.commands.remove(command);
+            }
+        }
+
+        let rule = format!("{}{}", if allow { '+' } else { '-' }, name);
+        if name == format!("@{}", ALL) {
+            self.command_rules.clear();
+        }
+        self.command_rules.push(rule);
+        Ok(())
+    }
+}
+
+/// The users of the server, shared by every client.
+///
+/// There's always a default user, the one clients are until they authenticate. It
+/// starts enabled, without password and allowed everything, so a server without
+/// users works as one without ACL. requirepass sets its password.
+#[derive(Clone)]
+pub struct Acl {
+    #[doc(hidden)]
+    users: Arc<RwLock<HashMap<String, User>>>,
+}
+
+impl Acl {
+    /// Creates a new Acl with only the default user.
+    pub fn new() -> Acl {
+        let mut users = HashMap::new();
+        users.insert(DEFAULT_USER.to_string(), default_user());
+        Acl {
+            users: Arc::new(RwLock::new(users)),
+        }
+    }
+
+    /// Makes password the only password of the default user, or lets it in without
+    /// password if it's None.
+    pub fn set_requirepass(&self, password: Option<&str>) {
+        let mut users = self.users.write().unwrap();
+        let user = users
+            .entry(DEFAULT_USER.to_string())
+            .or_insert_with(default_user);
+        user.passwords.clear();
+        match password {
+            Some(password) => user.add_password(sha256_hex(password.as_bytes())),
+            None => user.nopass = true,
+        }
+    }
+
+    /// Returns true if user exists, is enabled and needs no password.
+    pub fn is_open(&self, user: &str) -> bool {
+        let users = self.users.read().unwrap();
+        users
+            .get(user)
+            .is_some_and(|user| user.enabled && user.nopass)
+    }
+
+    /// Returns true if user exists, is enabled and password is one of its passwords.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let acl = Acl::new();
+    /// acl.set_user("alice", &["on", ">secret"])?;
+    ///
+    /// assert!(acl.authenticate("alice", b"secret"));
+    /// assert!(!acl.authenticate("alice", b"wrong"));
+    /// ```
+    pub fn authenticate(&self, user: &str, password: &[u8]) -> bool {
+        let users = self.users.read().unwrap();
+        let user = match users.get(user) {
+            Some(user) if user.enabled => user,
+            _ => return false,
+        };
+        let hash = sha256_hex(password);
+
+        user.nopass
+            || user
+                .passwords
+                .iter()
+                .any(|password| same_password(password.as_bytes(), hash.as_bytes()))
+    }
+
+    /// Returns Ok if a client authenticated as user, or not authenticated if it's
+    /// None, can run command. A client that isn't authenticated is the default user
+    /// only while it needs no password, and a user that was disabled or deleted
+    /// has to authenticate again.
+    pub fn check(&self, user: Option<&str>, command: &[Vec<u8>]) -> Result<(), AclError> {
+        let users = self.users.read().unwrap();
+        match users.get(user.unwrap_or(DEFAULT_USER)) {
+            Some(found) if found.enabled && (user.is_some() || found.nopass) => {
+                found.can_run(command)
+            }
+            _ => Err(AclError::AuthRequired),
+        }
+    }
+
+    /// Applies rules to user, creating it if it doesn't exist. If a rule is invalid
+    /// the user is left as it was.
+    pub fn set_user(&self, user: &str, rules: &[&str]) -> Result<(), AclError> {
+        let mut users = self.users.write().unwrap();
+        let mut changed = match users.get(user) {
+            Some(existing) => existing.clone(),
+            None => User::new(user),
+        };
+        for rule in rules {
+            changed.apply(rule)?;
+        }
+
+        users.insert(user.to_string(), changed);
+        Ok(())
+    }
+
+    pub fn get_user(&self, user: &str) -> Option<User> {
+        self.users.read().unwrap().get(user).cloned()
+    }
+
+    /// Deletes users, returning how many existed. The default user can't be deleted.
+    pub fn del_users(&self, names: &[&str]) -> Result<usize, AclError> {
+        if names.contains(&DEFAULT_USER) {
+            return Err(AclError::DefaultUserRemoval);
+        }
+
+        let mut users = self.users.write().unwrap();
+        Ok(names
+            .iter()
+            .filter(|name| users.remove(**name).is_some())
+            .count())
+    }
+
+    /// Returns every user as ACL LIST shows them, sorted by name.
+    pub fn list(&self) -> Vec<String> {
+        let users = self.users.read().unwrap();
+        let mut names: Vec<&String> = users.keys().collect();
+        names.sort();
+        names.iter().map(|name| users[*name].describe()).collect()
+    }
+
+    /// Returns the name of every user, sorted.
+    pub fn users(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.users.read().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Saves every user to path, one per line, as ACL LIST shows them.
+    pub fn save(&self, path: &str) -> Result<(), AclError> {
+        let mut contents = self.list().join("\n");
+        contents.push('\n');
+
+        File::create(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|e| AclError::AclFile(format!("Error saving ACLs to {}: {}", path, e)))
+    }
+
+    /// Replaces the users with the ones saved in path. If the file has any error
+    /// nothing changes. The default user stays as it was if the file doesn't have it.
+    pub fn load(&self, path: &str) -> Result<(), AclError> {
+        let file = File::open(path)
+            .map_err(|e| AclError::AclFile(format!("Error loading ACLs from {}: {}", path, e)))?;
+
+        let mut loaded = HashMap::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let error =
+                |reason: String| AclError::AclFile(format!("{}:{}: {}", path, number + 1, reason));
+            let line = line.map_err(|e| error(e.to_string()))?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                [] => continue,
+                ["user", name, ..] => {
+                    let mut user = User::new(name);
+                    for rule in &words[2..] {
+                        user.apply(rule).map_err(|e| error(e.to_string()))?;
+                    }
+                    loaded.insert(name.to_string(), user);
+                }
+                _ => return Err(error("should start with user <name>".to_string())),
+            }
+        }
+
+        let mut users = self.users.write().unwrap();
+        if !loaded.contains_key(DEFAULT_USER) {
+            let default = users.remove(DEFAULT_USER).unwrap_or_else(default_user);
+            loaded.insert(DEFAULT_USER.to_string(), default);
+        }
+        *users = loaded;
+        Ok(())
+    }
+}
+
+impl Display for AclError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AclError::InvalidRule(rule, reason) => {
+                write!(f, "Error in ACL SETUSER modifier '{}': {}", rule, reason)
+            }
+            AclError::UnknownCategory(category) => {
+                write!(f, "Unknown category '{}'", category)
+            }
+            AclError::AuthRequired => write!(f, "NOAUTH Authentication required."),
+            AclError::NoCommandPermission(user, command) => write!(
+                f,
+                "NOPERM User {} has no permissions to run the '{}' command",
+                user, command
+            ),
+            AclError::NoKeyPermission => write!(f, "NOPERM No permissions to access a key"),
+            AclError::NoChannelPermission => {
+                write!(f, "NOPERM No permissions to access a channel")
+            }
+            AclError::DefaultUserRemoval => write!(f, "The 'default' user cannot be removed"),
+            AclError::NoAclFile => {
+                write!(f, "This server is not configured to use an ACL file")
+            }
+            AclError::AclFile(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// Returns the commands of category, sorted.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// assert_eq!(commands_of("transaction")?, vec!["discard", "exec", "multi", "unwatch", "watch"]);
+/// assert!(commands_of("unknown").is_err());
+/// ```
+pub fn commands_of(category: &str) -> Result<Vec<&'static str>, AclError> {
+    if !CATEGORIES.contains(&category) {
+        return Err(AclError::UnknownCategory(category.to_string()));
+    }
+
+    Ok(COMMANDS
+        .iter()
+        .filter(|(_, categories, _)| categories.contains(&category))
+        .map(|(command, _, _)| *command)
+        .collect())
+}
+
+#[doc(hidden)]
+fn default_user() -> User {
+    let mut user = User::new(DEFAULT_USER);
+    for rule in ["on", "nopass", "allkeys", "allchannels", "allcommands"] {
+        user.apply(rule).unwrap();
+    }
+    user
+}
+
+#[doc(hidden)]
+fn command_keys(spec: KeySpec, args: &[Vec<u8>]) -> Vec<&[u8]> {
+    let keys = args.iter().map(|arg| arg.as_slice());
+    match spec {
+        KeySpec::NoKeys => Vec::new(),
+        KeySpec::First => keys.take(1).collect(),
+        KeySpec::Pair => keys.take(2).collect(),
+        KeySpec::All => keys.collect(),
+        KeySpec::Pairs => keys.step_by(2).collect(),
+        KeySpec::Store => {
+            let numkeys = args
+                .get(1)
+                .and_then(|numkeys| str::from_utf8(numkeys).ok())
+                .and_then(|numkeys| numkeys.parse::<usize>().ok())
+                .unwrap_or(0);
+            keys.clone()
+                .take(1)
+                .chain(keys.skip(2).take(numkeys))
+                .collect()
+        }
+    }
+}
+
+#[doc(hidden)]
+fn command_channels<'a>(name: &str, args: &'a [Vec<u8>]) -> &'a [Vec<u8>] {
+    match name {
+        "subscribe" | "ssubscribe" => args,
+        "publish" | "spublish" => &args[..args.len().min(1)],
+        _ => &[],
+    }
+}
+
+#[doc(hidden)]
+fn is_password_hash(hash: &str) -> bool {
+    hash.len() == 64
+        && hash
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
+
+/// Compares two passwords looking at every byte, so the time it takes doesn't tell how
+/// much of a wrong password was right.
+#[doc(hidden)]
+fn same_password(expected: &[u8], given: &[u8]) -> bool {
+    let difference = expected
+        .iter()
+        .zip(given.iter())
+        .fold(0, |difference, (a, b)| difference | (a ^ b));
+
+    expected.len() == given.len() && difference == 0
+}
+
+#[doc(hidden)]
+const SHA256_ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Returns the SHA-256 of data in hexadecimal, the way passwords are kept, so they
+/// aren't shown by ACL LIST nor saved to the aclfile.
+#[doc(hidden)]
+fn sha256_hex(data: &[u8]) -> String {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut schedule = [0u32; 64];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..64 {
+            let s0 = schedule[i - 15].rotate_right(7)
+                ^ schedule[i - 15].rotate_right(18)
+                ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17)
+                ^ schedule[i - 2].rotate_right(19)
+                ^ (schedule[i - 2] >> 10);
+            schedule[i] = schedule[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (constant, word) in SHA256_ROUND_CONSTANTS.iter().zip(schedule.iter()) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(*constant)
+                .wrapping_add(*word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    state.iter().map(|word| format!("{:08x}", word)).collect()
+}
+
+#[cfg(test)]
+mod acl_test {
+    use super::*;
+    use std::fs;
+
+    fn command(args: &[&str]) -> Vec<Vec<u8>> {
+        args.iter().map(|arg| arg.as_bytes().to_vec()).collect()
+    }
+
+    fn user(rules: &[&str]) -> User {
+        let mut user = User::new("alice");
+        for rule in rules {
+            user.apply(rule).unwrap();
+        }
+        user
+    }
+
+    #[test]
+    fn sha256_of_known_values() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(&[b'a'; 1000]),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn a_new_user_can_not_run_anything() {
+        let alice = User::new("alice");
+
+        assert_eq!(
+            alice.can_run(&command(&["get", "key"])),
+            Err(AclError::NoCommandPermission(
+                "alice".to_string(),
+                "get".to_string()
+            ))
+        );
+        assert_eq!(alice.describe(), "user alice off resetchannels -@all");
+    }
+
+    #[test]
+    fn categories_allow_their_commands() {
+        let alice = user(&["allkeys", "+@read", "-@hash"]);
+
+        assert_eq!(alice.can_run(&command(&["get", "key"])), Ok(()));
+        assert_eq!(
+            alice.can_run(&command(&["lrange", "key", "0", "1"])),
+            Ok(())
+        );
+        assert!(alice.can_run(&command(&["hget", "key", "field"])).is_err());
+        assert!(alice.can_run(&command(&["set", "key", "value"])).is_err());
+        assert_eq!(alice.commands(), "-@all +@read -@hash");
+    }
+
+    #[test]
+    fn single_commands_can_be_allowed_and_denied() {
+        let alice = user(&["allkeys", "+@write", "-flushdb", "+get"]);
+
+        assert_eq!(alice.can_run(&command(&["set", "key", "value"])), Ok(()));
+        assert_eq!(alice.can_run(&command(&["get", "key"])), Ok(()));
+        assert!(alice.can_run(&command(&["flushdb"])).is_err());
+    }
+
+    #[test]
+    fn allowing_every_command_forgets_the_rules_before() {
+        let alice = user(&["+get", "-set", "allcommands"]);
+
+        assert_eq!(alice.commands(), "+@all");
+    }
+
+    #[test]
+    fn keys_must_match_a_pattern() {
+        let alice = user(&["allcommands", "~cache:*", "~user:?"]);
+
+        assert_eq!(alice.can_run(&command(&["get", "cache:a"])), Ok(()));
+        assert_eq!(alice.can_run(&command(&["get", "user:1"])), Ok(()));
+        assert_eq!(
+            alice.can_run(&command(&["get", "user:10"])),
+            Err(AclError::NoKeyPermission)
+        );
+        assert_eq!(
+            alice.can_run(&command(&["mset", "cache:a", "1", "secret", "2"])),
+            Err(AclError::NoKeyPermission)
+        );
+        assert_eq!(
+            alice.can_run(&command(&["mset", "cache:a", "secret", "cache:b", "2"])),
+            Ok(())
+        );
+        assert_eq!(
+            alice.can_run(&command(&["rename", "cache:a", "secret"])),
+            Err(AclError::NoKeyPermission)
+        );
+    }
+
+    #[test]
+    fn store_commands_check_the_destination_and_the_sources() {
+        let alice = user(&["allcommands", "~cache:*"]);
+
+        let store = command(&["zunionstore", "cache:d", "2", "cache:a", "cache:b"]);
+        assert_eq!(alice.can_run(&store), Ok(()));
+        let store = command(&["zunionstore", "cache:d", "2", "cache:a", "secret"]);
+        assert_eq!(alice.can_run(&store), Err(AclError::NoKeyPermission));
+        let store = command(&["zinterstore", "secret", "1", "cache:a"]);
+        assert_eq!(alice.can_run(&store), Err(AclError::NoKeyPermission));
+    }
+
+    #[test]
+    fn channels_must_match_a_pattern() {
+        let alice = user(&["allcommands", "&news.*"]);
+
+        assert_eq!(
+            alice.can_run(&command(&["publish", "news.tech", "hi"])),
+            Ok(())
+        );
+        assert_eq!(
+            alice.can_run(&command(&["subscribe", "news.tech", "sports"])),
+            Err(AclError::NoChannelPermission)
+        );
+        assert_eq!(
+            alice.can_run(&command(&["spublish", "sports", "hi"])),
+            Err(AclError::NoChannelPermission)
+        );
+    }
+
+    #[test]
+    fn patterns_must_be_a_pattern_of_the_user() {
+        let alice = user(&["allcommands", "&news.*"]);
+        let bob = user(&["allcommands", "allchannels"]);
+
+        assert_eq!(alice.can_run(&command(&["psubscribe", "news.*"])), Ok(()));
+        assert_eq!(
+            alice.can_run(&command(&["psubscribe", "news.t*"])),
+            Err(AclError::NoChannelPermission)
+        );
+        assert_eq!(bob.can_run(&command(&["psubscribe", "*"])), Ok(()));
+    }
+
+    #[test]
+    fn unknown_rules_fail() {
+        let mut alice = User::new("alice");
+
+        assert_eq!(
+            alice.apply("+nothing"),
+            Err(AclError::InvalidRule(
+                "+nothing".to_string(),
+                "Unknown command"
+            ))
+        );
+        assert!(alice.apply("+@nothing").is_err());
+        assert!(alice.apply("maybe").is_err());
+        assert!(alice.apply("#1234").is_err());
+        assert!(alice.apply("<secret").is_err());
+    }
+
+    #[test]
+    fn passwords_are_kept_hashed() {
+        let alice = user(&["on", ">secret", ">other", "<other"]);
+
+        assert_eq!(alice.passwords(), &[sha256_hex(b"secret")]);
+        assert!(!alice.describe().contains("secret"));
+    }
+
+    #[test]
+    fn reset_leaves_a_new_user() {
+        let alice = user(&["on", ">secret", "allkeys", "allchannels", "+@all", "reset"]);
+
+        assert_eq!(alice, User::new("alice"));
+    }
+
+    #[test]
+    fn authenticate_with_a_password_of_the_user() {
+        let acl = Acl::new();
+        acl.set_user("alice", &["on", ">secret"]).unwrap();
+        acl.set_user("bob", &[">secret"]).unwrap();
+
+        assert!(acl.authenticate("alice", b"secret"));
+        assert!(!acl.authenticate("alice", b"wrong"));
+        assert!(!acl.authenticate("bob", b"secret"));
+        assert!(!acl.authenticate("carol", b"secret"));
+    }
+
+    #[test]
+    fn default_user_without_password_lets_everyone_in() {
+        let acl = Acl::new();
+
+        assert!(acl.is_open(DEFAULT_USER));
+        assert_eq!(acl.check(None, &command(&["flushall"])), Ok(()));
+    }
+
+    #[test]
+    fn requirepass_sets_the_password_of_the_default_user() {
+        let acl = Acl::new();
+        acl.set_requirepass(Some("secret"));
+
+        assert!(!acl.is_open(DEFAULT_USER));
+        assert!(acl.authenticate(DEFAULT_USER, b"secret"));
+        assert_eq!(
+            acl.check(None, &command(&["get", "key"])),
+            Err(AclError::AuthRequired)
+        );
+        assert_eq!(
+            acl.check(Some(DEFAULT_USER), &command(&["get", "key"])),
+            Ok(())
+        );
+
+        acl.set_requirepass(None);
+        assert!(acl.is_open(DEFAULT_USER));
+    }
+
+    #[test]
+    fn disabled_users_have_to_authenticate_again() {
+        let acl = Acl::new();
+        acl.set_user("alice", &["on", ">secret", "+@all"]).unwrap();
+        acl.set_user("alice", &["off"]).unwrap();
+
+        assert_eq!(
+            acl.check(Some("alice"), &command(&["ping"])),
+            Err(AclError::AuthRequired)
+        );
+    }
+
+    #[test]
+    fn an_invalid_rule_leaves_the_user_as_it_was() {
+        let acl = Acl::new();
+        acl.set_user("alice", &["on", "+get"]).unwrap();
+
+        assert!(acl.set_user("alice", &["+set", "+nothing"]).is_err());
+        assert_eq!(acl.get_user("alice").unwrap().commands(), "-@all +get");
+    }
+
+    #[test]
+    fn default_user_can_not_be_deleted() {
+        let acl = Acl::new();
+        acl.set_user("alice", &[]).unwrap();
+
+        assert_eq!(
+            acl.del_users(&["alice", DEFAULT_USER]),
+            Err(AclError::DefaultUserRemoval)
+        );
+        assert_eq!(acl.del_users(&["alice", "bob"]), Ok(1));
+        assert_eq!(acl.users(), vec![DEFAULT_USER.to_string()]);
+    }
+
+    #[test]
+    fn users_are_saved_and_loaded() {
+        let path = "acl_test_users.acl";
+        let acl = Acl::new();
+        acl.set_requirepass(Some("secret"));
+        acl.set_user(
+            "alice",
+            &["on", ">pass", "~cache:*", "&news", "+@read", "-hget"],
+        )
+        .unwrap();
+        acl.save(path).unwrap();
+
+        let loaded = Acl::new();
+        loaded.set_user("bob", &[]).unwrap();
+        loaded.load(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.list(), acl.list());
+        assert!(loaded.authenticate("alice", b"pass"));
+        assert!(loaded.authenticate(DEFAULT_USER, b"secret"));
+    }
+
+    #[test]
+    fn a_file_with_errors_is_not_loaded() {
+        let path = "acl_test_errors.acl";
+        fs::write(path, "user alice on +get\nuser bob +nothing\n").unwrap();
+        let acl = Acl::new();
+        let result = acl.load(path);
+        fs::remove_file(path).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(acl.users(), vec![DEFAULT_USER.to_string()]);
+    }
+
+    #[test]
+    fn whoami_is_always_allowed() {
+        let alice = User::new("alice");
+
+        assert_eq!(alice.can_run(&command(&["acl", "whoami"])), Ok(()));
+        assert!(alice.can_run(&command(&["acl", "list"])).is_err());
+    }
+
+    #[test]
+    fn unknown_commands_are_denied_even_with_every_command() {
+        let alice = user(&["allcommands", "allkeys"]);
+
+        assert_eq!(
+            alice.can_run(&command(&["notacommand", "key"])),
+            Err(AclError::NoCommandPermission(
+                "alice".to_string(),
+                "notacommand".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn key_patterns_are_globs_not_regular_expressions() {
+        let alice = user(&["allcommands", "~a|"]);
+        assert!(alice.can_run(&command(&["get", "secret"])).is_err());
+        assert_eq!(alice.can_run(&command(&["get", "a|"])), Ok(()));
+
+        let bob = user(&["allcommands", "allkeys"]);
+        assert_eq!(bob.can_run(&command(&["set", "a\nb", "v"])), Ok(()));
+        let carol = user(&["allcommands", "~a*"]);
+        assert_eq!(carol.can_run(&command(&["set", "a\nb", "v"])), Ok(()));
+    }
+}
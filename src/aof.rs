@@ -0,0 +1,564 @@
+use crate::database::Database;
+use crate::databasehelper::DataBaseError;
+use crate::request::{Request, TransactionRequest};
+use crate::resp::{self, Command, RespValue};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How often the append only file is flushed to disk.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AppendFsync {
+    /// After every write, before the client gets its reply.
+    Always,
+    /// Once per second, so a crash loses at most one second of writes.
+    Everysec,
+    /// Never, the operating system decides when.
+    No,
+}
+
+impl AppendFsync {
+    /// Parses the value of the appendfsync option.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// assert_eq!(AppendFsync::parse("always"), Some(AppendFsync::Always));
+    /// assert_eq!(AppendFsync::parse("sometimes"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<AppendFsync> {
+        match value.to_lowercase().as_str() {
+            "always" => Some(AppendFsync::Always),
+            "everysec" => Some(AppendFsync::Everysec),
+            "no" => Some(AppendFsync::No),
+            _ => None,
+        }
+    }
+}
+
+/// An append only file, where every write command is logged as it is executed so
+/// the data can be rebuilt by replaying them.
+///
+/// Commands are written with the RESP format, and a SELECT command is written
+/// every time the database of a command isn't the one of the previous command.
+pub struct Aof {
+    #[doc(hidden)]
+    file: File,
+    #[doc(hidden)]
+    path: String,
+    #[doc(hidden)]
+    fsync: AppendFsync,
+    #[doc(hidden)]
+    selected_db: Option<usize>,
+    #[doc(hidden)]
+    rewriting: bool,
+    #[doc(hidden)]
+    rewrite_buffer: Option<Vec<u8>>,
+    #[doc(hidden)]
+    stopped: bool,
+}
+
+#[doc(hidden)]
+fn open_append(path: &str) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Encodes command as a RESP array of bulk strings.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// let command = vec![b"get".to_vec(), b"key".to_vec()];
+/// assert_eq!(encode_command(&command), b"*2\r\n$3\r\nget\r\n$3\r\nkey\r\n".to_vec());
+/// ```
+pub fn encode_command(command: &[Vec<u8>]) -> Vec<u8> {
+    let values = command
+        .iter()
+        .map(|arg| RespValue::BulkString(arg.to_vec()))
+        .collect();
+    RespValue::Array(values).encode()
+}
+
+impl Aof {
+    /// Opens the append only file at path, creating it if it doesn't exist.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let aof = Aof::open("appendonly.aof", AppendFsync::Everysec)?;
+    /// ```
+    pub fn open(path: &str, fsync: AppendFsync) -> Result<Aof, String> {
+        match open_append(path) {
+            Ok(file) => Ok(Aof {
+                file,
+                path: path.to_string(),
+                fsync,
+                selected_db: None,
+                rewriting: false,
+                rewrite_buffer: None,
+                stopped: false,
+            }),
+            Err(why) => Err(format!("Couldn't open file: {}", why)),
+        }
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Logs command, executed on database db.
+    ///
+    /// With AppendFsync::Always the command is on disk when this returns. If the
+    /// command can't be written, whatever part of it was written is cut off so the
+    /// next commands don't follow half a command. When that fails too nothing else
+    /// is written until the file is rewritten, which leaves the broken command at
+    /// the end of the file, where replay ignores it.
+    pub fn feed(&mut self, db: usize, command: &[Vec<u8>]) {
+        let mut bytes = Vec::new();
+        if self.selected_db != Some(db) {
+            let select = vec![b"select".to_vec(), db.to_string().into_bytes()];
+            bytes.extend_from_slice(&encode_command(&select));
+            self.selected_db = Some(db);
+        }
+        bytes.extend_from_slice(&encode_command(command));
+
+        if let Some(buffer) = self.rewrite_buffer.as_mut() {
+            buffer.extend_from_slice(&bytes);
+        }
+
+        if self.stopped {
+            return;
+        }
+
+        let len = self.file.metadata().map(|metadata| metadata.len());
+        if let Err(e) = self.file.write_all(&bytes) {
+            eprintln!("Couldn't write the append only file: {}", e);
+            self.selected_db = None;
+            if let Err(e) = len.and_then(|len| self.file.set_len(len)) {
+                eprintln!(
+                    "Couldn't cut the append only file, it's stopped until rewritten: {}",
+                    e
+                );
+                self.stopped = true;
+            }
+            return;
+        }
+
+        if self.fsync == AppendFsync::Always {
+            self.sync();
+        }
+    }
+
+    /// Flushes the written commands to disk.
+    pub fn sync(&mut self) {
+        if let Err(e) = self.file.sync_data() {
+            eprintln!("Couldn't sync the append only file: {}", e);
+        }
+    }
+
+    /// Marks the file as being rewritten. Returns false if there's already a rewrite
+    /// in progress.
+    pub fn start_rewrite(&mut self) -> bool {
+        if self.rewriting {
+            return false;
+        }
+
+        self.rewriting = true;
+        true
+    }
+
+    /// Starts keeping a copy of every command fed from now on, to be appended to the
+    /// rewritten file. It has to be called at the same time the data for the rewrite
+    /// is taken, with no write in between.
+    pub fn buffer_rewrite(&mut self) {
+        self.rewrite_buffer = Some(Vec::new());
+        self.selected_db = None;
+    }
+
+    #[doc(hidden)]
+    fn finish_rewrite(&mut self, temp_path: &str) -> io::Result<()> {
+        let mut temp = open_append(temp_path)?;
+        if let Some(buffer) = self.rewrite_buffer.take() {
+            temp.write_all(&buffer)?;
+        }
+        temp.sync_data()?;
+
+        fs::rename(temp_path, &self.path)?;
+        self.file = open_append(&self.path)?;
+        self.rewriting = false;
+        self.stopped = false;
+        Ok(())
+    }
+
+    #[doc(hidden)]
+    fn abort_rewrite(&mut self) {
+        self.rewrite_buffer = None;
+        self.rewriting = false;
+    }
+}
+
+/// Replaces the append only file with rewritten, the commands that rebuild the data
+/// at the moment Aof::buffer_rewrite was called, followed by the commands fed since
+/// then.
+///
+/// The new file is written aside and renamed over the old one, so the old file is
+/// used until the new one is complete. Writers are only blocked while the buffered
+/// commands are appended.
+pub fn rewrite(aof: &Mutex<Aof>, rewritten: &[u8]) -> io::Result<()> {
+    let temp_path = format!("{}.rewrite", aof.lock().unwrap().path());
+
+    let result = fs::write(&temp_path, rewritten)
+        .and_then(|_| aof.lock().unwrap().finish_rewrite(&temp_path));
+
+    if result.is_err() {
+        aof.lock().unwrap().abort_rewrite();
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+/// Shares aof, and flushes it to disk every second if its policy is
+/// AppendFsync::Everysec.
+pub fn run(aof: Aof) -> Arc<Mutex<Aof>> {
+    let everysec = aof.fsync == AppendFsync::Everysec;
+    let aof = Arc::new(Mutex::new(aof));
+
+    if everysec {
+        let aof = aof.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+            aof.lock().unwrap().sync();
+        });
+    }
+
+    aof
+}
+
+/// Executes on database every command logged in the file at path.
+///
+/// A command cut by a crash at the end of the file is ignored, and so are the commands
+/// of a transaction without its EXEC. They're also cut from the file, so the commands
+/// appended from now on are replayed next time.
+///
+/// Returns true if the file had any command, or an error with the byte where the file
+/// stops following the protocol if it isn't just its end that's cut. The file is left
+/// as it is then, as cutting it would lose every command after that byte.
+pub fn replay(path: &str, database: &mut Database) -> Result<bool, DataBaseError> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(_) => return Ok(false),
+    };
+
+    let mut used = 0;
+    let mut valid = 0;
+    let mut replayed = false;
+    let mut transaction: Option<Vec<Command>> = None;
+
+    loop {
+        let command = match resp::decode(&data[used..]) {
+            Ok(Some((command, len))) => {
+                used += len;
+                command
+            }
+            Ok(None) => break,
+            Err(e) => {
+                let reason = format!("{} at byte {}", e, used);
+                return Err(DataBaseError::CorruptAof(reason));
+            }
+        };
+        if command.is_empty() {
+            continue;
+        }
+        replayed = true;
+
+        match Request::new(&command, false) {
+            Request::Transaction(TransactionRequest::Multi) => transaction = Some(Vec::new()),
+            Request::Transaction(TransactionRequest::Exec) => {
+                for command in transaction.take().unwrap_or_default() {
+                    if let Request::DataBase(query) = Request::new(&command, false) {
+                        query.exec_query(database);
+                    }
+                }
+            }
+            Request::DataBase(query) => match transaction.as_mut() {
+                Some(queued) => queued.push(command.clone()),
+                None => {
+                    query.exec_query(database);
+                }
+            },
+            _ => {}
+        }
+        if transaction.is_none() {
+            valid = used;
+        }
+    }
+
+    if valid < data.len() {
+        eprintln!(
+            "Cutting the append only file at byte {}, the last complete command",
+            valid
+        );
+        let truncated = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_len(valid as u64));
+        if let Err(e) = truncated {
+            eprintln!("Couldn't cut the append only file: {}", e);
+        }
+    }
+
+    Ok(replayed)
+}
+
+#[cfg(test)]
+mod aof_test {
+    use super::*;
+    use crate::databasehelper::{DataBaseError, ExpireFlags, SuccessQuery};
+    use crate::eviction::EvictionPolicy;
+    use crate::request::Request;
+
+    const DB_DUMP: &str = "db_dump_aof_test";
+
+    fn command(args: &[&str]) -> Command {
+        args.iter().map(|arg| arg.as_bytes().to_vec()).collect()
+    }
+
+    fn replayed(path: &str) -> Database {
+        let mut database = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        replay(path, &mut database).unwrap();
+        database.select(0).unwrap();
+        database
+    }
+
+    #[test]
+    fn feed_then_replay_keeps_the_database_of_each_command() {
+        let path = "aof_test_feed.aof";
+        let _ = fs::remove_file(path);
+        let mut aof = Aof::open(path, AppendFsync::Always).unwrap();
+        aof.feed(0, &command(&["set", "key", "a"]));
+        aof.feed(2, &command(&["set", "key", "b"]));
+        aof.feed(0, &command(&["append", "key", "c"]));
+
+        let mut database = replayed(path);
+        fs::remove_file(path).unwrap();
+
+        let result = database.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"ac".to_vec()));
+        database.select(2).unwrap();
+        let result = database.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"b".to_vec()));
+    }
+
+    #[test]
+    fn replay_ignores_a_truncated_command() {
+        let path = "aof_test_truncated.aof";
+        let mut data = encode_command(&command(&["set", "key", "a"]));
+        let cut = encode_command(&command(&["set", "key", "b"]));
+        data.extend_from_slice(&cut[..cut.len() - 3]);
+        fs::write(path, data).unwrap();
+
+        let mut database = replayed(path);
+        fs::remove_file(path).unwrap();
+
+        let result = database.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"a".to_vec()));
+    }
+
+    #[test]
+    fn replay_cuts_a_truncated_command_so_later_commands_are_replayed() {
+        let path = "aof_test_cut.aof";
+        let mut data = encode_command(&command(&["set", "key", "a"]));
+        data.extend_from_slice(b"*2\r\n$3\r\nset\r\n");
+        fs::write(path, &data).unwrap();
+        replayed(path);
+
+        let mut aof = Aof::open(path, AppendFsync::Always).unwrap();
+        aof.feed(0, &command(&["append", "key", "b"]));
+        let mut database = replayed(path);
+        fs::remove_file(path).unwrap();
+
+        let result = database.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"ab".to_vec()));
+    }
+
+    #[test]
+    fn evicted_keys_are_deleted_on_replay() {
+        let path = "aof_test_evicted.aof";
+        let _ = fs::remove_file(path);
+        let aof = Aof::open(path, AppendFsync::Always).unwrap();
+        let mut database = Database::new_with_aof(DB_DUMP.to_string(), 16, 16, aof).unwrap();
+        database.set(b"key", b"a").unwrap();
+        database.feed_aof(&command(&["set", "key", "a"]));
+
+        database.set_maxmemory(1, EvictionPolicy::AllkeysRandom);
+        database.free_memory().unwrap();
+
+        let mut database = replayed(path);
+        fs::remove_file(path).unwrap();
+
+        let result = database.exists(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::Boolean(false));
+    }
+
+    #[test]
+    fn replay_refuses_a_file_broken_before_its_end() {
+        let path = "aof_test_garbage.aof";
+        let mut data = encode_command(&command(&["set", "key", "a"]));
+        let offset = data.len();
+        data.extend_from_slice(b"*2\r\n$3\r\nset\r\n");
+        data.extend_from_slice(&encode_command(&command(&["multi"])));
+        fs::write(path, &data).unwrap();
+
+        let mut database = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        let result = replay(path, &mut database);
+        let left = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        match result {
+            Err(DataBaseError::CorruptAof(reason)) => {
+                assert!(reason.ends_with(&format!("at byte {}", offset)))
+            }
+            other => panic!("Expected a corrupt file, got {:?}", other),
+        }
+        assert_eq!(left, data);
+    }
+
+    #[test]
+    fn feed_stops_when_a_failed_write_cant_be_cut() {
+        let path = "aof_test_stopped.aof";
+        let data = encode_command(&command(&["set", "key", "a"]));
+        fs::write(path, &data).unwrap();
+        let mut aof = Aof::open(path, AppendFsync::No).unwrap();
+        aof.file = File::open(path).unwrap();
+
+        aof.feed(0, &command(&["set", "key", "b"]));
+        aof.file = open_append(path).unwrap();
+        aof.feed(0, &command(&["set", "key", "c"]));
+        let left = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert!(aof.stopped);
+        assert_eq!(left, data);
+    }
+
+    #[test]
+    fn replay_ignores_a_transaction_without_exec() {
+        let path = "aof_test_transaction.aof";
+        let mut data = Vec::new();
+        for args in [
+            &["multi"][..],
+            &["set", "key", "a"],
+            &["exec"],
+            &["multi"],
+            &["set", "key", "b"],
+        ] {
+            data.extend_from_slice(&encode_command(&command(args)));
+        }
+        fs::write(path, data).unwrap();
+
+        let mut database = replayed(path);
+        fs::remove_file(path).unwrap();
+
+        let result = database.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"a".to_vec()));
+    }
+
+    #[test]
+    fn rewrite_keeps_the_commands_fed_while_rewriting() {
+        let path = "aof_test_rewrite.aof";
+        let _ = fs::remove_file(path);
+        let aof = run(Aof::open(path, AppendFsync::No).unwrap());
+        aof.lock().unwrap().feed(0, &command(&["set", "key", "a"]));
+        aof.lock().unwrap().feed(0, &command(&["set", "key", "b"]));
+
+        assert!(aof.lock().unwrap().start_rewrite());
+        aof.lock().unwrap().buffer_rewrite();
+        assert!(!aof.lock().unwrap().start_rewrite());
+        aof.lock()
+            .unwrap()
+            .feed(0, &command(&["append", "key", "c"]));
+        let mut rewritten = encode_command(&command(&["select", "0"]));
+        rewritten.extend_from_slice(&encode_command(&command(&["set", "key", "b"])));
+        rewrite(&aof, &rewritten).unwrap();
+        aof.lock()
+            .unwrap()
+            .feed(0, &command(&["append", "key", "d"]));
+
+        let mut database = replayed(path);
+        let data = fs::read(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let result = database.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"bcd".to_vec()));
+        let old_set = encode_command(&command(&["set", "key", "a"]));
+        assert!(!data.windows(old_set.len()).any(|w| w == &old_set[..]));
+    }
+
+    #[test]
+    fn new_with_aof_loads_the_dump_into_an_empty_file() {
+        let path = "aof_test_dump.aof";
+        let dump_path = DB_DUMP.to_string() + "_dump";
+        let _ = fs::remove_file(path);
+        let mut database = Database::new(dump_path.clone(), 16, 16).unwrap();
+        database.set(b"key", b"a").unwrap();
+        database
+            .expire(b"key", 100, ExpireFlags::default())
+            .unwrap();
+        fs::write(&dump_path, database.dump()).unwrap();
+
+        let aof = Aof::open(path, AppendFsync::Always).unwrap();
+        let mut database = Database::new_with_aof(dump_path.clone(), 16, 16, aof).unwrap();
+        database.select(1).unwrap();
+        database.feed_aof(&command(&["set", "key", "b"]));
+        fs::remove_file(&dump_path).unwrap();
+
+        let aof = Aof::open(path, AppendFsync::Always).unwrap();
+        let mut loaded = Database::new_with_aof(dump_path, 16, 16, aof).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let result = loaded.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"a".to_vec()));
+        if let SuccessQuery::Integer(ttl) = loaded.ttl(b"key").unwrap() {
+            assert!(ttl > 0 && ttl <= 100);
+        }
+        loaded.select(1).unwrap();
+        let result = loaded.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"b".to_vec()));
+    }
+
+    #[test]
+    fn relative_expire_times_are_replayed_as_absolute_ones() {
+        let path = "aof_test_expire_times.aof";
+        let _ = fs::remove_file(path);
+        let mut aof = Aof::open(path, AppendFsync::Always).unwrap();
+        for args in [
+            &["set", "key", "a", "nx", "ex", "100"][..],
+            &["set", "key", "b", "nx", "ex", "200"],
+            &["setex", "other", "100", "a"],
+            &["getex", "other", "persist"],
+            &["expire", "other", "50", "xx"],
+        ] {
+            let command = command(args);
+            if let Request::DataBase(query) = Request::new(&command, false) {
+                aof.feed(0, &query.aof_command(&command).unwrap());
+            }
+        }
+
+        let mut database = replayed(path);
+        fs::remove_file(path).unwrap();
+
+        let result = database.get(b"key").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"a".to_vec()));
+        assert_eq!(database.ttl(b"key").unwrap(), SuccessQuery::Integer(100));
+        assert_eq!(database.ttl(b"other").unwrap(), SuccessQuery::Integer(-1));
+    }
+
+    #[test]
+    fn bgrewriteaof_fails_without_an_append_only_file() {
+        let database = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+
+        let result = database.bgrewriteaof().unwrap_err();
+        assert_eq!(result, DataBaseError::AofDisabled);
+    }
+}
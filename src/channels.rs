@@ -1,8 +1,12 @@
+use mio::Waker;
 use std::collections::HashMap;
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, SendError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 
-use crate::matcher::matcher;
+use crate::hash_shard::hash_funcion;
+use crate::matcher::Glob;
+use crate::resp::RespValue;
 
 #[doc(hidden)]
 pub const MONITOR: &str = "Monitor";
@@ -11,8 +15,94 @@ pub const LOGGER: &str = "Logger";
 #[doc(hidden)]
 pub const LOGGER_SPECIAL_ID: u32 = 0;
 
+/// Number of pieces the channels and the shard channels are divided in.
+pub const CHANNEL_PARTITIONS: usize = 16;
+
+#[doc(hidden)]
+type Listeners = HashMap<String, Vec<(u32, MessageSender)>>;
 #[doc(hidden)]
-type Dictionary = Arc<Mutex<HashMap<String, Vec<(u32, Sender<String>)>>>>;
+type Partitions = Arc<Vec<Mutex<Listeners>>>;
+/// The subscribers of each pattern, with the pattern already read as a Glob, so it
+/// isn't read again for every message published.
+#[doc(hidden)]
+type PatternListeners = HashMap<String, (Glob, Vec<(u32, MessageSender)>)>;
+
+/// Creates the queue of the messages of a client: its channels, its patterns and
+/// the monitor send them with the MessageSender, and its connection takes them from
+/// the MessageReceiver. Both keep count of the bytes sent and not taken yet.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// let (s, r) = message_channel();
+/// s.send("hola".to_string()).unwrap();
+/// assert_eq!(r.queued(), 4);
+///
+/// assert_eq!(r.try_iter().collect::<Vec<_>>(), vec!["hola".to_string()]);
+/// assert_eq!(r.queued(), 0);
+/// ```
+pub fn message_channel() -> (MessageSender, MessageReceiver) {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let queued = Arc::new(AtomicUsize::new(0));
+    (
+        MessageSender {
+            sender,
+            queued: queued.clone(),
+        },
+        MessageReceiver { receiver, queued },
+    )
+}
+
+/// The sending side of the messages of a client, created with message_channel.
+#[derive(Clone, Debug)]
+pub struct MessageSender {
+    #[doc(hidden)]
+    sender: Sender<String>,
+    #[doc(hidden)]
+    queued: Arc<AtomicUsize>,
+}
+
+impl MessageSender {
+    /// Sends message, failing if the receiving side is gone.
+    pub fn send(&self, message: String) -> Result<(), SendError<String>> {
+        let len = message.len();
+        self.queued.fetch_add(len, Ordering::Relaxed);
+        self.sender.send(message).inspect_err(|_| {
+            self.queued.fetch_sub(len, Ordering::Relaxed);
+        })
+    }
+}
+
+/// For the logger, whose messages nobody counts.
+impl From<Sender<String>> for MessageSender {
+    fn from(sender: Sender<String>) -> Self {
+        MessageSender {
+            sender,
+            queued: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+/// The receiving side of the messages of a client, created with message_channel.
+pub struct MessageReceiver {
+    #[doc(hidden)]
+    receiver: Receiver<String>,
+    #[doc(hidden)]
+    queued: Arc<AtomicUsize>,
+}
+
+impl MessageReceiver {
+    /// Takes every message sent so far, without waiting for more.
+    pub fn try_iter(&self) -> impl Iterator<Item = String> + '_ {
+        self.receiver.try_iter().inspect(move |message| {
+            self.queued.fetch_sub(message.len(), Ordering::Relaxed);
+        })
+    }
+
+    /// Returns the bytes of the messages sent and not taken yet.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+}
 
 /// A Channels implemented in a multithreading context.
 ///
@@ -21,21 +111,54 @@ type Dictionary = Arc<Mutex<HashMap<String, Vec<(u32, Sender<String>)>>>>;
 /// It is the one in charge of subscribe or unsubscribe clients to specific channels,
 /// it also has two special channels: Monitor and Logger.
 ///
+/// Clients can also subscribe to patterns, and get the messages published in every
+/// channel that matches them.
+///
+/// The channels are divided in pieces by the hash of their names, each one with its
+/// own lock, so clients subscribing or publishing to channels of different pieces
+/// don't wait for each other. The patterns are only read by PUBLISH, so they're
+/// shared by every publisher. Shard channels, used with SSUBSCRIBE and SPUBLISH,
+/// live in pieces of their own and patterns never match them.
+///
+/// If it has a waker, it's woken every time a message is sent to the clients, so the
+/// event loop of the server delivers it.
+///
 pub struct Channels {
     #[doc(hidden)]
-    channels: Dictionary,
+    channels: Partitions,
+    #[doc(hidden)]
+    shard_channels: Partitions,
+    #[doc(hidden)]
+    patterns: Arc<RwLock<PatternListeners>>,
+    #[doc(hidden)]
+    waker: Option<Arc<Waker>>,
 }
 
-impl<'a> Clone for Channels {
+impl Clone for Channels {
     fn clone(&self) -> Self {
-        Channels::new_from_channels(self.channels.clone())
+        Channels::new_from_channels(
+            self.channels.clone(),
+            self.shard_channels.clone(),
+            self.patterns.clone(),
+            self.waker.clone(),
+        )
     }
 }
 
 impl Channels {
     #[doc(hidden)]
-    fn new_from_channels(channels: Dictionary) -> Self {
-        Channels { channels }
+    fn new_from_channels(
+        channels: Partitions,
+        shard_channels: Partitions,
+        patterns: Arc<RwLock<PatternListeners>>,
+        waker: Option<Arc<Waker>>,
+    ) -> Self {
+        Channels {
+            channels,
+            shard_channels,
+            patterns,
+            waker,
+        }
     }
 
     /// Creates a new Channels with 2 channels: Logger and Monitor.
@@ -45,11 +168,29 @@ impl Channels {
     /// let channels = Channels::new();
     /// ```
     pub fn new() -> Channels {
-        let mut hash = HashMap::new();
-        hash.insert(MONITOR.to_string(), Vec::new());
-        hash.insert(LOGGER.to_string(), Vec::new());
-        let channels = Arc::new(Mutex::new(hash));
-        Channels { channels }
+        let channels = new_partitions();
+        partition(&channels, MONITOR).insert(MONITOR.to_string(), Vec::new());
+        partition(&channels, LOGGER).insert(LOGGER.to_string(), Vec::new());
+        Channels {
+            channels,
+            shard_channels: new_partitions(),
+            patterns: Arc::new(RwLock::new(HashMap::new())),
+            waker: None,
+        }
+    }
+
+    /// Sets the waker to wake after sending a message to the clients. Only the clones
+    /// made afterwards have it.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let poll = Poll::new()?;
+    /// let waker = Waker::new(poll.registry(), WAKER)?;
+    /// let mut channels = Channels::new();
+    /// channels.set_waker(Arc::new(waker));
+    /// ```
+    pub fn set_waker(&mut self, waker: Arc<Waker>) {
+        self.waker = Some(waker);
     }
 
     /// Subscribes a client with his sender and id in the corresponding channel.
@@ -57,17 +198,11 @@ impl Channels {
     /// Basic Usage:
     /// ```
     /// let mut channels = Channels::new();
-    /// let (s, _) = channel();
+    /// let (s, _) = message_channel();
     /// channels.subscribe("channel", s, 1);
     /// ```
-    pub fn subscribe(&mut self, channel: &str, sender: Sender<String>, id: u32) {
-        let mut guard = self.channels.lock().unwrap();
-        match guard.get_mut(channel) {
-            Some(l) => l.push((id, sender)),
-            None => {
-                guard.insert(channel.to_string(), vec![(id, sender)]);
-            }
-        }
+    pub fn subscribe(&mut self, channel: &str, sender: MessageSender, id: u32) {
+        add_listener(&mut partition(&self.channels, channel), channel, sender, id);
     }
 
     /// Unsubscribes a client with that id of the corresponding channel.
@@ -76,13 +211,13 @@ impl Channels {
     /// Basic Usage:
     /// ```
     /// let mut channels = Channels::new();
-    /// let (s, r) = channel();
+    /// let (s, r) = message_channel();
     /// channels.subscribe("channel", s, 1);
     ///
     /// let number = channels.send("channel", "hola");
     /// assert_eq!(number, 1);
     ///
-    /// let r = r.recv().unwrap();
+    /// let r = r.try_iter().next().unwrap();
     /// assert_eq!(r, "hola");
     ///
     /// channels.unsubscribe("channel", 1);
@@ -90,11 +225,60 @@ impl Channels {
     /// assert_eq!(number, 0);
     /// ```
     pub fn unsubscribe(&mut self, channel: &str, id: u32) {
-        let mut guard = self.channels.lock().unwrap();
-        if let Some(l) = guard.get_mut(channel) {
-            l.retain(|x| x.0 != id);
-            if l.is_empty() {
-                guard.remove(channel);
+        remove_listener(&mut partition(&self.channels, channel), channel, id);
+    }
+
+    /// Subscribes a client with his sender and id in the corresponding shard channel.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut channels = Channels::new();
+    /// let (s, _) = message_channel();
+    /// channels.ssubscribe("orders", s, 1);
+    ///
+    /// assert_eq!(channels.shard_subscriptors_number("orders"), 1);
+    /// assert_eq!(channels.subcriptors_number("orders"), 0);
+    /// ```
+    pub fn ssubscribe(&mut self, channel: &str, sender: MessageSender, id: u32) {
+        add_listener(
+            &mut partition(&self.shard_channels, channel),
+            channel,
+            sender,
+            id,
+        );
+    }
+
+    /// Unsubscribes a client with that id of the corresponding shard channel.
+    pub fn sunsubscribe(&mut self, channel: &str, id: u32) {
+        remove_listener(&mut partition(&self.shard_channels, channel), channel, id);
+    }
+
+    /// Subscribes a client with his sender and id to the channels that match pattern.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut channels = Channels::new();
+    /// let (s, _) = message_channel();
+    /// channels.psubscribe("news.*", s, 1);
+    /// ```
+    pub fn psubscribe(&mut self, pattern: &str, sender: MessageSender, id: u32) {
+        self.patterns
+            .write()
+            .unwrap()
+            .entry(pattern.to_string())
+            .or_insert_with(|| (Glob::new(pattern.as_bytes()), Vec::new()))
+            .1
+            .push((id, sender));
+    }
+
+    /// Unsubscribes a client with that id of pattern, and removes pattern if no one
+    /// else is subscribed to it.
+    pub fn punsubscribe(&mut self, pattern: &str, id: u32) {
+        let mut patterns = self.patterns.write().unwrap();
+        if let Some((_, listeners)) = patterns.get_mut(pattern) {
+            listeners.retain(|x| x.0 != id);
+            if listeners.is_empty() {
+                patterns.remove(pattern);
             }
         }
     }
@@ -109,52 +293,121 @@ impl Channels {
     /// channels.add_logger(log_sender);
     /// ```
     pub fn add_logger(&mut self, logger_sender: Sender<String>) {
-        self.subscribe(LOGGER, logger_sender, LOGGER_SPECIAL_ID);
+        self.subscribe(LOGGER, logger_sender.into(), LOGGER_SPECIAL_ID);
     }
 
-    /// Adds a new monitor and rerturn his receiver to listen.
+    /// Adds a new monitor with his sender, that receives every request already encoded
+    /// to be written to the client.
     /// # Examples
     /// Basic Usage:
     /// ```
     /// let mut channels = Channels::new();
-    /// let r = channels.add_monitor();
-    /// for msg in r.iter() {
+    /// let (s, r) = message_channel();
+    /// channels.add_monitor(s, 1);
+    /// for msg in r.try_iter() {
     ///     println!("{}", msg);
     /// }
     /// ```
-    pub fn add_monitor(&mut self, id: u32) -> Receiver<String> {
-        let (s, r) = channel();
-        self.subscribe(MONITOR, s, id);
-
-        r
+    pub fn add_monitor(&mut self, monitor_sender: MessageSender, id: u32) {
+        self.subscribe(MONITOR, monitor_sender, id);
     }
 
     /// Sends a message to all the subscriptors in the corresponding channel
     /// and return the number of subscriptors that received the message.
+    /// The subscriptors that are gone without unsubscribing are removed.
     /// # Examples
     /// Basic Usage:
     /// ```
     /// let mut channels = Channels::new();
-    /// let (s, r) = channel();
+    /// let (s, r) = message_channel();
     /// channels.subscribe("channel_1", s, 1);
     ///
     /// let number = channels.send("channel_1", "hola");
     /// assert_eq!(number, 1);
     ///
-    /// let r = r.recv().unwrap();
+    /// let r = r.try_iter().next().unwrap();
     /// assert_eq!(r, "hola");
     /// ```
     pub fn send(&mut self, channel: &str, msg: &str) -> i32 {
-        let guard = self.channels.lock().unwrap();
-        match guard.get(channel) {
-            Some(listeners) => {
-                listeners.iter().for_each(|x| {
-                    x.1.send(msg.to_string()).unwrap();
-                });
-                listeners.len() as i32
+        let mut guard = partition(&self.channels, channel);
+        let receivers = deliver(&mut guard, channel, msg);
+        drop(guard);
+        if receivers > 0 && channel != LOGGER {
+            self.wake();
+        }
+
+        receivers as i32
+    }
+
+    /// Publishes message in channel: its subscribers get it as a message and the
+    /// subscribers of the patterns that match channel as a pmessage, with the
+    /// pattern, both already encoded. Returns the number of clients that got it.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut channels = Channels::new();
+    /// let (s, r) = message_channel();
+    /// channels.psubscribe("news.*", s, 1);
+    ///
+    /// let number = channels.publish("news.tech", "hola");
+    /// assert_eq!(number, 1);
+    ///
+    /// let r = r.try_iter().next().unwrap();
+    /// assert_eq!(r, "*4\r\n$8\r\npmessage\r\n$6\r\nnews.*\r\n$9\r\nnews.tech\r\n$4\r\nhola\r\n");
+    /// ```
+    pub fn publish(&mut self, channel: &str, message: &str) -> i32 {
+        let mut receivers = self.send(channel, &encode(&["message", channel, message]));
+
+        let mut gone = Vec::new();
+        let guard = self.patterns.read().unwrap();
+        let patterns = guard
+            .iter()
+            .filter(|(_, (glob, _))| glob.matches(channel.as_bytes()));
+        for (pattern, (_, listeners)) in patterns {
+            let pmessage = encode(&["pmessage", pattern, channel, message]);
+            for (id, sender) in listeners {
+                match sender.send(pmessage.clone()) {
+                    Ok(()) => receivers += 1,
+                    Err(_) => gone.push((pattern.clone(), *id)),
+                }
             }
-            None => 0,
         }
+        drop(guard);
+        for (pattern, id) in gone {
+            self.punsubscribe(&pattern, id);
+        }
+        if receivers > 0 {
+            self.wake();
+        }
+
+        receivers
+    }
+
+    /// Publishes message in the shard channel channel, already encoded as a
+    /// smessage. Returns the number of clients that got it.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut channels = Channels::new();
+    /// let (s, r) = message_channel();
+    /// channels.ssubscribe("orders", s, 1);
+    ///
+    /// let number = channels.spublish("orders", "hola");
+    /// assert_eq!(number, 1);
+    ///
+    /// let r = r.try_iter().next().unwrap();
+    /// assert_eq!(r, "*3\r\n$8\r\nsmessage\r\n$6\r\norders\r\n$4\r\nhola\r\n");
+    /// ```
+    pub fn spublish(&mut self, channel: &str, message: &str) -> i32 {
+        let smessage = encode(&["smessage", channel, message]);
+        let mut guard = partition(&self.shard_channels, channel);
+        let receivers = deliver(&mut guard, channel, &smessage);
+        drop(guard);
+        if receivers > 0 {
+            self.wake();
+        }
+
+        receivers as i32
     }
 
     /// Sends a message to the logger, if there's anyone.
@@ -172,7 +425,8 @@ impl Channels {
     /// you can use in the same way that other channel.
     pub fn send_monitor(&mut self, id: u32, msg: &str) {
         let msg: String = "Client: ".to_string() + &id.to_string() + " " + msg;
-        self.send(MONITOR, &msg);
+        let msg = RespValue::SimpleString(msg).encode();
+        self.send(MONITOR, &String::from_utf8_lossy(&msg));
     }
 
     /// Get all channels that matches with the pattern passed in a list of strings
@@ -183,7 +437,7 @@ impl Channels {
     /// let mut channels = Channels::new();
     ///
     /// for i in 1..6 {
-    ///     let (s, _) = channel();
+    ///     let (s, _) = message_channel();
     ///     channels.subscribe(&i.to_string(), s, i);
     /// }
     ///
@@ -200,20 +454,24 @@ impl Channels {
     /// );
     /// ```
     pub fn get_channels(&self, pattern: &str) -> Vec<String> {
-        let guard = self.channels.lock().unwrap();
-        guard
-            .keys()
-            .filter(|x| matcher(x, pattern) && *x != MONITOR && *x != LOGGER)
-            .map(|item| item.to_string())
+        names(&self.channels, pattern)
+            .into_iter()
+            .filter(|x| x != MONITOR && x != LOGGER)
             .collect()
     }
 
+    /// Get all shard channels that matches with the pattern passed in a list of
+    /// strings.
+    pub fn get_shard_channels(&self, pattern: &str) -> Vec<String> {
+        names(&self.shard_channels, pattern)
+    }
+
     /// Gets the number of subscriptors in the corresponding channel.
     /// # Examples
     /// Basic Usage:
     /// ```
     /// let mut channels = Channels::new();
-    /// let (s, _) = channel();
+    /// let (s, _) = message_channel();
     ///
     /// for i in 1..6 {
     ///     channels.subscribe("channel", s.clone(), i);
@@ -222,17 +480,120 @@ impl Channels {
     /// }
     /// ```
     pub fn subcriptors_number(&self, channel: &str) -> usize {
-        let guard = self.channels.lock().unwrap();
-        match guard.get(channel) {
-            Some(l) => l.len(),
-            None => 0,
+        partition(&self.channels, channel)
+            .get(channel)
+            .map_or(0, |l| l.len())
+    }
+
+    /// Gets the number of subscriptors in the corresponding shard channel.
+    pub fn shard_subscriptors_number(&self, channel: &str) -> usize {
+        partition(&self.shard_channels, channel)
+            .get(channel)
+            .map_or(0, |l| l.len())
+    }
+
+    /// Gets the number of patterns subscribed to by any client.
+    pub fn patterns_number(&self) -> usize {
+        self.patterns.read().unwrap().len()
+    }
+
+    /// Wakes the waker, if there's one, for the event loop to deliver the messages.
+    #[doc(hidden)]
+    fn wake(&self) {
+        if let Some(waker) = &self.waker {
+            if let Err(e) = waker.wake() {
+                eprintln!("Couldn't wake the event loop: {}", e);
+            }
         }
     }
 }
 
+#[doc(hidden)]
+fn new_partitions() -> Partitions {
+    let partitions = (0..CHANNEL_PARTITIONS)
+        .map(|_| Mutex::new(HashMap::new()))
+        .collect();
+    Arc::new(partitions)
+}
+
+/// Locks the piece of partitions the channel name goes to.
+#[doc(hidden)]
+fn partition<'a>(partitions: &'a Partitions, name: &str) -> MutexGuard<'a, Listeners> {
+    partitions[hash_funcion(name.as_bytes(), partitions.len())]
+        .lock()
+        .unwrap()
+}
+
+/// Returns the names of every piece of partitions that match pattern.
+#[doc(hidden)]
+fn names(partitions: &Partitions, pattern: &str) -> Vec<String> {
+    let glob = Glob::new(pattern.as_bytes());
+    partitions
+        .iter()
+        .flat_map(|listeners| {
+            listeners
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|x| glob.matches(x.as_bytes()))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[doc(hidden)]
+fn add_listener(dictionary: &mut Listeners, name: &str, sender: MessageSender, id: u32) {
+    dictionary
+        .entry(name.to_string())
+        .or_default()
+        .push((id, sender));
+}
+
+/// Removes the listener with that id of name, and name itself if none is left.
+#[doc(hidden)]
+fn remove_listener(dictionary: &mut Listeners, name: &str, id: u32) {
+    if let Some(l) = dictionary.get_mut(name) {
+        l.retain(|x| x.0 != id);
+        if l.is_empty() {
+            dictionary.remove(name);
+        }
+    }
+}
+
+/// Sends msg to the listeners of name in dictionary, removing the ones whose
+/// receiver is gone, and name itself if none is left. Returns how many got it.
+#[doc(hidden)]
+fn deliver(dictionary: &mut Listeners, name: &str, msg: &str) -> usize {
+    let listeners = match dictionary.get_mut(name) {
+        Some(listeners) => listeners,
+        None => return 0,
+    };
+
+    listeners.retain(|x| x.1.send(msg.to_string()).is_ok());
+    let receivers = listeners.len();
+    if receivers == 0 {
+        dictionary.remove(name);
+    }
+    receivers
+}
+
+/// Encodes a message for the subscribers: an array of bulk strings.
+#[doc(hidden)]
+fn encode(parts: &[&str]) -> String {
+    let parts = parts
+        .iter()
+        .map(|part| RespValue::BulkString(part.as_bytes().to_vec()))
+        .collect();
+    String::from_utf8_lossy(&RespValue::Array(parts).encode()).to_string()
+}
+
 #[cfg(test)]
 mod channels_test {
     use super::*;
+    use mio::{Events, Poll, Token};
+    use std::sync::mpsc::{channel, Receiver};
+    use std::time::Duration;
 
     const CHANNEL_1: &str = "1";
     const CHANNEL_2: &str = "2";
@@ -255,7 +616,7 @@ mod channels_test {
         let mut receivers = Vec::new();
         for i in 1..6 {
             let (s, r) = channel();
-            channels.subscribe(&i.to_string(), s, i);
+            channels.subscribe(&i.to_string(), s.into(), i);
             receivers.push(r);
         }
 
@@ -268,7 +629,7 @@ mod channels_test {
             let (s, r) = channel();
             receivers.push(r);
             for c in &CHANNELS {
-                channels.subscribe(c, s.clone(), *id);
+                channels.subscribe(c, s.clone().into(), *id);
             }
         }
 
@@ -280,10 +641,9 @@ mod channels_test {
         let mut channels = Channels::new();
         add_channels(&mut channels);
 
-        let guard = channels.channels.lock().unwrap();
-
         for i in 1..6 {
-            assert!(guard.contains_key(&i.to_string()));
+            let channel = i.to_string();
+            assert!(partition(&channels.channels, &channel).contains_key(&channel));
         }
     }
 
@@ -308,7 +668,7 @@ mod channels_test {
     fn subscribe_and_send_a_msg() {
         let mut channels = Channels::new();
         let (s, r) = channel();
-        channels.subscribe(CHANNEL_1, s, ID_1);
+        channels.subscribe(CHANNEL_1, s.into(), ID_1);
 
         let number = channels.send(CHANNEL_1, MSG);
         assert_eq!(number, 1);
@@ -338,7 +698,7 @@ mod channels_test {
         let mut channels = Channels::new();
         let (s, r) = channel();
         for c in &CHANNELS {
-            channels.subscribe(c, s.clone(), ID_2);
+            channels.subscribe(c, s.clone().into(), ID_2);
         }
 
         for c in &CHANNELS {
@@ -372,7 +732,7 @@ mod channels_test {
         let (s, _) = channel();
 
         for i in 1..6 {
-            channels.subscribe(CHANNEL_1, s.clone(), i);
+            channels.subscribe(CHANNEL_1, s.clone().into(), i);
             let number = channels.subcriptors_number(CHANNEL_1);
             assert_eq!(number, i as usize);
         }
@@ -383,7 +743,7 @@ mod channels_test {
         let mut channels = Channels::new();
         let (s, r) = channel();
 
-        channels.subscribe(CHANNEL_1, s, ID_1);
+        channels.subscribe(CHANNEL_1, s.into(), ID_1);
         let number = channels.send(CHANNEL_1, MSG);
         assert_eq!(number, 1);
 
@@ -450,4 +810,172 @@ mod channels_test {
             assert_eq!(msgs, vec![MSG; i]);
         }
     }
+
+    #[test]
+    fn monitors_receive_the_requests_encoded() {
+        let mut channels = Channels::new();
+        let (s, r) = channel();
+        channels.add_monitor(s.into(), ID_1);
+
+        channels.send_monitor(ID_2, MSG);
+
+        assert_eq!(r.recv().unwrap(), "+Client: 2 hola\r\n");
+    }
+
+    #[test]
+    fn send_removes_the_clients_that_are_gone() {
+        let mut channels = Channels::new();
+        let (s1, r1) = channel();
+        let (s2, _r2) = channel();
+        channels.subscribe(CHANNEL_1, s1.clone().into(), ID_1);
+        channels.subscribe(CHANNEL_1, s2.into(), ID_2);
+        channels.subscribe(CHANNEL_2, s1.into(), ID_1);
+        drop(r1);
+
+        assert_eq!(channels.send(CHANNEL_1, MSG), 1);
+        assert_eq!(channels.subcriptors_number(CHANNEL_1), 1);
+        assert_eq!(channels.send(CHANNEL_2, MSG), 0);
+        assert_eq!(channels.get_channels("*"), vec![CHANNEL_1.to_string()]);
+    }
+
+    #[test]
+    fn publish_removes_the_patterns_of_the_clients_that_are_gone() {
+        let mut channels = Channels::new();
+        let (s, r) = channel();
+        channels.psubscribe("news.*", s.into(), ID_1);
+        drop(r);
+
+        assert_eq!(channels.publish("news.tech", MSG), 0);
+        assert_eq!(channels.patterns_number(), 0);
+    }
+
+    #[test]
+    fn publish_reaches_the_patterns_that_match() {
+        let mut channels = Channels::new();
+        let (s1, r1) = channel();
+        let (s2, r2) = channel();
+        channels.subscribe("news.tech", s1.into(), ID_1);
+        channels.psubscribe("news.*", s2.clone().into(), ID_2);
+        channels.psubscribe("sports.*", s2.into(), ID_2);
+
+        assert_eq!(channels.publish("news.tech", MSG), 2);
+        assert_eq!(
+            r1.recv().unwrap(),
+            "*3\r\n$7\r\nmessage\r\n$9\r\nnews.tech\r\n$4\r\nhola\r\n"
+        );
+        assert_eq!(
+            r2.recv().unwrap(),
+            "*4\r\n$8\r\npmessage\r\n$6\r\nnews.*\r\n$9\r\nnews.tech\r\n$4\r\nhola\r\n"
+        );
+        assert!(r2.try_recv().is_err());
+    }
+
+    #[test]
+    fn patterns_with_regex_metacharacters_match_as_globs() {
+        let mut channels = Channels::new();
+        let (s, r) = channel();
+        channels.psubscribe("a|", s.clone().into(), ID_1);
+        channels.psubscribe("news.[0-9]*", s.into(), ID_1);
+
+        assert_eq!(channels.publish("secret", MSG), 0);
+        assert_eq!(channels.publish("a|", MSG), 1);
+        assert_eq!(channels.publish("newsx1", MSG), 0);
+        assert_eq!(channels.publish("news.1\nlocal", MSG), 1);
+        assert_eq!(r.try_iter().count(), 2);
+        assert_eq!(channels.get_channels("a|"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn message_channels_count_the_bytes_not_taken_yet() {
+        let mut channels = Channels::new();
+        let (s, r) = message_channel();
+        channels.subscribe(CHANNEL_1, s.clone(), ID_1);
+        channels.psubscribe("*", s, ID_1);
+
+        assert_eq!(channels.publish(CHANNEL_1, MSG), 2);
+        let message = encode(&["message", CHANNEL_1, MSG]);
+        let pmessage = encode(&["pmessage", "*", CHANNEL_1, MSG]);
+        assert_eq!(r.queued(), message.len() + pmessage.len());
+
+        assert_eq!(r.try_iter().count(), 2);
+        assert_eq!(r.queued(), 0);
+    }
+
+    #[test]
+    fn a_message_not_sent_is_not_counted() {
+        let (s, r) = message_channel();
+        let queued = r.queued.clone();
+        drop(r);
+
+        assert!(s.send(MSG.to_string()).is_err());
+        assert_eq!(queued.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn punsubscribe_removes_the_empty_patterns() {
+        let mut channels = Channels::new();
+        let (s, _r) = channel();
+        channels.psubscribe("news.*", s.clone().into(), ID_1);
+        channels.psubscribe("news.*", s.clone().into(), ID_2);
+        channels.psubscribe("sports.*", s.into(), ID_1);
+        assert_eq!(channels.patterns_number(), 2);
+
+        channels.punsubscribe("news.*", ID_1);
+        channels.punsubscribe("sports.*", ID_1);
+
+        assert_eq!(channels.patterns_number(), 1);
+        assert_eq!(channels.publish("news.tech", MSG), 1);
+    }
+
+    #[test]
+    fn shard_channels_are_apart_from_channels_and_patterns() {
+        let mut channels = Channels::new();
+        let (s1, r1) = channel();
+        let (s2, r2) = channel();
+        channels.ssubscribe("orders", s1.into(), ID_1);
+        channels.subscribe("orders", s2.clone().into(), ID_2);
+        channels.psubscribe("*", s2.into(), ID_2);
+
+        assert_eq!(channels.spublish("orders", MSG), 1);
+        assert_eq!(
+            r1.recv().unwrap(),
+            "*3\r\n$8\r\nsmessage\r\n$6\r\norders\r\n$4\r\nhola\r\n"
+        );
+        assert!(r2.try_recv().is_err());
+        assert_eq!(channels.get_shard_channels("*"), vec!["orders".to_string()]);
+        assert_eq!(channels.shard_subscriptors_number("orders"), 1);
+
+        channels.sunsubscribe("orders", ID_1);
+        assert_eq!(channels.spublish("orders", MSG), 0);
+        assert!(channels.get_shard_channels("*").is_empty());
+    }
+
+    #[test]
+    fn channels_of_every_partition_are_listed() {
+        let mut channels = Channels::new();
+        let (s, _r) = channel();
+        for i in 0..CHANNEL_PARTITIONS * 4 {
+            channels.subscribe(&format!("channel{}", i), s.clone().into(), ID_1);
+        }
+
+        assert_eq!(channels.get_channels("*").len(), CHANNEL_PARTITIONS * 4);
+        assert_eq!(channels.get_channels("channel1?").len(), 10);
+    }
+
+    #[test]
+    fn send_wakes_the_waker() {
+        let mut poll = Poll::new().unwrap();
+        let waker = Waker::new(poll.registry(), Token(0)).unwrap();
+        let mut channels = Channels::new();
+        channels.set_waker(Arc::new(waker));
+        let (s, _r) = channel();
+        channels.subscribe(CHANNEL_1, s.into(), ID_1);
+
+        channels.send(CHANNEL_1, MSG);
+
+        let mut events = Events::with_capacity(4);
+        poll.poll(&mut events, Some(Duration::from_secs(1)))
+            .unwrap();
+        assert!(events.iter().any(|event| event.token() == Token(0)));
+    }
 }
@@ -1,155 +1,482 @@
-use crate::channels::Channels;
+use crate::acl::{AclError, DEFAULT_USER};
+use crate::channels::{Channels, MessageSender, MONITOR};
 use crate::database::Database;
-use crate::logger::Logger;
-use crate::request::{self, Reponse, Request};
+use crate::request::{Query, Reponse, Request, TransactionRequest};
+use crate::resp::{Command, RespValue};
 use crate::server_conf::ServerConf;
-use std::net::TcpStream;
+use std::mem;
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime};
+use std::time::SystemTime;
 
-/// Server is the one in charge of distribute and share the resources for
-/// each command executed.
+const NOT_ALLOWED_IN_TRANSACTION: &str = "Command not allowed inside a transaction";
+const EXEC_ABORT: &str = "EXECABORT Transaction discarded because of previous errors.";
+const WRONGPASS: &str = "WRONGPASS invalid username-password pair or user is disabled.";
+const NOPROTO: &str = "NOPROTO unsupported protocol version";
+const AUTH_WITHOUT_PASSWORD: &str = "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?";
+
+/// Client is the one in charge of executing the commands of a connection, with the
+/// resources shared by every client and what belongs to its session: the selected
+/// database, its subscriptions, its transaction and its watched keys.
+///
+/// It doesn't own the connection: the server reads the commands and writes back the
+/// replies, so a client is only busy while its commands are executed.
+///
+/// Every command is checked against the user the client authenticated as with AUTH.
+/// Clients start authenticated as the default user if it needs no password when they
+/// connect; otherwise every command but AUTH is rejected until they authenticate.
+///
+/// Clients speak RESP2 until they switch to RESP3 with HELLO 3. Then they get the
+/// messages of their channels as pushes and can keep running any command while
+/// subscribed, instead of only the subscription ones.
 pub struct Client {
     #[doc(hidden)]
-    stream: TcpStream,
+    id: u32,
     #[doc(hidden)]
-    subscriptions: Vec<String>,
+    database: Database,
     #[doc(hidden)]
-    id: u32,
+    channels: Channels,
+    #[doc(hidden)]
+    config: ServerConf,
+    #[doc(hidden)]
+    uptime: SystemTime,
     #[doc(hidden)]
     total_clients: Arc<Mutex<u64>>,
     #[doc(hidden)]
-    logger_ref: Arc<Mutex<Logger>>,
+    messages: MessageSender,
+    #[doc(hidden)]
+    subscriptions: Vec<String>,
+    #[doc(hidden)]
+    patterns: Vec<String>,
+    #[doc(hidden)]
+    shard_subscriptions: Vec<String>,
+    #[doc(hidden)]
+    subscription_mode: bool,
+    #[doc(hidden)]
+    resp3: bool,
+    #[doc(hidden)]
+    user: Option<String>,
+    #[doc(hidden)]
+    output: Vec<u8>,
+    #[doc(hidden)]
+    transaction: Option<Vec<Command>>,
+    #[doc(hidden)]
+    transaction_failed: bool,
+    #[doc(hidden)]
+    watched_keys: Vec<(usize, Vec<u8>, u64)>,
 }
 
 impl Client {
-    /// Creates a new Client with all the data passed by arguments.
+    /// Creates a new Client with all the data passed by arguments. The messages of the
+    /// channels it subscribes to, and the requests seen if it's a monitor, are sent
+    /// to messages.
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let server = Server::new("redis.conf")?;
+    /// let (messages, r) = message_channel();
+    /// let client = Client::new(1, database, channels, config, uptime, total_clients, messages);
     /// ```
     pub fn new(
-        stream: TcpStream,
         id: u32,
+        database: Database,
+        channels: Channels,
+        config: ServerConf,
+        uptime: SystemTime,
         total_clients: Arc<Mutex<u64>>,
-        logger_ref: Arc<Mutex<Logger>>,
+        messages: MessageSender,
     ) -> Client {
-        let subscriptions = Vec::new();
+        let user = Some(DEFAULT_USER.to_string()).filter(|_| config.acl().is_open(DEFAULT_USER));
         Client {
-            stream,
-            subscriptions,
             id,
+            database,
+            channels,
+            config,
+            uptime,
             total_clients,
-            logger_ref,
+            messages,
+            subscriptions: Vec::new(),
+            patterns: Vec::new(),
+            shard_subscriptions: Vec::new(),
+            subscription_mode: false,
+            resp3: false,
+            user,
+            output: Vec::new(),
+            transaction: None,
+            transaction_failed: false,
+            watched_keys: Vec::new(),
         }
     }
 
-    /// Handles a client with all the resources that it needs and executes each command
-    /// arrived from the stream untill it desconnectes for any reason.
-    pub fn handle_client(
-        &mut self,
-        mut database: Database,
-        mut channels: Channels,
-        uptime: SystemTime,
-        mut config: ServerConf,
-    ) {
+    /// Executes the commands arrived from the connection, in order, and keeps their
+    /// replies to be taken with take_output. Returns false if the client asked to
+    /// close the connection, and the commands after that one aren't executed.
+    ///
+    /// Between MULTI and EXEC commands are queued, and EXEC runs all of them while no
+    /// other client can execute a command, unless a key watched with WATCH changed.
+    pub fn handle_commands(&mut self, commands: &[Command]) -> bool {
         let mut a_live = true;
-        let mut subscription_mode = false;
-
-        while a_live {
-            let time_out = config.time_out();
-            let time_out = if time_out > 0 && !subscription_mode {
-                Some(Duration::from_secs(time_out))
-            } else {
-                None
+
+        for command in commands.iter() {
+            if !a_live {
+                break;
+            }
+
+            let request = Request::new(command, self.subscription_mode && !self.resp3);
+            let respond = match request {
+                Request::Auth(username, password) => self.auth(username, password),
+                Request::Hello(protover, auth) => self.hello(protover, auth),
+                Request::Reset => self.reset(),
+                Request::CloseClient => {
+                    a_live = false;
+                    Reponse::Valid(RespValue::SimpleString("OK".to_string()))
+                }
+                // It only replies why it can't run, whoever sent it.
+                request @ Request::Invalid(_, _) => self.handle_request(command, request),
+                request => match self.config.acl().check(self.user.as_deref(), command) {
+                    Ok(()) => self.handle_request(command, request),
+                    Err(error) => Reponse::Error(error.to_string()),
+                },
             };
+            if let Reponse::Valid(msg) = &respond {
+                self.channels.send_logger(self.id, &msg.to_string());
+            }
 
-            self.stream.set_read_timeout(time_out).unwrap();
-
-            let mut logger = self.logger_ref.lock().unwrap();
-            logger.set_verbose(config.verbose());
-            drop(logger);
-
-            match request::parse_request(&mut self.stream) {
-                Ok(request) => {
-                    let request = Request::new(&request, subscription_mode);
-                    let respond = match request {
-                        Request::DataBase(query) => {
-                            self.emit_request(query.to_string(), &mut channels);
-                            query.exec_query(&mut database)
-                        }
-                        Request::Server(request) => {
-                            self.emit_request(request.to_string(), &mut channels);
-                            request.exec_request(&mut config, uptime, self.total_clients.clone())
-                        }
-                        Request::Publisher(request) => {
-                            self.emit_request(request.to_string(), &mut channels);
-                            request.execute(&mut channels)
-                        }
-                        Request::Suscriber(request) => {
-                            self.emit_request(request.to_string(), &mut channels);
-                            request.execute(
-                                &mut self.stream,
-                                &mut channels,
-                                &mut self.subscriptions,
-                                self.id,
-                                &mut subscription_mode,
-                            )
-                        }
-                        Request::Touch(key) => {
-                            let r = database.touch(key);
-                            let (response, time) = match r {
-                                Some(time) => ("(Integer) 1".to_string(), time),
-                                None => ("(Integer) 0".to_string(), 0),
-                            };
-                            let msg = format!("{} - Time since last access: {}", request, time);
-                            self.emit_request(msg, &mut channels);
-                            Reponse::Valid(response)
-                        }
-                        Request::Invalid(_, _) => Reponse::Error(request.to_string()),
-                        Request::CloseClient => {
-                            a_live = false;
-                            let mut clients = self.total_clients.lock().unwrap();
-                            *clients -= 1;
-                            Reponse::Valid("OK".to_string())
-                        }
-                    };
-                    if let Reponse::Valid(msg) = &respond {
-                        channels.send_logger(self.id, msg);
-                    }
+            respond.respond(&mut self.output);
+        }
 
-                    match &respond {
-                        Reponse::Error(e) => {
-                            if e != "Monitor" {
-                                respond.respond(&mut self.stream);
-                            }
-                        }
-                        _ => {
-                            respond.respond(&mut self.stream);
-                        }
+        a_live
+    }
+
+    #[doc(hidden)]
+    fn handle_request(&mut self, command: &Command, request: Request) -> Reponse {
+        match request {
+            Request::Transaction(request) => {
+                self.emit_request(request.to_string());
+                self.exec_transaction_request(request)
+            }
+            request if self.transaction.is_some() => self.queue(command, request),
+            Request::Suscriber(request) => {
+                self.emit_request(request.to_string());
+                let respond = request.execute(
+                    &mut self.channels,
+                    &mut self.subscriptions,
+                    &mut self.patterns,
+                    &mut self.shard_subscriptions,
+                    self.id,
+                    &self.messages,
+                );
+                // MONITOR doesn't take the client to subscription mode.
+                self.subscription_mode = self.subscriptions.iter().any(|c| c != MONITOR)
+                    || !self.patterns.is_empty()
+                    || !self.shard_subscriptions.is_empty();
+                match respond {
+                    Reponse::Frames(frames) if self.resp3 => {
+                        Reponse::Frames(frames.into_iter().map(RespValue::into_push).collect())
                     }
+                    respond => respond,
                 }
-                Err(error) => {
-                    a_live = false;
-                    let mut clients = self.total_clients.lock().unwrap();
-                    *clients -= 1;
-                    if error != "EOF" {
-                        let response = Reponse::Error(error);
-                        response.respond(&mut self.stream);
+            }
+            request => {
+                let lock = self.database.transaction_lock();
+                // DEBUG RELOAD empties the data before loading it back: no one else
+                // may see it empty or write between the save and the load.
+                if let Request::DataBase(Query::DebugReload()) = request {
+                    let _guard = lock.write().unwrap();
+                    return self.execute(command, request);
+                }
+                let _guard = lock.read().unwrap();
+                self.execute(command, request)
+            }
+        }
+    }
+
+    /// Takes the replies of the commands executed so far.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        mem::take(&mut self.output)
+    }
+
+    /// Returns true if the client switched to RESP3 with HELLO 3.
+    pub fn is_resp3(&self) -> bool {
+        self.resp3
+    }
+
+    /// Returns true if the client subscribed to any channel or pattern or is a
+    /// monitor, so it waits for messages and the idle timeout doesn't apply to it.
+    pub fn is_subscribed(&self) -> bool {
+        !self.subscriptions.is_empty()
+            || !self.patterns.is_empty()
+            || !self.shard_subscriptions.is_empty()
+    }
+
+    /// Releases what the client holds once its connection is closed: its
+    /// subscriptions and its watched keys.
+    pub fn disconnect(&mut self) {
+        for subs in self.subscriptions.drain(..) {
+            self.channels.unsubscribe(&subs, self.id);
+        }
+        for pattern in self.patterns.drain(..) {
+            self.channels.punsubscribe(&pattern, self.id);
+        }
+        for channel in self.shard_subscriptions.drain(..) {
+            self.channels.sunsubscribe(&channel, self.id);
+        }
+        self.unwatch_all();
+    }
+
+    #[doc(hidden)]
+    fn execute(&mut self, command: &Command, request: Request) -> Reponse {
+        match request {
+            Request::DataBase(query) => {
+                self.emit_request(query.to_string());
+                let aof_command = query.aof_command(command);
+                let respond = query.exec_query(&mut self.database);
+                if let (Reponse::Valid(_), Some(aof_command)) = (&respond, aof_command) {
+                    self.database.feed_aof(&aof_command);
+                }
+                respond
+            }
+            Request::Server(request) => {
+                self.emit_request(request.to_string());
+                request.exec_request(
+                    &mut self.config,
+                    self.uptime,
+                    self.total_clients.clone(),
+                    &self.database,
+                )
+            }
+            Request::Publisher(request) => {
+                self.emit_request(request.to_string());
+                request.execute(&mut self.channels)
+            }
+            Request::Acl(request) => {
+                self.emit_request(request.to_string());
+                let user = self.user.as_deref().unwrap_or(DEFAULT_USER);
+                request.execute(&self.config, user)
+            }
+            Request::Touch(key) => {
+                let r = self.database.touch(key);
+                let (response, time) = match r {
+                    Some(time) => (RespValue::Integer(1), time),
+                    None => (RespValue::Integer(0), 0),
+                };
+                let msg = format!("{} - Time since last access: {}", request, time);
+                self.emit_request(msg);
+                Reponse::Valid(response)
+            }
+            Request::Ping(message) => {
+                self.emit_request(request.to_string());
+                match message {
+                    // Subscribed RESP2 clients only expect arrays, so PING gets one.
+                    _ if self.subscription_mode && !self.resp3 => {
+                        Reponse::Valid(RespValue::Array(vec![
+                            RespValue::BulkString(b"pong".to_vec()),
+                            RespValue::BulkString(message.unwrap_or_default().to_vec()),
+                        ]))
                     }
+                    Some(message) => Reponse::Valid(RespValue::BulkString(message.to_vec())),
+                    None => Reponse::Valid(RespValue::SimpleString("PONG".to_string())),
                 }
             }
+            _ => Reponse::Error(request.to_string()),
         }
+    }
 
-        for subs in self.subscriptions.iter() {
-            channels.unsubscribe(subs, self.id);
+    /// Queues a command received between MULTI and EXEC. A command that can't be
+    /// queued makes EXEC discard the whole transaction.
+    #[doc(hidden)]
+    fn queue(&mut self, command: &Command, request: Request) -> Reponse {
+        match request {
+            Request::DataBase(_)
+            | Request::Server(_)
+            | Request::Publisher(_)
+            | Request::Acl(_)
+            | Request::Touch(_)
+            | Request::Ping(_) => {
+                if let Some(queued) = self.transaction.as_mut() {
+                    queued.push(command.clone());
+                }
+                Reponse::Valid(RespValue::SimpleString("QUEUED".to_string()))
+            }
+            Request::Invalid(_, _) => {
+                self.transaction_failed = true;
+                Reponse::Error(request.to_string())
+            }
+            _ => {
+                self.transaction_failed = true;
+                Reponse::Error(NOT_ALLOWED_IN_TRANSACTION.to_string())
+            }
         }
     }
 
     #[doc(hidden)]
-    fn emit_request(&mut self, request: String, channels: &mut Channels) {
-        channels.send_logger(self.id, &request);
-        channels.send_monitor(self.id, &request);
+    fn exec_transaction_request(&mut self, request: TransactionRequest) -> Reponse {
+        match request {
+            TransactionRequest::Multi => {
+                if self.transaction.is_some() {
+                    return Reponse::Error("ERR MULTI calls can not be nested".to_string());
+                }
+                self.transaction = Some(Vec::new());
+                self.transaction_failed = false;
+                Reponse::Valid(RespValue::SimpleString("OK".to_string()))
+            }
+            TransactionRequest::Exec => {
+                let queued = match self.transaction.take() {
+                    Some(queued) => queued,
+                    None => return Reponse::Error("ERR EXEC without MULTI".to_string()),
+                };
+                if self.transaction_failed {
+                    self.unwatch_all();
+                    return Reponse::Error(EXEC_ABORT.to_string());
+                }
+
+                let lock = self.database.transaction_lock();
+                let _guard = lock.write().unwrap();
+
+                let watched_key_changed = self
+                    .watched_keys
+                    .iter()
+                    .any(|(db, key, version)| self.database.key_version(*db, key) != *version);
+                self.unwatch_all();
+                if watched_key_changed {
+                    return Reponse::Valid(RespValue::Nil);
+                }
+
+                let writes = queued
+                    .iter()
+                    .any(|command| match Request::new(command, false) {
+                        Request::DataBase(query) => query.is_write(),
+                        _ => false,
+                    });
+                if writes {
+                    self.database.feed_aof(&[b"multi".to_vec()]);
+                }
+
+                let mut replies = Vec::new();
+                for command in queued.iter() {
+                    let request = Request::new(command, false);
+                    let respond = self.execute(command, request);
+                    replies.push(respond.into_value());
+                }
+
+                if writes {
+                    self.database.feed_aof(&[b"exec".to_vec()]);
+                }
+                Reponse::Valid(RespValue::Array(replies))
+            }
+            TransactionRequest::Discard => {
+                if self.transaction.take().is_none() {
+                    return Reponse::Error("ERR DISCARD without MULTI".to_string());
+                }
+                self.unwatch_all();
+                Reponse::Valid(RespValue::SimpleString("OK".to_string()))
+            }
+            TransactionRequest::Watch(keys) => {
+                if self.transaction.is_some() {
+                    return Reponse::Error("ERR WATCH inside MULTI is not allowed".to_string());
+                }
+                for key in keys {
+                    let version = self.database.watch(key);
+                    let db = self.database.selected_db();
+                    self.watched_keys.push((db, key.to_vec(), version));
+                }
+                Reponse::Valid(RespValue::SimpleString("OK".to_string()))
+            }
+            TransactionRequest::Unwatch => {
+                self.unwatch_all();
+                Reponse::Valid(RespValue::SimpleString("OK".to_string()))
+            }
+        }
+    }
+
+    #[doc(hidden)]
+    fn unwatch_all(&mut self) {
+        for (db, key, _) in self.watched_keys.drain(..) {
+            self.database.unwatch(db, &key);
+        }
+    }
+
+    /// Authenticates the client as username, or the default user if it's None, if
+    /// password is one of its passwords. A wrong password leaves the client as it was.
+    #[doc(hidden)]
+    fn auth(&mut self, username: Option<&[u8]>, password: &[u8]) -> Reponse {
+        self.emit_request(Request::Auth(username, password).to_string());
+        let acl = self.config.acl();
+        let user = match username {
+            Some(username) => String::from_utf8_lossy(username).to_string(),
+            None if acl.is_open(DEFAULT_USER) => {
+                return Reponse::Error(AUTH_WITHOUT_PASSWORD.to_string())
+            }
+            None => DEFAULT_USER.to_string(),
+        };
+
+        if acl.authenticate(&user, password) {
+            self.user = Some(user);
+            Reponse::Valid(RespValue::SimpleString("OK".to_string()))
+        } else {
+            Reponse::Error(WRONGPASS.to_string())
+        }
+    }
+
+    /// Switches to the protocol protover, if it's given, after authenticating if
+    /// AUTH is given, and replies with the details of the server and the connection.
+    /// A wrong protocol or password leaves the client as it was.
+    #[doc(hidden)]
+    fn hello(&mut self, protover: Option<i64>, auth: Option<(&[u8], &[u8])>) -> Reponse {
+        self.emit_request(Request::Hello(protover, None).to_string());
+        let resp3 = match protover {
+            Some(2) => false,
+            Some(3) => true,
+            None => self.resp3,
+            Some(_) => return Reponse::Error(NOPROTO.to_string()),
+        };
+        if let Some((username, password)) = auth {
+            if let Reponse::Error(error) = self.auth(Some(username), password) {
+                return Reponse::Error(error);
+            }
+        }
+        if self.user.is_none() {
+            return Reponse::Error(AclError::AuthRequired.to_string());
+        }
+
+        self.resp3 = resp3;
+        let bulk = |value: &str| RespValue::BulkString(value.as_bytes().to_vec());
+        let details = vec![
+            (bulk("server"), bulk("redis")),
+            (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+            (bulk("proto"), RespValue::Integer(if resp3 { 3 } else { 2 })),
+            (bulk("id"), RespValue::Integer(self.id as i64)),
+            (bulk("mode"), bulk("standalone")),
+            (bulk("role"), bulk("master")),
+            (bulk("modules"), RespValue::Array(Vec::new())),
+        ];
+        if resp3 {
+            Reponse::Valid(RespValue::Map(details))
+        } else {
+            let details = details.into_iter().flat_map(|(k, v)| vec![k, v]).collect();
+            Reponse::Valid(RespValue::Array(details))
+        }
+    }
+
+    /// Takes the client back to how it was when it connected: without transaction,
+    /// watched keys or subscriptions, in the database 0, speaking RESP2 and
+    /// authenticated as the default user only if it needs no password.
+    #[doc(hidden)]
+    fn reset(&mut self) -> Reponse {
+        self.emit_request(Request::Reset.to_string());
+        self.transaction = None;
+        self.transaction_failed = false;
+        self.disconnect();
+        self.subscription_mode = false;
+        self.resp3 = false;
+        let _ = self.database.select(0);
+        self.user =
+            Some(DEFAULT_USER.to_string()).filter(|_| self.config.acl().is_open(DEFAULT_USER));
+
+        Reponse::Valid(RespValue::SimpleString("RESET".to_string()))
+    }
+
+    #[doc(hidden)]
+    fn emit_request(&mut self, request: String) {
+        self.channels.send_logger(self.id, &request);
+        self.channels.send_monitor(self.id, &request);
     }
 }
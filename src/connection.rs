@@ -0,0 +1,382 @@
+use crate::channels::MessageReceiver;
+use crate::client::Client;
+use crate::request::{self, Reponse};
+use crate::resp::Command;
+use crate::tls::TlsStream;
+use mio::net::TcpStream;
+use mio::{Interest, Registry, Token};
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::{Duration, Instant};
+
+#[doc(hidden)]
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+
+/// The socket of a connection, plain or with TLS. Either way, reading gives what the
+/// client sent and writing sends to the client.
+pub enum Stream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream>),
+}
+
+impl Stream {
+    #[doc(hidden)]
+    fn socket(&mut self) -> &mut TcpStream {
+        match self {
+            Stream::Plain(socket) => socket,
+            Stream::Tls(stream) => stream.socket(),
+        }
+    }
+
+    /// Returns true if something already written is still waiting to be sent.
+    #[doc(hidden)]
+    fn wants_write(&self) -> bool {
+        match self {
+            Stream::Plain(_) => false,
+            Stream::Tls(stream) => stream.wants_write(),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(socket) => socket.read(buf),
+            Stream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(socket) => socket.write(buf),
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(socket) => socket.flush(),
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// The kinds of clients the client-output-buffer-limit option of Redis sets limits
+/// for: the normal ones, the replicas and the ones subscribed to any channel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClientClass {
+    #[doc(hidden)]
+    Normal,
+    #[doc(hidden)]
+    Replica,
+    #[doc(hidden)]
+    PubSub,
+}
+
+impl ClientClass {
+    /// Parses a class of the client-output-buffer-limit option, where slave is
+    /// another name for replica.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// assert_eq!(ClientClass::parse("pubsub"), Some(ClientClass::PubSub));
+    /// assert_eq!(ClientClass::parse("monitor"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<ClientClass> {
+        match value {
+            "normal" => Some(ClientClass::Normal),
+            "replica" | "slave" => Some(ClientClass::Replica),
+            "pubsub" => Some(ClientClass::PubSub),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ClientClass {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ClientClass::Normal => write!(f, "normal"),
+            ClientClass::Replica => write!(f, "replica"),
+            ClientClass::PubSub => write!(f, "pubsub"),
+        }
+    }
+}
+
+/// How many bytes can wait to be written to a client before it's disconnected: at
+/// once over hard, or over soft for soft_seconds in a row. A limit of 0 is no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OutputBufferLimit {
+    pub hard: usize,
+    pub soft: usize,
+    pub soft_seconds: u64,
+}
+
+/// A Connection is what the server keeps of each client between its commands: the
+/// socket, the bytes read that aren't a whole command yet, the replies that couldn't
+/// be written yet and the client itself, unless a worker is executing its commands.
+///
+/// The socket is non blocking: reads and writes take whatever the socket can give or
+/// take right now, and the rest waits in the buffers for the next event.
+pub struct Connection {
+    #[doc(hidden)]
+    stream: Stream,
+    #[doc(hidden)]
+    input: Vec<u8>,
+    #[doc(hidden)]
+    output: Vec<u8>,
+    #[doc(hidden)]
+    client: Option<Client>,
+    #[doc(hidden)]
+    busy: bool,
+    #[doc(hidden)]
+    messages: MessageReceiver,
+    #[doc(hidden)]
+    last_activity: Instant,
+    #[doc(hidden)]
+    interest: Interest,
+    #[doc(hidden)]
+    read_closed: bool,
+    #[doc(hidden)]
+    closing: bool,
+    #[doc(hidden)]
+    broken: bool,
+    #[doc(hidden)]
+    over_soft_limit_since: Option<Instant>,
+    #[doc(hidden)]
+    resp3: bool,
+}
+
+impl Connection {
+    /// Creates a new Connection for client, connected through stream, that receives
+    /// the messages sent to the client in messages.
+    pub fn new(stream: Stream, client: Client, messages: MessageReceiver) -> Connection {
+        Connection {
+            stream,
+            input: Vec::new(),
+            output: Vec::new(),
+            client: Some(client),
+            busy: false,
+            messages,
+            last_activity: Instant::now(),
+            interest: Interest::READABLE,
+            read_closed: false,
+            closing: false,
+            broken: false,
+            over_soft_limit_since: None,
+            resp3: false,
+        }
+    }
+
+    /// Registers the socket in registry, to be told when there's something to read.
+    pub fn register(&mut self, registry: &Registry, token: Token) -> io::Result<()> {
+        registry.register(self.stream.socket(), token, self.interest)
+    }
+
+    /// Reads everything the socket has into the input buffer. If the client closed its
+    /// side of the connection the commands already read are still executed.
+    pub fn read(&mut self) {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.read_closed = true;
+                    return;
+                }
+                Ok(bytes_read) => {
+                    self.input.extend_from_slice(&chunk[..bytes_read]);
+                    self.last_activity = Instant::now();
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => {
+                    self.broken = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Writes as much of the output buffer as the socket takes.
+    pub fn write(&mut self) {
+        while !self.output.is_empty() && !self.broken {
+            match self.stream.write(&self.output) {
+                Ok(0) => self.broken = true,
+                Ok(bytes_written) => {
+                    self.output.drain(..bytes_written);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => self.broken = true,
+            }
+        }
+        if !self.broken && self.stream.flush().is_err() {
+            self.broken = true;
+        }
+        if self.output.is_empty() {
+            self.over_soft_limit_since = None;
+        }
+    }
+
+    /// Takes the client with the complete commands read so far, to execute them, if
+    /// there are any and the client isn't already executing others. A client that
+    /// sent something that isn't a command gets the error and the connection is
+    /// closed.
+    pub fn take_commands(&mut self) -> Option<(Client, Vec<Command>)> {
+        if self.busy || self.closing || self.broken || self.client.is_none() {
+            return None;
+        }
+
+        match request::parse_requests(&mut self.input) {
+            Ok(commands) if !commands.is_empty() => {
+                self.busy = true;
+                self.client.take().map(|client| (client, commands))
+            }
+            Ok(_) => {
+                self.closing = self.read_closed;
+                None
+            }
+            Err(error) => {
+                self.respond_error(error);
+                None
+            }
+        }
+    }
+
+    /// Gives back the client once its commands were executed, with their replies, or
+    /// None if the client was lost while executing them. If alive is false the client
+    /// asked to close the connection.
+    pub fn finish(&mut self, outcome: Option<(Client, Vec<u8>, bool)>) {
+        self.busy = false;
+        match outcome {
+            Some((client, output, alive)) => {
+                self.resp3 = client.is_resp3();
+                self.client = Some(client);
+                self.output.extend_from_slice(&output);
+                self.closing |= !alive;
+            }
+            None => self.broken = true,
+        }
+    }
+
+    /// Moves the messages sent to the client, from the channels it subscribes to or
+    /// the monitor, to the output buffer.
+    ///
+    /// The channels encode the messages as RESP2 arrays, so for RESP3 clients they
+    /// become pushes, which only differ in the first byte.
+    pub fn receive_messages(&mut self) {
+        for message in self.messages.try_iter() {
+            let start = self.output.len();
+            self.output.extend_from_slice(message.as_bytes());
+            if self.resp3 && message.starts_with('*') {
+                self.output[start] = b'>';
+            }
+        }
+    }
+
+    /// Returns how many bytes are waiting to be written to the socket: the output
+    /// buffer and the messages sent to the client not moved to it yet. A TLS session
+    /// holds at most TLS_BUFFER_LIMIT bytes more.
+    pub fn pending_output(&self) -> usize {
+        self.output.len() + self.messages.queued()
+    }
+
+    /// Closes the connection, dropping what wasn't written yet, if it has more output
+    /// waiting than limit allows, as a client that doesn't read its messages as fast
+    /// as they're published would make the server keep them forever. Returns true if
+    /// it did.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let limit = OutputBufferLimit { hard: 32 * 1024 * 1024, soft: 0, soft_seconds: 0 };
+    /// if connection.enforce_output_limit(limit) {
+    ///     println!("Closed a slow client");
+    /// }
+    /// ```
+    pub fn enforce_output_limit(&mut self, limit: OutputBufferLimit) -> bool {
+        let pending = self.pending_output();
+        if limit.soft == 0 || pending <= limit.soft {
+            self.over_soft_limit_since = None;
+        } else if self.over_soft_limit_since.is_none() {
+            self.over_soft_limit_since = Some(Instant::now());
+        }
+
+        let over_hard = limit.hard > 0 && pending > limit.hard;
+        let over_soft = self
+            .over_soft_limit_since
+            .is_some_and(|since| since.elapsed() >= Duration::from_secs(limit.soft_seconds));
+        if !over_hard && !over_soft {
+            return false;
+        }
+
+        self.output.clear();
+        self.messages.try_iter().for_each(drop);
+        self.closing = true;
+        true
+    }
+
+    /// Replies error to the client and closes the connection once it's written.
+    pub fn respond_error(&mut self, error: String) {
+        Reponse::Error(error).respond(&mut self.output);
+        self.closing = true;
+    }
+
+    /// Returns true if the client subscribed to any channel or is a monitor.
+    pub fn is_subscribed(&self) -> bool {
+        self.client
+            .as_ref()
+            .is_some_and(|client| client.is_subscribed())
+    }
+
+    /// Returns true if the client didn't send anything for timeout, while it wasn't
+    /// waiting for messages.
+    pub fn is_idle_for(&self, timeout: Duration) -> bool {
+        !self.busy
+            && !self.closing
+            && !self.is_subscribed()
+            && self.last_activity.elapsed() >= timeout
+    }
+
+    /// Returns true if the connection can be closed: its client isn't executing
+    /// anything and either the socket failed or every reply was written after the
+    /// client, or the server, decided to close it.
+    pub fn is_done(&self) -> bool {
+        !self.busy && (self.broken || self.client.is_none() || (self.closing && !self.has_output()))
+    }
+
+    /// Asks registry to tell when the socket can be written only while there are
+    /// replies waiting to be written.
+    pub fn update_interest(&mut self, registry: &Registry, token: Token) -> io::Result<()> {
+        let interest = if self.has_output() {
+            Interest::READABLE | Interest::WRITABLE
+        } else {
+            Interest::READABLE
+        };
+
+        if interest != self.interest {
+            registry.reregister(self.stream.socket(), token, interest)?;
+            self.interest = interest;
+        }
+        Ok(())
+    }
+
+    /// Closes the connection, releasing what its client holds.
+    pub fn close(mut self, registry: &Registry) {
+        if let Stream::Tls(stream) = &mut self.stream {
+            stream.close();
+        }
+        if let Err(e) = registry.deregister(self.stream.socket()) {
+            eprintln!("Couldn't deregister a connection: {}", e);
+        }
+        if let Some(client) = self.client.as_mut() {
+            client.disconnect();
+        }
+    }
+
+    /// Returns true if there's something waiting to be written to the socket.
+    #[doc(hidden)]
+    fn has_output(&self) -> bool {
+        !self.output.is_empty() || self.stream.wants_write()
+    }
+}
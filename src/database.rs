@@ -1,4133 +1,8704 @@
+use crate::aof::{self, Aof};
+use crate::channels::Channels;
 use crate::databasehelper::{
-    DataBaseError, KeyTtl, MessageTtl, RespondTtl, SortFlags, StorageValue, SuccessQuery,
+    crc32, parse_bytes, random_number, write_field, Aggregate, DataBaseError, DumpReader,
+    ExpireFlags, KeyExpiry, KeyTtl, SetFlags, SortFlags, StorageValue, SuccessQuery, TtlIndex,
+    ZaddFlags, ZrangeBy,
 };
+use crate::eviction::EvictionPolicy;
 use crate::hash_shard::HashShard;
 use crate::matcher::matcher;
+use crate::notify::{EventClass, KeyspaceEvents, Notifier};
+use crate::rdb::{self, DumpFormat, RdbWriter};
+use crate::server_conf::ServerConf;
+use crate::sorted_set::{LexBound, ScoreBound, SortedSet};
 use core::str;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Formatter};
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufRead, Write};
-use std::path::Path;
-use std::sync::mpsc::{self, channel, Receiver, Sender};
-use std::sync::{Arc, Mutex};
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::thread;
 use std::time::{Duration, SystemTime};
 
 #[doc(hidden)]
-type TtlVector = Arc<Mutex<Vec<KeyTtl>>>;
+fn format_float(number: f64) -> String {
+    if number == number.trunc() && number.abs() < 1e17 {
+        format!("{}", number as i64)
+    } else {
+        format!("{}", number)
+    }
+}
+
+#[doc(hidden)]
+fn not_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+#[doc(hidden)]
+fn aggregate_scores(aggregate: Aggregate, current: f64, score: f64) -> f64 {
+    match aggregate {
+        Aggregate::Sum => not_nan(current + score),
+        Aggregate::Min => current.min(score),
+        Aggregate::Max => current.max(score),
+    }
+}
+
+/// Turns the start and stop indexes of a range, which can be negative to count from the
+/// end, into the positions [start, end) of a sequence of len elements.
+#[doc(hidden)]
+fn index_range(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+
+    if start > stop || start >= len {
+        None
+    } else {
+        Some((start as usize, stop as usize + 1))
+    }
+}
+
+#[doc(hidden)]
+fn zset_reply(members: Vec<(Vec<u8>, f64)>, with_scores: bool) -> SuccessQuery {
+    let mut result = Vec::new();
+    for (member, score) in members {
+        result.push(SuccessQuery::String(member));
+        if with_scores {
+            result.push(SuccessQuery::String(format_float(score).into_bytes()));
+        }
+    }
+    SuccessQuery::List(result)
+}
+
+#[doc(hidden)]
+fn scan_position(field: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    field.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns the moment ms milliseconds from now, in the past if ms is negative, or
+/// None if it can't be represented.
+#[doc(hidden)]
+fn time_from_now(ms: i64) -> Option<SystemTime> {
+    let duration = Duration::from_millis(ms.unsigned_abs());
+    if ms < 0 {
+        Some(
+            SystemTime::now()
+                .checked_sub(duration)
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        )
+    } else {
+        SystemTime::now().checked_add(duration)
+    }
+}
+
+/// Returns the moment ms milliseconds after the Unix epoch, or None if it can't be
+/// represented.
+#[doc(hidden)]
+fn time_from_unix_ms(ms: i64) -> Option<SystemTime> {
+    SystemTime::UNIX_EPOCH.checked_add(Duration::from_millis(ms.max(0) as u64))
+}
+
+#[doc(hidden)]
+fn unix_ms(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+#[doc(hidden)]
+type Expires = Arc<Mutex<TtlIndex>>;
+#[doc(hidden)]
+type WatchedKeys = Arc<Mutex<HashMap<(usize, Vec<u8>), (u64, usize)>>>;
+
+/// One of the logical databases: its keys, the supervisor of their time to live and
+/// its number, which changes with SWAPDB.
+#[doc(hidden)]
+#[derive(Clone)]
+struct Keyspace {
+    dictionary: HashShard,
+    expires: Expires,
+    index: Arc<AtomicUsize>,
+}
+
+impl Keyspace {
+    #[doc(hidden)]
+    fn new(shards: usize, index: usize, watched_keys: WatchedKeys, notifier: Notifier) -> Keyspace {
+        let dictionary = HashShard::new(shards);
+        let expires = Arc::new(Mutex::new(TtlIndex::new()));
+        let index = Arc::new(AtomicUsize::new(index));
+        ttl_supervisor_run(
+            dictionary.clone(),
+            Arc::downgrade(&expires),
+            index.clone(),
+            watched_keys,
+            notifier,
+        );
+
+        Keyspace {
+            dictionary,
+            expires,
+            index,
+        }
+    }
+
+    #[doc(hidden)]
+    fn ttls(&self) -> Vec<KeyTtl> {
+        self.expires.lock().unwrap().to_vec()
+    }
+}
 
 /// A Database implemented in a multithreading context.
 ///
 /// Database uses Arc and Mutex to be shared safety in a multithreading context
 /// implementing clone.
-/// It also supervises the keys's time to live and save its own data every 30
-/// seconds.
+/// It holds a fixed number of logical databases, numbered from 0, and every clone
+/// has its own selected database, the one every command works with.
+/// It also supervises the keys's time to live and, once run_serializer is called,
+/// saves its own data when the configured save points are reached.
+/// Once it has channels, it publishes the keyspace events set with
+/// set_notify_keyspace_events in them.
 ///
 pub struct Database {
     #[doc(hidden)]
-    dictionary: HashShard,
+    keyspaces: Arc<RwLock<Vec<Keyspace>>>,
     #[doc(hidden)]
-    ttl_msg_sender: Sender<MessageTtl>,
+    index: usize,
     #[doc(hidden)]
     db_dump_path: String,
+    #[doc(hidden)]
+    transaction_lock: Arc<RwLock<()>>,
+    #[doc(hidden)]
+    watched_keys: WatchedKeys,
+    #[doc(hidden)]
+    aof: Option<Arc<Mutex<Aof>>>,
+    #[doc(hidden)]
+    save_state: Arc<Mutex<SaveState>>,
+    #[doc(hidden)]
+    memory_limit: Arc<Mutex<MemoryLimit>>,
+    #[doc(hidden)]
+    notifier: Notifier,
+    #[doc(hidden)]
+    modified: bool,
 }
 
+/// Writes contents to path through a temporary file that is renamed over it once it's
+/// on disk, so path always has either the old or the new contents.
 #[doc(hidden)]
-fn open_serializer(path: &str) -> Result<File, String> {
-    match OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(path)
-    {
-        Err(why) => Err(format!("Couldn't open file: {}", why)),
-        Ok(file) => Ok(file),
+fn write_atomically(path: &str, contents: &[u8]) -> io::Result<()> {
+    let temp_path = format!("{}.tmp", path);
+    let result = File::create(&temp_path)
+        .and_then(|mut file| file.write_all(contents).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+#[doc(hidden)]
+const DUMP_VERSION: u32 = 2;
+
+/// Checks the header and the checksum of a dump written by Database::dump, returning
+/// a reader over its records and the version of the format. Dumps written before
+/// the format had a version have neither, so they are read as they are, as version 0.
+#[doc(hidden)]
+fn open_dump(dump: &[u8]) -> Result<(DumpReader<'_>, u32), String> {
+    if !dump.starts_with(b"Dump ") {
+        return Ok((DumpReader::new(dump), 0));
+    }
+
+    let last_line = dump[..dump.len() - 1]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |pos| pos + 1);
+    let mut trailer = DumpReader::new(&dump[last_line..]);
+    let checksum = match (trailer.next_word(), trailer.next_number::<u32>()) {
+        (Some(b"Checksum"), Some(checksum)) if trailer.is_empty() => checksum,
+        _ => return Err("missing checksum, the dump may be truncated".to_string()),
+    };
+    if crc32(&dump[..last_line]) != checksum {
+        return Err("checksum mismatch".to_string());
+    }
+
+    let mut reader = DumpReader::new(&dump[..last_line]);
+    reader.next_word();
+    match reader.next_number::<u32>() {
+        Some(version) if (1..=DUMP_VERSION).contains(&version) => Ok((reader, version)),
+        Some(version) => Err(format!("unsupported version {}", version)),
+        None => Err("bad header".to_string()),
     }
 }
 
+/// The memory limit of the data, maxmemory bytes or none if it is 0, how keys are
+/// evicted to stay below it and how many were.
+#[doc(hidden)]
+struct MemoryLimit {
+    maxmemory: usize,
+    policy: EvictionPolicy,
+    evicted_keys: u64,
+}
+
+#[doc(hidden)]
+const MAXMEMORY_SAMPLES: usize = 5;
+
 #[doc(hidden)]
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+struct SaveState {
+    dirty: u64,
+    last_save: SystemTime,
+    saving: bool,
+    format: DumpFormat,
 }
 
 impl Database {
-    /// Creates a new Database.
+    /// Creates a new Database with the given number of logical databases, at least one,
+    /// each one dividing its keys in the given number of shards.
     ///
     /// If there's somenthing in path_to_dump.txt loads all the data there and run the
     /// ttl_supervisor, wich supervises the time to live for every key.
+    ///
+    /// Error if the dump can't be read, is corrupt, or has keys of a database that
+    /// doesn't exist, so a bad dump is never loaded partially.
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut database = Database("path_to_dump.txt");
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
     /// ```
-    pub fn new(db_dump_path: String) -> Database {
-        let (ttl_msg_sender, ttl_rec) = mpsc::channel();
-
-        let mut database = Database {
-            dictionary: HashShard::new(),
-            ttl_msg_sender,
-            db_dump_path,
-        };
-
-        database.ttl_supervisor_run(ttl_rec);
-
-        if let Ok(lines) = read_lines(&database.db_dump_path) {
-            let mut dic = database.dictionary.clone();
-            let mut expires: Vec<(String, i64)> = Vec::new();
-
-            for line in lines.flatten() {
-                if &line[0..3] == "TTL" {
-                    let ttl_list: Vec<&str> = line.split_whitespace().collect();
-
-                    if ttl_list.len() > 1 {
-                        let ttl_list: Vec<&str> = ttl_list[1].split(';').collect();
-                        for &key_ttl in ttl_list.iter() {
-                            if !key_ttl.is_empty() {
-                                let key_ttl: Vec<&str> = key_ttl.split(',').collect();
-                                //Get Key
-                                let key: Vec<&str> = key_ttl[0].split(':').collect();
-                                let key = key[1];
-                                //Get TTL
-                                let ttl: Vec<&str> = key_ttl[1].split(':').collect();
-                                let ttl = ttl[1].parse::<i64>().unwrap();
-
-                                expires.push((key.to_string(), ttl));
-                            }
-                        }
-                    }
-                } else {
-                    let key_value: Vec<&str> = line.split(',').collect();
-
-                    let key: Vec<&str> = key_value[0].split(':').collect();
-                    let key = key[1];
+    pub fn new(
+        db_dump_path: String,
+        databases: usize,
+        shards: usize,
+    ) -> Result<Database, DataBaseError> {
+        let mut database = Database::empty(db_dump_path, databases, shards);
+        database.load_dump()?;
 
-                    let value = key_value[1];
-                    if let Ok(value) = StorageValue::unserialize(value) {
-                        dic.insert(key.to_string(), value);
-                    }
-                }
-            }
+        Ok(database)
+    }
 
-            for (key, ttl) in expires {
-                database.expireat(&key, ttl).unwrap();
+    /// Creates a new Database that logs every write command in aof.
+    ///
+    /// If aof has commands they are replayed and the dump isn't loaded, because the
+    /// file has every write while the dump can be older. Otherwise the dump is
+    /// loaded and aof is rewritten with its keys, so they are not lost on the next start.
+    ///
+    /// Error if aof stops following the protocol before its end, which is reported
+    /// with the byte where it does, or if the dump can't be loaded.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let aof = Aof::open("appendonly.aof", AppendFsync::Everysec)?;
+    /// let mut database = Database::new_with_aof("path_to_dump.txt", 16, 16, aof).unwrap();
+    /// ```
+    pub fn new_with_aof(
+        db_dump_path: String,
+        databases: usize,
+        shards: usize,
+        aof: Aof,
+    ) -> Result<Database, DataBaseError> {
+        let mut database = Database::empty(db_dump_path, databases, shards);
+        let replayed = aof::replay(aof.path(), &mut database)?;
+        database.index = 0;
+        let aof = aof::run(aof);
+
+        if !replayed {
+            database.load_dump()?;
+            aof.lock().unwrap().start_rewrite();
+            aof.lock().unwrap().buffer_rewrite();
+            if let Err(e) = aof::rewrite(&aof, &database.aof_rewrite()) {
+                eprintln!("Couldn't rewrite the append only file: {}", e);
             }
         }
 
-        let serializer_db = database.clone();
-        serializer_db.run_serializer();
+        database.aof = Some(aof);
+        database.save_state.lock().unwrap().dirty = 0;
 
-        database
+        Ok(database)
     }
 
     #[doc(hidden)]
-    pub fn new_from_db(
-        ttl_msg_sender: Sender<MessageTtl>,
-        dictionary: HashShard,
-        db_dump_path: String,
-    ) -> Database {
+    fn empty(db_dump_path: String, databases: usize, shards: usize) -> Database {
+        let notifier = Notifier::default();
+        let watched_keys: WatchedKeys = Arc::new(Mutex::new(HashMap::new()));
+        let keyspaces = (0..databases.max(1))
+            .map(|index| Keyspace::new(shards, index, watched_keys.clone(), notifier.clone()))
+            .collect();
+
         Database {
-            dictionary,
-            ttl_msg_sender,
+            keyspaces: Arc::new(RwLock::new(keyspaces)),
+            index: 0,
             db_dump_path,
+            transaction_lock: Arc::new(RwLock::new(())),
+            watched_keys,
+            aof: None,
+            save_state: Arc::new(Mutex::new(SaveState {
+                dirty: 0,
+                last_save: SystemTime::now(),
+                saving: false,
+                format: DumpFormat::Text,
+            })),
+            memory_limit: Arc::new(Mutex::new(MemoryLimit {
+                maxmemory: 0,
+                policy: EvictionPolicy::NoEviction,
+                evicted_keys: 0,
+            })),
+            notifier,
+            modified: false,
         }
     }
 
     #[doc(hidden)]
-    pub fn run_serializer(&self) {
-        let path = self.db_dump_path.clone();
-        let dic = self.dictionary.clone();
-        let ttl_msg_sender = self.ttl_msg_sender.clone();
-        thread::spawn(move || loop {
-            thread::sleep(Duration::from_secs(30));
-            let mut serializer = open_serializer(&path).unwrap();
-
-            serializer.set_len(0).unwrap();
+    fn load_dump(&mut self) -> Result<(), DataBaseError> {
+        let dump = match fs::read(&self.db_dump_path) {
+            Ok(dump) => dump,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(DataBaseError::CorruptDump(e.to_string())),
+        };
 
-            let (sender, reciver) = channel();
-            ttl_msg_sender.send(MessageTtl::AllTtL(sender)).unwrap();
+        if dump.starts_with(b"REDIS") {
+            return self.load_rdb(&dump);
+        }
 
-            if let Err(e) = write!(serializer, "TTL ") {
-                eprintln!("Couldn't write: {}", e);
-            }
+        let mut index = 0;
+        let mut expires: Vec<(usize, Vec<u8>, i64)> = Vec::new();
+        let (mut reader, version) = open_dump(&dump).map_err(DataBaseError::CorruptDump)?;
+        // Before version 2 times to live were saved in seconds instead of milliseconds.
+        let ttl_unit = if version < 2 { 1000 } else { 1 };
 
-            if let Ok(RespondTtl::List(list)) = reciver.recv() {
-                let guard = list.lock().unwrap();
+        while !reader.is_empty() {
+            let position = reader.position();
+            let corrupt = |record: &str| {
+                let message = format!("bad {} record at byte {}", record, position);
+                DataBaseError::CorruptDump(message)
+            };
 
-                for key_ttl in guard.iter() {
-                    let duration = key_ttl
-                        .expire_time
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap();
-                    if let Err(e) = write!(
-                        serializer,
-                        "Key:{},Ttl:{};",
-                        key_ttl.key,
-                        duration.as_secs()
-                    ) {
-                        eprintln!("Couldn't write: {}", e);
+            match reader.next_word() {
+                Some(b"Db") => match reader.next_number::<usize>() {
+                    Some(n) if self.select(n).is_ok() => index = n,
+                    _ => return Err(corrupt("Db")),
+                },
+                Some(b"TTL") => match (reader.next_field(), reader.next_number::<i64>()) {
+                    (Some(key), Some(ttl)) => {
+                        expires.push((index, key.to_vec(), ttl.saturating_mul(ttl_unit)))
                     }
+                    _ => return Err(corrupt("TTL")),
+                },
+                Some(b"Key") => {
+                    let key = reader.next_field().ok_or_else(|| corrupt("Key"))?;
+                    let value =
+                        StorageValue::unserialize(&mut reader).map_err(|_| corrupt("Key"))?;
+                    self.dictionary().insert(key.to_vec(), value);
                 }
+                _ => return Err(corrupt("unknown")),
             }
+        }
 
-            if let Err(e) = writeln!(serializer) {
-                eprintln!("Couldn't write: {}", e);
-            }
-
-            for (key, value) in dic.key_value() {
-                if let Err(e) = writeln!(serializer, "Key:{},{}", key, value.serialize()) {
-                    eprintln!("Couldn't write: {}", e);
-                }
-            }
-        });
+        for (index, key, ttl) in expires {
+            self.select(index).unwrap();
+            let _ = self.pexpireat(&key, ttl, ExpireFlags::default());
+        }
+        self.index = 0;
+        Ok(())
     }
 
     #[doc(hidden)]
-    pub fn ttl_supervisor_run(&mut self, reciver: Receiver<MessageTtl>) {
-        let mut dictionary = self.dictionary.clone();
+    fn load_rdb(&mut self, dump: &[u8]) -> Result<(), DataBaseError> {
+        let entries = rdb::load(dump).map_err(DataBaseError::CorruptDump)?;
+        let mut expires = Vec::new();
+
+        for entry in entries {
+            if self.select(entry.db).is_err() {
+                let message = format!("keys of database {}, which doesn't exist", entry.db);
+                return Err(DataBaseError::CorruptDump(message));
+            }
+            if let Some(expire_ms) = entry.expire_ms {
+                expires.push((entry.db, entry.key.clone(), expire_ms as i64));
+            }
+            self.dictionary().insert(entry.key, entry.value);
+        }
 
-        thread::spawn(move || {
-            let ttl_keys: TtlVector = Arc::new(Mutex::new(Vec::new()));
+        for (index, key, ttl) in expires {
+            self.select(index).unwrap();
+            let _ = self.pexpireat(&key, ttl, ExpireFlags::default());
+        }
+        self.index = 0;
+        Ok(())
+    }
 
-            for message in reciver.iter() {
-                match message {
-                    MessageTtl::Expire(new_key_ttl) => {
-                        let keys = ttl_keys.clone();
-                        let mut keys_locked = keys.lock().unwrap();
+    #[doc(hidden)]
+    fn dictionary(&self) -> HashShard {
+        self.keyspaces.read().unwrap()[self.index]
+            .dictionary
+            .clone()
+    }
 
-                        if let Some(pos) = keys_locked.iter().position(|x| x.key == new_key_ttl.key)
-                        {
-                            let mut value = keys_locked.get_mut(pos).unwrap();
-                            value.expire_time = new_key_ttl.expire_time;
-                        } else {
-                            match keys_locked.binary_search(&new_key_ttl) {
-                                Ok(pos) => {
-                                    keys_locked.remove(pos);
-                                    keys_locked.insert(pos, new_key_ttl);
-                                }
-                                Err(pos) => {
-                                    keys_locked.insert(pos, new_key_ttl);
-                                }
-                            }
-                        }
+    #[doc(hidden)]
+    fn expires(&self) -> Expires {
+        self.keyspaces.read().unwrap()[self.index].expires.clone()
+    }
 
-                        if keys_locked.len() == 1 {
-                            let dic = dictionary.clone();
-                            let ttl_vector = ttl_keys.clone();
+    /// Records that the running command changed a key, as seen while holding its lock.
+    #[doc(hidden)]
+    fn mark_modified(&mut self) {
+        self.modified = true;
+    }
 
-                            thread::spawn(move || {
-                                executor(dic, ttl_vector);
-                            });
-                        }
-                    }
-                    MessageTtl::Clear(key) => {
-                        let keys = ttl_keys.clone();
-                        let mut keys_locked = keys.lock().unwrap();
+    /// Returns true if a command run through this handle changed a key since the last
+    /// call, and forgets it. Commands that find nothing to change, as DEL of a missing
+    /// key or SET NX of an existing one, leave it false.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    /// assert!(database.take_modified());
+    /// assert!(!database.take_modified());
+    /// ```
+    pub fn take_modified(&mut self) -> bool {
+        std::mem::replace(&mut self.modified, false)
+    }
 
-                        if let Some(pos) = keys_locked.iter().position(|x| x.key == key) {
-                            keys_locked.remove(pos);
-                        }
-                    }
+    /// Removes key, and its time to live, from the selected database.
+    #[doc(hidden)]
+    fn remove_key(&mut self, key: &[u8]) -> Option<StorageValue> {
+        let expires = self.expires();
+        let mut expires = expires.lock().unwrap();
+        expires.remove(key);
+        self.dictionary().remove(key)
+    }
 
-                    MessageTtl::Transfer(from_key, to_key) => {
-                        let keys = ttl_keys.clone();
-                        let mut keys_locked = keys.lock().unwrap();
+    /// Saves the data in background every time a save point of config is reached: a
+    /// save point (seconds, changes) is reached when there were at least that many
+    /// changes and that many seconds passed since the last save.
+    pub fn run_serializer(&self, config: ServerConf) {
+        let database = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+
+            let state = database.save_state.lock().unwrap();
+            let elapsed = SystemTime::now()
+                .duration_since(state.last_save)
+                .unwrap_or_default()
+                .as_secs();
+            let save = !state.saving
+                && state.dirty > 0
+                && config
+                    .save_points()
+                    .iter()
+                    .any(|(seconds, changes)| state.dirty >= *changes && elapsed >= *seconds);
+            drop(state);
 
-                        if let Some(pos) = keys_locked.iter().position(|x| x.key == from_key) {
-                            let ttl = keys_locked.get_mut(pos).unwrap();
-                            ttl.key = to_key;
-                        }
-                    }
-                    MessageTtl::Ttl(key, sender_respond) => {
-                        let keys = ttl_keys.clone();
-                        let keys_locked = keys.lock().unwrap();
-
-                        if let Some(pos) = keys_locked.iter().position(|x| x.key == key) {
-                            let ttl = keys_locked.get(pos).unwrap();
-                            sender_respond
-                                .send(RespondTtl::Ttl(ttl.expire_time))
-                                .unwrap();
-                        } else {
-                            sender_respond.send(RespondTtl::Persistent).unwrap();
-                        }
-                    }
-                    MessageTtl::AllTtL(sender_respond) => {
-                        sender_respond
-                            .send(RespondTtl::List(ttl_keys.clone()))
-                            .unwrap();
-                    }
-                    MessageTtl::Check(key) => {
-                        let keys = ttl_keys.clone();
-                        let mut keys_locked = keys.lock().unwrap();
-
-                        if let Some(pos) = keys_locked.iter().position(|x| x.key == key) {
-                            let ttl = keys_locked.get(pos).unwrap();
-                            if ttl.expire_time < SystemTime::now() {
-                                dictionary.remove(&ttl.key);
-                                keys_locked.remove(pos);
-                            }
-                        }
-                    }
-                };
+            if save {
+                let _ = database.bgsave();
             }
         });
     }
 
-    // KEYS
+    #[doc(hidden)]
+    fn finish_save(&self, dirty: u64, result: io::Result<()>) -> Result<(), DataBaseError> {
+        let mut state = self.save_state.lock().unwrap();
+        state.saving = false;
 
-    /// Delete all the keys of the currently selected DB. This command never fails.
+        match result {
+            Ok(()) => {
+                state.dirty -= dirty.min(state.dirty);
+                state.last_save = SystemTime::now();
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Couldn't write the dump: {}", e);
+                Err(DataBaseError::SaveFailed)
+            }
+        }
+    }
+
+    /// Saves the data in the dump file, blocking until it's written. The dump is
+    /// written to a temporary file first, so a failure never leaves the dump file
+    /// empty or half written.
     ///
-    /// Reply: SuccessQuery:Success
+    /// Reply: SuccessQuery::Success.
     ///
+    /// Error if a background save is in progress or if the file couldn't be written.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// let mut db = Database("path_to_dump.txt");
-    ///
-    /// db.mset(vec!["KEY1", "VALUE1", "KEY2", "VALUE2"]).unwrap();
-    /// let r = db.get("KEY1").unwrap();
-    /// assert_eq!(r, SuccessQuery::String("VALUE1"));
-    /// let r = db.get("KEY2").unwrap();
-    /// assert_eq!(r, SuccessQuery::String("VALUE2"));
-    ///
-    /// let r = db.flushdb().unwrap();
-    /// assert_eq!(r, SuccessQuery::Success);
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
     ///
-    /// let guard = db.dictionary;
-    /// assert!(guard.len() == 0);
+    /// assert_eq!(database.save().unwrap(), SuccessQuery::Success);
     /// ```
-    pub fn flushdb(&mut self) -> Result<SuccessQuery, DataBaseError> {
-        self.dictionary.clear();
+    pub fn save(&self) -> Result<SuccessQuery, DataBaseError> {
+        let mut state = self.save_state.lock().unwrap();
+        if state.saving {
+            return Err(DataBaseError::SaveInProgress);
+        }
+        state.saving = true;
+        let dirty = state.dirty;
+        drop(state);
+
+        let result = write_atomically(&self.db_dump_path, &self.snapshot());
+        self.finish_save(dirty, result)?;
         Ok(SuccessQuery::Success)
     }
 
-    /// Return the number of keys in the currently-selected database. This command never fails.
+    /// Saves the data in the dump file in background, like Database::save.
     ///
-    /// Reply: SuccessQuery:Integer(n) where n is the number of keys in currently database.
+    /// Reply: SuccessQuery::String with a message telling the save started.
     ///
+    /// Error if a save is already in progress.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// let mut db = Database("path_to_dump.txt");
-    /// let _ = db.set(KEY1, VALUE1);
-    /// let r = db.get(KEY1).unwrap();
-    /// assert_eq!(r, SuccessQuery::String(VALUE1.to_owned()));
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
     ///
-    /// let r = db.dbsize().unwrap();
-    /// assert_eq!(r, SuccessQuery::Integer(1));
+    /// database.bgsave().unwrap();
     /// ```
-    pub fn dbsize(&self) -> Result<SuccessQuery, DataBaseError> {
-        Ok(SuccessQuery::Integer(self.dictionary.len() as i32))
+    pub fn bgsave(&self) -> Result<SuccessQuery, DataBaseError> {
+        let mut state = self.save_state.lock().unwrap();
+        if state.saving {
+            return Err(DataBaseError::SaveInProgress);
+        }
+        state.saving = true;
+        drop(state);
+
+        let database = self.clone();
+        thread::spawn(move || {
+            let lock = database.transaction_lock();
+            let guard = lock.read().unwrap();
+            let dirty = database.save_state.lock().unwrap().dirty;
+            let dump = database.snapshot();
+            drop(guard);
+
+            let result = write_atomically(&database.db_dump_path, &dump);
+            let _ = database.finish_save(dirty, result);
+        });
+
+        Ok(SuccessQuery::String(b"Background saving started".to_vec()))
     }
 
-    /// This command copies the value stored at the source key to the destination key.
-    ///
-    /// The command returns an error when the destination key already exists.
-    ///
-    /// Reply: SuccessQuery:Success if copies sucessful. Database error in wrong case.
+    /// Returns the Unix time of the last successful save, or of the moment the
+    /// Database was created if it was never saved.
     ///
+    /// Reply: SuccessQuery::Integer with the seconds since the Unix epoch.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// let mut database = Database("path_to_dump.txt");
+    /// let database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
     ///
-    /// database.set("KEY", "VALUE").unwrap();
-    /// database.set("SECOND_KEY", "SECOND_VALUE").unwrap();
-    /// let result = database.copy("KEY", "SECOND_KEY");
-    ///
-    /// assert_eq!(result.unwrap_err(), DataBaseError::KeyAlredyExist);
+    /// assert_eq!(database.lastsave().unwrap(), SuccessQuery::Integer(now.as_secs() as i64));
     /// ```
-    pub fn copy(&mut self, key: &str, to_key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if self._exists(to_key) {
-            return Err(DataBaseError::KeyAlredyExist);
-        }
+    pub fn lastsave(&self) -> Result<SuccessQuery, DataBaseError> {
+        let last_save = self.save_state.lock().unwrap().last_save;
+        let seconds = last_save
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Ok(SuccessQuery::Integer(seconds as i64))
+    }
 
-        if !self._exists(key) {
-            return Err(DataBaseError::NonExistentKey);
-        }
+    /// Counts a change done since the last save.
+    pub fn increment_dirty(&self) {
+        self.save_state.lock().unwrap().dirty += 1;
+    }
 
-        self.dictionary.touch(key);
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get(key) {
-            Some(val) => {
-                let val = val.clone();
-                dictionary.insert(to_key.to_owned(), val);
-                Ok(SuccessQuery::Success)
-            }
-            None => Err(DataBaseError::NonExistentKey),
+    /// Sets the format the dump is saved in. Dumps are loaded in whatever format
+    /// they were saved.
+    pub fn set_dump_format(&self, format: DumpFormat) {
+        self.save_state.lock().unwrap().format = format;
+    }
+
+    #[doc(hidden)]
+    fn snapshot(&self) -> Vec<u8> {
+        match self.save_state.lock().unwrap().format {
+            DumpFormat::Text => self.dump(),
+            DumpFormat::Rdb => self.rdb_dump(),
         }
     }
 
-    /// Removes the specified keys. A key is ignored if it does not exist.
+    /// Saves the data and loads it back from the dump file, replacing the keys of
+    /// every database, so a round trip through the dump can be checked.
     ///
-    /// Reply: SuccessQuery:Integer(n) where n=1 if were removed or n=0 if were not removed.
+    /// The caller holds the write lock of transaction_lock, so no other client sees
+    /// the data while it's flushed nor writes anything that isn't saved.
     ///
+    /// Reply: SuccessQuery::Success.
+    ///
+    /// Error if the data couldn't be saved or the saved dump couldn't be loaded.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// todo
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    ///
+    /// database.debug_reload().unwrap();
+    ///
+    /// assert_eq!(database.get(b"key").unwrap(), SuccessQuery::String(b"value".to_vec()));
     /// ```
-    pub fn del(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        self.dictionary.remove(key);
-        Ok(SuccessQuery::Success)
-    }
+    pub fn debug_reload(&mut self) -> Result<SuccessQuery, DataBaseError> {
+        self.save()?;
 
-    #[doc(hidden)]
-    fn _exists(&self, key: &str) -> bool {
-        let contains_key = self.dictionary.contains_key(key);
-        let expire_time_passed = match self.get_expire_time(key) {
-            RespondTtl::Ttl(expire_time) => expire_time < SystemTime::now(),
-            _ => false,
-        };
+        let index = self.index;
+        self.flushall()?;
+        let result = self.load_dump();
+        self.index = index;
 
-        !expire_time_passed && contains_key
+        result.map(|_| SuccessQuery::Success)
     }
 
-    /// Returns if key exists.
+    /// Saves the data if save is true and flushes the append only file, if there's one,
+    /// so nothing is lost when the server exits.
     ///
-    /// Reply: SuccessQuery:Integer(n) where n is specified by:
-    ///
-    /// n=1 if the key exists.
-    ///
-    /// n=0 if the key does not exist.
+    /// Error if the data couldn't be saved.
+    pub fn prepare_shutdown(&self, save: bool) -> Result<(), DataBaseError> {
+        if let Some(aof) = &self.aof {
+            aof.lock().unwrap().sync();
+        }
+
+        if save {
+            while self.save_state.lock().unwrap().saving {
+                thread::sleep(Duration::from_millis(10));
+            }
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Serializes all the keys, and their time to live in milliseconds, in the format
+    /// read by Database::new. The dump starts with a line with the version of the format and
+    /// ends with a line with the CRC-32 of everything before it. The records of every
+    /// non empty database follow a line with its number.
     ///
+    /// Every record takes a line and every key and value is prefixed by its length,
+    /// so they can contain spaces, new lines or any other byte.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// let mut database = Database("path_to_dump.txt");
-    /// database.set("KEY", "VALUE").unwrap();
-    ///
-    /// let result = database.exists("KEY").unwrap();
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"a value").unwrap();
     ///
-    /// assert_eq!(result, SuccessQuery::Boolean(true));
+    /// let dump = database.dump();
+    /// assert!(dump.starts_with(b"Dump 2\nDb 0\nKey 3:key String 7:a value\nChecksum "));
     /// ```
-    pub fn exists(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        self.dictionary.touch(key);
-        Ok(SuccessQuery::Boolean(self._exists(key)))
+    pub fn dump(&self) -> Vec<u8> {
+        let mut dump = format!("Dump {}\n", DUMP_VERSION).into_bytes();
+        let keyspaces = self.keyspaces.read().unwrap().clone();
+
+        for (index, keyspace) in keyspaces.iter().enumerate() {
+            if keyspace.dictionary.len() == 0 {
+                continue;
+            }
+            dump.extend_from_slice(format!("Db {}\n", index).as_bytes());
+
+            for key_ttl in keyspace.ttls() {
+                let duration = key_ttl
+                    .expire_time
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap();
+
+                dump.extend_from_slice(b"TTL ");
+                write_field(&mut dump, &key_ttl.key);
+                dump.extend_from_slice(format!(" {}\n", duration.as_millis()).as_bytes());
+            }
+
+            for (key, value) in keyspace.dictionary.key_value() {
+                dump.extend_from_slice(b"Key ");
+                write_field(&mut dump, &key);
+                dump.push(b' ');
+                dump.extend_from_slice(&value.serialize());
+                dump.push(b'\n');
+            }
+        }
+
+        let checksum = crc32(&dump);
+        dump.extend_from_slice(format!("Checksum {}\n", checksum).as_bytes());
+        dump
     }
 
-    /// Set a timeout on key. After the timeout has expired, the key will automatically be deleted.
-    /// A key with an associated timeout is often said to be volatile in Redis terminology.
+    /// Serializes all the keys, and their time to live, in the RDB format of Redis,
+    /// so the dump can be loaded by Redis too.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"a value").unwrap();
     ///
-    /// The timeout will only be cleared by commands that delete or overwrite the contents of the key, including DEL, SET, GETSET and
-    /// all the *STORE commands.
-    ///
-    /// This means that all the operations that conceptually alter the value stored at the key without replacing it with a new one will leave the timeout untouched.
-    /// For instance, incrementing the value of a key with INCR, pushing a new value into a list with LPUSH,
-    /// or altering the field value of a hash with HSET are all operations that will leave the timeout untouched.
-    ///
-    ///
-    /// The timeout can also be cleared, turning the key back into a persistent key, using the PERSIST command.
-    ///
-    /// If a key is renamed with RENAME, the associated time to live is transferred to the new key name.
+    /// let dump = database.rdb_dump();
+    /// assert!(dump.starts_with(b"REDIS0009"));
+    /// ```
+    pub fn rdb_dump(&self) -> Vec<u8> {
+        let mut writer = RdbWriter::new();
+        let keyspaces = self.keyspaces.read().unwrap().clone();
+
+        for (index, keyspace) in keyspaces.iter().enumerate() {
+            let keys = keyspace.dictionary.key_value();
+            if keys.is_empty() {
+                continue;
+            }
+
+            let mut expires = HashMap::new();
+            for key_ttl in keyspace.ttls() {
+                let duration = key_ttl
+                    .expire_time
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap();
+                expires.insert(key_ttl.key, duration.as_millis() as u64);
+            }
+
+            writer.select_db(index, keys.len(), expires.len());
+            for (key, value) in keys {
+                let expire_ms = expires.get(&key).copied();
+                writer.write_key(&key, &value, expire_ms);
+            }
+        }
+
+        writer.finish()
+    }
+
+    /// Returns the commands that rebuild every key, with its time to live, encoded
+    /// as they are written in the append only file.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
     ///
-    /// If a key is overwritten by RENAME, like in the case of an existing key Key_A that is overwritten by a call like RENAME Key_B Key_A,
-    /// it does not matter if the original Key_A had a timeout associated or not,
-    /// the new key Key_A will inherit all the characteristics of Key_B.
+    /// let mut commands = encode_command(&[b"select".to_vec(), b"0".to_vec()]);
+    /// commands.extend(encode_command(&[b"set".to_vec(), b"key".to_vec(), b"value".to_vec()]));
+    /// assert_eq!(database.aof_rewrite(), commands);
+    /// ```
+    pub fn aof_rewrite(&self) -> Vec<u8> {
+        let mut rewritten = Vec::new();
+        let keyspaces = self.keyspaces.read().unwrap().clone();
+
+        for (index, keyspace) in keyspaces.iter().enumerate() {
+            if keyspace.dictionary.len() == 0 {
+                continue;
+            }
+            let select = vec![b"select".to_vec(), index.to_string().into_bytes()];
+            rewritten.extend_from_slice(&aof::encode_command(&select));
+
+            for (key, value) in keyspace.dictionary.key_value() {
+                if let Some(command) = value.aof_command(&key) {
+                    rewritten.extend_from_slice(&aof::encode_command(&command));
+                }
+            }
+
+            for key_ttl in keyspace.ttls() {
+                let duration = key_ttl
+                    .expire_time
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap();
+                let pexpireat = vec![
+                    b"pexpireat".to_vec(),
+                    key_ttl.key,
+                    duration.as_millis().to_string().into_bytes(),
+                ];
+                rewritten.extend_from_slice(&aof::encode_command(&pexpireat));
+            }
+        }
+
+        rewritten
+    }
+
+    /// Logs command in the append only file, if there's one, as executed on the selected
+    /// database.
+    pub fn feed_aof(&self, command: &[Vec<u8>]) {
+        if let Some(aof) = &self.aof {
+            aof.lock().unwrap().feed(self.index, command);
+        }
+    }
+
+    /// Rewrites the append only file in background with the shortest sequence of
+    /// commands that rebuilds the current data. Writes done while it's rewritten are
+    /// kept in both files, so the old file is valid until it's replaced.
     ///
-    /// Reply: SuccessQuery:Integer(n) where n is specified by:
+    /// Reply: SuccessQuery::String with a message telling the rewrite started.
     ///
-    /// n=1 if the timeout was set.
+    /// Error if there's no append only file or if it's already being rewritten.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let aof = Aof::open("appendonly.aof", AppendFsync::Everysec)?;
+    /// let database = Database::new_with_aof("path_to_dump.txt", 16, 16, aof).unwrap();
+    /// database.bgrewriteaof().unwrap();
     ///
-    /// n=0 if key does not exist.
+    /// assert_eq!(database.bgrewriteaof().unwrap_err(), DataBaseError::AofRewriteInProgress);
+    /// ```
+    pub fn bgrewriteaof(&self) -> Result<SuccessQuery, DataBaseError> {
+        let aof = match &self.aof {
+            Some(aof) => aof.clone(),
+            None => return Err(DataBaseError::AofDisabled),
+        };
+        if !aof.lock().unwrap().start_rewrite() {
+            return Err(DataBaseError::AofRewriteInProgress);
+        }
+
+        let database = self.clone();
+        thread::spawn(move || {
+            let lock = database.transaction_lock();
+            let guard = lock.write().unwrap();
+            let rewritten = database.aof_rewrite();
+            aof.lock().unwrap().buffer_rewrite();
+            drop(guard);
+
+            if let Err(e) = aof::rewrite(&aof, &rewritten) {
+                eprintln!("Couldn't rewrite the append only file: {}", e);
+            }
+        });
+
+        let message = "Background append only file rewriting started";
+        Ok(SuccessQuery::String(message.as_bytes().to_vec()))
+    }
+
+    /// Returns the lock that isolates transactions from other clients: single commands
+    /// hold it shared, so they run concurrently, while a transaction holds it exclusively
+    /// until all its commands are executed.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// todo
+    /// let database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// let lock = database.transaction_lock();
+    /// let _guard = lock.write().unwrap();
     /// ```
-    pub fn expire(&mut self, key: &str, seconds: i64) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Boolean(false));
+    pub fn transaction_lock(&self) -> Arc<RwLock<()>> {
+        self.transaction_lock.clone()
+    }
+
+    /// Starts watching key in the selected database and returns its current version.
+    /// The version of a watched key changes every time the key is modified, until
+    /// every watcher unwatches it.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// let version = database.watch(b"key");
+    /// database.set(b"key", b"value").unwrap();
+    /// database.signal_modified_key(b"key");
+    ///
+    /// assert_ne!(database.key_version(0, b"key"), version);
+    /// ```
+    pub fn watch(&self, key: &[u8]) -> u64 {
+        let mut watched_keys = self.watched_keys.lock().unwrap();
+        let entry = watched_keys.entry((self.index, key.to_vec()));
+        let (version, watchers) = entry.or_insert((0, 0));
+        *watchers += 1;
+        *version
+    }
+
+    /// Stops watching key of database db once. The key version is forgotten when
+    /// nobody watches it.
+    pub fn unwatch(&self, db: usize, key: &[u8]) {
+        let mut watched_keys = self.watched_keys.lock().unwrap();
+        let watched_key = (db, key.to_vec());
+        if let Some((_, watchers)) = watched_keys.get_mut(&watched_key) {
+            *watchers -= 1;
+            if *watchers == 0 {
+                watched_keys.remove(&watched_key);
+            }
         }
-        self.dictionary.touch(key);
+    }
 
-        if seconds < 0 {
-            self.dictionary.remove(key);
-        } else {
-            let duration = Duration::new(seconds as u64, 0);
-            let expire_time = SystemTime::now().checked_add(duration).unwrap();
-            let key_ttl = KeyTtl::new(key, expire_time);
-            self.ttl_msg_sender
-                .send(MessageTtl::Expire(key_ttl))
-                .unwrap();
+    /// Returns the current version of a watched key of database db.
+    pub fn key_version(&self, db: usize, key: &[u8]) -> u64 {
+        let watched_keys = self.watched_keys.lock().unwrap();
+        watched_keys
+            .get(&(db, key.to_vec()))
+            .map_or(0, |(version, _)| *version)
+    }
+
+    /// Marks key of the selected database as modified, changing its version if it is
+    /// watched.
+    pub fn signal_modified_key(&self, key: &[u8]) {
+        self.signal_modified_key_of(self.index, key);
+    }
+
+    #[doc(hidden)]
+    fn signal_modified_key_of(&self, db: usize, key: &[u8]) {
+        signal_watched_key(&self.watched_keys, db, key);
+    }
+
+    #[doc(hidden)]
+    fn signal_modified_database(&self, db: usize) {
+        let keyspaces = self.keyspaces.read().unwrap();
+        let dictionary = &keyspaces[db].dictionary;
+        let mut watched_keys = self.watched_keys.lock().unwrap();
+        for ((index, key), (version, _)) in watched_keys.iter_mut() {
+            if *index == db && dictionary.contains_key(key) {
+                *version += 1;
+            }
         }
+    }
 
-        Ok(SuccessQuery::Boolean(true))
+    /// Sets the channels the keyspace events are published in. Until it's called
+    /// nothing is published.
+    pub fn set_notifications(&self, channels: Channels) {
+        self.notifier.set_channels(channels);
     }
 
-    /// EXPIREAT has the same effect and semantic as EXPIRE, but instead of specifying the number of seconds representing the TTL (time to live),
-    /// it takes an absolute Unix timestamp (seconds since January 1, 1970).
+    /// Sets which keyspace events are published, from the notify-keyspace-events
+    /// option.
+    pub fn set_notify_keyspace_events(&self, events: KeyspaceEvents) {
+        self.notifier.set_events(events);
+    }
+
+    /// Publishes that event of class happened to key of the selected database, if
+    /// the events of class are published.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set_notifications(Channels::new());
+    /// database.set_notify_keyspace_events(KeyspaceEvents::parse("KEA").unwrap());
     ///
-    /// A timestamp in the past will delete the key immediately.
-    /// Please for the specific semantics of the command refer to the documentation of EXPIRE.
+    /// database.notify_keyspace_event(EventClass::Generic, "del", b"key");
+    /// ```
+    pub fn notify_keyspace_event(&self, class: EventClass, event: &str, key: &[u8]) {
+        self.notifier.notify(class, event, key, self.index);
+    }
+
+    /// Sets the memory limit of the data, in bytes, or removes it if maxmemory is 0,
+    /// and how keys are evicted once it's reached.
+    pub fn set_maxmemory(&self, maxmemory: usize, policy: EvictionPolicy) {
+        let mut memory_limit = self.memory_limit.lock().unwrap();
+        memory_limit.maxmemory = maxmemory;
+        memory_limit.policy = policy;
+    }
+
+    /// Returns the approximate bytes taken by the keys and values of every database.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// assert_eq!(database.used_memory(), 0);
     ///
-    /// Reply: SuccessQuery:Integer(n) where n is specified by:
+    /// database.set(b"key", b"value").unwrap();
+    /// assert!(database.used_memory() > 0);
+    /// ```
+    pub fn used_memory(&self) -> usize {
+        let keyspaces = self.keyspaces.read().unwrap();
+        keyspaces.iter().map(|k| k.dictionary.used_memory()).sum()
+    }
+
+    /// Returns the used memory, maxmemory, the eviction policy and how many keys were
+    /// evicted, for INFO.
+    pub fn memory_info(&self) -> (usize, usize, EvictionPolicy, u64) {
+        let used_memory = self.used_memory();
+        let memory_limit = self.memory_limit.lock().unwrap();
+        (
+            used_memory,
+            memory_limit.maxmemory,
+            memory_limit.policy,
+            memory_limit.evicted_keys,
+        )
+    }
+
+    /// Returns how many keys each shard has, adding up the same shard of every logical
+    /// database, for INFO to show how the keys are spread.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let database = Database::new("path_to_dump.txt", 16, 4).unwrap();
     ///
-    /// n=1 if the timeout was set.
+    /// assert_eq!(database.shard_info(), vec![0, 0, 0, 0]);
+    /// ```
+    pub fn shard_info(&self) -> Vec<usize> {
+        let keyspaces = self.keyspaces.read().unwrap();
+        let mut shards: Vec<usize> = Vec::new();
+        for keyspace in keyspaces.iter() {
+            for (i, len) in keyspace.dictionary.shard_lens().into_iter().enumerate() {
+                match shards.get_mut(i) {
+                    Some(total) => *total += len,
+                    None => shards.push(len),
+                }
+            }
+        }
+
+        shards
+    }
+
+    /// Computes again the memory taken by key of the selected database, after a
+    /// command changed it.
+    pub fn update_used_memory(&self, key: &[u8]) {
+        self.dictionary().refresh_size(key);
+    }
+
+    /// Evicts keys, following the eviction policy, until the used memory is below
+    /// maxmemory. Keys are chosen among a few sampled from each database, so the least
+    /// recently or frequently used key is approximated, like Redis does.
     ///
-    /// n=0 if key does not exist.
+    /// Error if the used memory is over maxmemory and no key can be evicted, because
+    /// the policy is noeviction or no key has the time to live a volatile policy needs.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// todo
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    /// database.set_maxmemory(1, EvictionPolicy::AllkeysLru);
+    ///
+    /// database.free_memory().unwrap();
+    /// assert_eq!(database.get(b"key").unwrap(), SuccessQuery::Nil);
     /// ```
-    pub fn expireat(&mut self, key: &str, seconds: i64) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Boolean(false));
+    pub fn free_memory(&self) -> Result<(), DataBaseError> {
+        let (maxmemory, policy) = {
+            let memory_limit = self.memory_limit.lock().unwrap();
+            (memory_limit.maxmemory, memory_limit.policy)
+        };
+        if maxmemory == 0 {
+            return Ok(());
         }
-        self.dictionary.touch(key);
 
-        let duration = Duration::new(seconds as u64, 0);
-        let expire_time = SystemTime::UNIX_EPOCH.checked_add(duration).unwrap();
+        while self.used_memory() > maxmemory {
+            if policy == EvictionPolicy::NoEviction {
+                return Err(DataBaseError::OutOfMemory);
+            }
+            let (db, key) = self
+                .eviction_candidate(policy)
+                .ok_or(DataBaseError::OutOfMemory)?;
+
+            let keyspace = self.keyspaces.read().unwrap()[db].clone();
+            keyspace.expires.lock().unwrap().remove(&key);
+            keyspace.dictionary.clone().remove(&key);
+            self.signal_modified_key_of(db, &key);
+            if let Some(aof) = &self.aof {
+                aof.lock()
+                    .unwrap()
+                    .feed(db, &[b"del".to_vec(), key.clone()]);
+            }
+            self.memory_limit.lock().unwrap().evicted_keys += 1;
+            self.notifier
+                .notify(EventClass::Evicted, "evicted", &key, db);
+        }
 
-        if expire_time < SystemTime::now() {
-            self.dictionary.remove(key);
-        } else {
-            let key_ttl = KeyTtl::new(key, expire_time);
-            self.ttl_msg_sender
-                .send(MessageTtl::Expire(key_ttl))
-                .unwrap();
+        Ok(())
+    }
+
+    /// Returns the database and the key to evict next, the best one following policy
+    /// among those sampled from every database.
+    #[doc(hidden)]
+    fn eviction_candidate(&self, policy: EvictionPolicy) -> Option<(usize, Vec<u8>)> {
+        let keyspaces = self.keyspaces.read().unwrap().clone();
+        let mut best: Option<(u64, usize, Vec<u8>)> = None;
+
+        for (db, keyspace) in keyspaces.iter().enumerate() {
+            let candidates: Vec<_> = if policy.is_volatile() {
+                let expires = keyspace.expires.lock().unwrap();
+                let keys = match policy {
+                    EvictionPolicy::VolatileTtl => {
+                        expires.first().map(|(_, key)| key).into_iter().collect()
+                    }
+                    _ => expires.sample(MAXMEMORY_SAMPLES),
+                };
+                keys.into_iter()
+                    .filter_map(|key| {
+                        let stats = keyspace.dictionary.stats(&key)?;
+                        let expire_time = expires.get(&key);
+                        Some((key, stats, expire_time))
+                    })
+                    .collect()
+            } else {
+                keyspace
+                    .dictionary
+                    .sample(MAXMEMORY_SAMPLES)
+                    .into_iter()
+                    .map(|(key, stats)| (key, stats, None))
+                    .collect()
+            };
+
+            for (key, stats, expire_time) in candidates {
+                // The higher the score the better the key is to evict.
+                let score = match policy {
+                    EvictionPolicy::AllkeysLru | EvictionPolicy::VolatileLru => {
+                        stats.idle_time().as_millis() as u64
+                    }
+                    EvictionPolicy::AllkeysLfu | EvictionPolicy::VolatileLfu => {
+                        (u8::MAX - stats.frequency()) as u64
+                    }
+                    EvictionPolicy::VolatileTtl => u64::MAX - expire_time.map_or(0, unix_ms) as u64,
+                    _ => random_number(),
+                };
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_score, _, _)| score > *best_score)
+                {
+                    best = Some((score, db, key));
+                }
+            }
         }
 
-        Ok(SuccessQuery::Boolean(true))
+        best.map(|(_, db, key)| (db, key))
     }
 
-    /// Returns all keys matching pattern.
-    ///
-    /// This command is intended for debugging and special operations, such as changing your keyspace layout.
-    ///
-    /// Supported glob-style patterns:
-    ///
-    /// h?llo matches hello, hallo and hxllo
-    ///
-    /// h*llo matches hllo and heeeello
-    ///
-    /// h{ae}llo matches hello and hallo, but not hillo
-    ///
-    /// h{^e}llo matches hallo, hbllo, ... but not hello
-    ///
-    /// h{a-b}llo matches hallo and hbllo
-    ///
-    /// list of keys matching pattern.
+    /// Returns the number of the selected database.
+    pub fn selected_db(&self) -> usize {
+        self.index
+    }
+
+    /// Change the selected database for this Database, every command that follows works
+    /// with the keys of the new one. Databases are numbered from 0.
     ///
-    /// Reply: SuccessQuery:List(list) where list is an array of keys matching pattern.
+    /// Reply: SuccessQuery::Success.
     ///
+    /// Error if index is out of range.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// let mut database = Database("path_to_dump.txt");
-    /// database.set("firstname", "alex").unwrap();
-    /// database.set("lastname", "arbieto").unwrap();
-    /// database.set("age", "22").unwrap();
-    ///
-    /// if let Ok(SuccessQuery::List(list)) = database.keys("????name") {
-    ///     let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    /// database.select(1).unwrap();
     ///
-    ///     assert!(list.contains("lastname"));
-    /// }
+    /// assert_eq!(database.exists(b"key").unwrap(), SuccessQuery::Boolean(false));
     /// ```
-    /// other example with * pattern:
+    pub fn select(&mut self, index: usize) -> Result<SuccessQuery, DataBaseError> {
+        if index >= self.keyspaces.read().unwrap().len() {
+            return Err(DataBaseError::DbIndexOutOfRange);
+        }
+
+        self.index = index;
+        Ok(SuccessQuery::Success)
+    }
+
+    /// Swaps two databases, so that every client connected to one of them sees the data
+    /// of the other one immediately.
     ///
-    /// ```
-    /// let mut database = Database("path_to_dump.txt");;
-    /// database.set("firstname", "alex").unwrap();
-    /// database.set("lastname", "arbieto").unwrap();
-    /// database.set("age", "22").unwrap();
+    /// Reply: SuccessQuery::Success.
     ///
-    /// if let Ok(SuccessQuery::List(list)) = database.keys("*name") {
-    ///     let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+    /// Error if any index is out of range.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    /// database.swapdb(0, 1).unwrap();
     ///
-    ///     assert!(list.contains("firstname"));
-    ///     assert!(list.contains("lastname"));
-    /// }
+    /// assert_eq!(database.exists(b"key").unwrap(), SuccessQuery::Boolean(false));
     /// ```
-    pub fn keys(&mut self, pattern: &str) -> Result<SuccessQuery, DataBaseError> {
-        let keys = self.dictionary.keys();
-        let list: Vec<SuccessQuery> = keys
-            .iter()
-            .filter(|x| matcher(x, pattern))
-            .map(|item| SuccessQuery::String(item.to_string()))
-            .collect::<Vec<SuccessQuery>>();
+    pub fn swapdb(&mut self, index1: usize, index2: usize) -> Result<SuccessQuery, DataBaseError> {
+        let len = self.keyspaces.read().unwrap().len();
+        if index1 >= len || index2 >= len {
+            return Err(DataBaseError::DbIndexOutOfRange);
+        }
+
+        self.signal_modified_database(index1);
+        self.signal_modified_database(index2);
+        let mut keyspaces = self.keyspaces.write().unwrap();
+        keyspaces.swap(index1, index2);
+        keyspaces[index1]
+            .index
+            .store(index1, AtomicOrdering::SeqCst);
+        keyspaces[index2]
+            .index
+            .store(index2, AtomicOrdering::SeqCst);
+        drop(keyspaces);
+        self.signal_modified_database(index1);
+        self.signal_modified_database(index2);
+        self.mark_modified();
 
-        Ok(SuccessQuery::List(list))
+        Ok(SuccessQuery::Success)
     }
 
-    /// Remove the existing timeout on key, turning the key from volatile (a key with an expire set)
-    /// to persistent (a key that will never expire as no timeout is associated).
-    ///
-    /// Reply: SuccessQuery:Integer(n) where n is specified by:
+    /// Move key from the selected database to the database db, keeping its time to
+    /// live. It does nothing if the key doesn't exist or if it already exists in db.
     ///
-    /// n=1 if the timeout was removed.
+    /// Reply: SuccessQuery::Boolean(true) if key was moved, false if it wasn't.
     ///
-    /// n=0 if key does not exist or does not have an associated timeout.
+    /// Error if db is out of range or is the selected database.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// todo
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    ///
+    /// assert_eq!(database.move_key(b"key", 1).unwrap(), SuccessQuery::Boolean(true));
+    /// assert_eq!(database.exists(b"key").unwrap(), SuccessQuery::Boolean(false));
     /// ```
-    pub fn persist(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Err(DataBaseError::NonExistentKey);
+    pub fn move_key(&mut self, key: &[u8], db: usize) -> Result<SuccessQuery, DataBaseError> {
+        let mut target = self.clone();
+        target.select(db)?;
+        if db == self.index {
+            return Err(DataBaseError::SameDb);
         }
-        self.dictionary.touch(key);
 
-        self.ttl_msg_sender
-            .send(MessageTtl::Clear(key.to_owned()))
-            .unwrap();
+        if !self._exists(key) || target._exists(key) {
+            return Ok(SuccessQuery::Boolean(false));
+        }
+
+        let expire_time = self.get_expire_time(key);
+        let value = match self.remove_key(key) {
+            Some(value) => value,
+            None => return Ok(SuccessQuery::Boolean(false)),
+        };
+
+        target.dictionary().insert(key.to_vec(), value);
+        if let Some(expire_time) = expire_time {
+            let key_ttl = KeyTtl::new(key, expire_time);
+            target.expires().lock().unwrap().insert(key_ttl);
+        }
+        target.signal_modified_key(key);
+        self.mark_modified();
 
         Ok(SuccessQuery::Boolean(true))
     }
 
-    /// Renames key to newkey.
-    ///
-    /// It returns an error when key does not exist.
-    ///
-    /// If newkey already exists it is overwritten, when this happens RENAME executes an implicit DEL operation,
-    /// so if the deleted key contains a very big value it may cause high latency even if RENAME itself is usually a constant-time operation.
-    ///
-    /// Reply: SuccessQuery::Success if success result or DatabaseError::NonExistentKey if key does not exists.
+    /// Delete all the keys of every database. This command never fails.
     ///
+    /// Reply: SuccessQuery::Success
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// let mut database = Database("path_to_dump.txt");
-    /// database.set("KEY", "VALUE").unwrap();
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    /// database.select(1).unwrap();
+    /// database.set(b"key", b"value").unwrap();
     ///
-    /// let result = database.rename("KEY", "SECOND_KEY").unwrap();
-    /// assert_eq!(result, SuccessQuery::Success);
+    /// database.flushall().unwrap();
     ///
-    /// let result = database.get("KEY").unwrap();
-    /// assert_eq!(result, SuccessQuery::Nil);
+    /// assert_eq!(database.dbsize().unwrap(), SuccessQuery::Integer(0));
     /// ```
-    pub fn rename(&mut self, old_key: &str, new_key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(old_key) {
-            return Err(DataBaseError::NonExistentKey);
+    pub fn flushall(&mut self) -> Result<SuccessQuery, DataBaseError> {
+        let keyspaces = self.keyspaces.read().unwrap().clone();
+        for (index, keyspace) in keyspaces.iter().enumerate() {
+            self.signal_modified_database(index);
+            keyspace.expires.lock().unwrap().clear();
+            keyspace.dictionary.clone().clear();
         }
+        self.mark_modified();
 
-        match self.dictionary.remove(old_key) {
-            Some(value) => {
-                self.dictionary.insert(new_key.to_owned(), value);
-
-                self.ttl_msg_sender
-                    .send(MessageTtl::Transfer(old_key.to_owned(), new_key.to_owned()))
-                    .unwrap();
+        Ok(SuccessQuery::Success)
+    }
 
-                Ok(SuccessQuery::Success)
+    /// Returns, for every database with keys, its number, how many keys it has and how
+    /// many of them have a time to live.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    ///
+    /// assert_eq!(database.keyspace_info(), vec![(0, 1, 0)]);
+    /// ```
+    pub fn keyspace_info(&self) -> Vec<(usize, usize, usize)> {
+        let keyspaces = self.keyspaces.read().unwrap().clone();
+        let mut info = Vec::new();
+
+        for (index, keyspace) in keyspaces.iter().enumerate() {
+            let keys = keyspace.dictionary.len();
+            if keys == 0 {
+                continue;
             }
-            None => Err(DataBaseError::NonExistentKey),
+
+            info.push((index, keys, keyspace.expires.lock().unwrap().len()));
         }
-    }
 
-    #[doc(hidden)]
-    pub fn sort_by(
-        &mut self,
-        to_order: &mut Vec<String>,
-        pattern: &str,
-    ) -> Result<Vec<String>, DataBaseError> {
-        let mut list_elem_weight: Vec<(&str, i32)> = Vec::new();
+        info
+    }
 
-        let keys = self.dictionary.keys();
-        let list_key_match = keys
-            .iter()
-            .filter(|x| matcher(x, pattern) && self._exists(x))
-            .collect::<Vec<&String>>();
+    // KEYS
 
-        for (i, elem) in to_order.iter().enumerate() {
-            let without_weight = 0;
-            for pal in list_key_match.iter() {
-                if pal.contains(&elem.to_owned()) {
-                    let dictionary = self.dictionary.get_atomic_hash(&(*pal).clone());
-                    let dictionary = dictionary.lock().unwrap();
-                    if let Some((StorageValue::String(val), _)) = dictionary.get(&(*pal).clone()) {
-                        let result_weight = match val.parse::<i32>() {
-                            Ok(weight_ok) => Ok(weight_ok),
-                            Err(_) => Err(DataBaseError::SortByParseError),
-                        };
-                        if let Ok(weight) = result_weight {
-                            list_elem_weight.push((elem, weight));
-                            break;
-                        } else {
-                            return Err(DataBaseError::SortByParseError);
-                        }
-                    }
-                }
-            }
-            if list_elem_weight.len() < i + 1 {
-                list_elem_weight.push((elem, without_weight));
-            }
-        }
-
-        if list_elem_weight.is_empty() {
-            return Ok(to_order.to_vec());
-        }
-
-        list_elem_weight.sort_by(|a, b| a.1.cmp(&b.1));
-
-        let to_build: Vec<String> = list_elem_weight.iter().map(|x| x.0.to_string()).collect();
-        Ok(to_build)
-    }
-
-    #[doc(hidden)]
-    pub fn limit(
-        to_order: &mut Vec<String>,
-        pos_begin: &mut i32,
-        num_elems: &mut i32,
-    ) -> Vec<String> {
-        let empty_list = Vec::new();
-        if *pos_begin >= to_order.len() as i32 {
-            return empty_list;
-        }
-        if *num_elems == 0 {
-            return empty_list;
-        }
-        if *pos_begin < 0 {
-            *pos_begin = 0;
-        }
-        if *num_elems < 0 || *num_elems > to_order.len() as i32 {
-            *num_elems = to_order.len() as i32;
-        }
-        to_order.to_vec()
+    /// Delete all the keys of the currently selected DB. This command never fails.
+    ///
+    /// Reply: SuccessQuery:Success
+    ///
+    /// # Examples
+    /// ```
+    /// let mut db = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    ///
+    /// db.mset(vec![b"KEY1", b"VALUE1", b"KEY2", b"VALUE2"]).unwrap();
+    /// let r = db.get(b"KEY1").unwrap();
+    /// assert_eq!(r, SuccessQuery::String(b"VALUE1".to_vec()));
+    /// let r = db.get(b"KEY2").unwrap();
+    /// assert_eq!(r, SuccessQuery::String(b"VALUE2".to_vec()));
+    ///
+    /// let r = db.flushdb().unwrap();
+    /// assert_eq!(r, SuccessQuery::Success);
+    ///
+    /// let guard = db.dictionary();
+    /// assert!(guard.len() == 0);
+    /// ```
+    pub fn flushdb(&mut self) -> Result<SuccessQuery, DataBaseError> {
+        self.signal_modified_database(self.index);
+        self.expires().lock().unwrap().clear();
+        self.dictionary().clear();
+        self.mark_modified();
+        Ok(SuccessQuery::Success)
     }
 
-    #[doc(hidden)]
-    pub fn sort_limit(
-        to_order: &mut Vec<String>,
-        pos_begin: &mut i32,
-        num_elems: &mut i32,
-    ) -> Result<Vec<String>, DataBaseError> {
-        let mut to_build: Vec<String> = Database::limit(to_order, pos_begin, num_elems);
-        Database::sort_without_flags(&mut to_build)
+    /// Return the number of keys in the currently-selected database. This command never fails.
+    ///
+    /// Reply: SuccessQuery:Integer(n) where n is the number of keys in currently database.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut db = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// let _ = db.set(KEY1, VALUE1);
+    /// let r = db.get(KEY1).unwrap();
+    /// assert_eq!(r, SuccessQuery::String(VALUE1.to_owned()));
+    ///
+    /// let r = db.dbsize().unwrap();
+    /// assert_eq!(r, SuccessQuery::Integer(1));
+    /// ```
+    pub fn dbsize(&self) -> Result<SuccessQuery, DataBaseError> {
+        Ok(SuccessQuery::Integer(self.dictionary().len() as i64))
     }
 
-    #[doc(hidden)]
-    pub fn desc(to_order: &mut Vec<String>) -> Vec<String> {
-        to_order.reverse();
-        to_order.to_vec()
-    }
+    /// This command copies the value stored at the source key to the destination key.
+    ///
+    /// The command returns an error when the destination key already exists.
+    ///
+    /// Reply: SuccessQuery:Success if copies sucessful. Database error in wrong case.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    ///
+    /// database.set(b"KEY", b"VALUE").unwrap();
+    /// database.set(b"SECOND_KEY", b"SECOND_VALUE").unwrap();
+    /// let result = database.copy(b"KEY", b"SECOND_KEY");
+    ///
+    /// assert_eq!(result.unwrap_err(), DataBaseError::KeyAlredyExist);
+    /// ```
+    pub fn copy(&mut self, key: &[u8], to_key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if self._exists(to_key) {
+            return Err(DataBaseError::KeyAlredyExist);
+        }
 
-    #[doc(hidden)]
-    pub fn sort_desc(to_order: &mut Vec<String>) -> Result<Vec<String>, DataBaseError> {
-        let mut parse_error = false;
-        let mut to_order: Vec<_> = to_order
-            .iter()
-            .map(|x| match x.parse::<i32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    parse_error = true;
-                    -1
-                }
-            })
-            .collect();
-        if parse_error {
-            return Err(DataBaseError::SortParseError);
+        if !self._exists(key) {
+            return Err(DataBaseError::NonExistentKey);
         }
-        to_order.sort_by(|a, b| b.cmp(a));
-        let to_build: Vec<String> = to_order.iter().map(|x| x.to_string()).collect();
-        Ok(to_build)
-    }
 
-    #[doc(hidden)]
-    pub fn sort_without_flags(to_order: &mut Vec<String>) -> Result<Vec<String>, DataBaseError> {
-        let mut parse_error = false;
-        let mut to_order: Vec<_> = to_order
-            .iter()
-            .map(|x| match x.parse::<i32>() {
-                Ok(val) => val,
-                Err(_) => {
-                    parse_error = true;
-                    -1
-                }
-            })
-            .collect();
-        if parse_error {
-            return Err(DataBaseError::SortParseError);
+        self.dictionary().touch(key);
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let value = dictionary
+            .read()
+            .unwrap()
+            .get(key)
+            .map(|(val, _)| val.clone());
+        match value {
+            Some(val) => {
+                self.dictionary().insert(to_key.to_owned(), val);
+                self.mark_modified();
+                Ok(SuccessQuery::Success)
+            }
+            None => Err(DataBaseError::NonExistentKey),
         }
-        to_order.sort_unstable();
-        let to_build: Vec<String> = to_order.iter().map(|x| x.to_string()).collect();
-        Ok(to_build)
     }
 
-    #[doc(hidden)]
-    pub fn build_sort_vector(
-        to_build: Vec<String>,
-        pos_begin: &i32,
-        num_elems: &i32,
-    ) -> Result<Vec<SuccessQuery>, DataBaseError> {
-        let mut result_list: Vec<SuccessQuery> = Vec::new();
-        for i in *pos_begin..(pos_begin + num_elems) {
-            result_list.push(SuccessQuery::String((&to_build[i as usize]).to_string()));
+    /// Removes the specified keys. A key is ignored if it does not exist.
+    ///
+    /// Reply: SuccessQuery:Integer(n) where n=1 if were removed or n=0 if were not removed.
+    ///
+    /// # Examples
+    /// ```
+    /// todo
+    /// ```
+    pub fn del(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if self.remove_key(key).is_some() {
+            self.mark_modified();
         }
-        Ok(result_list)
+        Ok(SuccessQuery::Success)
     }
 
+    /// Returns if key exists, removing it first if its time to live is over, so
+    /// expired keys are never seen even before the active expiry removes them.
     #[doc(hidden)]
-    pub fn _sort(
-        &mut self,
-        to_order: &mut Vec<String>,
-        sort_flags: SortFlags,
-    ) -> Result<Vec<SuccessQuery>, DataBaseError> {
-        let mut num_elems = to_order.len() as i32;
-        let mut pos_begin = 0;
-        let mut to_order: Vec<String> = to_order.iter().map(|x| x.to_string()).collect();
-
-        match sort_flags {
-            SortFlags::WithoutFlags => match Database::sort_without_flags(&mut to_order) {
-                Ok(to_build) => Database::build_sort_vector(to_build, &pos_begin, &num_elems),
-                Err(err) => Err(err),
-            },
-            SortFlags::Alpha => {
-                to_order.sort();
-                Database::build_sort_vector(to_order, &pos_begin, &num_elems)
-            }
-            SortFlags::Desc => match Database::sort_desc(&mut to_order) {
-                Ok(to_build) => Database::build_sort_vector(to_build, &pos_begin, &num_elems),
-                Err(err) => Err(err),
-            },
-            SortFlags::Limit(mut pos_ini, mut num_elem) => {
-                match Database::sort_limit(&mut to_order, &mut pos_ini, &mut num_elem) {
-                    Ok(to_build) => Database::build_sort_vector(to_build, &pos_ini, &num_elem),
-                    Err(err) => Err(err),
-                }
-            }
-            SortFlags::By(pattern) => match self.sort_by(&mut to_order, pattern) {
-                Ok(to_build) => Database::build_sort_vector(to_build, &pos_begin, &num_elems),
-                Err(err) => Err(err),
-            },
-            SortFlags::CompositeFlags(sort_flags) => {
-                if sort_flags
-                    .iter()
-                    .any(|s| matches!(s, SortFlags::Limit(_, _)))
-                    && sort_flags.iter().any(|s| matches!(s, SortFlags::Desc))
-                    && sort_flags.len() == 2
-                {
-                    return match Database::sort_desc(&mut to_order) {
-                        Ok(mut to_build) => {
-                            let to_build: Vec<String> =
-                                Database::limit(&mut to_build, &mut pos_begin, &mut num_elems);
-                            Database::build_sort_vector(to_build, &pos_begin, &num_elems)
-                        }
-                        Err(err) => Err(err),
-                    };
-                }
-
-                if sort_flags.iter().any(|s| matches!(s, SortFlags::By(_))) {
-                    let pattern: Option<String> = sort_flags.iter().find_map(|d| match d {
-                        SortFlags::By(pattern) => Some(pattern.to_string()),
-                        _ => None,
-                    });
-
-                    if let Ok(to_build) = self.sort_by(&mut to_order, &pattern.clone().unwrap()) {
-                        to_order = to_build;
-                    } else if let Err(err) = self.sort_by(&mut to_order, &pattern.unwrap()) {
-                        return Err(err);
-                    }
-                }
-
-                if sort_flags.iter().any(|s| matches!(s, SortFlags::Alpha))
-                    && !sort_flags.iter().any(|s| matches!(s, SortFlags::By(_)))
-                {
-                    to_order.sort();
-                }
-
-                for flag in sort_flags {
-                    if let SortFlags::Desc = flag {
-                        to_order = Database::desc(&mut to_order);
-                    }
-                    if let SortFlags::Limit(l_pos_begin, l_num_elems) = flag {
-                        pos_begin = l_pos_begin;
-                        num_elems = l_num_elems;
-                        to_order = Database::limit(&mut to_order, &mut pos_begin, &mut num_elems);
-                    }
-                }
-                Database::build_sort_vector(to_order, &pos_begin, &num_elems)
-            }
-        }
+    fn _exists(&self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+        self.dictionary().contains_key(key)
     }
 
-    /// # Sort in simplest Form
-    ///
-    /// Returns or stores the elements contained in the list or set at key. By default,
-    /// sorting is numeric and elements are compared by their value interpreted as double precision floating point number.
+    /// Returns if key exists.
     ///
-    /// `database.sort("key", SortingFlags::WithoutFlags)`
+    /// Reply: SuccessQuery:Integer(n) where n is specified by:
     ///
-    /// # Sort with Params
+    /// n=1 if the key exists.
     ///
-    /// Assuming key is a list of numbers, this command will return the same list with the elements sorted from small to large.
+    /// n=0 if the key does not exist.
     ///
-    /// In order to sort the numbers from large to small to large. use the DESC modifier:
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"KEY", b"VALUE").unwrap();
     ///
-    /// `database.sort("key", SortFlags::Desc)`
+    /// let result = database.exists(b"KEY").unwrap();
     ///
-    /// When mylist contains string values and you want to sort them lexicographically, use the ALPHA modifier:
+    /// assert_eq!(result, SuccessQuery::Boolean(true));
+    /// ```
+    pub fn exists(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.dictionary().touch(key);
+        Ok(SuccessQuery::Boolean(self._exists(key)))
+    }
+
+    /// Set a timeout on key. After the timeout has expired, the key will automatically be deleted.
+    /// A key with an associated timeout is often said to be volatile in Redis terminology.
     ///
-    /// `database.sort("key", SortFlags::Alpha)`
+    /// The timeout will only be cleared by commands that delete or overwrite the contents of the key, including DEL, SET, GETSET and
+    /// all the *STORE commands.
     ///
-    /// The number of returned elements can be limited using the LIMIT modifier.
+    /// This means that all the operations that conceptually alter the value stored at the key without replacing it with a new one will leave the timeout untouched.
+    /// For instance, incrementing the value of a key with INCR, pushing a new value into a list with LPUSH,
+    /// or altering the field value of a hash with HSET are all operations that will leave the timeout untouched.
     ///
-    /// This modifier takes the offset argument, specifying the number of elements to skip and the count argument,
-    /// specifying the number of elements to return from starting at offset.
     ///
-    /// The following example will return 10 elements of the sorted version of mylist, starting at element 0 (offset is zero-based):
+    /// The timeout can also be cleared, turning the key back into a persistent key, using the PERSIST command.
     ///
-    /// `database.sort("key", SortFlags::Limit(0, 10))`
+    /// If a key is renamed with RENAME, the associated time to live is transferred to the new key name.
     ///
-    /// Almost all modifiers can be used together.
-    /// The following example will return the first 5 elements, lexicographically sorted in descending order:
+    /// If a key is overwritten by RENAME, like in the case of an existing key Key_A that is overwritten by a call like RENAME Key_B Key_A,
+    /// it does not matter if the original Key_A had a timeout associated or not,
+    /// the new key Key_A will inherit all the characteristics of Key_B.
     ///
-    /// `database.sort("key", SortFlags::CompositeFlags(![SortFlags::Limit(0,5), SortingFlags::Alpha, SortingFlags::Desc]))`
+    /// Reply: SuccessQuery:Integer(n) where n is specified by:
     ///
-    /// # Sorting By External Keys
+    /// n=1 if the timeout was set.
     ///
-    /// Sometimes you want to sort elements using external keys as weights to compare instead of comparing the actual elements in the list,
-    /// set or sorted set.
-    ///
-    /// Let's say the list mylist contains the elements 1, 2 and 3 representing unique IDs of objects stored in object_1, object_2 and object_3.
-    /// When these objects have associated weights stored in weight_1, weight_2 and weight_3,
-    /// SORT can be instructed to use these weights to sort mylist with the following statement:
-    ///
-    /// `database.sort("key", SortingFlags::By("weight_*"))`
-    ///
-    /// The BY option takes a pattern (equal to weight_* in this example) that is used to generate the keys that are used for sorting.
-    ///
-    /// These key names are obtained substituting the first occurrence of * with the actual value of the element in the list (1, 2 and 3 in this example).
-    ///
-    /// Reply: without passing the store option the command returns a SuccessQuery::List(list) where list is an array of sorted elements.
+    /// n=0 if key does not exist.
     ///
+    /// Error if seconds is too big to be a time.
     /// # Examples
     /// ```
-    /// let mut database = Database("path_to_dump.txt");
-    /// database
-    ///     .lpush("LIST", ["3", "1", "2"].to_vec())
-    ///     .unwrap();
-    ///
-    /// if let SuccessQuery::List(list) = database.sort("LIST", SortFlags::WithoutFlags).unwrap()
-    /// {
-    ///     let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-    ///     let to_compare_list: Vec<&str> = vec!["1", "2", "3"];
-    ///     let pair_list: Vec<(&String, &str)> =
-    ///         list_result.iter().zip(to_compare_list).collect();
-    ///     pair_list.iter().for_each(|x| {
-    ///         assert_eq!(x.0, x.1);
-    ///     });
-    /// }
+    /// todo
     /// ```
-    pub fn sort(
+    pub fn expire(
         &mut self,
-        key: &str,
-        sort_flags: SortFlags,
+        key: &[u8],
+        seconds: i64,
+        flags: ExpireFlags,
     ) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::List(Vec::new()));
-        }
-        self.dictionary.touch(key);
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let dictionary = dictionary.lock().unwrap();
-        let mut to_order = match dictionary.get(key) {
-            Some((StorageValue::Set(hash_set), _)) => {
-                hash_set.iter().map(|s| s.to_string()).collect()
-            }
-            Some((StorageValue::List(list), _)) => list.iter().map(|x| x.to_string()).collect(),
-            Some(_) => return Err(DataBaseError::NotAList),
-            None => return Ok(SuccessQuery::List(Vec::new())),
-        };
-        drop(dictionary);
-
-        match self._sort(&mut to_order, sort_flags) {
-            Ok(result_list) => Ok(SuccessQuery::List(result_list)),
-            Err(e) => Err(e),
-        }
+        let ms = seconds
+            .checked_mul(1000)
+            .ok_or(DataBaseError::InvalidExpireTime)?;
+        self.pexpire(key, ms, flags)
     }
 
-    /// Update the key's last access value
-    ///
-    /// Indicates in the log file the last previous access or the time since that previous access.
-    ///
-    /// Check if the key expiration condition (TTL) was met and if so, perform the deletion
-    ///
-    /// Reply: SuccessQuery::Integer(n) where n is specified by .
-    ///
-    /// n=1 if the key was be touched.
+    /// Works exactly like EXPIRE but the time to live of the key is specified in
+    /// milliseconds instead of seconds.
     ///
-    /// n=0 if key does not be touch.
+    /// Reply: SuccessQuery::Boolean, true if the timeout was set and false if key
+    /// does not exist.
     ///
+    /// Error if ms is too big to be a time.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// let mut database = Database("path_to_dump.txt");
-    ///
-    ///
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
     ///
+    /// assert_eq!(database.pexpire(b"key", 1500, ExpireFlags::default()).unwrap(), SuccessQuery::Boolean(true));
     /// ```
-    pub fn touch(&mut self, key: &str) -> Option<u64> {
-        self.ttl_msg_sender
-            .send(MessageTtl::Check(key.to_string()))
-            .unwrap();
-        match self.dictionary.touch(key) {
-            Some(t) => {
-                self.ttl_msg_sender
-                    .send(MessageTtl::Check(key.to_string()))
-                    .unwrap();
-                Some(t)
-            }
-            None => None,
-        }
+    pub fn pexpire(
+        &mut self,
+        key: &[u8],
+        ms: i64,
+        flags: ExpireFlags,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let expire_time = time_from_now(ms).ok_or(DataBaseError::InvalidExpireTime)?;
+        self.expire_key_at(key, expire_time, flags)
     }
 
     #[doc(hidden)]
-    fn get_expire_time(&self, key: &str) -> RespondTtl {
-        let (respond_sender, respond_reciver) = channel();
+    fn expire_key_at(
+        &mut self,
+        key: &[u8],
+        expire_time: SystemTime,
+        flags: ExpireFlags,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Boolean(false));
+        }
 
-        self.ttl_msg_sender
-            .send(MessageTtl::Ttl(key.to_owned(), respond_sender))
-            .unwrap();
+        // A key without time to live never expires, so it is greater than any time.
+        let allowed = match self.get_expire_time(key) {
+            Some(current) => {
+                !flags.nx
+                    && (!flags.gt || expire_time > current)
+                    && (!flags.lt || expire_time < current)
+            }
+            None => !flags.xx && !flags.gt,
+        };
+        if !allowed {
+            return Ok(SuccessQuery::Boolean(false));
+        }
+        self.dictionary().touch(key);
+
+        if expire_time <= SystemTime::now() {
+            self.remove_key(key);
+        } else {
+            let key_ttl = KeyTtl::new(key, expire_time);
+            self.expires().lock().unwrap().insert(key_ttl);
+        }
+        self.mark_modified();
 
-        respond_reciver.recv().unwrap()
+        Ok(SuccessQuery::Boolean(true))
     }
 
-    /// Returns the remaining time to live of a key that has a timeout.
-    /// This introspection capability allows a Redis client to check how many seconds a given key will continue to be part of the dataset.
-    ///
-    /// Reply: TTL in seconds, or a negative value in order to signal an error (see the description above).
-    /// SuccessQuery::Integer(n) where n is the time to live of the key.
+    /// EXPIREAT has the same effect and semantic as EXPIRE, but instead of specifying the number of seconds representing the TTL (time to live),
+    /// it takes an absolute Unix timestamp (seconds since January 1, 1970).
     ///
-    /// SuccessQuery::Integer(n) when n is negative if an error ocurred according to:
+    /// A timestamp in the past will delete the key immediately.
+    /// Please for the specific semantics of the command refer to the documentation of EXPIRE.
     ///
-    /// -2 if the key does not exist.
+    /// Reply: SuccessQuery:Integer(n) where n is specified by:
     ///
-    /// -1 if the key exists but has no associated expire.
+    /// n=1 if the timeout was set.
     ///
+    /// n=0 if key does not exist.
     /// # Examples
     /// ```
     /// todo
     /// ```
-    pub fn ttl(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Integer(-2));
-        }
-
-        match self.get_expire_time(key) {
-            RespondTtl::Ttl(time) => {
-                let duration = time.duration_since(SystemTime::now()).unwrap();
-                Ok(SuccessQuery::Integer(duration.as_secs() as i32))
-            }
-            RespondTtl::Persistent => Ok(SuccessQuery::Integer(-1)),
-            _ => Ok(SuccessQuery::Integer(-1)),
-        }
+    pub fn expireat(
+        &mut self,
+        key: &[u8],
+        seconds: i64,
+        flags: ExpireFlags,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let ms = seconds
+            .checked_mul(1000)
+            .ok_or(DataBaseError::InvalidExpireTime)?;
+        self.pexpireat(key, ms, flags)
     }
 
-    /// Returns the string representation of the type of the value stored at key.
-    /// The different types that can be returned are: string, list, set.
+    /// Has the same effect and semantic as EXPIREAT, but the Unix time at which the
+    /// key will expire is specified in milliseconds instead of seconds.
     ///
-    /// Reply: SuccessQuery::String(s) where s is type of key or none when key does not exist.
+    /// Reply: SuccessQuery::Boolean, true if the timeout was set and false if key
+    /// does not exist.
     ///
+    /// Error if ms is too big to be a time.
     /// # Examples
+    /// Basic Usage:
     /// ```
-    /// todo
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    ///
+    /// database.pexpireat(b"key", 1000, ExpireFlags::default()).unwrap();
+    /// assert_eq!(database.get(b"key").unwrap(), SuccessQuery::Nil);
     /// ```
-    pub fn get_type(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::String("none".to_owned()));
-        }
-
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((val, last_access)) => {
-                *last_access = SystemTime::now();
-                Ok(SuccessQuery::String(val.get_type()))
-            }
-            None => Ok(SuccessQuery::String("none".to_owned())),
-        }
+    pub fn pexpireat(
+        &mut self,
+        key: &[u8],
+        ms: i64,
+        flags: ExpireFlags,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let expire_time = time_from_unix_ms(ms).ok_or(DataBaseError::InvalidExpireTime)?;
+        self.expire_key_at(key, expire_time, flags)
     }
 
-    //STRINGS
-
-    /// This command appends the value at the end of the string, if key already exists and is a string.
-    /// If key does not exist it is created and set as an empty string, so APPEND will be similar to SET in this special case.
+    /// Returns all keys matching pattern.
     ///
-    /// Returns: SuccessQuery of the length of the string after the append operation.
+    /// This command is intended for debugging and special operations, such as changing your keyspace layout.
     ///
-    /// # Examples
+    /// Supported glob-style patterns:
+    ///
+    /// h?llo matches hello, hallo and hxllo
+    ///
+    /// h*llo matches hllo and heeeello
+    ///
+    /// h{ae}llo matches hello and hallo, but not hillo
+    ///
+    /// h{^e}llo matches hallo, hbllo, ... but not hello
     ///
+    /// h{a-b}llo matches hallo and hbllo
+    ///
+    /// list of keys matching pattern.
+    ///
+    /// Reply: SuccessQuery:List(list) where list is an array of keys matching pattern.
+    ///
+    /// # Examples
     /// ```
-    /// let database = Database::new("dump_path.txt");
-    /// if let SuccessQuery::Integer(lenght) = database.append("key", "value").unwrap() {
-    ///     assert_eq!(lenght, 5);
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"firstname", b"alex").unwrap();
+    /// database.set(b"lastname", b"arbieto").unwrap();
+    /// database.set(b"age", b"22").unwrap();
+    ///
+    /// if let Ok(SuccessQuery::List(list)) = database.keys(b"????name") {
+    ///     let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+    ///
+    ///     assert!(list.contains(b"lastname"));
     /// }
     /// ```
-    pub fn append(&mut self, key: &str, value: &str) -> Result<SuccessQuery, DataBaseError> {
-        if self._exists(key) {
-            let dictionary = self.dictionary.get_atomic_hash(key);
-            let mut dictionary = dictionary.lock().unwrap();
-            if let Some((StorageValue::String(val), last_access)) = dictionary.get_mut(key) {
-                val.push_str(value);
-                let len_result = val.len() as i32;
-                *last_access = SystemTime::now();
-                Ok(SuccessQuery::Integer(len_result))
-            } else {
-                Err(DataBaseError::NotAString)
-            }
-        } else {
-            let len_result = value.len() as i32;
-            self.dictionary
-                .insert(key.to_owned(), StorageValue::String(value.to_string()));
-            Ok(SuccessQuery::Integer(len_result))
-        }
-    }
-
-    /// Decrements the number stored at key by decrement.
-    /// If the key does not exist, it is set to 0 before performing the operation.
-    /// An error is returned if the key contains a value of the wrong type or contains a string that can not be represented as integer.
-    /// This operation is limited to 64 bit signed integers.
+    /// other example with * pattern:
     ///
-    /// # Examples
     /// ```
-    /// let database = Database::new("dump_path.txt");
-    /// let database = create_database();
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();;
+    /// database.set(b"firstname", b"alex").unwrap();
+    /// database.set(b"lastname", b"arbieto").unwrap();
+    /// database.set(b"age", b"22").unwrap();
     ///
-    /// database.set(KEY, "5").unwrap();
-    /// let result = database.decrby(KEY, 4).unwrap();
+    /// if let Ok(SuccessQuery::List(list)) = database.keys(b"*name") {
+    ///     let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
     ///
-    /// assert_eq!(result, SuccessQuery::Integer(1));
+    ///     assert!(list.contains(b"firstname"));
+    ///     assert!(list.contains(b"lastname"));
+    /// }
     /// ```
-    pub fn decrby(&mut self, key: &str, decr: i32) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            self.dictionary
-                .insert(key.to_string(), StorageValue::String("0".to_string()));
-        }
-
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        if let Some((StorageValue::String(val), _)) = dictionary.get(key) {
-            let val = match val.parse::<i32>() {
-                Ok(val) => val - decr,
-                Err(_) => return Err(DataBaseError::NotAnInteger),
-            };
+    pub fn keys(&mut self, pattern: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        let keys = self.dictionary().keys();
+        let list: Vec<SuccessQuery> = keys
+            .iter()
+            .filter(|x| matcher(x, pattern))
+            .map(|item| SuccessQuery::String(item.to_vec()))
+            .collect::<Vec<SuccessQuery>>();
 
-            dictionary.insert(
-                key.to_owned(),
-                (StorageValue::String(val.to_string()), SystemTime::now()),
-            );
-            Ok(SuccessQuery::Integer(val))
-        } else {
-            Err(DataBaseError::NotAString)
-        }
+        Ok(SuccessQuery::List(list))
     }
 
-    /// Get the value of key.
-    /// If the key does not exist the special value nil is returned.
-    /// An error is returned if the value stored at key is not a string, because GET only handles string values.
+    /// Remove the existing timeout on key, turning the key from volatile (a key with an expire set)
+    /// to persistent (a key that will never expire as no timeout is associated).
     ///
-    /// Returns value: SuccessQuery::String with the value of key, or DataBaseError::NonExistentKey when key does not exist.
+    /// Reply: SuccessQuery:Integer(n) where n is specified by:
     ///
+    /// n=1 if the timeout was removed.
+    ///
+    /// n=0 if key does not exist or does not have an associated timeout.
     /// # Examples
     /// ```
-    /// let db = Database::new("dump_path.txt");
-    /// db.set("KEY", "VALUE").unwrap();
-    /// if let SuccessQuery::String(value) = database.get("KEY").unwrap() {
-    ///         assert_eq!("VALUE", value.to_string());
-    /// }
+    /// todo
     /// ```
-    pub fn get(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
+    pub fn persist(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
         if !self._exists(key) {
-            return Ok(SuccessQuery::Nil);
+            return Ok(SuccessQuery::Boolean(false));
         }
-
-        self.dictionary.touch(key);
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::String(val), _)) => Ok(SuccessQuery::String(val.clone())),
-            Some(_) => Err(DataBaseError::NotAString),
-            None => Ok(SuccessQuery::Nil),
+        self.dictionary().touch(key);
+        let removed = self.expires().lock().unwrap().remove(key).is_some();
+        if removed {
+            self.mark_modified();
         }
+
+        Ok(SuccessQuery::Boolean(removed))
     }
 
-    /// Get the value of key and delete the key.
-    /// This command is similar to GET, except for the fact that it also deletes the key on success
-    /// (if and only if the key's value type is a string).
+    /// Renames key to newkey.
     ///
-    /// Returns value: the value of key, nil when key does not exist, or an error if the key's value type isn't a string.
+    /// It returns an error when key does not exist.
+    ///
+    /// If newkey already exists it is overwritten, when this happens RENAME executes an implicit DEL operation,
+    /// so if the deleted key contains a very big value it may cause high latency even if RENAME itself is usually a constant-time operation.
+    ///
+    /// Reply: SuccessQuery::Success if success result or DatabaseError::NonExistentKey if key does not exists.
     ///
     /// # Examples
     /// ```
-    /// let database = Database::new("path_to_dump.txt");
-    /// database.set("KEY", "VALUE").unwrap();
-    /// let database = create_database_with_string();
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"KEY", b"VALUE").unwrap();
     ///
-    /// if let SuccessQuery::String(value) = database.getdel("KEY").unwrap() {
-    ///     assert_eq!(value.to_string(), "VALUE");
-    /// }
+    /// let result = database.rename(b"KEY", b"SECOND_KEY").unwrap();
+    /// assert_eq!(result, SuccessQuery::Success);
     ///
-    /// let result = database.get("KEY").unwrap_err();
-    /// assert_eq!(result, DataBaseError::NonExistentKey);
+    /// let result = database.get(b"KEY").unwrap();
+    /// assert_eq!(result, SuccessQuery::Nil);
     /// ```
-    pub fn getdel(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        self.ttl_msg_sender
-            .send(MessageTtl::Clear(key.to_owned()))
-            .unwrap();
+    pub fn rename(
+        &mut self,
+        old_key: &[u8],
+        new_key: &[u8],
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(old_key) {
+            return Err(DataBaseError::NonExistentKey);
+        }
 
-        match self.get(key) {
-            Ok(SuccessQuery::String(val)) => {
-                self.dictionary.remove(key);
-                Ok(SuccessQuery::String(val))
+        let expires = self.expires();
+        let mut expires = expires.lock().unwrap();
+        match self.dictionary().remove(old_key) {
+            Some(value) => {
+                self.dictionary().insert(new_key.to_owned(), value);
+                expires.rename(old_key, new_key);
+                self.mark_modified();
+
+                Ok(SuccessQuery::Success)
             }
-            other => other,
+            None => Err(DataBaseError::NonExistentKey),
         }
     }
 
-    /// Atomically sets key to value and returns the old value stored at key.
-    /// Returns an error when key exists but does not hold a string value.
-    /// Any previous time to live associated with the key is discarded on successful SET operation.
-    ///
-    /// Returns value: the old value stored at key, or nil when key did not exist.
-    pub fn getset(&mut self, key: &str, new_val: &str) -> Result<SuccessQuery, DataBaseError> {
-        let old_value = match self.get(key) {
-            Ok(SuccessQuery::String(old_value)) => old_value,
-            other => return other,
-        };
-
-        self.ttl_msg_sender
-            .send(MessageTtl::Clear(key.to_owned()))
-            .unwrap();
+    #[doc(hidden)]
+    pub fn sort_by(
+        &mut self,
+        to_order: &mut [Vec<u8>],
+        pattern: &[u8],
+    ) -> Result<Vec<Vec<u8>>, DataBaseError> {
+        if let Some(arrow) = pattern.windows(2).position(|w| w == b"->") {
+            let (key_pattern, field) = (&pattern[..arrow], &pattern[arrow + 2..]);
+            return self.sort_by_hash_field(to_order, key_pattern, field);
+        }
 
-        self.dictionary
-            .insert(key.to_owned(), StorageValue::String(new_val.to_owned()));
+        let mut list_elem_weight: Vec<(&[u8], i32)> = Vec::new();
 
-        Ok(SuccessQuery::String(old_value))
-    }
+        let keys = self.dictionary().keys();
+        let list_key_match = keys
+            .iter()
+            .filter(|x| matcher(x, pattern) && self._exists(x))
+            .collect::<Vec<&Vec<u8>>>();
 
-    /// Increments the number stored at key by increment.
-    /// If the key does not exist, it is set to 0 before performing the operation.
-    /// An error is returned if the key contains a value of the wrong type or contains a string that can not be represented as integer.
-    /// This operation is limited to 64 bit signed integers.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// let database = Database::new("dump_path.txt");
-    ///
-    /// database.set(KEY, "1").unwrap();
-    ///
-    /// let result = database.incrby(KEY, 4).unwrap();
-    ///
-    /// assert_eq!(result, SuccessQuery::Integer(5));
-    ///
-    /// ```
-    pub fn incrby(&mut self, key: &str, incr: i32) -> Result<SuccessQuery, DataBaseError> {
-        self.decrby(key, -incr)
-    }
-
-    /// Returns the values of all specified keys.
-    /// For every key that does not hold a string value or does not exist, the special value nil is returned.
-    /// Because of this, the operation never fails.
-    /// Returns: list of SuccessQuery::String values at the specified keys.
-    /// # Examples
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.set("KEY_A", "VALUE_A").unwrap();
-    /// database.set("KEY_B", "VALUE_B").unwrap();
-    ///
-    /// let vec_keys = vec!["KEY_A", "KEY_B", "KEY_C", "KEY_D"];
-    ///
-    /// if let SuccessQuery::List(list) = database.mget(vec_keys).unwrap() {
-    ///     assert_eq!(list[0], SuccessQuery::String("VALUE_A"));
-    ///     assert_eq!(list[1], SuccessQuery::String("VALUE_B"));
-    ///     assert_eq!(list[2], SuccessQuery::Nil);
-    ///     assert_eq!(list[3], SuccessQuery::Nil);
-    /// }
-    /// ```
-    pub fn mget(&mut self, params: Vec<&str>) -> Result<SuccessQuery, DataBaseError> {
-        let mut list: Vec<SuccessQuery> = Vec::new();
-
-        for key in params {
-            let r = self.get(key);
-            match r {
-                Err(DataBaseError::NotAString) => {
-                    list.push(SuccessQuery::Nil);
-                }
-                other => {
-                    list.push(other.unwrap());
+        for (i, elem) in to_order.iter().enumerate() {
+            let without_weight = 0;
+            for pal in list_key_match.iter() {
+                if pal.windows(elem.len()).any(|w| w == &elem[..]) {
+                    let dictionary = self.dictionary().get_atomic_hash(pal);
+                    let dictionary = dictionary.read().unwrap();
+                    if let Some((StorageValue::String(val), _)) = dictionary.get(*pal) {
+                        let result_weight = match parse_bytes::<i32>(val) {
+                            Some(weight_ok) => Ok(weight_ok),
+                            None => Err(DataBaseError::SortByParseError),
+                        };
+                        if let Ok(weight) = result_weight {
+                            list_elem_weight.push((elem, weight));
+                            break;
+                        } else {
+                            return Err(DataBaseError::SortByParseError);
+                        }
+                    }
                 }
             }
+            if list_elem_weight.len() < i + 1 {
+                list_elem_weight.push((elem, without_weight));
+            }
         }
 
-        Ok(SuccessQuery::List(list))
-    }
+        if list_elem_weight.is_empty() {
+            return Ok(to_order.to_vec());
+        }
 
-    /// Sets the given keys to their respective values. MSET replaces existing values with new values, just as regular SET.
-    ///
-    /// Reply: always OK since MSET can't fail.
-    ///
-    /// # Examples
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    ///
-    /// let vec_key_value = vec![
-    ///     "KEY_A", "VALUE_A", "KEY_B", "VALUE_B", "KEY_C", "VALUE_C", "KEY_D", "VALUE_D",
-    /// ];
-    ///
-    /// let result = database.mset(vec_key_value).unwrap();
-    /// assert_eq!(result, SuccessQuery::Success);
-    ///
-    /// let result_get1 = database.get("KEY_A").unwrap();
-    /// let result_get2 = database.get("KEY_B").unwrap();
-    /// let result_get3 = database.get("KEY_C").unwrap();
-    ///
-    /// assert_eq!(result_get1, SuccessQuery::String("VALUE_A"));
-    /// assert_eq!(result_get2, SuccessQuery::String("VALUE_B"));
-    /// assert_eq!(result_get3, SuccessQuery::String("VALUE_C"));
-    /// ```
-    pub fn mset(&mut self, params: Vec<&str>) -> Result<SuccessQuery, DataBaseError> {
-        for i in (0..params.len()).step_by(2) {
-            let key = params.get(i).unwrap();
-            let value = params.get(i + 1).unwrap();
+        list_elem_weight.sort_by_key(|a| a.1);
 
-            let _ = self.set(key, value);
-        }
-        Ok(SuccessQuery::Success)
+        let to_build: Vec<Vec<u8>> = list_elem_weight.iter().map(|x| x.0.to_vec()).collect();
+        Ok(to_build)
     }
 
-    /// Set key to hold the string value.
-    /// If key already holds a value, it is overwritten, regardless of its type.
-    /// Any previous time to live associated with the key is discarded on successful SET operation.
-    ///
-    /// Reply: OK. SET was always executed correctly.
-    ///
-    /// # Example
-    /// ```
-    /// let database = Database::new("dump_path.txt");
-    ///
-    /// let result = database.set("KEY","VALUE").unwrap();
-    /// assert_eq!(SuccessQuery::Success, result);
-    ///
-    /// if let SuccessQuery::String(value) = database.get("KEY").unwrap() {
-    ///     assert_eq!("VALUE", value);
-    /// }
-    /// ```
-    pub fn set(&mut self, key: &str, val: &str) -> Result<SuccessQuery, DataBaseError> {
-        self.ttl_msg_sender
-            .send(MessageTtl::Clear(key.to_owned()))
-            .unwrap();
+    #[doc(hidden)]
+    fn sort_by_hash_field(
+        &mut self,
+        to_order: &mut [Vec<u8>],
+        key_pattern: &[u8],
+        field: &[u8],
+    ) -> Result<Vec<Vec<u8>>, DataBaseError> {
+        let mut list_elem_weight: Vec<(&[u8], i32)> = Vec::new();
+
+        for elem in to_order.iter() {
+            let key = match key_pattern.iter().position(|byte| *byte == b'*') {
+                Some(star) => [&key_pattern[..star], elem, &key_pattern[star + 1..]].concat(),
+                None => key_pattern.to_vec(),
+            };
+            let weight = match self.hget(&key, field) {
+                Ok(SuccessQuery::String(value)) => {
+                    parse_bytes::<i32>(&value).ok_or(DataBaseError::SortByParseError)?
+                }
+                _ => 0,
+            };
+            list_elem_weight.push((elem, weight));
+        }
 
-        self.dictionary
-            .insert(key.to_owned(), StorageValue::String(val.to_owned()));
+        list_elem_weight.sort_by_key(|a| a.1);
 
-        Ok(SuccessQuery::Success)
+        Ok(list_elem_weight.iter().map(|x| x.0.to_vec()).collect())
     }
 
-    /// Returns the length of the string value stored at key.
-    /// An error is returned when key holds a non-string value.
-    ///
-    /// Reply: SuccessQuery::Integer with the length of the string at key, or 0 when key does not exist.
-    /// # Examples
-    /// ```
-    /// let database = Database::new("dump_path.txt");
-    ///
-    /// database.set("KEY", "VALUE").unwrap();
-    ///
-    /// if let SuccessQuery::Integer(value) = database.strlen("KEY").unwrap() {
-    ///     assert_eq!(value, 5);
-    /// }
-    /// ```
-    pub fn strlen(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        match self.get(key) {
-            Ok(SuccessQuery::String(val)) => Ok(SuccessQuery::Integer(val.len() as i32)),
-            other => other,
+    #[doc(hidden)]
+    pub fn limit(
+        to_order: &mut [Vec<u8>],
+        pos_begin: &mut i32,
+        num_elems: &mut i32,
+    ) -> Vec<Vec<u8>> {
+        let empty_list = Vec::new();
+        if *pos_begin >= to_order.len() as i32 {
+            return empty_list;
         }
-    }
-
-    // Lists
-
-    /// Returns the element at index in the list stored at key.
-    /// The index is zero-based, so 0 means the first element, 1 the second element and so on.
-    /// Negative indices can be used to designate elements starting at the tail of the list.
-    /// Here, -1 means the last element, -2 means the penultimate and so forth.
-    /// When the value at key is not a list, an error is returned.
-    ///
-    /// Reply: SuccessQuery::String(val) when val is the requested element, or SuccessQuery::Nil when index is out of range.
-    /// # Examples
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    ///
-    /// database.lpush("KEY", ["VALUE"].to_vec()).unwrap();
-    ///
-    /// if let SuccessQuery::String(value) = database.lindex("KEY", 0).unwrap() {
-    ///     assert_eq!(value, "VALUE");
-    /// }
-    /// ```
-    pub fn lindex(&mut self, key: &str, index: i32) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Nil);
+        if *num_elems == 0 {
+            return empty_list;
         }
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                let index = if index < 0 {
-                    ((list.len() as i32) + index) as usize
-                } else {
-                    index as usize
-                };
-
-                match list.get(index) {
-                    Some(val) => {
-                        *last_access = SystemTime::now();
-                        Ok(SuccessQuery::String(val.clone()))
-                    }
-                    None => Ok(SuccessQuery::Nil),
-                }
-            }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Ok(SuccessQuery::Nil),
+        if *pos_begin < 0 {
+            *pos_begin = 0;
         }
+        if *num_elems < 0 || *num_elems > to_order.len() as i32 {
+            *num_elems = to_order.len() as i32;
+        }
+        to_order.to_vec()
     }
 
-    /// Returns the length of the list stored at key.
-    /// If key does not exist, it is interpreted as an empty list and 0 is returned.
-    /// An error is returned when the value stored at key is not a list.
-    ///
-    /// Reply: SuccessQuery::Integer(n) when n is the the length of the list at key.
-    /// # Examples
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    ///
-    /// let database = database.lpush("KEY", ["VALUE_A","VALUE_B", "VALUE_C"].to_vec()).unwrap();
-    ///
-    /// let result = database.llen("KEY").unwrap();
-    ///
-    /// assert_eq!(result, SuccessQuery::Integer(3));
-    /// ```
-    pub fn llen(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Integer(0));
-        }
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                *last_access = SystemTime::now();
-                Ok(SuccessQuery::Integer(list.len() as i32))
-            }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Ok(SuccessQuery::Integer(0)),
-        }
+    #[doc(hidden)]
+    pub fn sort_limit(
+        to_order: &mut [Vec<u8>],
+        pos_begin: &mut i32,
+        num_elems: &mut i32,
+    ) -> Result<Vec<Vec<u8>>, DataBaseError> {
+        let mut to_build: Vec<Vec<u8>> = Database::limit(to_order, pos_begin, num_elems);
+        Database::sort_without_flags(&mut to_build)
     }
 
-    /// Removes and returns the first elements of the list stored at key.
-    /// By default, the command pops a single element from the beginning of the list.
-    /// When provided with the optional count argument, the reply will consist of up to count elements, depending on the list's length.
-    ///
-    /// Reply: SuccessQuery::String(s) when s is the the value of the first element, or SuccessQuery::Nil when key does not exist.
-    /// # Examples
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    ///
-    /// let database = database.rpush("KEY", "VALUE_A").unwrap();
-    /// let database = database.rpush("KEY", "VALUE_B").unwrap();
-    ///
-    /// if let SuccessQuery::String(val) = database.lpop("KEY").unwrap() {
-    ///     assert_eq!(val.to_string(), "VALUE_A".to_string());
-    /// }
-    ///
-    /// let result = database.llen("KEY").unwrap();
-    /// assert_eq!(result, SuccessQuery::Integer(1));
-    /// ```
-    pub fn lpop(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Nil);
-        }
+    #[doc(hidden)]
+    pub fn desc(to_order: &mut [Vec<u8>]) -> Vec<Vec<u8>> {
+        to_order.reverse();
+        to_order.to_vec()
+    }
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                *last_access = SystemTime::now();
-                if list.is_empty() {
-                    Ok(SuccessQuery::Nil)
-                } else {
-                    Ok(SuccessQuery::String(list.remove(0)))
+    #[doc(hidden)]
+    pub fn sort_desc(to_order: &mut [Vec<u8>]) -> Result<Vec<Vec<u8>>, DataBaseError> {
+        let mut parse_error = false;
+        let mut to_order: Vec<_> = to_order
+            .iter()
+            .map(|x| match parse_bytes::<i32>(x) {
+                Some(val) => val,
+                None => {
+                    parse_error = true;
+                    -1
                 }
-            }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Ok(SuccessQuery::Nil),
+            })
+            .collect();
+        if parse_error {
+            return Err(DataBaseError::SortParseError);
         }
+        to_order.sort_by(|a, b| b.cmp(a));
+        let to_build: Vec<Vec<u8>> = to_order
+            .iter()
+            .map(|x| x.to_string().into_bytes())
+            .collect();
+        Ok(to_build)
     }
 
     #[doc(hidden)]
-    fn lpush_one(&mut self, key: &str, value: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            let list: Vec<String> = vec![value.to_owned()];
-            let len = list.len();
-            self.dictionary
-                .insert(key.to_owned(), StorageValue::List(list));
-            return Ok(SuccessQuery::Integer(len as i32));
+    pub fn sort_without_flags(to_order: &mut [Vec<u8>]) -> Result<Vec<Vec<u8>>, DataBaseError> {
+        let mut parse_error = false;
+        let mut to_order: Vec<_> = to_order
+            .iter()
+            .map(|x| match parse_bytes::<i32>(x) {
+                Some(val) => val,
+                None => {
+                    parse_error = true;
+                    -1
+                }
+            })
+            .collect();
+        if parse_error {
+            return Err(DataBaseError::SortParseError);
         }
+        to_order.sort_unstable();
+        let to_build: Vec<Vec<u8>> = to_order
+            .iter()
+            .map(|x| x.to_string().into_bytes())
+            .collect();
+        Ok(to_build)
+    }
 
-        self.dictionary.touch(key);
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), _)) => {
-                list.insert(0, value.to_owned());
-                Ok(SuccessQuery::Integer(list.len() as i32))
-            }
-            _ => Err(DataBaseError::NotAList),
+    #[doc(hidden)]
+    pub fn build_sort_vector(
+        to_build: Vec<Vec<u8>>,
+        pos_begin: &i32,
+        num_elems: &i32,
+    ) -> Result<Vec<SuccessQuery>, DataBaseError> {
+        let mut result_list: Vec<SuccessQuery> = Vec::new();
+        for i in *pos_begin..(pos_begin + num_elems) {
+            result_list.push(SuccessQuery::String(to_build[i as usize].to_vec()));
         }
+        Ok(result_list)
     }
 
-    /// Insert all the specified values at the head of the list stored at key.
-    /// If key does not exist, it is created as empty list before performing the push operations.
-    /// When key holds a value that is not a list, an error is returned.
+    #[doc(hidden)]
+    pub fn _sort(
+        &mut self,
+        to_order: &mut [Vec<u8>],
+        sort_flags: SortFlags,
+    ) -> Result<Vec<SuccessQuery>, DataBaseError> {
+        let mut num_elems = to_order.len() as i32;
+        let mut pos_begin = 0;
+        let mut to_order: Vec<Vec<u8>> = to_order.to_vec();
+
+        match sort_flags {
+            SortFlags::WithoutFlags => match Database::sort_without_flags(&mut to_order) {
+                Ok(to_build) => Database::build_sort_vector(to_build, &pos_begin, &num_elems),
+                Err(err) => Err(err),
+            },
+            SortFlags::Alpha => {
+                to_order.sort();
+                Database::build_sort_vector(to_order, &pos_begin, &num_elems)
+            }
+            SortFlags::Desc => match Database::sort_desc(&mut to_order) {
+                Ok(to_build) => Database::build_sort_vector(to_build, &pos_begin, &num_elems),
+                Err(err) => Err(err),
+            },
+            SortFlags::Limit(mut pos_ini, mut num_elem) => {
+                match Database::sort_limit(&mut to_order, &mut pos_ini, &mut num_elem) {
+                    Ok(to_build) => Database::build_sort_vector(to_build, &pos_ini, &num_elem),
+                    Err(err) => Err(err),
+                }
+            }
+            SortFlags::By(pattern) => match self.sort_by(&mut to_order, pattern) {
+                Ok(to_build) => Database::build_sort_vector(to_build, &pos_begin, &num_elems),
+                Err(err) => Err(err),
+            },
+            SortFlags::CompositeFlags(sort_flags) => {
+                if sort_flags
+                    .iter()
+                    .any(|s| matches!(s, SortFlags::Limit(_, _)))
+                    && sort_flags.iter().any(|s| matches!(s, SortFlags::Desc))
+                    && sort_flags.len() == 2
+                {
+                    return match Database::sort_desc(&mut to_order) {
+                        Ok(mut to_build) => {
+                            let to_build: Vec<Vec<u8>> =
+                                Database::limit(&mut to_build, &mut pos_begin, &mut num_elems);
+                            Database::build_sort_vector(to_build, &pos_begin, &num_elems)
+                        }
+                        Err(err) => Err(err),
+                    };
+                }
+
+                if sort_flags.iter().any(|s| matches!(s, SortFlags::By(_))) {
+                    let pattern: Option<Vec<u8>> = sort_flags.iter().find_map(|d| match d {
+                        SortFlags::By(pattern) => Some(pattern.to_vec()),
+                        _ => None,
+                    });
+
+                    to_order = self.sort_by(&mut to_order, &pattern.unwrap())?;
+                }
+
+                if sort_flags.iter().any(|s| matches!(s, SortFlags::Alpha))
+                    && !sort_flags.iter().any(|s| matches!(s, SortFlags::By(_)))
+                {
+                    to_order.sort();
+                }
+
+                for flag in sort_flags {
+                    if let SortFlags::Desc = flag {
+                        to_order = Database::desc(&mut to_order);
+                    }
+                    if let SortFlags::Limit(l_pos_begin, l_num_elems) = flag {
+                        pos_begin = l_pos_begin;
+                        num_elems = l_num_elems;
+                        to_order = Database::limit(&mut to_order, &mut pos_begin, &mut num_elems);
+                    }
+                }
+                Database::build_sort_vector(to_order, &pos_begin, &num_elems)
+            }
+        }
+    }
+
+    /// # Sort in simplest Form
     ///
-    /// It is possible to push multiple elements using a single command call just specifying multiple arguments at the end of the command.
-    /// Elements are inserted one after the other to the head of the list, from the leftmost element to the rightmost element.
-    /// So for instance the command LPUSH mylist a b c will result into a list containing c as first element, b as second element and a as third element.
+    /// Returns or stores the elements contained in the list or set at key. By default,
+    /// sorting is numeric and elements are compared by their value interpreted as double precision floating point number.
     ///
-    /// Reply: SuccessQuery::Integer(n) when n is the the length of the list after the push operations.
+    /// `database.sort(b"key", SortingFlags::WithoutFlags)`
     ///
-    /// # Examples
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.lpush("KEY", ["VALUEA"].to_vec()).unwrap();
+    /// # Sort with Params
     ///
-    /// let result = database.lpush("KEY", ["VALUEB"].to_vec()).unwrap();
+    /// Assuming key is a list of numbers, this command will return the same list with the elements sorted from small to large.
     ///
-    /// assert_eq!(result, SuccessQuery::Integer(2));
+    /// In order to sort the numbers from large to small to large. use the DESC modifier:
     ///
-    /// let dictionary = database.dictionary;
-    /// let dictionary = dictionary.get_atomic_hash("KEY");
-    /// let dictionary = dictionary.lock().unwrap();
+    /// `database.sort(b"key", SortFlags::Desc)`
     ///
-    /// if let StorageValue::List(list) = dictionary.get("KEY").unwrap() {
-    ///     assert_eq!(list.len(), 2);
-    ///     assert_eq!(list[1], "VALUEA");
-    ///     assert_eq!(list[0], "VALUEB");
+    /// When mylist contains string values and you want to sort them lexicographically, use the ALPHA modifier:
+    ///
+    /// `database.sort(b"key", SortFlags::Alpha)`
+    ///
+    /// The number of returned elements can be limited using the LIMIT modifier.
+    ///
+    /// This modifier takes the offset argument, specifying the number of elements to skip and the count argument,
+    /// specifying the number of elements to return from starting at offset.
+    ///
+    /// The following example will return 10 elements of the sorted version of mylist, starting at element 0 (offset is zero-based):
+    ///
+    /// `database.sort(b"key", SortFlags::Limit(0, 10))`
+    ///
+    /// Almost all modifiers can be used together.
+    /// The following example will return the first 5 elements, lexicographically sorted in descending order:
+    ///
+    /// `database.sort(b"key", SortFlags::CompositeFlags(![SortFlags::Limit(0,5), SortingFlags::Alpha, SortingFlags::Desc]))`
+    ///
+    /// # Sorting By External Keys
+    ///
+    /// Sometimes you want to sort elements using external keys as weights to compare instead of comparing the actual elements in the list,
+    /// set or sorted set.
+    ///
+    /// Let's say the list mylist contains the elements 1, 2 and 3 representing unique IDs of objects stored in object_1, object_2 and object_3.
+    /// When these objects have associated weights stored in weight_1, weight_2 and weight_3,
+    /// SORT can be instructed to use these weights to sort mylist with the following statement:
+    ///
+    /// `database.sort(b"key", SortingFlags::By(b"weight_*"))`
+    ///
+    /// The BY option takes a pattern (equal to weight_* in this example) that is used to generate the keys that are used for sorting.
+    ///
+    /// These key names are obtained substituting the first occurrence of * with the actual value of the element in the list (1, 2 and 3 in this example).
+    ///
+    /// When the pattern has the form `weight_*->field`, the weights are read from the field
+    /// of the hashes stored at the keys obtained substituting `*` in `weight_*`.
+    ///
+    /// Reply: without passing the store option the command returns a SuccessQuery::List(list) where list is an array of sorted elements.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database
+    ///     .lpush(b"LIST", [b"3", b"1", b"2"].to_vec())
+    ///     .unwrap();
+    ///
+    /// if let SuccessQuery::List(list) = database.sort(b"LIST", SortFlags::WithoutFlags).unwrap()
+    /// {
+    ///     let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+    ///     let to_compare_list: Vec<&[u8]> = vec![b"1", b"2", b"3"];
+    ///     let pair_list: Vec<(&String, &str)> =
+    ///         list_result.iter().zip(to_compare_list).collect();
+    ///     pair_list.iter().for_each(|x| {
+    ///         assert_eq!(x.0, x.1);
+    ///     });
     /// }
     /// ```
-    pub fn lpush(&mut self, key: &str, values: Vec<&str>) -> Result<SuccessQuery, DataBaseError> {
-        let mut result = self.lpush_one(key, values[0]);
-        for item in values.iter().skip(1) {
-            result = self.lpush_one(key, item)
+    pub fn sort(
+        &mut self,
+        key: &[u8],
+        sort_flags: SortFlags,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::List(Vec::new()));
         }
+        self.dictionary().touch(key);
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        let mut to_order: Vec<Vec<u8>> = match dictionary.get(key) {
+            Some((StorageValue::Set(hash_set), _)) => hash_set.iter().map(|s| s.to_vec()).collect(),
+            Some((StorageValue::List(list), _)) => list.iter().map(|x| x.to_vec()).collect(),
+            Some((StorageValue::Zset(sorted_set), _)) => sorted_set
+                .members()
+                .into_iter()
+                .map(|(member, _)| member)
+                .collect(),
+            Some(_) => return Err(DataBaseError::NotAList),
+            None => return Ok(SuccessQuery::List(Vec::new())),
+        };
+        drop(dictionary);
 
-        result
+        match self._sort(&mut to_order, sort_flags) {
+            Ok(result_list) => Ok(SuccessQuery::List(result_list)),
+            Err(e) => Err(e),
+        }
     }
 
-    /// Inserts specified value/s at the head of the list stored at key, only if key already exists and holds a list.
-    /// In contrary to LPUSH, no operation will be performed when key does not yet exist.
+    /// Update the key's last access value
     ///
-    /// Reply: SuccessQuery::Integer(n) when n is the the length of the list after the push operation.
+    /// Indicates in the log file the last previous access or the time since that previous access.
     ///
-    /// n=0 if key not hold a list and not push data.
+    /// Check if the key expiration condition (TTL) was met and if so, perform the deletion
+    ///
+    /// Reply: SuccessQuery::Integer(n) where n is specified by .
+    ///
+    /// n=1 if the key was be touched.
+    ///
+    /// n=0 if key does not be touch.
     ///
     /// # Examples
     /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.lpushx("KEY", ["VALUEA"].to_vec()).unwrap();
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    ///
     ///
-    /// assert_eq!(result, SuccessQuery::Integer(0));
-    /// ```
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.lpush("KEY", ["VALUEA"].to_vec()).unwrap();
-    /// database.lpushx("KEY", ["VALUEB"].to_vec()).unwrap();
-    /// let dictionary = database.dictionary;
-    /// let dictionary = dictionary.get_atomic_hash("KEY");
-    /// let dictionary = dictionary.lock().unwrap();
     ///
-    /// if let StorageValue::List(list) = dictionary.get("KEY").unwrap() {
-    ///     assert_eq!(list.len(), 2);
-    ///     assert_eq!(list[1], "VALUEA");
-    ///     assert_eq!(list[0], "VALUEB");
-    /// }
     /// ```
-    pub fn lpushx(&mut self, key: &str, values: Vec<&str>) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Integer(0));
-        }
+    pub fn touch(&mut self, key: &[u8]) -> Option<u64> {
+        self.expire_if_needed(key);
+        self.dictionary().touch(key)
+    }
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                *last_access = SystemTime::now();
-                values.iter().for_each(|&val| {
-                    list.insert(0, val.to_owned());
-                });
-                Ok(SuccessQuery::Integer(list.len() as i32))
+    /// Removes key if its time to live is over.
+    #[doc(hidden)]
+    fn expire_if_needed(&self, key: &[u8]) {
+        let expires = self.expires();
+        let mut expires = expires.lock().unwrap();
+        if let Some(expire_time) = expires.get(key) {
+            if expire_time <= SystemTime::now() {
+                expires.remove(key);
+                self.dictionary().remove(key);
+                self.signal_modified_key(key);
+                drop(expires);
+                self.notify_keyspace_event(EventClass::Expired, "expired", key);
             }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Ok(SuccessQuery::Integer(0)),
         }
     }
 
-    /// Returns the specified elements of the list stored at key.
-    /// The offsets start and stop are zero-based indexes, with 0 being the first element of the list (the head of the list),
-    /// 1 being the next element and so on.
+    #[doc(hidden)]
+    fn get_expire_time(&self, key: &[u8]) -> Option<SystemTime> {
+        self.expires().lock().unwrap().get(key)
+    }
+
+    /// Returns the remaining time to live of a key that has a timeout.
+    /// This introspection capability allows a Redis client to check how many seconds a given key will continue to be part of the dataset.
     ///
-    /// These offsets can also be negative numbers indicating offsets starting at the end of the list.
-    /// For example, -1 is the last element of the list, -2 the penultimate, and so on.
+    /// Reply: TTL in seconds, or a negative value in order to signal an error (see the description above).
+    /// SuccessQuery::Integer(n) where n is the time to live of the key.
     ///
-    /// Reply: SuccessQuery::List(list) when list is the list of elements in the specified range.
+    /// SuccessQuery::Integer(n) when n is negative if an error ocurred according to:
+    ///
+    /// -2 if the key does not exist.
+    ///
+    /// -1 if the key exists but has no associated expire.
     ///
     /// # Examples
     /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.lpush("KEY", ["VALUEA","VALUEB", "VALUEC","VALUED"].to_vec()).unwrap();
-    ///
-    /// if let SuccessQuery::List(list) = database.lrange("KEY", 0, 2).unwrap() {
-    ///     let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-    ///     let second_list: Vec<&str> = vec!["VALUED", "VALUEC", "VALUEB"];
-    ///     let pair_list: Vec<(&String, &str)> = list.iter().zip(second_list).collect();
-    ///     pair_list.iter().for_each(|x| {
-    ///         assert_eq!(x.0, x.1);
-    ///     })
-    /// }
+    /// todo
     /// ```
+    pub fn ttl(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        match self.pttl(key)? {
+            SuccessQuery::Integer(ms) if ms >= 0 => Ok(SuccessQuery::Integer((ms + 500) / 1000)),
+            reply => Ok(reply),
+        }
+    }
+
+    /// Like TTL, returns the remaining time to live of a key, but in milliseconds.
+    ///
+    /// Reply: SuccessQuery::Integer with the milliseconds, -2 if the key does not exist
+    /// or -1 if the key exists but has no associated expire.
+    /// # Examples
+    /// Basic Usage:
     /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.lpush("KEY", ["VALUEA","VALUEB", "VALUEC","VALUED"].to_vec()).unwrap();
-    /// if let SuccessQuery::List(list) = database.lrange("KEY", 0, -1).unwrap() {
-    ///     let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-    ///     let second_list: Vec<&str> = vec!["VALUED", "VALUEC", "VALUEB", "VALUEA"];
-    ///     let pair_list: Vec<(&String, &str)> = list.iter().zip(second_list).collect();
-    ///     pair_list.iter().for_each(|x| {
-    ///         assert_eq!(x.0, x.1);
-    ///     })
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    /// database.pexpire(b"key", 1500, ExpireFlags::default()).unwrap();
+    ///
+    /// if let SuccessQuery::Integer(ms) = database.pttl(b"key").unwrap() {
+    ///     assert!(ms > 1400 && ms <= 1500);
     /// }
     /// ```
-    pub fn lrange(&mut self, key: &str, ini: i32, end: i32) -> Result<SuccessQuery, DataBaseError> {
-        let mut sub_list: Vec<SuccessQuery> = Vec::new();
+    pub fn pttl(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
         if !self._exists(key) {
-            return Ok(SuccessQuery::List(sub_list));
+            return Ok(SuccessQuery::Integer(-2));
         }
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                let len = list.len() as i32;
-                let ini = if ini < 0 {
-                    (len + ini) as usize
-                } else {
-                    ini as usize
-                };
-                let end = if end < 0 {
-                    (len + end) as usize
-                } else {
-                    end as usize
-                };
-
-                if end < len as usize && ini <= end {
-                    for elem in list[ini..(end + 1)].iter() {
-                        sub_list.push(SuccessQuery::String(elem.clone()));
-                    }
-                }
-
-                *last_access = SystemTime::now();
-                Ok(SuccessQuery::List(sub_list))
+        match self.get_expire_time(key) {
+            Some(time) => {
+                let duration = time.duration_since(SystemTime::now()).unwrap_or_default();
+                Ok(SuccessQuery::Integer(duration.as_millis() as i64))
             }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Ok(SuccessQuery::List(sub_list)),
+            None => Ok(SuccessQuery::Integer(-1)),
         }
     }
 
-    /// Removes the first count occurrences of elements equal to element from the list stored at key.
-    /// The count argument influences the operation in the following ways:
+    /// Returns the absolute Unix time, in seconds, at which key will expire.
     ///
-    /// count > 0: Remove elements equal to element moving from head to tail.
-    ///
-    /// count < 0: Remove elements equal to element moving from tail to head.
+    /// Reply: SuccessQuery::Integer with the seconds, -2 if the key does not exist or
+    /// -1 if the key exists but has no associated expire.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"key", b"value").unwrap();
+    /// database.expireat(b"key", 33177117420, ExpireFlags::default()).unwrap();
     ///
-    /// count = 0: Remove all elements equal to element.
+    /// assert_eq!(database.expiretime(b"key").unwrap(), SuccessQuery::Integer(33177117420));
+    /// ```
+    pub fn expiretime(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        match self.pexpiretime(key)? {
+            SuccessQuery::Integer(ms) if ms >= 0 => Ok(SuccessQuery::Integer(ms / 1000)),
+            reply => Ok(reply),
+        }
+    }
+
+    /// Like EXPIRETIME, returns the absolute Unix time at which key will expire, but
+    /// in milliseconds.
     ///
-    /// For example, LREM list -2 "hello" will remove the last two occurrences of "hello" in the list stored at list.
+    /// Reply: SuccessQuery::Integer with the milliseconds, -2 if the key does not exist
+    /// or -1 if the key exists but has no associated expire.
+    pub fn pexpiretime(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Integer(-2));
+        }
+
+        match self.get_expire_time(key) {
+            Some(time) => Ok(SuccessQuery::Integer(unix_ms(time))),
+            None => Ok(SuccessQuery::Integer(-1)),
+        }
+    }
+
+    /// Returns the string representation of the type of the value stored at key.
+    /// The different types that can be returned are: string, list, set.
     ///
-    /// Note that non-existing keys are treated like empty lists, so when key does not exist, the command will always return 0.
+    /// Reply: SuccessQuery::String(s) where s is type of key or none when key does not exist.
     ///
-    /// Reply: SuccessQuery::Integer(n) when n is the number of removed elements.
+    /// # Examples
+    /// ```
+    /// todo
+    /// ```
+    pub fn get_type(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::String(b"none".to_vec()));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((val, stats)) => {
+                stats.hit();
+                Ok(SuccessQuery::String(val.get_type().into_bytes()))
+            }
+            None => Ok(SuccessQuery::String(b"none".to_vec())),
+        }
+    }
+
+    //STRINGS
+
+    /// This command appends the value at the end of the string, if key already exists and is a string.
+    /// If key does not exist it is created and set as an empty string, so APPEND will be similar to SET in this special case.
+    ///
+    /// Returns: SuccessQuery of the length of the string after the append operation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// if let SuccessQuery::Integer(lenght) = database.append(b"key", b"value").unwrap() {
+    ///     assert_eq!(lenght, 5);
+    /// }
+    /// ```
+    pub fn append(&mut self, key: &[u8], value: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if self._exists(key) {
+            let dictionary = self.dictionary().get_atomic_hash(key);
+            let mut dictionary = dictionary.write().unwrap();
+            if let Some((StorageValue::String(val), stats)) = dictionary.get_mut(key) {
+                val.extend_from_slice(value);
+                let len_result = val.len() as i64;
+                stats.hit();
+                self.mark_modified();
+                Ok(SuccessQuery::Integer(len_result))
+            } else {
+                Err(DataBaseError::NotAString)
+            }
+        } else {
+            let len_result = value.len() as i64;
+            self.dictionary()
+                .insert(key.to_owned(), StorageValue::String(value.to_vec()));
+            self.mark_modified();
+            Ok(SuccessQuery::Integer(len_result))
+        }
+    }
+
+    /// Decrements the number stored at key by decrement.
+    /// If the key does not exist, it is set to 0 before performing the operation.
+    /// An error is returned if the key contains a value of the wrong type or contains a string that can not be represented as integer.
+    /// This operation is limited to 64 bit signed integers.
     ///
     /// # Examples
     /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.lpush("KEY", ["VALUEA", "VALUEA", "VALUEC", "VALUEA"].to_vec()).unwrap();
+    /// let database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let database = create_database();
     ///
-    /// let result = database.lrem("KEY", 2, "VALUEA");
+    /// database.set(KEY, b"5").unwrap();
+    /// let result = database.decrby(KEY, 4).unwrap();
     ///
-    /// assert_eq!(SuccessQuery::Integer(2), result.unwrap());
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn decrby(&mut self, key: &[u8], decr: i32) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            self.dictionary()
+                .insert(key.to_vec(), StorageValue::String(b"0".to_vec()));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        if let Some((StorageValue::String(val), stats)) = dictionary.get_mut(key) {
+            let new_val = match parse_bytes::<i32>(val) {
+                Some(val) => val - decr,
+                None => return Err(DataBaseError::NotAnInteger),
+            };
+
+            *val = new_val.to_string().into_bytes();
+            stats.hit();
+            self.mark_modified();
+            Ok(SuccessQuery::Integer(new_val as i64))
+        } else {
+            Err(DataBaseError::NotAString)
+        }
+    }
+
+    /// Get the value of key.
+    /// If the key does not exist the special value nil is returned.
+    /// An error is returned if the value stored at key is not a string, because GET only handles string values.
     ///
-    /// let dictionary = database.dictionary;
-    /// let dictionary = dictionary.get_atomic_hash("KEY");
-    /// let dictionary = dictionary.lock().unwrap();
+    /// Returns value: SuccessQuery::String with the value of key, or DataBaseError::NonExistentKey when key does not exist.
     ///
-    /// if let Some(StorageValue::List(list)) = dictionary.get("KEY") {
-    ///     assert_eq!(list[0], "VALUEC");
-    ///     assert_eq!(list[1], "VALUEA");
+    /// # Examples
+    /// ```
+    /// let db = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// db.set(b"KEY", b"VALUE").unwrap();
+    /// if let SuccessQuery::String(value) = database.get(b"KEY").unwrap() {
+    ///         assert_eq!(b"VALUE", value);
     /// }
     /// ```
-    pub fn lrem(
-        &mut self,
-        key: &str,
-        mut count: i32,
-        elem: &str,
-    ) -> Result<SuccessQuery, DataBaseError> {
+    pub fn get(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
         if !self._exists(key) {
-            return Ok(SuccessQuery::Integer(0));
+            return Ok(SuccessQuery::Nil);
         }
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                *last_access = SystemTime::now();
-                match count.cmp(&0) {
-                    Ordering::Greater => {
-                        let mut exist_elem = true;
+        self.dictionary().touch(key);
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::String(val), _)) => Ok(SuccessQuery::String(val.clone())),
+            Some(_) => Err(DataBaseError::NotAString),
+            None => Ok(SuccessQuery::Nil),
+        }
+    }
 
-                        while count > 0 && exist_elem {
-                            if let Some(pos) = list.iter().position(|x| *x == elem) {
-                                list.remove(pos);
-                                count -= 1;
-                            } else {
-                                exist_elem = false;
-                            }
-                        }
+    /// Get the value of key and delete the key.
+    /// This command is similar to GET, except for the fact that it also deletes the key on success
+    /// (if and only if the key's value type is a string).
+    ///
+    /// Returns value: the value of key, nil when key does not exist, or an error if the key's value type isn't a string.
+    ///
+    /// # Examples
+    /// ```
+    /// let database = Database::new("path_to_dump.txt", 16, 16).unwrap();
+    /// database.set(b"KEY", b"VALUE").unwrap();
+    /// let database = create_database_with_string();
+    ///
+    /// if let SuccessQuery::String(value) = database.getdel(b"KEY").unwrap() {
+    ///     assert_eq!(value, b"VALUE");
+    /// }
+    ///
+    /// let result = database.get(b"KEY").unwrap_err();
+    /// assert_eq!(result, DataBaseError::NonExistentKey);
+    /// ```
+    pub fn getdel(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        match self.get(key) {
+            Ok(SuccessQuery::String(val)) => {
+                if self.remove_key(key).is_some() {
+                    self.mark_modified();
+                }
+                Ok(SuccessQuery::String(val))
+            }
+            other => other,
+        }
+    }
 
-                        Ok(SuccessQuery::Integer(list.len() as i32))
-                    }
-                    Ordering::Less => {
-                        let mut exist_elem = true;
+    /// Atomically sets key to value and returns the old value stored at key.
+    /// Returns an error when key exists but does not hold a string value.
+    /// Any previous time to live associated with the key is discarded on successful SET operation.
+    ///
+    /// Returns value: the old value stored at key, or nil when key did not exist.
+    pub fn getset(&mut self, key: &[u8], new_val: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        let old_value = match self.get(key) {
+            Ok(SuccessQuery::String(old_value)) => old_value,
+            other => return other,
+        };
 
-                        while count < 0 && exist_elem {
-                            if let Some(pos) = list.iter().rposition(|x| *x == elem) {
-                                list.remove(pos);
-                                count += 1;
-                            } else {
-                                exist_elem = false;
-                            }
-                        }
+        self.expires().lock().unwrap().remove(key);
+        self.dictionary()
+            .insert(key.to_owned(), StorageValue::String(new_val.to_owned()));
+        self.mark_modified();
+
+        Ok(SuccessQuery::String(old_value))
+    }
+
+    /// Increments the number stored at key by increment.
+    /// If the key does not exist, it is set to 0 before performing the operation.
+    /// An error is returned if the key contains a value of the wrong type or contains a string that can not be represented as integer.
+    /// This operation is limited to 64 bit signed integers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let database = Database::new("dump_path.txt", 16, 16).unwrap();
+    ///
+    /// database.set(KEY, b"1").unwrap();
+    ///
+    /// let result = database.incrby(KEY, 4).unwrap();
+    ///
+    /// assert_eq!(result, SuccessQuery::Integer(5));
+    ///
+    /// ```
+    pub fn incrby(&mut self, key: &[u8], incr: i32) -> Result<SuccessQuery, DataBaseError> {
+        self.decrby(key, -incr)
+    }
+
+    /// Returns the values of all specified keys.
+    /// For every key that does not hold a string value or does not exist, the special value nil is returned.
+    /// Because of this, the operation never fails.
+    /// Returns: list of SuccessQuery::String values at the specified keys.
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.set(b"KEY_A", b"VALUE_A").unwrap();
+    /// database.set(b"KEY_B", b"VALUE_B").unwrap();
+    ///
+    /// let vec_keys = vec![b"KEY_A", b"KEY_B", b"KEY_C", b"KEY_D"];
+    ///
+    /// if let SuccessQuery::List(list) = database.mget(vec_keys).unwrap() {
+    ///     assert_eq!(list[0], SuccessQuery::String(b"VALUE_A".to_vec()));
+    ///     assert_eq!(list[1], SuccessQuery::String(b"VALUE_B".to_vec()));
+    ///     assert_eq!(list[2], SuccessQuery::Nil);
+    ///     assert_eq!(list[3], SuccessQuery::Nil);
+    /// }
+    /// ```
+    pub fn mget(&mut self, params: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        let mut list: Vec<SuccessQuery> = Vec::new();
+
+        for key in params {
+            let r = self.get(key);
+            match r {
+                Err(DataBaseError::NotAString) => {
+                    list.push(SuccessQuery::Nil);
+                }
+                other => {
+                    list.push(other.unwrap());
+                }
+            }
+        }
+
+        Ok(SuccessQuery::List(list))
+    }
+
+    /// Sets the given keys to their respective values. MSET replaces existing values with new values, just as regular SET.
+    ///
+    /// Reply: always OK since MSET can't fail.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    ///
+    /// let vec_key_value = vec![
+    ///     "KEY_A", "VALUE_A", "KEY_B", "VALUE_B", "KEY_C", "VALUE_C", "KEY_D", "VALUE_D",
+    /// ];
+    ///
+    /// let result = database.mset(vec_key_value).unwrap();
+    /// assert_eq!(result, SuccessQuery::Success);
+    ///
+    /// let result_get1 = database.get(b"KEY_A").unwrap();
+    /// let result_get2 = database.get(b"KEY_B").unwrap();
+    /// let result_get3 = database.get(b"KEY_C").unwrap();
+    ///
+    /// assert_eq!(result_get1, SuccessQuery::String(b"VALUE_A".to_vec()));
+    /// assert_eq!(result_get2, SuccessQuery::String(b"VALUE_B".to_vec()));
+    /// assert_eq!(result_get3, SuccessQuery::String(b"VALUE_C".to_vec()));
+    /// ```
+    pub fn mset(&mut self, params: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        for i in (0..params.len()).step_by(2) {
+            let key = params.get(i).unwrap();
+            let value = params.get(i + 1).unwrap();
+
+            let _ = self.set(key, value);
+        }
+        Ok(SuccessQuery::Success)
+    }
+
+    /// Set key to hold the string value.
+    /// If key already holds a value, it is overwritten, regardless of its type.
+    /// Any previous time to live associated with the key is discarded on successful SET operation.
+    ///
+    /// Reply: OK. SET was always executed correctly.
+    ///
+    /// # Example
+    /// ```
+    /// let database = Database::new("dump_path.txt", 16, 16).unwrap();
+    ///
+    /// let result = database.set(b"KEY",b"VALUE").unwrap();
+    /// assert_eq!(SuccessQuery::Success, result);
+    ///
+    /// if let SuccessQuery::String(value) = database.get(b"KEY").unwrap() {
+    ///     assert_eq!(b"VALUE", value);
+    /// }
+    /// ```
+    pub fn set(&mut self, key: &[u8], val: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.expires().lock().unwrap().remove(key);
+        self.dictionary()
+            .insert(key.to_owned(), StorageValue::String(val.to_owned()));
+        self.mark_modified();
+
+        Ok(SuccessQuery::Success)
+    }
+
+    /// Set key to hold the string value, with the options of SET.
+    ///
+    /// With NX the key is only set if it does not exist and with XX only if it does.
+    /// With an expiry the key gets that time to live, with KEEPTTL it keeps the one it
+    /// had and without one any previous time to live is discarded.
+    ///
+    /// Reply: SuccessQuery::Success if the key was set and SuccessQuery::Nil if it
+    /// wasn't because of NX or XX. With GET the old value or SuccessQuery::Nil when
+    /// key did not exist.
+    ///
+    /// Error if the expire time isn't positive or is too big, or if GET is given and
+    /// the old value isn't a string.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let flags = SetFlags { nx: true, expiry: Some(KeyExpiry::Ex(10)), ..SetFlags::default() };
+    ///
+    /// assert_eq!(database.set_with_flags(b"KEY", b"VALUE", flags).unwrap(), SuccessQuery::Success);
+    /// assert_eq!(database.ttl(b"KEY").unwrap(), SuccessQuery::Integer(10));
+    /// ```
+    pub fn set_with_flags(
+        &mut self,
+        key: &[u8],
+        val: &[u8],
+        flags: SetFlags,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let expire_time = self.expire_time_of(flags.expiry)?;
+
+        // The key is checked and set holding the lock of the times to live and the one
+        // of its piece, so two SET NX can't both set it.
+        let expires = self.expires();
+        let mut expires = expires.lock().unwrap();
+        let expired = expires
+            .get(key)
+            .is_some_and(|expire_time| expire_time <= SystemTime::now());
+        if expired {
+            expires.remove(key);
+            self.dictionary().remove(key);
+        }
+
+        let value = StorageValue::String(val.to_owned());
+        let (set, reply) = self.dictionary().insert_if(key.to_owned(), value, |old| {
+            let old_value = match old {
+                _ if !flags.get => Ok(SuccessQuery::Nil),
+                Some(StorageValue::String(old)) => Ok(SuccessQuery::String(old.clone())),
+                Some(_) => Err(DataBaseError::NotAString),
+                None => Ok(SuccessQuery::Nil),
+            };
+            let set =
+                old_value.is_ok() && !(flags.nx && old.is_some() || flags.xx && old.is_none());
+            let reply = old_value.map(|old_value| match old_value {
+                _ if flags.get => old_value,
+                _ if set => SuccessQuery::Success,
+                _ => SuccessQuery::Nil,
+            });
+            (set, (set, reply))
+        });
+        if set {
+            if flags.expiry != Some(KeyExpiry::KeepTtl) {
+                expires.remove(key);
+            }
+            if let Some(expire_time) = expire_time {
+                expires.insert(KeyTtl::new(key, expire_time));
+            }
+            self.mark_modified();
+        }
+        drop(expires);
+        if expired {
+            self.notify_keyspace_event(EventClass::Expired, "expired", key);
+        }
+
+        reply
+    }
+
+    /// Set key to hold string value if key does not exist. When key already holds a
+    /// value, no operation is performed.
+    ///
+    /// Reply: SuccessQuery::Boolean, true if the key was set and false if it wasn't.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    ///
+    /// assert_eq!(database.setnx(b"KEY", b"VALUE").unwrap(), SuccessQuery::Boolean(true));
+    /// assert_eq!(database.setnx(b"KEY", b"OTHER").unwrap(), SuccessQuery::Boolean(false));
+    /// ```
+    pub fn setnx(&mut self, key: &[u8], val: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        let flags = SetFlags {
+            nx: true,
+            ..SetFlags::default()
+        };
+        let set = self.set_with_flags(key, val, flags)? == SuccessQuery::Success;
+
+        Ok(SuccessQuery::Boolean(set))
+    }
+
+    /// Get the value of key and optionally set its expiration. With PERSIST the time
+    /// to live of the key is removed and without an expiry it is left untouched, like
+    /// GET does. A time in the past deletes the key.
+    ///
+    /// Reply: SuccessQuery::String with the value of key, or SuccessQuery::Nil when
+    /// key does not exist.
+    ///
+    /// Error if the value stored at key is not a string, or if the expire time isn't
+    /// positive or is too big.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.set(b"KEY", b"VALUE").unwrap();
+    ///
+    /// database.getex(b"KEY", Some(KeyExpiry::Px(1500))).unwrap();
+    /// assert_eq!(database.ttl(b"KEY").unwrap(), SuccessQuery::Integer(2));
+    /// ```
+    pub fn getex(
+        &mut self,
+        key: &[u8],
+        expiry: Option<KeyExpiry>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let expire_time = self.expire_time_of(expiry)?;
+        let value = self.get(key)?;
+        if value == SuccessQuery::Nil {
+            return Ok(value);
+        }
+
+        match (expiry, expire_time) {
+            (Some(KeyExpiry::Persist), _) => {
+                let removed = self.expires().lock().unwrap().remove(key).is_some();
+                if removed {
+                    self.mark_modified();
+                }
+            }
+            (_, Some(expire_time)) => {
+                self.expire_key_at(key, expire_time, ExpireFlags::default())?;
+            }
+            _ => {}
+        }
+
+        Ok(value)
+    }
+
+    #[doc(hidden)]
+    fn expire_time_of(
+        &self,
+        expiry: Option<KeyExpiry>,
+    ) -> Result<Option<SystemTime>, DataBaseError> {
+        let ms = match expiry {
+            Some(expiry) => expiry.unix_ms(unix_ms(SystemTime::now()))?,
+            None => None,
+        };
+        match ms {
+            Some(ms) => time_from_unix_ms(ms)
+                .map(Some)
+                .ok_or(DataBaseError::InvalidExpireTime),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the length of the string value stored at key.
+    /// An error is returned when key holds a non-string value.
+    ///
+    /// Reply: SuccessQuery::Integer with the length of the string at key, or 0 when key does not exist.
+    /// # Examples
+    /// ```
+    /// let database = Database::new("dump_path.txt", 16, 16).unwrap();
+    ///
+    /// database.set(b"KEY", b"VALUE").unwrap();
+    ///
+    /// if let SuccessQuery::Integer(value) = database.strlen(b"KEY").unwrap() {
+    ///     assert_eq!(value, 5);
+    /// }
+    /// ```
+    pub fn strlen(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        match self.get(key) {
+            Ok(SuccessQuery::String(val)) => Ok(SuccessQuery::Integer(val.len() as i64)),
+            other => other,
+        }
+    }
+
+    // Lists
+
+    /// Returns the element at index in the list stored at key.
+    /// The index is zero-based, so 0 means the first element, 1 the second element and so on.
+    /// Negative indices can be used to designate elements starting at the tail of the list.
+    /// Here, -1 means the last element, -2 means the penultimate and so forth.
+    /// When the value at key is not a list, an error is returned.
+    ///
+    /// Reply: SuccessQuery::String(val) when val is the requested element, or SuccessQuery::Nil when index is out of range.
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    ///
+    /// database.lpush(b"KEY", [b"VALUE"].to_vec()).unwrap();
+    ///
+    /// if let SuccessQuery::String(value) = database.lindex(b"KEY", 0).unwrap() {
+    ///     assert_eq!(value, b"VALUE");
+    /// }
+    /// ```
+    pub fn lindex(&mut self, key: &[u8], index: i32) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Nil);
+        }
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::List(list), stats)) => {
+                let index = if index < 0 {
+                    ((list.len() as i32) + index) as usize
+                } else {
+                    index as usize
+                };
+
+                match list.get(index) {
+                    Some(val) => {
+                        stats.hit();
+                        Ok(SuccessQuery::String(val.clone()))
+                    }
+                    None => Ok(SuccessQuery::Nil),
+                }
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Ok(SuccessQuery::Nil),
+        }
+    }
+
+    /// Returns the length of the list stored at key.
+    /// If key does not exist, it is interpreted as an empty list and 0 is returned.
+    /// An error is returned when the value stored at key is not a list.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the the length of the list at key.
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    ///
+    /// let database = database.lpush(b"KEY", [b"VALUE_A",b"VALUE_B", b"VALUE_C"].to_vec()).unwrap();
+    ///
+    /// let result = database.llen(b"KEY").unwrap();
+    ///
+    /// assert_eq!(result, SuccessQuery::Integer(3));
+    /// ```
+    pub fn llen(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Integer(0));
+        }
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::List(list), stats)) => {
+                stats.hit();
+                Ok(SuccessQuery::Integer(list.len() as i64))
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Ok(SuccessQuery::Integer(0)),
+        }
+    }
+
+    /// Removes and returns the first elements of the list stored at key.
+    /// By default, the command pops a single element from the beginning of the list.
+    /// When provided with the optional count argument, the reply will consist of up to count elements, depending on the list's length.
+    ///
+    /// Reply: SuccessQuery::String(s) when s is the the value of the first element, or SuccessQuery::Nil when key does not exist.
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    ///
+    /// let database = database.rpush(b"KEY", b"VALUE_A").unwrap();
+    /// let database = database.rpush(b"KEY", b"VALUE_B").unwrap();
+    ///
+    /// if let SuccessQuery::String(val) = database.lpop(b"KEY").unwrap() {
+    ///     assert_eq!(val, b"VALUE_A");
+    /// }
+    ///
+    /// let result = database.llen(b"KEY").unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn lpop(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Nil);
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::List(list), stats)) => {
+                stats.hit();
+                if list.is_empty() {
+                    Ok(SuccessQuery::Nil)
+                } else {
+                    self.mark_modified();
+                    Ok(SuccessQuery::String(list.remove(0)))
+                }
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Ok(SuccessQuery::Nil),
+        }
+    }
+
+    #[doc(hidden)]
+    fn lpush_one(&mut self, key: &[u8], value: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            let list: Vec<Vec<u8>> = vec![value.to_owned()];
+            let len = list.len();
+            self.dictionary()
+                .insert(key.to_owned(), StorageValue::List(list));
+            self.mark_modified();
+            return Ok(SuccessQuery::Integer(len as i64));
+        }
+
+        self.dictionary().touch(key);
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::List(list), _)) => {
+                list.insert(0, value.to_owned());
+                self.mark_modified();
+                Ok(SuccessQuery::Integer(list.len() as i64))
+            }
+            _ => Err(DataBaseError::NotAList),
+        }
+    }
+
+    /// Insert all the specified values at the head of the list stored at key.
+    /// If key does not exist, it is created as empty list before performing the push operations.
+    /// When key holds a value that is not a list, an error is returned.
+    ///
+    /// It is possible to push multiple elements using a single command call just specifying multiple arguments at the end of the command.
+    /// Elements are inserted one after the other to the head of the list, from the leftmost element to the rightmost element.
+    /// So for instance the command LPUSH mylist a b c will result into a list containing c as first element, b as second element and a as third element.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the the length of the list after the push operations.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.lpush(b"KEY", [b"VALUEA"].to_vec()).unwrap();
+    ///
+    /// let result = database.lpush(b"KEY", [b"VALUEB"].to_vec()).unwrap();
+    ///
+    /// assert_eq!(result, SuccessQuery::Integer(2));
+    ///
+    /// let dictionary = database.dictionary();
+    /// let dictionary = dictionary.get_atomic_hash(b"KEY");
+    /// let dictionary = dictionary.read().unwrap();
+    ///
+    /// if let StorageValue::List(list) = dictionary.get(b"KEY").unwrap() {
+    ///     assert_eq!(list.len(), 2);
+    ///     assert_eq!(list[1], b"VALUEA");
+    ///     assert_eq!(list[0], b"VALUEB");
+    /// }
+    /// ```
+    pub fn lpush(&mut self, key: &[u8], values: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        let mut result = self.lpush_one(key, values[0]);
+        for item in values.iter().skip(1) {
+            result = self.lpush_one(key, item)
+        }
+
+        result
+    }
+
+    /// Inserts specified value/s at the head of the list stored at key, only if key already exists and holds a list.
+    /// In contrary to LPUSH, no operation will be performed when key does not yet exist.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the the length of the list after the push operation.
+    ///
+    /// n=0 if key not hold a list and not push data.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.lpushx(b"KEY", [b"VALUEA"].to_vec()).unwrap();
+    ///
+    /// assert_eq!(result, SuccessQuery::Integer(0));
+    /// ```
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.lpush(b"KEY", [b"VALUEA"].to_vec()).unwrap();
+    /// database.lpushx(b"KEY", [b"VALUEB"].to_vec()).unwrap();
+    /// let dictionary = database.dictionary();
+    /// let dictionary = dictionary.get_atomic_hash(b"KEY");
+    /// let dictionary = dictionary.read().unwrap();
+    ///
+    /// if let StorageValue::List(list) = dictionary.get(b"KEY").unwrap() {
+    ///     assert_eq!(list.len(), 2);
+    ///     assert_eq!(list[1], b"VALUEA");
+    ///     assert_eq!(list[0], b"VALUEB");
+    /// }
+    /// ```
+    pub fn lpushx(
+        &mut self,
+        key: &[u8],
+        values: Vec<&[u8]>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Integer(0));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::List(list), stats)) => {
+                stats.hit();
+                values.iter().for_each(|&val| {
+                    list.insert(0, val.to_owned());
+                });
+                self.mark_modified();
+                Ok(SuccessQuery::Integer(list.len() as i64))
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Ok(SuccessQuery::Integer(0)),
+        }
+    }
+
+    /// Returns the specified elements of the list stored at key.
+    /// The offsets start and stop are zero-based indexes, with 0 being the first element of the list (the head of the list),
+    /// 1 being the next element and so on.
+    ///
+    /// These offsets can also be negative numbers indicating offsets starting at the end of the list.
+    /// For example, -1 is the last element of the list, -2 the penultimate, and so on.
+    ///
+    /// Reply: SuccessQuery::List(list) when list is the list of elements in the specified range.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.lpush(b"KEY", [b"VALUEA",b"VALUEB", b"VALUEC",b"VALUED"].to_vec()).unwrap();
+    ///
+    /// if let SuccessQuery::List(list) = database.lrange(b"KEY", 0, 2).unwrap() {
+    ///     let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+    ///     let second_list: Vec<&[u8]> = vec![b"VALUED", b"VALUEC", b"VALUEB"];
+    ///     let pair_list: Vec<(&String, &str)> = list.iter().zip(second_list).collect();
+    ///     pair_list.iter().for_each(|x| {
+    ///         assert_eq!(x.0, x.1);
+    ///     })
+    /// }
+    /// ```
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.lpush(b"KEY", [b"VALUEA",b"VALUEB", b"VALUEC",b"VALUED"].to_vec()).unwrap();
+    /// if let SuccessQuery::List(list) = database.lrange(b"KEY", 0, -1).unwrap() {
+    ///     let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+    ///     let second_list: Vec<&[u8]> = vec![b"VALUED", b"VALUEC", b"VALUEB", b"VALUEA"];
+    ///     let pair_list: Vec<(&String, &str)> = list.iter().zip(second_list).collect();
+    ///     pair_list.iter().for_each(|x| {
+    ///         assert_eq!(x.0, x.1);
+    ///     })
+    /// }
+    /// ```
+    pub fn lrange(
+        &mut self,
+        key: &[u8],
+        ini: i32,
+        end: i32,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let mut sub_list: Vec<SuccessQuery> = Vec::new();
+        if !self._exists(key) {
+            return Ok(SuccessQuery::List(sub_list));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+
+        match dictionary.get(key) {
+            Some((StorageValue::List(list), stats)) => {
+                let len = list.len() as i32;
+                let ini = if ini < 0 {
+                    (len + ini) as usize
+                } else {
+                    ini as usize
+                };
+                let end = if end < 0 {
+                    (len + end) as usize
+                } else {
+                    end as usize
+                };
+
+                if end < len as usize && ini <= end {
+                    for elem in list[ini..(end + 1)].iter() {
+                        sub_list.push(SuccessQuery::String(elem.clone()));
+                    }
+                }
+
+                stats.hit();
+                Ok(SuccessQuery::List(sub_list))
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Ok(SuccessQuery::List(sub_list)),
+        }
+    }
+
+    /// Removes the first count occurrences of elements equal to element from the list stored at key.
+    /// The count argument influences the operation in the following ways:
+    ///
+    /// count > 0: Remove elements equal to element moving from head to tail.
+    ///
+    /// count < 0: Remove elements equal to element moving from tail to head.
+    ///
+    /// count = 0: Remove all elements equal to element.
+    ///
+    /// For example, LREM list -2 "hello" will remove the last two occurrences of "hello" in the list stored at list.
+    ///
+    /// Note that non-existing keys are treated like empty lists, so when key does not exist, the command will always return 0.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of removed elements.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.lpush(b"KEY", [b"VALUEA", b"VALUEA", b"VALUEC", b"VALUEA"].to_vec()).unwrap();
+    ///
+    /// let result = database.lrem(b"KEY", 2, b"VALUEA");
+    ///
+    /// assert_eq!(SuccessQuery::Integer(2), result.unwrap());
+    ///
+    /// let dictionary = database.dictionary();
+    /// let dictionary = dictionary.get_atomic_hash(b"KEY");
+    /// let dictionary = dictionary.read().unwrap();
+    ///
+    /// if let Some(StorageValue::List(list)) = dictionary.get(b"KEY") {
+    ///     assert_eq!(list[0], b"VALUEC");
+    ///     assert_eq!(list[1], b"VALUEA");
+    /// }
+    /// ```
+    pub fn lrem(
+        &mut self,
+        key: &[u8],
+        mut count: i32,
+        elem: &[u8],
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Integer(0));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::List(list), stats)) => {
+                stats.hit();
+                let len = list.len();
+                match count.cmp(&0) {
+                    Ordering::Greater => {
+                        let mut exist_elem = true;
+
+                        while count > 0 && exist_elem {
+                            if let Some(pos) = list.iter().position(|x| *x == elem) {
+                                list.remove(pos);
+                                count -= 1;
+                            } else {
+                                exist_elem = false;
+                            }
+                        }
+                    }
+                    Ordering::Less => {
+                        let mut exist_elem = true;
+
+                        while count < 0 && exist_elem {
+                            if let Some(pos) = list.iter().rposition(|x| *x == elem) {
+                                list.remove(pos);
+                                count += 1;
+                            } else {
+                                exist_elem = false;
+                            }
+                        }
+                    }
+                    Ordering::Equal => {
+                        list.retain(|x| *x != elem);
+                    }
+                }
+                if list.len() != len {
+                    self.mark_modified();
+                }
+
+                Ok(SuccessQuery::Integer(list.len() as i64))
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Ok(SuccessQuery::Integer(0)),
+        }
+    }
+
+    /// Sets the list element at index to element. For more information on the index argument, see lindex.
+    ///
+    /// An error is returned for out of range indexes.
+    ///
+    /// Reply: SuccessQuery::Success if set the list element correctly.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.lpush(KEY, [b"VALUEA", b"VALUEB", b"VALUEC", b"VALUED"].to_vec()).unwrap();
+    ///
+    /// let result = database.lset(b"KEY", 0, b"VALUEA");
+    /// assert_eq!(SuccessQuery::Success, result.unwrap());
+    ///
+    /// let dictionary = database.dictionary();
+    /// let dictionary = dictionary.get_atomic_hash(b"KEY");
+    /// let dictionary = dictionary.read().unwrap();
+    ///
+    /// if let StorageValue::List(list) = dictionary.get(b"KEY").unwrap() {
+    ///     assert_eq!(list[0], b"VALUEA");
+    /// }
+    /// ```
+    pub fn lset(
+        &mut self,
+        key: &[u8],
+        index: usize,
+        value: &[u8],
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Err(DataBaseError::NonExistentKey);
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::List(list), stats)) => {
+                stats.hit();
+                match list.get_mut(index) {
+                    Some(val) => {
+                        val.clear();
+                        val.extend_from_slice(value);
+                        self.mark_modified();
+                        Ok(SuccessQuery::Success)
+                    }
+                    None => Err(DataBaseError::IndexOutOfRange),
+                }
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Err(DataBaseError::NonExistentKey),
+        }
+    }
+
+    /// Removes and returns the last elements of the list stored at key.
+    /// By default, the command pops a single element from the end of the list.
+    ///
+    /// Reply: SuccessQuery::String(val) when val is the value of the last element, or SuccessQuery::Nil when key does not exist.
+    ///
+    /// # Examples
+    /// ```
+    /// todo
+    /// ```
+    pub fn rpop(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Nil);
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::List(list), stats)) => {
+                stats.hit();
+                match list.pop() {
+                    Some(value) => {
+                        self.mark_modified();
+                        Ok(SuccessQuery::String(value))
+                    }
+                    None => Ok(SuccessQuery::Nil),
+                }
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Ok(SuccessQuery::Nil),
+        }
+    }
+
+    /// Insert all the specified values at the tail of the list stored at key.
+    /// If key does not exist, it is created as empty list before performing the push operation.
+    /// When key holds a value that is not a list, an error is returned.
+    ///
+    /// It is possible to push multiple elements using a single command call just specifying multiple arguments at the end of the command.
+    /// Elements are inserted one after the other to the tail of the list, from the leftmost element to the rightmost element.
+    ///
+    /// So for instance the command RPUSH mylist a b c will result into a list containing a as first element, b as second element and c as third element.
+    ///
+    /// Reply: the length of the list after the push operation.
+    ///
+    /// # Examples
+    /// ```
+    /// todo
+    /// ```
+    pub fn rpush(&mut self, key: &[u8], values: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            let mut list: Vec<Vec<u8>> = Vec::new();
+            values.iter().for_each(|&val| {
+                list.push(val.to_owned());
+            });
+
+            let len = list.len();
+            self.dictionary()
+                .insert(key.to_owned(), StorageValue::List(list));
+            self.mark_modified();
+            return Ok(SuccessQuery::Integer(len as i64));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::List(list), stats)) => {
+                stats.hit();
+                values.iter().for_each(|&val| {
+                    list.push(val.to_owned());
+                });
+                self.mark_modified();
+                Ok(SuccessQuery::Integer(list.len() as i64))
+            }
+            _ => Err(DataBaseError::NotAList),
+        }
+    }
+
+    /// Inserts specified values at the tail of the list stored at key, only if key already exists and holds a list.
+    /// In contrary to RPUSH, no operation will be performed when key does not yet exist.
+    ///
+    /// Reply: SuccessQuery:Integer(n) when n is the length of the list after the push operation.
+    ///
+    /// # Examples
+    /// ```
+    /// todo
+    /// ```
+    pub fn rpushx(
+        &mut self,
+        key: &[u8],
+        values: Vec<&[u8]>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Boolean(false));
+        }
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::List(list), stats)) => {
+                stats.hit();
+                values.iter().for_each(|&val| {
+                    list.push(val.to_owned());
+                });
+                self.mark_modified();
+                Ok(SuccessQuery::Integer(list.len() as i64))
+            }
+            Some(_) => Err(DataBaseError::NotAList),
+            None => Ok(SuccessQuery::Boolean(false)),
+        }
+    }
+
+    //SETS
+
+    /// Returns if member is a member of the set stored at key.
+    ///
+    /// Reply: SuccessQuery::Boolean(true) if the element is a member of the set.
+    ///
+    /// SuccessQuery::Boolean(false) if the element is not a member of the set, or if key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let result = database.sadd(b"key", [b"element"].to_vec()).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// let is_member = database.sismember(b"key", b"element").unwrap();
+    /// assert_eq!(is_member, SuccessQuery::Boolean(true));
+    /// ```
+    pub fn sismember(&mut self, key: &[u8], value: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Boolean(false));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::Set(hash_set), stats)) => {
+                stats.hit();
+                match hash_set.get(value) {
+                    Some(_val) => Ok(SuccessQuery::Boolean(true)),
+                    None => Ok(SuccessQuery::Boolean(false)),
+                }
+            }
+            Some(_) => Err(DataBaseError::NotASet),
+            None => Ok(SuccessQuery::Boolean(false)),
+        }
+    }
+
+    /// Returns the set cardinality (number of elements) of the set stored at key.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the cardinality (number of elements) of the set,
+    /// or SuccessQuery::Boolean(false) if key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let elements = vec![b"0", b"1", b"2", b"3"];
+    ///
+    /// let _ = database.sadd(b"key", elements);
+    /// let len_set = database.scard(b"key").unwrap();
+    ///
+    /// assert_eq!(len_set, SuccessQuery::Integer(4));
+    /// ```
+    pub fn scard(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Boolean(false));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::Set(hash_set), stats)) => {
+                stats.hit();
+                Ok(SuccessQuery::Integer(hash_set.len() as i64))
+            }
+            Some(_) => Err(DataBaseError::NotASet),
+            None => Ok(SuccessQuery::Boolean(false)),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn sadd_one(&mut self, key: &[u8], value: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            let mut set: HashSet<Vec<u8>> = HashSet::new();
+            set.insert(value.to_owned());
+            self.dictionary()
+                .insert(key.to_owned(), StorageValue::Set(set));
+            self.mark_modified();
+            return Ok(SuccessQuery::Integer(1));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::Set(hash_set), stats)) => {
+                stats.hit();
+                if hash_set.contains(value) {
+                    Ok(SuccessQuery::Integer(0))
+                } else {
+                    hash_set.insert(value.to_owned());
+                    self.mark_modified();
+                    Ok(SuccessQuery::Integer(1))
+                }
+            }
+            _ => Err(DataBaseError::NotASet),
+        }
+    }
+
+    /// Add the specified members to the set stored at key. Specified members that are already a member of this set are ignored.
+    /// If key does not exist, a new set is created before adding the specified members.
+    /// An error is returned when the value stored at key is not a set.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of elements that were added to the set,
+    /// not including all the elements already present in the set.
+    ///
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.sadd(b"KEY", [b"ELEMENT"].to_vec()).unwrap();
+    /// let result = database.sadd(b"KEY", [b"ELEMENT", b"ELEMENT_2", b"ELEMENT_3"].to_vec()).unwrap();
+    ///
+    /// assert_eq!(result, SuccessQuery::Integer(2));
+    /// let len_set = database.scard(b"KEY").unwrap();
+    /// assert_eq!(len_set, SuccessQuery::Integer(3));
+    /// ```
+    pub fn sadd(&mut self, key: &[u8], values: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        let mut elems_added = 0;
+        let mut result = self.sadd_one(key, values[0]);
+        if let Ok(SuccessQuery::Integer(val)) = result {
+            elems_added += val;
+        }
+        for item in values.iter().skip(1) {
+            result = self.sadd_one(key, item);
+            if let Ok(SuccessQuery::Integer(val)) = result {
+                elems_added += val;
+            }
+        }
+        match result {
+            Ok(SuccessQuery::Integer(_val)) => Ok(SuccessQuery::Integer(elems_added)),
+            Err(err) => Err(err),
+            _ => Err(DataBaseError::NotASet),
+        }
+    }
+
+    /// Returns all the members of the set value stored at key.
+    ///
+    /// Reply: SuccessQuery::List(list) when list are all elements of the set.
+    ///
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.sadd(b"KEY", [b"ELEMENT"].to_vec()).unwrap();
+    /// database.sadd(b"KEY", [b"OTHER_ELEMENT"].to_vec()).unwrap();
+    ///
+    /// if let SuccessQuery::List(list) = database.smembers(b"KEY").unwrap() {
+    ///     for elem in list {
+    ///         let is_member = database.sismember(b"KEY", &elem.to_string()).unwrap();
+    ///         assert_eq!(is_member, SuccessQuery::Boolean(true));
+    ///     }
+    /// }
+    /// ```
+    pub fn smembers(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        let mut result: Vec<SuccessQuery> = Vec::new();
+        if !self._exists(key) {
+            return Ok(SuccessQuery::List(result));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::Set(hash_set), stats)) => {
+                stats.hit();
+                for elem in hash_set.iter() {
+                    result.push(SuccessQuery::String(elem.clone()));
+                }
+                Ok(SuccessQuery::List(result))
+            }
+            Some(_) => Err(DataBaseError::NotASet),
+            None => Ok(SuccessQuery::List(result)),
+        }
+    }
+
+    /// Remove the specified members from the set stored at key.
+    /// Specified members that are not a member of this set are ignored.
+    ///
+    /// If key does not exist, it is treated as an empty set and this command returns 0.
+    /// An error is returned when the value stored at key is not a set.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members that were removed from the set, not including non existing members.
+    ///
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let members = vec![b"ELEMENT"];
+    ///
+    /// database.sadd(b"KEY", [b"ELEMENT"].to_vec()).unwrap();
+    /// let result = database.srem(b"KEY", members).unwrap();
+    /// let is_member = database.sismember(b"KEY", b"ELEMENT").unwrap();
+    ///
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// assert_eq!(is_member, SuccessQuery::Boolean(false));
+    /// ```
+    pub fn srem(
+        &mut self,
+        key: &[u8],
+        members_to_rmv: Vec<&[u8]>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Boolean(false));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        match dictionary.get_mut(key) {
+            Some((StorageValue::Set(hash_set), stats)) => {
+                stats.hit();
+                let mut count: i32 = 0;
+                for member in members_to_rmv {
+                    if let Some(_mem) = hash_set.take(member) {
+                        count += 1;
+                    }
+                }
+                if count > 0 {
+                    self.mark_modified();
+                }
+                Ok(SuccessQuery::Integer(count as i64))
+            }
+            Some(_) => Err(DataBaseError::NotASet),
+            None => Ok(SuccessQuery::Boolean(false)),
+        }
+    }
+
+    //HASHES
+
+    #[doc(hidden)]
+    fn read_hash<T, F>(&mut self, key: &[u8], default: T, read: F) -> Result<T, DataBaseError>
+    where
+        F: FnOnce(&HashMap<Vec<u8>, Vec<u8>>) -> T,
+    {
+        if !self._exists(key) {
+            return Ok(default);
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::Hash(hash), stats)) => {
+                stats.hit();
+                Ok(read(hash))
+            }
+            Some(_) => Err(DataBaseError::NotAHash),
+            None => Ok(default),
+        }
+    }
+
+    /// Runs write on the hash at key, created if it doesn't exist, which returns the
+    /// reply and whether it changed the hash. A hash left empty is removed.
+    #[doc(hidden)]
+    fn write_hash<T, F>(&mut self, key: &[u8], write: F) -> Result<T, DataBaseError>
+    where
+        F: FnOnce(&mut HashMap<Vec<u8>, Vec<u8>>) -> Result<(T, bool), DataBaseError>,
+    {
+        if !self._exists(key) {
+            self.dictionary()
+                .insert(key.to_owned(), StorageValue::Hash(HashMap::new()));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        let (result, is_empty) = match dictionary.get_mut(key) {
+            Some((StorageValue::Hash(hash), stats)) => {
+                stats.hit();
+                let result = write(hash);
+                (result, hash.is_empty())
+            }
+            _ => return Err(DataBaseError::NotAHash),
+        };
+
+        drop(dictionary);
+        if is_empty {
+            self.remove_key(key);
+        }
+
+        let (result, changed) = result?;
+        if changed {
+            self.mark_modified();
+        }
+        Ok(result)
+    }
+
+    /// Sets the specified fields to their respective values in the hash stored at key.
+    /// Fields and values are passed in pairs: field value [field value ...].
+    ///
+    /// If key does not exist, a new key holding a hash is created. Fields already
+    /// present in the hash are overwritten.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of fields that were added.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let result = database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// let result = database.hset(b"key", vec![b"field", b"other", b"field2", b"value"]).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn hset(&mut self, key: &[u8], params: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        self.write_hash(key, |hash| {
+            let mut added = 0;
+            for pair in params.chunks(2) {
+                if let [field, value] = pair {
+                    if hash.insert(field.to_vec(), value.to_vec()).is_none() {
+                        added += 1;
+                    }
+                }
+            }
+            Ok((SuccessQuery::Integer(added), true))
+        })
+    }
+
+    /// Sets field in the hash stored at key to value, only if field does not yet exist.
+    ///
+    /// Reply: SuccessQuery::Boolean(true) if field is a new field in the hash and value was set.
+    ///
+    /// SuccessQuery::Boolean(false) if field already exists in the hash and no operation was performed.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hsetnx(b"key", b"field", b"value").unwrap();
+    /// let result = database.hsetnx(b"key", b"field", b"other").unwrap();
+    /// assert_eq!(result, SuccessQuery::Boolean(false));
+    /// ```
+    pub fn hsetnx(
+        &mut self,
+        key: &[u8],
+        field: &[u8],
+        value: &[u8],
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.write_hash(key, |hash| {
+            if hash.contains_key(field) {
+                Ok((SuccessQuery::Boolean(false), false))
+            } else {
+                hash.insert(field.to_vec(), value.to_vec());
+                Ok((SuccessQuery::Boolean(true), true))
+            }
+        })
+    }
+
+    /// Returns the value associated with field in the hash stored at key.
+    ///
+    /// Reply: SuccessQuery::String(value) with the value associated with field,
+    /// or SuccessQuery::Nil when field is not present in the hash or key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hget(b"key", b"field").unwrap();
+    /// assert_eq!(result, SuccessQuery::String(b"value".to_vec()));
+    /// ```
+    pub fn hget(&mut self, key: &[u8], field: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_hash(key, SuccessQuery::Nil, |hash| match hash.get(field) {
+            Some(value) => SuccessQuery::String(value.clone()),
+            None => SuccessQuery::Nil,
+        })
+    }
+
+    /// Returns the values associated with the specified fields in the hash stored at key.
+    ///
+    /// Reply: SuccessQuery::List(list) with a value, or SuccessQuery::Nil, for every field
+    /// in the same order as they are requested.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hmget(b"key", vec![b"field", b"nofield"]).unwrap();
+    /// assert_eq!(
+    ///     result,
+    ///     SuccessQuery::List(vec![SuccessQuery::String(b"value".to_vec()), SuccessQuery::Nil])
+    /// );
+    /// ```
+    pub fn hmget(&mut self, key: &[u8], fields: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        let missing = fields.iter().map(|_| SuccessQuery::Nil).collect();
+        self.read_hash(key, SuccessQuery::List(missing), |hash| {
+            let values = fields
+                .iter()
+                .map(|field| match hash.get(*field) {
+                    Some(value) => SuccessQuery::String(value.clone()),
+                    None => SuccessQuery::Nil,
+                })
+                .collect();
+            SuccessQuery::List(values)
+        })
+    }
+
+    /// Returns all fields and values of the hash stored at key. Every field name
+    /// is followed by its value.
+    ///
+    /// Reply: SuccessQuery::List(list) with the fields and values, or an empty list when key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hgetall(b"key").unwrap();
+    /// assert_eq!(
+    ///     result,
+    ///     SuccessQuery::List(vec![
+    ///         SuccessQuery::String(b"field".to_vec()),
+    ///         SuccessQuery::String(b"value".to_vec())
+    ///     ])
+    /// );
+    /// ```
+    pub fn hgetall(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_hash(key, SuccessQuery::List(Vec::new()), |hash| {
+            let mut result = Vec::new();
+            for (field, value) in hash.iter() {
+                result.push(SuccessQuery::String(field.clone()));
+                result.push(SuccessQuery::String(value.clone()));
+            }
+            SuccessQuery::List(result)
+        })
+    }
+
+    /// Removes the specified fields from the hash stored at key. Specified fields that
+    /// do not exist within this hash are ignored. When the hash is left empty the key is removed.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of fields that were removed.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hdel(b"key", vec![b"field", b"nofield"]).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn hdel(&mut self, key: &[u8], fields: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Integer(0));
+        }
+
+        self.write_hash(key, |hash| {
+            let removed = fields
+                .iter()
+                .filter(|field| hash.remove(**field).is_some())
+                .count();
+            Ok((SuccessQuery::Integer(removed as i64), removed > 0))
+        })
+    }
+
+    /// Returns if field is an existing field in the hash stored at key.
+    ///
+    /// Reply: SuccessQuery::Boolean(true) if the hash contains field.
+    ///
+    /// SuccessQuery::Boolean(false) if the hash does not contain field, or key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hexists(b"key", b"field").unwrap();
+    /// assert_eq!(result, SuccessQuery::Boolean(true));
+    /// ```
+    pub fn hexists(&mut self, key: &[u8], field: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_hash(key, SuccessQuery::Boolean(false), |hash| {
+            SuccessQuery::Boolean(hash.contains_key(field))
+        })
+    }
+
+    /// Returns the number of fields contained in the hash stored at key.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of fields, or 0 when key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value", b"field2", b"value"]).unwrap();
+    /// let result = database.hlen(b"key").unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(2));
+    /// ```
+    pub fn hlen(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_hash(key, SuccessQuery::Integer(0), |hash| {
+            SuccessQuery::Integer(hash.len() as i64)
+        })
+    }
+
+    /// Returns all field names in the hash stored at key.
+    ///
+    /// Reply: SuccessQuery::List(list) with the fields, or an empty list when key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hkeys(b"key").unwrap();
+    /// assert_eq!(result, SuccessQuery::List(vec![SuccessQuery::String(b"field".to_vec())]));
+    /// ```
+    pub fn hkeys(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_hash(key, SuccessQuery::List(Vec::new()), |hash| {
+            SuccessQuery::List(
+                hash.keys()
+                    .map(|field| SuccessQuery::String(field.clone()))
+                    .collect(),
+            )
+        })
+    }
+
+    /// Returns all values in the hash stored at key.
+    ///
+    /// Reply: SuccessQuery::List(list) with the values, or an empty list when key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hvals(b"key").unwrap();
+    /// assert_eq!(result, SuccessQuery::List(vec![SuccessQuery::String(b"value".to_vec())]));
+    /// ```
+    pub fn hvals(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_hash(key, SuccessQuery::List(Vec::new()), |hash| {
+            SuccessQuery::List(
+                hash.values()
+                    .map(|value| SuccessQuery::String(value.clone()))
+                    .collect(),
+            )
+        })
+    }
+
+    /// Returns the length of the value associated with field in the hash stored at key.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the length of the value, or 0 when field
+    /// or key do not exist.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hstrlen(b"key", b"field").unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(5));
+    /// ```
+    pub fn hstrlen(&mut self, key: &[u8], field: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_hash(key, SuccessQuery::Integer(0), |hash| {
+            match hash.get(field) {
+                Some(value) => SuccessQuery::Integer(value.len() as i64),
+                None => SuccessQuery::Integer(0),
+            }
+        })
+    }
+
+    /// Increments the number stored at field in the hash stored at key by increment.
+    /// If key or field do not exist they are created holding 0 before the operation.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the value at field after the increment.
+    ///
+    /// Error if key of database exists but not hold a Hash, if the field doesn't hold
+    /// an integer or if the operation would overflow.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"5"]).unwrap();
+    /// let result = database.hincrby(b"key", b"field", -10).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(-5));
+    /// ```
+    pub fn hincrby(
+        &mut self,
+        key: &[u8],
+        field: &[u8],
+        incr: i32,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.write_hash(key, |hash| {
+            let current = match hash.get(field) {
+                Some(value) => parse_bytes::<i32>(value).ok_or(DataBaseError::NotAnInteger)?,
+                None => 0,
+            };
+            let result = current.checked_add(incr).ok_or(DataBaseError::Overflow)?;
+            hash.insert(field.to_vec(), result.to_string().into_bytes());
+            Ok((SuccessQuery::Integer(result as i64), true))
+        })
+    }
+
+    /// Increments the floating point number stored at field in the hash stored at key
+    /// by increment. If key or field do not exist they are created holding 0 before the operation.
+    ///
+    /// Reply: SuccessQuery::String(value) with the value at field after the increment.
+    ///
+    /// Error if key of database exists but not hold a Hash, if the field doesn't hold
+    /// a number or if the result is not a finite number.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"10.5"]).unwrap();
+    /// let result = database.hincrbyfloat(b"key", b"field", 0.1).unwrap();
+    /// assert_eq!(result, SuccessQuery::String(b"10.6".to_vec()));
+    /// ```
+    pub fn hincrbyfloat(
+        &mut self,
+        key: &[u8],
+        field: &[u8],
+        incr: f64,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.write_hash(key, |hash| {
+            let current = match hash.get(field) {
+                Some(value) => match parse_bytes::<f64>(value) {
+                    Some(number) if number.is_finite() => number,
+                    _ => return Err(DataBaseError::NotAFloat),
+                },
+                None => 0.0,
+            };
+            let result = current + incr;
+            if !result.is_finite() {
+                return Err(DataBaseError::NotAFloat);
+            }
+            let result = format_float(result).into_bytes();
+            hash.insert(field.to_vec(), result.clone());
+            Ok((SuccessQuery::String(result), true))
+        })
+    }
+
+    /// Returns random fields from the hash stored at key.
+    ///
+    /// Without count returns a single field, or SuccessQuery::Nil when key does not exist.
+    /// With a positive count returns up to count distinct fields; with a negative one
+    /// returns exactly -count fields, which may repeat. With values, every field is
+    /// followed by its value.
+    ///
+    /// Reply: SuccessQuery::String(field), SuccessQuery::Nil or SuccessQuery::List(list).
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hrandfield(b"key", None, false).unwrap();
+    /// assert_eq!(result, SuccessQuery::String(b"field".to_vec()));
+    /// ```
+    pub fn hrandfield(
+        &mut self,
+        key: &[u8],
+        count: Option<i32>,
+        with_values: bool,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let default = match count {
+            Some(_) => SuccessQuery::List(Vec::new()),
+            None => SuccessQuery::Nil,
+        };
+
+        self.read_hash(key, default, |hash| {
+            let mut pairs: Vec<(&Vec<u8>, &Vec<u8>)> = hash.iter().collect();
+            let count = match count {
+                Some(count) => count,
+                None => {
+                    let (field, _) = pairs[random_number() as usize % pairs.len()];
+                    return SuccessQuery::String(field.clone());
+                }
+            };
+
+            let chosen: Vec<(&Vec<u8>, &Vec<u8>)> = if count >= 0 {
+                let count = (count as usize).min(pairs.len());
+                for i in 0..count {
+                    let j = i + random_number() as usize % (pairs.len() - i);
+                    pairs.swap(i, j);
+                }
+                pairs.truncate(count);
+                pairs
+            } else {
+                (0..count.unsigned_abs())
+                    .map(|_| pairs[random_number() as usize % pairs.len()])
+                    .collect()
+            };
+
+            let mut result = Vec::new();
+            for (field, value) in chosen {
+                result.push(SuccessQuery::String(field.clone()));
+                if with_values {
+                    result.push(SuccessQuery::String(value.clone()));
+                }
+            }
+            SuccessQuery::List(result)
+        })
+    }
+
+    /// Incrementally iterates the fields of the hash stored at key.
+    ///
+    /// Every call returns the cursor to use in the next call and up to count fields,
+    /// each followed by its value. The iteration starts with cursor 0 and ends when the
+    /// returned cursor is 0. Fields present during the whole iteration are returned at
+    /// least once. When a pattern is given, only matching fields are returned.
+    ///
+    /// Reply: SuccessQuery::List(list) with the next cursor and the list of fields and values.
+    ///
+    /// Error if key of database exists but not hold a Hash.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.hset(b"key", vec![b"field", b"value"]).unwrap();
+    /// let result = database.hscan(b"key", 0, None, 10).unwrap();
+    /// assert_eq!(
+    ///     result,
+    ///     SuccessQuery::List(vec![
+    ///         SuccessQuery::String(b"0".to_vec()),
+    ///         SuccessQuery::List(vec![
+    ///             SuccessQuery::String(b"field".to_vec()),
+    ///             SuccessQuery::String(b"value".to_vec())
+    ///         ])
+    ///     ])
+    /// );
+    /// ```
+    pub fn hscan(
+        &mut self,
+        key: &[u8],
+        cursor: u64,
+        pattern: Option<&[u8]>,
+        count: usize,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let default = SuccessQuery::List(vec![
+            SuccessQuery::String(b"0".to_vec()),
+            SuccessQuery::List(Vec::new()),
+        ]);
+
+        self.read_hash(key, default, |hash| {
+            let mut pending: Vec<(u64, &Vec<u8>, &Vec<u8>)> = hash
+                .iter()
+                .map(|(field, value)| (scan_position(field), field, value))
+                .filter(|(position, _, _)| *position >= cursor)
+                .collect();
+            pending.sort_by_key(|(position, _, _)| *position);
+
+            let count = count.max(1);
+            let next_cursor = if pending.len() > count {
+                pending[count].0
+            } else {
+                0
+            };
+
+            let mut result = Vec::new();
+            for (_, field, value) in pending.into_iter().take(count) {
+                if pattern.is_none_or(|pattern| matcher(field, pattern)) {
+                    result.push(SuccessQuery::String(field.clone()));
+                    result.push(SuccessQuery::String(value.clone()));
+                }
+            }
+
+            SuccessQuery::List(vec![
+                SuccessQuery::String(next_cursor.to_string().into_bytes()),
+                SuccessQuery::List(result),
+            ])
+        })
+    }
+
+    //SORTED SETS
+
+    #[doc(hidden)]
+    fn read_zset<T, F>(&mut self, key: &[u8], default: T, read: F) -> Result<T, DataBaseError>
+    where
+        F: FnOnce(&SortedSet) -> T,
+    {
+        if !self._exists(key) {
+            return Ok(default);
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::Zset(sorted_set), stats)) => {
+                stats.hit();
+                Ok(read(sorted_set))
+            }
+            Some(_) => Err(DataBaseError::NotAZset),
+            None => Ok(default),
+        }
+    }
+
+    /// Runs write on the sorted set at key, created if it doesn't exist, which returns
+    /// the reply and whether it changed the sorted set. A sorted set left empty is removed.
+    #[doc(hidden)]
+    fn write_zset<T, F>(&mut self, key: &[u8], write: F) -> Result<T, DataBaseError>
+    where
+        F: FnOnce(&mut SortedSet) -> Result<(T, bool), DataBaseError>,
+    {
+        if !self._exists(key) {
+            self.dictionary()
+                .insert(key.to_owned(), StorageValue::Zset(SortedSet::new()));
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let mut dictionary = dictionary.write().unwrap();
+        let (result, is_empty) = match dictionary.get_mut(key) {
+            Some((StorageValue::Zset(sorted_set), stats)) => {
+                stats.hit();
+                let result = write(sorted_set);
+                (result, sorted_set.is_empty())
+            }
+            _ => return Err(DataBaseError::NotAZset),
+        };
+
+        drop(dictionary);
+        if is_empty {
+            self.remove_key(key);
+        }
+
+        let (result, changed) = result?;
+        if changed {
+            self.mark_modified();
+        }
+        Ok(result)
+    }
+
+    /// Adds all the specified members with the specified scores to the sorted set stored at key.
+    /// If a specified member is already a member of the sorted set, the score is updated
+    /// and the element reinserted at the right position to ensure the correct ordering.
+    ///
+    /// If key does not exist, a new sorted set with the specified members is created.
+    /// ZaddFlags changes which members are added or updated, see ZaddFlags.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members added, or added and
+    /// changed with CH. With INCR, SuccessQuery::String(score) with the new score of the member,
+    /// or SuccessQuery::Nil when the operation was aborted by a flag.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// let result = database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(2));
+    /// ```
+    pub fn zadd(
+        &mut self,
+        key: &[u8],
+        flags: ZaddFlags,
+        pairs: Vec<(f64, &[u8])>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.write_zset(key, |sorted_set| {
+            if flags.incr {
+                let (incr, member) = pairs[0];
+                let current = sorted_set.score(member);
+                if (flags.nx && current.is_some()) || (flags.xx && current.is_none()) {
+                    return Ok((SuccessQuery::Nil, false));
+                }
+
+                let score = current.unwrap_or(0.0) + incr;
+                if score.is_nan() {
+                    return Err(DataBaseError::NotANumber);
+                }
+                if let Some(current) = current {
+                    if (flags.gt && score <= current) || (flags.lt && score >= current) {
+                        return Ok((SuccessQuery::Nil, false));
+                    }
+                }
+
+                sorted_set.insert(member, score);
+                let score = format_float(score).into_bytes();
+                return Ok((SuccessQuery::String(score), true));
+            }
+
+            let mut count = 0;
+            let mut changed = false;
+            for (score, member) in pairs {
+                match sorted_set.score(member) {
+                    None if !flags.xx => {
+                        sorted_set.insert(member, score);
+                        count += 1;
+                        changed = true;
+                    }
+                    None => {}
+                    Some(current) => {
+                        if flags.nx
+                            || current == score
+                            || (flags.gt && score <= current)
+                            || (flags.lt && score >= current)
+                        {
+                            continue;
+                        }
+                        sorted_set.insert(member, score);
+                        changed = true;
+                        if flags.ch {
+                            count += 1;
+                        }
+                    }
+                }
+            }
+            Ok((SuccessQuery::Integer(count), changed))
+        })
+    }
+
+    /// Returns the score of member in the sorted set at key.
+    ///
+    /// Reply: SuccessQuery::String(score), or SuccessQuery::Nil if member or key do not exist.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.zadd(b"key", ZaddFlags::default(), vec![(1.5, b"one")]).unwrap();
+    /// let result = database.zscore(b"key", b"one").unwrap();
+    /// assert_eq!(result, SuccessQuery::String(b"1.5".to_vec()));
+    /// ```
+    pub fn zscore(&mut self, key: &[u8], member: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_zset(key, SuccessQuery::Nil, |sorted_set| {
+            match sorted_set.score(member) {
+                Some(score) => SuccessQuery::String(format_float(score).into_bytes()),
+                None => SuccessQuery::Nil,
+            }
+        })
+    }
+
+    /// Increments the score of member in the sorted set stored at key by increment.
+    /// If member does not exist in the sorted set, it is added with increment as its score.
+    ///
+    /// Reply: SuccessQuery::String(score) with the new score of member.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set, or if the result is not a number.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.zadd(b"key", ZaddFlags::default(), vec![(1.0, b"one")]).unwrap();
+    /// let result = database.zincrby(b"key", 2.5, b"one").unwrap();
+    /// assert_eq!(result, SuccessQuery::String(b"3.5".to_vec()));
+    /// ```
+    pub fn zincrby(
+        &mut self,
+        key: &[u8],
+        incr: f64,
+        member: &[u8],
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let flags = ZaddFlags {
+            incr: true,
+            ..ZaddFlags::default()
+        };
+        self.zadd(key, flags, vec![(incr, member)])
+    }
+
+    /// Returns the rank of member in the sorted set stored at key, with the scores ordered
+    /// from low to high. The rank is 0-based, which means that the member with the lowest
+    /// score has rank 0.
+    ///
+    /// Reply: SuccessQuery::Integer(rank), or SuccessQuery::Nil if member or key do not exist.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database.zrank(b"key", b"two").unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zrank(&mut self, key: &[u8], member: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_zset(key, SuccessQuery::Nil, |sorted_set| {
+            match sorted_set.rank(member) {
+                Some(rank) => SuccessQuery::Integer(rank as i64),
+                None => SuccessQuery::Nil,
+            }
+        })
+    }
+
+    /// Returns the rank of member in the sorted set stored at key, with the scores ordered
+    /// from high to low. The rank is 0-based, which means that the member with the highest
+    /// score has rank 0.
+    ///
+    /// Reply: SuccessQuery::Integer(rank), or SuccessQuery::Nil if member or key do not exist.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database.zrevrank(b"key", b"two").unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(0));
+    /// ```
+    pub fn zrevrank(&mut self, key: &[u8], member: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_zset(key, SuccessQuery::Nil, |sorted_set| {
+            match sorted_set.rank(member) {
+                Some(rank) => SuccessQuery::Integer((sorted_set.len() - 1 - rank) as i64),
+                None => SuccessQuery::Nil,
+            }
+        })
+    }
+
+    /// Returns the specified range of members in the sorted set stored at key.
+    ///
+    /// Members can be selected by index, where negative indexes count from the end, by
+    /// score or lexicographically (which expects all the members to have the same score).
+    /// Members are ordered from the lowest to the highest score, or the other way around with rev.
+    /// Limit takes an offset and a count, a negative count returns all the members from offset,
+    /// and can only be used selecting by score or lexicographically.
+    ///
+    /// Reply: SuccessQuery::List(list) with the members, each followed by its score with scores.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database.zrange(b"key", ZrangeBy::Index(0, -1), true, None, false).unwrap();
+    /// assert_eq!(
+    ///     result,
+    ///     SuccessQuery::List(vec![
+    ///         SuccessQuery::String(b"two".to_vec()),
+    ///         SuccessQuery::String(b"one".to_vec())
+    ///     ])
+    /// );
+    /// ```
+    pub fn zrange(
+        &mut self,
+        key: &[u8],
+        by: ZrangeBy,
+        rev: bool,
+        limit: Option<(i64, i64)>,
+        with_scores: bool,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.read_zset(key, SuccessQuery::List(Vec::new()), |sorted_set| {
+            let len = sorted_set.len();
+            let (start, end) = match by {
+                ZrangeBy::Index(start, stop) => match index_range(len, start, stop) {
+                    Some((start, end)) if rev => (len - end, len - start),
+                    Some(range) => range,
+                    None => (0, 0),
+                },
+                ZrangeBy::Score(min, max) => (
+                    sorted_set.rank_of_min_score(min),
+                    sorted_set.rank_of_max_score(max),
+                ),
+                ZrangeBy::Lex(min, max) => (
+                    sorted_set.rank_of_min_lex(min),
+                    sorted_set.rank_of_max_lex(max),
+                ),
+            };
+            let end = end.max(start);
+
+            let (start, end) = match limit {
+                Some((offset, _)) if offset < 0 => (0, 0),
+                Some((offset, count)) => {
+                    let offset = offset as usize;
+                    let count = if count < 0 {
+                        usize::MAX
+                    } else {
+                        count as usize
+                    };
+                    if rev {
+                        let end = end.saturating_sub(offset).max(start);
+                        (end.saturating_sub(count).max(start), end)
+                    } else {
+                        let start = start.saturating_add(offset).min(end);
+                        (start, start.saturating_add(count).min(end))
+                    }
+                }
+                None => (start, end),
+            };
+
+            let mut members = sorted_set.range(start, end);
+            if rev {
+                members.reverse();
+            }
+            zset_reply(members, with_scores)
+        })
+    }
+
+    /// Removes the specified members from the sorted set stored at key. Non existing members
+    /// are ignored. When the sorted set is left empty the key is removed.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members removed.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.zadd(b"key", ZaddFlags::default(), vec![(1.0, b"one")]).unwrap();
+    /// let result = database.zrem(b"key", vec![b"one", b"two"]).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zrem(&mut self, key: &[u8], members: Vec<&[u8]>) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Integer(0));
+        }
+
+        self.write_zset(key, |sorted_set| {
+            let removed = members
+                .iter()
+                .filter(|member| sorted_set.remove(member).is_some())
+                .count();
+            Ok((SuccessQuery::Integer(removed as i64), removed > 0))
+        })
+    }
+
+    #[doc(hidden)]
+    fn zremrange<F>(&mut self, key: &[u8], range: F) -> Result<SuccessQuery, DataBaseError>
+    where
+        F: FnOnce(&SortedSet) -> (usize, usize),
+    {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::Integer(0));
+        }
+
+        self.write_zset(key, |sorted_set| {
+            let (start, end) = range(sorted_set);
+            let members = sorted_set.range(start, end);
+            for (member, _) in members.iter() {
+                sorted_set.remove(member);
+            }
+            Ok((
+                SuccessQuery::Integer(members.len() as i64),
+                !members.is_empty(),
+            ))
+        })
+    }
+
+    /// Removes all members in the sorted set stored at key with rank between start and stop,
+    /// both included. Negative indexes count from the member with the highest score.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members removed.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database.zremrangebyrank(b"key", 0, 0).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zremrangebyrank(
+        &mut self,
+        key: &[u8],
+        start: i64,
+        stop: i64,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.zremrange(key, |sorted_set| {
+            index_range(sorted_set.len(), start, stop).unwrap_or((0, 0))
+        })
+    }
+
+    /// Removes all members in the sorted set stored at key with a score between min and max.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members removed.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database
+    ///     .zremrangebyscore(b"key", ScoreBound::Exclusive(1.0), ScoreBound::Inclusive(5.0))
+    ///     .unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zremrangebyscore(
+        &mut self,
+        key: &[u8],
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.zremrange(key, |sorted_set| {
+            (
+                sorted_set.rank_of_min_score(min),
+                sorted_set.rank_of_max_score(max),
+            )
+        })
+    }
+
+    /// Removes all members in the sorted set stored at key between the lexicographical
+    /// range specified by min and max. All the members are expected to have the same score.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members removed.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(0.0, b"a"), (0.0, b"b")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database
+    ///     .zremrangebylex(b"key", LexBound::Min, LexBound::Exclusive(b"b"))
+    ///     .unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zremrangebylex(
+        &mut self,
+        key: &[u8],
+        min: LexBound,
+        max: LexBound,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.zremrange(key, |sorted_set| {
+            (
+                sorted_set.rank_of_min_lex(min),
+                sorted_set.rank_of_max_lex(max),
+            )
+        })
+    }
+
+    /// Returns the number of members of the sorted set stored at key.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members, or 0 if key does not exist.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.zadd(b"key", ZaddFlags::default(), vec![(1.0, b"one")]).unwrap();
+    /// let result = database.zcard(b"key").unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zcard(&mut self, key: &[u8]) -> Result<SuccessQuery, DataBaseError> {
+        self.read_zset(key, SuccessQuery::Integer(0), |sorted_set| {
+            SuccessQuery::Integer(sorted_set.len() as i64)
+        })
+    }
+
+    /// Returns the number of members in the sorted set at key with a score between min and max.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members in the range.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database
+    ///     .zcount(b"key", ScoreBound::Inclusive(f64::NEG_INFINITY), ScoreBound::Exclusive(2.0))
+    ///     .unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zcount(
+        &mut self,
+        key: &[u8],
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.read_zset(key, SuccessQuery::Integer(0), |sorted_set| {
+            let start = sorted_set.rank_of_min_score(min);
+            let end = sorted_set.rank_of_max_score(max);
+            SuccessQuery::Integer(end.saturating_sub(start) as i64)
+        })
+    }
+
+    #[doc(hidden)]
+    fn zpop(
+        &mut self,
+        key: &[u8],
+        count: Option<usize>,
+        highest: bool,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(SuccessQuery::List(Vec::new()));
+        }
+
+        self.write_zset(key, |sorted_set| {
+            let len = sorted_set.len();
+            let count = count.unwrap_or(1).min(len);
+            let members = if highest {
+                let mut members = sorted_set.range(len - count, len);
+                members.reverse();
+                members
+            } else {
+                sorted_set.range(0, count)
+            };
+            for (member, _) in members.iter() {
+                sorted_set.remove(member);
+            }
+            let changed = !members.is_empty();
+            Ok((zset_reply(members, true), changed))
+        })
+    }
+
+    /// Removes and returns up to count members with the lowest scores in the sorted set
+    /// stored at key. When count is not given it defaults to 1.
+    ///
+    /// Reply: SuccessQuery::List(list) with the popped members, each followed by its score.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database.zpopmin(b"key", None).unwrap();
+    /// assert_eq!(
+    ///     result,
+    ///     SuccessQuery::List(vec![
+    ///         SuccessQuery::String(b"one".to_vec()),
+    ///         SuccessQuery::String(b"1".to_vec())
+    ///     ])
+    /// );
+    /// ```
+    pub fn zpopmin(
+        &mut self,
+        key: &[u8],
+        count: Option<usize>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.zpop(key, count, false)
+    }
+
+    /// Removes and returns up to count members with the highest scores in the sorted set
+    /// stored at key. When count is not given it defaults to 1.
+    ///
+    /// Reply: SuccessQuery::List(list) with the popped members, each followed by its score.
+    ///
+    /// Error if key of database exists but not hold a Sorted Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// let pairs: Vec<(f64, &[u8])> = vec![(1.0, b"one"), (2.0, b"two")];
+    /// database.zadd(b"key", ZaddFlags::default(), pairs).unwrap();
+    /// let result = database.zpopmax(b"key", None).unwrap();
+    /// assert_eq!(
+    ///     result,
+    ///     SuccessQuery::List(vec![
+    ///         SuccessQuery::String(b"two".to_vec()),
+    ///         SuccessQuery::String(b"2".to_vec())
+    ///     ])
+    /// );
+    /// ```
+    pub fn zpopmax(
+        &mut self,
+        key: &[u8],
+        count: Option<usize>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.zpop(key, count, true)
+    }
+
+    #[doc(hidden)]
+    fn zset_source(&mut self, key: &[u8]) -> Result<HashMap<Vec<u8>, f64>, DataBaseError> {
+        if !self._exists(key) {
+            return Ok(HashMap::new());
+        }
+
+        let dictionary = self.dictionary().get_atomic_hash(key);
+        let dictionary = dictionary.read().unwrap();
+        match dictionary.get(key) {
+            Some((StorageValue::Zset(sorted_set), stats)) => {
+                stats.hit();
+                Ok(sorted_set.members().into_iter().collect())
+            }
+            Some((StorageValue::Set(hash_set), stats)) => {
+                stats.hit();
+                Ok(hash_set
+                    .iter()
+                    .map(|member| (member.clone(), 1.0))
+                    .collect())
+            }
+            Some(_) => Err(DataBaseError::NotAZset),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    #[doc(hidden)]
+    fn weighted_sources(
+        &mut self,
+        keys: &[&[u8]],
+        weights: Option<Vec<f64>>,
+    ) -> Result<Vec<HashMap<Vec<u8>, f64>>, DataBaseError> {
+        let mut sources = Vec::new();
+        for (i, key) in keys.iter().enumerate() {
+            let weight = weights.as_ref().map_or(1.0, |weights| weights[i]);
+            let mut source = self.zset_source(key)?;
+            for score in source.values_mut() {
+                *score = not_nan(*score * weight);
+            }
+            sources.push(source);
+        }
+        Ok(sources)
+    }
+
+    #[doc(hidden)]
+    fn store_zset(
+        &mut self,
+        destination: &[u8],
+        members: HashMap<Vec<u8>, f64>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        self.expires().lock().unwrap().remove(destination);
+
+        let len = members.len();
+        if members.is_empty() {
+            if self.dictionary().remove(destination).is_some() {
+                self.mark_modified();
+            }
+        } else {
+            let mut sorted_set = SortedSet::new();
+            for (member, score) in members {
+                sorted_set.insert(&member, score);
+            }
+            self.dictionary()
+                .insert(destination.to_owned(), StorageValue::Zset(sorted_set));
+            self.mark_modified();
+        }
+
+        Ok(SuccessQuery::Integer(len as i64))
+    }
+
+    /// Computes the union of the sorted sets given by the specified keys, and stores the result
+    /// in destination, overwriting it. Sets are taken as sorted sets with every score set to 1.
+    ///
+    /// The score of every member is multiplied by the weight of its key, 1 by default, and the
+    /// scores of a member in the different keys are combined as aggregate says.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members in destination.
+    ///
+    /// Error if any of the keys exists but not hold a Sorted Set or a Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.zadd(b"a", ZaddFlags::default(), vec![(1.0, b"one")]).unwrap();
+    /// database.zadd(b"b", ZaddFlags::default(), vec![(2.0, b"one"), (2.0, b"two")]).unwrap();
+    /// let result = database.zunionstore(b"dest", vec![b"a", b"b"], None, Aggregate::Sum).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(2));
+    /// ```
+    pub fn zunionstore(
+        &mut self,
+        destination: &[u8],
+        keys: Vec<&[u8]>,
+        weights: Option<Vec<f64>>,
+        aggregate: Aggregate,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let mut result: HashMap<Vec<u8>, f64> = HashMap::new();
+        for source in self.weighted_sources(&keys, weights)? {
+            for (member, score) in source {
+                let score = match result.get(&member) {
+                    Some(current) => aggregate_scores(aggregate, *current, score),
+                    None => score,
+                };
+                result.insert(member, score);
+            }
+        }
+
+        self.store_zset(destination, result)
+    }
+
+    /// Computes the intersection of the sorted sets given by the specified keys, and stores the
+    /// result in destination, overwriting it. Sets are taken as sorted sets with every score set to 1.
+    ///
+    /// The score of every member is multiplied by the weight of its key, 1 by default, and the
+    /// scores of a member in the different keys are combined as aggregate says.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members in destination.
+    ///
+    /// Error if any of the keys exists but not hold a Sorted Set or a Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.zadd(b"a", ZaddFlags::default(), vec![(1.0, b"one")]).unwrap();
+    /// database.zadd(b"b", ZaddFlags::default(), vec![(2.0, b"one"), (2.0, b"two")]).unwrap();
+    /// let result = database.zinterstore(b"dest", vec![b"a", b"b"], None, Aggregate::Max).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zinterstore(
+        &mut self,
+        destination: &[u8],
+        keys: Vec<&[u8]>,
+        weights: Option<Vec<f64>>,
+        aggregate: Aggregate,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let mut sources = self.weighted_sources(&keys, weights)?.into_iter();
+        let mut result = sources.next().unwrap_or_default();
+        for source in sources {
+            result = result
+                .into_iter()
+                .filter_map(|(member, current)| {
+                    let score = aggregate_scores(aggregate, current, *source.get(&member)?);
+                    Some((member, score))
+                })
+                .collect();
+        }
+
+        self.store_zset(destination, result)
+    }
+
+    /// Computes the difference between the first and all successive sorted sets given by the
+    /// specified keys, and stores the result in destination, overwriting it.
+    ///
+    /// Reply: SuccessQuery::Integer(n) when n is the number of members in destination.
+    ///
+    /// Error if any of the keys exists but not hold a Sorted Set or a Set.
+    /// # Example
+    /// ```
+    /// let mut database = Database::new("dump_path.txt", 16, 16).unwrap();
+    /// database.zadd(b"a", ZaddFlags::default(), vec![(1.0, b"one"), (2.0, b"two")]).unwrap();
+    /// database.zadd(b"b", ZaddFlags::default(), vec![(2.0, b"one")]).unwrap();
+    /// let result = database.zdiffstore(b"dest", vec![b"a", b"b"]).unwrap();
+    /// assert_eq!(result, SuccessQuery::Integer(1));
+    /// ```
+    pub fn zdiffstore(
+        &mut self,
+        destination: &[u8],
+        keys: Vec<&[u8]>,
+    ) -> Result<SuccessQuery, DataBaseError> {
+        let mut sources = self.weighted_sources(&keys, None)?.into_iter();
+        let mut result = sources.next().unwrap_or_default();
+        for source in sources {
+            result.retain(|member, _| !source.contains_key(member));
+        }
+
+        self.store_zset(destination, result)
+    }
+}
+
+impl Clone for Database {
+    fn clone(&self) -> Self {
+        Database {
+            keyspaces: self.keyspaces.clone(),
+            index: self.index,
+            db_dump_path: self.db_dump_path.clone(),
+            transaction_lock: self.transaction_lock.clone(),
+            watched_keys: self.watched_keys.clone(),
+            aof: self.aof.clone(),
+            save_state: self.save_state.clone(),
+            memory_limit: self.memory_limit.clone(),
+            notifier: self.notifier.clone(),
+            modified: false,
+        }
+    }
+}
+
+#[doc(hidden)]
+const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+#[doc(hidden)]
+const ACTIVE_EXPIRE_KEYS_PER_LOCK: usize = 200;
+
+/// Changes the version of key of database db if it is watched, so the transactions
+/// watching it fail.
+#[doc(hidden)]
+fn signal_watched_key(watched_keys: &WatchedKeys, db: usize, key: &[u8]) {
+    let mut watched_keys = watched_keys.lock().unwrap();
+    if let Some((version, _)) = watched_keys.get_mut(&(db, key.to_vec())) {
+        *version += 1;
+    }
+}
+
+/// Removes the keys of dictionary whose time to live is over every
+/// ACTIVE_EXPIRE_INTERVAL, until expires is dropped along with its database.
+/// Expired keys are taken from expires in batches, releasing it between them so
+/// commands aren't blocked while a lot of keys expire at once. The watched ones
+/// change their version as they are removed, and their expired events are published
+/// once expires is released.
+#[doc(hidden)]
+fn ttl_supervisor_run(
+    mut dictionary: HashShard,
+    expires: Weak<Mutex<TtlIndex>>,
+    index: Arc<AtomicUsize>,
+    watched_keys: WatchedKeys,
+    notifier: Notifier,
+) {
+    thread::spawn(move || loop {
+        thread::sleep(ACTIVE_EXPIRE_INTERVAL);
+        let expires = match expires.upgrade() {
+            Some(expires) => expires,
+            None => break,
+        };
+
+        let now = SystemTime::now();
+        loop {
+            let mut expires = expires.lock().unwrap();
+            let db = index.load(AtomicOrdering::SeqCst);
+            let mut expired = Vec::new();
+            while expired.len() < ACTIVE_EXPIRE_KEYS_PER_LOCK {
+                match expires.pop_expired(now) {
+                    Some(key) => {
+                        dictionary.remove(&key);
+                        signal_watched_key(&watched_keys, db, &key);
+                        expired.push(key);
+                    }
+                    None => break,
+                };
+            }
+            drop(expires);
+
+            for key in &expired {
+                notifier.notify(EventClass::Expired, "expired", key, db);
+            }
+            if expired.len() < ACTIVE_EXPIRE_KEYS_PER_LOCK {
+                break;
+            }
+        }
+    });
+}
+
+impl fmt::Display for Database {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (key, value) in self.dictionary().key_value() {
+            writeln!(
+                f,
+                "key: {}, value: {}",
+                String::from_utf8_lossy(&key),
+                value
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod ttl_commands {
+    use super::*;
+    use std::time::Duration;
+
+    const KEY_A: &[u8] = b"KEY_A";
+    const VALUE_A: &[u8] = b"VALUE_A";
+
+    const KEY_B: &[u8] = b"KEY_B";
+    const VALUE_B: &[u8] = b"VALUE_B";
+
+    const KEY_C: &[u8] = b"KEY_C";
+    const VALUE_C: &[u8] = b"VALUE_C";
+
+    const KEY_D: &[u8] = b"KEY_D";
+    const VALUE_D: &[u8] = b"VALUE_D";
+
+    const DB_DUMP: &str = "db_dump_path";
+
+    // duration_since
+
+    #[test]
+    fn ttl_supervisor_run_supervaise_a_key() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+
+        db.append(KEY_A, VALUE_A).unwrap();
+
+        let now = SystemTime::now();
+        let expire_time_a = now.checked_add(Duration::new(1, 0)).unwrap();
+        let ttl_pair = KeyTtl::new(KEY_A, expire_time_a);
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(value);
+        }
+
+        db.expires().lock().unwrap().insert(ttl_pair);
+
+        thread::sleep(Duration::new(2, 0));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(!value);
+        }
+    }
+
+    #[test]
+    fn expired_keys_are_published_with_their_database() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        let mut channels = Channels::new();
+        db.set_notifications(channels.clone());
+        db.set_notify_keyspace_events(KeyspaceEvents::parse("Ex").unwrap());
+        let (s, r) = std::sync::mpsc::channel();
+        channels.subscribe("__keyevent@1__:expired", s.into(), 1);
+
+        db.select(2).unwrap();
+        db.append(KEY_A, VALUE_A).unwrap();
+        db.pexpire(KEY_A, 50, ExpireFlags::default()).unwrap();
+        db.swapdb(1, 2).unwrap();
+
+        let message = r.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(message.ends_with("$5\r\nKEY_A\r\n"));
+    }
+
+    #[test]
+    fn ttl_supervisor_run_supervaise_two_key() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+
+        db.append(KEY_A, VALUE_A).unwrap();
+        db.append(KEY_B, VALUE_B).unwrap();
+
+        let now = SystemTime::now();
+        let expire_time_a = now.checked_add(Duration::new(1, 0)).unwrap();
+        let expire_time_b = now.checked_add(Duration::new(5, 0)).unwrap();
+
+        let ttl_pair_a = KeyTtl::new(KEY_A, expire_time_a);
+        let ttl_pair_b = KeyTtl::new(KEY_B, expire_time_b);
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(value);
+        }
+
+        db.expires().lock().unwrap().insert(ttl_pair_a);
+        db.expires().lock().unwrap().insert(ttl_pair_b);
+
+        thread::sleep(Duration::new(2, 0));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(!value);
+        }
+
+        thread::sleep(Duration::new(4, 0));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(!value);
+        }
+    }
+
+    #[test]
+    fn removing_a_key_removes_its_time_to_live() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+        db.set(KEY_B, VALUE_B).unwrap();
+        db.set(KEY_C, VALUE_C).unwrap();
+        db.expire(KEY_A, 100, ExpireFlags::default()).unwrap();
+        db.expire(KEY_B, 100, ExpireFlags::default()).unwrap();
+        db.expire(KEY_C, 100, ExpireFlags::default()).unwrap();
+
+        db.del(KEY_A).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+        db.rename(KEY_A, KEY_B).unwrap();
+
+        assert_eq!(db.ttl(KEY_B).unwrap(), SuccessQuery::Integer(-1));
+        assert_eq!(db.expires().lock().unwrap().len(), 1);
+
+        db.flushdb().unwrap();
+        assert_eq!(db.expires().lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn pexpire_hides_the_key_as_soon_as_it_expires() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+
+        assert_eq!(
+            db.pexpire(KEY_A, 50, ExpireFlags::default()).unwrap(),
+            SuccessQuery::Boolean(true)
+        );
+        assert_eq!(db.exists(KEY_A).unwrap(), SuccessQuery::Boolean(true));
+        thread::sleep(Duration::from_millis(60));
+
+        assert_eq!(db.get(KEY_A).unwrap(), SuccessQuery::Nil);
+        assert_eq!(db.pttl(KEY_A).unwrap(), SuccessQuery::Integer(-2));
+    }
+
+    #[test]
+    fn active_expiry_removes_keys_that_are_never_read() {
+        let db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        let mut dictionary = db.dictionary();
+        dictionary.insert(KEY_A.to_vec(), StorageValue::String(VALUE_A.to_vec()));
+        let expire_time = SystemTime::now() + Duration::from_millis(10);
+        db.expires()
+            .lock()
+            .unwrap()
+            .insert(KeyTtl::new(KEY_A, expire_time));
+
+        thread::sleep(ACTIVE_EXPIRE_INTERVAL * 3);
+
+        assert_eq!(dictionary.len(), 0);
+        assert_eq!(db.expires().lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn pttl_and_pexpiretime_are_in_milliseconds() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+        db.set(KEY_B, VALUE_B).unwrap();
+        db.pexpireat(KEY_A, 33177117420123, ExpireFlags::default())
+            .unwrap();
+        db.pexpire(KEY_B, 2600, ExpireFlags::default()).unwrap();
+
+        let result = db.pexpiretime(KEY_A).unwrap();
+        assert_eq!(result, SuccessQuery::Integer(33177117420123));
+        let result = db.expiretime(KEY_A).unwrap();
+        assert_eq!(result, SuccessQuery::Integer(33177117420));
+        if let SuccessQuery::Integer(ms) = db.pttl(KEY_B).unwrap() {
+            assert!(ms > 2500 && ms <= 2600);
+        }
+        assert_eq!(db.ttl(KEY_B).unwrap(), SuccessQuery::Integer(3));
+        assert_eq!(db.pexpiretime(KEY_C).unwrap(), SuccessQuery::Integer(-2));
+    }
+
+    #[test]
+    fn persist_tells_if_a_timeout_was_removed() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+        db.expire(KEY_A, 100, ExpireFlags::default()).unwrap();
+
+        assert_eq!(db.persist(KEY_A).unwrap(), SuccessQuery::Boolean(true));
+        assert_eq!(db.persist(KEY_A).unwrap(), SuccessQuery::Boolean(false));
+        assert_eq!(db.persist(KEY_B).unwrap(), SuccessQuery::Boolean(false));
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(-1));
+    }
+
+    #[test]
+    fn expire_fails_if_the_time_overflows() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+
+        let result = db
+            .expire(KEY_A, i64::MAX, ExpireFlags::default())
+            .unwrap_err();
+        assert_eq!(result, DataBaseError::InvalidExpireTime);
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(-1));
+    }
+
+    #[test]
+    fn expire_nx_and_xx_depend_on_the_key_having_a_timeout() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+        let nx = ExpireFlags {
+            nx: true,
+            ..ExpireFlags::default()
+        };
+        let xx = ExpireFlags {
+            xx: true,
+            ..ExpireFlags::default()
+        };
+
+        assert_eq!(
+            db.expire(KEY_A, 100, xx).unwrap(),
+            SuccessQuery::Boolean(false)
+        );
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(-1));
+        assert_eq!(
+            db.expire(KEY_A, 100, nx).unwrap(),
+            SuccessQuery::Boolean(true)
+        );
+        assert_eq!(
+            db.expire(KEY_A, 200, nx).unwrap(),
+            SuccessQuery::Boolean(false)
+        );
+        assert_eq!(
+            db.expire(KEY_A, 200, xx).unwrap(),
+            SuccessQuery::Boolean(true)
+        );
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(200));
+    }
+
+    #[test]
+    fn expire_gt_and_lt_compare_with_the_current_timeout() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+        let gt = ExpireFlags {
+            gt: true,
+            ..ExpireFlags::default()
+        };
+        let lt = ExpireFlags {
+            lt: true,
+            ..ExpireFlags::default()
+        };
+
+        assert_eq!(
+            db.expire(KEY_A, 100, gt).unwrap(),
+            SuccessQuery::Boolean(false)
+        );
+        assert_eq!(
+            db.expire(KEY_A, 100, lt).unwrap(),
+            SuccessQuery::Boolean(true)
+        );
+        assert_eq!(
+            db.expire(KEY_A, 200, lt).unwrap(),
+            SuccessQuery::Boolean(false)
+        );
+        assert_eq!(
+            db.expire(KEY_A, 50, gt).unwrap(),
+            SuccessQuery::Boolean(false)
+        );
+        assert_eq!(
+            db.expire(KEY_A, 200, gt).unwrap(),
+            SuccessQuery::Boolean(true)
+        );
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(200));
+    }
+
+    #[test]
+    fn set_with_an_expiry_gives_the_key_a_timeout() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        let flags = SetFlags {
+            expiry: Some(KeyExpiry::Px(2600)),
+            ..SetFlags::default()
+        };
+
+        db.set_with_flags(KEY_A, VALUE_A, flags).unwrap();
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(3));
+
+        db.set(KEY_A, VALUE_B).unwrap();
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(-1));
+    }
+
+    #[test]
+    fn set_keepttl_keeps_the_timeout() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+        db.expire(KEY_A, 100, ExpireFlags::default()).unwrap();
+        let flags = SetFlags {
+            expiry: Some(KeyExpiry::KeepTtl),
+            ..SetFlags::default()
+        };
+
+        db.set_with_flags(KEY_A, VALUE_B, flags).unwrap();
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(100));
+        assert_eq!(
+            db.get(KEY_A).unwrap(),
+            SuccessQuery::String(VALUE_B.to_vec())
+        );
+    }
+
+    #[test]
+    fn set_fails_if_the_expire_time_is_not_positive() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        let flags = SetFlags {
+            expiry: Some(KeyExpiry::Ex(0)),
+            ..SetFlags::default()
+        };
+
+        let result = db.set_with_flags(KEY_A, VALUE_A, flags).unwrap_err();
+        assert_eq!(result, DataBaseError::InvalidExpireTime);
+        assert_eq!(db.get(KEY_A).unwrap(), SuccessQuery::Nil);
+    }
+
+    #[test]
+    fn getex_sets_or_removes_the_timeout() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY_A, VALUE_A).unwrap();
+
+        let result = db.getex(KEY_A, Some(KeyExpiry::Ex(100))).unwrap();
+        assert_eq!(result, SuccessQuery::String(VALUE_A.to_vec()));
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(100));
+
+        db.getex(KEY_A, None).unwrap();
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(100));
+
+        db.getex(KEY_A, Some(KeyExpiry::Persist)).unwrap();
+        assert_eq!(db.ttl(KEY_A).unwrap(), SuccessQuery::Integer(-1));
+
+        db.getex(KEY_A, Some(KeyExpiry::PxAt(1000))).unwrap();
+        assert_eq!(db.get(KEY_A).unwrap(), SuccessQuery::Nil);
+        assert_eq!(
+            db.getex(KEY_B, Some(KeyExpiry::Ex(100))).unwrap(),
+            SuccessQuery::Nil
+        );
+    }
+
+    const SEC: u64 = 1;
+
+    #[test]
+    fn ttl_supervisor_run_supervaise_four_keys() {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+
+        db.append(KEY_A, VALUE_A).unwrap();
+        db.append(KEY_B, VALUE_B).unwrap();
+        db.append(KEY_C, VALUE_C).unwrap();
+        db.append(KEY_D, VALUE_D).unwrap();
+
+        let now = SystemTime::now();
+        let expire_time_a = now.checked_add(Duration::from_secs(SEC)).unwrap();
+        let expire_time_b = now.checked_add(Duration::from_secs(SEC * 3)).unwrap();
+        let expire_time_c = now.checked_add(Duration::from_secs(SEC * 5)).unwrap();
+        let expire_time_d = now.checked_add(Duration::from_secs(SEC * 10)).unwrap();
+
+        let ttl_pair_a = KeyTtl::new(KEY_A, expire_time_a);
+        let ttl_pair_b = KeyTtl::new(KEY_B, expire_time_b);
+        let ttl_pair_c = KeyTtl::new(KEY_C, expire_time_c);
+        let ttl_pair_d = KeyTtl::new(KEY_D, expire_time_d);
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
+            assert!(value);
+        }
+
+        db.expires().lock().unwrap().insert(ttl_pair_a);
+        db.expires().lock().unwrap().insert(ttl_pair_b);
+        db.expires().lock().unwrap().insert(ttl_pair_c);
+        db.expires().lock().unwrap().insert(ttl_pair_d);
+
+        thread::sleep(Duration::from_secs(SEC * 2));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
+            assert!(value);
+        }
+
+        thread::sleep(Duration::from_secs(SEC * 2));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(!value);
+        }
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(value);
+        }
+
+        thread::sleep(Duration::from_secs(SEC));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
+            assert!(value);
+        }
+
+        thread::sleep(Duration::from_secs(SEC + 4));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
+            assert!(!value);
+        }
+    }
+
+    #[test]
+    fn ttl_supervisor_run_supervaise_four_keys_one_of_the_key_is_inserted_with_a_lower_expire_time_the_actual_key(
+    ) {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+
+        db.append(KEY_A, VALUE_A).unwrap();
+        db.append(KEY_B, VALUE_B).unwrap();
+        db.append(KEY_C, VALUE_C).unwrap();
+        db.append(KEY_D, VALUE_D).unwrap();
+
+        let now = SystemTime::now();
+        let expire_time_a = now.checked_add(Duration::from_secs(SEC)).unwrap();
+        let expire_time_b = now.checked_add(Duration::from_secs(SEC * 3)).unwrap();
+        let expire_time_c = now.checked_add(Duration::from_secs(SEC * 5)).unwrap();
+        let expire_time_d = now.checked_add(Duration::from_secs(SEC * 10)).unwrap();
+
+        let ttl_pair_a = KeyTtl::new(KEY_A, expire_time_a);
+        let ttl_pair_b = KeyTtl::new(KEY_B, expire_time_b);
+        let ttl_pair_c = KeyTtl::new(KEY_C, expire_time_c);
+        let ttl_pair_d = KeyTtl::new(KEY_D, expire_time_d);
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
+            assert!(value);
+        }
+
+        db.expires().lock().unwrap().insert(ttl_pair_b);
+        db.expires().lock().unwrap().insert(ttl_pair_c);
+        db.expires().lock().unwrap().insert(ttl_pair_a);
+        db.expires().lock().unwrap().insert(ttl_pair_d);
+
+        thread::sleep(Duration::from_secs(SEC * 2));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(value);
+        }
+
+        thread::sleep(Duration::from_secs(SEC * 2));
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
+            assert!(value);
+        }
+
+        thread::sleep(Duration::from_secs(SEC));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
+            assert!(value);
+        }
+        thread::sleep(Duration::from_secs(SEC + 4));
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
+            assert!(!value);
+        }
+
+        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
+            assert!(!value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod group_string {
+
+    use super::*;
+
+    const KEY: &[u8] = b"KEY";
+    const VALUE: &[u8] = b"VALUE";
+
+    const DB_DUMP: &str = "db_dump_path.txt";
+
+    fn create_database_with_string() -> Database {
+        let mut db = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        db.set(KEY, VALUE).unwrap();
+        db
+    }
+
+    fn create_database() -> Database {
+        Database::new(DB_DUMP.to_string(), 16, 16).unwrap()
+    }
+
+    mod append_test {
+
+        use super::*;
+
+        #[test]
+        fn test_append_new_key_return_lenght_of_the_value() {
+            let mut database = create_database();
+
+            if let SuccessQuery::Integer(lenght) = database.append(KEY, VALUE).unwrap() {
+                assert_eq!(lenght, 5);
+            }
+        }
+
+        #[test]
+        fn test_append_key_with_old_value_return_lenght_of_the_total_value() {
+            let mut database = create_database();
+
+            if let SuccessQuery::Integer(lenght) = database.append(KEY, VALUE).unwrap() {
+                assert_eq!(lenght, 5);
+                if let SuccessQuery::Integer(lenght) = database.append(KEY, VALUE).unwrap() {
+                    assert_eq!(lenght, 10);
+                }
+            }
+        }
+    }
+
+    mod get_test {
+
+        use super::*;
+
+        #[test]
+        fn test_get_returns_value_of_key() {
+            let mut database = create_database_with_string();
+
+            if let SuccessQuery::String(value) = database.get(KEY).unwrap() {
+                assert_eq!(VALUE, value);
+            }
+        }
+
+        #[test]
+        fn test_get_returns_error_if_the_key_does_not_exist() {
+            let mut database = create_database();
+
+            let result = database.get(KEY).unwrap();
+
+            assert_eq!(result, SuccessQuery::Nil);
+        }
+    }
+
+    mod set_test {
+        use super::*;
+
+        #[test]
+        fn test_set_returns_ok() {
+            let mut database = create_database();
+
+            let result = database.set(KEY, VALUE).unwrap();
+            assert_eq!(SuccessQuery::Success, result);
+
+            if let SuccessQuery::String(value) = database.get(KEY).unwrap() {
+                assert_eq!(VALUE, value);
+            }
+        }
+
+        #[test]
+        fn test_set_binary_key_and_value() {
+            let mut database = create_database();
+            let key: &[u8] = b"\x00binary key\r\n";
+            let value: &[u8] = b"\xff\xfe value with spaces\n";
+
+            database.set(key, value).unwrap();
+
+            let result = database.get(key).unwrap();
+            assert_eq!(result, SuccessQuery::String(value.to_vec()));
+        }
+
+        #[test]
+        fn test_set_nx_and_xx_depend_on_the_key_existing() {
+            let mut database = create_database();
+            let nx = SetFlags {
+                nx: true,
+                ..SetFlags::default()
+            };
+            let xx = SetFlags {
+                xx: true,
+                ..SetFlags::default()
+            };
+
+            let result = database.set_with_flags(KEY, VALUE, xx).unwrap();
+            assert_eq!(result, SuccessQuery::Nil);
+            assert_eq!(database.get(KEY).unwrap(), SuccessQuery::Nil);
+
+            let result = database.set_with_flags(KEY, VALUE, nx).unwrap();
+            assert_eq!(result, SuccessQuery::Success);
+            let result = database.set_with_flags(KEY, b"OTHER", nx).unwrap();
+            assert_eq!(result, SuccessQuery::Nil);
+            assert_eq!(
+                database.get(KEY).unwrap(),
+                SuccessQuery::String(VALUE.to_vec())
+            );
+        }
+
+        #[test]
+        fn test_set_nx_sets_the_key_once_for_concurrent_clients() {
+            let database = create_database();
+            let nx = SetFlags {
+                nx: true,
+                expiry: Some(KeyExpiry::Px(30000)),
+                ..SetFlags::default()
+            };
+
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    let mut database = database.clone();
+                    thread::spawn(move || {
+                        (0..100)
+                            .filter(|i| {
+                                let key = format!("lock{}", i);
+                                let result = database.set_with_flags(key.as_bytes(), VALUE, nx);
+                                result.unwrap() == SuccessQuery::Success
+                            })
+                            .count()
+                    })
+                })
+                .collect();
+            let set: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+            assert_eq!(set, 100);
+        }
+
+        #[test]
+        fn test_set_get_returns_the_old_value() {
+            let mut database = create_database();
+            let get = SetFlags {
+                get: true,
+                ..SetFlags::default()
+            };
+
+            let result = database.set_with_flags(KEY, VALUE, get).unwrap();
+            assert_eq!(result, SuccessQuery::Nil);
+            let result = database.set_with_flags(KEY, b"OTHER", get).unwrap();
+            assert_eq!(result, SuccessQuery::String(VALUE.to_vec()));
+
+            database.lpush(b"LIST", vec![VALUE]).unwrap();
+            let result = database.set_with_flags(b"LIST", VALUE, get).unwrap_err();
+            assert_eq!(result, DataBaseError::NotAString);
+        }
+
+        #[test]
+        fn test_setnx_only_sets_new_keys() {
+            let mut database = create_database();
+
+            assert_eq!(
+                database.setnx(KEY, VALUE).unwrap(),
+                SuccessQuery::Boolean(true)
+            );
+            assert_eq!(
+                database.setnx(KEY, b"OTHER").unwrap(),
+                SuccessQuery::Boolean(false)
+            );
+            assert_eq!(
+                database.get(KEY).unwrap(),
+                SuccessQuery::String(VALUE.to_vec())
+            );
+        }
+    }
+
+    mod getdel_test {
+        use super::*;
+
+        #[test]
+        fn test_getdel_returns_old_value_of_key_and_after_value_of_key_what_not_exists_in_database_is_nul(
+        ) {
+            let mut database = create_database_with_string();
+
+            if let SuccessQuery::String(value) = database.getdel(KEY).unwrap() {
+                assert_eq!(value, VALUE);
+            }
+
+            let result = database.get(KEY).unwrap();
+            assert_eq!(result, SuccessQuery::Nil);
+        }
+
+        #[test]
+        fn test_getdel_returns_nil_value_if_key_not_exist_in_database() {
+            let mut database = create_database();
+
+            let result = database.getdel(KEY).unwrap();
+            assert_eq!(result, SuccessQuery::Nil);
+        }
+    }
+
+    mod incrby_test {
+        use super::*;
+
+        #[test]
+        fn test_incrby_returns_lenght_of_the_resulting_value_after_increment() {
+            let mut database = create_database();
+
+            database.set(KEY, b"1").unwrap();
+
+            let result = database.incrby(KEY, 4).unwrap();
+
+            assert_eq!(result, SuccessQuery::Integer(5));
+        }
+
+        #[test]
+        fn test_incrby_returns_error_if_the_value_of_key_not_hold_parseable_value_to_number() {
+            let mut database = create_database();
+
+            database.set(KEY, b"1a").unwrap();
+
+            let result = database.incrby(KEY, 4).unwrap_err();
+
+            assert_eq!(result, DataBaseError::NotAnInteger);
+        }
+    }
+
+    mod decrby_test {
+        use super::*;
+
+        #[test]
+        fn test_decrby_returns_lenght_of_the_resulting_value_after_increment() {
+            let mut database = create_database();
+
+            database.set(KEY, b"5").unwrap();
+            let result = database.decrby(KEY, 4).unwrap();
+
+            assert_eq!(result, SuccessQuery::Integer(1));
+        }
+
+        #[test]
+        fn test_decrby_returns_error_if_the_value_of_key_not_hold_parseable_value_to_number() {
+            let mut database = create_database();
+
+            database.set(KEY, b"5a").unwrap();
+            let result = database.decrby(KEY, 4).unwrap_err();
+
+            assert_eq!(result, DataBaseError::NotAnInteger);
+        }
+    }
+
+    mod strlen_test {
+        use super::*;
+
+        #[test]
+        fn test_strlen_returns_the_lenght_of_value_key() {
+            let mut database = create_database();
+
+            database.set(KEY, VALUE).unwrap();
+
+            if let SuccessQuery::Integer(value) = database.strlen(KEY).unwrap() {
+                assert_eq!(value, 5);
+            }
+        }
+
+        #[test]
+        fn test_strlen_returns_zero_if_the_key_not_exists_in_database() {
+            let mut database = create_database();
+
+            if let SuccessQuery::Integer(value) = database.strlen(KEY).unwrap() {
+                assert_eq!(value, 0);
+            }
+        }
+    }
+
+    mod mset_test {
+        use super::*;
+
+        const KEY_A: &[u8] = b"KEY_A";
+        const VALUE_A: &[u8] = b"VALUE_A";
+
+        const KEY_B: &[u8] = b"KEY_B";
+        const VALUE_B: &[u8] = b"VALUE_B";
+
+        const KEY_C: &[u8] = b"KEY_C";
+        const VALUE_C: &[u8] = b"VALUE_C";
+
+        const KEY_D: &[u8] = b"KEY_D";
+        const VALUE_D: &[u8] = b"VALUE_D";
+
+        #[test]
+        fn test_mset_set_multiple_key_and_value_ok() {
+            let mut database = create_database();
+
+            let vec_key_value = vec![
+                KEY_A, VALUE_A, KEY_B, VALUE_B, KEY_C, VALUE_C, KEY_D, VALUE_D,
+            ];
+
+            let result = database.mset(vec_key_value).unwrap();
+            assert_eq!(result, SuccessQuery::Success);
+
+            let result_get1 = database.get(KEY_A).unwrap();
+            let result_get2 = database.get(KEY_B).unwrap();
+            let result_get3 = database.get(KEY_C).unwrap();
+            let result_get4 = database.get(KEY_D).unwrap();
+
+            assert_eq!(result_get1, SuccessQuery::String(VALUE_A.to_owned()));
+            assert_eq!(result_get2, SuccessQuery::String(VALUE_B.to_owned()));
+            assert_eq!(result_get3, SuccessQuery::String(VALUE_C.to_owned()));
+            assert_eq!(result_get4, SuccessQuery::String(VALUE_D.to_owned()));
+        }
+    }
+
+    mod mget_test {
+        use super::*;
+
+        const KEY_A: &[u8] = b"KEY_A";
+        const VALUE_A: &[u8] = b"VALUE_A";
+
+        const KEY_B: &[u8] = b"KEY_B";
+        const VALUE_B: &[u8] = b"VALUE_B";
+
+        const KEY_C: &[u8] = b"KEY_C";
+        const VALUE_C: &[u8] = b"VALUE_C";
+
+        const KEY_D: &[u8] = b"KEY_D";
+        const VALUE_D: &[u8] = b"VALUE_D";
+
+        fn create_a_database_with_key_values() -> Database {
+            let mut database = create_database();
+
+            database.set(KEY_A, VALUE_A).unwrap();
+            database.set(KEY_B, VALUE_B).unwrap();
+            database.set(KEY_C, VALUE_C).unwrap();
+            database.set(KEY_D, VALUE_D).unwrap();
+
+            database
+        }
+
+        #[test]
+        fn test_mget_return_all_values_of_keys_if_all_keys_are_in_database() {
+            let mut database = create_a_database_with_key_values();
+
+            let vec_keys = vec![KEY_A, KEY_B, KEY_C, KEY_D];
+
+            if let SuccessQuery::List(list) = database.mget(vec_keys).unwrap() {
+                assert_eq!(list[0], SuccessQuery::String(VALUE_A.to_owned()));
+                assert_eq!(list[1], SuccessQuery::String(VALUE_B.to_owned()));
+                assert_eq!(list[2], SuccessQuery::String(VALUE_C.to_owned()));
+                assert_eq!(list[3], SuccessQuery::String(VALUE_D.to_owned()));
+            }
+        }
+
+        #[test]
+        fn test_mget_returns_nil_for_some_key_that_no_exists_in_database() {
+            let mut database = create_database();
+
+            database.set(KEY_A, VALUE_A).unwrap();
+            database.set(KEY_B, VALUE_B).unwrap();
+
+            let vec_keys = vec![KEY_A, KEY_B, KEY_C, KEY_D];
+
+            if let SuccessQuery::List(list) = database.mget(vec_keys).unwrap() {
+                assert_eq!(list[0], SuccessQuery::String(VALUE_A.to_owned()));
+                assert_eq!(list[1], SuccessQuery::String(VALUE_B.to_owned()));
+                assert_eq!(list[2], SuccessQuery::Nil);
+                assert_eq!(list[3], SuccessQuery::Nil);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod group_keys {
+    use super::*;
+
+    const DB_DUMP: &str = "db_dump_path.txt.txt";
+
+    fn create_database() -> Database {
+        Database::new(DB_DUMP.to_string(), 16, 16).unwrap()
+    }
+
+    const KEY: &[u8] = b"KEY";
+    const SECOND_KEY: &[u8] = b"SECOND_KEY";
+
+    const VALUE: &[u8] = b"VALUE";
+    const SECOND_VALUE: &[u8] = b"SECOND_VALUE";
+
+    mod copy_test {
+        use super::*;
+
+        #[test]
+        fn test_copy_set_dolly_sheep_then_copy_to_clone() {
+            let mut database = create_database();
+
+            database.set(KEY, VALUE).unwrap();
+            let result = database.copy(KEY, SECOND_KEY);
+            assert_eq!(result.unwrap(), SuccessQuery::Success);
+            if let SuccessQuery::String(value) = database.strlen(SECOND_KEY).unwrap() {
+                assert_eq!(value, VALUE);
+            }
+        }
+
+        #[test]
+        fn test_copy_set_dolly_sheep_then_copy_to_clone_when_clone_exist() {
+            let mut database = create_database();
+
+            database.set(KEY, VALUE).unwrap();
+            database.set(SECOND_KEY, SECOND_VALUE).unwrap();
+            let result = database.copy(KEY, SECOND_KEY);
+
+            assert_eq!(result.unwrap_err(), DataBaseError::KeyAlredyExist);
+        }
+
+        #[test]
+        fn test_copy_try_to_copy_a_key_does_not_exist() {
+            let mut database = create_database();
+            let result = database.copy(KEY, SECOND_KEY);
+
+            assert_eq!(result.unwrap_err(), DataBaseError::NonExistentKey);
+        }
+    }
+
+    mod del_test {
+        use super::*;
+
+        #[test]
+        fn test_del_key_value_returns_succes() {
+            let mut database = create_database();
+            database.set(KEY, VALUE).unwrap();
+
+            let result = database.del(KEY);
+            assert_eq!(result.unwrap(), SuccessQuery::Success);
+            assert_eq!(database.get(KEY).unwrap(), SuccessQuery::Nil);
+        }
+        #[test]
+        fn test_del_key_non_exist_returns_nil() {
+            let mut database = create_database();
+
+            let result = database.del(KEY);
+            assert_eq!(result.unwrap(), SuccessQuery::Success);
+        }
+    }
+
+    mod exists_test {
+
+        use super::*;
+
+        #[test]
+        fn test_exists_key_non_exist_returns_bool_false() {
+            let mut database = create_database();
+
+            let result = database.exists(KEY);
+            assert_eq!(result.unwrap(), SuccessQuery::Boolean(false));
+            assert_eq!(database.get(KEY).unwrap(), SuccessQuery::Nil);
+        }
+
+        #[test]
+        fn test_exists_key_hello_returns_bool_true() {
+            let mut database = create_database();
+            database.set(KEY, VALUE).unwrap();
+
+            let result = database.exists(KEY);
+
+            assert_eq!(result.unwrap(), SuccessQuery::Boolean(true));
+
+            if let SuccessQuery::String(value) = database.strlen(KEY).unwrap() {
+                assert_eq!(value, VALUE);
+            }
+        }
+    }
+
+    mod keys_test {
+        use super::*;
+
+        const FIRST_NAME: &[u8] = b"firstname";
+        const LAST_NAME: &[u8] = b"lastname";
+        const AGE: &[u8] = b"age";
+
+        const FIRST_NAME_VALUE: &[u8] = b"Alex";
+        const LAST_NAME_VALUE: &[u8] = b"Arbieto";
+        const AGE_VALUE: &[u8] = b"22";
+
+        fn create_database_with_keys() -> Database {
+            let mut database = create_database();
+            database.set(FIRST_NAME, FIRST_NAME_VALUE).unwrap();
+            database.set(LAST_NAME, LAST_NAME_VALUE).unwrap();
+            database.set(AGE, AGE_VALUE).unwrap();
+            database
+        }
+
+        #[test]
+        fn test_keys_obtain_keys_with_name() {
+            let mut database = create_database_with_keys();
+
+            if let Ok(SuccessQuery::List(list)) = database.keys(b"*name") {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+
+                assert!(list.contains(&FIRST_NAME.to_owned()));
+                assert!(list.contains(&LAST_NAME.to_owned()));
+            }
+        }
+
+        #[test]
+        fn test_keys_obtain_keys_with_four_question_name() {
+            let mut database = create_database_with_keys();
+            if let Ok(SuccessQuery::List(list)) = database.keys(b"????name") {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+
+                assert!(list.contains(&LAST_NAME.to_owned()));
+            }
+        }
+
+        const KEY_A: &[u8] = b"key";
+        const KEY_B: &[u8] = b"keeeey";
+        const KEY_C: &[u8] = b"ky";
+        const NO_MATCH: &[u8] = b"notmatch";
+
+        const VAL_A: &[u8] = b"valA";
+        const VAL_B: &[u8] = b"valB";
+        const VAL_C: &[u8] = b"valC";
+        const VAL_D: &[u8] = b"valD";
+
+        fn create_database_with_keys_two() -> Database {
+            let mut database = create_database();
+
+            database.set(KEY_A, VAL_A).unwrap();
+            database.set(KEY_B, VAL_B).unwrap();
+            database.set(KEY_C, VAL_C).unwrap();
+            database.set(NO_MATCH, VAL_D).unwrap();
+
+            database
+        }
+
+        #[test]
+        fn test_keys_obtain_all_keys_with_an_asterisk_in_the_middle() {
+            let mut database = create_database_with_keys_two();
+
+            if let Ok(SuccessQuery::List(list)) = database.keys(b"k*y") {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+
+                assert!(list.contains(&KEY_A.to_owned()));
+                assert!(list.contains(&KEY_B.to_owned()));
+                assert!(list.contains(&KEY_C.to_owned()));
+            }
+        }
+
+        #[test]
+        fn test_keys_obtain_all_keys_with_question_in_the_middle() {
+            let mut database = create_database_with_keys_two();
+
+            if let Ok(SuccessQuery::List(list)) = database.keys(b"k?y") {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+
+                assert!(list.contains(&KEY_A.to_owned()));
+            }
+        }
+
+        const HALL: &[u8] = b"hall";
+        const HELLO: &[u8] = b"hello";
+        const HEEEELLO: &[u8] = b"heeeeeello";
+        const HALLO: &[u8] = b"hallo";
+        const HXLLO: &[u8] = b"hxllo";
+        const HLLO: &[u8] = b"hllo";
+        const RHLLO: &[u8] = br"h\llo";
+        const AHLLO: &[u8] = b"ahllo";
+        const HALLOWN: &[u8] = b"hallown";
+
+        fn create_database_with_keys_three() -> Database {
+            let mut database = create_database();
+            database.set(HELLO, VAL_A).unwrap();
+            database.set(HEEEELLO, VAL_A).unwrap();
+            database.set(HALLO, VAL_B).unwrap();
+            database.set(HXLLO, VAL_C).unwrap();
+            database.set(HLLO, VAL_C).unwrap();
+            database.set(RHLLO, VAL_D).unwrap();
+            database.set(AHLLO, VAL_D).unwrap();
+            database.set(HALLOWN, VAL_D).unwrap();
+            database.set(HALL, VAL_D).unwrap();
+
+            database
+        }
+
+        #[test]
+        fn test_keys_obtain_keys_with_h_question_llo_matches_correctly() {
+            let mut database = create_database_with_keys_three();
 
-                        Ok(SuccessQuery::Integer(list.len() as i32))
-                    }
-                    Ordering::Equal => {
-                        list.retain(|x| *x != elem);
-                        Ok(SuccessQuery::Integer(list.len() as i32))
-                    }
-                }
+            if let Ok(SuccessQuery::List(list)) = database.keys(b"h?llo") {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+
+                assert!(list.contains(&HELLO.to_owned()));
+                assert!(list.contains(&HXLLO.to_owned()));
+                assert!(list.contains(&RHLLO.to_owned()));
+                assert!(list.contains(&HXLLO.to_owned()));
+                assert!(list.contains(&RHLLO.to_owned()));
             }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Ok(SuccessQuery::Integer(0)),
         }
-    }
 
-    /// Sets the list element at index to element. For more information on the index argument, see lindex.
-    ///
-    /// An error is returned for out of range indexes.
-    ///
-    /// Reply: SuccessQuery::Success if set the list element correctly.
-    ///
-    /// # Examples
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.lpush(KEY, ["VALUEA", "VALUEB", "VALUEC", "VALUED"].to_vec()).unwrap();
-    ///
-    /// let result = database.lset("KEY", 0, "VALUEA");
-    /// assert_eq!(SuccessQuery::Success, result.unwrap());
-    ///
-    /// let dictionary = database.dictionary;
-    /// let dictionary = dictionary.get_atomic_hash("KEY");
-    /// let dictionary = dictionary.lock().unwrap();
-    ///
-    /// if let StorageValue::List(list) = dictionary.get("KEY").unwrap() {
-    ///     assert_eq!(list[0], "VALUEA");
-    /// }
-    /// ```
-    pub fn lset(
-        &mut self,
-        key: &str,
-        index: usize,
-        value: &str,
-    ) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Err(DataBaseError::NonExistentKey);
+        #[test]
+        fn test_keys_obtain_keys_with_h_asterisk_llo_matches_correctly() {
+            let mut database = create_database_with_keys_three();
+
+            if let Ok(SuccessQuery::List(list)) = database.keys(b"h*llo") {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+
+                assert!(list.contains(&HELLO.to_owned()));
+                assert!(list.contains(&HALLO.to_owned()));
+                assert!(list.contains(&HEEEELLO.to_owned()));
+                assert!(list.contains(&HELLO.to_owned()));
+            }
         }
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                *last_access = SystemTime::now();
-                match list.get_mut(index) {
-                    Some(val) => {
-                        val.clear();
-                        val.push_str(value);
-                        Ok(SuccessQuery::Success)
-                    }
-                    None => Err(DataBaseError::IndexOutOfRange),
-                }
+        #[test]
+        fn test_keys_obtain_keys_with_nomatch_returns_empty_list() {
+            let mut database = create_database_with_keys();
+
+            if let SuccessQuery::List(list) = database.keys(b"nomatch").unwrap() {
+                assert_eq!(list.len(), 0);
             }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Err(DataBaseError::NonExistentKey),
         }
     }
 
-    /// Removes and returns the last elements of the list stored at key.
-    /// By default, the command pops a single element from the end of the list.
-    ///
-    /// Reply: SuccessQuery::String(val) when val is the value of the last element, or SuccessQuery::Nil when key does not exist.
-    ///
-    /// # Examples
-    /// ```
-    /// todo
-    /// ```
-    pub fn rpop(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Nil);
+    mod rename_test {
+        use super::*;
+
+        #[test]
+        fn test_rename_key_with_mykey_get_hello() {
+            let mut database = create_database();
+            database.set(KEY, VALUE).unwrap();
+
+            let result = database.rename(KEY, SECOND_KEY).unwrap();
+            assert_eq!(result, SuccessQuery::Success);
+
+            let result = database.get(KEY).unwrap();
+            assert_eq!(result, SuccessQuery::Nil);
         }
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                *last_access = SystemTime::now();
-                match list.pop() {
-                    Some(value) => Ok(SuccessQuery::String(value)),
-                    None => Ok(SuccessQuery::Nil),
-                }
-            }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Ok(SuccessQuery::Nil),
+        #[test]
+        fn test_rename_key_non_exists_error() {
+            let mut database = create_database();
+
+            let result = database.rename(KEY, SECOND_KEY).unwrap_err();
+
+            assert_eq!(result, DataBaseError::NonExistentKey);
         }
     }
 
-    /// Insert all the specified values at the tail of the list stored at key.
-    /// If key does not exist, it is created as empty list before performing the push operation.
-    /// When key holds a value that is not a list, an error is returned.
-    ///
-    /// It is possible to push multiple elements using a single command call just specifying multiple arguments at the end of the command.
-    /// Elements are inserted one after the other to the tail of the list, from the leftmost element to the rightmost element.
-    ///
-    /// So for instance the command RPUSH mylist a b c will result into a list containing a as first element, b as second element and c as third element.
-    ///
-    /// Reply: the length of the list after the push operation.
-    ///
-    /// # Examples
-    /// ```
-    /// todo
-    /// ```
-    pub fn rpush(&mut self, key: &str, values: Vec<&str>) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            let mut list: Vec<String> = Vec::new();
-            values.iter().for_each(|&val| {
-                list.push(val.to_owned());
-            });
+    mod sort_test {
+        use super::*;
+        const LIST: &[u8] = b"list";
+        const SET: &[u8] = b"set";
+        const VALUE_1: &[u8] = b"1";
+        const VALUE_2: &[u8] = b"2";
+        const VALUE_3: &[u8] = b"3";
+        const VALUE_A: &[u8] = b"a";
 
-            let len = list.len();
-            self.dictionary
-                .insert(key.to_owned(), StorageValue::List(list));
-            return Ok(SuccessQuery::Integer(len as i32));
-        }
+        const LIMIT_OFFSET_OFF: i32 = 0;
+        const LIMIT_COUNT_OFF: i32 = -1;
+        const LIMIT_COUNT_ZERO: i32 = 0;
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                *last_access = SystemTime::now();
-                values.iter().for_each(|&val| {
-                    list.push(val.to_owned());
+        const PATTERN: &[u8] = b"weight_*";
+        const UNMATCH_PATTERN: &[u8] = b"no_exist";
+
+        const KEY_WEIGHT_1: &[u8] = b"weight_1";
+        const KEY_WEIGHT_2: &[u8] = b"weight_2";
+        const KEY_WEIGHT_3: &[u8] = b"weight_3";
+
+        const VAL_WEIGHT_1: &[u8] = b"10";
+        const VAL_WEIGHT_2: &[u8] = b"30";
+        const VAL_WEIGHT_3: &[u8] = b"20";
+        const VAL_WEIGHT_2_NOT_NUMBER: &[u8] = b"a";
+
+        #[test]
+        fn test_sort_list_without_flags_return_sorted_list_with_numbers_ascending() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_3, VALUE_1, VALUE_2].to_vec())
+                .unwrap();
+
+            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::WithoutFlags).unwrap()
+            {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_1, VALUE_2, VALUE_3];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
                 });
-                Ok(SuccessQuery::Integer(list.len() as i32))
             }
-            _ => Err(DataBaseError::NotAList),
         }
-    }
 
-    /// Inserts specified values at the tail of the list stored at key, only if key already exists and holds a list.
-    /// In contrary to RPUSH, no operation will be performed when key does not yet exist.
-    ///
-    /// Reply: SuccessQuery:Integer(n) when n is the length of the list after the push operation.
-    ///
-    /// # Examples
-    /// ```
-    /// todo
-    /// ```
-    pub fn rpushx(&mut self, key: &str, values: Vec<&str>) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Boolean(false));
+        #[test]
+        fn test_sort_list_without_flags_return_err_if_elem_in_list_are_not_numbers() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_2, VALUE_A].to_vec())
+                .unwrap();
+
+            let result = database.sort(LIST, SortFlags::WithoutFlags);
+
+            assert_eq!(result.unwrap_err(), DataBaseError::SortParseError);
         }
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::List(list), last_access)) => {
-                *last_access = SystemTime::now();
-                values.iter().for_each(|&val| {
-                    list.push(val.to_owned());
+
+        #[test]
+        fn test_sort_list_with_alpha_on_return_sorted_list_with_numbers_and_string_values() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_A, VALUE_2, VALUE_3].to_vec())
+                .unwrap();
+
+            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::Alpha).unwrap() {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_2, VALUE_3, VALUE_A];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
                 });
-                Ok(SuccessQuery::Integer(list.len() as i32))
             }
-            Some(_) => Err(DataBaseError::NotAList),
-            None => Ok(SuccessQuery::Boolean(false)),
         }
-    }
 
-    //SETS
+        #[test]
+        fn test_sort_list_with_desc_on_return_sorted_list_with_numbers_descending() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .unwrap();
 
-    /// Returns if member is a member of the set stored at key.
-    ///
-    /// Reply: SuccessQuery::Boolean(true) if the element is a member of the set.
-    ///
-    /// SuccessQuery::Boolean(false) if the element is not a member of the set, or if key does not exist.
-    ///
-    /// Error if key of database exists but not hold a Set.
-    /// # Example
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// let result = database.sadd("key", ["element"].to_vec()).unwrap();
-    /// assert_eq!(result, SuccessQuery::Integer(1));
-    /// let is_member = database.sismember("key", "element").unwrap();
-    /// assert_eq!(is_member, SuccessQuery::Boolean(true));
-    /// ```
-    pub fn sismember(&mut self, key: &str, value: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Boolean(false));
+            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::Desc).unwrap() {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_3, VALUE_2, VALUE_1];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                });
+            }
         }
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::Set(hash_set), last_access)) => {
-                *last_access = SystemTime::now();
-                match hash_set.get(value) {
-                    Some(_val) => Ok(SuccessQuery::Boolean(true)),
-                    None => Ok(SuccessQuery::Boolean(false)),
-                }
+        #[test]
+        fn test_sort_list_with_limit_offset_count_0_return_empty_list() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .unwrap();
+
+            if let SuccessQuery::List(list) = database
+                .sort(LIST, SortFlags::Limit(LIMIT_OFFSET_OFF, LIMIT_COUNT_ZERO))
+                .unwrap()
+            {
+                assert!(list.is_empty());
             }
-            Some(_) => Err(DataBaseError::NotASet),
-            None => Ok(SuccessQuery::Boolean(false)),
         }
-    }
 
-    /// Returns the set cardinality (number of elements) of the set stored at key.
-    ///
-    /// Reply: SuccessQuery::Integer(n) when n is the cardinality (number of elements) of the set,
-    /// or SuccessQuery::Boolean(false) if key does not exist.
-    ///
-    /// Error if key of database exists but not hold a Set.
-    /// # Example
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// let elements = vec!["0", "1", "2", "3"];
-    ///
-    /// let _ = database.sadd("key", elements);
-    /// let len_set = database.scard("key").unwrap();
-    ///
-    /// assert_eq!(len_set, SuccessQuery::Integer(4));
-    /// ```
-    pub fn scard(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Boolean(false));
-        }
+        #[test]
+        fn test_sort_list_with_limit_offset_count_in_range_of_list_return_sorted_list_with_count_elem(
+        ) {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .unwrap();
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::Set(hash_set), last_access)) => {
-                *last_access = SystemTime::now();
-                Ok(SuccessQuery::Integer(hash_set.len() as i32))
+            if let SuccessQuery::List(list) = database
+                .sort(LIST, SortFlags::Limit(LIMIT_OFFSET_OFF, 2))
+                .unwrap()
+            {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_1, VALUE_2];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                });
+                assert_eq!(list.len(), 2);
             }
-            Some(_) => Err(DataBaseError::NotASet),
-            None => Ok(SuccessQuery::Boolean(false)),
         }
-    }
 
-    #[doc(hidden)]
-    pub fn sadd_one(&mut self, key: &str, value: &str) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            let mut set: HashSet<String> = HashSet::new();
-            set.insert(value.to_owned());
-            self.dictionary
-                .insert(key.to_owned(), StorageValue::Set(set));
-            return Ok(SuccessQuery::Integer(1));
-        }
+        #[test]
+        fn test_sort_list_with_limit_offset_count_negative_return_sorted_list_with_all_elements() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .unwrap();
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::Set(hash_set), last_access)) => {
-                *last_access = SystemTime::now();
-                if hash_set.contains(value) {
-                    Ok(SuccessQuery::Integer(0))
-                } else {
-                    hash_set.insert(value.to_owned());
-                    Ok(SuccessQuery::Integer(1))
-                }
+            if let SuccessQuery::List(list) = database
+                .sort(LIST, SortFlags::Limit(LIMIT_OFFSET_OFF, -1))
+                .unwrap()
+            {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_1, VALUE_2, VALUE_3];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                });
+                assert_eq!(list.len(), 3);
             }
-            _ => Err(DataBaseError::NotASet),
         }
-    }
 
-    /// Add the specified members to the set stored at key. Specified members that are already a member of this set are ignored.
-    /// If key does not exist, a new set is created before adding the specified members.
-    /// An error is returned when the value stored at key is not a set.
-    ///
-    /// Reply: SuccessQuery::Integer(n) when n is the number of elements that were added to the set,
-    /// not including all the elements already present in the set.
-    ///
-    /// # Example
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.sadd("KEY", ["ELEMENT"].to_vec()).unwrap();
-    /// let result = database.sadd("KEY", ["ELEMENT", "ELEMENT_2", "ELEMENT_3"].to_vec()).unwrap();
-    ///
-    /// assert_eq!(result, SuccessQuery::Integer(2));
-    /// let len_set = database.scard("KEY").unwrap();
-    /// assert_eq!(len_set, SuccessQuery::Integer(3));
-    /// ```
-    pub fn sadd(&mut self, key: &str, values: Vec<&str>) -> Result<SuccessQuery, DataBaseError> {
-        let mut elems_added = 0;
-        let mut result = self.sadd_one(key, values[0]);
-        if let Ok(SuccessQuery::Integer(val)) = result {
-            elems_added += val;
-        }
-        for item in values.iter().skip(1) {
-            result = self.sadd_one(key, item);
-            if let Ok(SuccessQuery::Integer(val)) = result {
-                elems_added += val;
+        #[test]
+        fn test_sort_list_with_limit_offset_neg_count_0_return_emptylist() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .unwrap();
+
+            if let SuccessQuery::List(list) = database
+                .sort(LIST, SortFlags::Limit(-2, LIMIT_COUNT_ZERO))
+                .unwrap()
+            {
+                assert!(list.is_empty());
             }
         }
-        match result {
-            Ok(SuccessQuery::Integer(_val)) => Ok(SuccessQuery::Integer(elems_added)),
-            Err(err) => Err(err),
-            _ => Err(DataBaseError::NotASet),
-        }
-    }
 
-    /// Returns all the members of the set value stored at key.
-    ///
-    /// Reply: SuccessQuery::List(list) when list are all elements of the set.
-    ///
-    /// # Example
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// database.sadd("KEY", ["ELEMENT"].to_vec()).unwrap();
-    /// database.sadd("KEY", ["OTHER_ELEMENT"].to_vec()).unwrap();
-    ///
-    /// if let SuccessQuery::List(list) = database.smembers("KEY").unwrap() {
-    ///     for elem in list {
-    ///         let is_member = database.sismember("KEY", &elem.to_string()).unwrap();
-    ///         assert_eq!(is_member, SuccessQuery::Boolean(true));
-    ///     }
-    /// }
-    /// ```
-    pub fn smembers(&mut self, key: &str) -> Result<SuccessQuery, DataBaseError> {
-        let mut result: Vec<SuccessQuery> = Vec::new();
-        if !self._exists(key) {
-            return Ok(SuccessQuery::List(result));
-        }
+        #[test]
+        fn test_sort_list_with_limit_offset_out_of_range_of_list_count_return_empty_list() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .unwrap();
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::Set(hash_set), last_access)) => {
-                *last_access = SystemTime::now();
-                for elem in hash_set.iter() {
-                    result.push(SuccessQuery::String(elem.clone()));
-                }
-                Ok(SuccessQuery::List(result))
+            if let SuccessQuery::List(list) = database
+                .sort(LIST, SortFlags::Limit(4, LIMIT_COUNT_OFF))
+                .unwrap()
+            {
+                assert!(list.is_empty());
             }
-            Some(_) => Err(DataBaseError::NotASet),
-            None => Ok(SuccessQuery::List(result)),
         }
-    }
 
-    /// Remove the specified members from the set stored at key.
-    /// Specified members that are not a member of this set are ignored.
-    ///
-    /// If key does not exist, it is treated as an empty set and this command returns 0.
-    /// An error is returned when the value stored at key is not a set.
-    ///
-    /// Reply: SuccessQuery::Integer(n) when n is the number of members that were removed from the set, not including non existing members.
-    ///
-    /// # Example
-    /// ```
-    /// let mut database = Database::new("dump_path.txt");
-    /// let members = vec!["ELEMENT"];
-    ///
-    /// database.sadd("KEY", ["ELEMENT"].to_vec()).unwrap();
-    /// let result = database.srem("KEY", members).unwrap();
-    /// let is_member = database.sismember("KEY", "ELEMENT").unwrap();
-    ///
-    /// assert_eq!(result, SuccessQuery::Integer(1));
-    /// assert_eq!(is_member, SuccessQuery::Boolean(false));
-    /// ```
-    pub fn srem(
-        &mut self,
-        key: &str,
-        members_to_rmv: Vec<&str>,
-    ) -> Result<SuccessQuery, DataBaseError> {
-        if !self._exists(key) {
-            return Ok(SuccessQuery::Boolean(false));
-        }
+        #[test]
+        fn test_sort_list_with_limit_offset_negative_count_negative_return_sorted_list_with_all_elements(
+        ) {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .unwrap();
 
-        let dictionary = self.dictionary.get_atomic_hash(key);
-        let mut dictionary = dictionary.lock().unwrap();
-        match dictionary.get_mut(key) {
-            Some((StorageValue::Set(hash_set), last_access)) => {
-                *last_access = SystemTime::now();
-                let mut count: i32 = 0;
-                for member in members_to_rmv {
-                    if let Some(_mem) = hash_set.take(member) {
-                        count += 1;
-                    }
-                }
-                Ok(SuccessQuery::Integer(count))
+            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::Limit(-2, -2)).unwrap()
+            {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_1, VALUE_2, VALUE_3];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                });
+                assert_eq!(list.len(), 3);
             }
-            Some(_) => Err(DataBaseError::NotASet),
-            None => Ok(SuccessQuery::Boolean(false)),
         }
-    }
-}
 
-impl<'a> Clone for Database {
-    fn clone(&self) -> Self {
-        Database::new_from_db(
-            self.ttl_msg_sender.clone(),
-            self.dictionary.clone(),
-            self.db_dump_path.clone(),
-        )
-    }
-}
+        #[test]
+        fn test_sort_list_with_limit_offset_in_range_count_2_return_sorted_sublist_of_2_elements() {
+            let mut database = create_database();
+            database
+                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .unwrap();
 
-#[doc(hidden)]
-fn executor(mut dictionary: HashShard, ttl_vector: TtlVector) {
-    loop {
-        thread::sleep(Duration::new(30, 0));
-        let keys_ttl = ttl_vector.clone();
-        let mut keys_locked = keys_ttl.lock().unwrap();
-
-        while let Some(ttl) = keys_locked.get(0) {
-            if ttl.expire_time < SystemTime::now() {
-                let ttl_key = keys_locked.remove(0);
-                dictionary.remove(&ttl_key.key);
-            } else {
-                break;
+            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::Limit(1, 2)).unwrap() {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_2, VALUE_3];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                });
+                assert_eq!(list.len(), 2);
             }
         }
 
-        if keys_locked.is_empty() {
-            break;
-        }
-    }
-}
+        #[test]
+        fn test_sort_set_by_weight_return_list_ordered_by_weight() {
+            let mut database = create_database();
 
-impl fmt::Display for Database {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        for (key, value) in self.dictionary.key_value() {
-            writeln!(f, "key: {}, value: {}", key, value)?;
+            database.set(KEY_WEIGHT_3, VAL_WEIGHT_3).unwrap();
+            database.set(KEY_WEIGHT_1, VAL_WEIGHT_1).unwrap();
+            database.set(KEY_WEIGHT_2, VAL_WEIGHT_2).unwrap();
+
+            database.sadd(SET, [VALUE_1].to_vec()).unwrap();
+            database.sadd(SET, [VALUE_3].to_vec()).unwrap();
+            database.sadd(SET, [VALUE_2].to_vec()).unwrap();
+
+            if let SuccessQuery::List(list) = database.sort(SET, SortFlags::By(PATTERN)).unwrap() {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_1, VALUE_3, VALUE_2];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                });
+                assert_eq!(list.len(), 3);
+            }
         }
-        Ok(())
-    }
-}
 
-#[cfg(test)]
-mod ttl_commands {
-    use super::*;
-    use std::time::Duration;
+        #[test]
+        fn test_sort_list_by_weight_with_pattern_that_no_match_return_list_unordered() {
+            let mut database = create_database();
+
+            database.set(KEY_WEIGHT_3, VAL_WEIGHT_3).unwrap();
+            database.set(KEY_WEIGHT_1, VAL_WEIGHT_1).unwrap();
+            database.set(KEY_WEIGHT_2, VAL_WEIGHT_2).unwrap();
 
-    const KEY_A: &str = "KEY_A";
-    const VALUE_A: &str = "VALUE_A";
+            database.rpush(LIST, vec![VALUE_1]).unwrap();
+            database.rpush(LIST, vec![VALUE_3]).unwrap();
+            database.rpush(LIST, vec![VALUE_2]).unwrap();
 
-    const KEY_B: &str = "KEY_B";
-    const VALUE_B: &str = "VALUE_B";
+            if let SuccessQuery::List(list) =
+                database.sort(LIST, SortFlags::By(UNMATCH_PATTERN)).unwrap()
+            {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_1, VALUE_3, VALUE_2];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                });
+                assert_eq!(list.len(), 3);
+            }
+        }
 
-    const KEY_C: &str = "KEY_C";
-    const VALUE_C: &str = "VALUE_C";
+        #[test]
+        fn test_sort_set_by_weight_if_weight_of_elem_that_does_not_match_weight_is_set_to_zero_and_return_list_ordered_by_pattern(
+        ) {
+            let mut database = create_database();
 
-    const KEY_D: &str = "KEY_D";
-    const VALUE_D: &str = "VALUE_D";
+            database.set(KEY_WEIGHT_3, VAL_WEIGHT_3).unwrap();
+            database.set(KEY_WEIGHT_1, VAL_WEIGHT_1).unwrap();
+            // Weight of VALUE_2 is zero.
 
-    const DB_DUMP: &str = "db_dump_path";
+            database.sadd(SET, [VALUE_1].to_vec()).unwrap();
+            database.sadd(SET, [VALUE_3].to_vec()).unwrap();
+            database.sadd(SET, [VALUE_2].to_vec()).unwrap();
 
-    // duration_since
+            if let SuccessQuery::List(list) = database.sort(SET, SortFlags::By(PATTERN)).unwrap() {
+                let list_result: Vec<Vec<u8>> =
+                    list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let to_compare_list: Vec<&[u8]> = vec![VALUE_2, VALUE_1, VALUE_3];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> =
+                    list_result.iter().zip(to_compare_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                });
+                assert_eq!(list.len(), 3);
+            }
+        }
 
-    #[test]
-    fn ttl_supervisor_run_supervaise_a_key() {
-        let mut db = Database::new(DB_DUMP.to_string());
+        #[test]
+        fn test_sort_set_by_weight_and_only_weight_isnt_a_number_return_err() {
+            let mut database = create_database();
 
-        db.append(KEY_A, VALUE_A).unwrap();
+            database.set(KEY_WEIGHT_3, VAL_WEIGHT_3).unwrap();
+            database.set(KEY_WEIGHT_1, VAL_WEIGHT_1).unwrap();
+            database.set(KEY_WEIGHT_2, VAL_WEIGHT_2_NOT_NUMBER).unwrap();
 
-        let now = SystemTime::now();
-        let expire_time_a = now.checked_add(Duration::new(1, 0)).unwrap();
-        let ttl_pair = KeyTtl::new(KEY_A, expire_time_a);
+            database.sadd(SET, [VALUE_1].to_vec()).unwrap();
+            database.sadd(SET, [VALUE_3].to_vec()).unwrap();
+            database.sadd(SET, [VALUE_2].to_vec()).unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, true);
+            let result = database.sort(SET, SortFlags::By(PATTERN));
+            assert_eq!(result.unwrap_err(), DataBaseError::SortByParseError);
         }
 
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair))
-            .unwrap();
+        #[test]
+        fn test_sort_list_by_hash_field_return_list_ordered_by_field() {
+            let mut database = create_database();
 
-        thread::sleep(Duration::new(2, 0));
+            database.hset(b"object_1", vec![b"weight", b"30"]).unwrap();
+            database.hset(b"object_2", vec![b"weight", b"10"]).unwrap();
+            // object_3 has no weight field, so its weight is zero.
+            database.hset(b"object_3", vec![b"name", b"3"]).unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, false);
+            database.rpush(LIST, vec![b"1", b"2", b"3"]).unwrap();
+
+            let result = database
+                .sort(LIST, SortFlags::By(b"object_*->weight"))
+                .unwrap();
+            let expected = vec![
+                SuccessQuery::String(b"3".to_vec()),
+                SuccessQuery::String(b"2".to_vec()),
+                SuccessQuery::String(b"1".to_vec()),
+            ];
+            assert_eq!(result, SuccessQuery::List(expected));
         }
     }
+}
 
-    #[test]
-    fn ttl_supervisor_run_supervaise_two_key() {
-        let mut db = Database::new(DB_DUMP.to_string());
+#[cfg(test)]
+mod group_list {
 
-        db.append(KEY_A, VALUE_A).unwrap();
-        db.append(KEY_B, VALUE_B).unwrap();
+    const KEY: &[u8] = b"KEY";
+    const VALUE: &[u8] = b"VALUE";
 
-        let now = SystemTime::now();
-        let expire_time_a = now.checked_add(Duration::new(1, 0)).unwrap();
-        let expire_time_b = now.checked_add(Duration::new(5, 0)).unwrap();
+    const VALUEA: &[u8] = b"ValueA";
+    const VALUEB: &[u8] = b"ValueB";
+    const VALUEC: &[u8] = b"ValueC";
+    const VALUED: &[u8] = b"ValueD";
 
-        let ttl_pair_a = KeyTtl::new(KEY_A, expire_time_a);
-        let ttl_pair_b = KeyTtl::new(KEY_B, expire_time_b);
+    const DB_DUMP: &str = "db_dump_path.txt";
+    use super::*;
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, true);
-        }
+    fn create_database() -> Database {
+        Database::new(DB_DUMP.to_string(), 16, 16).unwrap()
+    }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, true);
-        }
+    fn database_with_a_list() -> Database {
+        let mut database = create_database();
 
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_a))
-            .unwrap();
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_b))
+        database
+            .lpush(KEY, [VALUEA, VALUEB, VALUEC, VALUED].to_vec())
             .unwrap();
 
-        thread::sleep(Duration::new(2, 0));
+        database
+    }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, false);
-        }
+    fn database_with_a_three_repeated_values() -> Database {
+        let mut database = create_database();
 
-        thread::sleep(Duration::new(4, 0));
+        database
+            .lpush(KEY, [VALUEA, VALUEA, VALUEC, VALUEA].to_vec())
+            .unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, false);
-        }
+        database
     }
 
-    const SEC: u64 = 1;
-
-    #[test]
-    fn ttl_supervisor_run_supervaise_four_keys() {
-        let mut db = Database::new(DB_DUMP.to_string());
+    fn database_with_a_string() -> Database {
+        let mut database = create_database();
 
-        db.append(KEY_A, VALUE_A).unwrap();
-        db.append(KEY_B, VALUE_B).unwrap();
-        db.append(KEY_C, VALUE_C).unwrap();
-        db.append(KEY_D, VALUE_D).unwrap();
+        database.append(KEY, VALUE).unwrap();
 
-        let now = SystemTime::now();
-        let expire_time_a = now.checked_add(Duration::from_secs(SEC)).unwrap();
-        let expire_time_b = now.checked_add(Duration::from_secs(SEC * 3)).unwrap();
-        let expire_time_c = now.checked_add(Duration::from_secs(SEC * 5)).unwrap();
-        let expire_time_d = now.checked_add(Duration::from_secs(SEC * 10)).unwrap();
+        database
+    }
 
-        let ttl_pair_a = KeyTtl::new(KEY_A, expire_time_a);
-        let ttl_pair_b = KeyTtl::new(KEY_B, expire_time_b);
-        let ttl_pair_c = KeyTtl::new(KEY_C, expire_time_c);
-        let ttl_pair_d = KeyTtl::new(KEY_D, expire_time_d);
+    mod llen_test {
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, true);
-        }
+        use super::*;
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, true);
-        }
+        #[test]
+        fn test_llen_on_a_non_existent_key_gets_len_zero() {
+            let mut database = create_database();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, true);
-        }
+            let result = database.llen(KEY);
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
-            assert_eq!(value, true);
+            assert_eq!(result.unwrap(), SuccessQuery::Integer(0));
         }
 
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_a))
-            .unwrap();
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_b))
-            .unwrap();
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_c))
-            .unwrap();
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_d))
-            .unwrap();
-
-        thread::sleep(Duration::from_secs(SEC * 2));
+        #[test]
+        fn test_llen_on_a_list_with_one_value() {
+            let mut database = create_database();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, false);
-        }
+            database.lpush(KEY, [VALUE].to_vec()).unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, true);
-        }
+            let result = database.llen(KEY);
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, true);
+            assert_eq!(result.unwrap(), SuccessQuery::Integer(1));
         }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
-            assert_eq!(value, true);
-        }
+        #[test]
+        fn test_llen_on_a_list_with_more_than_one_value() {
+            let mut database = database_with_a_list();
 
-        thread::sleep(Duration::from_secs(SEC * 2));
+            let result = database.llen(KEY).unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, false);
-        }
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, true);
+            assert_eq!(result, SuccessQuery::Integer(4));
         }
 
-        thread::sleep(Duration::from_secs(SEC));
+        #[test]
+        fn test_llen_on_an_existent_key_that_isnt_a_list() {
+            let mut database = database_with_a_string();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, false);
-        }
+            let result = database.llen(KEY);
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
-            assert_eq!(value, true);
+            assert_eq!(result.unwrap_err(), DataBaseError::NotAList);
         }
+    }
 
-        thread::sleep(Duration::from_secs(SEC + 4));
+    mod lindex_test {
+        use super::*;
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, false);
-        }
+        #[test]
+        fn test_lindex_on_a_non_existent_key() {
+            let mut database = create_database();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, false);
-        }
+            let result = database.lindex(KEY, 10);
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
-            assert_eq!(value, false);
+            assert_eq!(result.unwrap(), SuccessQuery::Nil);
         }
-    }
-
-    #[test]
-    fn ttl_supervisor_run_supervaise_four_keys_one_of_the_key_is_inserted_with_a_lower_expire_time_the_actual_key(
-    ) {
-        let mut db = Database::new(DB_DUMP.to_string());
-
-        db.append(KEY_A, VALUE_A).unwrap();
-        db.append(KEY_B, VALUE_B).unwrap();
-        db.append(KEY_C, VALUE_C).unwrap();
-        db.append(KEY_D, VALUE_D).unwrap();
-
-        let now = SystemTime::now();
-        let expire_time_a = now.checked_add(Duration::from_secs(SEC)).unwrap();
-        let expire_time_b = now.checked_add(Duration::from_secs(SEC * 3)).unwrap();
-        let expire_time_c = now.checked_add(Duration::from_secs(SEC * 5)).unwrap();
-        let expire_time_d = now.checked_add(Duration::from_secs(SEC * 10)).unwrap();
 
-        let ttl_pair_a = KeyTtl::new(KEY_A, expire_time_a);
-        let ttl_pair_b = KeyTtl::new(KEY_B, expire_time_b);
-        let ttl_pair_c = KeyTtl::new(KEY_C, expire_time_c);
-        let ttl_pair_d = KeyTtl::new(KEY_D, expire_time_d);
+        #[test]
+        fn test_lindex_with_a_list_with_one_value_on_idex_zero() {
+            let mut database = create_database();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, true);
-        }
+            database.lpush(KEY, [VALUE].to_vec()).unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, true);
+            if let SuccessQuery::String(value) = database.lindex(KEY, 0).unwrap() {
+                assert_eq!(value, VALUE);
+            }
         }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, true);
-        }
+        #[test]
+        fn test_lindex_with_more_than_one_value() {
+            let mut database = database_with_a_list();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
-            assert_eq!(value, true);
+            if let SuccessQuery::String(value) = database.lindex(KEY, 3).unwrap() {
+                assert_eq!(value, VALUEA);
+            }
         }
 
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_b))
-            .unwrap();
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_c))
-            .unwrap();
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_a))
-            .unwrap();
-        db.ttl_msg_sender
-            .send(MessageTtl::Expire(ttl_pair_d))
-            .unwrap();
-
-        thread::sleep(Duration::from_secs(SEC * 2));
+        #[test]
+        fn test_lindex_with_more_than_one_value_with_a_negative_index() {
+            let mut database = database_with_a_list();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, false);
+            if let SuccessQuery::String(value) = database.lindex(KEY, -1).unwrap() {
+                assert_eq!(value, VALUEA);
+            }
         }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, true);
-        }
+        #[test]
+        fn test_lindex_on_a_reverse_out_of_bound() {
+            let mut database = database_with_a_list();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, true);
-        }
+            let result = database.lindex(KEY, -5);
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, true);
+            assert_eq!(result.unwrap(), SuccessQuery::Nil);
         }
 
-        thread::sleep(Duration::from_secs(SEC * 2));
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, false);
-        }
+        #[test]
+        fn test_lindex_on_an_out_of_bound() {
+            let mut database = database_with_a_list();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, false);
-        }
+            let result = database.lindex(KEY, 100);
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, true);
+            assert_eq!(result.unwrap(), SuccessQuery::Nil);
         }
+    }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
-            assert_eq!(value, true);
-        }
+    mod lpop_test {
+        use super::*;
 
-        thread::sleep(Duration::from_secs(SEC));
+        #[test]
+        fn test_lpop_on_a_non_existent_key() {
+            let mut database = create_database();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, false);
-        }
+            let result = database.lpop(KEY).unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, false);
+            assert_eq!(result, SuccessQuery::Nil);
         }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, false);
-        }
+        #[test]
+        fn test_lpop_with_a_list_with_one_value_on_idex_zero() {
+            let mut database = create_database();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
-            assert_eq!(value, true);
-        }
-        thread::sleep(Duration::from_secs(SEC + 4));
+            database.lpush(KEY, [VALUE].to_vec()).unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_A).unwrap() {
-            assert_eq!(value, false);
-        }
+            if let SuccessQuery::String(val) = database.lpop(KEY).unwrap() {
+                assert_eq!(val, VALUE);
+            }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_B).unwrap() {
-            assert_eq!(value, false);
+            let result = database.llen(KEY).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(0));
         }
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_C).unwrap() {
-            assert_eq!(value, false);
-        }
+        #[test]
+        fn test_lpop_with_a_list_with_more_than_one_value() {
+            let mut database = database_with_a_list();
+
+            if let SuccessQuery::String(val) = database.lpop(KEY).unwrap() {
+                assert_eq!(val, VALUED);
+            }
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
 
-        if let SuccessQuery::Boolean(value) = db.exists(KEY_D).unwrap() {
-            assert_eq!(value, false);
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert_eq!(list[0], VALUEC);
+            }
         }
-    }
-}
 
-#[cfg(test)]
-mod group_string {
+        #[test]
+        fn test_lpop_on_an_empty_list() {
+            let mut database = create_database();
 
-    use super::*;
+            database.lpush(KEY, [VALUE].to_vec()).unwrap();
 
-    const KEY: &str = "KEY";
-    const VALUE: &str = "VALUE";
+            if let SuccessQuery::String(val) = database.lpop(KEY).unwrap() {
+                assert_eq!(val, VALUE);
+            }
 
-    const DB_DUMP: &str = "db_dump_path.txt";
+            let value = database.lpop(KEY).unwrap();
+            assert_eq!(value, SuccessQuery::Nil);
 
-    fn create_database_with_string() -> Database {
-        let mut db = Database::new(DB_DUMP.to_string());
-        db.set(KEY, VALUE).unwrap();
-        db
-    }
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
 
-    fn create_database() -> Database {
-        let db = Database::new(DB_DUMP.to_string());
-        db
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert!(list.is_empty());
+            }
+        }
     }
 
-    mod append_test {
+    mod lpush_test {
 
         use super::*;
 
         #[test]
-        fn test_append_new_key_return_lenght_of_the_value() {
+        fn test_lpush_on_a_non_existent_key_creates_a_list_with_new_value() {
             let mut database = create_database();
 
-            if let SuccessQuery::Integer(lenght) = database.append(KEY, VALUE).unwrap() {
-                assert_eq!(lenght, 5);
+            let result = database.lpush(KEY, [VALUE].to_vec()).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
+
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert_eq!(list.len(), 1);
+                assert_eq!(list[0], VALUE);
             }
         }
 
         #[test]
-        fn test_append_key_with_old_value_return_lenght_of_the_total_value() {
+        fn test_lpush_on_an_existent_key_is_valid() {
             let mut database = create_database();
 
-            if let SuccessQuery::Integer(lenght) = database.append(KEY, VALUE).unwrap() {
-                assert_eq!(lenght, 5);
-                if let SuccessQuery::Integer(lenght) = database.append(KEY, VALUE).unwrap() {
-                    assert_eq!(lenght, 10);
-                }
-            }
-        }
-    }
+            database.lpush(KEY, [VALUEA].to_vec()).unwrap();
 
-    mod get_test {
+            let result = database.lpush(KEY, [VALUEB].to_vec()).unwrap();
 
-        use super::*;
+            assert_eq!(result, SuccessQuery::Integer(2));
 
-        #[test]
-        fn test_get_returns_value_of_key() {
-            let mut database = create_database_with_string();
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
 
-            if let SuccessQuery::String(value) = database.get(KEY).unwrap() {
-                assert_eq!(VALUE, value.to_string());
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert_eq!(list.len(), 2);
+                assert_eq!(list[1], VALUEA);
+                assert_eq!(list[0], VALUEB);
             }
         }
 
         #[test]
-        fn test_get_returns_error_if_the_key_does_not_exist() {
+        fn test_lpush_on_an_existent_key_that_isnt_a_list() {
             let mut database = create_database();
 
-            let result = database.get(KEY).unwrap();
+            database.append(KEY, VALUE).unwrap();
 
-            assert_eq!(result, SuccessQuery::Nil);
-        }
-    }
+            let result = database.lpush(KEY, [VALUE].to_vec()).unwrap_err();
 
-    mod set_test {
-        use super::*;
+            assert_eq!(result, DataBaseError::NotAList);
+        }
 
         #[test]
-        fn test_set_returns_ok() {
+        fn test_lpush_more_than_one_value_with_a_key_non_existent() {
             let mut database = create_database();
 
-            let result = database.set(KEY, VALUE).unwrap();
-            assert_eq!(SuccessQuery::Success, result);
-
-            if let SuccessQuery::String(value) = database.get(KEY).unwrap() {
-                assert_eq!(VALUE, value.to_string());
-            }
-        }
-    }
+            let result = database
+                .lpush(KEY, [VALUEA, VALUEB, VALUEC].to_vec())
+                .unwrap();
 
-    mod getdel_test {
-        use super::*;
+            assert_eq!(result, SuccessQuery::Integer(3));
 
-        #[test]
-        fn test_getdel_returns_old_value_of_key_and_after_value_of_key_what_not_exists_in_database_is_nul(
-        ) {
-            let mut database = create_database_with_string();
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
 
-            if let SuccessQuery::String(value) = database.getdel(KEY).unwrap() {
-                assert_eq!(value.to_string(), VALUE);
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert_eq!(list.len(), 3);
+                assert_eq!(list[2], VALUEA);
+                assert_eq!(list[1], VALUEB);
+                assert_eq!(list[0], VALUEC);
             }
-
-            let result = database.get(KEY).unwrap();
-            assert_eq!(result, SuccessQuery::Nil);
         }
 
         #[test]
-        fn test_getdel_returns_nil_value_if_key_not_exist_in_database() {
+        fn test_lpush_more_than_one_value_with_a_key_with_list() {
             let mut database = create_database();
 
-            let result = database.getdel(KEY).unwrap();
-            assert_eq!(result, SuccessQuery::Nil);
+            database.lpush(KEY, [VALUEA].to_vec()).unwrap();
+            let result = database.lpush(KEY, [VALUEB, VALUEC].to_vec()).unwrap();
+
+            assert_eq!(result, SuccessQuery::Integer(3));
+
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
+
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert_eq!(list.len(), 3);
+                assert_eq!(list[2], VALUEA);
+                assert_eq!(list[1], VALUEB);
+                assert_eq!(list[0], VALUEC);
+            }
         }
     }
 
-    mod incrby_test {
+    mod lrange_test {
         use super::*;
 
         #[test]
-        fn test_incrby_returns_lenght_of_the_resulting_value_after_increment() {
+        fn test_lrange_on_zero_zero_range() {
             let mut database = create_database();
 
-            database.set(KEY, "1").unwrap();
-
-            let result = database.incrby(KEY, 4).unwrap();
+            database.lpush(KEY, [VALUE].to_vec()).unwrap();
 
-            assert_eq!(result, SuccessQuery::Integer(5));
+            if let SuccessQuery::List(list) = database.lrange(KEY, 0, 0).unwrap() {
+                assert_eq!(list[0], SuccessQuery::String(VALUE.to_vec()));
+            }
         }
 
         #[test]
-        fn test_incrby_returns_error_if_the_value_of_key_not_hold_parseable_value_to_number() {
-            let mut database = create_database();
+        fn test_lrange_on_zero_two_range_applied_to_a_list_with_four_elements() {
+            let mut database = database_with_a_list();
 
-            database.set(KEY, "1a").unwrap();
+            if let SuccessQuery::List(list) = database.lrange(KEY, 0, 2).unwrap() {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let second_list: Vec<&[u8]> = vec![VALUED, VALUEC, VALUEB];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> = list.iter().zip(second_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                })
+            }
+        }
 
-            let result = database.incrby(KEY, 4).unwrap_err();
+        #[test]
+        fn test_lrange_on_one_three_range_applied_to_a_list_with_four_elements() {
+            let mut database = database_with_a_list();
 
-            assert_eq!(result, DataBaseError::NotAnInteger);
+            if let SuccessQuery::List(list) = database.lrange(KEY, 1, 3).unwrap() {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let second_list: Vec<&[u8]> = vec![VALUEC, VALUEB, VALUEA];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> = list.iter().zip(second_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                })
+            }
         }
-    }
-
-    mod decrby_test {
-        use super::*;
 
         #[test]
-        fn test_decrby_returns_lenght_of_the_resulting_value_after_increment() {
-            let mut database = create_database();
+        fn test_lrange_on_a_superior_out_of_bound() {
+            let mut database = database_with_a_list();
 
-            database.set(KEY, "5").unwrap();
-            let result = database.decrby(KEY, 4).unwrap();
+            let result = database.lrange(KEY, 1, 5).unwrap();
 
-            assert_eq!(result, SuccessQuery::Integer(1));
+            assert_eq!(result, SuccessQuery::List(Vec::new()));
         }
 
         #[test]
-        fn test_decrby_returns_error_if_the_value_of_key_not_hold_parseable_value_to_number() {
-            let mut database = create_database();
+        fn test_lrange_on_an_inferior_out_of_bound() {
+            let mut database = database_with_a_list();
 
-            database.set(KEY, "5a").unwrap();
-            let result = database.decrby(KEY, 4).unwrap_err();
+            let result = database.lrange(KEY, -10, 3).unwrap();
 
-            assert_eq!(result, DataBaseError::NotAnInteger);
+            assert_eq!(result, SuccessQuery::List(Vec::new()));
         }
-    }
-
-    mod strlen_test {
-        use super::*;
 
         #[test]
-        fn test_strlen_returns_the_lenght_of_value_key() {
-            let mut database = create_database();
+        fn test_lrange_on_an_two_way_out_of_bound() {
+            let mut database = database_with_a_list();
 
-            database.set(KEY, VALUE).unwrap();
+            let result = database.lrange(KEY, -10, 10).unwrap();
 
-            if let SuccessQuery::Integer(value) = database.strlen(KEY).unwrap() {
-                assert_eq!(value, 5);
-            }
+            assert_eq!(result, SuccessQuery::List(Vec::new()));
         }
 
         #[test]
-        fn test_strlen_returns_zero_if_the_key_not_exists_in_database() {
-            let mut database = create_database();
+        fn test_lrange_with_valid_negative_bounds() {
+            let mut database = database_with_a_list();
 
-            if let SuccessQuery::Integer(value) = database.strlen(KEY).unwrap() {
-                assert_eq!(value, 0);
+            if let SuccessQuery::List(list) = database.lrange(KEY, -3, -1).unwrap() {
+                let list: Vec<Vec<u8>> = list.iter().map(|x| x.to_string().into_bytes()).collect();
+                let second_list: Vec<&[u8]> = vec![VALUEC, VALUEB, VALUEA];
+                let pair_list: Vec<(&Vec<u8>, &[u8])> = list.iter().zip(second_list).collect();
+                pair_list.iter().for_each(|x| {
+                    assert_eq!(x.0, x.1);
+                })
             }
         }
     }
 
-    mod mset_test {
+    mod lrem_test {
         use super::*;
 
-        const KEY_A: &str = "KEY_A";
-        const VALUE_A: &str = "VALUE_A";
+        #[test]
+        fn test_lrem_2_on_a_list() {
+            let mut database = database_with_a_three_repeated_values();
+
+            let result = database.lrem(KEY, 2, VALUEA);
 
-        const KEY_B: &str = "KEY_B";
-        const VALUE_B: &str = "VALUE_B";
+            assert_eq!(SuccessQuery::Integer(2), result.unwrap());
 
-        const KEY_C: &str = "KEY_C";
-        const VALUE_C: &str = "VALUE_C";
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
 
-        const KEY_D: &str = "KEY_D";
-        const VALUE_D: &str = "VALUE_D";
+            if let Some((StorageValue::List(list), _)) = dictionary.get(KEY) {
+                assert_eq!(list[0], VALUEC);
+                assert_eq!(list[1], VALUEA);
+            }
+        }
 
         #[test]
-        fn test_mset_set_multiple_key_and_value_ok() {
-            let mut database = create_database();
+        fn test_lrem_negative_2_on_a_list() {
+            let mut database = database_with_a_three_repeated_values();
 
-            let vec_key_value = vec![
-                KEY_A, VALUE_A, KEY_B, VALUE_B, KEY_C, VALUE_C, KEY_D, VALUE_D,
-            ];
+            let result = database.lrem(KEY, -2, VALUEA);
 
-            let result = database.mset(vec_key_value).unwrap();
-            assert_eq!(result, SuccessQuery::Success);
+            assert_eq!(SuccessQuery::Integer(2), result.unwrap());
 
-            let result_get1 = database.get(KEY_A).unwrap();
-            let result_get2 = database.get(KEY_B).unwrap();
-            let result_get3 = database.get(KEY_C).unwrap();
-            let result_get4 = database.get(KEY_D).unwrap();
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
 
-            assert_eq!(result_get1, SuccessQuery::String(VALUE_A.to_owned()));
-            assert_eq!(result_get2, SuccessQuery::String(VALUE_B.to_owned()));
-            assert_eq!(result_get3, SuccessQuery::String(VALUE_C.to_owned()));
-            assert_eq!(result_get4, SuccessQuery::String(VALUE_D.to_owned()));
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert_eq!(list[0], VALUEA);
+                assert_eq!(list[1], VALUEC);
+            }
         }
-    }
-
-    mod mget_test {
-        use super::*;
 
-        const KEY_A: &str = "KEY_A";
-        const VALUE_A: &str = "VALUE_A";
+        #[test]
+        fn test_lrem_zero_on_a_list() {
+            let mut database = database_with_a_three_repeated_values();
 
-        const KEY_B: &str = "KEY_B";
-        const VALUE_B: &str = "VALUE_B";
+            let result = database.lrem(KEY, 0, VALUEA);
 
-        const KEY_C: &str = "KEY_C";
-        const VALUE_C: &str = "VALUE_C";
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
 
-        const KEY_D: &str = "KEY_D";
-        const VALUE_D: &str = "VALUE_D";
+            assert_eq!(SuccessQuery::Integer(1), result.unwrap());
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert_eq!(list[0], VALUEC);
+            }
+        }
 
-        fn create_a_database_with_key_values() -> Database {
+        #[test]
+        fn test_lset_with_a_non_existen_key() {
             let mut database = create_database();
 
-            database.set(KEY_A, VALUE_A).unwrap();
-            database.set(KEY_B, VALUE_B).unwrap();
-            database.set(KEY_C, VALUE_C).unwrap();
-            database.set(KEY_D, VALUE_D).unwrap();
+            let result = database.lset(KEY, 0, VALUEA).unwrap_err();
 
-            database
+            assert_eq!(result, DataBaseError::NonExistentKey);
         }
 
         #[test]
-        fn test_mget_return_all_values_of_keys_if_all_keys_are_in_database() {
-            let mut database = create_a_database_with_key_values();
+        fn test_lset_with_a_value_that_isn_a_list() {
+            let mut database = database_with_a_string();
 
-            let vec_keys = vec![KEY_A, KEY_B, KEY_C, KEY_D];
+            let result = database.lset(KEY, 0, VALUEA).unwrap_err();
 
-            if let SuccessQuery::List(list) = database.mget(vec_keys).unwrap() {
-                assert_eq!(list[0], SuccessQuery::String(VALUE_A.to_owned()));
-                assert_eq!(list[1], SuccessQuery::String(VALUE_B.to_owned()));
-                assert_eq!(list[2], SuccessQuery::String(VALUE_C.to_owned()));
-                assert_eq!(list[3], SuccessQuery::String(VALUE_D.to_owned()));
-            }
+            assert_eq!(result, DataBaseError::NotAList);
         }
 
         #[test]
-        fn test_mget_returns_nil_for_some_key_that_no_exists_in_database() {
-            let mut database = create_database();
+        fn test_lset_on_a_list_with_values() {
+            let mut database = database_with_a_list();
 
-            database.set(KEY_A, VALUE_A).unwrap();
-            database.set(KEY_B, VALUE_B).unwrap();
+            let result = database.lset(KEY, 0, VALUEA);
+            assert_eq!(SuccessQuery::Success, result.unwrap());
 
-            let vec_keys = vec![KEY_A, KEY_B, KEY_C, KEY_D];
+            let dictionary = database.dictionary();
+            let dictionary = dictionary.get_atomic_hash(KEY);
+            let dictionary = dictionary.read().unwrap();
 
-            if let SuccessQuery::List(list) = database.mget(vec_keys).unwrap() {
-                assert_eq!(list[0], SuccessQuery::String(VALUE_A.to_owned()));
-                assert_eq!(list[1], SuccessQuery::String(VALUE_B.to_owned()));
-                assert_eq!(list[2], SuccessQuery::Nil);
-                assert_eq!(list[3], SuccessQuery::Nil);
+            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
+                assert_eq!(list[0], VALUEA);
             }
         }
     }
 }
 
 #[cfg(test)]
-mod group_keys {
+mod group_set {
     use super::*;
 
-    const DB_DUMP: &str = "db_dump_path.txt.txt";
+    const KEY: &[u8] = b"KEY";
+    const KEY_WITH_STR: &[u8] = b"KEY_WITH_STRING";
+    const VALUE_A: &[u8] = b"VALUE_A";
+    const NON_EXIST_KEY: &[u8] = b"NON_EXIST_KEY";
+    const NON_EXIST_ELEMENT: &[u8] = b"NON_EXIST_ELEMENT";
+    const ELEMENT: &[u8] = b"ELEMENT";
+    const ELEMENT_2: &[u8] = b"ELEMENT2";
+    const ELEMENT_3: &[u8] = b"ELEMENT3";
+    const OTHER_ELEMENT: &[u8] = b"OTHER_ELEMENT";
+
+    const DB_DUMP: &str = "db_dump_path.txt";
 
     fn create_database() -> Database {
-        let db = Database::new(DB_DUMP.to_string());
-        db
+        Database::new(DB_DUMP.to_string(), 16, 16).unwrap()
     }
 
-    const KEY: &str = "KEY";
-    const SECOND_KEY: &str = "SECOND_KEY";
-
-    const VALUE: &str = "VALUE";
-    const SECOND_VALUE: &str = "SECOND_VALUE";
-
-    mod copy_test {
+    mod saad_test {
         use super::*;
 
         #[test]
-        fn test_copy_set_dolly_sheep_then_copy_to_clone() {
+        fn test_sadd_create_new_set_with_element_returns_1_if_key_set_not_exist_in_database() {
             let mut database = create_database();
 
-            database.set(KEY, VALUE).unwrap();
-            let result = database.copy(KEY, SECOND_KEY);
-            assert_eq!(result.unwrap(), SuccessQuery::Success);
-            if let SuccessQuery::String(value) = database.strlen(SECOND_KEY).unwrap() {
-                assert_eq!(value.to_string(), VALUE);
-            }
+            let result = database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+            let is_member = database.sismember(KEY, ELEMENT).unwrap();
+            assert_eq!(is_member, SuccessQuery::Boolean(true));
         }
 
         #[test]
-        fn test_copy_set_dolly_sheep_then_copy_to_clone_when_clone_exist() {
+        fn test_sadd_create_set_with_repeating_elements_returns_0() {
             let mut database = create_database();
 
-            database.set(KEY, VALUE).unwrap();
-            database.set(SECOND_KEY, SECOND_VALUE).unwrap();
-            let result = database.copy(KEY, SECOND_KEY);
+            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
 
-            assert_eq!(result.unwrap_err(), DataBaseError::KeyAlredyExist);
+            let result = database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(0));
+            let len_set = database.scard(KEY).unwrap();
+            assert_eq!(len_set, SuccessQuery::Integer(1));
         }
 
         #[test]
-        fn test_copy_try_to_copy_a_key_does_not_exist() {
+        fn test_sadd_key_with_another_type_of_set_returns_err() {
             let mut database = create_database();
-            let result = database.copy(KEY, SECOND_KEY);
+            database.set(KEY, ELEMENT).unwrap();
 
-            assert_eq!(result.unwrap_err(), DataBaseError::NonExistentKey);
+            let result = database.sadd(KEY, [ELEMENT].to_vec()).unwrap_err();
+            assert_eq!(result, DataBaseError::NotASet);
         }
-    }
-
-    mod del_test {
-        use super::*;
-
-        #[test]
-        fn test_del_key_value_returns_succes() {
-            let mut database = create_database();
-            database.set(KEY, VALUE).unwrap();
 
-            let result = database.del(KEY);
-            assert_eq!(result.unwrap(), SuccessQuery::Success);
-            assert_eq!(database.get(KEY).unwrap(), SuccessQuery::Nil);
-        }
         #[test]
-        fn test_del_key_non_exist_returns_nil() {
+        fn test_sadd_add_element_with_set_created_returns_1() {
             let mut database = create_database();
+            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
 
-            let result = database.del(KEY);
-            assert_eq!(result.unwrap(), SuccessQuery::Success);
+            let result = database.sadd(KEY, [OTHER_ELEMENT].to_vec()).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+            let len_set = database.scard(KEY).unwrap();
+            assert_eq!(len_set, SuccessQuery::Integer(2));
         }
-    }
-
-    mod exists_test {
-
-        use super::*;
 
         #[test]
-        fn test_exists_key_non_exist_returns_bool_false() {
+        fn test_sadd_add_multiple_elem_in_set_returns_lenght_of_correctly_added_set() {
             let mut database = create_database();
+            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
 
-            let result = database.exists(KEY);
-            assert_eq!(result.unwrap(), SuccessQuery::Boolean(false));
-            assert_eq!(database.get(KEY).unwrap(), SuccessQuery::Nil);
+            let result = database
+                .sadd(KEY, [ELEMENT, ELEMENT_2, ELEMENT_3].to_vec())
+                .unwrap();
+
+            assert_eq!(result, SuccessQuery::Integer(2));
+            let len_set = database.scard(KEY).unwrap();
+            assert_eq!(len_set, SuccessQuery::Integer(3));
         }
 
         #[test]
-        fn test_exists_key_hello_returns_bool_true() {
+        fn test_sadd_add_multiple_elem_in_key_that_not_contains_set_return_err() {
             let mut database = create_database();
-            database.set(KEY, VALUE).unwrap();
-
-            let result = database.exists(KEY);
+            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+            database.set(KEY, ELEMENT).unwrap();
 
-            assert_eq!(result.unwrap(), SuccessQuery::Boolean(true));
+            let result = database
+                .sadd(KEY, [ELEMENT, ELEMENT_2, ELEMENT_3].to_vec())
+                .unwrap_err();
 
-            if let SuccessQuery::String(value) = database.strlen(KEY).unwrap() {
-                assert_eq!(value.to_string(), VALUE);
-            }
+            assert_eq!(result, DataBaseError::NotASet);
         }
     }
 
-    mod keys_test {
+    mod sismember_test {
         use super::*;
 
-        const FIRST_NAME: &str = "firstname";
-        const LAST_NAME: &str = "lastname";
-        const AGE: &str = "age";
+        #[test]
+        fn test_sismember_set_with_element_returns_1() {
+            let mut database = create_database();
+            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
 
-        const FIRST_NAME_VALUE: &str = "Alex";
-        const LAST_NAME_VALUE: &str = "Arbieto";
-        const AGE_VALUE: &str = "22";
+            let is_member = database.sismember(KEY, ELEMENT).unwrap();
 
-        fn create_database_with_keys() -> Database {
-            let mut database = create_database();
-            database.set(FIRST_NAME, FIRST_NAME_VALUE).unwrap();
-            database.set(LAST_NAME, LAST_NAME_VALUE).unwrap();
-            database.set(AGE, AGE_VALUE).unwrap();
-            database
+            assert_eq!(is_member, SuccessQuery::Boolean(true));
         }
 
         #[test]
-        fn test_keys_obtain_keys_with_name() {
-            let mut database = create_database_with_keys();
+        fn test_sismember_set_without_element_returns_0() {
+            let mut database = create_database();
+            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
 
-            if let Ok(SuccessQuery::List(list)) = database.keys("*name") {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+            let result = database.sismember(KEY, OTHER_ELEMENT).unwrap();
 
-                assert!(list.contains(&FIRST_NAME.to_owned()));
-                assert!(list.contains(&LAST_NAME.to_owned()));
-            }
+            assert_eq!(result, SuccessQuery::Boolean(false));
         }
 
         #[test]
-        fn test_keys_obtain_keys_with_four_question_name() {
-            let mut database = create_database_with_keys();
-            if let Ok(SuccessQuery::List(list)) = database.keys("????name") {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+        fn test_sismember_key_with_another_type_of_set_returns_err() {
+            let mut database = create_database();
+            database.set(KEY, ELEMENT).unwrap();
+            let result = database.sismember(KEY, ELEMENT).unwrap_err();
 
-                assert!(list.contains(&LAST_NAME.to_owned()));
-            }
+            assert_eq!(result, DataBaseError::NotASet);
         }
 
-        const KEY_A: &str = "key";
-        const KEY_B: &str = "keeeey";
-        const KEY_C: &str = "ky";
-        const NO_MATCH: &str = "notmatch";
-
-        const VAL_A: &str = "valA";
-        const VAL_B: &str = "valB";
-        const VAL_C: &str = "valC";
-        const VAL_D: &str = "valD";
-
-        fn create_database_with_keys_two() -> Database {
+        #[test]
+        fn test_sismember_with_non_exist_key_set_returns_0() {
             let mut database = create_database();
 
-            database.set(KEY_A, VAL_A).unwrap();
-            database.set(KEY_B, VAL_B).unwrap();
-            database.set(KEY_C, VAL_C).unwrap();
-            database.set(NO_MATCH, VAL_D).unwrap();
+            let result = database.sismember(KEY, ELEMENT).unwrap();
 
-            database
+            assert_eq!(result, SuccessQuery::Boolean(false));
         }
+    }
 
+    mod srem_test {
+        use super::*;
         #[test]
-        fn test_keys_obtain_all_keys_with_an_asterisk_in_the_middle() {
-            let mut database = create_database_with_keys_two();
+        fn test_srem_one_member_in_set_returns_1_if_set_contains_member() {
+            let members = vec![ELEMENT];
+            let mut database = create_database();
+            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+            let result = database.srem(KEY, members).unwrap();
+            let is_member = database.sismember(KEY, ELEMENT).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+            assert_eq!(is_member, SuccessQuery::Boolean(false));
+        }
+        #[test]
+        fn test_srem_key_no_exits_in_database_returns_0() {
+            let mut database = create_database();
+            let members_to_rmv = vec![ELEMENT];
+            let result = database.srem(KEY, members_to_rmv).unwrap();
+            assert_eq!(result, SuccessQuery::Boolean(false));
+        }
+        #[test]
+        fn test_srem_multiple_members_in_set_returns_lenght_of_removed_elements_in_the_set() {
+            let mut database = create_database();
+            let members = vec![ELEMENT, ELEMENT_2, ELEMENT_3];
+            let members_to_rmv = vec![ELEMENT, ELEMENT_2, ELEMENT_3, NON_EXIST_ELEMENT];
 
-            if let Ok(SuccessQuery::List(list)) = database.keys("k*y") {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+            let _ = database.sadd(KEY, members.clone());
 
-                assert!(list.contains(&KEY_A.to_owned()));
-                assert!(list.contains(&KEY_B.to_owned()));
-                assert!(list.contains(&KEY_C.to_owned()));
+            let result = database.srem(KEY, members_to_rmv).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(3));
+            for member in members {
+                let is_member = database.sismember(KEY, member).unwrap();
+                assert_eq!(is_member, SuccessQuery::Boolean(false));
             }
         }
-
         #[test]
-        fn test_keys_obtain_all_keys_with_question_in_the_middle() {
-            let mut database = create_database_with_keys_two();
+        fn test_srem_key_with_another_type_another_besides_set_return_err() {
+            let mut database = create_database();
+            let members_to_rmv = vec![ELEMENT];
+            let _ = database.set(KEY_WITH_STR, VALUE_A).unwrap();
+            let result = database.srem(KEY_WITH_STR, members_to_rmv).unwrap_err();
+            assert_eq!(result, DataBaseError::NotASet);
+        }
+    }
 
-            if let Ok(SuccessQuery::List(list)) = database.keys("k?y") {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+    #[test]
+    fn test_scard_set_with_one_element_returns_1() {
+        let mut database = create_database();
+        database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
 
-                assert!(list.contains(&KEY_A.to_owned()));
-            }
-        }
+        let len_set = database.scard(KEY).unwrap();
 
-        const HALL: &str = "hall";
-        const HELLO: &str = "hello";
-        const HEEEELLO: &str = "heeeeeello";
-        const HALLO: &str = "hallo";
-        const HXLLO: &str = "hxllo";
-        const HLLO: &str = "hllo";
-        const RHLLO: &str = r"h\llo";
-        const AHLLO: &str = "ahllo";
-        const HALLOWN: &str = "hallown";
+        assert_eq!(len_set, SuccessQuery::Integer(1));
+    }
 
-        fn create_database_with_keys_three() -> Database {
-            let mut database = create_database();
-            database.set(HELLO, VAL_A).unwrap();
-            database.set(HEEEELLO, VAL_A).unwrap();
-            database.set(HALLO, VAL_B).unwrap();
-            database.set(HXLLO, VAL_C).unwrap();
-            database.set(HLLO, VAL_C).unwrap();
-            database.set(RHLLO, VAL_D).unwrap();
-            database.set(AHLLO, VAL_D).unwrap();
-            database.set(HALLOWN, VAL_D).unwrap();
-            database.set(HALL, VAL_D).unwrap();
+    #[test]
+    fn test_scard_create_set_with_multiple_elements_returns_lenght_of_set() {
+        let mut database = create_database();
+        let elements: Vec<&[u8]> = vec![b"0", b"1", b"2", b"3"];
 
-            database
-        }
+        let _ = database.sadd(KEY, elements);
 
-        #[test]
-        fn test_keys_obtain_keys_with_h_question_llo_matches_correctly() {
-            let mut database = create_database_with_keys_three();
+        let len_set = database.scard(KEY).unwrap();
+        assert_eq!(len_set, SuccessQuery::Integer(4));
+    }
+
+    #[test]
+    fn test_scard_key_with_another_type_of_set_returns_err() {
+        let mut database = create_database();
+        database.set(KEY, ELEMENT).unwrap();
 
-            if let Ok(SuccessQuery::List(list)) = database.keys("h?llo") {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+        let result = database.scard(KEY).unwrap_err();
 
-                assert!(list.contains(&HELLO.to_owned()));
-                assert!(list.contains(&HXLLO.to_owned()));
-                assert!(list.contains(&RHLLO.to_owned()));
-                assert!(list.contains(&HXLLO.to_owned()));
-                assert!(list.contains(&RHLLO.to_owned()));
-            }
-        }
+        assert_eq!(result, DataBaseError::NotASet);
+    }
 
-        #[test]
-        fn test_keys_obtain_keys_with_h_asterisk_llo_matches_correctly() {
-            let mut database = create_database_with_keys_three();
+    #[test]
+    fn test_scard_key_set_not_exist_in_database_returns_0() {
+        let mut database = create_database();
 
-            if let Ok(SuccessQuery::List(list)) = database.keys("h*llo") {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
+        let result = database.scard(KEY).unwrap();
 
-                assert!(list.contains(&HELLO.to_owned()));
-                assert!(list.contains(&HALLO.to_owned()));
-                assert!(list.contains(&HEEEELLO.to_owned()));
-                assert!(list.contains(&HELLO.to_owned()));
-            }
-        }
+        assert_eq!(result, SuccessQuery::Boolean(false));
+    }
+
+    mod smembers_test {
+        use super::*;
 
         #[test]
-        fn test_keys_obtain_keys_with_nomatch_returns_empty_list() {
-            let mut database = create_database_with_keys();
+        fn test_smembers_with_elements_return_list_of_elements() {
+            let mut database = create_database();
+            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+            database.sadd(KEY, [OTHER_ELEMENT].to_vec()).unwrap();
 
-            if let SuccessQuery::List(list) = database.keys("nomatch").unwrap() {
-                assert_eq!(list.len(), 0);
+            if let SuccessQuery::List(list) = database.smembers(KEY).unwrap() {
+                for elem in list {
+                    let is_member = database
+                        .sismember(KEY, &elem.to_string().into_bytes())
+                        .unwrap();
+                    assert_eq!(is_member, SuccessQuery::Boolean(true));
+                }
             }
         }
-    }
-
-    mod rename_test {
-        use super::*;
 
         #[test]
-        fn test_rename_key_with_mykey_get_hello() {
+        fn test_smembers_with_non_exist_key_return_empty_list() {
             let mut database = create_database();
-            database.set(KEY, VALUE).unwrap();
 
-            let result = database.rename(KEY, SECOND_KEY).unwrap();
-            assert_eq!(result, SuccessQuery::Success);
+            let result = database.smembers(NON_EXIST_KEY).unwrap();
 
-            let result = database.get(KEY).unwrap();
-            assert_eq!(result, SuccessQuery::Nil);
+            assert_eq!(result, SuccessQuery::List(Vec::new()));
         }
 
         #[test]
-        fn test_rename_key_non_exists_error() {
+        fn test_smembers_key_with_another_type_another_besides_set_return_err() {
             let mut database = create_database();
 
-            let result = database.rename(KEY, SECOND_KEY).unwrap_err();
+            let _ = database.set(KEY_WITH_STR, VALUE_A);
 
-            assert_eq!(result, DataBaseError::NonExistentKey);
+            let result = database.smembers(KEY_WITH_STR).unwrap_err();
+
+            assert_eq!(result, DataBaseError::NotASet);
         }
     }
+}
 
-    mod sort_test {
-        use super::*;
-        const LIST: &str = "list";
-        const SET: &str = "set";
-        const VALUE_1: &str = "1";
-        const VALUE_2: &str = "2";
-        const VALUE_3: &str = "3";
-        const VALUE_A: &str = "a";
+#[cfg(test)]
+mod group_hash {
+    use super::*;
 
-        const LIMIT_OFFSET_OFF: i32 = 0;
-        const LIMIT_COUNT_OFF: i32 = -1;
-        const LIMIT_COUNT_ZERO: i32 = 0;
+    const KEY: &[u8] = b"KEY";
+    const KEY_WITH_STR: &[u8] = b"KEY_WITH_STRING";
+    const NON_EXIST_KEY: &[u8] = b"NON_EXIST_KEY";
+    const FIELD: &[u8] = b"FIELD";
+    const FIELD_2: &[u8] = b"FIELD_2";
+    const NON_EXIST_FIELD: &[u8] = b"NON_EXIST_FIELD";
+    const VALUE: &[u8] = b"VALUE";
+    const VALUE_2: &[u8] = b"VALUE_2";
 
-        const PATTERN: &str = "weight_*";
-        const UNMATCH_PATTERN: &str = "no_exist";
+    const DB_DUMP: &str = "db_dump_path.txt";
 
-        const KEY_WEIGHT_1: &str = "weight_1";
-        const KEY_WEIGHT_2: &str = "weight_2";
-        const KEY_WEIGHT_3: &str = "weight_3";
+    fn create_database() -> Database {
+        let mut database = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        database.set(KEY_WITH_STR, VALUE).unwrap();
+        database
+    }
 
-        const VAL_WEIGHT_1: &str = "10";
-        const VAL_WEIGHT_2: &str = "30";
-        const VAL_WEIGHT_3: &str = "20";
-        const VAL_WEIGHT_2_NOT_NUMBER: &str = "a";
+    mod hset_test {
+        use super::*;
 
         #[test]
-        fn test_sort_list_without_flags_return_sorted_list_with_numbers_ascending() {
+        fn test_hset_returns_the_number_of_new_fields() {
             let mut database = create_database();
-            database
-                .lpush(LIST, [VALUE_3, VALUE_1, VALUE_2].to_vec())
+
+            let result = database.hset(KEY, vec![FIELD, VALUE]).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+
+            let result = database
+                .hset(KEY, vec![FIELD, VALUE_2, FIELD_2, VALUE])
                 .unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
 
-            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::WithoutFlags).unwrap()
-            {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_1, VALUE_2, VALUE_3];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-            }
+            let result = database.hget(KEY, FIELD).unwrap();
+            assert_eq!(result, SuccessQuery::String(VALUE_2.to_vec()));
         }
 
         #[test]
-        fn test_sort_list_without_flags_return_err_if_elem_in_list_are_not_numbers() {
+        fn test_hset_on_a_key_that_isnt_a_hash_returns_error() {
             let mut database = create_database();
-            database
-                .lpush(LIST, [VALUE_1, VALUE_2, VALUE_A].to_vec())
-                .unwrap();
-
-            let result = database.sort(LIST, SortFlags::WithoutFlags);
 
-            assert_eq!(result.unwrap_err(), DataBaseError::SortParseError);
+            let result = database.hset(KEY_WITH_STR, vec![FIELD, VALUE]);
+            assert_eq!(result.unwrap_err(), DataBaseError::NotAHash);
         }
 
         #[test]
-        fn test_sort_list_with_alpha_on_return_sorted_list_with_numbers_and_string_values() {
+        fn test_hset_creates_a_key_of_type_hash() {
             let mut database = create_database();
-            database
-                .lpush(LIST, [VALUE_A, VALUE_2, VALUE_3].to_vec())
-                .unwrap();
 
-            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::Alpha).unwrap() {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_2, VALUE_3, VALUE_A];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-            }
+            database.hset(KEY, vec![FIELD, VALUE]).unwrap();
+            let result = database.get_type(KEY).unwrap();
+            assert_eq!(result, SuccessQuery::String(b"Hash".to_vec()));
         }
 
         #[test]
-        fn test_sort_list_with_desc_on_return_sorted_list_with_numbers_descending() {
+        fn test_hsetnx_only_sets_new_fields() {
             let mut database = create_database();
-            database
-                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
-                .unwrap();
 
-            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::Desc).unwrap() {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_3, VALUE_2, VALUE_1];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-            }
+            let result = database.hsetnx(KEY, FIELD, VALUE).unwrap();
+            assert_eq!(result, SuccessQuery::Boolean(true));
+            let result = database.hsetnx(KEY, FIELD, VALUE_2).unwrap();
+            assert_eq!(result, SuccessQuery::Boolean(false));
+
+            let result = database.hget(KEY, FIELD).unwrap();
+            assert_eq!(result, SuccessQuery::String(VALUE.to_vec()));
         }
+    }
+
+    mod hget_test {
+        use super::*;
 
         #[test]
-        fn test_sort_list_with_limit_offset_count_0_return_empty_list() {
+        fn test_hget_non_existent_key_or_field_returns_nil() {
             let mut database = create_database();
-            database
-                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
-                .unwrap();
+            database.hset(KEY, vec![FIELD, VALUE]).unwrap();
 
-            if let SuccessQuery::List(list) = database
-                .sort(LIST, SortFlags::Limit(LIMIT_OFFSET_OFF, LIMIT_COUNT_ZERO))
-                .unwrap()
-            {
-                assert!(list.is_empty());
-            }
+            assert_eq!(
+                database.hget(NON_EXIST_KEY, FIELD).unwrap(),
+                SuccessQuery::Nil
+            );
+            assert_eq!(
+                database.hget(KEY, NON_EXIST_FIELD).unwrap(),
+                SuccessQuery::Nil
+            );
         }
 
         #[test]
-        fn test_sort_list_with_limit_offset_count_in_range_of_list_return_sorted_list_with_count_elem(
-        ) {
+        fn test_hget_on_a_key_that_isnt_a_hash_returns_error() {
             let mut database = create_database();
-            database
-                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
-                .unwrap();
 
-            if let SuccessQuery::List(list) = database
-                .sort(LIST, SortFlags::Limit(LIMIT_OFFSET_OFF, 2))
-                .unwrap()
-            {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_1, VALUE_2];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-                assert_eq!(list.len(), 2);
-            }
+            let result = database.hget(KEY_WITH_STR, FIELD);
+            assert_eq!(result.unwrap_err(), DataBaseError::NotAHash);
         }
 
         #[test]
-        fn test_sort_list_with_limit_offset_count_negative_return_sorted_list_with_all_elements() {
+        fn test_hmget_returns_nil_for_missing_fields() {
             let mut database = create_database();
-            database
-                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
-                .unwrap();
+            database.hset(KEY, vec![FIELD, VALUE]).unwrap();
 
-            if let SuccessQuery::List(list) = database
-                .sort(LIST, SortFlags::Limit(LIMIT_OFFSET_OFF, -1))
-                .unwrap()
-            {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_1, VALUE_2, VALUE_3];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-                assert_eq!(list.len(), 3);
-            }
+            let result = database.hmget(KEY, vec![FIELD, NON_EXIST_FIELD]).unwrap();
+            let expected = vec![SuccessQuery::String(VALUE.to_vec()), SuccessQuery::Nil];
+            assert_eq!(result, SuccessQuery::List(expected));
+
+            let result = database.hmget(NON_EXIST_KEY, vec![FIELD]).unwrap();
+            assert_eq!(result, SuccessQuery::List(vec![SuccessQuery::Nil]));
         }
 
         #[test]
-        fn test_sort_list_with_limit_offset_neg_count_0_return_emptylist() {
+        fn test_hgetall_returns_every_field_followed_by_its_value() {
             let mut database = create_database();
             database
-                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .hset(KEY, vec![FIELD, VALUE, FIELD_2, VALUE_2])
                 .unwrap();
 
-            if let SuccessQuery::List(list) = database
-                .sort(LIST, SortFlags::Limit(-2, LIMIT_COUNT_ZERO))
-                .unwrap()
-            {
-                assert!(list.is_empty());
+            if let SuccessQuery::List(list) = database.hgetall(KEY).unwrap() {
+                assert_eq!(list.len(), 4);
+                for pair in list.chunks(2) {
+                    if let [SuccessQuery::String(field), value] = pair {
+                        assert_eq!(database.hget(KEY, field).unwrap(), *value);
+                    } else {
+                        panic!("Unexpected reply");
+                    }
+                }
+            } else {
+                panic!("Unexpected reply");
             }
         }
 
         #[test]
-        fn test_sort_list_with_limit_offset_out_of_range_of_list_count_return_empty_list() {
+        fn test_hkeys_and_hvals_return_fields_and_values() {
             let mut database = create_database();
-            database
-                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
-                .unwrap();
+            database.hset(KEY, vec![FIELD, VALUE]).unwrap();
 
-            if let SuccessQuery::List(list) = database
-                .sort(LIST, SortFlags::Limit(4, LIMIT_COUNT_OFF))
-                .unwrap()
-            {
-                assert!(list.is_empty());
-            }
+            let result = database.hkeys(KEY).unwrap();
+            let expected = vec![SuccessQuery::String(FIELD.to_vec())];
+            assert_eq!(result, SuccessQuery::List(expected));
+
+            let result = database.hvals(KEY).unwrap();
+            let expected = vec![SuccessQuery::String(VALUE.to_vec())];
+            assert_eq!(result, SuccessQuery::List(expected));
+
+            let result = database.hkeys(NON_EXIST_KEY).unwrap();
+            assert_eq!(result, SuccessQuery::List(Vec::new()));
         }
 
         #[test]
-        fn test_sort_list_with_limit_offset_negative_count_negative_return_sorted_list_with_all_elements(
-        ) {
+        fn test_hlen_hexists_and_hstrlen() {
             let mut database = create_database();
             database
-                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .hset(KEY, vec![FIELD, VALUE, FIELD_2, VALUE_2])
                 .unwrap();
 
-            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::Limit(-2, -2)).unwrap()
-            {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_1, VALUE_2, VALUE_3];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-                assert_eq!(list.len(), 3);
-            }
+            assert_eq!(database.hlen(KEY).unwrap(), SuccessQuery::Integer(2));
+            assert_eq!(
+                database.hexists(KEY, FIELD).unwrap(),
+                SuccessQuery::Boolean(true)
+            );
+            assert_eq!(
+                database.hexists(KEY, NON_EXIST_FIELD).unwrap(),
+                SuccessQuery::Boolean(false)
+            );
+            assert_eq!(
+                database.hstrlen(KEY, FIELD_2).unwrap(),
+                SuccessQuery::Integer(VALUE_2.len() as i64)
+            );
+            assert_eq!(
+                database.hlen(NON_EXIST_KEY).unwrap(),
+                SuccessQuery::Integer(0)
+            );
         }
+    }
+
+    mod hdel_test {
+        use super::*;
 
         #[test]
-        fn test_sort_list_with_limit_offset_in_range_count_2_return_sorted_sublist_of_2_elements() {
+        fn test_hdel_returns_the_number_of_removed_fields() {
             let mut database = create_database();
             database
-                .lpush(LIST, [VALUE_1, VALUE_3, VALUE_2].to_vec())
+                .hset(KEY, vec![FIELD, VALUE, FIELD_2, VALUE_2])
                 .unwrap();
 
-            if let SuccessQuery::List(list) = database.sort(LIST, SortFlags::Limit(1, 2)).unwrap() {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_2, VALUE_3];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-                assert_eq!(list.len(), 2);
-            }
+            let result = database.hdel(KEY, vec![FIELD, NON_EXIST_FIELD]).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+            assert_eq!(database.hlen(KEY).unwrap(), SuccessQuery::Integer(1));
         }
 
         #[test]
-        fn test_sort_set_by_weight_return_list_ordered_by_weight() {
+        fn test_hdel_of_the_last_field_removes_the_key() {
             let mut database = create_database();
+            database.hset(KEY, vec![FIELD, VALUE]).unwrap();
 
-            database.set(KEY_WEIGHT_3, VAL_WEIGHT_3).unwrap();
-            database.set(KEY_WEIGHT_1, VAL_WEIGHT_1).unwrap();
-            database.set(KEY_WEIGHT_2, VAL_WEIGHT_2).unwrap();
+            database.hdel(KEY, vec![FIELD]).unwrap();
+            assert_eq!(database.exists(KEY).unwrap(), SuccessQuery::Boolean(false));
+        }
 
-            database.sadd(SET, [VALUE_1].to_vec()).unwrap();
-            database.sadd(SET, [VALUE_3].to_vec()).unwrap();
-            database.sadd(SET, [VALUE_2].to_vec()).unwrap();
+        #[test]
+        fn test_hdel_non_existent_key_returns_0() {
+            let mut database = create_database();
 
-            if let SuccessQuery::List(list) = database.sort(SET, SortFlags::By(PATTERN)).unwrap() {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_1, VALUE_3, VALUE_2];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-                assert_eq!(list.len(), 3);
-            }
+            let result = database.hdel(NON_EXIST_KEY, vec![FIELD]).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(0));
+            assert_eq!(
+                database.exists(NON_EXIST_KEY).unwrap(),
+                SuccessQuery::Boolean(false)
+            );
         }
+    }
+
+    mod hincrby_test {
+        use super::*;
 
         #[test]
-        fn test_sort_list_by_weight_with_pattern_that_no_match_return_list_unordered() {
+        fn test_hincrby_on_a_new_field_starts_from_0() {
             let mut database = create_database();
 
-            database.set(KEY_WEIGHT_3, VAL_WEIGHT_3).unwrap();
-            database.set(KEY_WEIGHT_1, VAL_WEIGHT_1).unwrap();
-            database.set(KEY_WEIGHT_2, VAL_WEIGHT_2).unwrap();
+            let result = database.hincrby(KEY, FIELD, 5).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(5));
+            let result = database.hincrby(KEY, FIELD, -7).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(-2));
+        }
 
-            database.rpush(LIST, vec![VALUE_1]).unwrap();
-            database.rpush(LIST, vec![VALUE_3]).unwrap();
-            database.rpush(LIST, vec![VALUE_2]).unwrap();
+        #[test]
+        fn test_hincrby_on_a_field_that_isnt_a_number_returns_error() {
+            let mut database = create_database();
+            database.hset(KEY, vec![FIELD, VALUE]).unwrap();
 
-            if let SuccessQuery::List(list) =
-                database.sort(LIST, SortFlags::By(UNMATCH_PATTERN)).unwrap()
-            {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_1, VALUE_3, VALUE_2];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-                assert_eq!(list.len(), 3);
-            }
+            let result = database.hincrby(KEY, FIELD, 1);
+            assert_eq!(result.unwrap_err(), DataBaseError::NotAnInteger);
         }
 
         #[test]
-        fn test_sort_set_by_weight_if_weight_of_elem_that_does_not_match_weight_is_set_to_zero_and_return_list_ordered_by_pattern(
-        ) {
+        fn test_hincrby_that_overflows_returns_error_and_keeps_the_value() {
             let mut database = create_database();
+            database.hset(KEY, vec![FIELD, b"2147483647"]).unwrap();
 
-            database.set(KEY_WEIGHT_3, VAL_WEIGHT_3).unwrap();
-            database.set(KEY_WEIGHT_1, VAL_WEIGHT_1).unwrap();
-            // Weight of VALUE_2 is zero.
+            let result = database.hincrby(KEY, FIELD, 1);
+            assert_eq!(result.unwrap_err(), DataBaseError::Overflow);
+            let result = database.hget(KEY, FIELD).unwrap();
+            assert_eq!(result, SuccessQuery::String(b"2147483647".to_vec()));
+        }
 
-            database.sadd(SET, [VALUE_1].to_vec()).unwrap();
-            database.sadd(SET, [VALUE_3].to_vec()).unwrap();
-            database.sadd(SET, [VALUE_2].to_vec()).unwrap();
+        #[test]
+        fn test_hincrbyfloat_that_fails_on_a_new_key_doesnt_create_it() {
+            let mut database = create_database();
 
-            if let SuccessQuery::List(list) = database.sort(SET, SortFlags::By(PATTERN)).unwrap() {
-                let list_result: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let to_compare_list: Vec<&str> = vec![VALUE_2, VALUE_1, VALUE_3];
-                let pair_list: Vec<(&String, &str)> =
-                    list_result.iter().zip(to_compare_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                });
-                assert_eq!(list.len(), 3);
-            }
+            let result = database.hincrbyfloat(KEY, FIELD, f64::INFINITY);
+            assert_eq!(result.unwrap_err(), DataBaseError::NotAFloat);
+            assert_eq!(database.exists(KEY).unwrap(), SuccessQuery::Boolean(false));
         }
 
         #[test]
-        fn test_sort_set_by_weight_and_only_weight_isnt_a_number_return_err() {
+        fn test_hincrbyfloat_returns_the_new_value() {
             let mut database = create_database();
+            database.hset(KEY, vec![FIELD, b"10.5"]).unwrap();
 
-            database.set(KEY_WEIGHT_3, VAL_WEIGHT_3).unwrap();
-            database.set(KEY_WEIGHT_1, VAL_WEIGHT_1).unwrap();
-            database.set(KEY_WEIGHT_2, VAL_WEIGHT_2_NOT_NUMBER).unwrap();
+            let result = database.hincrbyfloat(KEY, FIELD, 0.1).unwrap();
+            assert_eq!(result, SuccessQuery::String(b"10.6".to_vec()));
+            let result = database.hincrbyfloat(KEY, FIELD, -0.6).unwrap();
+            assert_eq!(result, SuccessQuery::String(b"10".to_vec()));
+        }
 
-            database.sadd(SET, [VALUE_1].to_vec()).unwrap();
-            database.sadd(SET, [VALUE_3].to_vec()).unwrap();
-            database.sadd(SET, [VALUE_2].to_vec()).unwrap();
+        #[test]
+        fn test_hincrbyfloat_on_a_field_that_isnt_a_number_returns_error() {
+            let mut database = create_database();
+            database.hset(KEY, vec![FIELD, VALUE]).unwrap();
 
-            let result = database.sort(SET, SortFlags::By(PATTERN));
-            assert_eq!(result.unwrap_err(), DataBaseError::SortByParseError);
+            let result = database.hincrbyfloat(KEY, FIELD, 1.0);
+            assert_eq!(result.unwrap_err(), DataBaseError::NotAFloat);
         }
     }
-}
 
-#[cfg(test)]
-mod group_list {
+    mod hrandfield_test {
+        use super::*;
 
-    const KEY: &str = "KEY";
-    const VALUE: &str = "VALUE";
+        fn create_hash() -> Database {
+            let mut database = create_database();
+            database
+                .hset(KEY, vec![FIELD, VALUE, FIELD_2, VALUE_2])
+                .unwrap();
+            database
+        }
 
-    const VALUEA: &str = "ValueA";
-    const VALUEB: &str = "ValueB";
-    const VALUEC: &str = "ValueC";
-    const VALUED: &str = "ValueD";
+        #[test]
+        fn test_hrandfield_without_count_returns_a_field() {
+            let mut database = create_hash();
 
-    const DB_DUMP: &str = "db_dump_path.txt";
-    use super::*;
+            match database.hrandfield(KEY, None, false).unwrap() {
+                SuccessQuery::String(field) => assert!(field == FIELD || field == FIELD_2),
+                _ => panic!("Unexpected reply"),
+            }
+            let result = database.hrandfield(NON_EXIST_KEY, None, false).unwrap();
+            assert_eq!(result, SuccessQuery::Nil);
+        }
 
-    fn create_database() -> Database {
-        let db = Database::new(DB_DUMP.to_string());
-        db
+        #[test]
+        fn test_hrandfield_with_positive_count_returns_distinct_fields() {
+            let mut database = create_hash();
+
+            if let SuccessQuery::List(list) = database.hrandfield(KEY, Some(5), false).unwrap() {
+                assert_eq!(list.len(), 2);
+                assert_ne!(list[0], list[1]);
+            } else {
+                panic!("Unexpected reply");
+            }
+        }
+
+        #[test]
+        fn test_hrandfield_with_negative_count_returns_exactly_count_fields() {
+            let mut database = create_hash();
+
+            if let SuccessQuery::List(list) = database.hrandfield(KEY, Some(-5), true).unwrap() {
+                assert_eq!(list.len(), 10);
+                for pair in list.chunks(2) {
+                    if let [SuccessQuery::String(field), value] = pair {
+                        assert_eq!(database.hget(KEY, field).unwrap(), *value);
+                    }
+                }
+            } else {
+                panic!("Unexpected reply");
+            }
+        }
     }
 
-    fn database_with_a_list() -> Database {
-        let mut database = create_database();
+    mod hscan_test {
+        use super::*;
 
-        database
-            .lpush(KEY, [VALUEA, VALUEB, VALUEC, VALUED].to_vec())
-            .unwrap();
+        #[test]
+        fn test_hscan_iterates_all_fields() {
+            let mut database = create_database();
+            let fields: Vec<Vec<u8>> = (0..25)
+                .map(|i| format!("field{}", i).into_bytes())
+                .collect();
+            for field in fields.iter() {
+                database.hset(KEY, vec![field, VALUE]).unwrap();
+            }
 
-        database
-    }
+            let mut seen = HashSet::new();
+            let mut cursor = 0;
+            loop {
+                let result = database.hscan(KEY, cursor, None, 10).unwrap();
+                match result {
+                    SuccessQuery::List(reply) => match &reply[..] {
+                        [SuccessQuery::String(next), SuccessQuery::List(items)] => {
+                            assert!(items.len() <= 20);
+                            for pair in items.chunks(2) {
+                                seen.insert(pair[0].to_string());
+                            }
+                            cursor = parse_bytes::<u64>(next).unwrap();
+                        }
+                        _ => panic!("Unexpected reply"),
+                    },
+                    _ => panic!("Unexpected reply"),
+                }
+                if cursor == 0 {
+                    break;
+                }
+            }
 
-    fn database_with_a_three_repeated_values() -> Database {
-        let mut database = create_database();
+            assert_eq!(seen.len(), 25);
+        }
 
-        database
-            .lpush(KEY, [VALUEA, VALUEA, VALUEC, VALUEA].to_vec())
-            .unwrap();
+        #[test]
+        fn test_hscan_with_pattern_returns_only_matching_fields() {
+            let mut database = create_database();
+            database
+                .hset(KEY, vec![FIELD, VALUE, b"other", VALUE_2])
+                .unwrap();
 
-        database
+            let result = database.hscan(KEY, 0, Some(b"oth*"), 10).unwrap();
+            let expected = SuccessQuery::List(vec![
+                SuccessQuery::String(b"0".to_vec()),
+                SuccessQuery::List(vec![
+                    SuccessQuery::String(b"other".to_vec()),
+                    SuccessQuery::String(VALUE_2.to_vec()),
+                ]),
+            ]);
+            assert_eq!(result, expected);
+        }
     }
+}
 
-    fn database_with_a_string() -> Database {
-        let mut database = create_database();
+#[cfg(test)]
+mod group_zset {
+    use super::*;
 
-        database.append(KEY, VALUE).unwrap();
+    const KEY: &[u8] = b"KEY";
+    const KEY_2: &[u8] = b"KEY_2";
+    const DESTINATION: &[u8] = b"DESTINATION";
+    const KEY_WITH_STR: &[u8] = b"KEY_WITH_STRING";
+    const NON_EXIST_KEY: &[u8] = b"NON_EXIST_KEY";
+    const ONE: &[u8] = b"one";
+    const TWO: &[u8] = b"two";
+    const THREE: &[u8] = b"three";
+
+    const DB_DUMP: &str = "db_dump_path.txt";
 
+    fn create_database() -> Database {
+        let mut database = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        database.set(KEY_WITH_STR, b"VALUE").unwrap();
+        let pairs: Vec<(f64, &[u8])> = vec![(1.0, ONE), (2.0, TWO), (3.0, THREE)];
+        database.zadd(KEY, ZaddFlags::default(), pairs).unwrap();
         database
     }
 
-    mod llen_test {
+    fn strings(elements: &[&[u8]]) -> SuccessQuery {
+        SuccessQuery::List(
+            elements
+                .iter()
+                .map(|element| SuccessQuery::String(element.to_vec()))
+                .collect(),
+        )
+    }
 
+    mod zadd_test {
         use super::*;
 
         #[test]
-        fn test_llen_on_a_non_existent_key_gets_len_zero() {
+        fn test_zadd_returns_the_number_of_added_members() {
             let mut database = create_database();
 
-            let result = database.llen(KEY);
-
-            assert_eq!(result.unwrap(), SuccessQuery::Integer(0));
+            let pairs: Vec<(f64, &[u8])> = vec![(5.0, ONE), (4.0, b"four")];
+            let result = database.zadd(KEY, ZaddFlags::default(), pairs).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+            assert_eq!(
+                database.zscore(KEY, ONE).unwrap(),
+                SuccessQuery::String(b"5".to_vec())
+            );
         }
 
         #[test]
-        fn test_llen_on_a_list_with_one_value() {
+        fn test_zadd_with_ch_counts_changed_members() {
             let mut database = create_database();
+            let flags = ZaddFlags {
+                ch: true,
+                ..ZaddFlags::default()
+            };
 
-            database.lpush(KEY, [VALUE].to_vec()).unwrap();
-
-            let result = database.llen(KEY);
-
-            assert_eq!(result.unwrap(), SuccessQuery::Integer(1));
+            let pairs: Vec<(f64, &[u8])> = vec![(5.0, ONE), (2.0, TWO), (4.0, b"four")];
+            let result = database.zadd(KEY, flags, pairs).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(2));
         }
 
         #[test]
-        fn test_llen_on_a_list_with_more_than_one_value() {
-            let mut database = database_with_a_list();
+        fn test_zadd_with_nx_and_xx() {
+            let mut database = create_database();
+            let nx = ZaddFlags {
+                nx: true,
+                ..ZaddFlags::default()
+            };
+            let xx = ZaddFlags {
+                xx: true,
+                ..ZaddFlags::default()
+            };
 
-            let result = database.llen(KEY).unwrap();
+            let pairs: Vec<(f64, &[u8])> = vec![(5.0, ONE), (4.0, b"four")];
+            assert_eq!(
+                database.zadd(KEY, nx, pairs).unwrap(),
+                SuccessQuery::Integer(1)
+            );
+            assert_eq!(
+                database.zscore(KEY, ONE).unwrap(),
+                SuccessQuery::String(b"1".to_vec())
+            );
 
-            assert_eq!(result, SuccessQuery::Integer(4));
+            let pairs: Vec<(f64, &[u8])> = vec![(5.0, ONE), (6.0, b"six")];
+            assert_eq!(
+                database.zadd(KEY, xx, pairs).unwrap(),
+                SuccessQuery::Integer(0)
+            );
+            assert_eq!(
+                database.zscore(KEY, ONE).unwrap(),
+                SuccessQuery::String(b"5".to_vec())
+            );
+            assert_eq!(database.zscore(KEY, b"six").unwrap(), SuccessQuery::Nil);
         }
 
         #[test]
-        fn test_llen_on_an_existent_key_that_isnt_a_list() {
-            let mut database = database_with_a_string();
-
-            let result = database.llen(KEY);
+        fn test_zadd_with_gt_only_updates_greater_scores() {
+            let mut database = create_database();
+            let gt = ZaddFlags {
+                gt: true,
+                ..ZaddFlags::default()
+            };
 
-            assert_eq!(result.unwrap_err(), DataBaseError::NotAList);
+            let pairs: Vec<(f64, &[u8])> = vec![(0.0, ONE), (10.0, TWO)];
+            database.zadd(KEY, gt, pairs).unwrap();
+            assert_eq!(
+                database.zscore(KEY, ONE).unwrap(),
+                SuccessQuery::String(b"1".to_vec())
+            );
+            assert_eq!(
+                database.zscore(KEY, TWO).unwrap(),
+                SuccessQuery::String(b"10".to_vec())
+            );
         }
-    }
-
-    mod lindex_test {
-        use super::*;
 
         #[test]
-        fn test_lindex_on_a_non_existent_key() {
+        fn test_zadd_with_incr_returns_the_new_score() {
             let mut database = create_database();
+            let incr = ZaddFlags {
+                incr: true,
+                ..ZaddFlags::default()
+            };
+            let incr_xx = ZaddFlags {
+                incr: true,
+                xx: true,
+                ..ZaddFlags::default()
+            };
 
-            let result = database.lindex(KEY, 10);
-
-            assert_eq!(result.unwrap(), SuccessQuery::Nil);
+            let result = database.zadd(KEY, incr, vec![(1.5, ONE)]).unwrap();
+            assert_eq!(result, SuccessQuery::String(b"2.5".to_vec()));
+            let result = database.zadd(KEY, incr_xx, vec![(1.0, b"new")]).unwrap();
+            assert_eq!(result, SuccessQuery::Nil);
         }
 
         #[test]
-        fn test_lindex_with_a_list_with_one_value_on_idex_zero() {
+        fn test_zadd_on_a_key_that_isnt_a_sorted_set_returns_error() {
             let mut database = create_database();
 
-            database.lpush(KEY, [VALUE].to_vec()).unwrap();
-
-            if let SuccessQuery::String(value) = database.lindex(KEY, 0).unwrap() {
-                assert_eq!(value, VALUE);
-            }
+            let result = database.zadd(KEY_WITH_STR, ZaddFlags::default(), vec![(1.0, ONE)]);
+            assert_eq!(result.unwrap_err(), DataBaseError::NotAZset);
         }
 
         #[test]
-        fn test_lindex_with_more_than_one_value() {
-            let mut database = database_with_a_list();
+        fn test_zincrby_that_results_in_nan_returns_error() {
+            let mut database = create_database();
+            database.zincrby(KEY, f64::INFINITY, ONE).unwrap();
 
-            if let SuccessQuery::String(value) = database.lindex(KEY, 3).unwrap() {
-                assert_eq!(value, VALUEA);
-            }
+            let result = database.zincrby(KEY, f64::NEG_INFINITY, ONE);
+            assert_eq!(result.unwrap_err(), DataBaseError::NotANumber);
         }
+    }
 
-        #[test]
-        fn test_lindex_with_more_than_one_value_with_a_negative_index() {
-            let mut database = database_with_a_list();
-
-            if let SuccessQuery::String(value) = database.lindex(KEY, -1).unwrap() {
-                assert_eq!(value, VALUEA);
-            }
-        }
+    mod zrank_test {
+        use super::*;
 
         #[test]
-        fn test_lindex_on_a_reverse_out_of_bound() {
-            let mut database = database_with_a_list();
-
-            let result = database.lindex(KEY, -5);
+        fn test_zrank_and_zrevrank() {
+            let mut database = create_database();
 
-            assert_eq!(result.unwrap(), SuccessQuery::Nil);
+            assert_eq!(database.zrank(KEY, ONE).unwrap(), SuccessQuery::Integer(0));
+            assert_eq!(
+                database.zrevrank(KEY, ONE).unwrap(),
+                SuccessQuery::Integer(2)
+            );
+            assert_eq!(database.zrank(KEY, b"none").unwrap(), SuccessQuery::Nil);
+            assert_eq!(
+                database.zrank(NON_EXIST_KEY, ONE).unwrap(),
+                SuccessQuery::Nil
+            );
         }
 
         #[test]
-        fn test_lindex_on_an_out_of_bound() {
-            let mut database = database_with_a_list();
-
-            let result = database.lindex(KEY, 100);
+        fn test_zcard_and_zcount() {
+            let mut database = create_database();
 
-            assert_eq!(result.unwrap(), SuccessQuery::Nil);
+            assert_eq!(database.zcard(KEY).unwrap(), SuccessQuery::Integer(3));
+            assert_eq!(
+                database.zcard(NON_EXIST_KEY).unwrap(),
+                SuccessQuery::Integer(0)
+            );
+            let result = database
+                .zcount(KEY, ScoreBound::Exclusive(1.0), ScoreBound::Inclusive(3.0))
+                .unwrap();
+            assert_eq!(result, SuccessQuery::Integer(2));
         }
     }
 
-    mod lpop_test {
+    mod zrange_test {
         use super::*;
 
         #[test]
-        fn test_lpop_on_a_non_existent_key() {
+        fn test_zrange_by_index() {
             let mut database = create_database();
 
-            let result = database.lpop(KEY).unwrap();
+            let result = database
+                .zrange(KEY, ZrangeBy::Index(0, -1), false, None, false)
+                .unwrap();
+            assert_eq!(result, strings(&[ONE, TWO, THREE]));
 
-            assert_eq!(result, SuccessQuery::Nil);
+            let result = database
+                .zrange(KEY, ZrangeBy::Index(0, 0), true, None, true)
+                .unwrap();
+            assert_eq!(result, strings(&[THREE, b"3"]));
+
+            let result = database
+                .zrange(KEY, ZrangeBy::Index(5, 10), false, None, false)
+                .unwrap();
+            assert_eq!(result, strings(&[]));
         }
 
         #[test]
-        fn test_lpop_with_a_list_with_one_value_on_idex_zero() {
+        fn test_zrange_by_score_with_limit() {
             let mut database = create_database();
+            let by = ZrangeBy::Score(
+                ScoreBound::Inclusive(f64::NEG_INFINITY),
+                ScoreBound::Exclusive(3.0),
+            );
 
-            database.lpush(KEY, [VALUE].to_vec()).unwrap();
-
-            if let SuccessQuery::String(val) = database.lpop(KEY).unwrap() {
-                assert_eq!(val.to_string(), VALUE);
-            }
+            let result = database.zrange(KEY, by, false, None, false).unwrap();
+            assert_eq!(result, strings(&[ONE, TWO]));
 
-            let result = database.llen(KEY).unwrap();
-            assert_eq!(result, SuccessQuery::Integer(0));
+            let by = ZrangeBy::Score(
+                ScoreBound::Inclusive(f64::NEG_INFINITY),
+                ScoreBound::Inclusive(f64::INFINITY),
+            );
+            let result = database.zrange(KEY, by, true, Some((1, 1)), false).unwrap();
+            assert_eq!(result, strings(&[TWO]));
         }
 
         #[test]
-        fn test_lpop_with_a_list_with_more_than_one_value() {
-            let mut database = database_with_a_list();
+        fn test_zrange_by_lex() {
+            let mut database = create_database();
+            let pairs: Vec<(f64, &[u8])> = vec![(0.0, b"a"), (0.0, b"b"), (0.0, b"c")];
+            database.zadd(KEY_2, ZaddFlags::default(), pairs).unwrap();
 
-            if let SuccessQuery::String(val) = database.lpop(KEY).unwrap() {
-                assert_eq!(val.to_string(), VALUED);
-            }
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+            let by = ZrangeBy::Lex(LexBound::Exclusive(b"a"), LexBound::Max);
+            let result = database.zrange(KEY_2, by, false, None, false).unwrap();
+            assert_eq!(result, strings(&[b"b", b"c"]));
 
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert_eq!(list[0], VALUEC);
-            }
+            let by = ZrangeBy::Lex(LexBound::Min, LexBound::Inclusive(b"b"));
+            let result = database.zrange(KEY_2, by, true, None, false).unwrap();
+            assert_eq!(result, strings(&[b"b", b"a"]));
         }
+    }
+
+    mod zrem_test {
+        use super::*;
 
         #[test]
-        fn test_lpop_on_an_empty_list() {
+        fn test_zrem_removes_the_key_when_empty() {
             let mut database = create_database();
 
-            database.lpush(KEY, [VALUE].to_vec()).unwrap();
-
-            if let SuccessQuery::String(val) = database.lpop(KEY).unwrap() {
-                assert_eq!(val.to_string(), VALUE);
-            }
-
-            let value = database.lpop(KEY).unwrap();
-            assert_eq!(value, SuccessQuery::Nil);
-
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
-
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert!(list.is_empty());
-            }
+            let result = database.zrem(KEY, vec![ONE, TWO, b"none"]).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(2));
+            database.zrem(KEY, vec![THREE]).unwrap();
+            assert_eq!(database.exists(KEY).unwrap(), SuccessQuery::Boolean(false));
         }
-    }
-
-    mod lpush_test {
-
-        use super::*;
 
         #[test]
-        fn test_lpush_on_a_non_existent_key_creates_a_list_with_new_value() {
+        fn test_zremrangebyrank_and_zremrangebyscore() {
             let mut database = create_database();
 
-            let result = database.lpush(KEY, [VALUE].to_vec()).unwrap();
+            let result = database.zremrangebyrank(KEY, -1, -1).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+            let result = database
+                .zremrangebyscore(KEY, ScoreBound::Inclusive(2.0), ScoreBound::Inclusive(2.0))
+                .unwrap();
             assert_eq!(result, SuccessQuery::Integer(1));
 
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+            let result = database
+                .zrange(KEY, ZrangeBy::Index(0, -1), false, None, false)
+                .unwrap();
+            assert_eq!(result, strings(&[ONE]));
+        }
 
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert_eq!(list.len(), 1);
-                assert_eq!(list[0], VALUE);
-            }
+        #[test]
+        fn test_zremrangebylex() {
+            let mut database = create_database();
+            let pairs: Vec<(f64, &[u8])> = vec![(0.0, b"a"), (0.0, b"b"), (0.0, b"c")];
+            database.zadd(KEY_2, ZaddFlags::default(), pairs).unwrap();
+
+            let result = database
+                .zremrangebylex(KEY_2, LexBound::Inclusive(b"b"), LexBound::Max)
+                .unwrap();
+            assert_eq!(result, SuccessQuery::Integer(2));
+            assert_eq!(database.zcard(KEY_2).unwrap(), SuccessQuery::Integer(1));
         }
 
         #[test]
-        fn test_lpush_on_an_existent_key_is_valid() {
+        fn test_zpopmin_and_zpopmax() {
             let mut database = create_database();
 
-            database.lpush(KEY, [VALUEA].to_vec()).unwrap();
-
-            let result = database.lpush(KEY, [VALUEB].to_vec()).unwrap();
-
-            assert_eq!(result, SuccessQuery::Integer(2));
+            let result = database.zpopmin(KEY, None).unwrap();
+            assert_eq!(result, strings(&[ONE, b"1"]));
+            let result = database.zpopmax(KEY, Some(5)).unwrap();
+            assert_eq!(result, strings(&[THREE, b"3", TWO, b"2"]));
+            let result = database.zpopmax(KEY, Some(5)).unwrap();
+            assert_eq!(result, strings(&[]));
+        }
+    }
 
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+    mod zstore_test {
+        use super::*;
 
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert_eq!(list.len(), 2);
-                assert_eq!(list[1], VALUEA);
-                assert_eq!(list[0], VALUEB);
-            }
+        fn create_sources() -> Database {
+            let mut database = create_database();
+            let pairs: Vec<(f64, &[u8])> = vec![(10.0, ONE), (20.0, b"four")];
+            database.zadd(KEY_2, ZaddFlags::default(), pairs).unwrap();
+            database
         }
 
         #[test]
-        fn test_lpush_on_an_existent_key_that_isnt_a_list() {
-            let mut database = create_database();
-
-            database.append(KEY, VALUE).unwrap();
-
-            let result = database.lpush(KEY, [VALUE].to_vec()).unwrap_err();
+        fn test_zunionstore_with_weights() {
+            let mut database = create_sources();
 
-            assert_eq!(result, DataBaseError::NotAList);
+            let result = database
+                .zunionstore(
+                    DESTINATION,
+                    vec![KEY, KEY_2],
+                    Some(vec![2.0, 1.0]),
+                    Aggregate::Sum,
+                )
+                .unwrap();
+            assert_eq!(result, SuccessQuery::Integer(4));
+            assert_eq!(
+                database.zscore(DESTINATION, ONE).unwrap(),
+                SuccessQuery::String(b"12".to_vec())
+            );
+            assert_eq!(
+                database.zscore(DESTINATION, TWO).unwrap(),
+                SuccessQuery::String(b"4".to_vec())
+            );
         }
 
         #[test]
-        fn test_lpush_more_than_one_value_with_a_key_non_existent() {
-            let mut database = create_database();
+        fn test_zinterstore_with_aggregate_min() {
+            let mut database = create_sources();
 
             let result = database
-                .lpush(KEY, [VALUEA, VALUEB, VALUEC].to_vec())
+                .zinterstore(DESTINATION, vec![KEY, KEY_2], None, Aggregate::Min)
                 .unwrap();
+            assert_eq!(result, SuccessQuery::Integer(1));
+            assert_eq!(
+                database.zscore(DESTINATION, ONE).unwrap(),
+                SuccessQuery::String(b"1".to_vec())
+            );
+        }
 
-            assert_eq!(result, SuccessQuery::Integer(3));
+        #[test]
+        fn test_zdiffstore_and_empty_result_removes_destination() {
+            let mut database = create_sources();
 
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+            let result = database.zdiffstore(DESTINATION, vec![KEY, KEY_2]).unwrap();
+            assert_eq!(result, SuccessQuery::Integer(2));
 
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert_eq!(list.len(), 3);
-                assert_eq!(list[2], VALUEA);
-                assert_eq!(list[1], VALUEB);
-                assert_eq!(list[0], VALUEC);
-            }
+            let result = database
+                .zdiffstore(DESTINATION, vec![NON_EXIST_KEY, KEY])
+                .unwrap();
+            assert_eq!(result, SuccessQuery::Integer(0));
+            assert_eq!(
+                database.exists(DESTINATION).unwrap(),
+                SuccessQuery::Boolean(false)
+            );
         }
 
         #[test]
-        fn test_lpush_more_than_one_value_with_a_key_with_list() {
-            let mut database = create_database();
+        fn test_zunionstore_takes_sets_with_score_1() {
+            let mut database = create_sources();
+            database.sadd(b"SET", vec![ONE]).unwrap();
 
-            database.lpush(KEY, [VALUEA].to_vec()).unwrap();
-            let result = database.lpush(KEY, [VALUEB, VALUEC].to_vec()).unwrap();
+            database
+                .zunionstore(DESTINATION, vec![KEY, b"SET"], None, Aggregate::Sum)
+                .unwrap();
+            assert_eq!(
+                database.zscore(DESTINATION, ONE).unwrap(),
+                SuccessQuery::String(b"2".to_vec())
+            );
+        }
+    }
+}
 
-            assert_eq!(result, SuccessQuery::Integer(3));
+#[cfg(test)]
+mod group_server {
+    use super::*;
+    const KEY1: &[u8] = b"key1";
+    const VALUE1: &[u8] = b"value1";
+    const KEY2: &[u8] = b"key2";
+    const VALUE2: &[u8] = b"value2";
 
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+    const DB_DUMP: &str = "db_dump_path.txt";
 
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert_eq!(list.len(), 3);
-                assert_eq!(list[2], VALUEA);
-                assert_eq!(list[1], VALUEB);
-                assert_eq!(list[0], VALUEC);
-            }
-        }
+    fn create_database() -> Database {
+        Database::new(DB_DUMP.to_string(), 16, 16).unwrap()
     }
 
-    mod lrange_test {
+    mod flushdb_test {
         use super::*;
 
         #[test]
-        fn test_lrange_on_zero_zero_range() {
-            let mut database = create_database();
-
-            database.lpush(KEY, [VALUE].to_vec()).unwrap();
+        fn flushdb_clear_dictionary() {
+            let mut db = create_database();
 
-            if let SuccessQuery::List(list) = database.lrange(KEY, 0, 0).unwrap() {
-                assert_eq!(list[0].to_string(), VALUE);
-            }
-        }
+            db.mset(vec![KEY1, VALUE1, KEY2, VALUE2]).unwrap();
+            let r = db.get(KEY1).unwrap();
+            assert_eq!(r, SuccessQuery::String(VALUE1.to_owned()));
+            let r = db.get(KEY2).unwrap();
+            assert_eq!(r, SuccessQuery::String(VALUE2.to_owned()));
 
-        #[test]
-        fn test_lrange_on_zero_two_range_applied_to_a_list_with_four_elements() {
-            let mut database = database_with_a_list();
+            let r = db.flushdb().unwrap();
+            assert_eq!(r, SuccessQuery::Success);
 
-            if let SuccessQuery::List(list) = database.lrange(KEY, 0, 2).unwrap() {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let second_list: Vec<&str> = vec![VALUED, VALUEC, VALUEB];
-                let pair_list: Vec<(&String, &str)> = list.iter().zip(second_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                })
-            }
+            let guard = db.dictionary();
+            assert!(guard.len() == 0);
         }
+    }
+
+    mod watch_test {
+        use super::*;
 
         #[test]
-        fn test_lrange_on_one_three_range_applied_to_a_list_with_four_elements() {
-            let mut database = database_with_a_list();
+        fn signal_modified_key_changes_the_version_of_watched_keys() {
+            let db = create_database();
 
-            if let SuccessQuery::List(list) = database.lrange(KEY, 1, 3).unwrap() {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let second_list: Vec<&str> = vec![VALUEC, VALUEB, VALUEA];
-                let pair_list: Vec<(&String, &str)> = list.iter().zip(second_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                })
-            }
+            let version = db.watch(KEY1);
+            assert_eq!(db.key_version(0, KEY1), version);
+            db.signal_modified_key(KEY1);
+            assert_ne!(db.key_version(0, KEY1), version);
         }
 
         #[test]
-        fn test_lrange_on_a_superior_out_of_bound() {
-            let mut database = database_with_a_list();
+        fn flushdb_changes_the_version_of_existing_watched_keys() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
 
-            let result = database.lrange(KEY, 1, 5).unwrap();
+            let version1 = db.watch(KEY1);
+            let version2 = db.watch(KEY2);
+            db.flushdb().unwrap();
 
-            assert_eq!(result, SuccessQuery::List(Vec::new()));
+            assert_ne!(db.key_version(0, KEY1), version1);
+            assert_eq!(db.key_version(0, KEY2), version2);
         }
 
         #[test]
-        fn test_lrange_on_an_inferior_out_of_bound() {
-            let mut database = database_with_a_list();
+        fn unwatched_keys_are_forgotten() {
+            let db = create_database();
 
-            let result = database.lrange(KEY, -10, 3).unwrap();
+            db.watch(KEY1);
+            db.signal_modified_key(KEY1);
+            db.unwatch(0, KEY1);
 
-            assert_eq!(result, SuccessQuery::List(Vec::new()));
+            assert_eq!(db.watch(KEY1), 0);
         }
 
         #[test]
-        fn test_lrange_on_an_two_way_out_of_bound() {
-            let mut database = database_with_a_list();
+        fn a_watched_key_expired_when_accessed_changes_its_version() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            let key_ttl = KeyTtl::new(KEY1, SystemTime::now());
+            db.expires().lock().unwrap().insert(key_ttl);
 
-            let result = database.lrange(KEY, -10, 10).unwrap();
+            let version = db.watch(KEY1);
+            let r = db.get(KEY1).unwrap();
 
-            assert_eq!(result, SuccessQuery::List(Vec::new()));
+            assert_eq!(r, SuccessQuery::Nil);
+            assert_ne!(db.key_version(0, KEY1), version);
         }
 
         #[test]
-        fn test_lrange_with_valid_negative_bounds() {
-            let mut database = database_with_a_list();
+        fn a_watched_key_expired_by_the_supervisor_changes_its_version() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.pexpire(KEY1, 10, ExpireFlags::default()).unwrap();
 
-            if let SuccessQuery::List(list) = database.lrange(KEY, -3, -1).unwrap() {
-                let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
-                let second_list: Vec<&str> = vec![VALUEC, VALUEB, VALUEA];
-                let pair_list: Vec<(&String, &str)> = list.iter().zip(second_list).collect();
-                pair_list.iter().for_each(|x| {
-                    assert_eq!(x.0, x.1);
-                })
-            }
+            let version = db.watch(KEY1);
+            thread::sleep(ACTIVE_EXPIRE_INTERVAL * 3);
+
+            assert!(!db.dictionary().contains_key(KEY1));
+            assert_ne!(db.key_version(0, KEY1), version);
         }
     }
 
-    mod lrem_test {
+    mod modified_test {
         use super::*;
 
         #[test]
-        fn test_lrem_2_on_a_list() {
-            let mut database = database_with_a_three_repeated_values();
+        fn writes_that_find_nothing_to_change_dont_mark_the_database() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.sadd(KEY2, vec![VALUE1]).unwrap();
+            db.take_modified();
+
+            db.del(b"missing").unwrap();
+            let nx = SetFlags {
+                nx: true,
+                get: true,
+                ..SetFlags::default()
+            };
+            db.set_with_flags(KEY1, VALUE2, nx).unwrap();
+            db.srem(KEY2, vec![VALUE2]).unwrap();
+            db.hdel(b"missing", vec![b"field"]).unwrap();
+            db.zrem(b"missing", vec![b"member"]).unwrap();
 
-            let result = database.lrem(KEY, 2, VALUEA);
+            assert!(!db.take_modified());
+        }
 
-            assert_eq!(SuccessQuery::Integer(2), result.unwrap());
+        #[test]
+        fn writes_that_change_a_key_mark_the_database_once() {
+            let mut db = create_database();
 
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+            db.set(KEY1, VALUE1).unwrap();
+            assert!(db.take_modified());
+            assert!(!db.take_modified());
 
-            if let Some((StorageValue::List(list), _)) = dictionary.get(KEY) {
-                assert_eq!(list[0], VALUEC);
-                assert_eq!(list[1], VALUEA);
-            }
+            db.del(KEY1).unwrap();
+            assert!(db.take_modified());
         }
 
         #[test]
-        fn test_lrem_negative_2_on_a_list() {
-            let mut database = database_with_a_three_repeated_values();
+        fn lrem_that_empties_the_list_marks_the_database() {
+            let mut db = create_database();
+            db.rpush(KEY1, vec![VALUE1, VALUE1]).unwrap();
+            db.take_modified();
 
-            let result = database.lrem(KEY, -2, VALUEA);
+            let r = db.lrem(KEY1, 0, VALUE1).unwrap();
 
-            assert_eq!(SuccessQuery::Integer(2), result.unwrap());
+            assert_eq!(r, SuccessQuery::Integer(0));
+            assert!(db.take_modified());
+        }
 
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+        #[test]
+        fn an_empty_zunionstore_marks_the_database_if_it_deletes_destination() {
+            let mut db = create_database();
+            db.zadd(KEY1, ZaddFlags::default(), vec![(1.0, VALUE1)])
+                .unwrap();
+            db.take_modified();
 
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert_eq!(list[0], VALUEA);
-                assert_eq!(list[1], VALUEC);
-            }
+            let r = db
+                .zunionstore(KEY1, vec![b"missing"], None, Aggregate::Sum)
+                .unwrap();
+            assert_eq!(r, SuccessQuery::Integer(0));
+            assert!(db.take_modified());
+
+            db.zunionstore(KEY1, vec![b"missing"], None, Aggregate::Sum)
+                .unwrap();
+            assert!(!db.take_modified());
         }
 
         #[test]
-        fn test_lrem_zero_on_a_list() {
-            let mut database = database_with_a_three_repeated_values();
+        fn clones_have_their_own_mark() {
+            let mut db = create_database();
+            let mut other = db.clone();
 
-            let result = database.lrem(KEY, 0, VALUEA);
+            other.set(KEY1, VALUE1).unwrap();
 
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+            assert!(!db.take_modified());
+            assert!(other.take_modified());
+        }
+    }
 
-            assert_eq!(SuccessQuery::Integer(1), result.unwrap());
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert_eq!(list[0], VALUEC);
-            }
+    mod dbsize_test {
+        use super::*;
+
+        #[test]
+        fn dbsize_empty_gets_0() {
+            let db = create_database();
+
+            let r = db.dbsize().unwrap();
+            assert_eq!(r, SuccessQuery::Integer(0));
         }
 
         #[test]
-        fn test_lset_with_a_non_existen_key() {
-            let mut database = create_database();
+        fn dbsize_with_one_element_gets_1() {
+            let mut db = create_database();
+            let _ = db.set(KEY1, VALUE1);
+            let r = db.get(KEY1).unwrap();
+            assert_eq!(r, SuccessQuery::String(VALUE1.to_owned()));
 
-            let result = database.lset(KEY, 0, &VALUEA).unwrap_err();
+            let r = db.dbsize().unwrap();
+            assert_eq!(r, SuccessQuery::Integer(1));
+        }
 
-            assert_eq!(result, DataBaseError::NonExistentKey);
+        #[test]
+        fn dbsize_with_two_element_gets_2() {
+            let mut db = create_database();
+            let _ = db.mset(vec![KEY1, VALUE1, KEY2, VALUE2]);
+            let r = db.get(KEY1).unwrap();
+            assert_eq!(r, SuccessQuery::String(VALUE1.to_owned()));
+            let r = db.get(KEY2).unwrap();
+            assert_eq!(r, SuccessQuery::String(VALUE2.to_owned()));
+
+            let r = db.dbsize().unwrap();
+            assert_eq!(r, SuccessQuery::Integer(2));
         }
+    }
+
+    mod databases_test {
+        use super::*;
 
         #[test]
-        fn test_lset_with_a_value_that_isn_a_list() {
-            let mut database = database_with_a_string();
+        fn select_changes_the_keys_a_database_works_with() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
 
-            let result = database.lset(KEY, 0, &VALUEA).unwrap_err();
+            assert_eq!(db.select(1).unwrap(), SuccessQuery::Success);
+            assert_eq!(db.exists(KEY1).unwrap(), SuccessQuery::Boolean(false));
+            db.set(KEY1, VALUE2).unwrap();
 
-            assert_eq!(result, DataBaseError::NotAList);
+            db.select(0).unwrap();
+            assert_eq!(db.get(KEY1).unwrap(), SuccessQuery::String(VALUE1.to_vec()));
         }
 
         #[test]
-        fn test_lset_on_a_list_with_values() {
-            let mut database = database_with_a_list();
+        fn select_out_of_range_fails() {
+            let mut db = Database::new(DB_DUMP.to_string(), 2, 16).unwrap();
 
-            let result = database.lset(KEY, 0, &VALUEA);
-            assert_eq!(SuccessQuery::Success, result.unwrap());
+            assert_eq!(db.select(2).unwrap_err(), DataBaseError::DbIndexOutOfRange);
+            assert_eq!(db.selected_db(), 0);
+        }
 
-            let dictionary = database.dictionary;
-            let dictionary = dictionary.get_atomic_hash(KEY);
-            let dictionary = dictionary.lock().unwrap();
+        #[test]
+        fn clones_select_databases_independently() {
+            let mut db = create_database();
+            let mut other = db.clone();
+            other.select(1).unwrap();
+            other.set(KEY1, VALUE1).unwrap();
 
-            if let (StorageValue::List(list), _) = dictionary.get(KEY).unwrap() {
-                assert_eq!(list[0], VALUEA);
-            }
+            assert_eq!(db.exists(KEY1).unwrap(), SuccessQuery::Boolean(false));
         }
-    }
-}
 
-#[cfg(test)]
-mod group_set {
-    use super::*;
+        #[test]
+        fn flushdb_only_clears_the_selected_database() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.select(1).unwrap();
+            db.set(KEY2, VALUE2).unwrap();
 
-    const KEY: &str = "KEY";
-    const KEY_WITH_STR: &str = "KEY_WITH_STRING";
-    const VALUE_A: &str = "VALUE_A";
-    const NON_EXIST_KEY: &str = "NON_EXIST_KEY";
-    const NON_EXIST_ELEMENT: &str = "NON_EXIST_ELEMENT";
-    const ELEMENT: &str = "ELEMENT";
-    const ELEMENT_2: &str = "ELEMENT2";
-    const ELEMENT_3: &str = "ELEMENT3";
-    const OTHER_ELEMENT: &str = "OTHER_ELEMENT";
+            db.flushdb().unwrap();
+            db.select(0).unwrap();
 
-    const DB_DUMP: &str = "db_dump_path.txt";
+            assert_eq!(db.dbsize().unwrap(), SuccessQuery::Integer(1));
+        }
 
-    fn create_database() -> Database {
-        let db = Database::new(DB_DUMP.to_string());
-        db
-    }
+        #[test]
+        fn flushall_clears_every_database() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.select(1).unwrap();
+            db.set(KEY2, VALUE2).unwrap();
 
-    mod saad_test {
-        use super::*;
+            db.flushall().unwrap();
+
+            assert_eq!(db.dbsize().unwrap(), SuccessQuery::Integer(0));
+            db.select(0).unwrap();
+            assert_eq!(db.dbsize().unwrap(), SuccessQuery::Integer(0));
+        }
 
         #[test]
-        fn test_sadd_create_new_set_with_element_returns_1_if_key_set_not_exist_in_database() {
-            let mut database = create_database();
+        fn swapdb_is_seen_by_every_clone() {
+            let mut db = create_database();
+            let mut other = db.clone();
+            db.set(KEY1, VALUE1).unwrap();
+            other.select(1).unwrap();
 
-            let result = database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
-            assert_eq!(result, SuccessQuery::Integer(1));
-            let is_member = database.sismember(KEY, ELEMENT).unwrap();
-            assert_eq!(is_member, SuccessQuery::Boolean(true));
+            db.swapdb(0, 1).unwrap();
+
+            assert_eq!(db.exists(KEY1).unwrap(), SuccessQuery::Boolean(false));
+            assert_eq!(
+                other.get(KEY1).unwrap(),
+                SuccessQuery::String(VALUE1.to_vec())
+            );
         }
 
         #[test]
-        fn test_sadd_create_set_with_repeating_elements_returns_0() {
-            let mut database = create_database();
+        fn swapdb_changes_the_version_of_watched_keys() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            let version = db.watch(KEY1);
 
-            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+            db.swapdb(0, 1).unwrap();
 
-            let result = database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
-            assert_eq!(result, SuccessQuery::Integer(0));
-            let len_set = database.scard(KEY).unwrap();
-            assert_eq!(len_set, SuccessQuery::Integer(1));
+            assert_ne!(db.key_version(0, KEY1), version);
         }
 
         #[test]
-        fn test_sadd_key_with_another_type_of_set_returns_err() {
-            let mut database = create_database();
-            database.set(KEY, ELEMENT).unwrap();
+        fn move_key_keeps_its_ttl() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.expire(KEY1, 100, ExpireFlags::default()).unwrap();
 
-            let result = database.sadd(KEY, [ELEMENT].to_vec()).unwrap_err();
-            assert_eq!(result, DataBaseError::NotASet);
+            assert_eq!(db.move_key(KEY1, 1).unwrap(), SuccessQuery::Boolean(true));
+            assert_eq!(db.exists(KEY1).unwrap(), SuccessQuery::Boolean(false));
+
+            db.select(1).unwrap();
+            assert_eq!(db.get(KEY1).unwrap(), SuccessQuery::String(VALUE1.to_vec()));
+            if let SuccessQuery::Integer(ttl) = db.ttl(KEY1).unwrap() {
+                assert!(ttl > 0 && ttl <= 100);
+            }
         }
 
         #[test]
-        fn test_sadd_add_element_with_set_created_returns_1() {
-            let mut database = create_database();
-            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+        fn move_key_does_nothing_if_the_key_exists_in_the_target() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.select(1).unwrap();
+            db.set(KEY1, VALUE2).unwrap();
+            db.select(0).unwrap();
 
-            let result = database.sadd(KEY, [OTHER_ELEMENT].to_vec()).unwrap();
-            assert_eq!(result, SuccessQuery::Integer(1));
-            let len_set = database.scard(KEY).unwrap();
-            assert_eq!(len_set, SuccessQuery::Integer(2));
+            assert_eq!(db.move_key(KEY1, 1).unwrap(), SuccessQuery::Boolean(false));
+            assert_eq!(db.get(KEY1).unwrap(), SuccessQuery::String(VALUE1.to_vec()));
         }
 
         #[test]
-        fn test_sadd_add_multiple_elem_in_set_returns_lenght_of_correctly_added_set() {
-            let mut database = create_database();
-            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
-
-            let result = database
-                .sadd(KEY, [ELEMENT, ELEMENT_2, ELEMENT_3].to_vec())
-                .unwrap();
+        fn move_key_to_the_same_database_fails() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
 
-            assert_eq!(result, SuccessQuery::Integer(2));
-            let len_set = database.scard(KEY).unwrap();
-            assert_eq!(len_set, SuccessQuery::Integer(3));
+            assert_eq!(db.move_key(KEY1, 0).unwrap_err(), DataBaseError::SameDb);
         }
 
         #[test]
-        fn test_sadd_add_multiple_elem_in_key_that_not_contains_set_return_err() {
-            let mut database = create_database();
-            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
-            database.set(KEY, ELEMENT).unwrap();
+        fn keyspace_info_lists_non_empty_databases() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.select(2).unwrap();
+            db.mset(vec![KEY1, VALUE1, KEY2, VALUE2]).unwrap();
+            db.expire(KEY2, 100, ExpireFlags::default()).unwrap();
 
-            let result = database
-                .sadd(KEY, [ELEMENT, ELEMENT_2, ELEMENT_3].to_vec())
-                .unwrap_err();
+            assert_eq!(db.keyspace_info(), vec![(0, 1, 0), (2, 2, 1)]);
+        }
 
-            assert_eq!(result, DataBaseError::NotASet);
+        #[test]
+        fn shard_info_adds_up_the_keys_of_every_database() {
+            let mut db = Database::new(DB_DUMP.to_string(), 16, 4).unwrap();
+            db.set(KEY1, VALUE1).unwrap();
+            db.select(2).unwrap();
+            db.mset(vec![KEY1, VALUE1, KEY2, VALUE2]).unwrap();
+
+            let shards = db.shard_info();
+            assert_eq!(shards.len(), 4);
+            assert_eq!(shards.iter().sum::<usize>(), 3);
         }
     }
 
-    mod sismember_test {
+    mod memory_test {
         use super::*;
+        use std::thread::sleep;
+
+        const KEY3: &[u8] = b"key3";
 
         #[test]
-        fn test_sismember_set_with_element_returns_1() {
-            let mut database = create_database();
-            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+        fn used_memory_follows_the_changes_of_the_values() {
+            let mut db = create_database();
+            db.rpush(KEY1, vec![VALUE1]).unwrap();
+            db.update_used_memory(KEY1);
+            let one_element = db.used_memory();
 
-            let is_member = database.sismember(KEY, ELEMENT).unwrap();
+            db.rpush(KEY1, vec![VALUE2]).unwrap();
+            db.update_used_memory(KEY1);
+            assert!(db.used_memory() > one_element);
 
-            assert_eq!(is_member, SuccessQuery::Boolean(true));
+            db.del(KEY1).unwrap();
+            assert_eq!(db.used_memory(), 0);
         }
 
         #[test]
-        fn test_sismember_set_without_element_returns_0() {
-            let mut database = create_database();
-            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+        fn noeviction_fails_once_maxmemory_is_reached() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
 
-            let result = database.sismember(KEY, OTHER_ELEMENT).unwrap();
+            db.set_maxmemory(db.used_memory(), EvictionPolicy::NoEviction);
+            assert_eq!(db.free_memory(), Ok(()));
 
-            assert_eq!(result, SuccessQuery::Boolean(false));
+            db.set(KEY2, VALUE2).unwrap();
+            assert_eq!(db.free_memory(), Err(DataBaseError::OutOfMemory));
+            assert_eq!(db.dbsize().unwrap(), SuccessQuery::Integer(2));
         }
 
         #[test]
-        fn test_sismember_key_with_another_type_of_set_returns_err() {
-            let mut database = create_database();
-            database.set(KEY, ELEMENT).unwrap();
-            let result = database.sismember(KEY, ELEMENT).unwrap_err();
+        fn allkeys_lru_evicts_the_least_recently_used_key() {
+            let mut db = create_database();
+            for key in [KEY1, KEY2, KEY3] {
+                db.set(key, VALUE1).unwrap();
+                sleep(Duration::from_millis(10));
+            }
+            db.get(KEY1).unwrap();
 
-            assert_eq!(result, DataBaseError::NotASet);
+            db.set_maxmemory(db.used_memory() - 1, EvictionPolicy::AllkeysLru);
+            db.free_memory().unwrap();
+
+            assert_eq!(db.exists(KEY2).unwrap(), SuccessQuery::Boolean(false));
+            assert_eq!(db.exists(KEY1).unwrap(), SuccessQuery::Boolean(true));
+            assert_eq!(db.exists(KEY3).unwrap(), SuccessQuery::Boolean(true));
+            let (_, _, _, evicted_keys) = db.memory_info();
+            assert_eq!(evicted_keys, 1);
         }
 
         #[test]
-        fn test_sismember_with_non_exist_key_set_returns_0() {
-            let mut database = create_database();
+        fn evicted_keys_are_published() {
+            let mut db = create_database();
+            let mut channels = Channels::new();
+            db.set_notifications(channels.clone());
+            db.set_notify_keyspace_events(KeyspaceEvents::parse("Ke").unwrap());
+            let (s, r) = std::sync::mpsc::channel();
+            channels.subscribe("__keyspace@0__:key1", s.into(), 1);
+            db.set(KEY1, VALUE1).unwrap();
 
-            let result = database.sismember(KEY, ELEMENT).unwrap();
+            db.set_maxmemory(1, EvictionPolicy::AllkeysRandom);
+            db.free_memory().unwrap();
 
-            assert_eq!(result, SuccessQuery::Boolean(false));
+            let message = r.try_recv().unwrap();
+            assert!(message.ends_with("$7\r\nevicted\r\n"));
         }
-    }
 
-    mod srem_test {
-        use super::*;
-        #[test]
-        fn test_srem_one_member_in_set_returns_1_if_set_contains_member() {
-            let members = vec![ELEMENT];
-            let mut database = create_database();
-            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
-            let result = database.srem(KEY, members).unwrap();
-            let is_member = database.sismember(KEY, &ELEMENT.to_string()).unwrap();
-            assert_eq!(result, SuccessQuery::Integer(1));
-            assert_eq!(is_member, SuccessQuery::Boolean(false));
-        }
         #[test]
-        fn test_srem_key_no_exits_in_database_returns_0() {
-            let mut database = create_database();
-            let members_to_rmv = vec![ELEMENT];
-            let result = database.srem(KEY, members_to_rmv).unwrap();
-            assert_eq!(result, SuccessQuery::Boolean(false));
-        }
-        #[test]
-        fn test_srem_multiple_members_in_set_returns_lenght_of_removed_elements_in_the_set() {
-            let mut database = create_database();
-            let members = vec![ELEMENT, ELEMENT_2, ELEMENT_3];
-            let members_to_rmv = vec![ELEMENT, ELEMENT_2, ELEMENT_3, NON_EXIST_ELEMENT];
+        fn allkeys_lfu_evicts_the_least_frequently_used_key() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.set(KEY2, VALUE2).unwrap();
+            for _ in 0..10 {
+                db.get(KEY1).unwrap();
+            }
 
-            let _ = database.sadd(KEY, members.clone());
+            db.set_maxmemory(db.used_memory() - 1, EvictionPolicy::AllkeysLfu);
+            db.free_memory().unwrap();
 
-            let result = database.srem(KEY, members_to_rmv).unwrap();
-            assert_eq!(result, SuccessQuery::Integer(3));
-            for member in members {
-                let is_member = database.sismember(KEY, &member.to_string()).unwrap();
-                assert_eq!(is_member, SuccessQuery::Boolean(false));
-            }
+            assert_eq!(db.exists(KEY1).unwrap(), SuccessQuery::Boolean(true));
+            assert_eq!(db.exists(KEY2).unwrap(), SuccessQuery::Boolean(false));
         }
+
         #[test]
-        fn test_srem_key_with_another_type_another_besides_set_return_err() {
-            let mut database = create_database();
-            let members_to_rmv = vec![ELEMENT];
-            let _ = database.set(KEY_WITH_STR, VALUE_A).unwrap();
-            let result = database.srem(KEY_WITH_STR, members_to_rmv).unwrap_err();
-            assert_eq!(result, DataBaseError::NotASet);
+        fn volatile_ttl_evicts_the_key_that_expires_first() {
+            let mut db = create_database();
+            db.set(KEY1, VALUE1).unwrap();
+            db.set(KEY2, VALUE2).unwrap();
+            db.select(1).unwrap();
+            db.set(KEY3, VALUE1).unwrap();
+            db.expire(KEY3, 100, ExpireFlags::default()).unwrap();
+            db.select(0).unwrap();
+            db.expire(KEY2, 50, ExpireFlags::default()).unwrap();
+
+            db.set_maxmemory(db.used_memory() - 1, EvictionPolicy::VolatileTtl);
+            db.free_memory().unwrap();
+            assert_eq!(db.exists(KEY2).unwrap(), SuccessQuery::Boolean(false));
+
+            db.set_maxmemory(1, EvictionPolicy::VolatileTtl);
+            assert_eq!(db.free_memory(), Err(DataBaseError::OutOfMemory));
+            assert_eq!(db.exists(KEY1).unwrap(), SuccessQuery::Boolean(true));
+            db.select(1).unwrap();
+            assert_eq!(db.exists(KEY3).unwrap(), SuccessQuery::Boolean(false));
         }
     }
+}
 
-    #[test]
-    fn test_scard_set_with_one_element_returns_1() {
-        let mut database = create_database();
-        database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
+#[cfg(test)]
+mod dump_test {
+    use super::*;
 
-        let len_set = database.scard(KEY).unwrap();
+    const STRING_KEY: &[u8] = b"string key\r\n";
+    const LIST_KEY: &[u8] = b"list\x00key";
+    const SET_KEY: &[u8] = b"set key";
+    const HASH_KEY: &[u8] = b"hash key";
+    const ZSET_KEY: &[u8] = b"zset key";
 
-        assert_eq!(len_set, SuccessQuery::Integer(1));
-    }
+    const VALUE_A: &[u8] = b"\x00\xff value with spaces\n";
+    const VALUE_B: &[u8] = b"12:not a length";
+    const VALUE_C: &[u8] = b"";
 
-    #[test]
-    fn test_scard_create_set_with_multiple_elements_returns_lenght_of_set() {
-        let mut database = create_database();
-        let elements = vec!["0", "1", "2", "3"];
+    const DB_DUMP: &str = "db_dump_test_path";
 
-        let _ = database.sadd(KEY, elements);
+    fn filled_database(path: &str) -> Database {
+        let mut database = Database::new(path.to_string(), 16, 16).unwrap();
+        database.set(STRING_KEY, VALUE_A).unwrap();
+        database
+            .rpush(LIST_KEY, vec![VALUE_A, VALUE_B, VALUE_C])
+            .unwrap();
+        database.sadd(SET_KEY, vec![VALUE_B, VALUE_C]).unwrap();
+        database
+            .hset(HASH_KEY, vec![VALUE_B, VALUE_A, VALUE_C, VALUE_B])
+            .unwrap();
+        let pairs: Vec<(f64, &[u8])> = vec![(-1.5, VALUE_A), (f64::INFINITY, VALUE_C)];
+        database
+            .zadd(ZSET_KEY, ZaddFlags::default(), pairs)
+            .unwrap();
+        database
+            .expire(STRING_KEY, 100, ExpireFlags::default())
+            .unwrap();
+        database
+    }
 
-        let len_set = database.scard(KEY).unwrap();
-        assert_eq!(len_set, SuccessQuery::Integer(4));
+    fn assert_filled(loaded: &mut Database) {
+        let result = loaded.get(STRING_KEY).unwrap();
+        assert_eq!(result, SuccessQuery::String(VALUE_A.to_vec()));
+
+        let result = loaded.lrange(LIST_KEY, 0, -1).unwrap();
+        let list = vec![
+            SuccessQuery::String(VALUE_A.to_vec()),
+            SuccessQuery::String(VALUE_B.to_vec()),
+            SuccessQuery::String(VALUE_C.to_vec()),
+        ];
+        assert_eq!(result, SuccessQuery::List(list));
+
+        assert_eq!(loaded.scard(SET_KEY).unwrap(), SuccessQuery::Integer(2));
+        assert_eq!(
+            loaded.sismember(SET_KEY, VALUE_B).unwrap(),
+            SuccessQuery::Boolean(true)
+        );
+
+        let result = loaded.hget(HASH_KEY, VALUE_B).unwrap();
+        assert_eq!(result, SuccessQuery::String(VALUE_A.to_vec()));
+        assert_eq!(loaded.hlen(HASH_KEY).unwrap(), SuccessQuery::Integer(2));
+
+        let result = loaded
+            .zrange(ZSET_KEY, ZrangeBy::Index(0, -1), false, None, true)
+            .unwrap();
+        let sorted_set = vec![
+            SuccessQuery::String(VALUE_A.to_vec()),
+            SuccessQuery::String(b"-1.5".to_vec()),
+            SuccessQuery::String(VALUE_C.to_vec()),
+            SuccessQuery::String(b"inf".to_vec()),
+        ];
+        assert_eq!(result, SuccessQuery::List(sorted_set));
+
+        if let SuccessQuery::Integer(ttl) = loaded.ttl(STRING_KEY).unwrap() {
+            assert!(ttl > 0 && ttl <= 100);
+        }
     }
 
     #[test]
-    fn test_scard_key_with_another_type_of_set_returns_err() {
-        let mut database = create_database();
-        database.set(KEY, ELEMENT).unwrap();
+    fn dump_then_load_keeps_binary_keys_and_values() {
+        let database = filled_database(DB_DUMP);
 
-        let result = database.scard(KEY).unwrap_err();
+        fs::write(DB_DUMP, database.dump()).unwrap();
+        let mut loaded = Database::new(DB_DUMP.to_string(), 16, 16).unwrap();
+        fs::remove_file(DB_DUMP).unwrap();
 
-        assert_eq!(result, DataBaseError::NotASet);
+        assert_filled(&mut loaded);
     }
 
     #[test]
-    fn test_scard_key_set_not_exist_in_database_returns_0() {
-        let mut database = create_database();
-
-        let result = database.scard(KEY).unwrap();
-
-        assert_eq!(result, SuccessQuery::Boolean(false));
+    fn rdb_dump_then_load_keeps_binary_keys_and_values() {
+        let path = DB_DUMP.to_string() + "_rdb";
+        let mut database = filled_database(&path);
+        database.select(9).unwrap();
+        database.set(STRING_KEY, VALUE_B).unwrap();
+
+        fs::write(&path, database.rdb_dump()).unwrap();
+        let mut loaded = Database::new(path.clone(), 16, 16).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_filled(&mut loaded);
+        loaded.select(9).unwrap();
+        let result = loaded.get(STRING_KEY).unwrap();
+        assert_eq!(result, SuccessQuery::String(VALUE_B.to_vec()));
     }
 
-    mod smembers_test {
-        use super::*;
+    #[test]
+    fn save_uses_the_dump_format() {
+        let path = DB_DUMP.to_string() + "_format";
+        let database = filled_database(&path);
+        database.set_dump_format(DumpFormat::Rdb);
 
-        #[test]
-        fn test_smembers_with_elements_return_list_of_elements() {
-            let mut database = create_database();
-            database.sadd(KEY, [ELEMENT].to_vec()).unwrap();
-            database.sadd(KEY, [OTHER_ELEMENT].to_vec()).unwrap();
+        database.save().unwrap();
+        let contents = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
 
-            if let SuccessQuery::List(list) = database.smembers(KEY).unwrap() {
-                for elem in list {
-                    let is_member = database.sismember(KEY, &elem.to_string()).unwrap();
-                    assert_eq!(is_member, SuccessQuery::Boolean(true));
-                }
-            }
-        }
+        assert!(contents.starts_with(b"REDIS0009"));
+    }
 
-        #[test]
-        fn test_smembers_with_non_exist_key_return_empty_list() {
-            let mut database = create_database();
+    #[test]
+    fn debug_reload_keeps_the_data_and_the_selected_database() {
+        let path = DB_DUMP.to_string() + "_reload";
+        let mut database = filled_database(&path);
+        database.set_dump_format(DumpFormat::Rdb);
+        database.select(3).unwrap();
+        database.set(STRING_KEY, VALUE_B).unwrap();
+
+        let result = database.debug_reload();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap(), SuccessQuery::Success);
+        let result = database.get(STRING_KEY).unwrap();
+        assert_eq!(result, SuccessQuery::String(VALUE_B.to_vec()));
+        database.select(0).unwrap();
+        assert_filled(&mut database);
+    }
 
-            let result = database.smembers(NON_EXIST_KEY).unwrap();
+    #[test]
+    fn load_reports_a_corrupt_rdb_dump() {
+        let path = DB_DUMP.to_string() + "_bad_rdb";
+        let mut dump = filled_database(&path).rdb_dump();
+        let len = dump.len();
+        dump[len - 12] ^= 1;
 
-            assert_eq!(result, SuccessQuery::List(Vec::new()));
-        }
+        fs::write(&path, dump).unwrap();
+        let result = Database::new(path.clone(), 16, 16);
+        fs::remove_file(&path).unwrap();
 
-        #[test]
-        fn test_smembers_key_with_another_type_another_besides_set_return_err() {
-            let mut database = create_database();
+        match result {
+            Err(error) => assert_eq!(error.to_string(), "Bad dump file: checksum mismatch"),
+            Ok(_) => panic!("loaded a corrupt dump"),
+        }
+    }
 
-            let _ = database.set(KEY_WITH_STR, VALUE_A);
+    #[test]
+    fn load_reports_a_truncated_dump() {
+        let mut database = Database::new(DB_DUMP.to_string() + "_truncated", 16, 16).unwrap();
+        database.set(STRING_KEY, VALUE_A).unwrap();
+        database.set(SET_KEY, VALUE_B).unwrap();
 
-            let result = database.smembers(KEY_WITH_STR).unwrap_err();
+        let dump = database.dump();
+        let path = DB_DUMP.to_string() + "_truncated";
+        fs::write(&path, &dump[..dump.len() - 3]).unwrap();
+        let loaded = Database::new(path.clone(), 16, 16);
+        fs::remove_file(&path).unwrap();
 
-            assert_eq!(result, DataBaseError::NotASet);
+        match loaded {
+            Err(DataBaseError::CorruptDump(_)) => {}
+            _ => panic!("A truncated dump was loaded"),
         }
     }
-}
-
-#[cfg(test)]
-mod group_server {
-    use super::*;
-    const KEY1: &str = "key1";
-    const VALUE1: &str = "value1";
-    const KEY2: &str = "key2";
-    const VALUE2: &str = "value2";
-
-    const DB_DUMP: &str = "db_dump_path.txt";
 
-    fn create_database() -> Database {
-        let db = Database::new(DB_DUMP.to_string());
-        db
+    #[test]
+    fn load_reports_a_dump_with_a_changed_byte() {
+        let mut database = Database::new(DB_DUMP.to_string() + "_changed", 16, 16).unwrap();
+        database.set(STRING_KEY, VALUE_A).unwrap();
+
+        let mut dump = database.dump();
+        let path = DB_DUMP.to_string() + "_changed";
+        dump[8] ^= 1;
+        fs::write(&path, &dump).unwrap();
+        let loaded = Database::new(path.clone(), 16, 16);
+        fs::remove_file(&path).unwrap();
+
+        let error = DataBaseError::CorruptDump("checksum mismatch".to_string());
+        assert_eq!(loaded.err(), Some(error));
     }
 
-    mod flushdb_test {
-        use super::*;
+    #[test]
+    fn load_reports_an_unsupported_version() {
+        let path = DB_DUMP.to_string() + "_version";
+        let mut dump = b"Dump 9\n".to_vec();
+        dump.extend_from_slice(format!("Checksum {}\n", crc32(&dump)).as_bytes());
+        fs::write(&path, &dump).unwrap();
+        let loaded = Database::new(path.clone(), 16, 16);
+        fs::remove_file(&path).unwrap();
+
+        let error = DataBaseError::CorruptDump("unsupported version 9".to_string());
+        assert_eq!(loaded.err(), Some(error));
+    }
 
-        #[test]
-        fn flushdb_clear_dictionary() {
-            let mut db = create_database();
+    #[test]
+    fn load_reads_times_to_live_of_version_1_in_seconds() {
+        let path = DB_DUMP.to_string() + "_version_1";
+        let expire_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 100;
+        let mut dump = format!("Dump 1\nDb 0\nTTL 1:a {}\n", expire_at).into_bytes();
+        dump.extend_from_slice(b"Key 1:a String 1:b\n");
+        dump.extend_from_slice(format!("Checksum {}\n", crc32(&dump)).as_bytes());
+        fs::write(&path, &dump).unwrap();
+        let mut loaded = Database::new(path.clone(), 16, 16).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let result = loaded.expiretime(b"a").unwrap();
+        assert_eq!(result, SuccessQuery::Integer(expire_at as i64));
+    }
 
-            db.mset(vec![KEY1, VALUE1, KEY2, VALUE2]).unwrap();
-            let r = db.get(KEY1).unwrap();
-            assert_eq!(r, SuccessQuery::String(VALUE1.to_owned()));
-            let r = db.get(KEY2).unwrap();
-            assert_eq!(r, SuccessQuery::String(VALUE2.to_owned()));
+    #[test]
+    fn load_reads_a_dump_without_version() {
+        let path = DB_DUMP.to_string() + "_unversioned";
+        fs::write(&path, b"Key 4:a:,b String 5:c d e\n").unwrap();
+        let mut loaded = Database::new(path.clone(), 16, 16).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let result = loaded.get(b"a:,b").unwrap();
+        assert_eq!(result, SuccessQuery::String(b"c d e".to_vec()));
+    }
 
-            let r = db.flushdb().unwrap();
-            assert_eq!(r, SuccessQuery::Success);
+    #[test]
+    fn load_reports_an_unknown_record() {
+        let path = DB_DUMP.to_string() + "_unknown";
+        fs::write(&path, b"Key 1:a String 1:b\nFoo\n").unwrap();
+        let loaded = Database::new(path.clone(), 16, 16);
+        fs::remove_file(&path).unwrap();
+
+        let error = DataBaseError::CorruptDump("bad unknown record at byte 19".to_string());
+        assert_eq!(loaded.err(), Some(error));
+    }
 
-            let guard = db.dictionary;
-            assert!(guard.len() == 0);
-        }
+    #[test]
+    fn save_replaces_the_dump_file_and_resets_the_changes() {
+        let path = DB_DUMP.to_string() + "_save";
+        fs::write(&path, b"old contents").unwrap();
+        let mut database = Database::new(DB_DUMP.to_string() + "_empty", 16, 16).unwrap();
+        database.db_dump_path = path.clone();
+        database.set(STRING_KEY, VALUE_A).unwrap();
+        database.increment_dirty();
+
+        assert_eq!(database.save().unwrap(), SuccessQuery::Success);
+        let contents = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, database.dump());
+        assert!(fs::metadata(path + ".tmp").is_err());
+        assert_eq!(database.save_state.lock().unwrap().dirty, 0);
     }
 
-    mod dbsize_test {
-        use super::*;
+    #[test]
+    fn bgsave_writes_the_dump_and_updates_lastsave() {
+        let path = DB_DUMP.to_string() + "_bgsave";
+        let mut database = Database::new(path.clone(), 16, 16).unwrap();
+        database.set(STRING_KEY, VALUE_A).unwrap();
+        database.save_state.lock().unwrap().last_save = SystemTime::UNIX_EPOCH;
+
+        database.bgsave().unwrap();
+        assert_eq!(database.save().unwrap_err(), DataBaseError::SaveInProgress);
+        while database.save_state.lock().unwrap().saving {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let loaded = Database::new(path.clone(), 16, 16).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.dbsize().unwrap(), SuccessQuery::Integer(1));
+        assert_ne!(database.lastsave().unwrap(), SuccessQuery::Integer(0));
+    }
 
-        #[test]
-        fn dbsize_empty_gets_0() {
-            let db = create_database();
+    #[test]
+    fn save_fails_if_the_dump_cant_be_written() {
+        let mut database = Database::new(DB_DUMP.to_string() + "_empty", 16, 16).unwrap();
+        database.db_dump_path = "non_existent_dir/dump".to_string();
+        database.increment_dirty();
 
-            let r = db.dbsize().unwrap();
-            assert_eq!(r, SuccessQuery::Integer(0));
-        }
+        assert_eq!(database.save().unwrap_err(), DataBaseError::SaveFailed);
+        assert_eq!(database.save_state.lock().unwrap().dirty, 1);
+    }
 
-        #[test]
-        fn dbsize_with_one_element_gets_1() {
-            let mut db = create_database();
-            let _ = db.set(KEY1, VALUE1);
-            let r = db.get(KEY1).unwrap();
-            assert_eq!(r, SuccessQuery::String(VALUE1.to_owned()));
+    #[test]
+    fn dump_then_load_keeps_every_database() {
+        let path = DB_DUMP.to_string() + "_databases";
+        let mut database = Database::new(path.clone(), 16, 16).unwrap();
+        database.set(STRING_KEY, VALUE_A).unwrap();
+        database.select(3).unwrap();
+        database.set(STRING_KEY, VALUE_B).unwrap();
+        database
+            .expire(STRING_KEY, 100, ExpireFlags::default())
+            .unwrap();
 
-            let r = db.dbsize().unwrap();
-            assert_eq!(r, SuccessQuery::Integer(1));
-        }
+        fs::write(&path, database.dump()).unwrap();
+        let mut loaded = Database::new(path.clone(), 16, 16).unwrap();
+        fs::remove_file(&path).unwrap();
 
-        #[test]
-        fn dbsize_with_two_element_gets_2() {
-            let mut db = create_database();
-            let _ = db.mset(vec![KEY1, VALUE1, KEY2, VALUE2]);
-            let r = db.get(KEY1).unwrap();
-            assert_eq!(r, SuccessQuery::String(VALUE1.to_owned()));
-            let r = db.get(KEY2).unwrap();
-            assert_eq!(r, SuccessQuery::String(VALUE2.to_owned()));
+        let result = loaded.get(STRING_KEY).unwrap();
+        assert_eq!(result, SuccessQuery::String(VALUE_A.to_vec()));
+        assert_eq!(loaded.ttl(STRING_KEY).unwrap(), SuccessQuery::Integer(-1));
 
-            let r = db.dbsize().unwrap();
-            assert_eq!(r, SuccessQuery::Integer(2));
+        loaded.select(3).unwrap();
+        let result = loaded.get(STRING_KEY).unwrap();
+        assert_eq!(result, SuccessQuery::String(VALUE_B.to_vec()));
+        if let SuccessQuery::Integer(ttl) = loaded.ttl(STRING_KEY).unwrap() {
+            assert!(ttl > 0 && ttl <= 100);
         }
     }
 }
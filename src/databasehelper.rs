@@ -1,20 +1,28 @@
+use crate::sorted_set::{parse_score, LexBound, ScoreBound, SortedSet};
 use core::fmt::{Display, Formatter};
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt;
-use std::sync::mpsc::Sender;
-use std::sync::{Arc, Mutex};
+use std::hash::{BuildHasher, Hasher};
+use std::str::{self, FromStr};
 use std::time::SystemTime;
 
 /// An abstraction used by Database and represents the different types of data that each key in the database can store.
+///
+/// Every value is a byte string, so keys and values can hold any binary payload.
 #[derive(Clone, Debug)]
 pub enum StorageValue {
     #[doc(hidden)]
-    String(String),
+    String(Vec<u8>),
+    #[doc(hidden)]
+    List(Vec<Vec<u8>>),
     #[doc(hidden)]
-    List(Vec<String>),
+    Set(HashSet<Vec<u8>>),
     #[doc(hidden)]
-    Set(HashSet<String>),
+    Hash(HashMap<Vec<u8>, Vec<u8>>),
+    #[doc(hidden)]
+    Zset(SortedSet),
 }
 
 pub enum StorageValueError {
@@ -22,49 +30,340 @@ pub enum StorageValueError {
 }
 
 impl StorageValue {
+    /// Returns the command that creates this value at key, or None if the value is
+    /// empty.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let value = StorageValue::List(vec![b"a".to_vec(), b"b".to_vec()]);
+    /// let command = vec![b"rpush".to_vec(), b"key".to_vec(), b"a".to_vec(), b"b".to_vec()];
+    ///
+    /// assert_eq!(value.aof_command(b"key"), Some(command));
+    /// ```
+    pub fn aof_command(&self, key: &[u8]) -> Option<Vec<Vec<u8>>> {
+        let (name, mut args): (&[u8], Vec<Vec<u8>>) = match self {
+            StorageValue::String(value) => (b"set", vec![value.clone()]),
+            StorageValue::List(list) => (b"rpush", list.clone()),
+            StorageValue::Set(set) => (b"sadd", set.iter().cloned().collect()),
+            StorageValue::Hash(hash) => {
+                let pairs = hash
+                    .iter()
+                    .flat_map(|(field, value)| vec![field.clone(), value.clone()]);
+                (b"hset", pairs.collect())
+            }
+            StorageValue::Zset(zset) => {
+                let pairs = zset
+                    .members()
+                    .into_iter()
+                    .flat_map(|(member, score)| vec![score.to_string().into_bytes(), member]);
+                (b"zadd", pairs.collect())
+            }
+        };
+
+        if args.is_empty() {
+            return None;
+        }
+
+        let mut command = vec![name.to_vec(), key.to_vec()];
+        command.append(&mut args);
+        Some(command)
+    }
+
     pub fn get_type(&self) -> String {
         match self {
             StorageValue::String(_) => "String".to_owned(),
             StorageValue::List(_) => "List".to_owned(),
             StorageValue::Set(_) => "Set".to_owned(),
+            StorageValue::Hash(_) => "Hash".to_owned(),
+            StorageValue::Zset(_) => "Zset".to_owned(),
         }
     }
 
-    pub fn serialize(&self) -> String {
+    /// Serializes the value as its type followed by its elements, every element
+    /// framed by its length so it can contain any byte.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let value = StorageValue::List(vec![b"a b".to_vec(), b"c".to_vec()]);
+    /// assert_eq!(value.serialize(), b"List 2 3:a b 1:c".to_vec());
+    /// ```
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
         match self {
-            StorageValue::String(_) => format!("String {}", self),
-            StorageValue::List(_) => format!("List {}", self),
-            StorageValue::Set(_) => format!("Set {}", self),
+            StorageValue::String(value) => {
+                buffer.extend_from_slice(b"String ");
+                write_field(&mut buffer, value);
+            }
+            StorageValue::List(list) => {
+                buffer.extend_from_slice(format!("List {}", list.len()).as_bytes());
+                for elem in list {
+                    buffer.push(b' ');
+                    write_field(&mut buffer, elem);
+                }
+            }
+            StorageValue::Set(hash_set) => {
+                buffer.extend_from_slice(format!("Set {}", hash_set.len()).as_bytes());
+                for elem in hash_set {
+                    buffer.push(b' ');
+                    write_field(&mut buffer, elem);
+                }
+            }
+            StorageValue::Hash(hash) => {
+                buffer.extend_from_slice(format!("Hash {}", hash.len()).as_bytes());
+                for (field, value) in hash {
+                    buffer.push(b' ');
+                    write_field(&mut buffer, field);
+                    buffer.push(b' ');
+                    write_field(&mut buffer, value);
+                }
+            }
+            StorageValue::Zset(sorted_set) => {
+                buffer.extend_from_slice(format!("Zset {}", sorted_set.len()).as_bytes());
+                for (member, score) in sorted_set.members() {
+                    buffer.extend_from_slice(format!(" {} ", score).as_bytes());
+                    write_field(&mut buffer, &member);
+                }
+            }
         }
+
+        buffer
     }
 
-    pub fn unserialize(value: &str) -> Result<StorageValue, StorageValueError> {
-        let value: Vec<&str> = value.split_whitespace().collect();
+    /// Reads a value written by serialize from the reader.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut reader = DumpReader::new(b"String 5:a b c");
+    /// if let Ok(StorageValue::String(value)) = StorageValue::unserialize(&mut reader) {
+    ///     assert_eq!(value, b"a b c");
+    /// }
+    /// ```
+    pub fn unserialize(reader: &mut DumpReader) -> Result<StorageValue, StorageValueError> {
+        match reader.next_word() {
+            Some(b"String") => match reader.next_field() {
+                Some(value) => Ok(StorageValue::String(value.to_vec())),
+                None => Err(StorageValueError::NonExisten),
+            },
+            Some(b"List") => {
+                let list = read_elements(reader)?;
+                Ok(StorageValue::List(list))
+            }
+            Some(b"Set") => {
+                let set = read_elements(reader)?;
+                Ok(StorageValue::Set(set.into_iter().collect()))
+            }
+            Some(b"Hash") => {
+                let len = match reader.next_number::<usize>() {
+                    Some(len) => len,
+                    None => return Err(StorageValueError::NonExisten),
+                };
+
+                let mut hash = HashMap::new();
+                for _ in 0..len {
+                    match (reader.next_field(), reader.next_field()) {
+                        (Some(field), Some(value)) => {
+                            hash.insert(field.to_vec(), value.to_vec());
+                        }
+                        _ => return Err(StorageValueError::NonExisten),
+                    }
+                }
 
-        match value[..] {
-            ["String", value] => Ok(StorageValue::String(value.to_owned())),
-            ["List", ..] => {
-                let value: Vec<String> = value[1..].iter().map(|&x| x.to_owned()).collect();
-                Ok(StorageValue::List(value))
+                Ok(StorageValue::Hash(hash))
             }
-            ["Set", ..] => {
-                let value: HashSet<String> = value[1..].iter().map(|&x| x.to_owned()).collect();
-                Ok(StorageValue::Set(value))
+            Some(b"Zset") => {
+                let len = match reader.next_number::<usize>() {
+                    Some(len) => len,
+                    None => return Err(StorageValueError::NonExisten),
+                };
+
+                let mut sorted_set = SortedSet::new();
+                for _ in 0..len {
+                    let score = reader.next_word().and_then(parse_score);
+                    match (score, reader.next_field()) {
+                        (Some(score), Some(member)) => {
+                            sorted_set.insert(member, score);
+                        }
+                        _ => return Err(StorageValueError::NonExisten),
+                    }
+                }
+
+                Ok(StorageValue::Zset(sorted_set))
             }
             _ => Err(StorageValueError::NonExisten),
         }
     }
 }
 
+#[doc(hidden)]
+fn read_elements(reader: &mut DumpReader) -> Result<Vec<Vec<u8>>, StorageValueError> {
+    let len = match reader.next_number::<usize>() {
+        Some(len) => len,
+        None => return Err(StorageValueError::NonExisten),
+    };
+
+    let mut elements = Vec::new();
+    for _ in 0..len {
+        match reader.next_field() {
+            Some(elem) => elements.push(elem.to_vec()),
+            None => return Err(StorageValueError::NonExisten),
+        }
+    }
+
+    Ok(elements)
+}
+
+/// Parses a number written as text in a byte string.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// assert_eq!(parse_bytes::<i32>(b"-12"), Some(-12));
+/// assert_eq!(parse_bytes::<i32>(b"\xff"), None);
+/// ```
+pub fn parse_bytes<T: FromStr>(bytes: &[u8]) -> Option<T> {
+    str::from_utf8(bytes).ok()?.parse::<T>().ok()
+}
+
+/// Returns a random number, taken from the random keys the standard library
+/// generates for each HashMap.
+pub fn random_number() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Returns up to count different positions below len, each one taken at random
+/// on its own, or every position if there aren't more than count.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// let positions = random_positions(100, 5);
+/// assert_eq!(positions.len(), 5);
+/// assert!(positions.iter().all(|&position| position < 100));
+///
+/// assert_eq!(random_positions(3, 5), vec![0, 1, 2]);
+/// ```
+pub fn random_positions(len: usize, count: usize) -> Vec<usize> {
+    if len <= count {
+        return (0..len).collect();
+    }
+
+    let mut positions = Vec::with_capacity(count);
+    while positions.len() < count {
+        let position = random_number() as usize % len;
+        if !positions.contains(&position) {
+            positions.push(position);
+        }
+    }
+    positions
+}
+
+/// Appends bytes to buffer framed as `<len>:<bytes>`.
+pub fn write_field(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+    buffer.extend_from_slice(bytes);
+}
+
+/// Returns the CRC-32 (IEEE) checksum of bytes.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// assert_eq!(crc32(b"123456789"), 0xCBF43926);
+/// ```
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// A cursor over a dump, used to read back the words and length-prefixed
+/// fields written by Database and StorageValue.
+pub struct DumpReader<'a> {
+    #[doc(hidden)]
+    buffer: &'a [u8],
+    #[doc(hidden)]
+    pos: usize,
+}
+
+impl<'a> DumpReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> DumpReader<'a> {
+        DumpReader { buffer, pos: 0 }
+    }
+
+    #[doc(hidden)]
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.buffer.get(self.pos) {
+            if !byte.is_ascii_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Returns true if there's nothing left to read but whitespace.
+    pub fn is_empty(&mut self) -> bool {
+        self.skip_whitespace();
+        self.pos >= self.buffer.len()
+    }
+
+    /// Reads the next word, delimited by whitespace.
+    pub fn next_word(&mut self) -> Option<&'a [u8]> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while let Some(byte) = self.buffer.get(self.pos) {
+            if byte.is_ascii_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        if start == self.pos {
+            None
+        } else {
+            Some(&self.buffer[start..self.pos])
+        }
+    }
+
+    /// Returns how many bytes were read.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Reads the next word as a number.
+    pub fn next_number<T: FromStr>(&mut self) -> Option<T> {
+        parse_bytes(self.next_word()?)
+    }
+
+    /// Reads the next field written by write_field.
+    pub fn next_field(&mut self) -> Option<&'a [u8]> {
+        self.skip_whitespace();
+        let tail = &self.buffer[self.pos..];
+        let colon = tail.iter().position(|&b| b == b':')?;
+        let len = str::from_utf8(&tail[..colon]).ok()?.parse::<usize>().ok()?;
+        let start = self.pos + colon + 1;
+        let end = start.checked_add(len)?;
+        let field = self.buffer.get(start..end)?;
+        self.pos = end;
+
+        Some(field)
+    }
+}
+
 impl Display for StorageValue {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            StorageValue::String(string) => write!(f, "{}", string),
+            StorageValue::String(string) => write!(f, "{}", String::from_utf8_lossy(string)),
             StorageValue::List(list) => {
                 let mut parms_string = String::new();
 
                 for elem in list {
-                    parms_string.push_str(elem);
+                    parms_string.push_str(&String::from_utf8_lossy(elem));
                     parms_string.push(' ');
                 }
 
@@ -74,12 +373,36 @@ impl Display for StorageValue {
                 let mut hash_set_string = String::new();
 
                 for elem in hash_set {
-                    hash_set_string.push_str(elem);
+                    hash_set_string.push_str(&String::from_utf8_lossy(elem));
                     hash_set_string.push(' ');
                 }
 
                 write!(f, "{}", hash_set_string)
             }
+            StorageValue::Hash(hash) => {
+                let mut hash_string = String::new();
+
+                for (field, value) in hash {
+                    hash_string.push_str(&String::from_utf8_lossy(field));
+                    hash_string.push(' ');
+                    hash_string.push_str(&String::from_utf8_lossy(value));
+                    hash_string.push(' ');
+                }
+
+                write!(f, "{}", hash_string)
+            }
+            StorageValue::Zset(sorted_set) => {
+                let mut sorted_set_string = String::new();
+
+                for (member, score) in sorted_set.members() {
+                    sorted_set_string.push_str(&String::from_utf8_lossy(&member));
+                    sorted_set_string.push(' ');
+                    sorted_set_string.push_str(&score.to_string());
+                    sorted_set_string.push(' ');
+                }
+
+                write!(f, "{}", sorted_set_string)
+            }
         }
     }
 }
@@ -97,11 +420,145 @@ pub enum SortFlags<'a> {
     #[doc(hidden)]
     Limit(i32, i32),
     #[doc(hidden)]
-    By(&'a str),
+    By(&'a [u8]),
     #[doc(hidden)]
     CompositeFlags(Vec<SortFlags<'a>>),
 }
 
+/// Abstraction that represents the flags that the zadd command from Database can receive.
+///
+/// NX only adds new members, XX only updates existing ones, GT and LT only update
+/// when the new score is greater or less than the current one, CH counts changed
+/// members as well as added ones and INCR increments the score instead of setting it.
+#[derive(Default)]
+pub struct ZaddFlags {
+    #[doc(hidden)]
+    pub nx: bool,
+    #[doc(hidden)]
+    pub xx: bool,
+    #[doc(hidden)]
+    pub gt: bool,
+    #[doc(hidden)]
+    pub lt: bool,
+    #[doc(hidden)]
+    pub ch: bool,
+    #[doc(hidden)]
+    pub incr: bool,
+}
+
+/// The time to live SET and GETEX give a key: EX and PX are relative to now, in
+/// seconds and milliseconds, EXAT and PXAT are Unix times, KEEPTTL keeps the one the
+/// key had and PERSIST removes it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyExpiry {
+    #[doc(hidden)]
+    Ex(i64),
+    #[doc(hidden)]
+    Px(i64),
+    #[doc(hidden)]
+    ExAt(i64),
+    #[doc(hidden)]
+    PxAt(i64),
+    #[doc(hidden)]
+    KeepTtl,
+    #[doc(hidden)]
+    Persist,
+}
+
+impl KeyExpiry {
+    /// Returns the Unix time in milliseconds the key expires at, or None for KEEPTTL
+    /// and PERSIST.
+    ///
+    /// Error if the time isn't positive or is too big.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// assert_eq!(KeyExpiry::PxAt(1500).unix_ms(0), Ok(Some(1500)));
+    /// assert_eq!(KeyExpiry::Ex(2).unix_ms(1000), Ok(Some(3000)));
+    /// assert_eq!(KeyExpiry::Px(0).unix_ms(1000), Err(DataBaseError::InvalidExpireTime));
+    /// ```
+    pub fn unix_ms(&self, now_ms: i64) -> Result<Option<i64>, DataBaseError> {
+        let (time, scale, relative) = match *self {
+            KeyExpiry::Ex(seconds) => (seconds, 1000, true),
+            KeyExpiry::Px(ms) => (ms, 1, true),
+            KeyExpiry::ExAt(seconds) => (seconds, 1000, false),
+            KeyExpiry::PxAt(ms) => (ms, 1, false),
+            KeyExpiry::KeepTtl | KeyExpiry::Persist => return Ok(None),
+        };
+        if time <= 0 {
+            return Err(DataBaseError::InvalidExpireTime);
+        }
+
+        let ms = time
+            .checked_mul(scale)
+            .and_then(|ms| {
+                if relative {
+                    ms.checked_add(now_ms)
+                } else {
+                    Some(ms)
+                }
+            })
+            .ok_or(DataBaseError::InvalidExpireTime)?;
+        Ok(Some(ms))
+    }
+}
+
+/// Abstraction that represents the options of the set command from Database.
+///
+/// NX only sets the key if it doesn't exist and XX only if it does, GET replies with
+/// the value the key had and expiry is the time to live to give the key, which is
+/// otherwise removed.
+#[derive(Default, Clone, Copy)]
+pub struct SetFlags {
+    #[doc(hidden)]
+    pub nx: bool,
+    #[doc(hidden)]
+    pub xx: bool,
+    #[doc(hidden)]
+    pub get: bool,
+    #[doc(hidden)]
+    pub expiry: Option<KeyExpiry>,
+}
+
+/// Abstraction that represents the options of the expire commands from Database.
+///
+/// NX only sets the time to live if the key has none and XX only if it has one, GT
+/// and LT only if the new one is greater or less than the current one. A key without
+/// time to live counts as one that never expires.
+#[derive(Default, Clone, Copy)]
+pub struct ExpireFlags {
+    #[doc(hidden)]
+    pub nx: bool,
+    #[doc(hidden)]
+    pub xx: bool,
+    #[doc(hidden)]
+    pub gt: bool,
+    #[doc(hidden)]
+    pub lt: bool,
+}
+
+/// Abstraction that represents the ways the zrange command from Database can select members:
+/// by position, by score or lexicographically. Bounds are always given as (min, max).
+pub enum ZrangeBy<'a> {
+    #[doc(hidden)]
+    Index(i64, i64),
+    #[doc(hidden)]
+    Score(ScoreBound, ScoreBound),
+    #[doc(hidden)]
+    Lex(LexBound<'a>, LexBound<'a>),
+}
+
+/// How the scores of a member are combined by zunionstore and zinterstore.
+#[derive(Clone, Copy)]
+pub enum Aggregate {
+    #[doc(hidden)]
+    Sum,
+    #[doc(hidden)]
+    Min,
+    #[doc(hidden)]
+    Max,
+}
+
 /// Structure created in order to standardize the different ways of returning a result from the Database, when executing a command
 #[derive(Debug, PartialEq)]
 pub enum SuccessQuery {
@@ -109,22 +566,22 @@ pub enum SuccessQuery {
     Success,
     Boolean(bool),
     #[doc(hidden)]
-    Integer(i32),
+    Integer(i64),
     #[doc(hidden)]
-    String(String),
+    String(Vec<u8>),
     #[doc(hidden)]
     List(Vec<SuccessQuery>),
     #[doc(hidden)]
     Nil,
 }
 
-impl<'a> fmt::Display for SuccessQuery {
+impl fmt::Display for SuccessQuery {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SuccessQuery::Success => write!(f, "Ok"),
             SuccessQuery::Boolean(boolean) => write!(f, "(integer) {}", *boolean as i32),
             SuccessQuery::Integer(val) => write!(f, "(integer) {}", val),
-            SuccessQuery::String(val) => write!(f, "{}", val),
+            SuccessQuery::String(val) => write!(f, "{}", String::from_utf8_lossy(val)),
             SuccessQuery::List(list) => {
                 if list.is_empty() {
                     write!(f, "(empty list or set)")
@@ -167,11 +624,41 @@ pub enum DataBaseError {
     SortParseError,
     #[doc(hidden)]
     SortByParseError,
+    #[doc(hidden)]
+    NotAHash,
+    #[doc(hidden)]
+    NotAFloat,
+    #[doc(hidden)]
+    Overflow,
+    #[doc(hidden)]
+    NotAZset,
+    #[doc(hidden)]
+    NotANumber,
+    #[doc(hidden)]
+    DbIndexOutOfRange,
+    #[doc(hidden)]
+    SameDb,
+    #[doc(hidden)]
+    AofDisabled,
+    #[doc(hidden)]
+    AofRewriteInProgress,
+    #[doc(hidden)]
+    SaveInProgress,
+    #[doc(hidden)]
+    SaveFailed,
+    #[doc(hidden)]
+    InvalidExpireTime,
+    #[doc(hidden)]
+    OutOfMemory,
+    #[doc(hidden)]
+    CorruptDump(String),
+    #[doc(hidden)]
+    CorruptAof(String),
 }
 
 impl fmt::Display for DataBaseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
+        match self {
             DataBaseError::NonExistentKey => write!(f, "Non-existent key"),
             DataBaseError::NotAString => write!(f, "Value isn't a String"),
             DataBaseError::NotAnInteger => write!(f, "Value isn't an Integer"),
@@ -185,49 +672,178 @@ impl fmt::Display for DataBaseError {
             DataBaseError::SortByParseError => {
                 write!(f, "pattern has keys that values hasn't parse to number")
             }
+            DataBaseError::NotAHash => write!(f, "Value isn't a Hash"),
+            DataBaseError::NotAFloat => write!(f, "Value isn't a valid float"),
+            DataBaseError::Overflow => write!(f, "increment or decrement would overflow"),
+            DataBaseError::NotAZset => write!(f, "Value isn't a Sorted Set"),
+            DataBaseError::NotANumber => write!(f, "resulting score is not a number (NaN)"),
+            DataBaseError::DbIndexOutOfRange => write!(f, "DB index is out of range"),
+            DataBaseError::SameDb => write!(f, "source and destination objects are the same"),
+            DataBaseError::AofDisabled => write!(f, "Append only file is disabled"),
+            DataBaseError::AofRewriteInProgress => write!(
+                f,
+                "Background append only file rewriting already in progress"
+            ),
+            DataBaseError::SaveInProgress => write!(f, "Background save already in progress"),
+            DataBaseError::SaveFailed => write!(f, "Couldn't save the dump, check the logs"),
+            DataBaseError::InvalidExpireTime => write!(f, "invalid expire time"),
+            DataBaseError::OutOfMemory => {
+                write!(f, "OOM command not allowed when used memory > 'maxmemory'.")
+            }
+            DataBaseError::CorruptDump(reason) => write!(f, "Bad dump file: {}", reason),
+            DataBaseError::CorruptAof(reason) => {
+                write!(f, "Bad append only file: {}", reason)
+            }
         }
     }
 }
 
-/// Structure created to be able to manage from Database ttl_supervisor_run the different use cases of ttl command in the database keys.
-/// the structure also communicates to solve some commands of the group keys like Expire, Persist, Rename.
-pub enum MessageTtl {
-    #[doc(hidden)]
-    Expire(KeyTtl),
-    #[doc(hidden)]
-    Clear(String),
+/// The time to live of the keys of a database, indexed both by key, so a key's
+/// expire time is found or changed in O(log n), and by expire time, so the keys that
+/// already expired are found without going through the rest.
+#[derive(Default)]
+pub struct TtlIndex {
     #[doc(hidden)]
-    Transfer(String, String),
+    by_key: HashMap<Vec<u8>, (SystemTime, usize)>,
     #[doc(hidden)]
-    Ttl(String, Sender<RespondTtl>),
+    by_time: BTreeSet<(SystemTime, Vec<u8>)>,
+    /// Every key, each one at the position by_key has for it, to sample them.
     #[doc(hidden)]
-    Check(String),
-    #[doc(hidden)]
-    AllTtL(Sender<RespondTtl>),
+    keys: Vec<Vec<u8>>,
 }
 
-/// Structure created together with MessageTtl to resolve the ttl command and the serialization of the database.
-pub enum RespondTtl {
-    #[doc(hidden)]
-    Ttl(SystemTime),
-    #[doc(hidden)]
-    Persistent,
-    #[doc(hidden)]
-    List(Arc<Mutex<Vec<KeyTtl>>>),
+impl TtlIndex {
+    /// Creates an empty index.
+    pub fn new() -> TtlIndex {
+        TtlIndex::default()
+    }
+
+    /// Sets the expire time of key_ttl.key, returning the one it had, if any.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut index = TtlIndex::new();
+    /// let now = SystemTime::now();
+    ///
+    /// assert_eq!(index.insert(KeyTtl::new(b"key", now)), None);
+    /// assert_eq!(index.get(b"key"), Some(now));
+    /// ```
+    pub fn insert(&mut self, key_ttl: KeyTtl) -> Option<SystemTime> {
+        let old = self.remove(&key_ttl.key);
+        self.by_time
+            .insert((key_ttl.expire_time, key_ttl.key.clone()));
+        self.keys.push(key_ttl.key.clone());
+        self.by_key
+            .insert(key_ttl.key, (key_ttl.expire_time, self.keys.len() - 1));
+        old
+    }
+
+    /// Returns the expire time of key, or None if it has no time to live.
+    pub fn get(&self, key: &[u8]) -> Option<SystemTime> {
+        self.by_key.get(key).map(|(expire_time, _)| *expire_time)
+    }
+
+    /// Removes the time to live of key, returning it if it had one.
+    pub fn remove(&mut self, key: &[u8]) -> Option<SystemTime> {
+        let (expire_time, position) = self.by_key.remove(key)?;
+        self.by_time.remove(&(expire_time, key.to_vec()));
+        self.keys.swap_remove(position);
+        if let Some(moved) = self.keys.get(position) {
+            if let Some((_, moved_position)) = self.by_key.get_mut(moved) {
+                *moved_position = position;
+            }
+        }
+        Some(expire_time)
+    }
+
+    /// Moves the time to live of from to to. Whatever time to live to had is
+    /// discarded, even if from has none.
+    pub fn rename(&mut self, from: &[u8], to: &[u8]) {
+        self.remove(to);
+        if let Some(expire_time) = self.remove(from) {
+            self.insert(KeyTtl::new(to, expire_time));
+        }
+    }
+
+    /// Removes and returns the key that expires first if it expired before now.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut index = TtlIndex::new();
+    /// let now = SystemTime::now();
+    /// index.insert(KeyTtl::new(b"old", now - Duration::from_secs(1)));
+    /// index.insert(KeyTtl::new(b"new", now + Duration::from_secs(1)));
+    ///
+    /// assert_eq!(index.pop_expired(now), Some(b"old".to_vec()));
+    /// assert_eq!(index.pop_expired(now), None);
+    /// ```
+    pub fn pop_expired(&mut self, now: SystemTime) -> Option<Vec<u8>> {
+        let (expire_time, key) = self.by_time.iter().next()?.clone();
+        if expire_time >= now {
+            return None;
+        }
+
+        self.remove(&key);
+        Some(key)
+    }
+
+    /// Returns the key that expires first, with its expire time.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut index = TtlIndex::new();
+    /// let now = SystemTime::now();
+    /// index.insert(KeyTtl::new(b"later", now + Duration::from_secs(2)));
+    /// index.insert(KeyTtl::new(b"sooner", now + Duration::from_secs(1)));
+    ///
+    /// assert_eq!(index.first(), Some((now + Duration::from_secs(1), b"sooner".to_vec())));
+    /// ```
+    pub fn first(&self) -> Option<(SystemTime, Vec<u8>)> {
+        self.by_time.iter().next().cloned()
+    }
+
+    /// Returns up to count different keys with a time to live, each one taken at
+    /// random on its own.
+    pub fn sample(&self, count: usize) -> Vec<Vec<u8>> {
+        random_positions(self.keys.len(), count)
+            .into_iter()
+            .map(|position| self.keys[position].clone())
+            .collect()
+    }
+
+    /// Removes every time to live.
+    pub fn clear(&mut self) {
+        self.by_key.clear();
+        self.by_time.clear();
+        self.keys.clear();
+    }
+
+    /// Returns how many keys have a time to live.
+    pub fn len(&self) -> usize {
+        self.by_key.len()
+    }
+
+    /// Returns every key with its expire time, the ones that expire first first.
+    pub fn to_vec(&self) -> Vec<KeyTtl> {
+        self.by_time
+            .iter()
+            .map(|(expire_time, key)| KeyTtl::new(key, *expire_time))
+            .collect()
+    }
 }
 
 /// Structure created as support for the solution of the Expire command, for the creation of a key with expiration time.
 #[derive(Eq, Clone, Debug)]
 pub struct KeyTtl {
-    pub key: String,
+    pub key: Vec<u8>,
     pub expire_time: SystemTime,
 }
 
 impl KeyTtl {
     /// Function to create a key with time to live asociated.
-    pub fn new(key: &str, expire_time: SystemTime) -> KeyTtl {
+    pub fn new(key: &[u8], expire_time: SystemTime) -> KeyTtl {
         KeyTtl {
-            key: key.to_string(),
+            key: key.to_vec(),
             expire_time,
         }
     }
@@ -250,3 +866,146 @@ impl PartialEq for KeyTtl {
         self.key == other.key
     }
 }
+
+#[cfg(test)]
+mod databasehelper_test {
+    use super::*;
+
+    #[test]
+    fn ttl_index_keeps_one_expire_time_per_key() {
+        let mut index = TtlIndex::new();
+        let now = SystemTime::now();
+        let later = now + std::time::Duration::from_secs(10);
+
+        index.insert(KeyTtl::new(b"a", now));
+        assert_eq!(index.insert(KeyTtl::new(b"a", later)), Some(now));
+        index.insert(KeyTtl::new(b"b", now));
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.pop_expired(later), Some(b"b".to_vec()));
+        assert_eq!(index.pop_expired(later), None);
+        assert_eq!(index.get(b"a"), Some(later));
+    }
+
+    #[test]
+    fn ttl_index_rename_discards_the_destination_time_to_live() {
+        let mut index = TtlIndex::new();
+        let now = SystemTime::now();
+        index.insert(KeyTtl::new(b"from", now));
+        index.insert(KeyTtl::new(b"to", now));
+        index.insert(KeyTtl::new(b"other", now));
+
+        index.rename(b"from", b"to");
+        index.rename(b"persistent", b"other");
+
+        assert_eq!(index.get(b"from"), None);
+        assert_eq!(index.get(b"to"), Some(now));
+        assert_eq!(index.get(b"other"), None);
+        assert_eq!(index.to_vec().len(), 1);
+    }
+
+    #[test]
+    fn ttl_index_first_and_sample_look_at_the_keys_with_time_to_live() {
+        let mut index = TtlIndex::new();
+        let now = SystemTime::now();
+        let later = now + std::time::Duration::from_secs(10);
+        assert_eq!(index.first(), None);
+        assert!(index.sample(5).is_empty());
+
+        index.insert(KeyTtl::new(b"a", later));
+        index.insert(KeyTtl::new(b"b", now));
+
+        assert_eq!(index.first(), Some((now, b"b".to_vec())));
+        let mut sample = index.sample(5);
+        sample.sort();
+        assert_eq!(sample, vec![b"a".to_vec(), b"b".to_vec()]);
+    }
+
+    #[test]
+    fn ttl_index_sample_takes_different_keys_still_there_after_removes() {
+        let mut index = TtlIndex::new();
+        let now = SystemTime::now();
+        for i in 0..100 {
+            index.insert(KeyTtl::new(i.to_string().as_bytes(), now));
+        }
+        for i in 0..50 {
+            index.remove(i.to_string().as_bytes());
+        }
+
+        for _ in 0..50 {
+            let sample = index.sample(5);
+            let mut keys = sample.clone();
+            keys.sort();
+            keys.dedup();
+            assert_eq!(keys.len(), 5);
+            assert!(sample.iter().all(|key| index.get(key).is_some()));
+        }
+        assert_eq!(index.sample(100).len(), 50);
+    }
+
+    #[test]
+    fn random_positions_are_different_and_in_range() {
+        let mut positions = random_positions(10, 9);
+        positions.sort();
+        positions.dedup();
+
+        assert_eq!(positions.len(), 9);
+        assert!(positions.iter().all(|&position| position < 10));
+        assert_eq!(random_positions(2, 5), vec![0, 1]);
+    }
+
+    #[test]
+    fn crc32_of_the_check_string() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn serialize_frames_every_element_by_its_length() {
+        let value = StorageValue::List(vec![b"a b".to_vec(), b"c\n".to_vec()]);
+
+        assert_eq!(value.serialize(), b"List 2 3:a b 2:c\n".to_vec());
+    }
+
+    #[test]
+    fn unserialize_a_serialized_value_gets_the_same_value() {
+        let value = StorageValue::String(b"\x00 10:\r\n".to_vec());
+        let serialized = value.serialize();
+        let mut reader = DumpReader::new(&serialized);
+
+        match StorageValue::unserialize(&mut reader) {
+            Ok(StorageValue::String(unserialized)) => assert_eq!(unserialized, b"\x00 10:\r\n"),
+            _ => panic!("Couldn't unserialize the value"),
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn unserialize_a_serialized_hash_gets_the_same_hash() {
+        let mut hash = HashMap::new();
+        hash.insert(b"field one".to_vec(), b"value\r\none".to_vec());
+        hash.insert(b"".to_vec(), b"2".to_vec());
+        let serialized = StorageValue::Hash(hash.clone()).serialize();
+        let mut reader = DumpReader::new(&serialized);
+
+        match StorageValue::unserialize(&mut reader) {
+            Ok(StorageValue::Hash(unserialized)) => assert_eq!(unserialized, hash),
+            _ => panic!("Couldn't unserialize the hash"),
+        }
+    }
+
+    #[test]
+    fn unserialize_a_truncated_value_fails() {
+        let mut reader = DumpReader::new(b"Set 2 1:a 5:b");
+
+        assert!(StorageValue::unserialize(&mut reader).is_err());
+    }
+
+    #[test]
+    fn unserialize_a_length_past_the_end_fails() {
+        let dump = format!("String {}:a", usize::MAX);
+        let mut reader = DumpReader::new(dump.as_bytes());
+
+        assert!(StorageValue::unserialize(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::databasehelper::{random_number, StorageValue};
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+/// Bytes taken by a key besides its name and its value: the entry of the dictionary
+/// and the statistics kept for it.
+#[doc(hidden)]
+const KEY_OVERHEAD: usize = 64;
+/// Bytes taken by each element of a list, a set or a hash besides its contents.
+#[doc(hidden)]
+const ELEMENT_OVERHEAD: usize = 24;
+/// Frequency counter of a new key, so it isn't evicted before having a chance to be
+/// accessed again.
+pub const LFU_INIT_VAL: u8 = 5;
+#[doc(hidden)]
+const LFU_LOG_FACTOR: f64 = 10.0;
+#[doc(hidden)]
+const LFU_DECAY_TIME: Duration = Duration::from_secs(60);
+
+/// How Database chooses the keys to evict once maxmemory is reached, named like the
+/// maxmemory-policy option of Redis.
+///
+/// With NoEviction nothing is evicted and writes fail instead. The allkeys policies
+/// choose among every key and the volatile ones only among the keys with a time to
+/// live: the least recently used (LRU), the least frequently used (LFU) or a random
+/// one. VolatileTtl evicts the key that expires the soonest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EvictionPolicy {
+    #[doc(hidden)]
+    NoEviction,
+    #[doc(hidden)]
+    AllkeysLru,
+    #[doc(hidden)]
+    VolatileLru,
+    #[doc(hidden)]
+    AllkeysLfu,
+    #[doc(hidden)]
+    VolatileLfu,
+    #[doc(hidden)]
+    AllkeysRandom,
+    #[doc(hidden)]
+    VolatileRandom,
+    #[doc(hidden)]
+    VolatileTtl,
+}
+
+impl EvictionPolicy {
+    /// Parses the value of the maxmemory-policy option.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// assert_eq!(EvictionPolicy::parse("allkeys-lru"), Some(EvictionPolicy::AllkeysLru));
+    /// assert_eq!(EvictionPolicy::parse("lru"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<EvictionPolicy> {
+        match value {
+            "noeviction" => Some(EvictionPolicy::NoEviction),
+            "allkeys-lru" => Some(EvictionPolicy::AllkeysLru),
+            "volatile-lru" => Some(EvictionPolicy::VolatileLru),
+            "allkeys-lfu" => Some(EvictionPolicy::AllkeysLfu),
+            "volatile-lfu" => Some(EvictionPolicy::VolatileLfu),
+            "allkeys-random" => Some(EvictionPolicy::AllkeysRandom),
+            "volatile-random" => Some(EvictionPolicy::VolatileRandom),
+            "volatile-ttl" => Some(EvictionPolicy::VolatileTtl),
+            _ => None,
+        }
+    }
+
+    /// Returns true if only keys with a time to live can be evicted.
+    pub fn is_volatile(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileRandom
+                | EvictionPolicy::VolatileTtl
+        )
+    }
+}
+
+impl Display for EvictionPolicy {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            EvictionPolicy::NoEviction => "noeviction",
+            EvictionPolicy::AllkeysLru => "allkeys-lru",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+            EvictionPolicy::AllkeysLfu => "allkeys-lfu",
+            EvictionPolicy::VolatileLfu => "volatile-lfu",
+            EvictionPolicy::AllkeysRandom => "allkeys-random",
+            EvictionPolicy::VolatileRandom => "volatile-random",
+            EvictionPolicy::VolatileTtl => "volatile-ttl",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns an approximation of the bytes key and value take in memory: the bytes of
+/// their contents plus a fixed overhead for the key and for each element of value.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// let small = approximate_size(b"key", &StorageValue::String(b"a".to_vec()));
+/// let big = approximate_size(b"key", &StorageValue::String(vec![0; 1000]));
+///
+/// assert_eq!(big - small, 999);
+/// ```
+pub fn approximate_size(key: &[u8], value: &StorageValue) -> usize {
+    let value_size = match value {
+        StorageValue::String(string) => string.len(),
+        StorageValue::List(list) => list.iter().map(|e| e.len() + ELEMENT_OVERHEAD).sum(),
+        StorageValue::Set(set) => set.iter().map(|e| e.len() + ELEMENT_OVERHEAD).sum(),
+        StorageValue::Hash(hash) => hash
+            .iter()
+            .map(|(field, value)| field.len() + value.len() + ELEMENT_OVERHEAD)
+            .sum(),
+        StorageValue::Zset(sorted_set) => sorted_set.memory_usage(),
+    };
+
+    KEY_OVERHEAD + key.len() + value_size
+}
+
+/// Returns the frequency counter after an access: it grows logarithmically, so the
+/// more a key was accessed the less likely is it to grow, up to 255.
+pub fn lfu_increment(counter: u8) -> u8 {
+    if counter == u8::MAX {
+        return counter;
+    }
+
+    let base = counter.saturating_sub(LFU_INIT_VAL) as f64;
+    let probability = 1.0 / (base * LFU_LOG_FACTOR + 1.0);
+    let random = (random_number() >> 11) as f64 / (1u64 << 53) as f64;
+    if random < probability {
+        counter + 1
+    } else {
+        counter
+    }
+}
+
+/// Returns the frequency counter of a key that wasn't accessed for idle: it decreases
+/// by one every LFU_DECAY_TIME, so keys that were used a lot in the past can be
+/// evicted once they aren't used anymore.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// assert_eq!(lfu_decay(10, Duration::from_secs(150)), 8);
+/// assert_eq!(lfu_decay(1, Duration::from_secs(600)), 0);
+/// ```
+pub fn lfu_decay(counter: u8, idle: Duration) -> u8 {
+    let periods = idle.as_secs() / LFU_DECAY_TIME.as_secs();
+    counter.saturating_sub(periods.min(u8::MAX as u64) as u8)
+}
+
+#[cfg(test)]
+mod eviction_test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn parse_reads_every_policy_it_displays() {
+        for policy in [
+            EvictionPolicy::NoEviction,
+            EvictionPolicy::AllkeysLru,
+            EvictionPolicy::VolatileLru,
+            EvictionPolicy::AllkeysLfu,
+            EvictionPolicy::VolatileLfu,
+            EvictionPolicy::AllkeysRandom,
+            EvictionPolicy::VolatileRandom,
+            EvictionPolicy::VolatileTtl,
+        ] {
+            assert_eq!(EvictionPolicy::parse(&policy.to_string()), Some(policy));
+        }
+        assert_eq!(EvictionPolicy::parse("allkeys"), None);
+    }
+
+    #[test]
+    fn approximate_size_grows_with_the_elements() {
+        let one: HashSet<Vec<u8>> = vec![b"a".to_vec()].into_iter().collect();
+        let two: HashSet<Vec<u8>> = vec![b"a".to_vec(), b"b".to_vec()].into_iter().collect();
+
+        let one = approximate_size(b"key", &StorageValue::Set(one));
+        let two = approximate_size(b"key", &StorageValue::Set(two));
+        assert_eq!(two - one, 1 + ELEMENT_OVERHEAD);
+    }
+
+    #[test]
+    fn lfu_counter_grows_slower_the_bigger_it_is() {
+        let mut counter = LFU_INIT_VAL;
+        for _ in 0..1000 {
+            counter = lfu_increment(counter);
+        }
+
+        assert!(counter > LFU_INIT_VAL + 5);
+        assert!(counter < LFU_INIT_VAL + 50);
+    }
+
+    #[test]
+    fn lfu_counter_decays_once_per_period() {
+        assert_eq!(lfu_decay(10, Duration::from_secs(59)), 10);
+        assert_eq!(lfu_decay(10, Duration::from_secs(150)), 8);
+        assert_eq!(lfu_decay(3, Duration::from_secs(3600)), 0);
+    }
+}
@@ -1,60 +1,231 @@
-use crate::databasehelper::StorageValue;
+use crate::databasehelper::{random_number, random_positions, StorageValue};
+use crate::eviction::{approximate_size, lfu_decay, lfu_increment, LFU_INIT_VAL};
 use std::{
+    collections::hash_map::DefaultHasher,
     collections::HashMap,
-    sync::{Arc, Mutex},
-    time::SystemTime,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
 };
 
 #[doc(hidden)]
-type Dictionary = Arc<Mutex<HashMap<String, (StorageValue, SystemTime)>>>;
+type Dictionary = Arc<RwLock<Piece>>;
+/// Number of pieces a HashShard is divided in if the configuration doesn't say.
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// What a HashShard records about each key besides its value: when it was last
+/// accessed and how often, to choose the keys to evict, and the memory it takes.
+///
+/// Accesses are recorded atomically, so readers holding a shared lock of the piece
+/// the key is in can record them.
+#[derive(Debug)]
+pub struct KeyStats {
+    #[doc(hidden)]
+    last_access: AtomicU64,
+    #[doc(hidden)]
+    frequency: AtomicU8,
+    #[doc(hidden)]
+    size: usize,
+    /// Where the key is in the list of keys of its piece.
+    #[doc(hidden)]
+    position: usize,
+}
+
 #[doc(hidden)]
-const HASH_NUMBER: usize = 10;
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+impl KeyStats {
+    #[doc(hidden)]
+    fn new(size: usize) -> KeyStats {
+        KeyStats {
+            last_access: AtomicU64::new(now_ms()),
+            frequency: AtomicU8::new(LFU_INIT_VAL),
+            size,
+            position: 0,
+        }
+    }
+
+    /// Records an access to the key.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut hash_shard = HashShard::new(16);
+    /// hash_shard.insert(b"key".to_vec(), StorageValue::String(b"value".to_vec()));
+    ///
+    /// let atomic_hash = hash_shard.get_atomic_hash(b"key");
+    /// let atomic_hash = atomic_hash.read().unwrap();
+    /// let (_, stats) = atomic_hash.get(&b"key"[..]).unwrap();
+    /// stats.hit();
+    ///
+    /// assert_eq!(stats.idle_time().as_secs(), 0);
+    /// ```
+    pub fn hit(&self) {
+        let frequency = lfu_increment(self.frequency());
+        self.frequency.store(frequency, Ordering::Relaxed);
+        self.last_access.store(now_ms(), Ordering::Relaxed);
+    }
+
+    /// Returns the time passed since the last access to the key.
+    pub fn idle_time(&self) -> Duration {
+        let last_access = self.last_access.load(Ordering::Relaxed);
+        Duration::from_millis(now_ms().saturating_sub(last_access))
+    }
+
+    /// Returns the logarithmic counter of accesses to the key, which decays while the
+    /// key isn't accessed.
+    pub fn frequency(&self) -> u8 {
+        lfu_decay(self.frequency.load(Ordering::Relaxed), self.idle_time())
+    }
+}
+
+impl Clone for KeyStats {
+    fn clone(&self) -> Self {
+        KeyStats {
+            last_access: AtomicU64::new(self.last_access.load(Ordering::Relaxed)),
+            frequency: AtomicU8::new(self.frequency.load(Ordering::Relaxed)),
+            size: self.size,
+            position: self.position,
+        }
+    }
+}
+
+/// One of the pieces a HashShard is divided in: the keys that go to it, with their
+/// values and statistics, and besides a list of those keys, so one is taken at
+/// random in constant time.
+///
+/// It reads like the HashMap of its keys, but keys are only inserted or removed
+/// through the HashShard, which keeps both in step.
+#[derive(Default)]
+pub struct Piece {
+    #[doc(hidden)]
+    entries: HashMap<Vec<u8>, (StorageValue, KeyStats)>,
+    #[doc(hidden)]
+    keys: Vec<Vec<u8>>,
+}
+
+impl Piece {
+    /// Returns a mutable reference to the value and statistics of key.
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut (StorageValue, KeyStats)> {
+        self.entries.get_mut(key)
+    }
+
+    #[doc(hidden)]
+    fn insert(
+        &mut self,
+        key: Vec<u8>,
+        value: StorageValue,
+        mut stats: KeyStats,
+    ) -> Option<(StorageValue, KeyStats)> {
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                stats.position = entry.1.position;
+                Some(std::mem::replace(entry, (value, stats)))
+            }
+            None => {
+                stats.position = self.keys.len();
+                self.keys.push(key.clone());
+                self.entries.insert(key, (value, stats));
+                None
+            }
+        }
+    }
 
-/// A HashShard implemented with the simplest hash function in a multithreading
-/// context.
+    #[doc(hidden)]
+    fn remove(&mut self, key: &[u8]) -> Option<(StorageValue, KeyStats)> {
+        let (value, stats) = self.entries.remove(key)?;
+        self.keys.swap_remove(stats.position);
+        if let Some(moved) = self.keys.get(stats.position) {
+            if let Some((_, moved_stats)) = self.entries.get_mut(moved) {
+                moved_stats.position = stats.position;
+            }
+        }
+        Some((value, stats))
+    }
+
+    #[doc(hidden)]
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.keys.clear();
+    }
+
+    #[doc(hidden)]
+    fn random_key(&self) -> Option<&Vec<u8>> {
+        random_positions(self.keys.len(), 1)
+            .first()
+            .map(|&position| &self.keys[position])
+    }
+}
+
+impl Deref for Piece {
+    type Target = HashMap<Vec<u8>, (StorageValue, KeyStats)>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+/// A HashShard in a multithreading context, that spreads the keys among its pieces
+/// with the hash of each key.
 ///
-/// HashShard uses Arc and Mutex to be shared safety in a multithreading context
-/// implementing clone.
+/// HashShard uses Arc and RwLock to be shared safety in a multithreading context
+/// implementing clone: every piece has its own lock, so commands on keys of different
+/// pieces don't wait for each other, and commands that only read share it.
 /// It is the one in charge of dividing all the data in shorter pieces.
 ///
 pub struct HashShard {
     #[doc(hidden)]
-    pub data: Arc<Mutex<Vec<Dictionary>>>,
+    pub data: Arc<Vec<Dictionary>>,
+    #[doc(hidden)]
+    used_memory: Arc<AtomicUsize>,
 }
 
 impl HashShard {
     #[doc(hidden)]
-    pub fn new_from_hs(data: Arc<Mutex<Vec<Dictionary>>>) -> HashShard {
-        HashShard { data }
+    pub fn new_from_hs(data: Arc<Vec<Dictionary>>, used_memory: Arc<AtomicUsize>) -> HashShard {
+        HashShard { data, used_memory }
     }
 
-    /// Creates a new HashShard full with HashMaps.
+    /// Creates a new HashShard divided in the given number of HashMaps, at least one.
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let hash_shard = HashShard::new();
+    /// let hash_shard = HashShard::new(64);
+    ///
+    /// assert_eq!(hash_shard.shard_lens().len(), 64);
     /// ```
-    pub fn new() -> HashShard {
-        let mut data = Vec::with_capacity(HASH_NUMBER);
-        for _ in 0..HASH_NUMBER {
-            data.push(Arc::new(Mutex::new(HashMap::new())));
-        }
+    pub fn new(shards: usize) -> HashShard {
+        let data = (0..shards.max(1))
+            .map(|_| Arc::new(RwLock::new(Piece::default())))
+            .collect();
         HashShard {
-            data: Arc::new(Mutex::new(data)),
+            data: Arc::new(data),
+            used_memory: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Returns the number of keys in each piece of the hash shard.
+    pub fn shard_lens(&self) -> Vec<usize> {
+        self.data.iter().map(|h| h.read().unwrap().len()).collect()
+    }
+
     /// Updates the last access for the corresponding key and returns time passed since
     /// that point, if there's no key returns None.
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
+    /// let mut hash_shard = HashShard::new(16);
     ///
     /// let last_access = SystemTime::now();
     /// hash_shard.insert(
-    ///     "key1".to_string(),
-    ///     StorageValue::String("value1".to_string()),
+    ///     b"key1".to_vec(),
+    ///     StorageValue::String(b"value1".to_vec()),
     /// );
     ///
     /// sleep(std::time::Duration::from_secs(2));
@@ -62,47 +233,42 @@ impl HashShard {
     ///     .duration_since(last_access)
     ///     .expect("Clock may have gone backwards")
     ///     .as_secs();
-    /// let r = hash_shard.touch("key1").unwrap();
+    /// let r = hash_shard.touch(b"key1").unwrap();
     ///
     /// assert_eq!(r, time_passed);
     /// ```
-    pub fn touch(&mut self, key: &str) -> Option<u64> {
+    pub fn touch(&mut self, key: &[u8]) -> Option<u64> {
         let atomic_hash = self.get_atomic_hash(key);
-        let mut atomic_hash = atomic_hash.lock().unwrap();
-        match atomic_hash.get_mut(key) {
-            Some((_, l)) => {
-                let uptime_in_seconds = SystemTime::now()
-                    .duration_since(*l)
-                    .expect("Clock may have gone backwards");
-
-                *l = SystemTime::now();
-                Some(uptime_in_seconds.as_secs())
+        let atomic_hash = atomic_hash.read().unwrap();
+        match atomic_hash.get(key) {
+            Some((_, stats)) => {
+                let idle_time = stats.idle_time();
+                stats.hit();
+                Some(idle_time.as_secs())
             }
             None => None,
         }
     }
 
     /// Gets a piece from the data wich possibly contains the corresponding key protected
-    /// by an Arc and Mutex.
+    /// by an Arc and RwLock.
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
+    /// let mut hash_shard = HashShard::new(16);
     ///
     /// hash_shard.insert(
-    ///     "key1".to_string(),
-    ///     StorageValue::String("value1".to_string()),
+    ///     b"key1".to_vec(),
+    ///     StorageValue::String(b"value1".to_vec()),
     /// );
     ///
-    /// let atomic_hash = hash_shard.get_atomic_hash("key1");
-    /// let atomic_hash = atomic_hash.lock().unwrap();
+    /// let atomic_hash = hash_shard.get_atomic_hash(b"key1");
+    /// let atomic_hash = atomic_hash.read().unwrap();
     ///
-    /// assert!(atomic_hash.contains_key("key1"));
+    /// assert!(atomic_hash.contains_key(&b"key1"[..]));
     /// ```
-    pub fn get_atomic_hash(&self, key: &str) -> Dictionary {
-        let mut d = self.data.lock().unwrap();
-        let atomic_hash = d.get_mut(hash_funcion(key)).unwrap();
-        atomic_hash.clone()
+    pub fn get_atomic_hash(&self, key: &[u8]) -> Dictionary {
+        self.data[hash_funcion(key, self.data.len())].clone()
     }
 
     /// Inserts a key-value pair into the hash shard.
@@ -114,34 +280,70 @@ impl HashShard {
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
+    /// let mut hash_shard = HashShard::new(16);
     ///
     /// assert!(hash_shard
-    ///     .insert("key1".to_string(), StorageValue::String("value1".to_string()))
+    ///     .insert(b"key1".to_vec(), StorageValue::String(b"value1".to_vec()))
     ///     .is_none());
-    /// assert!(hash_shard.contains_key("key_1"));
+    /// assert!(hash_shard.contains_key(b"key1"));
     ///
     /// if let Some(StorageValue::String(old_value)) =
-    ///     hash_shard.insert("key_1".to_string(), StorageValue::String("value2".to_string()))
+    ///     hash_shard.insert(b"key1".to_vec(), StorageValue::String(b"value2".to_vec()))
     /// {
-    ///     assert_eq!(old_value, "value1");
+    ///     assert_eq!(old_value, b"value1");
     /// }
     /// ```
-    pub fn insert(&mut self, key: String, value: StorageValue) -> Option<StorageValue> {
+    pub fn insert(&mut self, key: Vec<u8>, value: StorageValue) -> Option<StorageValue> {
+        let size = approximate_size(&key, &value);
+        let atomic_hash = self.get_atomic_hash(&key);
+        let mut atomic_hash = atomic_hash.write().unwrap();
+        self.used_memory.fetch_add(size, Ordering::Relaxed);
+        let r = atomic_hash.insert(key, value, KeyStats::new(size));
+        r.map(|(value, stats)| {
+            self.used_memory.fetch_sub(stats.size, Ordering::Relaxed);
+            value
+        })
+    }
+
+    /// Inserts value at key if decide, called with the value key has, says so. It's
+    /// called and the value inserted holding the lock of the piece of key, so no other
+    /// write to key gets in between. Returns what decide returns besides whether to
+    /// insert.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut hash_shard = HashShard::new(16);
+    ///
+    /// let value = StorageValue::String(b"value1".to_vec());
+    /// assert!(hash_shard.insert_if(b"key1".to_vec(), value, |old| (old.is_none(), old.is_none())));
+    /// let value = StorageValue::String(b"value2".to_vec());
+    /// assert!(!hash_shard.insert_if(b"key1".to_vec(), value, |old| (old.is_none(), old.is_none())));
+    /// ```
+    pub fn insert_if<T, F>(&mut self, key: Vec<u8>, value: StorageValue, decide: F) -> T
+    where
+        F: FnOnce(Option<&StorageValue>) -> (bool, T),
+    {
         let atomic_hash = self.get_atomic_hash(&key);
-        let mut atomic_hash = atomic_hash.lock().unwrap();
-        let r = atomic_hash.insert(key, (value, SystemTime::now()));
-        r.map(|(value, _)| value)
+        let mut atomic_hash = atomic_hash.write().unwrap();
+        let (insert, result) = decide(atomic_hash.get(&key).map(|(value, _)| value));
+        if insert {
+            let size = approximate_size(&key, &value);
+            self.used_memory.fetch_add(size, Ordering::Relaxed);
+            if let Some((_, stats)) = atomic_hash.insert(key, value, KeyStats::new(size)) {
+                self.used_memory.fetch_sub(stats.size, Ordering::Relaxed);
+            }
+        }
+        result
     }
 
     /// Clears the hash shard, removing all key-value pairs.
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
+    /// let mut hash_shard = HashShard::new(16);
     ///
     /// for i in 0..100 {
-    ///     hash_shard.insert(i.to_string(), StorageValue::String(i.to_string()));
+    ///     hash_shard.insert(i.to_string().into_bytes(), StorageValue::String(i.to_string().into_bytes()));
     /// }
     /// assert_eq!(hash_shard.len(), 100);
     ///
@@ -149,33 +351,26 @@ impl HashShard {
     /// assert_eq!(hash_shard.len(), 0);
     /// ```
     pub fn clear(&mut self) {
-        let data = self.data.lock().unwrap();
-        data.iter().for_each(|h| {
-            let mut h = h.lock().unwrap();
+        self.data.iter().for_each(|h| {
+            let mut h = h.write().unwrap();
             h.clear();
         });
+        self.used_memory.store(0, Ordering::Relaxed);
     }
 
     /// Returns the number of elements in the hash shard.
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
+    /// let mut hash_shard = HashShard::new(16);
     ///
     /// for i in 0..100 {
-    ///     hash_shard.insert(i.to_string(), StorageValue::String(i.to_string()));
+    ///     hash_shard.insert(i.to_string().into_bytes(), StorageValue::String(i.to_string().into_bytes()));
     /// }
     /// assert_eq!(hash_shard.len(), 100);
     /// ```
     pub fn len(&self) -> usize {
-        let data = self.data.lock().unwrap();
-        let mut len = 0;
-        data.iter().for_each(|h| {
-            let h = h.lock().unwrap();
-            len += h.len();
-        });
-
-        len
+        self.shard_lens().iter().sum()
     }
 
     /// Returns true if the hash shard contains a value for the specified key, false
@@ -183,15 +378,15 @@ impl HashShard {
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
-    /// assert_eq!(hash_shard.contains_key(KEY_2), false);
+    /// let mut hash_shard = HashShard::new(16);
+    /// assert!(!hash_shard.contains_key(KEY_2));
     ///
-    /// hash_shard.insert(KEY_2.to_string(), StorageValue::String(VALUE_2.to_string()));
-    /// assert_eq!(hash_shard.contains_key(KEY_2), true);
+    /// hash_shard.insert(KEY_2.to_vec(), StorageValue::String(VALUE_2.to_vec()));
+    /// assert!(hash_shard.contains_key(KEY_2));
     /// ```
-    pub fn contains_key(&self, key: &str) -> bool {
+    pub fn contains_key(&self, key: &[u8]) -> bool {
         let atomic_hash = self.get_atomic_hash(key);
-        let guard = atomic_hash.lock().unwrap();
+        let guard = atomic_hash.read().unwrap();
         guard.contains_key(key)
     }
 
@@ -200,11 +395,11 @@ impl HashShard {
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
+    /// let mut hash_shard = HashShard::new(16);
     /// assert!(!hash_shard.contains_key(KEY_2));
     /// assert!(hash_shard.remove(KEY_2).is_none());
     ///
-    /// hash_shard.insert(KEY_2.to_string(), StorageValue::String(VALUE_2.to_string()));
+    /// hash_shard.insert(KEY_2.to_vec(), StorageValue::String(VALUE_2.to_vec()));
     /// assert!(hash_shard.contains_key(KEY_2));
     ///
     /// if let Some(StorageValue::String(value_removed)) = hash_shard.remove(KEY_2) {
@@ -212,41 +407,143 @@ impl HashShard {
     /// }
     /// assert!(!hash_shard.contains_key(KEY_2));
     /// ```
-    pub fn remove(&mut self, key: &str) -> Option<StorageValue> {
+    pub fn remove(&mut self, key: &[u8]) -> Option<StorageValue> {
         let atomic_hash = self.get_atomic_hash(key);
-        let mut guard = atomic_hash.lock().unwrap();
+        let mut guard = atomic_hash.write().unwrap();
         let r = guard.remove(key);
-        r.map(|(v, _)| v)
+        r.map(|(v, stats)| {
+            self.used_memory.fetch_sub(stats.size, Ordering::Relaxed);
+            v
+        })
+    }
+
+    /// Returns the approximate bytes taken by the keys and values of the hash shard.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut hash_shard = HashShard::new(16);
+    /// assert_eq!(hash_shard.used_memory(), 0);
+    ///
+    /// hash_shard.insert(KEY_1.to_vec(), StorageValue::String(VALUE_1.to_vec()));
+    /// assert!(hash_shard.used_memory() > 0);
+    /// ```
+    pub fn used_memory(&self) -> usize {
+        self.used_memory.load(Ordering::Relaxed)
+    }
+
+    /// Computes again the memory taken by key, after its value was changed in place.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut hash_shard = HashShard::new(16);
+    /// hash_shard.insert(KEY_1.to_vec(), StorageValue::List(vec![]));
+    /// let used_memory = hash_shard.used_memory();
+    ///
+    /// if let Some((StorageValue::List(list), _)) = hash_shard.get_atomic_hash(KEY_1).write().unwrap().get_mut(KEY_1) {
+    ///     list.push(VALUE_1.to_vec());
+    /// }
+    /// hash_shard.refresh_size(KEY_1);
+    ///
+    /// assert!(hash_shard.used_memory() > used_memory);
+    /// ```
+    pub fn refresh_size(&self, key: &[u8]) {
+        let atomic_hash = self.get_atomic_hash(key);
+        let mut guard = atomic_hash.write().unwrap();
+        if let Some((value, stats)) = guard.get_mut(key) {
+            let size = approximate_size(key, value);
+            self.used_memory.fetch_add(size, Ordering::Relaxed);
+            self.used_memory.fetch_sub(stats.size, Ordering::Relaxed);
+            stats.size = size;
+        }
+    }
+
+    /// Returns the statistics of key, if it is in the hash shard.
+    pub fn stats(&self, key: &[u8]) -> Option<KeyStats> {
+        let atomic_hash = self.get_atomic_hash(key);
+        let guard = atomic_hash.read().unwrap();
+        guard.get(key).map(|(_, stats)| stats.clone())
+    }
+
+    /// Returns up to count different keys, with their statistics, each one taken on
+    /// its own from a random position of a random piece of the hash shard.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut hash_shard = HashShard::new(16);
+    /// assert!(hash_shard.sample(5).is_empty());
+    ///
+    /// hash_shard.insert(KEY_1.to_vec(), StorageValue::String(VALUE_1.to_vec()));
+    /// assert_eq!(hash_shard.sample(5).len(), 1);
+    /// ```
+    pub fn sample(&self, count: usize) -> Vec<(Vec<u8>, KeyStats)> {
+        if self.len() <= count {
+            return self
+                .data
+                .iter()
+                .flat_map(|hash| {
+                    let hash = hash.read().unwrap();
+                    hash.iter()
+                        .map(|(key, (_, stats))| (key.clone(), stats.clone()))
+                        .collect::<Vec<_>>()
+                })
+                .take(count)
+                .collect();
+        }
+
+        let mut sample: Vec<(Vec<u8>, KeyStats)> = Vec::new();
+        for _ in 0..count.saturating_mul(4) {
+            if sample.len() == count {
+                break;
+            }
+            if let Some((key, stats)) = self.random_key() {
+                if sample.iter().all(|(taken, _)| *taken != key) {
+                    sample.push((key, stats));
+                }
+            }
+        }
+
+        sample
+    }
+
+    /// Returns a key taken at random from the first piece that has keys, starting from
+    /// a random one, with its statistics.
+    #[doc(hidden)]
+    fn random_key(&self) -> Option<(Vec<u8>, KeyStats)> {
+        let start = random_number() as usize;
+        (0..self.data.len()).find_map(|i| {
+            let hash = self.data[(start + i) % self.data.len()].read().unwrap();
+            let key = hash.random_key()?;
+            hash.get(key).map(|(_, stats)| (key.clone(), stats.clone()))
+        })
     }
 
     /// Obtains a list of tuples key-value from all the elements of the hash shard.
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
+    /// let mut hash_shard = HashShard::new(16);
     ///
     /// for i in 0..10 {
-    ///     hash_shard.insert(i.to_string(), StorageValue::String(i.to_string()));
+    ///     hash_shard.insert(i.to_string().into_bytes(), StorageValue::String(i.to_string().into_bytes()));
     /// }
     ///
     /// let key_values = hash_shard.key_value();
     /// for (key, value) in key_values {
-    ///     println!("{}: {:?}", key, value);
+    ///     println!("{:?}: {:?}", key, value);
     /// }
     /// ```
-    pub fn key_value(&self) -> Vec<(String, StorageValue)> {
-        let data = self.data.lock().unwrap();
-        let mut result: Vec<(String, StorageValue)> = Vec::new();
-        for hash in data.iter() {
-            let hash = hash.lock().unwrap();
+    pub fn key_value(&self) -> Vec<(Vec<u8>, StorageValue)> {
+        let mut result: Vec<(Vec<u8>, StorageValue)> = Vec::new();
+        for hash in self.data.iter() {
+            let hash = hash.read().unwrap();
             let mut vec = hash
                 .iter()
                 .map(|(k, v)| {
-                    let k = k.to_string();
+                    let k = k.to_vec();
                     let v = v.clone();
                     (k, v.0)
                 })
-                .collect::<Vec<(String, StorageValue)>>();
+                .collect::<Vec<(Vec<u8>, StorageValue)>>();
             result.append(&mut vec);
         }
 
@@ -257,27 +554,22 @@ impl HashShard {
     /// # Examples
     /// Basic Usage:
     /// ```
-    /// let mut hash_shard = HashShard::new();
+    /// let mut hash_shard = HashShard::new(16);
     ///
     /// for i in 0..5 {
-    ///     hash_shard.insert(i.to_string(), StorageValue::String(i.to_string()));
+    ///     hash_shard.insert(i.to_string().into_bytes(), StorageValue::String(i.to_string().into_bytes()));
     /// }
     ///
     /// let mut keys = hash_shard.keys();
-    /// keys.sort_by(|a, b| {
-    ///     let a = a.parse::<i32>().unwrap();
-    ///     let b = b.parse::<i32>().unwrap();
-    ///     a.cmp(&b)
-    /// });
+    /// keys.sort();
     ///
-    /// assert_eq!(keys, vec!["0", "1", "2", "3", "4"]);
+    /// assert_eq!(keys, vec![b"0", b"1", b"2", b"3", b"4"]);
     /// ```
-    pub fn keys(&self) -> Vec<String> {
-        let data = self.data.lock().unwrap();
-        let mut result: Vec<String> = Vec::new();
-        for hash in data.iter() {
-            let hash = hash.lock().unwrap();
-            let mut vec = hash.keys().map(|k| k.to_string()).collect::<Vec<String>>();
+    pub fn keys(&self) -> Vec<Vec<u8>> {
+        let mut result: Vec<Vec<u8>> = Vec::new();
+        for hash in self.data.iter() {
+            let hash = hash.read().unwrap();
+            let mut vec = hash.keys().map(|k| k.to_vec()).collect::<Vec<Vec<u8>>>();
             result.append(&mut vec);
         }
 
@@ -285,14 +577,18 @@ impl HashShard {
     }
 }
 
+/// Returns the piece, among shards of them, key goes to. The hasher has fixed keys,
+/// so a key always goes to the same piece.
 #[doc(hidden)]
-fn hash_funcion(key: &str) -> usize {
-    key.len() % HASH_NUMBER
+pub fn hash_funcion(key: &[u8], shards: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
 }
 
-impl<'a> Clone for HashShard {
+impl Clone for HashShard {
     fn clone(&self) -> Self {
-        HashShard::new_from_hs(self.data.clone())
+        HashShard::new_from_hs(self.data.clone(), self.used_memory.clone())
     }
 }
 
@@ -302,18 +598,18 @@ mod hash_shard_test {
 
     use super::*;
 
-    const KEY_1: &str = "key1";
-    const KEY_2: &str = "key2";
+    const KEY_1: &[u8] = b"key1";
+    const KEY_2: &[u8] = b"key2";
 
-    const VALUE_1: &str = "value1";
-    const VALUE_2: &str = "value2";
+    const VALUE_1: &[u8] = b"value1";
+    const VALUE_2: &[u8] = b"value2";
 
     #[test]
     fn touch_updates_last_access_properly() {
-        let mut hash_shard = HashShard::new();
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
 
         let last_access = SystemTime::now();
-        hash_shard.insert(KEY_1.to_string(), StorageValue::String(VALUE_1.to_string()));
+        hash_shard.insert(KEY_1.to_vec(), StorageValue::String(VALUE_1.to_vec()));
 
         sleep(std::time::Duration::from_secs(2));
         let time_passed = SystemTime::now()
@@ -327,48 +623,65 @@ mod hash_shard_test {
 
     #[test]
     fn get_atomic_hash_works_properly() {
-        let mut hash_shard = HashShard::new();
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
 
-        hash_shard.insert(KEY_1.to_string(), StorageValue::String(VALUE_1.to_string()));
+        hash_shard.insert(KEY_1.to_vec(), StorageValue::String(VALUE_1.to_vec()));
 
         let atomic_hash = hash_shard.get_atomic_hash(KEY_1);
-        let atomic_hash = atomic_hash.lock().unwrap();
+        let atomic_hash = atomic_hash.read().unwrap();
 
         assert!(atomic_hash.contains_key(KEY_1));
     }
 
     #[test]
     fn inserts_works_properly() {
-        let mut hash_shard = HashShard::new();
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
 
         assert!(hash_shard
-            .insert(KEY_1.to_string(), StorageValue::String(VALUE_1.to_string()))
+            .insert(KEY_1.to_vec(), StorageValue::String(VALUE_1.to_vec()))
             .is_none());
         assert!(hash_shard.contains_key(KEY_1));
 
         if let Some(StorageValue::String(old_value)) =
-            hash_shard.insert(KEY_1.to_string(), StorageValue::String(VALUE_2.to_string()))
+            hash_shard.insert(KEY_1.to_vec(), StorageValue::String(VALUE_2.to_vec()))
         {
             assert_eq!(old_value, VALUE_1);
         }
     }
 
+    #[test]
+    fn insert_binary_key_works_properly() {
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
+        let key = [0, 159, 146, 150];
+
+        hash_shard.insert(key.to_vec(), StorageValue::String(VALUE_1.to_vec()));
+
+        assert!(hash_shard.contains_key(&key));
+        assert!(!hash_shard.contains_key(&key[..3]));
+    }
+
     #[test]
     fn insert_100_elements_then_len_obtains_100() {
-        let mut hash_shard = HashShard::new();
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
 
         for i in 0..100 {
-            hash_shard.insert(i.to_string(), StorageValue::String(i.to_string()));
+            hash_shard.insert(
+                i.to_string().into_bytes(),
+                StorageValue::String(i.to_string().into_bytes()),
+            );
         }
         assert_eq!(hash_shard.len(), 100);
     }
 
     #[test]
     fn clear_then_there_are_no_keys() {
-        let mut hash_shard = HashShard::new();
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
 
         for i in 0..100 {
-            hash_shard.insert(i.to_string(), StorageValue::String(i.to_string()));
+            hash_shard.insert(
+                i.to_string().into_bytes(),
+                StorageValue::String(i.to_string().into_bytes()),
+            );
         }
         assert_eq!(hash_shard.len(), 100);
 
@@ -378,21 +691,21 @@ mod hash_shard_test {
 
     #[test]
     fn contains_key_gets_flase_then_adding_gets_true() {
-        let mut hash_shard = HashShard::new();
-        assert_eq!(hash_shard.contains_key(KEY_2), false);
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
+        assert!(!hash_shard.contains_key(KEY_2));
 
-        hash_shard.insert(KEY_2.to_string(), StorageValue::String(VALUE_2.to_string()));
-        assert_eq!(hash_shard.contains_key(KEY_2), true);
-        assert_eq!(hash_shard.contains_key(KEY_1), false);
+        hash_shard.insert(KEY_2.to_vec(), StorageValue::String(VALUE_2.to_vec()));
+        assert!(hash_shard.contains_key(KEY_2));
+        assert!(!hash_shard.contains_key(KEY_1));
     }
 
     #[test]
     fn remove_inexistent_key_gets_none_then_adding_it_gets_its_old_value_and_remove_the_key() {
-        let mut hash_shard = HashShard::new();
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
         assert!(!hash_shard.contains_key(KEY_2));
         assert!(hash_shard.remove(KEY_2).is_none());
 
-        hash_shard.insert(KEY_2.to_string(), StorageValue::String(VALUE_2.to_string()));
+        hash_shard.insert(KEY_2.to_vec(), StorageValue::String(VALUE_2.to_vec()));
         assert!(hash_shard.contains_key(KEY_2));
 
         if let Some(StorageValue::String(value_removed)) = hash_shard.remove(KEY_2) {
@@ -401,21 +714,148 @@ mod hash_shard_test {
         assert!(!hash_shard.contains_key(KEY_2));
     }
 
+    #[test]
+    fn used_memory_follows_inserts_and_removes() {
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
+
+        hash_shard.insert(KEY_1.to_vec(), StorageValue::String(VALUE_1.to_vec()));
+        let one_key = hash_shard.used_memory();
+        hash_shard.insert(KEY_2.to_vec(), StorageValue::String(VALUE_2.to_vec()));
+        assert_eq!(hash_shard.used_memory(), 2 * one_key);
+
+        hash_shard.insert(KEY_2.to_vec(), StorageValue::String(vec![0; 100]));
+        assert_eq!(hash_shard.used_memory(), 2 * one_key + 100 - VALUE_2.len());
+
+        hash_shard.remove(KEY_2);
+        assert_eq!(hash_shard.used_memory(), one_key);
+        hash_shard.clear();
+        assert_eq!(hash_shard.used_memory(), 0);
+    }
+
+    #[test]
+    fn refresh_size_accounts_changes_made_in_place() {
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
+        hash_shard.insert(KEY_1.to_vec(), StorageValue::String(VALUE_1.to_vec()));
+        let used_memory = hash_shard.used_memory();
+
+        let atomic_hash = hash_shard.get_atomic_hash(KEY_1);
+        if let Some((StorageValue::String(value), _)) = atomic_hash.write().unwrap().get_mut(KEY_1)
+        {
+            value.extend_from_slice(VALUE_2);
+        }
+        hash_shard.refresh_size(KEY_1);
+
+        assert_eq!(hash_shard.used_memory(), used_memory + VALUE_2.len());
+    }
+
+    #[test]
+    fn sample_takes_keys_of_the_hash_shard() {
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
+        assert!(hash_shard.sample(5).is_empty());
+
+        for i in 0..100 {
+            hash_shard.insert(
+                i.to_string().into_bytes(),
+                StorageValue::String(i.to_string().into_bytes()),
+            );
+        }
+
+        let sample = hash_shard.sample(5);
+        assert_eq!(sample.len(), 5);
+        assert!(sample.iter().all(|(key, _)| hash_shard.contains_key(key)));
+    }
+
+    #[test]
+    fn sample_takes_keys_from_several_shards() {
+        let mut hash_shard = HashShard::new(64);
+        hash_shard.insert(KEY_1.to_vec(), StorageValue::String(VALUE_1.to_vec()));
+        hash_shard.insert(KEY_2.to_vec(), StorageValue::String(VALUE_2.to_vec()));
+
+        assert_eq!(hash_shard.sample(5).len(), 2);
+    }
+
+    #[test]
+    fn sample_takes_different_keys_from_anywhere() {
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
+        for i in 0..1000 {
+            hash_shard.insert(
+                i.to_string().into_bytes(),
+                StorageValue::String(i.to_string().into_bytes()),
+            );
+        }
+
+        let mut taken = std::collections::HashSet::new();
+        for _ in 0..200 {
+            let sample = hash_shard.sample(5);
+            let keys: std::collections::HashSet<_> =
+                sample.into_iter().map(|(key, _)| key).collect();
+            assert_eq!(keys.len(), 5);
+            taken.extend(keys);
+        }
+        assert!(taken.len() > 500);
+    }
+
+    #[test]
+    fn sample_still_finds_keys_after_removing_others() {
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
+        for i in 0..100 {
+            hash_shard.insert(
+                i.to_string().into_bytes(),
+                StorageValue::String(i.to_string().into_bytes()),
+            );
+        }
+        for i in 0..90 {
+            hash_shard.remove(i.to_string().as_bytes());
+        }
+
+        let sample = hash_shard.sample(20);
+        assert_eq!(sample.len(), 10);
+        for _ in 0..50 {
+            assert!(hash_shard
+                .sample(3)
+                .iter()
+                .all(|(key, _)| hash_shard.contains_key(key)));
+        }
+    }
+
+    #[test]
+    fn keys_of_the_same_length_are_spread_among_the_shards() {
+        let mut hash_shard = HashShard::new(8);
+
+        for i in 0..800 {
+            hash_shard.insert(
+                format!("user:{:04}", i).into_bytes(),
+                StorageValue::String(VALUE_1.to_vec()),
+            );
+        }
+
+        let lens = hash_shard.shard_lens();
+        assert_eq!(lens.len(), 8);
+        assert_eq!(lens.iter().sum::<usize>(), 800);
+        assert!(lens.iter().all(|len| *len > 50 && *len < 150));
+    }
+
+    #[test]
+    fn a_key_always_goes_to_the_same_shard() {
+        assert_eq!(hash_funcion(KEY_1, 16), hash_funcion(KEY_1, 16));
+        assert!(hash_funcion(KEY_1, 16) < 16);
+        assert_eq!(HashShard::new(0).shard_lens().len(), 1);
+    }
+
     #[test]
     fn get_keys_works_properly() {
-        let mut hash_shard = HashShard::new();
+        let mut hash_shard = HashShard::new(DEFAULT_SHARDS);
 
         for i in 0..5 {
-            hash_shard.insert(i.to_string(), StorageValue::String(i.to_string()));
+            hash_shard.insert(
+                i.to_string().into_bytes(),
+                StorageValue::String(i.to_string().into_bytes()),
+            );
         }
 
         let mut keys = hash_shard.keys();
-        keys.sort_by(|a, b| {
-            let a = a.parse::<i32>().unwrap();
-            let b = b.parse::<i32>().unwrap();
-            a.cmp(&b)
-        });
+        keys.sort();
 
-        assert_eq!(keys, vec!["0", "1", "2", "3", "4"]);
+        assert_eq!(keys, vec![b"0", b"1", b"2", b"3", b"4"]);
     }
 }
@@ -1,10 +1,15 @@
 use std::fs;
 use std::io::prelude::*;
+use std::io::BufReader;
 use std::net::TcpListener;
 use std::net::TcpStream;
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::{env, thread};
 
+const CONSOLE_USER: &str = "console";
+/// The commands the console sends to the server, which are also the ones the console
+/// user is allowed. Anything else, like AUTH, HELLO, RESET, QUIT or CLIENT, would
+/// change the connection every visitor of the console shares.
 const COMMANDS_ALOWED: [&str; 40] = [
     // server (monitor, config, info not alowed)
     "flushdb",
@@ -57,6 +62,14 @@ const COMMANDS_ALOWED: [&str; 40] = [
 fn main() {
     let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
     let redis_stream = TcpStream::connect("127.0.0.1:8888").unwrap();
+    let logged_in = match login(&redis_stream) {
+        Ok(()) => true,
+        Err(error) => {
+            eprintln!("{}", error);
+            false
+        }
+    };
+    let logged_in = Arc::new(Mutex::new(logged_in));
 
     let records: Arc<Mutex<Vec<(String, String)>>> = Arc::new(Mutex::new(Vec::new()));
 
@@ -66,18 +79,51 @@ fn main() {
             Ok(stream) => {
                 let new_records = records.clone();
                 let redis_stream = redis_stream.try_clone().unwrap();
+                let logged_in = logged_in.clone();
                 thread::spawn(move || {
-                    handle_connection(stream, new_records, redis_stream);
+                    handle_connection(stream, new_records, redis_stream, logged_in);
                 });
             }
         }
     }
 }
 
+/// Authenticates the connection to the server as the console user, named by
+/// CONSOLE_USER or console by default, with the password in CONSOLE_PASSWORD. If it
+/// can't, returns an error that says how to create the user.
+fn login(redis_stream: &TcpStream) -> Result<(), String> {
+    let user = env::var("CONSOLE_USER").unwrap_or_else(|_| CONSOLE_USER.to_string());
+    let password = env::var("CONSOLE_PASSWORD").unwrap_or_default();
+
+    let reply = send_command(redis_stream, &format!("auth {} {}", user, password));
+    if reply.starts_with("(error)") {
+        return Err(format!(
+            "Couldn't log in as {}: {}. Create the user in the server with: ACL SETUSER {} on >password ~* resetchannels -@all +{}",
+            user,
+            reply,
+            user,
+            COMMANDS_ALOWED.join(" +")
+        ));
+    }
+    Ok(())
+}
+
+/// Logs in as the console user if it didn't yet, so the user can be created while
+/// the console is running.
+fn ensure_login(redis_stream: &TcpStream, logged_in: &Mutex<bool>) -> Result<(), String> {
+    let mut logged_in = logged_in.lock().unwrap();
+    if !*logged_in {
+        login(redis_stream)?;
+        *logged_in = true;
+    }
+    Ok(())
+}
+
 fn handle_connection(
     mut stream: TcpStream,
     records: Arc<Mutex<Vec<(String, String)>>>,
     redis_stream: TcpStream,
+    logged_in: Arc<Mutex<bool>>,
 ) {
     let mut buffer = [0; 2048];
 
@@ -90,7 +136,10 @@ fn handle_connection(
 
     let (status_line, contents) = if buffer.starts_with(b"POST / HTTP/1.1\r\n") {
         let command = get_command(request);
-        let response = handle_redis_connection(&redis_stream, &command);
+        let response = match ensure_login(&redis_stream, &logged_in) {
+            Ok(()) => handle_redis_connection(&redis_stream, &command),
+            Err(error) => error,
+        };
 
         let mut records_guard = records.lock().unwrap();
 
@@ -121,19 +170,24 @@ fn get_command(request: &str) -> String {
     body[3..].replace('+', " ")
 }
 
-fn handle_redis_connection(mut stream: &TcpStream, command: &str) -> String {
-    let c: Vec<&str> = command.split_whitespace().collect();
-    if let Some(command_name) = c.get(0) {
-        if !COMMANDS_ALOWED.contains(command_name) {
-            return build_non_existent_command_response(command);
-        }
-    } else {
+fn handle_redis_connection(stream: &TcpStream, command: &str) -> String {
+    // A line break would send the rest of the line as another command, unchecked.
+    if command.contains(['\r', '\n']) {
         return build_non_existent_command_response(command);
     }
+    match command.split_whitespace().next() {
+        Some(name) if COMMANDS_ALOWED.contains(&name.to_lowercase().as_str()) => {
+            send_command(stream, command)
+        }
+        _ => build_non_existent_command_response(command),
+    }
+}
 
+fn send_command(mut stream: &TcpStream, command: &str) -> String {
     stream.write_all(command.as_bytes()).unwrap();
+    stream.write_all(b"\r\n").unwrap();
     stream.flush().unwrap();
-    secure_read(&stream)
+    secure_read(stream)
 }
 
 fn build_non_existent_command_response(command: &str) -> String {
@@ -141,15 +195,46 @@ fn build_non_existent_command_response(command: &str) -> String {
 }
 
 fn secure_read(mut stream: &TcpStream) -> String {
-    let mut buffer_respond = [0; 1024];
-    let mut response = String::new();
-    while !response.ends_with('\n') {
-        let bytes_read = stream.read(&mut buffer_respond).unwrap();
-        let r = std::str::from_utf8(&buffer_respond[..bytes_read]).unwrap();
-        response.push_str(r);
+    let mut reader = BufReader::new(&mut stream);
+    read_reply(&mut reader)
+}
+
+fn read_line(reader: &mut BufReader<&mut &TcpStream>) -> String {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    line.trim_end_matches("\r\n").to_string()
+}
+
+fn read_reply(reader: &mut BufReader<&mut &TcpStream>) -> String {
+    let line = read_line(reader);
+    if line.is_empty() {
+        return line;
     }
 
-    response
+    let (kind, data) = line.split_at(1);
+    match kind {
+        "+" => data.to_string(),
+        "-" => format!("(error) {}", data),
+        ":" => format!("(integer) {}", data),
+        "$" => {
+            let len = data.parse::<i64>().unwrap_or(-1);
+            if len < 0 {
+                return "(nil)".to_string();
+            }
+            let mut bulk = vec![0; len as usize + 2];
+            reader.read_exact(&mut bulk).unwrap();
+            String::from_utf8_lossy(&bulk[..len as usize]).to_string()
+        }
+        "*" => {
+            let len = data.parse::<i64>().unwrap_or(-1);
+            if len <= 0 {
+                return "(empty list or set)".to_string();
+            }
+            let elements: Vec<String> = (0..len).map(|_| read_reply(reader)).collect();
+            elements.join(" ")
+        }
+        _ => line,
+    }
 }
 
 fn build_answer(records: &[(String, String)]) -> String {
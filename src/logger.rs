@@ -88,6 +88,7 @@ fn open_logger(path: &str) -> Result<File, String> {
         .read(true)
         .write(true)
         .create(true)
+        .truncate(false)
         .open(path)
     {
         Err(why) => Err(format!("Couldn't open file: {}", why)),
@@ -1,13 +1,23 @@
+mod acl;
+mod aof;
 mod channels;
 mod client;
+mod connection;
 mod database;
 mod databasehelper;
+mod eviction;
 mod hash_shard;
 mod logger;
 mod matcher;
+mod notify;
+mod rdb;
 mod request;
+mod resp;
 mod server;
 mod server_conf;
+mod sorted_set;
+mod tls;
+mod worker_pool;
 
 use server::Server;
 use std::env;
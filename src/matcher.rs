@@ -1,11 +1,233 @@
-use regex::Regex;
-
-pub fn matcher(text: &str, pattern: &str) -> bool {
-    let patt: String = "^".to_owned() + pattern + "$";
-    let patt: String = patt.replace("*", ".*");
-    let patt: String = patt.replace("?", ".");
-    match Regex::new(&patt) {
-        Ok(re) => re.is_match(text),
-        Err(_) => false,
+/// A glob-style pattern, read once so it can be matched against many byte strings.
+///
+/// `*` matches any number of bytes, `?` any single byte, `[abc]` any of the bytes
+/// between the brackets, `[^abc]` any byte but those, `[a-z]` any byte in the range,
+/// and `\` makes the byte after it match only itself. Every byte counts the same, so
+/// `*` and `?` match a newline or a non UTF-8 byte too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glob {
+    #[doc(hidden)]
+    tokens: Vec<Token>,
+}
+
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Any,
+    One,
+    Byte(u8),
+    Class {
+        negated: bool,
+        ranges: Vec<(u8, u8)>,
+    },
+}
+
+impl Token {
+    #[doc(hidden)]
+    fn matches(&self, byte: u8) -> bool {
+        match self {
+            Token::Any | Token::One => true,
+            Token::Byte(expected) => *expected == byte,
+            Token::Class { negated, ranges } => {
+                ranges
+                    .iter()
+                    .any(|(start, end)| (*start..=*end).contains(&byte))
+                    != *negated
+            }
+        }
+    }
+}
+
+impl Glob {
+    /// Reads pattern. A `[` without its `]` takes the rest of the pattern as the
+    /// bytes of the class, and a `\` at the end matches itself.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let glob = Glob::new(b"user:[0-9]*");
+    ///
+    /// assert!(glob.matches(b"user:1\n"));
+    /// assert!(!glob.matches(b"user:x"));
+    /// ```
+    pub fn new(pattern: &[u8]) -> Glob {
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < pattern.len() {
+            match pattern[i] {
+                b'*' => {
+                    if tokens.last() != Some(&Token::Any) {
+                        tokens.push(Token::Any);
+                    }
+                }
+                b'?' => tokens.push(Token::One),
+                b'\\' if i + 1 < pattern.len() => {
+                    i += 1;
+                    tokens.push(Token::Byte(pattern[i]));
+                }
+                b'[' => {
+                    let (token, end) = read_class(pattern, i + 1);
+                    tokens.push(token);
+                    i = end;
+                }
+                byte => tokens.push(Token::Byte(byte)),
+            }
+            i += 1;
+        }
+
+        Glob { tokens }
+    }
+
+    /// Returns true if the whole of text matches the pattern.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let glob = Glob::new(b"h?llo");
+    ///
+    /// assert!(glob.matches(b"hello"));
+    /// assert!(glob.matches(b"h\nllo"));
+    /// assert!(!glob.matches(b"hllo"));
+    /// ```
+    pub fn matches(&self, text: &[u8]) -> bool {
+        let (mut t, mut p) = (0, 0);
+        // Where to go on from if what follows the last `*` stops matching: the token
+        // after the `*` and the byte of text the `*` would take next.
+        let mut backtrack = None;
+        while t < text.len() {
+            match self.tokens.get(p) {
+                Some(Token::Any) => {
+                    backtrack = Some((p + 1, t));
+                    p += 1;
+                    continue;
+                }
+                Some(token) if token.matches(text[t]) => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            match backtrack {
+                Some((after_any, taken)) => {
+                    p = after_any;
+                    t = taken + 1;
+                    backtrack = Some((after_any, taken + 1));
+                }
+                None => return false,
+            }
+        }
+
+        self.tokens[p..].iter().all(|token| *token == Token::Any)
+    }
+}
+
+/// Reads the class that starts at start, right after its `[`, returning it and the
+/// position of its `]`.
+#[doc(hidden)]
+fn read_class(pattern: &[u8], start: usize) -> (Token, usize) {
+    let mut i = start;
+    let negated = pattern.get(i) == Some(&b'^');
+    if negated {
+        i += 1;
+    }
+
+    let mut ranges = Vec::new();
+    while i < pattern.len() && pattern[i] != b']' {
+        if pattern[i] == b'\\' && i + 1 < pattern.len() {
+            i += 1;
+        }
+        let first = pattern[i];
+        if pattern.get(i + 1) == Some(&b'-') && i + 2 < pattern.len() && pattern[i + 2] != b']' {
+            let mut last = pattern[i + 2];
+            i += 2;
+            if last == b'\\' && i + 1 < pattern.len() {
+                i += 1;
+                last = pattern[i];
+            }
+            ranges.push((first.min(last), first.max(last)));
+        } else {
+            ranges.push((first, first));
+        }
+        i += 1;
+    }
+
+    (Token::Class { negated, ranges }, i)
+}
+
+/// Returns true if the whole of text matches the glob-style pattern, as described in
+/// Glob.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// assert!(matcher(b"a|b", b"a|*"));
+/// assert!(!matcher(b"secret", b"a|"));
+/// ```
+pub fn matcher(text: &[u8], pattern: &[u8]) -> bool {
+    Glob::new(pattern).matches(text)
+}
+
+#[cfg(test)]
+mod matcher_test {
+    use super::*;
+
+    #[test]
+    fn star_and_question_mark_match_any_byte() {
+        assert!(matcher(b"", b"*"));
+        assert!(matcher(b"a\nb", b"a*b"));
+        assert!(matcher(b"a\nb", b"a?b"));
+        assert!(matcher(b"\xff\x00", b"??"));
+        assert!(matcher(b"hello world", b"h*o*d"));
+        assert!(!matcher(b"hello", b"h?"));
+        assert!(!matcher(b"hello", b"*x*"));
+    }
+
+    #[test]
+    fn regex_metacharacters_only_match_themselves() {
+        assert!(matcher(b"a|", b"a|"));
+        assert!(!matcher(b"secret", b"a|"));
+        assert!(!matcher(b"a", b"a|"));
+        assert!(matcher(b"a.b", b"a.b"));
+        assert!(!matcher(b"axb", b"a.b"));
+        assert!(matcher(b"(x)+$", b"(x)+$"));
+        assert!(!matcher(b"xx", b"(x)+"));
+        assert!(matcher(b"{1}", b"{1}"));
+    }
+
+    #[test]
+    fn classes_match_their_bytes_and_ranges() {
+        assert!(matcher(b"hallo", b"h[ae]llo"));
+        assert!(!matcher(b"hillo", b"h[ae]llo"));
+        assert!(matcher(b"hillo", b"h[^ae]llo"));
+        assert!(!matcher(b"hallo", b"h[^ae]llo"));
+        assert!(matcher(b"user:7", b"user:[0-9]"));
+        assert!(matcher(b"user:7", b"user:[9-0]"));
+        assert!(!matcher(b"user:x", b"user:[0-9]"));
+        assert!(matcher(b"-", b"[a-]"));
+        assert!(matcher(b"]", b"[\\]]"));
+        assert!(matcher(b"\n", b"[^a]"));
+    }
+
+    #[test]
+    fn backslash_escapes_the_next_byte() {
+        assert!(matcher(b"a*", b"a\\*"));
+        assert!(!matcher(b"ab", b"a\\*"));
+        assert!(matcher(b"?", b"\\?"));
+        assert!(!matcher(b"x", b"\\?"));
+        assert!(matcher(b"[", b"\\["));
+        assert!(matcher(b"a\\", b"a\\"));
+    }
+
+    #[test]
+    fn an_unclosed_class_takes_the_rest_of_the_pattern() {
+        assert!(matcher(b"b", b"[abc"));
+        assert!(!matcher(b"[abc", b"[abc"));
+    }
+
+    #[test]
+    fn a_glob_matches_many_texts() {
+        let glob = Glob::new(b"news.*");
+
+        assert!(glob.matches(b"news.sport"));
+        assert!(glob.matches(b"news.\r\n"));
+        assert!(!glob.matches(b"newsxsport"));
     }
 }
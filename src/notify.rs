@@ -0,0 +1,239 @@
+use crate::channels::Channels;
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, RwLock};
+
+/// The classes of the keyspace events, each one with the letter that turns it on in
+/// the notify-keyspace-events option of Redis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventClass {
+    /// Commands that work with any type of key, like DEL, EXPIRE or RENAME.
+    Generic,
+    #[doc(hidden)]
+    String,
+    #[doc(hidden)]
+    List,
+    #[doc(hidden)]
+    Set,
+    #[doc(hidden)]
+    Hash,
+    #[doc(hidden)]
+    SortedSet,
+    /// Keys removed because their time to live is over.
+    Expired,
+    /// Keys removed to stay below maxmemory.
+    Evicted,
+}
+
+#[doc(hidden)]
+const CLASSES: [(EventClass, char); 8] = [
+    (EventClass::Generic, 'g'),
+    (EventClass::String, '$'),
+    (EventClass::List, 'l'),
+    (EventClass::Set, 's'),
+    (EventClass::Hash, 'h'),
+    (EventClass::SortedSet, 'z'),
+    (EventClass::Expired, 'x'),
+    (EventClass::Evicted, 'e'),
+];
+
+impl EventClass {
+    #[doc(hidden)]
+    fn bit(self) -> u16 {
+        1 << CLASSES
+            .iter()
+            .position(|(class, _)| *class == self)
+            .unwrap()
+    }
+}
+
+#[doc(hidden)]
+const KEYSPACE: u16 = 1 << 8;
+#[doc(hidden)]
+const KEYEVENT: u16 = 1 << 9;
+#[doc(hidden)]
+const ALL_CLASSES: u16 = (1 << 8) - 1;
+
+/// The keyspace events to publish, parsed from the notify-keyspace-events option.
+///
+/// K publishes them in `__keyspace@<db>__:<key>`, with the event as the message, and
+/// E in `__keyevent@<db>__:<event>`, with the key as the message. Without any of both,
+/// or without any class, nothing is published. A is an alias for every class.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct KeyspaceEvents {
+    #[doc(hidden)]
+    flags: u16,
+}
+
+impl KeyspaceEvents {
+    /// Parses the value of the notify-keyspace-events option, None if it has a letter
+    /// that isn't a class, K, E or A.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let events = KeyspaceEvents::parse("Ex").unwrap();
+    /// assert!(events.publishes(EventClass::Expired));
+    /// assert_eq!(KeyspaceEvents::parse("Ey"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<KeyspaceEvents> {
+        let mut flags = 0;
+        for letter in value.chars() {
+            flags |= match letter {
+                'K' => KEYSPACE,
+                'E' => KEYEVENT,
+                'A' => ALL_CLASSES,
+                _ => CLASSES.iter().find(|(_, l)| *l == letter)?.0.bit(),
+            };
+        }
+
+        Some(KeyspaceEvents { flags })
+    }
+
+    /// Returns true if the events of class are published in any of both channels.
+    pub fn publishes(&self, class: EventClass) -> bool {
+        self.flags & (KEYSPACE | KEYEVENT) != 0 && self.flags & class.bit() != 0
+    }
+}
+
+impl Display for KeyspaceEvents {
+    /// Writes the option back as Redis does, with A if every class is on.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.flags & ALL_CLASSES == ALL_CLASSES {
+            write!(f, "A")?;
+        } else {
+            for (class, letter) in CLASSES.iter() {
+                if self.flags & class.bit() != 0 {
+                    write!(f, "{}", letter)?;
+                }
+            }
+        }
+        if self.flags & KEYSPACE != 0 {
+            write!(f, "K")?;
+        }
+        if self.flags & KEYEVENT != 0 {
+            write!(f, "E")?;
+        }
+        Ok(())
+    }
+}
+
+/// Publishes the keyspace events of a Database in its channels, shared by every
+/// clone of the Database and the supervisors of the keys's time to live.
+///
+/// Until it has channels nothing is published, so loading the data doesn't publish
+/// anything.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    #[doc(hidden)]
+    inner: Arc<RwLock<(Option<Channels>, KeyspaceEvents)>>,
+}
+
+impl Notifier {
+    /// Sets the channels the events are published in.
+    pub fn set_channels(&self, channels: Channels) {
+        self.inner.write().unwrap().0 = Some(channels);
+    }
+
+    /// Sets which events are published.
+    pub fn set_events(&self, events: KeyspaceEvents) {
+        self.inner.write().unwrap().1 = events;
+    }
+
+    /// Publishes that event of class happened to key in the database db, if the
+    /// events of class are published.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let notifier = Notifier::default();
+    /// notifier.set_channels(Channels::new());
+    /// notifier.set_events(KeyspaceEvents::parse("KEA").unwrap());
+    ///
+    /// notifier.notify(EventClass::Generic, "del", b"key", 0);
+    /// ```
+    pub fn notify(&self, class: EventClass, event: &str, key: &[u8], db: usize) {
+        let inner = self.inner.read().unwrap();
+        let (channels, events) = match &*inner {
+            (Some(channels), events) if events.publishes(class) => (channels, events),
+            _ => return,
+        };
+
+        let mut channels = channels.clone();
+        let key = String::from_utf8_lossy(key);
+        if events.flags & KEYSPACE != 0 {
+            channels.publish(&format!("__keyspace@{}__:{}", db, key), event);
+        }
+        if events.flags & KEYEVENT != 0 {
+            channels.publish(&format!("__keyevent@{}__:{}", db, event), &key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod notify_test {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    fn notifier(events: &str) -> Notifier {
+        let notifier = Notifier::default();
+        notifier.set_channels(Channels::new());
+        notifier.set_events(KeyspaceEvents::parse(events).unwrap());
+        notifier
+    }
+
+    fn message(channel: &str, message: &str) -> String {
+        format!(
+            "*3\r\n$7\r\nmessage\r\n${}\r\n{}\r\n${}\r\n{}\r\n",
+            channel.len(),
+            channel,
+            message.len(),
+            message
+        )
+    }
+
+    #[test]
+    fn parse_the_letters_of_redis() {
+        let events = KeyspaceEvents::parse("Kx").unwrap();
+        assert!(events.publishes(EventClass::Expired));
+        assert!(!events.publishes(EventClass::Generic));
+
+        assert_eq!(KeyspaceEvents::parse("KEA").unwrap().to_string(), "AKE");
+        assert_eq!(KeyspaceEvents::parse("E$lg").unwrap().to_string(), "g$lE");
+        assert_eq!(KeyspaceEvents::parse("").unwrap().to_string(), "");
+        assert_eq!(KeyspaceEvents::parse("KEw"), None);
+    }
+
+    #[test]
+    fn without_keyspace_or_keyevent_nothing_is_published() {
+        let events = KeyspaceEvents::parse("A").unwrap();
+
+        assert!(!events.publishes(EventClass::Generic));
+    }
+
+    #[test]
+    fn notify_publishes_in_both_channels() {
+        let notifier = notifier("KEg");
+        let mut channels = notifier.inner.read().unwrap().0.clone().unwrap();
+        let (s, r) = channel();
+        channels.subscribe("__keyspace@0__:key", s.clone().into(), 1);
+        channels.subscribe("__keyevent@0__:del", s.into(), 1);
+
+        notifier.notify(EventClass::Generic, "del", b"key", 0);
+
+        assert_eq!(r.recv().unwrap(), message("__keyspace@0__:key", "del"));
+        assert_eq!(r.recv().unwrap(), message("__keyevent@0__:del", "key"));
+    }
+
+    #[test]
+    fn notify_skips_the_classes_that_are_off() {
+        let notifier = notifier("Ex");
+        let mut channels = notifier.inner.read().unwrap().0.clone().unwrap();
+        let (s, r) = channel();
+        channels.subscribe("__keyevent@3__:expired", s.clone().into(), 1);
+        channels.subscribe("__keyevent@3__:set", s.into(), 1);
+
+        notifier.notify(EventClass::String, "set", b"key", 3);
+        notifier.notify(EventClass::Expired, "expired", b"key", 3);
+
+        assert_eq!(r.recv().unwrap(), message("__keyevent@3__:expired", "key"));
+        assert!(r.try_recv().is_err());
+    }
+}
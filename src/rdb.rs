@@ -0,0 +1,926 @@
+use crate::databasehelper::StorageValue;
+use crate::sorted_set::SortedSet;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::str;
+
+/// The format Database writes its dump in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DumpFormat {
+    /// The text format of Database::dump.
+    Text,
+    /// The binary format of Redis, so dumps can be moved to and from it.
+    Rdb,
+}
+
+impl DumpFormat {
+    /// Parses the value of the dbformat option.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// assert_eq!(DumpFormat::parse("rdb"), Some(DumpFormat::Rdb));
+    /// assert_eq!(DumpFormat::parse("json"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<DumpFormat> {
+        match value.to_lowercase().as_str() {
+            "text" => Some(DumpFormat::Text),
+            "rdb" => Some(DumpFormat::Rdb),
+            _ => None,
+        }
+    }
+}
+
+#[doc(hidden)]
+const RDB_VERSION: u32 = 9;
+#[doc(hidden)]
+const MAX_RDB_VERSION: u32 = 12;
+/// A back reference of LZF takes 3 bytes and repeats at most 264.
+#[doc(hidden)]
+const LZF_MAX_EXPANSION: usize = 88;
+
+#[doc(hidden)]
+const OPCODE_SLOT_INFO: u8 = 0xF4;
+#[doc(hidden)]
+const OPCODE_FUNCTION2: u8 = 0xF5;
+#[doc(hidden)]
+const OPCODE_IDLE: u8 = 0xF8;
+#[doc(hidden)]
+const OPCODE_FREQ: u8 = 0xF9;
+#[doc(hidden)]
+const OPCODE_AUX: u8 = 0xFA;
+#[doc(hidden)]
+const OPCODE_RESIZEDB: u8 = 0xFB;
+#[doc(hidden)]
+const OPCODE_EXPIRETIME_MS: u8 = 0xFC;
+#[doc(hidden)]
+const OPCODE_EXPIRETIME: u8 = 0xFD;
+#[doc(hidden)]
+const OPCODE_SELECTDB: u8 = 0xFE;
+#[doc(hidden)]
+const OPCODE_EOF: u8 = 0xFF;
+
+#[doc(hidden)]
+const TYPE_STRING: u8 = 0;
+#[doc(hidden)]
+const TYPE_LIST: u8 = 1;
+#[doc(hidden)]
+const TYPE_SET: u8 = 2;
+#[doc(hidden)]
+const TYPE_ZSET: u8 = 3;
+#[doc(hidden)]
+const TYPE_HASH: u8 = 4;
+#[doc(hidden)]
+const TYPE_ZSET_2: u8 = 5;
+#[doc(hidden)]
+const TYPE_LIST_ZIPLIST: u8 = 10;
+#[doc(hidden)]
+const TYPE_SET_INTSET: u8 = 11;
+#[doc(hidden)]
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+#[doc(hidden)]
+const TYPE_HASH_ZIPLIST: u8 = 13;
+#[doc(hidden)]
+const TYPE_LIST_QUICKLIST: u8 = 14;
+#[doc(hidden)]
+const TYPE_HASH_LISTPACK: u8 = 16;
+#[doc(hidden)]
+const TYPE_ZSET_LISTPACK: u8 = 17;
+#[doc(hidden)]
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+#[doc(hidden)]
+const TYPE_SET_LISTPACK: u8 = 20;
+
+#[doc(hidden)]
+const QUICKLIST_NODE_PLAIN: u64 = 1;
+
+/// Computes the CRC-64 used by Redis to check its dumps, the Jones variant with
+/// reflected input and output.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// assert_eq!(crc64(b"123456789"), 0xe9c6d914c4b8d9ca);
+/// ```
+pub fn crc64(data: &[u8]) -> u64 {
+    let mut crc: u64 = 0;
+    for &byte in data {
+        crc ^= byte as u64;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x95ac_9329_ac4b_c9b5
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Writes keys in the RDB format of Redis.
+///
+/// Every value is written with its plain encoding, which any version of Redis since
+/// the one of the format version written in the header can load.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// let mut writer = RdbWriter::new();
+/// writer.select_db(0, 1, 0);
+/// writer.write_key(b"key", &StorageValue::String(b"value".to_vec()), None);
+///
+/// let rdb = writer.finish();
+/// assert!(rdb.starts_with(b"REDIS0009"));
+/// ```
+pub struct RdbWriter {
+    #[doc(hidden)]
+    buffer: Vec<u8>,
+}
+
+impl RdbWriter {
+    /// Creates a writer with the header of the dump already written.
+    pub fn new() -> RdbWriter {
+        RdbWriter {
+            buffer: format!("REDIS{:04}", RDB_VERSION).into_bytes(),
+        }
+    }
+
+    /// Starts the keys of the database index, which has keys keys and expires of them
+    /// with a time to live.
+    pub fn select_db(&mut self, index: usize, keys: usize, expires: usize) {
+        self.buffer.push(OPCODE_SELECTDB);
+        self.write_length(index as u64);
+        self.buffer.push(OPCODE_RESIZEDB);
+        self.write_length(keys as u64);
+        self.write_length(expires as u64);
+    }
+
+    /// Writes key with value and, if it has one, the Unix time in milliseconds it
+    /// expires at.
+    pub fn write_key(&mut self, key: &[u8], value: &StorageValue, expire_ms: Option<u64>) {
+        if let Some(expire_ms) = expire_ms {
+            self.buffer.push(OPCODE_EXPIRETIME_MS);
+            self.buffer.extend_from_slice(&expire_ms.to_le_bytes());
+        }
+
+        match value {
+            StorageValue::String(string) => {
+                self.buffer.push(TYPE_STRING);
+                self.write_string(key);
+                self.write_string(string);
+            }
+            StorageValue::List(list) => {
+                self.buffer.push(TYPE_LIST);
+                self.write_string(key);
+                self.write_length(list.len() as u64);
+                for element in list {
+                    self.write_string(element);
+                }
+            }
+            StorageValue::Set(set) => {
+                self.buffer.push(TYPE_SET);
+                self.write_string(key);
+                self.write_length(set.len() as u64);
+                for member in set {
+                    self.write_string(member);
+                }
+            }
+            StorageValue::Hash(hash) => {
+                self.buffer.push(TYPE_HASH);
+                self.write_string(key);
+                self.write_length(hash.len() as u64);
+                for (field, value) in hash {
+                    self.write_string(field);
+                    self.write_string(value);
+                }
+            }
+            StorageValue::Zset(sorted_set) => {
+                self.buffer.push(TYPE_ZSET_2);
+                self.write_string(key);
+                self.write_length(sorted_set.len() as u64);
+                for (member, score) in sorted_set.members() {
+                    self.write_string(&member);
+                    self.buffer.extend_from_slice(&score.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    /// Ends the dump and returns it, followed by its checksum.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buffer.push(OPCODE_EOF);
+        let checksum = crc64(&self.buffer);
+        self.buffer.extend_from_slice(&checksum.to_le_bytes());
+        self.buffer
+    }
+
+    #[doc(hidden)]
+    fn write_length(&mut self, length: u64) {
+        if length < 1 << 6 {
+            self.buffer.push(length as u8);
+        } else if length < 1 << 14 {
+            self.buffer.push(0x40 | (length >> 8) as u8);
+            self.buffer.push(length as u8);
+        } else if length <= u32::MAX as u64 {
+            self.buffer.push(0x80);
+            self.buffer
+                .extend_from_slice(&(length as u32).to_be_bytes());
+        } else {
+            self.buffer.push(0x81);
+            self.buffer.extend_from_slice(&length.to_be_bytes());
+        }
+    }
+
+    #[doc(hidden)]
+    fn write_string(&mut self, string: &[u8]) {
+        self.write_length(string.len() as u64);
+        self.buffer.extend_from_slice(string);
+    }
+}
+
+/// A key read from a RDB dump.
+#[derive(Debug)]
+pub struct RdbEntry {
+    /// The database the key belongs to.
+    pub db: usize,
+    #[doc(hidden)]
+    pub key: Vec<u8>,
+    #[doc(hidden)]
+    pub value: StorageValue,
+    /// The Unix time in milliseconds the key expires at, if it has a time to live.
+    pub expire_ms: Option<u64>,
+}
+
+/// Reads every key of a dump written in the RDB format by Redis or by RdbWriter.
+///
+/// Besides the plain encodings, it reads the compact ones Redis uses for small
+/// values (ziplists, listpacks, intsets and quicklists) and compressed or integer
+/// encoded strings. Auxiliary fields, functions and the eviction hints are skipped.
+///
+/// Error if the dump is truncated, its checksum doesn't match, or it has a value of
+/// a type this server doesn't support, like streams or modules.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// let mut writer = RdbWriter::new();
+/// writer.select_db(3, 1, 0);
+/// writer.write_key(b"key", &StorageValue::String(b"value".to_vec()), None);
+///
+/// let entries = load(&writer.finish()).unwrap();
+/// assert_eq!(entries[0].db, 3);
+/// assert_eq!(entries[0].key, b"key".to_vec());
+/// ```
+pub fn load(rdb: &[u8]) -> Result<Vec<RdbEntry>, String> {
+    let mut reader = RdbReader { data: rdb, pos: 0 };
+
+    if reader.take(5)? != b"REDIS" {
+        return Err("bad header".to_string());
+    }
+    let version = str::from_utf8(reader.take(4)?)
+        .ok()
+        .and_then(|version| version.parse::<u32>().ok())
+        .ok_or_else(|| "bad header".to_string())?;
+    if version == 0 || version > MAX_RDB_VERSION {
+        return Err(format!("unsupported version {}", version));
+    }
+
+    let mut entries = Vec::new();
+    let mut db = 0;
+    let mut expire_ms = None;
+
+    loop {
+        let opcode = reader.byte()?;
+        match opcode {
+            OPCODE_EOF => break,
+            OPCODE_SELECTDB => db = reader.length()? as usize,
+            OPCODE_RESIZEDB => {
+                reader.length()?;
+                reader.length()?;
+            }
+            OPCODE_AUX => {
+                reader.string()?;
+                reader.string()?;
+            }
+            OPCODE_EXPIRETIME_MS => expire_ms = Some(u64::from_le_bytes(reader.array()?)),
+            OPCODE_EXPIRETIME => {
+                let seconds = u32::from_le_bytes(reader.array()?);
+                expire_ms = Some(seconds as u64 * 1000);
+            }
+            OPCODE_FREQ => {
+                reader.byte()?;
+            }
+            OPCODE_IDLE => {
+                reader.length()?;
+            }
+            OPCODE_FUNCTION2 => {
+                reader.string()?;
+            }
+            OPCODE_SLOT_INFO => {
+                reader.length()?;
+                reader.length()?;
+                reader.length()?;
+            }
+            value_type => {
+                let position = reader.pos - 1;
+                let key = reader.string()?;
+                let value = reader
+                    .value(value_type)
+                    .map_err(|e| format!("{} at byte {}", e, position))?;
+                entries.push(RdbEntry {
+                    db,
+                    key,
+                    value,
+                    expire_ms: expire_ms.take(),
+                });
+            }
+        }
+    }
+
+    if version >= 5 {
+        let body = reader.pos;
+        let checksum = u64::from_le_bytes(
+            reader
+                .array()
+                .map_err(|_| "missing checksum, the dump may be truncated".to_string())?,
+        );
+        if checksum != 0 && checksum != crc64(&rdb[..body]) {
+            return Err("checksum mismatch".to_string());
+        }
+    }
+
+    Ok(entries)
+}
+
+#[doc(hidden)]
+struct RdbReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RdbReader<'a> {
+    #[doc(hidden)]
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        match self.data.get(self.pos..self.pos.saturating_add(len)) {
+            Some(bytes) => {
+                self.pos += len;
+                Ok(bytes)
+            }
+            None => Err("unexpected end of file, the dump may be truncated".to_string()),
+        }
+    }
+
+    #[doc(hidden)]
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    #[doc(hidden)]
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    /// Reads a length, returning it and whether it's instead the kind of a
+    /// specially encoded string.
+    #[doc(hidden)]
+    fn encoded_length(&mut self) -> Result<(u64, bool), String> {
+        let first = self.byte()?;
+        match first >> 6 {
+            0 => Ok(((first & 0x3f) as u64, false)),
+            1 => Ok(((((first & 0x3f) as u64) << 8) | self.byte()? as u64, false)),
+            2 if first == 0x80 => Ok((u32::from_be_bytes(self.array()?) as u64, false)),
+            2 if first == 0x81 => Ok((u64::from_be_bytes(self.array()?), false)),
+            2 => Err(format!("bad length encoding {}", first)),
+            _ => Ok(((first & 0x3f) as u64, true)),
+        }
+    }
+
+    #[doc(hidden)]
+    fn length(&mut self) -> Result<u64, String> {
+        match self.encoded_length()? {
+            (length, false) => Ok(length),
+            _ => Err("unexpected encoded string".to_string()),
+        }
+    }
+
+    #[doc(hidden)]
+    fn string(&mut self) -> Result<Vec<u8>, String> {
+        let (length, encoded) = self.encoded_length()?;
+        if !encoded {
+            return Ok(self.take(length as usize)?.to_vec());
+        }
+
+        match length {
+            0 => Ok((self.byte()? as i8).to_string().into_bytes()),
+            1 => Ok(i16::from_le_bytes(self.array()?).to_string().into_bytes()),
+            2 => Ok(i32::from_le_bytes(self.array()?).to_string().into_bytes()),
+            3 => {
+                let compressed_len = self.length()? as usize;
+                let len = self.length()? as usize;
+                lzf_decompress(self.take(compressed_len)?, len)
+            }
+            _ => Err(format!("unknown string encoding {}", length)),
+        }
+    }
+
+    #[doc(hidden)]
+    fn strings(&mut self) -> Result<Vec<Vec<u8>>, String> {
+        let len = self.length()?;
+        let mut strings = Vec::new();
+        for _ in 0..len {
+            strings.push(self.string()?);
+        }
+        Ok(strings)
+    }
+
+    #[doc(hidden)]
+    fn text_double(&mut self) -> Result<f64, String> {
+        match self.byte()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => parse_score(self.take(len as usize)?),
+        }
+    }
+
+    #[doc(hidden)]
+    fn value(&mut self, value_type: u8) -> Result<StorageValue, String> {
+        match value_type {
+            TYPE_STRING => Ok(StorageValue::String(self.string()?)),
+            TYPE_LIST => Ok(StorageValue::List(self.strings()?)),
+            TYPE_SET => Ok(StorageValue::Set(self.strings()?.into_iter().collect())),
+            TYPE_ZSET | TYPE_ZSET_2 => {
+                let len = self.length()?;
+                let mut sorted_set = SortedSet::new();
+                for _ in 0..len {
+                    let member = self.string()?;
+                    let score = if value_type == TYPE_ZSET {
+                        self.text_double()?
+                    } else {
+                        f64::from_le_bytes(self.array()?)
+                    };
+                    sorted_set.insert(&member, score);
+                }
+                Ok(StorageValue::Zset(sorted_set))
+            }
+            TYPE_HASH => {
+                let len = self.length()?;
+                let mut hash = HashMap::new();
+                for _ in 0..len {
+                    let field = self.string()?;
+                    hash.insert(field, self.string()?);
+                }
+                Ok(StorageValue::Hash(hash))
+            }
+            TYPE_LIST_ZIPLIST => Ok(StorageValue::List(ziplist_entries(&self.string()?)?)),
+            TYPE_SET_INTSET => Ok(StorageValue::Set(intset_entries(&self.string()?)?)),
+            TYPE_SET_LISTPACK => {
+                let members = listpack_entries(&self.string()?)?;
+                Ok(StorageValue::Set(members.into_iter().collect()))
+            }
+            TYPE_ZSET_ZIPLIST | TYPE_ZSET_LISTPACK => {
+                let blob = self.string()?;
+                let entries = if value_type == TYPE_ZSET_ZIPLIST {
+                    ziplist_entries(&blob)?
+                } else {
+                    listpack_entries(&blob)?
+                };
+                let mut sorted_set = SortedSet::new();
+                for pair in entries.chunks(2) {
+                    match pair {
+                        [member, score] => {
+                            sorted_set.insert(member, parse_score(score)?);
+                        }
+                        _ => return Err("odd number of sorted set entries".to_string()),
+                    }
+                }
+                Ok(StorageValue::Zset(sorted_set))
+            }
+            TYPE_HASH_ZIPLIST | TYPE_HASH_LISTPACK => {
+                let blob = self.string()?;
+                let entries = if value_type == TYPE_HASH_ZIPLIST {
+                    ziplist_entries(&blob)?
+                } else {
+                    listpack_entries(&blob)?
+                };
+                let mut hash = HashMap::new();
+                let mut entries = entries.into_iter();
+                while let Some(field) = entries.next() {
+                    match entries.next() {
+                        Some(value) => hash.insert(field, value),
+                        None => return Err("odd number of hash entries".to_string()),
+                    };
+                }
+                Ok(StorageValue::Hash(hash))
+            }
+            TYPE_LIST_QUICKLIST | TYPE_LIST_QUICKLIST_2 => {
+                let nodes = self.length()?;
+                let mut list = Vec::new();
+                for _ in 0..nodes {
+                    if value_type == TYPE_LIST_QUICKLIST {
+                        list.extend(ziplist_entries(&self.string()?)?);
+                    } else if self.length()? == QUICKLIST_NODE_PLAIN {
+                        list.push(self.string()?);
+                    } else {
+                        list.extend(listpack_entries(&self.string()?)?);
+                    }
+                }
+                Ok(StorageValue::List(list))
+            }
+            _ => Err(format!("unsupported value type {}", value_type)),
+        }
+    }
+}
+
+#[doc(hidden)]
+fn parse_score(score: &[u8]) -> Result<f64, String> {
+    let score = str::from_utf8(score).map_err(|_| "bad score".to_string())?;
+    match score.to_lowercase().as_str() {
+        "inf" | "+inf" => Ok(f64::INFINITY),
+        "-inf" => Ok(f64::NEG_INFINITY),
+        score => score.parse::<f64>().map_err(|_| "bad score".to_string()),
+    }
+}
+
+#[doc(hidden)]
+fn bytes_at(data: &[u8], pos: usize, len: usize) -> Result<&[u8], String> {
+    data.get(pos..pos.saturating_add(len))
+        .ok_or_else(|| "bad encoded value".to_string())
+}
+
+#[doc(hidden)]
+fn int_at(data: &[u8], pos: usize, len: usize) -> Result<i64, String> {
+    let bytes = bytes_at(data, pos, len)?;
+    let mut value: i64 = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        value |= (byte as i64) << (8 * i);
+    }
+    let unused = 64 - 8 * len as u32;
+    Ok(value.wrapping_shl(unused).wrapping_shr(unused))
+}
+
+/// Decompresses data, compressed with LZF into len bytes.
+///
+/// len comes from the file, so it's checked against the most LZF can expand data
+/// before anything is allocated.
+#[doc(hidden)]
+fn lzf_decompress(data: &[u8], len: usize) -> Result<Vec<u8>, String> {
+    if len > data.len().saturating_mul(LZF_MAX_EXPANSION) {
+        return Err("bad compressed string".to_string());
+    }
+    let mut output: Vec<u8> = Vec::with_capacity(len);
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let control = data[pos] as usize;
+        pos += 1;
+
+        if control < 32 {
+            output.extend_from_slice(bytes_at(data, pos, control + 1)?);
+            pos += control + 1;
+        } else {
+            let mut run = control >> 5;
+            if run == 7 {
+                run += *bytes_at(data, pos, 1)?.first().unwrap() as usize;
+                pos += 1;
+            }
+            let back = ((control & 0x1f) << 8) + bytes_at(data, pos, 1)?[0] as usize + 1;
+            pos += 1;
+            if back > output.len() {
+                return Err("bad compressed string".to_string());
+            }
+            let start = output.len() - back;
+            if output.len() + run + 2 > len {
+                return Err("bad compressed string".to_string());
+            }
+            for i in 0..run + 2 {
+                output.push(output[start + i]);
+            }
+        }
+    }
+
+    if output.len() != len {
+        return Err("bad compressed string".to_string());
+    }
+    Ok(output)
+}
+
+/// Returns the entries of a ziplist, the compact encoding of small values used up
+/// to Redis 6.
+#[doc(hidden)]
+fn ziplist_entries(ziplist: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut entries = Vec::new();
+    let mut pos = 10;
+
+    loop {
+        let prevlen = bytes_at(ziplist, pos, 1)?[0];
+        if prevlen == 0xff {
+            return Ok(entries);
+        }
+        pos += if prevlen < 254 { 1 } else { 5 };
+
+        let encoding = bytes_at(ziplist, pos, 1)?[0];
+        let (len, header) = match encoding >> 6 {
+            0 => ((encoding & 0x3f) as usize, 1),
+            1 => {
+                let next = bytes_at(ziplist, pos + 1, 1)?[0];
+                ((((encoding & 0x3f) as usize) << 8) | next as usize, 2)
+            }
+            2 => {
+                let len = bytes_at(ziplist, pos + 1, 4)?;
+                (u32::from_be_bytes(len.try_into().unwrap()) as usize, 5)
+            }
+            _ => {
+                let (value, size) = match encoding {
+                    0xc0 => (int_at(ziplist, pos + 1, 2)?, 2),
+                    0xd0 => (int_at(ziplist, pos + 1, 4)?, 4),
+                    0xe0 => (int_at(ziplist, pos + 1, 8)?, 8),
+                    0xf0 => (int_at(ziplist, pos + 1, 3)?, 3),
+                    0xfe => (int_at(ziplist, pos + 1, 1)?, 1),
+                    0xf1..=0xfd => ((encoding & 0x0f) as i64 - 1, 0),
+                    _ => return Err("bad ziplist entry".to_string()),
+                };
+                entries.push(value.to_string().into_bytes());
+                pos += 1 + size;
+                continue;
+            }
+        };
+
+        entries.push(bytes_at(ziplist, pos + header, len)?.to_vec());
+        pos += header + len;
+    }
+}
+
+/// Returns the entries of a listpack, the compact encoding of small values used
+/// since Redis 7.
+#[doc(hidden)]
+fn listpack_entries(listpack: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut entries = Vec::new();
+    let mut pos = 6;
+
+    loop {
+        let encoding = bytes_at(listpack, pos, 1)?[0];
+        if encoding == 0xff {
+            return Ok(entries);
+        }
+
+        let (entry, len) = if encoding & 0x80 == 0 {
+            ((encoding & 0x7f).to_string().into_bytes(), 1)
+        } else if encoding & 0xc0 == 0x80 {
+            let len = (encoding & 0x3f) as usize;
+            (bytes_at(listpack, pos + 1, len)?.to_vec(), 1 + len)
+        } else if encoding & 0xe0 == 0xc0 {
+            let next = bytes_at(listpack, pos + 1, 1)?[0];
+            let value = (((encoding & 0x1f) as i64) << 8) | next as i64;
+            let value = if value >= 1 << 12 {
+                value - (1 << 13)
+            } else {
+                value
+            };
+            (value.to_string().into_bytes(), 2)
+        } else if encoding & 0xf0 == 0xe0 {
+            let next = bytes_at(listpack, pos + 1, 1)?[0];
+            let len = (((encoding & 0x0f) as usize) << 8) | next as usize;
+            (bytes_at(listpack, pos + 2, len)?.to_vec(), 2 + len)
+        } else {
+            let size = match encoding {
+                0xf0 => {
+                    let len = int_at(listpack, pos + 1, 4)? as u32 as usize;
+                    let entry = bytes_at(listpack, pos + 5, len)?.to_vec();
+                    let len = 5 + len;
+                    pos += len + backlen_size(len);
+                    entries.push(entry);
+                    continue;
+                }
+                0xf1 => 2,
+                0xf2 => 3,
+                0xf3 => 4,
+                0xf4 => 8,
+                _ => return Err("bad listpack entry".to_string()),
+            };
+            let value = int_at(listpack, pos + 1, size)?;
+            (value.to_string().into_bytes(), 1 + size)
+        };
+
+        entries.push(entry);
+        pos += len + backlen_size(len);
+    }
+}
+
+/// Returns how many bytes take the length of a listpack entry of len bytes, written
+/// after it so the listpack can be read backwards.
+#[doc(hidden)]
+fn backlen_size(len: usize) -> usize {
+    match len {
+        0..=127 => 1,
+        128..=16382 => 2,
+        16383..=2097150 => 3,
+        2097151..=268435454 => 4,
+        _ => 5,
+    }
+}
+
+/// Returns the members of an intset, the encoding of small sets of integers.
+#[doc(hidden)]
+fn intset_entries(intset: &[u8]) -> Result<HashSet<Vec<u8>>, String> {
+    let size = int_at(intset, 0, 4)? as usize;
+    let len = int_at(intset, 4, 4)? as u32 as usize;
+    if size != 2 && size != 4 && size != 8 {
+        return Err("bad intset encoding".to_string());
+    }
+
+    let mut members = HashSet::new();
+    for i in 0..len {
+        let member = int_at(intset, 8 + i * size, size)?;
+        members.insert(member.to_string().into_bytes());
+    }
+    Ok(members)
+}
+
+#[cfg(test)]
+mod rdb_test {
+    use super::*;
+
+    fn single(value_type: u8, value: &[u8]) -> Vec<u8> {
+        let mut rdb = b"REDIS0011".to_vec();
+        rdb.push(OPCODE_AUX);
+        rdb.extend_from_slice(b"\x09redis-ver\x057.2.4");
+        rdb.extend_from_slice(&[OPCODE_SELECTDB, 0, value_type, 3]);
+        rdb.extend_from_slice(b"key");
+        rdb.extend_from_slice(value);
+        rdb.push(OPCODE_EOF);
+        let checksum = crc64(&rdb);
+        rdb.extend_from_slice(&checksum.to_le_bytes());
+        rdb
+    }
+
+    fn loaded(value_type: u8, value: &[u8]) -> StorageValue {
+        let mut entries = load(&single(value_type, value)).unwrap();
+        assert_eq!(entries.len(), 1);
+        entries.remove(0).value
+    }
+
+    fn members(value: &StorageValue) -> Vec<Vec<u8>> {
+        match value {
+            StorageValue::String(string) => vec![string.clone()],
+            StorageValue::List(list) => list.clone(),
+            StorageValue::Set(set) => {
+                let mut members: Vec<Vec<u8>> = set.iter().cloned().collect();
+                members.sort();
+                members
+            }
+            StorageValue::Hash(hash) => {
+                let mut pairs: Vec<(&Vec<u8>, &Vec<u8>)> = hash.iter().collect();
+                pairs.sort();
+                pairs
+                    .into_iter()
+                    .flat_map(|(field, value)| vec![field.clone(), value.clone()])
+                    .collect()
+            }
+            StorageValue::Zset(sorted_set) => sorted_set
+                .members()
+                .into_iter()
+                .flat_map(|(member, score)| vec![member, score.to_string().into_bytes()])
+                .collect(),
+        }
+    }
+
+    fn bytes(values: &[&str]) -> Vec<Vec<u8>> {
+        values
+            .iter()
+            .map(|value| value.as_bytes().to_vec())
+            .collect()
+    }
+
+    fn listpack(entries: &[&[u8]]) -> Vec<u8> {
+        let mut listpack = vec![0; 6];
+        for entry in entries {
+            listpack.extend_from_slice(entry);
+        }
+        listpack.push(0xff);
+        let mut blob = vec![listpack.len() as u8];
+        blob.extend(listpack);
+        blob
+    }
+
+    #[test]
+    fn crc64_matches_the_redis_check_value() {
+        assert_eq!(crc64(b"123456789"), 0xe9c6d914c4b8d9ca);
+    }
+
+    #[test]
+    fn written_values_are_loaded_back() {
+        let mut sorted_set = SortedSet::new();
+        sorted_set.insert(b"one", 1.5);
+        sorted_set.insert(b"two", f64::NEG_INFINITY);
+        let mut hash = HashMap::new();
+        hash.insert(b"f".to_vec(), b"v".to_vec());
+        let long = vec![b'x'; 20000];
+
+        let mut writer = RdbWriter::new();
+        writer.select_db(0, 2, 1);
+        writer.write_key(b"string", &StorageValue::String(long.clone()), Some(42));
+        writer.write_key(b"hash", &StorageValue::Hash(hash), None);
+        writer.select_db(5, 3, 0);
+        let list = vec![b"a".to_vec(), b"b".to_vec()];
+        writer.write_key(b"list", &StorageValue::List(list.clone()), None);
+        let set: HashSet<Vec<u8>> = list.iter().cloned().collect();
+        writer.write_key(b"set", &StorageValue::Set(set), None);
+        writer.write_key(b"zset", &StorageValue::Zset(sorted_set), None);
+
+        let entries = load(&writer.finish()).unwrap();
+
+        assert_eq!(entries.len(), 5);
+        assert_eq!((entries[0].db, entries[0].expire_ms), (0, Some(42)));
+        assert_eq!(members(&entries[0].value), vec![long]);
+        assert_eq!(members(&entries[1].value), bytes(&["f", "v"]));
+        assert_eq!((entries[2].db, entries[2].expire_ms), (5, None));
+        assert_eq!(members(&entries[2].value), list);
+        assert_eq!(members(&entries[3].value), bytes(&["a", "b"]));
+        assert_eq!(
+            members(&entries[4].value),
+            bytes(&["two", "-inf", "one", "1.5"])
+        );
+    }
+
+    #[test]
+    fn reads_integer_and_compressed_strings() {
+        let value = loaded(TYPE_STRING, &[0xc1, 0x39, 0x30]);
+        assert_eq!(members(&value), bytes(&["12345"]));
+
+        // "aaaaaaaaaa": a literal 'a' followed by a back reference of 9 bytes.
+        let value = loaded(TYPE_STRING, &[0xc3, 5, 10, 0x00, b'a', 0xe0, 0, 0]);
+        assert_eq!(members(&value), bytes(&["aaaaaaaaaa"]));
+    }
+
+    #[test]
+    fn reports_a_compressed_string_longer_than_it_can_be() {
+        let mut value = vec![0xc3, 5, 0x81];
+        value.extend_from_slice(&u64::MAX.to_be_bytes());
+        value.extend_from_slice(&[0x00, b'a', 0xe0, 0, 0]);
+
+        let error = load(&single(TYPE_STRING, &value)).unwrap_err();
+        assert!(error.contains("bad compressed string"));
+
+        let value = [0xc3, 5, 20, 0x00, b'a', 0xe0, 0, 0];
+        assert!(load(&single(TYPE_STRING, &value)).is_err());
+    }
+
+    #[test]
+    fn reads_a_listpack_hash() {
+        let value = loaded(
+            TYPE_HASH_LISTPACK,
+            &listpack(&[b"\x81f\x02", b"\x05\x01", b"\xc1\xf4\x02", b"\x82ab\x03"]),
+        );
+
+        assert_eq!(members(&value), bytes(&["500", "ab", "f", "5"]));
+    }
+
+    #[test]
+    fn reads_a_quicklist_2_list() {
+        let mut value = vec![2, 2];
+        value.extend(listpack(&[b"\x81a\x02", b"\x81b\x02"]));
+        value.extend_from_slice(b"\x01\x05plain");
+
+        let value = loaded(TYPE_LIST_QUICKLIST_2, &value);
+        assert_eq!(members(&value), bytes(&["a", "b", "plain"]));
+    }
+
+    #[test]
+    fn reads_a_ziplist_sorted_set_and_an_intset() {
+        let mut ziplist = vec![0; 10];
+        ziplist.extend_from_slice(&[0, 0x01, b'm', 3, 0xf3]);
+        ziplist.push(0xff);
+        let mut value = vec![ziplist.len() as u8];
+        value.extend(ziplist);
+        let value = loaded(TYPE_ZSET_ZIPLIST, &value);
+        assert_eq!(members(&value), bytes(&["m", "2"]));
+
+        let value = [12, 2, 0, 0, 0, 2, 0, 0, 0, 0xff, 0xff, 7, 0];
+        let value = loaded(TYPE_SET_INTSET, &value);
+        assert_eq!(members(&value), bytes(&["-1", "7"]));
+    }
+
+    #[test]
+    fn reports_a_changed_byte() {
+        let mut rdb = single(TYPE_STRING, b"\x05value");
+        let len = rdb.len();
+        rdb[len - 12] = b'V';
+
+        assert_eq!(load(&rdb).unwrap_err(), "checksum mismatch");
+    }
+
+    #[test]
+    fn reports_a_truncated_dump() {
+        let rdb = single(TYPE_STRING, b"\x05value");
+
+        assert!(load(&rdb[..rdb.len() - 12]).is_err());
+        assert_eq!(
+            load(&rdb[..rdb.len() - 4]).unwrap_err(),
+            "missing checksum, the dump may be truncated"
+        );
+    }
+
+    #[test]
+    fn reports_unsupported_types() {
+        let error = load(&single(21, b"\x00")).unwrap_err();
+
+        assert!(error.starts_with("unsupported value type 21"));
+    }
+}
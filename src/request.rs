@@ -1,14 +1,19 @@
-use crate::channels::Channels;
+use crate::acl::{self, CATEGORIES};
+use crate::channels::{Channels, MessageSender, MONITOR};
 use crate::database::Database;
-use crate::databasehelper::SortFlags;
-use crate::server_conf::{ServerConf, SuccessServerRequest};
+use crate::databasehelper::{
+    parse_bytes, Aggregate, ExpireFlags, KeyExpiry, SetFlags, SortFlags, ZaddFlags, ZrangeBy,
+};
+use crate::notify::EventClass;
+use crate::resp::{self, Command, RespValue};
+use crate::server_conf::{ServerConf, ServerError, SuccessServerRequest};
+use crate::sorted_set::{parse_score, LexBound, ScoreBound};
 use core::fmt::{self, Display, Formatter};
-use std::io::{Read, Write};
-use std::net::TcpStream;
-use std::sync::mpsc::channel;
+use std::borrow::Cow;
+use std::io::Write;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
-use std::{process, thread};
+use std::{process, str};
 
 const SUBSCRIPTION_MODE_ERROR: &str = "Subscription mode doesn't support other commands";
 pub enum Request<'a> {
@@ -16,46 +21,101 @@ pub enum Request<'a> {
     Server(ServerRequest<'a>),
     Suscriber(SuscriberRequest<'a>),
     Publisher(PublisherRequest<'a>),
-    Touch(&'a str),
+    Transaction(TransactionRequest<'a>),
+    Acl(AclRequest<'a>),
+    Touch(&'a [u8]),
+    Ping(Option<&'a [u8]>),
+    Auth(Option<&'a [u8]>, &'a [u8]),
+    Hello(Option<i64>, Option<(&'a [u8], &'a [u8])>),
+    Reset,
     CloseClient,
-    Invalid(&'a str, RequestError),
+    Invalid(&'a [Vec<u8>], RequestError),
 }
 
 impl<'a> Request<'a> {
-    pub fn new(request_str: &str, subscription_mode: bool) -> Request {
-        let request: Vec<&str> = request_str.split_whitespace().collect();
+    pub fn new(command: &'a [Vec<u8>], subscription_mode: bool) -> Request<'a> {
+        let request: Vec<&[u8]> = command.iter().map(|s| s.as_slice()).collect();
 
         let request = match request[..] {
-            ["expire", key, seconds] => match seconds.parse::<i64>() {
-                Ok(seconds) => Request::DataBase(Query::Expire(key, seconds)),
-                Err(_) => Request::Invalid(request_str, RequestError::ParseError),
-            },
-            ["expireat", key, seconds] => match seconds.parse::<i64>() {
-                Ok(seconds) => Request::DataBase(Query::ExpireAt(key, seconds)),
-                Err(_) => Request::Invalid(request_str, RequestError::ParseError),
-            },
-            ["ttl", key] => Request::DataBase(Query::Ttl(key)),
-            ["type", key] => Request::DataBase(Query::Type(key)),
-            ["persist", key] => Request::DataBase(Query::Persist(key)),
-            ["append", key, value] => Request::DataBase(Query::Append(key, value)),
-            ["incrby", key, incr] => match incr.parse::<i32>() {
-                Ok(incr) => Request::DataBase(Query::Incrby(key, incr)),
-                Err(_) => Request::Invalid(request_str, RequestError::ParseError),
-            },
-            ["decrby", key, decr] => match decr.parse::<i32>() {
-                Ok(decr) => Request::DataBase(Query::Decrby(key, decr)),
-                Err(_) => Request::Invalid(request_str, RequestError::ParseError),
-            },
-            ["get", key] => Request::DataBase(Query::Get(key)),
-            ["getdel", key] => Request::DataBase(Query::Getdel(key)),
-            ["getset", key, value] => Request::DataBase(Query::Getset(key, value)),
-            ["set", key, value] => Request::DataBase(Query::Set(key, value)),
-            ["copy", key, to_key] => Request::DataBase(Query::Copy(key, to_key)),
-            ["del", key] => Request::DataBase(Query::Del(key)),
-            ["exists", key] => Request::DataBase(Query::Exists(key)),
-            ["keys", pattern] => Request::DataBase(Query::Keys(pattern)),
-            ["rename", old_key, new_key] => Request::DataBase(Query::Rename(old_key, new_key)),
-            ["sort", key, ..] => {
+            [b"expire", key, seconds, ..] => {
+                match (
+                    parse_bytes::<i64>(seconds),
+                    parse_expire_flags(&request[3..]),
+                ) {
+                    (Some(seconds), Ok(flags)) => {
+                        Request::DataBase(Query::Expire(key, seconds, flags))
+                    }
+                    (None, _) => Request::Invalid(command, RequestError::ParseError),
+                    (_, Err(error)) => Request::Invalid(command, error),
+                }
+            }
+            [b"expireat", key, seconds, ..] => {
+                match (
+                    parse_bytes::<i64>(seconds),
+                    parse_expire_flags(&request[3..]),
+                ) {
+                    (Some(seconds), Ok(flags)) => {
+                        Request::DataBase(Query::ExpireAt(key, seconds, flags))
+                    }
+                    (None, _) => Request::Invalid(command, RequestError::ParseError),
+                    (_, Err(error)) => Request::Invalid(command, error),
+                }
+            }
+            [b"pexpire", key, ms, ..] => {
+                match (parse_bytes::<i64>(ms), parse_expire_flags(&request[3..])) {
+                    (Some(ms), Ok(flags)) => Request::DataBase(Query::Pexpire(key, ms, flags)),
+                    (None, _) => Request::Invalid(command, RequestError::ParseError),
+                    (_, Err(error)) => Request::Invalid(command, error),
+                }
+            }
+            [b"pexpireat", key, ms, ..] => {
+                match (parse_bytes::<i64>(ms), parse_expire_flags(&request[3..])) {
+                    (Some(ms), Ok(flags)) => Request::DataBase(Query::PexpireAt(key, ms, flags)),
+                    (None, _) => Request::Invalid(command, RequestError::ParseError),
+                    (_, Err(error)) => Request::Invalid(command, error),
+                }
+            }
+            [b"ttl", key] => Request::DataBase(Query::Ttl(key)),
+            [b"pttl", key] => Request::DataBase(Query::Pttl(key)),
+            [b"expiretime", key] => Request::DataBase(Query::Expiretime(key)),
+            [b"pexpiretime", key] => Request::DataBase(Query::Pexpiretime(key)),
+            [b"type", key] => Request::DataBase(Query::Type(key)),
+            [b"persist", key] => Request::DataBase(Query::Persist(key)),
+            [b"append", key, value] => Request::DataBase(Query::Append(key, value)),
+            [b"incrby", key, incr] => match parse_bytes::<i32>(incr) {
+                Some(incr) => Request::DataBase(Query::Incrby(key, incr)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"decrby", key, decr] => match parse_bytes::<i32>(decr) {
+                Some(decr) => Request::DataBase(Query::Decrby(key, decr)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"get", key] => Request::DataBase(Query::Get(key)),
+            [b"getdel", key] => Request::DataBase(Query::Getdel(key)),
+            [b"getset", key, value] => Request::DataBase(Query::Getset(key, value)),
+            [b"set", key, value, ..] => match parse_set(&request[3..]) {
+                Ok(flags) => Request::DataBase(Query::Set(key, value, flags)),
+                Err(error) => Request::Invalid(command, error),
+            },
+            [b"setex", key, seconds, value] => match parse_bytes::<i64>(seconds) {
+                Some(seconds) => Request::DataBase(Query::Setex(key, seconds, value)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"psetex", key, ms, value] => match parse_bytes::<i64>(ms) {
+                Some(ms) => Request::DataBase(Query::Psetex(key, ms, value)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"setnx", key, value] => Request::DataBase(Query::Setnx(key, value)),
+            [b"getex", key, ..] => match parse_getex(&request[2..]) {
+                Ok(expiry) => Request::DataBase(Query::Getex(key, expiry)),
+                Err(error) => Request::Invalid(command, error),
+            },
+            [b"copy", key, to_key] => Request::DataBase(Query::Copy(key, to_key)),
+            [b"del", key] => Request::DataBase(Query::Del(key)),
+            [b"exists", key] => Request::DataBase(Query::Exists(key)),
+            [b"keys", pattern] => Request::DataBase(Query::Keys(pattern)),
+            [b"rename", old_key, new_key] => Request::DataBase(Query::Rename(old_key, new_key)),
+            [b"sort", key, ..] => {
                 let tail = &request[2..];
                 let mut sort_flags: Vec<SortFlags> = Vec::new();
                 if tail.is_empty() {
@@ -63,25 +123,28 @@ impl<'a> Request<'a> {
                     return Request::DataBase(Query::Sort(key, SortFlags::WithoutFlags));
                 }
                 for elem in tail.iter() {
-                    if *elem == "alpha" && tail.len() > 1 {
+                    if elem.eq_ignore_ascii_case(b"alpha") && tail.len() > 1 {
                         sort_flags.push(SortFlags::Alpha);
                         continue;
-                    } else if *elem == "alpha" && tail.len() == 1 {
+                    } else if elem.eq_ignore_ascii_case(b"alpha") && tail.len() == 1 {
                         return Request::DataBase(Query::Sort(key, SortFlags::Alpha));
                     }
-                    if *elem == "desc" && tail.len() > 1 {
+                    if elem.eq_ignore_ascii_case(b"desc") && tail.len() > 1 {
                         sort_flags.push(SortFlags::Desc);
                         continue;
-                    } else if *elem == "desc" && tail.len() == 1 {
+                    } else if elem.eq_ignore_ascii_case(b"desc") && tail.len() == 1 {
                         return Request::DataBase(Query::Sort(key, SortFlags::Desc));
                     }
-                    if *elem == "limit" {
-                        if let (Some(pos_begin), Some(num_elems)) = (
-                            tail.get(tail.iter().position(|r| *r == "limit").unwrap() + 1),
-                            tail.get(tail.iter().position(|r| *r == "limit").unwrap() + 2),
-                        ) {
-                            if let (Ok(num_pos_begin), Ok(num_elems)) =
-                                (pos_begin.parse::<i32>(), num_elems.parse::<i32>())
+                    if elem.eq_ignore_ascii_case(b"limit") {
+                        let limit_pos = tail
+                            .iter()
+                            .position(|r| r.eq_ignore_ascii_case(b"limit"))
+                            .unwrap();
+                        if let (Some(pos_begin), Some(num_elems)) =
+                            (tail.get(limit_pos + 1), tail.get(limit_pos + 2))
+                        {
+                            if let (Some(num_pos_begin), Some(num_elems)) =
+                                (parse_bytes::<i32>(pos_begin), parse_bytes::<i32>(num_elems))
                             {
                                 if tail.len() == 3 {
                                     return Request::DataBase(Query::Sort(
@@ -93,13 +156,15 @@ impl<'a> Request<'a> {
                                     continue;
                                 }
                             };
-                            return Request::Invalid(request_str, RequestError::ParseError);
+                            return Request::Invalid(command, RequestError::ParseError);
                         };
                     }
-                    if *elem == "by" {
-                        if let Some(pattern) =
-                            tail.get(tail.iter().position(|r| *r == "by").unwrap() + 1)
-                        {
+                    if elem.eq_ignore_ascii_case(b"by") {
+                        let by_pos = tail
+                            .iter()
+                            .position(|r| r.eq_ignore_ascii_case(b"by"))
+                            .unwrap();
+                        if let Some(pattern) = tail.get(by_pos + 1) {
                             if tail.len() > 1 {
                                 sort_flags.push(SortFlags::By(pattern));
                                 continue;
@@ -107,141 +172,423 @@ impl<'a> Request<'a> {
                                 return Request::DataBase(Query::Sort(key, SortFlags::By(pattern)));
                             }
                         }
-                        return Request::Invalid(request_str, RequestError::ParseError);
+                        return Request::Invalid(command, RequestError::ParseError);
                     }
                 }
                 Request::DataBase(Query::Sort(key, SortFlags::CompositeFlags(sort_flags)))
             }
-            ["strlen", key] => Request::DataBase(Query::Strlen(key)),
-            ["mset", ..] => {
+            [b"strlen", key] => Request::DataBase(Query::Strlen(key)),
+            [b"mset", ..] => {
                 let tail = &request[1..];
                 let len = tail.len() as i32;
 
                 if len > 0 && (len % 2 == 0) {
                     Request::DataBase(Query::Mset(tail.to_vec()))
                 } else {
-                    Request::Invalid(request_str, RequestError::InvalidNumberOfArguments)
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
                 }
             }
-            ["mget", ..] => {
+            [b"mget", ..] => {
                 let tail = &request[1..];
                 Request::DataBase(Query::Mget(tail.to_vec()))
             }
-            ["lindex", key, index] => match index.parse::<i32>() {
-                Ok(index) => Request::DataBase(Query::Lindex(key, index)),
-                Err(_) => Request::Invalid(request_str, RequestError::ParseError),
+            [b"lindex", key, index] => match parse_bytes::<i32>(index) {
+                Some(index) => Request::DataBase(Query::Lindex(key, index)),
+                None => Request::Invalid(command, RequestError::ParseError),
             },
-            ["llen", key] => Request::DataBase(Query::Llen(key)),
-            ["lpop", key] => Request::DataBase(Query::Lpop(key)),
-            ["lpush", key, ..] => {
+            [b"llen", key] => Request::DataBase(Query::Llen(key)),
+            [b"lpop", key] => Request::DataBase(Query::Lpop(key)),
+            [b"lpush", key, ..] => {
                 let tail = &request[2..];
                 if tail.is_empty() {
-                    Request::Invalid(request_str, RequestError::InvalidNumberOfArguments)
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
                 } else {
                     Request::DataBase(Query::Lpush(key, tail.to_vec()))
                 }
             }
-            ["lpushx", key, ..] => {
+            [b"lpushx", key, ..] => {
                 let tail = &request[2..];
                 if tail.is_empty() {
-                    Request::Invalid(request_str, RequestError::InvalidNumberOfArguments)
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
                 } else {
                     Request::DataBase(Query::Lpushx(key, tail.to_vec()))
                 }
             }
-            ["lrange", key, ini, end] => match ini.parse::<i32>() {
-                Ok(ini) => match end.parse::<i32>() {
-                    Ok(end) => Request::DataBase(Query::Lrange(key, ini, end)),
-                    Err(_) => Request::Invalid(request_str, RequestError::ParseError),
+            [b"lrange", key, ini, end] => match parse_bytes::<i32>(ini) {
+                Some(ini) => match parse_bytes::<i32>(end) {
+                    Some(end) => Request::DataBase(Query::Lrange(key, ini, end)),
+                    None => Request::Invalid(command, RequestError::ParseError),
                 },
-                Err(_) => Request::Invalid(request_str, RequestError::ParseError),
+                None => Request::Invalid(command, RequestError::ParseError),
             },
-            ["lrem", key, count, value] => match count.parse::<i32>() {
-                Ok(count) => Request::DataBase(Query::Lrem(key, count, value)),
-                Err(_) => Request::Invalid(request_str, RequestError::ParseError),
+            [b"lrem", key, count, value] => match parse_bytes::<i32>(count) {
+                Some(count) => Request::DataBase(Query::Lrem(key, count, value)),
+                None => Request::Invalid(command, RequestError::ParseError),
             },
-            ["lset", key, index, value] => match index.parse::<usize>() {
-                Ok(index) => Request::DataBase(Query::Lset(key, index, value)),
-                Err(_) => Request::Invalid(request_str, RequestError::ParseError),
+            [b"lset", key, index, value] => match parse_bytes::<usize>(index) {
+                Some(index) => Request::DataBase(Query::Lset(key, index, value)),
+                None => Request::Invalid(command, RequestError::ParseError),
             },
-            ["rpop", key] => Request::DataBase(Query::Rpop(key)),
-            ["rpush", key, ..] => {
+            [b"rpop", key] => Request::DataBase(Query::Rpop(key)),
+            [b"rpush", key, ..] => {
                 let tail = &request[2..];
                 if tail.is_empty() {
-                    Request::Invalid(request_str, RequestError::InvalidNumberOfArguments)
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
                 } else {
                     Request::DataBase(Query::Rpush(key, tail.to_vec()))
                 }
             }
-            ["rpushx", key, ..] => {
+            [b"rpushx", key, ..] => {
                 let tail = &request[2..];
                 if tail.is_empty() {
-                    Request::Invalid(request_str, RequestError::InvalidNumberOfArguments)
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
                 } else {
                     Request::DataBase(Query::Rpushx(key, tail.to_vec()))
                 }
             }
-            ["sadd", key, ..] => {
+            [b"sadd", key, ..] => {
                 let tail = &request[2..];
                 if tail.is_empty() {
-                    Request::Invalid(request_str, RequestError::InvalidNumberOfArguments)
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
                 } else {
                     Request::DataBase(Query::Sadd(key, tail.to_vec()))
                 }
             }
-            ["sismember", key, element] => Request::DataBase(Query::Sismember(key, element)),
-            ["scard", key] => Request::DataBase(Query::Scard(key)),
-            ["flushdb"] => Request::DataBase(Query::Flushdb()),
-            ["dbsize"] => Request::DataBase(Query::Dbsize()),
-            ["config", "get", pattern] => Request::Server(ServerRequest::ConfigGet(pattern)),
-            ["config", "set", option, new_value] => {
-                Request::Server(ServerRequest::ConfigSet(option, new_value))
+            [b"sismember", key, element] => Request::DataBase(Query::Sismember(key, element)),
+            [b"scard", key] => Request::DataBase(Query::Scard(key)),
+            [b"hset", key, ..] => {
+                let tail = &request[2..];
+                if tail.is_empty() || !tail.len().is_multiple_of(2) {
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
+                } else {
+                    Request::DataBase(Query::Hset(key, tail.to_vec()))
+                }
             }
-            ["smembers", key] => Request::DataBase(Query::Smembers(key)),
-            ["srem", key, ..] => {
-                let tail = &request[1..];
-                Request::DataBase(Query::Srem(key, tail.to_vec()))
+            [b"hsetnx", key, field, value] => Request::DataBase(Query::Hsetnx(key, field, value)),
+            [b"hget", key, field] => Request::DataBase(Query::Hget(key, field)),
+            [b"hmget", key, ..] => {
+                let tail = &request[2..];
+                if tail.is_empty() {
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
+                } else {
+                    Request::DataBase(Query::Hmget(key, tail.to_vec()))
+                }
             }
-            ["monitor"] => Request::Suscriber(SuscriberRequest::Monitor),
-            ["subscribe", ..] => {
-                let tail = &request[1..];
-                Request::Suscriber(SuscriberRequest::Subscribe(tail.to_vec()))
+            [b"hgetall", key] => Request::DataBase(Query::Hgetall(key)),
+            [b"hdel", key, ..] => {
+                let tail = &request[2..];
+                if tail.is_empty() {
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
+                } else {
+                    Request::DataBase(Query::Hdel(key, tail.to_vec()))
+                }
+            }
+            [b"hexists", key, field] => Request::DataBase(Query::Hexists(key, field)),
+            [b"hlen", key] => Request::DataBase(Query::Hlen(key)),
+            [b"hkeys", key] => Request::DataBase(Query::Hkeys(key)),
+            [b"hvals", key] => Request::DataBase(Query::Hvals(key)),
+            [b"hstrlen", key, field] => Request::DataBase(Query::Hstrlen(key, field)),
+            [b"hincrby", key, field, incr] => match parse_bytes::<i32>(incr) {
+                Some(incr) => Request::DataBase(Query::Hincrby(key, field, incr)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"hincrbyfloat", key, field, incr] => match parse_bytes::<f64>(incr) {
+                Some(incr) if incr.is_finite() => {
+                    Request::DataBase(Query::Hincrbyfloat(key, field, incr))
+                }
+                _ => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"hrandfield", key] => Request::DataBase(Query::Hrandfield(key, None, false)),
+            [b"hrandfield", key, count] => match parse_bytes::<i32>(count) {
+                Some(count) => Request::DataBase(Query::Hrandfield(key, Some(count), false)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"hrandfield", key, count, option] if option.eq_ignore_ascii_case(b"withvalues") => {
+                match parse_bytes::<i32>(count) {
+                    Some(count) => Request::DataBase(Query::Hrandfield(key, Some(count), true)),
+                    None => Request::Invalid(command, RequestError::ParseError),
+                }
             }
-            ["publish", chanel, msg] => Request::Publisher(PublisherRequest::Publish(chanel, msg)),
-            ["unsubscribe", ..] => {
+            [b"hscan", key, cursor, ..] => {
+                let cursor = match parse_bytes::<u64>(cursor) {
+                    Some(cursor) => cursor,
+                    None => return Request::Invalid(command, RequestError::ParseError),
+                };
+                let mut pattern = None;
+                let mut count = 10;
+                for option in request[3..].chunks(2) {
+                    match option {
+                        [name, value] if name.eq_ignore_ascii_case(b"match") => {
+                            pattern = Some(*value)
+                        }
+                        [name, value] if name.eq_ignore_ascii_case(b"count") => {
+                            match parse_bytes::<usize>(value) {
+                                Some(value) if value > 0 => count = value,
+                                _ => return Request::Invalid(command, RequestError::ParseError),
+                            }
+                        }
+                        _ => return Request::Invalid(command, RequestError::SyntaxError),
+                    }
+                }
+                Request::DataBase(Query::Hscan(key, cursor, pattern, count))
+            }
+            [b"zadd", key, ..] => match parse_zadd(key, &request[2..]) {
+                Ok(query) => Request::DataBase(query),
+                Err(error) => Request::Invalid(command, error),
+            },
+            [b"zscore", key, member] => Request::DataBase(Query::Zscore(key, member)),
+            [b"zincrby", key, incr, member] => match parse_score(incr) {
+                Some(incr) => Request::DataBase(Query::Zincrby(key, incr, member)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"zrank", key, member] => Request::DataBase(Query::Zrank(key, member)),
+            [b"zrevrank", key, member] => Request::DataBase(Query::Zrevrank(key, member)),
+            [b"zrange", key, min, max, ..] => match parse_zrange(key, min, max, &request[4..]) {
+                Ok(query) => Request::DataBase(query),
+                Err(error) => Request::Invalid(command, error),
+            },
+            [b"zrem", key, ..] => {
+                let tail = &request[2..];
+                if tail.is_empty() {
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
+                } else {
+                    Request::DataBase(Query::Zrem(key, tail.to_vec()))
+                }
+            }
+            [b"zremrangebyrank", key, start, stop] => {
+                match (parse_bytes::<i64>(start), parse_bytes::<i64>(stop)) {
+                    (Some(start), Some(stop)) => {
+                        Request::DataBase(Query::Zremrangebyrank(key, start, stop))
+                    }
+                    _ => Request::Invalid(command, RequestError::ParseError),
+                }
+            }
+            [b"zremrangebyscore", key, min, max] => {
+                match (ScoreBound::parse(min), ScoreBound::parse(max)) {
+                    (Some(min), Some(max)) => {
+                        Request::DataBase(Query::Zremrangebyscore(key, min, max))
+                    }
+                    _ => Request::Invalid(command, RequestError::ParseError),
+                }
+            }
+            [b"zremrangebylex", key, min, max] => {
+                match (LexBound::parse(min), LexBound::parse(max)) {
+                    (Some(min), Some(max)) => {
+                        Request::DataBase(Query::Zremrangebylex(key, min, max))
+                    }
+                    _ => Request::Invalid(command, RequestError::ParseError),
+                }
+            }
+            [b"zcard", key] => Request::DataBase(Query::Zcard(key)),
+            [b"zcount", key, min, max] => match (ScoreBound::parse(min), ScoreBound::parse(max)) {
+                (Some(min), Some(max)) => Request::DataBase(Query::Zcount(key, min, max)),
+                _ => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"zpopmin", key] => Request::DataBase(Query::Zpopmin(key, None)),
+            [b"zpopmin", key, count] => match parse_bytes::<usize>(count) {
+                Some(count) => Request::DataBase(Query::Zpopmin(key, Some(count))),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"zpopmax", key] => Request::DataBase(Query::Zpopmax(key, None)),
+            [b"zpopmax", key, count] => match parse_bytes::<usize>(count) {
+                Some(count) => Request::DataBase(Query::Zpopmax(key, Some(count))),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"zunionstore", destination, numkeys, ..] => {
+                match parse_zstore(numkeys, &request[3..], true) {
+                    Ok((keys, weights, aggregate)) => {
+                        Request::DataBase(Query::Zunionstore(destination, keys, weights, aggregate))
+                    }
+                    Err(error) => Request::Invalid(command, error),
+                }
+            }
+            [b"zinterstore", destination, numkeys, ..] => {
+                match parse_zstore(numkeys, &request[3..], true) {
+                    Ok((keys, weights, aggregate)) => {
+                        Request::DataBase(Query::Zinterstore(destination, keys, weights, aggregate))
+                    }
+                    Err(error) => Request::Invalid(command, error),
+                }
+            }
+            [b"zdiffstore", destination, numkeys, ..] => {
+                match parse_zstore(numkeys, &request[3..], false) {
+                    Ok((keys, _, _)) => Request::DataBase(Query::Zdiffstore(destination, keys)),
+                    Err(error) => Request::Invalid(command, error),
+                }
+            }
+            [b"flushdb"] => Request::DataBase(Query::Flushdb()),
+            [b"flushall"] => Request::DataBase(Query::Flushall()),
+            [b"bgrewriteaof"] => Request::DataBase(Query::Bgrewriteaof()),
+            [b"save"] => Request::DataBase(Query::Save()),
+            [b"bgsave"] => Request::DataBase(Query::Bgsave()),
+            [b"lastsave"] => Request::DataBase(Query::Lastsave()),
+            [b"debug", subcommand] if subcommand.eq_ignore_ascii_case(b"reload") => {
+                Request::DataBase(Query::DebugReload())
+            }
+            [b"shutdown"] => Request::Server(ServerRequest::Shutdown(None)),
+            [b"shutdown", mode] if mode.eq_ignore_ascii_case(b"save") => {
+                Request::Server(ServerRequest::Shutdown(Some(true)))
+            }
+            [b"shutdown", mode] if mode.eq_ignore_ascii_case(b"nosave") => {
+                Request::Server(ServerRequest::Shutdown(Some(false)))
+            }
+            [b"select", index] => match parse_bytes::<usize>(index) {
+                Some(index) => Request::DataBase(Query::Select(index)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"swapdb", index1, index2] => {
+                match (parse_bytes::<usize>(index1), parse_bytes::<usize>(index2)) {
+                    (Some(index1), Some(index2)) => {
+                        Request::DataBase(Query::Swapdb(index1, index2))
+                    }
+                    _ => Request::Invalid(command, RequestError::ParseError),
+                }
+            }
+            [b"move", key, db] => match parse_bytes::<usize>(db) {
+                Some(db) => Request::DataBase(Query::Move(key, db)),
+                None => Request::Invalid(command, RequestError::ParseError),
+            },
+            [b"dbsize"] => Request::DataBase(Query::Dbsize()),
+            [b"config", subcommand, pattern] if subcommand.eq_ignore_ascii_case(b"get") => {
+                match str::from_utf8(pattern) {
+                    Ok(pattern) => Request::Server(ServerRequest::ConfigGet(pattern)),
+                    Err(_) => Request::Invalid(command, RequestError::NotUtf8),
+                }
+            }
+            [b"config", subcommand, option, new_value]
+                if subcommand.eq_ignore_ascii_case(b"set") =>
+            {
+                match (str::from_utf8(option), str::from_utf8(new_value)) {
+                    (Ok(option), Ok(new_value)) => {
+                        Request::Server(ServerRequest::ConfigSet(option, new_value))
+                    }
+                    _ => Request::Invalid(command, RequestError::NotUtf8),
+                }
+            }
+            [b"smembers", key] => Request::DataBase(Query::Smembers(key)),
+            [b"srem", key, ..] => {
                 let tail = &request[1..];
-                Request::Suscriber(SuscriberRequest::Unsubscribe(tail.to_vec()))
+                Request::DataBase(Query::Srem(key, tail.to_vec()))
             }
-            ["pubsub", "channels", ..] => {
-                let arg = &mut request[2..].to_vec();
+            [b"monitor"] => Request::Suscriber(SuscriberRequest::Monitor),
+            [b"subscribe", ..] => match to_str_vec(&request[1..]) {
+                Some(tail) => Request::Suscriber(SuscriberRequest::Subscribe(tail)),
+                None => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            [b"publish", chanel, msg] => match (str::from_utf8(chanel), str::from_utf8(msg)) {
+                (Ok(chanel), Ok(msg)) => Request::Publisher(PublisherRequest::Publish(chanel, msg)),
+                _ => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            [b"unsubscribe", ..] => match to_str_vec(&request[1..]) {
+                Some(tail) => Request::Suscriber(SuscriberRequest::Unsubscribe(tail)),
+                None => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            [b"psubscribe", ..] => match to_str_vec(&request[1..]) {
+                Some(tail) => Request::Suscriber(SuscriberRequest::PSubscribe(tail)),
+                None => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            [b"punsubscribe", ..] => match to_str_vec(&request[1..]) {
+                Some(tail) => Request::Suscriber(SuscriberRequest::PUnsubscribe(tail)),
+                None => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            [b"pubsub", subcommand, ..] if subcommand.eq_ignore_ascii_case(b"channels") => {
+                let arg = match to_str_vec(&request[2..]) {
+                    Some(arg) => arg,
+                    None => return Request::Invalid(command, RequestError::NotUtf8),
+                };
                 if arg.len() > 1 {
-                    return Request::Invalid(request_str, RequestError::InvalidNumberOfArguments);
+                    return Request::Invalid(command, RequestError::InvalidNumberOfArguments);
                 }
 
-                let pattern = arg.pop();
+                let pattern = arg.first().copied();
                 Request::Publisher(PublisherRequest::PubSub(PubSubSubcommand::Channels(
                     pattern,
                 )))
             }
-            ["pubsub", "numsub", ..] => {
-                let tail = &request[2..];
-                Request::Publisher(PublisherRequest::PubSub(PubSubSubcommand::NumSub(
-                    tail.to_vec(),
-                )))
+            [b"pubsub", subcommand, ..] if subcommand.eq_ignore_ascii_case(b"numsub") => {
+                match to_str_vec(&request[2..]) {
+                    Some(tail) => {
+                        Request::Publisher(PublisherRequest::PubSub(PubSubSubcommand::NumSub(tail)))
+                    }
+                    None => Request::Invalid(command, RequestError::NotUtf8),
+                }
+            }
+            [b"pubsub", subcommand] if subcommand.eq_ignore_ascii_case(b"numpat") => {
+                Request::Publisher(PublisherRequest::PubSub(PubSubSubcommand::NumPat))
+            }
+            [b"pubsub", subcommand, ..] if subcommand.eq_ignore_ascii_case(b"shardchannels") => {
+                match to_str_vec(&request[2..]).as_deref() {
+                    Some([]) => Request::Publisher(PublisherRequest::PubSub(
+                        PubSubSubcommand::ShardChannels(None),
+                    )),
+                    Some([pattern]) => Request::Publisher(PublisherRequest::PubSub(
+                        PubSubSubcommand::ShardChannels(Some(pattern)),
+                    )),
+                    Some(_) => Request::Invalid(command, RequestError::InvalidNumberOfArguments),
+                    None => Request::Invalid(command, RequestError::NotUtf8),
+                }
+            }
+            [b"pubsub", subcommand, ..] if subcommand.eq_ignore_ascii_case(b"shardnumsub") => {
+                match to_str_vec(&request[2..]) {
+                    Some(tail) => Request::Publisher(PublisherRequest::PubSub(
+                        PubSubSubcommand::ShardNumSub(tail),
+                    )),
+                    None => Request::Invalid(command, RequestError::NotUtf8),
+                }
+            }
+            [b"ssubscribe", _, ..] => match to_str_vec(&request[1..]) {
+                Some(tail) => Request::Suscriber(SuscriberRequest::SSubscribe(tail)),
+                None => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            [b"sunsubscribe", ..] => match to_str_vec(&request[1..]) {
+                Some(tail) => Request::Suscriber(SuscriberRequest::SUnsubscribe(tail)),
+                None => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            [b"spublish", chanel, msg] => match (str::from_utf8(chanel), str::from_utf8(msg)) {
+                (Ok(chanel), Ok(msg)) => {
+                    Request::Publisher(PublisherRequest::SPublish(chanel, msg))
+                }
+                _ => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            [b"multi"] => Request::Transaction(TransactionRequest::Multi),
+            [b"exec"] => Request::Transaction(TransactionRequest::Exec),
+            [b"discard"] => Request::Transaction(TransactionRequest::Discard),
+            [b"watch", ..] => {
+                let tail = &request[1..];
+                if tail.is_empty() {
+                    Request::Invalid(command, RequestError::InvalidNumberOfArguments)
+                } else {
+                    Request::Transaction(TransactionRequest::Watch(tail.to_vec()))
+                }
             }
-            ["info"] => Request::Server(ServerRequest::Info()),
-            ["close"] => Request::CloseClient,
-            ["touch", key] => Request::Touch(key),
-            _ => Request::Invalid(request_str, RequestError::UnknownRequest),
+            [b"unwatch"] => Request::Transaction(TransactionRequest::Unwatch),
+            [b"info"] => Request::Server(ServerRequest::Info()),
+            [b"close"] | [b"quit"] => Request::CloseClient,
+            [b"reset"] => Request::Reset,
+            [b"hello", ..] => parse_hello(command, &request[1..]),
+            [b"touch", key] => Request::Touch(key),
+            [b"ping"] => Request::Ping(None),
+            [b"ping", message] => Request::Ping(Some(message)),
+            [b"auth", password] => Request::Auth(None, password),
+            [b"auth", username, password] => Request::Auth(Some(username), password),
+            [b"acl", subcommand, ..] => match str::from_utf8(subcommand) {
+                Ok(subcommand) => parse_acl(command, subcommand, &request[2..]),
+                Err(_) => Request::Invalid(command, RequestError::NotUtf8),
+            },
+            _ => Request::Invalid(command, RequestError::UnknownRequest),
         };
 
         if subscription_mode {
             match request {
                 Request::Suscriber(SuscriberRequest::Unsubscribe(_)) => request,
                 Request::Suscriber(SuscriberRequest::Subscribe(_)) => request,
-                Request::CloseClient => request,
+                Request::Suscriber(SuscriberRequest::PUnsubscribe(_)) => request,
+                Request::Suscriber(SuscriberRequest::PSubscribe(_)) => request,
+                Request::Suscriber(SuscriberRequest::SUnsubscribe(_)) => request,
+                Request::Suscriber(SuscriberRequest::SSubscribe(_)) => request,
+                Request::Ping(_) | Request::Reset | Request::CloseClient => request,
                 Request::Invalid(_, _) => request,
-                _ => Request::Invalid(request_str, RequestError::InvalidCommandSubscribeMode),
+                _ => Request::Invalid(command, RequestError::InvalidCommandSubscribeMode),
             }
         } else {
             request
@@ -254,10 +601,23 @@ impl<'a> Display for Request<'a> {
         match self {
             Request::DataBase(query) => write!(f, "{}", query),
             Request::Server(server_request) => write!(f, "{}", server_request),
-            Request::Invalid(request_str, error) => write!(f, "{} On: {}", error, request_str),
+            Request::Invalid(command, error) => {
+                let command: Vec<String> = command.iter().map(|s| show(s).to_string()).collect();
+                write!(f, "{} On: {}", error, command.join(" "))
+            }
             Request::Suscriber(sus_request) => write!(f, "{}", sus_request),
             Request::Publisher(pub_request) => write!(f, "{}", pub_request),
-            Request::Touch(key) => write!(f, "Touch - key: {}", key),
+            Request::Transaction(transaction_request) => write!(f, "{}", transaction_request),
+            Request::Acl(acl_request) => write!(f, "{}", acl_request),
+            Request::Touch(key) => write!(f, "Touch - key: {}", show(key)),
+            Request::Ping(message) => write!(f, "Ping - message: {}", show(message.unwrap_or(b""))),
+            Request::Auth(Some(username), _) => write!(f, "Auth - user: {}", show(username)),
+            Request::Auth(None, _) => write!(f, "Auth"),
+            Request::Hello(protover, _) => match protover {
+                Some(protover) => write!(f, "Hello - protocol: {}", protover),
+                None => write!(f, "Hello"),
+            },
+            Request::Reset => write!(f, "Reset"),
             Request::CloseClient => write!(f, "Close"),
         }
     }
@@ -268,15 +628,19 @@ pub enum RequestError {
     InvalidCommandSubscribeMode,
     UnknownRequest,
     InvalidNumberOfArguments,
+    NotUtf8,
+    SyntaxError,
 }
 
-impl<'a> Display for RequestError {
+impl Display for RequestError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             RequestError::ParseError => write!(f, "Couldn't Parse number input"),
             RequestError::InvalidNumberOfArguments => write!(f, "Invalid Number of Arguments"),
             RequestError::UnknownRequest => write!(f, "Non existent Request"),
             RequestError::InvalidCommandSubscribeMode => write!(f, "{}", SUBSCRIPTION_MODE_ERROR),
+            RequestError::NotUtf8 => write!(f, "Argument isn't valid UTF-8"),
+            RequestError::SyntaxError => write!(f, "Syntax error"),
         }
     }
 }
@@ -285,6 +649,7 @@ pub enum ServerRequest<'a> {
     ConfigGet(&'a str),
     ConfigSet(&'a str, &'a str),
     Info(),
+    Shutdown(Option<bool>),
 }
 
 impl<'a> ServerRequest<'a> {
@@ -293,10 +658,18 @@ impl<'a> ServerRequest<'a> {
         conf: &mut ServerConf,
         uptime: SystemTime,
         total_clients: Arc<Mutex<u64>>,
+        db: &Database,
     ) -> Reponse {
         let result = match self {
             ServerRequest::ConfigGet(option) => conf.get_config(option),
-            ServerRequest::ConfigSet(option, value) => conf.set_config(option, value),
+            ServerRequest::ConfigSet(option, value) => {
+                let result = conf.set_config(option, value);
+                if result.is_ok() {
+                    db.set_maxmemory(conf.maxmemory(), conf.maxmemory_policy());
+                    db.set_notify_keyspace_events(conf.notify_keyspace_events());
+                }
+                result
+            }
             ServerRequest::Info() => {
                 let mut r = format!("process_id:{}\r\n", process::id());
                 r.push_str(&format!("tcp_port:{}\r\n", conf.port()));
@@ -312,13 +685,39 @@ impl<'a> ServerRequest<'a> {
                 r.push_str(&format!("uptime_in_days:{}\r\n", uptime_in_days));
                 let clients = total_clients.lock().unwrap();
                 r.push_str(&format!("clients:{}", clients));
+                let (used_memory, maxmemory, policy, evicted_keys) = db.memory_info();
+                r.push_str("\r\n# Memory");
+                r.push_str(&format!("\r\nused_memory:{}", used_memory));
+                r.push_str(&format!("\r\nmaxmemory:{}", maxmemory));
+                r.push_str(&format!("\r\nmaxmemory_policy:{}", policy));
+                r.push_str(&format!("\r\nevicted_keys:{}", evicted_keys));
+                let shards = db.shard_info();
+                r.push_str("\r\n# Shards");
+                r.push_str(&format!("\r\nshards:{}", shards.len()));
+                for (index, keys) in shards.iter().enumerate() {
+                    r.push_str(&format!("\r\nshard{}:keys={}", index, keys));
+                }
+                r.push_str("\r\n# Keyspace");
+                for (index, keys, expires) in db.keyspace_info() {
+                    r.push_str(&format!(
+                        "\r\ndb{}:keys={},expires={}",
+                        index, keys, expires
+                    ));
+                }
 
                 Ok(SuccessServerRequest::String(r))
             }
+            ServerRequest::Shutdown(save) => {
+                let save = save.unwrap_or_else(|| !conf.save_points().is_empty());
+                match db.prepare_shutdown(save) {
+                    Ok(()) => process::exit(0),
+                    Err(_) => Err(ServerError::ShutdownFailed),
+                }
+            }
         };
 
         match result {
-            Ok(succes) => Reponse::Valid(succes.to_string()),
+            Ok(succes) => Reponse::Valid(RespValue::from(succes)),
             Err(err) => Reponse::Error(err.to_string()),
         }
     }
@@ -336,6 +735,7 @@ impl<'a> Display for ServerRequest<'a> {
                 option, new_value
             ),
             ServerRequest::Info() => write!(f, "Info"),
+            ServerRequest::Shutdown(save) => write!(f, "Shutdown - Save: {:?}", save),
         }
     }
 }
@@ -344,66 +744,56 @@ pub enum SuscriberRequest<'a> {
     Monitor,
     Subscribe(Vec<&'a str>),
     Unsubscribe(Vec<&'a str>),
+    PSubscribe(Vec<&'a str>),
+    PUnsubscribe(Vec<&'a str>),
+    SSubscribe(Vec<&'a str>),
+    SUnsubscribe(Vec<&'a str>),
 }
 
 impl<'a> SuscriberRequest<'a> {
+    /// Executes the request for the client with that id, whose messages go to
+    /// messages: the requests seen, for a monitor, or the ones published in the
+    /// channels it subscribes to, that match its patterns or in its shard channels.
+    /// The count of each reply includes both the channels and the patterns, or only
+    /// the shard channels for the shard commands.
     pub fn execute(
         self,
-        stream: &mut TcpStream,
         channels: &mut Channels,
         subscriptions: &mut Vec<String>,
+        patterns: &mut Vec<String>,
+        shard_subscriptions: &mut Vec<String>,
         id: u32,
-        subscription_mode: &mut bool,
+        messages: &MessageSender,
     ) -> Reponse {
         match self {
             Self::Monitor => {
-                let r = channels.add_monitor(id);
-                Reponse::Valid("Ok".to_string()).respond(stream);
-                subscriptions.push("Monitor".to_string());
-
-                for msg in r.iter() {
-                    if writeln!(stream, "{}", msg).is_err() {
-                        break;
-                    }
+                if !subscriptions.iter().any(|s| s == MONITOR) {
+                    channels.add_monitor(messages.clone(), id);
+                    subscriptions.push(MONITOR.to_string());
                 }
 
-                Reponse::Error("Monitor".to_string())
+                Reponse::Valid(RespValue::SimpleString("OK".to_string()))
             }
             Self::Subscribe(channels_to_add) => {
-                let (s, r) = channel();
-                let mut result = String::new();
+                let mut result = Vec::new();
 
                 for channel in channels_to_add {
                     if !subscriptions.contains(&channel.to_string()) {
                         subscriptions.push(channel.to_string());
-                        channels.subscribe(channel, s.clone(), id);
+                        channels.subscribe(channel, messages.clone(), id);
                     }
 
-                    let subscription =
-                        vec_to_string(&["subscribe", channel, &subscriptions.len().to_string()]);
-
-                    if result.is_empty() {
-                        result = subscription;
-                    } else {
-                        result = format!("{}\n{}", result, subscription);
-                    }
+                    result.push(subscription_reply(
+                        "subscribe",
+                        Some(channel),
+                        subscriptions.len() + patterns.len(),
+                    ));
                 }
 
-                let mut s = stream.try_clone().expect("clone failed...");
-
-                thread::spawn(move || {
-                    for msg in r.iter() {
-                        let msg = vec_to_string(&["message", &msg]);
-                        let respons = Reponse::Valid(msg);
-                        respons.respond(&mut s);
-                    }
-                });
-
-                *subscription_mode = true;
-                Reponse::Valid(result)
+                Reponse::Frames(result)
             }
             Self::Unsubscribe(channels_to_unsubscribe) => {
-                let mut result = String::new();
+                let mut result = Vec::new();
                 let mut channels_to_unsubscribe = channels_to_unsubscribe
                     .iter()
                     .map(|s| s.to_string())
@@ -417,17 +807,109 @@ impl<'a> SuscriberRequest<'a> {
                         channels.unsubscribe(&channel, id);
                     }
 
-                    let unsubscription =
-                        vec_to_string(&["unsubscribe", &channel, &subscriptions.len().to_string()]);
+                    result.push(subscription_reply(
+                        "unsubscribe",
+                        Some(&channel),
+                        subscriptions.len() + patterns.len(),
+                    ));
+                }
+                if result.is_empty() {
+                    let count = subscriptions.len() + patterns.len();
+                    result.push(subscription_reply("unsubscribe", None, count));
+                }
+
+                Reponse::Frames(result)
+            }
+            Self::PSubscribe(patterns_to_add) => {
+                let mut result = Vec::new();
+
+                for pattern in patterns_to_add {
+                    if !patterns.contains(&pattern.to_string()) {
+                        patterns.push(pattern.to_string());
+                        channels.psubscribe(pattern, messages.clone(), id);
+                    }
+
+                    result.push(subscription_reply(
+                        "psubscribe",
+                        Some(pattern),
+                        subscriptions.len() + patterns.len(),
+                    ));
+                }
+
+                Reponse::Frames(result)
+            }
+            Self::PUnsubscribe(patterns_to_unsubscribe) => {
+                let mut result = Vec::new();
+                let mut patterns_to_unsubscribe = patterns_to_unsubscribe
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>();
+                if patterns_to_unsubscribe.is_empty() {
+                    patterns_to_unsubscribe = patterns.to_owned();
+                }
+                for pattern in patterns_to_unsubscribe {
+                    if patterns.contains(&pattern) {
+                        patterns.retain(|x| *x != pattern);
+                        channels.punsubscribe(&pattern, id);
+                    }
+
+                    result.push(subscription_reply(
+                        "punsubscribe",
+                        Some(&pattern),
+                        subscriptions.len() + patterns.len(),
+                    ));
+                }
+                if result.is_empty() {
+                    let count = subscriptions.len() + patterns.len();
+                    result.push(subscription_reply("punsubscribe", None, count));
+                }
+
+                Reponse::Frames(result)
+            }
+            Self::SSubscribe(channels_to_add) => {
+                let mut result = Vec::new();
 
-                    if result.is_empty() {
-                        result = unsubscription;
-                    } else {
-                        result = format!("{}\n{}", result, unsubscription);
+                for channel in channels_to_add {
+                    if !shard_subscriptions.iter().any(|c| c == channel) {
+                        shard_subscriptions.push(channel.to_string());
+                        channels.ssubscribe(channel, messages.clone(), id);
                     }
+
+                    result.push(subscription_reply(
+                        "ssubscribe",
+                        Some(channel),
+                        shard_subscriptions.len(),
+                    ));
                 }
 
-                Reponse::Valid(result)
+                Reponse::Frames(result)
+            }
+            Self::SUnsubscribe(channels_to_unsubscribe) => {
+                let mut result = Vec::new();
+                let mut channels_to_unsubscribe = channels_to_unsubscribe
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>();
+                if channels_to_unsubscribe.is_empty() {
+                    channels_to_unsubscribe = shard_subscriptions.to_owned();
+                }
+                for channel in channels_to_unsubscribe {
+                    if shard_subscriptions.contains(&channel) {
+                        shard_subscriptions.retain(|x| *x != channel);
+                        channels.sunsubscribe(&channel, id);
+                    }
+
+                    result.push(subscription_reply(
+                        "sunsubscribe",
+                        Some(&channel),
+                        shard_subscriptions.len(),
+                    ));
+                }
+                if result.is_empty() {
+                    result.push(subscription_reply("sunsubscribe", None, 0));
+                }
+
+                Reponse::Frames(result)
             }
         }
     }
@@ -443,6 +925,22 @@ impl<'a> Display for SuscriberRequest<'a> {
             SuscriberRequest::Unsubscribe(unsuscriptions) => {
                 write!(f, "Unsubscribe channels: {}", vec_to_string(unsuscriptions))
             }
+            SuscriberRequest::PSubscribe(patterns) => {
+                write!(f, "PSubscribe patterns: {}", vec_to_string(patterns))
+            }
+            SuscriberRequest::PUnsubscribe(patterns) => {
+                write!(f, "PUnsubscribe patterns: {}", vec_to_string(patterns))
+            }
+            SuscriberRequest::SSubscribe(suscriptions) => {
+                write!(f, "SSubscribe channels: {}", vec_to_string(suscriptions))
+            }
+            SuscriberRequest::SUnsubscribe(unsuscriptions) => {
+                write!(
+                    f,
+                    "SUnsubscribe channels: {}",
+                    vec_to_string(unsuscriptions)
+                )
+            }
         }
     }
 }
@@ -450,6 +948,9 @@ impl<'a> Display for SuscriberRequest<'a> {
 pub enum PubSubSubcommand<'a> {
     Channels(Option<&'a str>),
     NumSub(Vec<&'a str>),
+    NumPat,
+    ShardChannels(Option<&'a str>),
+    ShardNumSub(Vec<&'a str>),
 }
 
 impl<'a> PubSubSubcommand<'a> {
@@ -458,24 +959,43 @@ impl<'a> PubSubSubcommand<'a> {
             Self::Channels(pattern) => {
                 let pattern = pattern.unwrap_or("*");
 
-                let c = channels.get_channels(pattern);
-                let c: Vec<&str> = c.iter().map(|s| &s[..]).collect();
-                if c.is_empty() {
-                    Reponse::Valid("(empty set or list)".to_string())
-                } else {
-                    Reponse::Valid(vec_to_string(&c))
-                }
+                let c = channels
+                    .get_channels(pattern)
+                    .into_iter()
+                    .map(|channel| RespValue::BulkString(channel.into_bytes()))
+                    .collect();
+
+                Reponse::Valid(RespValue::Array(c))
             }
             Self::NumSub(channels_to_count) => {
                 let mut r = Vec::new();
                 for channel in channels_to_count {
-                    r.push(channel.to_string());
+                    r.push(RespValue::BulkString(channel.as_bytes().to_vec()));
                     let count = channels.subcriptors_number(channel);
-                    r.push(count.to_string());
+                    r.push(RespValue::Integer(count as i64));
                 }
 
-                let r: Vec<&str> = r.iter().map(|s| &s[..]).collect();
-                Reponse::Valid(vec_to_string(&r))
+                Reponse::Valid(RespValue::Array(r))
+            }
+            Self::NumPat => Reponse::Valid(RespValue::Integer(channels.patterns_number() as i64)),
+            Self::ShardChannels(pattern) => {
+                let c = channels
+                    .get_shard_channels(pattern.unwrap_or("*"))
+                    .into_iter()
+                    .map(|channel| RespValue::BulkString(channel.into_bytes()))
+                    .collect();
+
+                Reponse::Valid(RespValue::Array(c))
+            }
+            Self::ShardNumSub(channels_to_count) => {
+                let mut r = Vec::new();
+                for channel in channels_to_count {
+                    r.push(RespValue::BulkString(channel.as_bytes().to_vec()));
+                    let count = channels.shard_subscriptors_number(channel);
+                    r.push(RespValue::Integer(count as i64));
+                }
+
+                Reponse::Valid(RespValue::Array(r))
             }
         }
     }
@@ -491,12 +1011,20 @@ impl<'a> Display for PubSubSubcommand<'a> {
             PubSubSubcommand::NumSub(channels) => {
                 write!(f, "numsub channels: {}", vec_to_string(channels))
             }
+            PubSubSubcommand::NumPat => write!(f, "numpat"),
+            PubSubSubcommand::ShardChannels(pattern) => {
+                write!(f, "shardchannels pattern: {}", pattern.unwrap_or("*"))
+            }
+            PubSubSubcommand::ShardNumSub(channels) => {
+                write!(f, "shardnumsub channels: {}", vec_to_string(channels))
+            }
         }
     }
 }
 
 pub enum PublisherRequest<'a> {
     Publish(&'a str, &'a str),
+    SPublish(&'a str, &'a str),
     PubSub(PubSubSubcommand<'a>),
 }
 
@@ -504,10 +1032,14 @@ impl<'a> PublisherRequest<'a> {
     pub fn execute(self, channels: &mut Channels) -> Reponse {
         match self {
             Self::Publish(chanel, msg) => {
-                let message = vec_to_string(&[chanel, msg]);
-                let subscribers = channels.send(chanel, &message);
+                let subscribers = channels.publish(chanel, msg);
 
-                Reponse::Valid(subscribers.to_string())
+                Reponse::Valid(RespValue::Integer(subscribers as i64))
+            }
+            Self::SPublish(chanel, msg) => {
+                let subscribers = channels.spublish(chanel, msg);
+
+                Reponse::Valid(RespValue::Integer(subscribers as i64))
             }
             Self::PubSub(pub_sub_command) => pub_sub_command.execute(channels),
         }
@@ -520,60 +1052,467 @@ impl<'a> Display for PublisherRequest<'a> {
             PublisherRequest::Publish(chanel, msg) => {
                 write!(f, "Publish - channel: {} - message: {}", chanel, msg)
             }
+            PublisherRequest::SPublish(chanel, msg) => {
+                write!(f, "SPublish - channel: {} - message: {}", chanel, msg)
+            }
             PublisherRequest::PubSub(pub_sub_command) => write!(f, "PubSub {}", pub_sub_command),
         }
     }
 }
 
+pub enum TransactionRequest<'a> {
+    Multi,
+    Exec,
+    Discard,
+    Watch(Vec<&'a [u8]>),
+    Unwatch,
+}
+
+impl<'a> Display for TransactionRequest<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TransactionRequest::Multi => write!(f, "Multi"),
+            TransactionRequest::Exec => write!(f, "Exec"),
+            TransactionRequest::Discard => write!(f, "Discard"),
+            TransactionRequest::Watch(keys) => write!(f, "Watch - Keys: {}", vec_to_string(keys)),
+            TransactionRequest::Unwatch => write!(f, "Unwatch"),
+        }
+    }
+}
+
+pub enum AclRequest<'a> {
+    SetUser(&'a str, Vec<&'a str>),
+    GetUser(&'a str),
+    DelUser(Vec<&'a str>),
+    List,
+    Users,
+    WhoAmI,
+    Cat(Option<&'a str>),
+    Save,
+    Load,
+}
+
+impl<'a> AclRequest<'a> {
+    /// Executes the request on the users of conf, for a client authenticated as user.
+    pub fn execute(self, conf: &ServerConf, user: &str) -> Reponse {
+        let users = conf.acl();
+        let bulk = |value: &str| RespValue::BulkString(value.as_bytes().to_vec());
+        let ok = || RespValue::SimpleString("OK".to_string());
+
+        let result = match self {
+            AclRequest::SetUser(name, rules) => users.set_user(name, &rules).map(|_| ok()),
+            AclRequest::GetUser(name) => Ok(match users.get_user(name) {
+                Some(found) => RespValue::Array(vec![
+                    bulk("flags"),
+                    RespValue::Array(found.flags().iter().map(|flag| bulk(flag)).collect()),
+                    bulk("passwords"),
+                    RespValue::Array(found.passwords().iter().map(|hash| bulk(hash)).collect()),
+                    bulk("commands"),
+                    bulk(&found.commands()),
+                    bulk("keys"),
+                    bulk(&found.keys()),
+                    bulk("channels"),
+                    bulk(&found.channels()),
+                ]),
+                None => RespValue::Nil,
+            }),
+            AclRequest::DelUser(names) => users
+                .del_users(&names)
+                .map(|deleted| RespValue::Integer(deleted as i64)),
+            AclRequest::List => Ok(RespValue::Array(
+                users.list().iter().map(|line| bulk(line)).collect(),
+            )),
+            AclRequest::Users => Ok(RespValue::Array(
+                users.users().iter().map(|name| bulk(name)).collect(),
+            )),
+            AclRequest::WhoAmI => Ok(bulk(user)),
+            AclRequest::Cat(None) => Ok(RespValue::Array(
+                CATEGORIES.iter().map(|category| bulk(category)).collect(),
+            )),
+            AclRequest::Cat(Some(category)) => acl::commands_of(&category.to_ascii_lowercase())
+                .map(|commands| RespValue::Array(commands.iter().map(|c| bulk(c)).collect())),
+            AclRequest::Save => match conf.aclfile() {
+                Some(aclfile) => users.save(&aclfile).map(|_| ok()),
+                None => Err(acl::AclError::NoAclFile),
+            },
+            AclRequest::Load => match conf.aclfile() {
+                Some(aclfile) => users.load(&aclfile).map(|_| ok()),
+                None => Err(acl::AclError::NoAclFile),
+            },
+        };
+
+        match result {
+            Ok(value) => Reponse::Valid(value),
+            Err(error) => Reponse::Error(error.to_string()),
+        }
+    }
+}
+
+impl<'a> Display for AclRequest<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            AclRequest::SetUser(name, _) => write!(f, "Acl setuser - user: {}", name),
+            AclRequest::GetUser(name) => write!(f, "Acl getuser - user: {}", name),
+            AclRequest::DelUser(names) => write!(f, "Acl deluser - users: {}", names.join(" ")),
+            AclRequest::List => write!(f, "Acl list"),
+            AclRequest::Users => write!(f, "Acl users"),
+            AclRequest::WhoAmI => write!(f, "Acl whoami"),
+            AclRequest::Cat(category) => {
+                write!(f, "Acl cat - category: {}", category.unwrap_or("*"))
+            }
+            AclRequest::Save => write!(f, "Acl save"),
+            AclRequest::Load => write!(f, "Acl load"),
+        }
+    }
+}
+
 pub enum Query<'a> {
     Flushdb(),
+    Flushall(),
+    Select(usize),
+    Swapdb(usize, usize),
+    Move(&'a [u8], usize),
+    Bgrewriteaof(),
+    Save(),
+    Bgsave(),
+    Lastsave(),
+    DebugReload(),
     Dbsize(),
-    Copy(&'a str, &'a str),
-    Del(&'a str),
-    Exists(&'a str),
-    Expire(&'a str, i64),
-    ExpireAt(&'a str, i64),
-    Keys(&'a str),
-    Persist(&'a str),
-    Rename(&'a str, &'a str),
-    Sort(&'a str, SortFlags<'a>),
-    Ttl(&'a str),
-    Type(&'a str),
-    Get(&'a str),
-    Append(&'a str, &'a str),
-    Incrby(&'a str, i32),
-    Decrby(&'a str, i32),
-    Getdel(&'a str),
-    Getset(&'a str, &'a str),
-    Set(&'a str, &'a str),
-    Strlen(&'a str),
-    Mset(Vec<&'a str>),
-    Mget(Vec<&'a str>),
-    Lindex(&'a str, i32),
-    Llen(&'a str),
-    Lpop(&'a str),
-    Lpush(&'a str, Vec<&'a str>),
-    Lpushx(&'a str, Vec<&'a str>),
-    Lrange(&'a str, i32, i32),
-    Lrem(&'a str, i32, &'a str),
-    Lset(&'a str, usize, &'a str),
-    Rpop(&'a str),
-    Rpush(&'a str, Vec<&'a str>),
-    Rpushx(&'a str, Vec<&'a str>),
-    Sadd(&'a str, Vec<&'a str>),
-    Sismember(&'a str, &'a str),
-    Scard(&'a str),
-    Smembers(&'a str),
-    Srem(&'a str, Vec<&'a str>),
+    Copy(&'a [u8], &'a [u8]),
+    Del(&'a [u8]),
+    Exists(&'a [u8]),
+    Expire(&'a [u8], i64, ExpireFlags),
+    ExpireAt(&'a [u8], i64, ExpireFlags),
+    Pexpire(&'a [u8], i64, ExpireFlags),
+    PexpireAt(&'a [u8], i64, ExpireFlags),
+    Keys(&'a [u8]),
+    Persist(&'a [u8]),
+    Rename(&'a [u8], &'a [u8]),
+    Sort(&'a [u8], SortFlags<'a>),
+    Ttl(&'a [u8]),
+    Pttl(&'a [u8]),
+    Expiretime(&'a [u8]),
+    Pexpiretime(&'a [u8]),
+    Type(&'a [u8]),
+    Get(&'a [u8]),
+    Append(&'a [u8], &'a [u8]),
+    Incrby(&'a [u8], i32),
+    Decrby(&'a [u8], i32),
+    Getdel(&'a [u8]),
+    Getset(&'a [u8], &'a [u8]),
+    Set(&'a [u8], &'a [u8], SetFlags),
+    Setex(&'a [u8], i64, &'a [u8]),
+    Psetex(&'a [u8], i64, &'a [u8]),
+    Setnx(&'a [u8], &'a [u8]),
+    Getex(&'a [u8], Option<KeyExpiry>),
+    Strlen(&'a [u8]),
+    Mset(Vec<&'a [u8]>),
+    Mget(Vec<&'a [u8]>),
+    Lindex(&'a [u8], i32),
+    Llen(&'a [u8]),
+    Lpop(&'a [u8]),
+    Lpush(&'a [u8], Vec<&'a [u8]>),
+    Lpushx(&'a [u8], Vec<&'a [u8]>),
+    Lrange(&'a [u8], i32, i32),
+    Lrem(&'a [u8], i32, &'a [u8]),
+    Lset(&'a [u8], usize, &'a [u8]),
+    Rpop(&'a [u8]),
+    Rpush(&'a [u8], Vec<&'a [u8]>),
+    Rpushx(&'a [u8], Vec<&'a [u8]>),
+    Sadd(&'a [u8], Vec<&'a [u8]>),
+    Sismember(&'a [u8], &'a [u8]),
+    Scard(&'a [u8]),
+    Smembers(&'a [u8]),
+    Srem(&'a [u8], Vec<&'a [u8]>),
+    Hset(&'a [u8], Vec<&'a [u8]>),
+    Hsetnx(&'a [u8], &'a [u8], &'a [u8]),
+    Hget(&'a [u8], &'a [u8]),
+    Hmget(&'a [u8], Vec<&'a [u8]>),
+    Hgetall(&'a [u8]),
+    Hdel(&'a [u8], Vec<&'a [u8]>),
+    Hexists(&'a [u8], &'a [u8]),
+    Hlen(&'a [u8]),
+    Hkeys(&'a [u8]),
+    Hvals(&'a [u8]),
+    Hstrlen(&'a [u8], &'a [u8]),
+    Hincrby(&'a [u8], &'a [u8], i32),
+    Hincrbyfloat(&'a [u8], &'a [u8], f64),
+    Hrandfield(&'a [u8], Option<i32>, bool),
+    Hscan(&'a [u8], u64, Option<&'a [u8]>, usize),
+    Zadd(&'a [u8], ZaddFlags, Vec<(f64, &'a [u8])>),
+    Zscore(&'a [u8], &'a [u8]),
+    Zincrby(&'a [u8], f64, &'a [u8]),
+    Zrank(&'a [u8], &'a [u8]),
+    Zrevrank(&'a [u8], &'a [u8]),
+    Zrange(&'a [u8], ZrangeBy<'a>, bool, Option<(i64, i64)>, bool),
+    Zrem(&'a [u8], Vec<&'a [u8]>),
+    Zremrangebyrank(&'a [u8], i64, i64),
+    Zremrangebyscore(&'a [u8], ScoreBound, ScoreBound),
+    Zremrangebylex(&'a [u8], LexBound<'a>, LexBound<'a>),
+    Zcard(&'a [u8]),
+    Zcount(&'a [u8], ScoreBound, ScoreBound),
+    Zpopmin(&'a [u8], Option<usize>),
+    Zpopmax(&'a [u8], Option<usize>),
+    Zunionstore(&'a [u8], Vec<&'a [u8]>, Option<Vec<f64>>, Aggregate),
+    Zinterstore(&'a [u8], Vec<&'a [u8]>, Option<Vec<f64>>, Aggregate),
+    Zdiffstore(&'a [u8], Vec<&'a [u8]>),
 }
 
 impl<'a> Query<'a> {
+    /// Returns true if the query can modify the data, so it has to be logged in the
+    /// append only file.
+    pub fn is_write(&self) -> bool {
+        match self {
+            Query::Flushdb() | Query::Flushall() | Query::Swapdb(_, _) => true,
+            _ => !self.written_keys().is_empty(),
+        }
+    }
+
+    /// Returns true if the query can take more memory, so once maxmemory is reached
+    /// keys have to be evicted before running it, or it fails. Writes that only
+    /// remove data are always allowed.
+    pub fn uses_memory(&self) -> bool {
+        match self {
+            Query::Del(_)
+            | Query::Getdel(_)
+            | Query::Persist(_)
+            | Query::Expire(_, _, _)
+            | Query::ExpireAt(_, _, _)
+            | Query::Pexpire(_, _, _)
+            | Query::PexpireAt(_, _, _)
+            | Query::Rename(_, _)
+            | Query::Move(_, _)
+            | Query::Lpop(_)
+            | Query::Rpop(_)
+            | Query::Lrem(_, _, _)
+            | Query::Srem(_, _)
+            | Query::Hdel(_, _)
+            | Query::Zrem(_, _)
+            | Query::Zremrangebyrank(_, _, _)
+            | Query::Zremrangebyscore(_, _, _)
+            | Query::Zremrangebylex(_, _, _)
+            | Query::Zpopmin(_, _)
+            | Query::Zpopmax(_, _)
+            | Query::Flushdb()
+            | Query::Flushall()
+            | Query::Swapdb(_, _) => false,
+            _ => self.is_write(),
+        }
+    }
+
+    /// Returns the command to log in the append only file for the query parsed from
+    /// command, or None if it isn't a write. Relative expire times are logged as
+    /// absolute ones in milliseconds, so replaying the file later gives the same result.
+    /// The NX, XX, GT and LT conditions are kept, as replaying them on the same data
+    /// takes the same decision.
+    pub fn aof_command(&self, command: &[Vec<u8>]) -> Option<Command> {
+        if !self.is_write() {
+            return None;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        let expire_at = |expiry: KeyExpiry| match expiry.unix_ms(now) {
+            Ok(Some(ms)) => Some(ms.to_string().into_bytes()),
+            _ => None,
+        };
+
+        let logged = match *self {
+            Query::Expire(key, seconds, _) => {
+                let ms = now.saturating_add(seconds.saturating_mul(1000));
+                let mut logged = vec![b"pexpireat".to_vec(), key.to_vec()];
+                logged.push(ms.to_string().into_bytes());
+                logged.extend_from_slice(&command[3..]);
+                logged
+            }
+            Query::Pexpire(key, ms, _) => {
+                let ms = now.saturating_add(ms);
+                let mut logged = vec![b"pexpireat".to_vec(), key.to_vec()];
+                logged.push(ms.to_string().into_bytes());
+                logged.extend_from_slice(&command[3..]);
+                logged
+            }
+            Query::Set(key, value, flags) => {
+                let mut logged = vec![b"set".to_vec(), key.to_vec(), value.to_vec()];
+                if flags.nx {
+                    logged.push(b"nx".to_vec());
+                }
+                if flags.xx {
+                    logged.push(b"xx".to_vec());
+                }
+                match flags.expiry {
+                    Some(KeyExpiry::KeepTtl) => logged.push(b"keepttl".to_vec()),
+                    Some(expiry) => {
+                        logged.push(b"pxat".to_vec());
+                        logged.push(expire_at(expiry)?);
+                    }
+                    None => {}
+                }
+                logged
+            }
+            Query::Setex(key, seconds, value) => vec![
+                b"set".to_vec(),
+                key.to_vec(),
+                value.to_vec(),
+                b"pxat".to_vec(),
+                expire_at(KeyExpiry::Ex(seconds))?,
+            ],
+            Query::Psetex(key, ms, value) => vec![
+                b"set".to_vec(),
+                key.to_vec(),
+                value.to_vec(),
+                b"pxat".to_vec(),
+                expire_at(KeyExpiry::Px(ms))?,
+            ],
+            Query::Getex(key, Some(KeyExpiry::Persist)) => {
+                vec![b"persist".to_vec(), key.to_vec()]
+            }
+            Query::Getex(key, Some(expiry)) => {
+                vec![b"pexpireat".to_vec(), key.to_vec(), expire_at(expiry)?]
+            }
+            _ => command.to_vec(),
+        };
+
+        Some(logged)
+    }
+
+    /// Returns the keys the query modifies when it succeeds, so watchers of those
+    /// keys can be told about it.
+    pub fn written_keys(&self) -> Vec<&'a [u8]> {
+        match self {
+            Query::Expire(key, _, _)
+            | Query::ExpireAt(key, _, _)
+            | Query::Pexpire(key, _, _)
+            | Query::PexpireAt(key, _, _)
+            | Query::Persist(key)
+            | Query::Append(key, _)
+            | Query::Incrby(key, _)
+            | Query::Decrby(key, _)
+            | Query::Getdel(key)
+            | Query::Getset(key, _)
+            | Query::Set(key, _, _)
+            | Query::Setex(key, _, _)
+            | Query::Psetex(key, _, _)
+            | Query::Setnx(key, _)
+            | Query::Getex(key, Some(_))
+            | Query::Copy(_, key)
+            | Query::Move(key, _)
+            | Query::Del(key)
+            | Query::Lpop(key)
+            | Query::Lpush(key, _)
+            | Query::Lpushx(key, _)
+            | Query::Lrem(key, _, _)
+            | Query::Lset(key, _, _)
+            | Query::Rpop(key)
+            | Query::Rpush(key, _)
+            | Query::Rpushx(key, _)
+            | Query::Sadd(key, _)
+            | Query::Srem(key, _)
+            | Query::Hset(key, _)
+            | Query::Hsetnx(key, _, _)
+            | Query::Hdel(key, _)
+            | Query::Hincrby(key, _, _)
+            | Query::Hincrbyfloat(key, _, _)
+            | Query::Zadd(key, _, _)
+            | Query::Zincrby(key, _, _)
+            | Query::Zrem(key, _)
+            | Query::Zremrangebyrank(key, _, _)
+            | Query::Zremrangebyscore(key, _, _)
+            | Query::Zremrangebylex(key, _, _)
+            | Query::Zpopmin(key, _)
+            | Query::Zpopmax(key, _)
+            | Query::Zunionstore(key, _, _, _)
+            | Query::Zinterstore(key, _, _, _)
+            | Query::Zdiffstore(key, _) => vec![*key],
+            Query::Rename(old_key, new_key) => vec![*old_key, *new_key],
+            Query::Mset(params) => params.iter().step_by(2).copied().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the keyspace events of the query when it succeeds: the class, the
+    /// name Redis gives the event and the key of each one.
+    pub fn keyspace_events(&self) -> Vec<(EventClass, &'static str, &'a [u8])> {
+        let (class, event) = match self {
+            Query::Expire(_, _, _)
+            | Query::ExpireAt(_, _, _)
+            | Query::Pexpire(_, _, _)
+            | Query::PexpireAt(_, _, _)
+            | Query::Getex(_, Some(_)) => (EventClass::Generic, "expire"),
+            Query::Persist(_) => (EventClass::Generic, "persist"),
+            Query::Del(_) | Query::Getdel(_) => (EventClass::Generic, "del"),
+            Query::Copy(_, _) => (EventClass::Generic, "copy_to"),
+            Query::Move(_, _) => (EventClass::Generic, "move_from"),
+            Query::Rename(old_key, new_key) => {
+                return vec![
+                    (EventClass::Generic, "rename_from", *old_key),
+                    (EventClass::Generic, "rename_to", *new_key),
+                ]
+            }
+            Query::Append(_, _) => (EventClass::String, "append"),
+            Query::Incrby(_, _) => (EventClass::String, "incrby"),
+            Query::Decrby(_, _) => (EventClass::String, "decrby"),
+            Query::Getset(_, _)
+            | Query::Set(_, _, _)
+            | Query::Setex(_, _, _)
+            | Query::Psetex(_, _, _)
+            | Query::Setnx(_, _)
+            | Query::Mset(_) => (EventClass::String, "set"),
+            Query::Lpush(_, _) | Query::Lpushx(_, _) => (EventClass::List, "lpush"),
+            Query::Rpush(_, _) | Query::Rpushx(_, _) => (EventClass::List, "rpush"),
+            Query::Lpop(_) => (EventClass::List, "lpop"),
+            Query::Rpop(_) => (EventClass::List, "rpop"),
+            Query::Lrem(_, _, _) => (EventClass::List, "lrem"),
+            Query::Lset(_, _, _) => (EventClass::List, "lset"),
+            Query::Sadd(_, _) => (EventClass::Set, "sadd"),
+            Query::Srem(_, _) => (EventClass::Set, "srem"),
+            Query::Hset(_, _) | Query::Hsetnx(_, _, _) => (EventClass::Hash, "hset"),
+            Query::Hdel(_, _) => (EventClass::Hash, "hdel"),
+            Query::Hincrby(_, _, _) => (EventClass::Hash, "hincrby"),
+            Query::Hincrbyfloat(_, _, _) => (EventClass::Hash, "hincrbyfloat"),
+            Query::Zadd(_, _, _) => (EventClass::SortedSet, "zadd"),
+            Query::Zincrby(_, _, _) => (EventClass::SortedSet, "zincr"),
+            Query::Zrem(_, _) => (EventClass::SortedSet, "zrem"),
+            Query::Zremrangebyrank(_, _, _) => (EventClass::SortedSet, "zremrangebyrank"),
+            Query::Zremrangebyscore(_, _, _) => (EventClass::SortedSet, "zremrangebyscore"),
+            Query::Zremrangebylex(_, _, _) => (EventClass::SortedSet, "zremrangebylex"),
+            Query::Zpopmin(_, _) => (EventClass::SortedSet, "zpopmin"),
+            Query::Zpopmax(_, _) => (EventClass::SortedSet, "zpopmax"),
+            Query::Zunionstore(_, _, _, _) => (EventClass::SortedSet, "zunionstore"),
+            Query::Zinterstore(_, _, _, _) => (EventClass::SortedSet, "zinterstore"),
+            Query::Zdiffstore(_, _) => (EventClass::SortedSet, "zdiffstore"),
+            _ => return Vec::new(),
+        };
+
+        self.written_keys()
+            .into_iter()
+            .map(|key| (class, event, key))
+            .collect()
+    }
+
     pub fn exec_query(self, db: &mut Database) -> Reponse {
+        if self.uses_memory() {
+            if let Err(err) = db.free_memory() {
+                return Reponse::Error(err.to_string());
+            }
+        }
+
+        let is_write = self.is_write();
+        let written_keys = self.written_keys();
+        let events = self.keyspace_events();
+        db.take_modified();
         let result = match self {
-            Query::ExpireAt(key, seconds) => db.expireat(key, seconds),
-            Query::Expire(key, seconds) => db.expire(key, seconds),
+            Query::ExpireAt(key, seconds, flags) => db.expireat(key, seconds, flags),
+            Query::Expire(key, seconds, flags) => db.expire(key, seconds, flags),
+            Query::Pexpire(key, ms, flags) => db.pexpire(key, ms, flags),
+            Query::PexpireAt(key, ms, flags) => db.pexpireat(key, ms, flags),
             Query::Persist(key) => db.persist(key),
             Query::Ttl(key) => db.ttl(key),
+            Query::Pttl(key) => db.pttl(key),
+            Query::Expiretime(key) => db.expiretime(key),
+            Query::Pexpiretime(key) => db.pexpiretime(key),
             Query::Type(key) => db.get_type(key),
             Query::Append(key, value) => db.append(key, value),
             Query::Incrby(key, incr) => db.incrby(key, incr),
@@ -581,7 +1520,23 @@ impl<'a> Query<'a> {
             Query::Get(key) => db.get(key),
             Query::Getdel(key) => db.getdel(key),
             Query::Getset(key, value) => db.getset(key, value),
-            Query::Set(key, value) => db.set(key, value),
+            Query::Set(key, value, flags) => db.set_with_flags(key, value, flags),
+            Query::Setex(key, seconds, value) => {
+                let flags = SetFlags {
+                    expiry: Some(KeyExpiry::Ex(seconds)),
+                    ..SetFlags::default()
+                };
+                db.set_with_flags(key, value, flags)
+            }
+            Query::Psetex(key, ms, value) => {
+                let flags = SetFlags {
+                    expiry: Some(KeyExpiry::Px(ms)),
+                    ..SetFlags::default()
+                };
+                db.set_with_flags(key, value, flags)
+            }
+            Query::Setnx(key, value) => db.setnx(key, value),
+            Query::Getex(key, expiry) => db.getex(key, expiry),
             Query::Copy(key, to_key) => db.copy(key, to_key),
             Query::Del(key) => db.del(key),
             Query::Exists(key) => db.exists(key),
@@ -606,13 +1561,77 @@ impl<'a> Query<'a> {
             Query::Sismember(set_key, value) => db.sismember(set_key, value),
             Query::Scard(set_key) => db.scard(set_key),
             Query::Flushdb() => db.flushdb(),
+            Query::Flushall() => db.flushall(),
+            Query::Select(index) => db.select(index),
+            Query::Swapdb(index1, index2) => db.swapdb(index1, index2),
+            Query::Move(key, index) => db.move_key(key, index),
+            Query::Bgrewriteaof() => db.bgrewriteaof(),
+            Query::Save() => db.save(),
+            Query::Bgsave() => db.bgsave(),
+            Query::Lastsave() => db.lastsave(),
+            Query::DebugReload() => db.debug_reload(),
             Query::Dbsize() => db.dbsize(),
             Query::Smembers(key) => db.smembers(key),
             Query::Srem(key, vec_str) => db.srem(key, vec_str),
+            Query::Hset(key, params) => db.hset(key, params),
+            Query::Hsetnx(key, field, value) => db.hsetnx(key, field, value),
+            Query::Hget(key, field) => db.hget(key, field),
+            Query::Hmget(key, fields) => db.hmget(key, fields),
+            Query::Hgetall(key) => db.hgetall(key),
+            Query::Hdel(key, fields) => db.hdel(key, fields),
+            Query::Hexists(key, field) => db.hexists(key, field),
+            Query::Hlen(key) => db.hlen(key),
+            Query::Hkeys(key) => db.hkeys(key),
+            Query::Hvals(key) => db.hvals(key),
+            Query::Hstrlen(key, field) => db.hstrlen(key, field),
+            Query::Hincrby(key, field, incr) => db.hincrby(key, field, incr),
+            Query::Hincrbyfloat(key, field, incr) => db.hincrbyfloat(key, field, incr),
+            Query::Hrandfield(key, count, with_values) => db.hrandfield(key, count, with_values),
+            Query::Hscan(key, cursor, pattern, count) => db.hscan(key, cursor, pattern, count),
+            Query::Zadd(key, flags, pairs) => db.zadd(key, flags, pairs),
+            Query::Zscore(key, member) => db.zscore(key, member),
+            Query::Zincrby(key, incr, member) => db.zincrby(key, incr, member),
+            Query::Zrank(key, member) => db.zrank(key, member),
+            Query::Zrevrank(key, member) => db.zrevrank(key, member),
+            Query::Zrange(key, by, rev, limit, with_scores) => {
+                db.zrange(key, by, rev, limit, with_scores)
+            }
+            Query::Zrem(key, members) => db.zrem(key, members),
+            Query::Zremrangebyrank(key, start, stop) => db.zremrangebyrank(key, start, stop),
+            Query::Zremrangebyscore(key, min, max) => db.zremrangebyscore(key, min, max),
+            Query::Zremrangebylex(key, min, max) => db.zremrangebylex(key, min, max),
+            Query::Zcard(key) => db.zcard(key),
+            Query::Zcount(key, min, max) => db.zcount(key, min, max),
+            Query::Zpopmin(key, count) => db.zpopmin(key, count),
+            Query::Zpopmax(key, count) => db.zpopmax(key, count),
+            Query::Zunionstore(destination, keys, weights, aggregate) => {
+                db.zunionstore(destination, keys, weights, aggregate)
+            }
+            Query::Zinterstore(destination, keys, weights, aggregate) => {
+                db.zinterstore(destination, keys, weights, aggregate)
+            }
+            Query::Zdiffstore(destination, keys) => db.zdiffstore(destination, keys),
         };
 
+        // The database tells if the query changed a key, as it saw it holding the lock
+        // of the key. When it didn't, as when SET NX finds the key or SREM doesn't find
+        // the member, watchers aren't told, the changes since the last save don't grow
+        // and there's no event.
+        if db.take_modified() {
+            for key in written_keys {
+                db.signal_modified_key(key);
+                db.update_used_memory(key);
+            }
+            if is_write {
+                db.increment_dirty();
+            }
+            for (class, event, key) in events {
+                db.notify_keyspace_event(class, event, key);
+            }
+        }
+
         match result {
-            Ok(succes) => Reponse::Valid(succes.to_string()),
+            Ok(succes) => Reponse::Valid(RespValue::from(succes)),
             Err(err) => Reponse::Error(err.to_string()),
         }
     }
@@ -621,40 +1640,78 @@ impl<'a> Query<'a> {
 impl<'a> Display for Query<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            Query::Expire(key, seconds) => {
-                write!(f, "Expire - Key: {} - Seconds: {}", key, seconds)
+            Query::Expire(key, seconds, _) => {
+                write!(f, "Expire - Key: {} - Seconds: {}", show(key), seconds)
+            }
+            Query::ExpireAt(key, seconds, _) => {
+                write!(f, "ExpireAt - Key: {} - Seconds: {}", show(key), seconds)
             }
-            Query::ExpireAt(key, seconds) => {
-                write!(f, "ExpireAt - Key: {} - Seconds: {}", key, seconds)
+            Query::Pexpire(key, ms, _) => {
+                write!(f, "Pexpire - Key: {} - Milliseconds: {}", show(key), ms)
             }
-            Query::Persist(key) => write!(f, "Persist - Key: {}", key),
-            Query::Type(key) => write!(f, "Type - Key: {}", key),
-            Query::Ttl(key) => write!(f, "TTL - Key: {}", key),
+            Query::PexpireAt(key, ms, _) => {
+                write!(f, "PexpireAt - Key: {} - Milliseconds: {}", show(key), ms)
+            }
+            Query::Persist(key) => write!(f, "Persist - Key: {}", show(key)),
+            Query::Type(key) => write!(f, "Type - Key: {}", show(key)),
+            Query::Ttl(key) => write!(f, "TTL - Key: {}", show(key)),
+            Query::Pttl(key) => write!(f, "PTTL - Key: {}", show(key)),
+            Query::Expiretime(key) => write!(f, "Expiretime - Key: {}", show(key)),
+            Query::Pexpiretime(key) => write!(f, "Pexpiretime - Key: {}", show(key)),
             Query::Append(key, value) => {
-                write!(f, "Append - Key: {} - Value: {} ", key, value)
+                write!(f, "Append - Key: {} - Value: {} ", show(key), show(value))
+            }
+            Query::Incrby(key, incr) => {
+                write!(f, "Incrby - Key: {} - Increment: {}", show(key), incr)
+            }
+            Query::Decrby(key, incr) => {
+                write!(f, "Decrby - Key: {} - Increment: {}", show(key), incr)
             }
-            Query::Incrby(key, incr) => write!(f, "Incrby - Key: {} - Increment: {}", key, incr),
-            Query::Decrby(key, incr) => write!(f, "Decrby - Key: {} - Increment: {}", key, incr),
-            Query::Get(key) => write!(f, "Get - Key: {}", key),
-            Query::Getdel(key) => write!(f, "Getdel - Key: {}", key),
+            Query::Get(key) => write!(f, "Get - Key: {}", show(key)),
+            Query::Getdel(key) => write!(f, "Getdel - Key: {}", show(key)),
             Query::Getset(key, value) => {
-                write!(f, "Getset - Key: {} - Value: {}", key, value)
+                write!(f, "Getset - Key: {} - Value: {}", show(key), show(value))
             }
 
             Query::Mget(params) => write!(f, "Mget Keys: {}", vec_to_string(params)),
             Query::Mset(params) => write!(f, "Mset pair: {}", vec_to_string(params)),
-            Query::Strlen(key) => write!(f, "Strlen - Key: {}", key),
-            Query::Set(key, value) => {
-                write!(f, "Set - Key: {} - Value: {}", key, value)
+            Query::Strlen(key) => write!(f, "Strlen - Key: {}", show(key)),
+            Query::Set(key, value, _) => {
+                write!(f, "Set - Key: {} - Value: {}", show(key), show(value))
+            }
+            Query::Setex(key, seconds, value) => write!(
+                f,
+                "Setex - Key: {} - Seconds: {} - Value: {}",
+                show(key),
+                seconds,
+                show(value)
+            ),
+            Query::Psetex(key, ms, value) => write!(
+                f,
+                "Psetex - Key: {} - Milliseconds: {} - Value: {}",
+                show(key),
+                ms,
+                show(value)
+            ),
+            Query::Setnx(key, value) => {
+                write!(f, "Setnx - Key: {} - Value: {}", show(key), show(value))
+            }
+            Query::Getex(key, expiry) => {
+                write!(f, "Getex - Key: {} - Expiry: {:?}", show(key), expiry)
             }
             Query::Copy(key, to_key) => {
-                write!(f, "Copy - Key: {} - To_Key: {}", key, to_key)
+                write!(f, "Copy - Key: {} - To_Key: {}", show(key), show(to_key))
             }
-            Query::Del(key) => write!(f, "Del - Key: {}", key),
-            Query::Exists(key) => write!(f, "Exists - Key: {}", key),
-            Query::Keys(pattern) => write!(f, "Keys - Pattern: {}", pattern),
+            Query::Del(key) => write!(f, "Del - Key: {}", show(key)),
+            Query::Exists(key) => write!(f, "Exists - Key: {}", show(key)),
+            Query::Keys(pattern) => write!(f, "Keys - Pattern: {}", show(pattern)),
             Query::Rename(old_key, new_key) => {
-                write!(f, "Rename - Old_Key {} - New_Key {}", old_key, new_key)
+                write!(
+                    f,
+                    "Rename - Old_Key {} - New_Key {}",
+                    show(old_key),
+                    show(new_key)
+                )
             }
             Query::Sort(_key, _sort_flags) => {
                 write!(
@@ -663,41 +1720,65 @@ impl<'a> Display for Query<'a> {
                 )
             }
             Query::Lindex(key, indx) => {
-                write!(f, "Lindex - Key: {} - Index: {}", key, indx)
+                write!(f, "Lindex - Key: {} - Index: {}", show(key), indx)
             }
-            Query::Llen(key) => write!(f, "Llen - Key {}", key),
-            Query::Lpop(key) => write!(f, "Lpop - Key {}", key),
+            Query::Llen(key) => write!(f, "Llen - Key {}", show(key)),
+            Query::Lpop(key) => write!(f, "Lpop - Key {}", show(key)),
             Query::Lpush(key, values) => {
-                write!(f, "Lpush - Key: {} - Value: {}", key, vec_to_string(values))
+                write!(
+                    f,
+                    "Lpush - Key: {} - Value: {}",
+                    show(key),
+                    vec_to_string(values)
+                )
             }
             Query::Lpushx(key, values) => {
                 write!(
                     f,
                     "Lpushx - Key: {} - Value: {}",
-                    key,
+                    show(key),
                     vec_to_string(values)
                 )
             }
             Query::Lrange(key, beg, end) => {
-                write!(f, "Lrange - Key: {} - Begining: {} - End {}", key, beg, end)
+                write!(
+                    f,
+                    "Lrange - Key: {} - Begining: {} - End {}",
+                    show(key),
+                    beg,
+                    end
+                )
             }
             Query::Lrem(key, rem, value) => {
-                write!(f, "Lrem - Key: {} - Rem: {} - Value: {}", key, rem, value)
+                write!(
+                    f,
+                    "Lrem - Key: {} - Rem: {} - Value: {}",
+                    show(key),
+                    rem,
+                    show(value)
+                )
             }
             Query::Lset(key, index, value) => write!(
                 f,
                 "Lset - Key: {} - Index: {} - Value: {}",
-                key, index, value
+                show(key),
+                index,
+                show(value)
             ),
-            Query::Rpop(key) => write!(f, "Rpop - Key: {}", key),
+            Query::Rpop(key) => write!(f, "Rpop - Key: {}", show(key)),
             Query::Rpush(key, value) => {
-                write!(f, "Rpush - Key: {} - Value: {} ", key, vec_to_string(value))
+                write!(
+                    f,
+                    "Rpush - Key: {} - Value: {} ",
+                    show(key),
+                    vec_to_string(value)
+                )
             }
             Query::Rpushx(key, value) => {
                 write!(
                     f,
                     "Rpushx - Key: {} - Value: {} ",
-                    key,
+                    show(key),
                     vec_to_string(value)
                 )
             }
@@ -705,77 +1786,577 @@ impl<'a> Display for Query<'a> {
                 write!(
                     f,
                     "Sadd - Key: {} - Element: {}",
-                    key,
+                    show(key),
                     vec_to_string(elements)
                 )
             }
             Query::Sismember(key, element) => {
-                write!(f, "Sismember - Key: {} - Element: {}", key, element)
+                write!(
+                    f,
+                    "Sismember - Key: {} - Element: {}",
+                    show(key),
+                    show(element)
+                )
             }
-            Query::Scard(key) => write!(f, "Sismember - Key: {}", key),
+            Query::Scard(key) => write!(f, "Sismember - Key: {}", show(key)),
             Query::Flushdb() => write!(f, "Flushdb"),
+            Query::Flushall() => write!(f, "Flushall"),
+            Query::Bgrewriteaof() => write!(f, "Bgrewriteaof"),
+            Query::Save() => write!(f, "Save"),
+            Query::Bgsave() => write!(f, "Bgsave"),
+            Query::Lastsave() => write!(f, "Lastsave"),
+            Query::DebugReload() => write!(f, "Debug reload"),
+            Query::Select(index) => write!(f, "Select - Db: {}", index),
+            Query::Swapdb(index1, index2) => {
+                write!(f, "Swapdb - Index1: {} - Index2: {}", index1, index2)
+            }
+            Query::Move(key, index) => write!(f, "Move - Key: {} - Db: {}", show(key), index),
             Query::Dbsize() => write!(f, "Dbsize"),
-            Query::Smembers(key) => write!(f, "Smembers - Key: {}", key),
+            Query::Smembers(key) => write!(f, "Smembers - Key: {}", show(key)),
             Query::Srem(key, vec_str) => write!(
                 f,
                 "Srem - Key: {} - members: {}",
-                key,
+                show(key),
                 vec_to_string(vec_str)
             ),
+            Query::Hset(key, params) => {
+                write!(
+                    f,
+                    "Hset - Key: {} - pairs: {}",
+                    show(key),
+                    vec_to_string(params)
+                )
+            }
+            Query::Hsetnx(key, field, value) => write!(
+                f,
+                "Hsetnx - Key: {} - Field: {} - Value: {}",
+                show(key),
+                show(field),
+                show(value)
+            ),
+            Query::Hget(key, field) => {
+                write!(f, "Hget - Key: {} - Field: {}", show(key), show(field))
+            }
+            Query::Hmget(key, fields) => {
+                write!(
+                    f,
+                    "Hmget - Key: {} - Fields: {}",
+                    show(key),
+                    vec_to_string(fields)
+                )
+            }
+            Query::Hgetall(key) => write!(f, "Hgetall - Key: {}", show(key)),
+            Query::Hdel(key, fields) => {
+                write!(
+                    f,
+                    "Hdel - Key: {} - Fields: {}",
+                    show(key),
+                    vec_to_string(fields)
+                )
+            }
+            Query::Hexists(key, field) => {
+                write!(f, "Hexists - Key: {} - Field: {}", show(key), show(field))
+            }
+            Query::Hlen(key) => write!(f, "Hlen - Key: {}", show(key)),
+            Query::Hkeys(key) => write!(f, "Hkeys - Key: {}", show(key)),
+            Query::Hvals(key) => write!(f, "Hvals - Key: {}", show(key)),
+            Query::Hstrlen(key, field) => {
+                write!(f, "Hstrlen - Key: {} - Field: {}", show(key), show(field))
+            }
+            Query::Hincrby(key, field, incr) => write!(
+                f,
+                "Hincrby - Key: {} - Field: {} - Increment: {}",
+                show(key),
+                show(field),
+                incr
+            ),
+            Query::Hincrbyfloat(key, field, incr) => write!(
+                f,
+                "Hincrbyfloat - Key: {} - Field: {} - Increment: {}",
+                show(key),
+                show(field),
+                incr
+            ),
+            Query::Hrandfield(key, count, with_values) => write!(
+                f,
+                "Hrandfield - Key: {} - Count: {:?} - Withvalues: {}",
+                show(key),
+                count,
+                with_values
+            ),
+            Query::Hscan(key, cursor, pattern, count) => write!(
+                f,
+                "Hscan - Key: {} - Cursor: {} - Pattern: {} - Count: {}",
+                show(key),
+                cursor,
+                show(pattern.unwrap_or(b"*")),
+                count
+            ),
+            Query::Zadd(key, _, pairs) => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(score, member)| format!("{} {}", score, show(member)))
+                    .collect();
+                write!(f, "Zadd - Key: {} - pairs: {}", show(key), pairs.join(" "))
+            }
+            Query::Zscore(key, member) => {
+                write!(f, "Zscore - Key: {} - Member: {}", show(key), show(member))
+            }
+            Query::Zincrby(key, incr, member) => write!(
+                f,
+                "Zincrby - Key: {} - Increment: {} - Member: {}",
+                show(key),
+                incr,
+                show(member)
+            ),
+            Query::Zrank(key, member) => {
+                write!(f, "Zrank - Key: {} - Member: {}", show(key), show(member))
+            }
+            Query::Zrevrank(key, member) => {
+                write!(
+                    f,
+                    "Zrevrank - Key: {} - Member: {}",
+                    show(key),
+                    show(member)
+                )
+            }
+            Query::Zrange(key, _, rev, _, with_scores) => write!(
+                f,
+                "Zrange - Key: {} - Rev: {} - Withscores: {}",
+                show(key),
+                rev,
+                with_scores
+            ),
+            Query::Zrem(key, members) => {
+                write!(
+                    f,
+                    "Zrem - Key: {} - Members: {}",
+                    show(key),
+                    vec_to_string(members)
+                )
+            }
+            Query::Zremrangebyrank(key, start, stop) => write!(
+                f,
+                "Zremrangebyrank - Key: {} - Start: {} - Stop: {}",
+                show(key),
+                start,
+                stop
+            ),
+            Query::Zremrangebyscore(key, min, max) => write!(
+                f,
+                "Zremrangebyscore - Key: {} - Min: {:?} - Max: {:?}",
+                show(key),
+                min,
+                max
+            ),
+            Query::Zremrangebylex(key, min, max) => write!(
+                f,
+                "Zremrangebylex - Key: {} - Min: {:?} - Max: {:?}",
+                show(key),
+                min,
+                max
+            ),
+            Query::Zcard(key) => write!(f, "Zcard - Key: {}", show(key)),
+            Query::Zcount(key, min, max) => write!(
+                f,
+                "Zcount - Key: {} - Min: {:?} - Max: {:?}",
+                show(key),
+                min,
+                max
+            ),
+            Query::Zpopmin(key, count) => {
+                write!(f, "Zpopmin - Key: {} - Count: {:?}", show(key), count)
+            }
+            Query::Zpopmax(key, count) => {
+                write!(f, "Zpopmax - Key: {} - Count: {:?}", show(key), count)
+            }
+            Query::Zunionstore(destination, keys, _, _) => write!(
+                f,
+                "Zunionstore - Destination: {} - Keys: {}",
+                show(destination),
+                vec_to_string(keys)
+            ),
+            Query::Zinterstore(destination, keys, _, _) => write!(
+                f,
+                "Zinterstore - Destination: {} - Keys: {}",
+                show(destination),
+                vec_to_string(keys)
+            ),
+            Query::Zdiffstore(destination, keys) => write!(
+                f,
+                "Zdiffstore - Destination: {} - Keys: {}",
+                show(destination),
+                vec_to_string(keys)
+            ),
         }
     }
 }
 
-pub fn parse_request(stream: &mut TcpStream) -> Result<String, String> {
-    let mut buf = [0; 512];
-    let mut request_str = String::new();
+/// Takes out of the buffer every complete command in it and returns them, in the
+/// order they arrived.
+///
+/// The bytes of an incomplete command are kept in the buffer until the rest of them
+/// arrive in the following reads, so a command can be split across many reads and a
+/// read can carry many commands.
+///
+/// Error if the buffer doesn't start with a valid command.
+pub fn parse_requests(buffer: &mut Vec<u8>) -> Result<Vec<Command>, String> {
+    let mut commands = Vec::new();
+    let mut used = 0;
 
-    while request_str.is_empty() {
-        match stream.read(&mut buf) {
-            Ok(0) => return Err("EOF".to_string()),
-            Err(_) => return Err("Time Out".to_string()),
-            Ok(bytes_read) => {
-                if let Ok(value) = std::str::from_utf8(&buf[..bytes_read]) {
-                    request_str = value.trim().to_string();
+    loop {
+        match resp::decode(&buffer[used..]) {
+            Ok(Some((command, len))) => {
+                used += len;
+                if !command.is_empty() {
+                    commands.push(command);
                 }
             }
+            Ok(None) => break,
+            Err(error) if commands.is_empty() => return Err(error),
+            Err(_) => break,
         }
     }
 
-    Ok(request_str)
+    buffer.drain(..used);
+    Ok(commands)
 }
 
 pub enum Reponse {
-    Valid(String),
+    Valid(RespValue),
+    Frames(Vec<RespValue>),
     Error(String),
 }
 
 impl Reponse {
-    pub fn respond(self, stream: &mut TcpStream) {
+    /// Returns the reply as a single value, as it goes inside the reply of EXEC.
+    pub fn into_value(self) -> RespValue {
         match self {
-            Reponse::Valid(message) => {
-                if writeln!(stream, "{}", message).is_err() {
-                    println!("Error");
-                }
-            }
-            Reponse::Error(message) => {
-                if writeln!(stream, "Error: {}", message).is_err() {
-                    println!("Error");
-                }
-            }
+            Reponse::Valid(value) => value,
+            Reponse::Frames(values) => RespValue::Array(values),
+            Reponse::Error(message) => RespValue::error(&message),
+        }
+    }
+
+    pub fn respond<W: Write>(self, stream: &mut W) {
+        let message = match self {
+            Reponse::Valid(value) => value.encode(),
+            Reponse::Frames(values) => values.iter().flat_map(|v| v.encode()).collect(),
+            Reponse::Error(message) => RespValue::error(&message).encode(),
         };
+
+        if stream.write_all(&message).is_err() {
+            println!("Error");
+        }
     }
 }
 
-impl<'a> Display for Reponse {
+impl Display for Reponse {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            Reponse::Valid(message) => write!(f, "{}", message),
+            Reponse::Valid(value) => write!(f, "{}", value),
+            Reponse::Frames(values) => {
+                for value in values {
+                    write!(f, "{}", value)?;
+                }
+                Ok(())
+            }
             Reponse::Error(error) => write!(f, "Error: {}", error),
         }
     }
 }
 
-fn vec_to_string(vec: &[&str]) -> String {
-    vec.iter().map(|s| s.to_string() + " ").collect()
+#[doc(hidden)]
+type StoreArguments<'a> = (Vec<&'a [u8]>, Option<Vec<f64>>, Aggregate);
+
+fn parse_expire_flags(args: &[&[u8]]) -> Result<ExpireFlags, RequestError> {
+    let mut flags = ExpireFlags::default();
+    for option in args {
+        let option = option.to_ascii_lowercase();
+        match &option[..] {
+            b"nx" => flags.nx = true,
+            b"xx" => flags.xx = true,
+            b"gt" => flags.gt = true,
+            b"lt" => flags.lt = true,
+            _ => return Err(RequestError::SyntaxError),
+        }
+    }
+
+    if (flags.nx && (flags.xx || flags.gt || flags.lt)) || (flags.gt && flags.lt) {
+        return Err(RequestError::SyntaxError);
+    }
+    Ok(flags)
+}
+
+fn parse_set(args: &[&[u8]]) -> Result<SetFlags, RequestError> {
+    let mut flags = SetFlags::default();
+    let mut i = 0;
+    while i < args.len() {
+        let option = args[i].to_ascii_lowercase();
+        match &option[..] {
+            b"nx" => flags.nx = true,
+            b"xx" => flags.xx = true,
+            b"get" => flags.get = true,
+            _ if flags.expiry.is_some() => return Err(RequestError::SyntaxError),
+            b"keepttl" => flags.expiry = Some(KeyExpiry::KeepTtl),
+            _ => {
+                flags.expiry = Some(parse_key_expiry(&option, args.get(i + 1))?);
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+
+    if flags.nx && flags.xx {
+        return Err(RequestError::SyntaxError);
+    }
+    Ok(flags)
+}
+
+fn parse_getex(args: &[&[u8]]) -> Result<Option<KeyExpiry>, RequestError> {
+    match args {
+        [] => Ok(None),
+        [option] if option.eq_ignore_ascii_case(b"persist") => Ok(Some(KeyExpiry::Persist)),
+        [option, time] => parse_key_expiry(&option.to_ascii_lowercase(), Some(time)).map(Some),
+        _ => Err(RequestError::SyntaxError),
+    }
+}
+
+/// Parses the EX, PX, EXAT or PXAT option, already in lowercase, followed by time.
+#[doc(hidden)]
+fn parse_key_expiry(option: &[u8], time: Option<&&[u8]>) -> Result<KeyExpiry, RequestError> {
+    let expiry: fn(i64) -> KeyExpiry = match option {
+        b"ex" => KeyExpiry::Ex,
+        b"px" => KeyExpiry::Px,
+        b"exat" => KeyExpiry::ExAt,
+        b"pxat" => KeyExpiry::PxAt,
+        _ => return Err(RequestError::SyntaxError),
+    };
+    let time = time.ok_or(RequestError::SyntaxError)?;
+
+    parse_bytes::<i64>(time)
+        .map(expiry)
+        .ok_or(RequestError::ParseError)
+}
+
+fn parse_zadd<'a>(key: &'a [u8], args: &[&'a [u8]]) -> Result<Query<'a>, RequestError> {
+    let mut flags = ZaddFlags::default();
+    let mut args = args;
+    while let Some((option, rest)) = args.split_first() {
+        let option = option.to_ascii_lowercase();
+        match &option[..] {
+            b"nx" => flags.nx = true,
+            b"xx" => flags.xx = true,
+            b"gt" => flags.gt = true,
+            b"lt" => flags.lt = true,
+            b"ch" => flags.ch = true,
+            b"incr" => flags.incr = true,
+            _ => break,
+        }
+        args = rest;
+    }
+
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(RequestError::InvalidNumberOfArguments);
+    }
+    if (flags.nx && (flags.xx || flags.gt || flags.lt))
+        || (flags.gt && flags.lt)
+        || (flags.incr && args.len() > 2)
+    {
+        return Err(RequestError::SyntaxError);
+    }
+
+    let mut pairs = Vec::new();
+    for pair in args.chunks(2) {
+        match parse_score(pair[0]) {
+            Some(score) => pairs.push((score, pair[1])),
+            None => return Err(RequestError::ParseError),
+        }
+    }
+
+    Ok(Query::Zadd(key, flags, pairs))
+}
+
+fn parse_zrange<'a>(
+    key: &'a [u8],
+    min: &'a [u8],
+    max: &'a [u8],
+    args: &[&'a [u8]],
+) -> Result<Query<'a>, RequestError> {
+    let (mut by_score, mut by_lex, mut rev, mut with_scores) = (false, false, false, false);
+    let mut limit = None;
+    let mut i = 0;
+    while i < args.len() {
+        let option = args[i].to_ascii_lowercase();
+        match &option[..] {
+            b"byscore" => by_score = true,
+            b"bylex" => by_lex = true,
+            b"rev" => rev = true,
+            b"withscores" => with_scores = true,
+            b"limit" => {
+                let offset = args
+                    .get(i + 1)
+                    .and_then(|offset| parse_bytes::<i64>(offset));
+                let count = args.get(i + 2).and_then(|count| parse_bytes::<i64>(count));
+                match (offset, count) {
+                    (Some(offset), Some(count)) => limit = Some((offset, count)),
+                    _ => return Err(RequestError::ParseError),
+                }
+                i += 2;
+            }
+            _ => return Err(RequestError::SyntaxError),
+        }
+        i += 1;
+    }
+
+    if (by_score && by_lex) || (limit.is_some() && !by_score && !by_lex) || (by_lex && with_scores)
+    {
+        return Err(RequestError::SyntaxError);
+    }
+
+    // With REV the range is given from the highest to the lowest member.
+    let (min, max) = if rev && (by_score || by_lex) {
+        (max, min)
+    } else {
+        (min, max)
+    };
+
+    let by = if by_score {
+        match (ScoreBound::parse(min), ScoreBound::parse(max)) {
+            (Some(min), Some(max)) => ZrangeBy::Score(min, max),
+            _ => return Err(RequestError::ParseError),
+        }
+    } else if by_lex {
+        match (LexBound::parse(min), LexBound::parse(max)) {
+            (Some(min), Some(max)) => ZrangeBy::Lex(min, max),
+            _ => return Err(RequestError::ParseError),
+        }
+    } else {
+        match (parse_bytes::<i64>(min), parse_bytes::<i64>(max)) {
+            (Some(start), Some(stop)) => ZrangeBy::Index(start, stop),
+            _ => return Err(RequestError::ParseError),
+        }
+    };
+
+    Ok(Query::Zrange(key, by, rev, limit, with_scores))
+}
+
+fn parse_zstore<'a>(
+    numkeys: &[u8],
+    args: &[&'a [u8]],
+    with_options: bool,
+) -> Result<StoreArguments<'a>, RequestError> {
+    let numkeys = match parse_bytes::<usize>(numkeys) {
+        Some(numkeys) if numkeys > 0 => numkeys,
+        _ => return Err(RequestError::ParseError),
+    };
+    if args.len() < numkeys {
+        return Err(RequestError::InvalidNumberOfArguments);
+    }
+
+    let keys = args[..numkeys].to_vec();
+    let mut weights = None;
+    let mut aggregate = Aggregate::Sum;
+    let mut args = &args[numkeys..];
+    while let Some((option, rest)) = args.split_first() {
+        let option = option.to_ascii_lowercase();
+        match &option[..] {
+            b"weights" if with_options && rest.len() >= numkeys => {
+                let parsed: Option<Vec<f64>> = rest[..numkeys]
+                    .iter()
+                    .map(|weight| parse_score(weight))
+                    .collect();
+                weights = Some(parsed.ok_or(RequestError::ParseError)?);
+                args = &rest[numkeys..];
+            }
+            b"aggregate" if with_options && !rest.is_empty() => {
+                let kind = rest[0].to_ascii_lowercase();
+                aggregate = match &kind[..] {
+                    b"sum" => Aggregate::Sum,
+                    b"min" => Aggregate::Min,
+                    b"max" => Aggregate::Max,
+                    _ => return Err(RequestError::SyntaxError),
+                };
+                args = &rest[1..];
+            }
+            _ => return Err(RequestError::SyntaxError),
+        }
+    }
+
+    Ok((keys, weights, aggregate))
+}
+
+fn parse_acl<'a>(command: &'a [Vec<u8>], subcommand: &str, args: &[&'a [u8]]) -> Request<'a> {
+    let args = match to_str_vec(args) {
+        Some(args) => args,
+        None => return Request::Invalid(command, RequestError::NotUtf8),
+    };
+
+    let request = match (subcommand.to_ascii_lowercase().as_str(), &args[..]) {
+        ("setuser", [name, ..]) => AclRequest::SetUser(name, args[1..].to_vec()),
+        ("getuser", [name]) => AclRequest::GetUser(name),
+        ("deluser", [_, ..]) => AclRequest::DelUser(args),
+        ("list", []) => AclRequest::List,
+        ("users", []) => AclRequest::Users,
+        ("whoami", []) => AclRequest::WhoAmI,
+        ("cat", []) => AclRequest::Cat(None),
+        ("cat", [category]) => AclRequest::Cat(Some(category)),
+        ("save", []) => AclRequest::Save,
+        ("load", []) => AclRequest::Load,
+        ("setuser" | "getuser" | "deluser" | "list" | "users" | "whoami" | "cat", _)
+        | ("save" | "load", _) => {
+            return Request::Invalid(command, RequestError::InvalidNumberOfArguments)
+        }
+        _ => return Request::Invalid(command, RequestError::UnknownRequest),
+    };
+
+    Request::Acl(request)
+}
+
+/// Parses the arguments of HELLO: the protocol version, optionally followed by AUTH
+/// with a username and a password.
+fn parse_hello<'a>(command: &'a [Vec<u8>], args: &[&'a [u8]]) -> Request<'a> {
+    let protover = match args.first() {
+        Some(protover) => match parse_bytes::<i64>(protover) {
+            Some(protover) => Some(protover),
+            None => return Request::Invalid(command, RequestError::ParseError),
+        },
+        None => None,
+    };
+
+    match args.get(1..).unwrap_or_default() {
+        [] => Request::Hello(protover, None),
+        [auth, username, password] if auth.eq_ignore_ascii_case(b"auth") => {
+            Request::Hello(protover, Some((username, password)))
+        }
+        _ => Request::Invalid(command, RequestError::SyntaxError),
+    }
+}
+
+/// The reply to each channel or pattern of the subscription commands, with nil
+/// instead of the channel when there was nothing to unsubscribe from.
+fn subscription_reply(kind: &str, channel: Option<&str>, count: usize) -> RespValue {
+    let channel = match channel {
+        Some(channel) => RespValue::BulkString(channel.as_bytes().to_vec()),
+        None => RespValue::Nil,
+    };
+    RespValue::Array(vec![
+        RespValue::BulkString(kind.as_bytes().to_vec()),
+        channel,
+        RespValue::Integer(count as i64),
+    ])
+}
+
+fn vec_to_string<T: AsRef<[u8]>>(vec: &[T]) -> String {
+    vec.iter()
+        .map(|s| show(s.as_ref()).to_string() + " ")
+        .collect()
+}
+
+fn show(bytes: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(bytes)
+}
+
+fn to_str_vec<'a>(args: &[&'a [u8]]) -> Option<Vec<&'a str>> {
+    args.iter().map(|arg| str::from_utf8(arg).ok()).collect()
 }
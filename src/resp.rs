@@ -0,0 +1,494 @@
+use crate::databasehelper::SuccessQuery;
+use crate::server_conf::SuccessServerRequest;
+use core::fmt::{self, Display, Formatter};
+
+/// A command decoded from a request, the name followed by its arguments.
+pub type Command = Vec<Vec<u8>>;
+
+#[doc(hidden)]
+const CRLF: &[u8] = b"\r\n";
+#[doc(hidden)]
+const MAX_MULTIBULK_LEN: i64 = 1024 * 1024;
+#[doc(hidden)]
+const MAX_BULK_LEN: i64 = 512 * 1024 * 1024;
+#[doc(hidden)]
+const MAX_INLINE_LEN: usize = 64 * 1024;
+/// The error codes sent as they are; any other message gets the generic ERR one.
+#[doc(hidden)]
+const ERROR_CODES: [&str; 10] = [
+    "ERR",
+    "WRONGTYPE",
+    "NOPERM",
+    "OOM",
+    "EXECABORT",
+    "WRONGPASS",
+    "NOPROTO",
+    "NOAUTH",
+    "BUSYKEY",
+    "NOSCRIPT",
+];
+
+/// RespValue is the representation of every reply that can be written to a client
+/// following the RESP2 protocol, and the maps and pushes of RESP3 for the clients that
+/// switch to it with HELLO 3.
+///
+#[derive(Debug, PartialEq, Clone)]
+pub enum RespValue {
+    #[doc(hidden)]
+    SimpleString(String),
+    #[doc(hidden)]
+    Error(String),
+    #[doc(hidden)]
+    Integer(i64),
+    #[doc(hidden)]
+    BulkString(Vec<u8>),
+    #[doc(hidden)]
+    Nil,
+    #[doc(hidden)]
+    Array(Vec<RespValue>),
+    /// A RESP3 map, as the reply of HELLO 3.
+    Map(Vec<(RespValue, RespValue)>),
+    /// A RESP3 push, an array that isn't the reply of a command, like the messages
+    /// of the channels.
+    Push(Vec<RespValue>),
+}
+
+impl RespValue {
+    /// Encodes the value with the RESP2 wire format.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let value = RespValue::Integer(4);
+    /// assert_eq!(value.encode(), b":4\r\n".to_vec());
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.encode_into(&mut buffer);
+        buffer
+    }
+
+    #[doc(hidden)]
+    fn encode_into(&self, buffer: &mut Vec<u8>) {
+        match self {
+            RespValue::SimpleString(value) => {
+                buffer.push(b'+');
+                buffer.extend_from_slice(sanitize(value).as_bytes());
+                buffer.extend_from_slice(CRLF);
+            }
+            RespValue::Error(value) => {
+                buffer.push(b'-');
+                buffer.extend_from_slice(sanitize(value).as_bytes());
+                buffer.extend_from_slice(CRLF);
+            }
+            RespValue::Integer(value) => {
+                buffer.extend_from_slice(format!(":{}\r\n", value).as_bytes());
+            }
+            RespValue::BulkString(value) => {
+                buffer.extend_from_slice(format!("${}\r\n", value.len()).as_bytes());
+                buffer.extend_from_slice(value);
+                buffer.extend_from_slice(CRLF);
+            }
+            RespValue::Nil => buffer.extend_from_slice(b"$-1\r\n"),
+            RespValue::Array(values) => {
+                buffer.extend_from_slice(format!("*{}\r\n", values.len()).as_bytes());
+                for value in values {
+                    value.encode_into(buffer);
+                }
+            }
+            RespValue::Map(pairs) => {
+                buffer.extend_from_slice(format!("%{}\r\n", pairs.len()).as_bytes());
+                for (key, value) in pairs {
+                    key.encode_into(buffer);
+                    value.encode_into(buffer);
+                }
+            }
+            RespValue::Push(values) => {
+                buffer.extend_from_slice(format!(">{}\r\n", values.len()).as_bytes());
+                for value in values {
+                    value.encode_into(buffer);
+                }
+            }
+        }
+    }
+
+    /// Turns an array into a push, for RESP3 clients. Any other value is kept.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let value = RespValue::Array(vec![RespValue::Integer(1)]);
+    /// assert_eq!(value.into_push(), RespValue::Push(vec![RespValue::Integer(1)]));
+    /// ```
+    pub fn into_push(self) -> RespValue {
+        match self {
+            RespValue::Array(values) => RespValue::Push(values),
+            value => value,
+        }
+    }
+
+    /// Creates an error reply, adding the generic ERR prefix when the message
+    /// doesn't start with one of the known error codes.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let value = RespValue::error("Value isn't a String");
+    /// assert_eq!(value, RespValue::Error("ERR Value isn't a String".to_string()));
+    /// ```
+    pub fn error(message: &str) -> RespValue {
+        let code = message.split_whitespace().next().unwrap_or("");
+        if ERROR_CODES.contains(&code) {
+            RespValue::Error(message.to_string())
+        } else {
+            RespValue::Error(format!("ERR {}", message))
+        }
+    }
+}
+
+impl Display for RespValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            RespValue::SimpleString(value) => write!(f, "{}", value),
+            RespValue::Error(value) => write!(f, "(error) {}", value),
+            RespValue::Integer(value) => write!(f, "(integer) {}", value),
+            RespValue::BulkString(value) => write!(f, "{}", String::from_utf8_lossy(value)),
+            RespValue::Nil => write!(f, "(Nil)"),
+            RespValue::Map(pairs) => {
+                for (key, value) in pairs {
+                    write!(f, "{}: {} ", key, value)?;
+                }
+                Ok(())
+            }
+            RespValue::Array(values) | RespValue::Push(values) => {
+                if values.is_empty() {
+                    write!(f, "(empty list or set)")
+                } else {
+                    let mut list_string = String::new();
+
+                    for value in values {
+                        list_string.push_str(&value.to_string());
+                        list_string.push(' ');
+                    }
+
+                    write!(f, "{}", list_string)
+                }
+            }
+        }
+    }
+}
+
+impl From<SuccessQuery> for RespValue {
+    fn from(success: SuccessQuery) -> Self {
+        match success {
+            SuccessQuery::Success => RespValue::SimpleString("OK".to_string()),
+            SuccessQuery::Boolean(value) => RespValue::Integer(value as i64),
+            SuccessQuery::Integer(value) => RespValue::Integer(value),
+            SuccessQuery::String(value) => RespValue::BulkString(value),
+            SuccessQuery::List(list) => {
+                RespValue::Array(list.into_iter().map(RespValue::from).collect())
+            }
+            SuccessQuery::Nil => RespValue::Nil,
+        }
+    }
+}
+
+impl From<SuccessServerRequest> for RespValue {
+    fn from(success: SuccessServerRequest) -> Self {
+        match success {
+            SuccessServerRequest::Success => RespValue::SimpleString("OK".to_string()),
+            SuccessServerRequest::String(value) => RespValue::BulkString(value.into_bytes()),
+            SuccessServerRequest::List(list) => {
+                RespValue::Array(list.into_iter().map(RespValue::from).collect())
+            }
+        }
+    }
+}
+
+/// Decodes the first command found in buffer.
+///
+/// Commands can be sent as RESP arrays of bulk strings or as inline commands
+/// (a line of words separated by spaces). Arguments are kept as raw bytes, so
+/// they can hold any binary payload. The command name is lowercased because
+/// commands are case insensitive.
+///
+/// Returns Ok(Some((command, bytes_used))) if there's a complete command in the buffer,
+/// Ok(None) if more bytes are needed and an Err if the buffer doesn't follow the protocol.
+/// An empty command means that the bytes used didn't contain any command, like an empty line.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// let (command, used) = decode(b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n").unwrap().unwrap();
+/// assert_eq!(command, vec![b"get".to_vec(), b"key".to_vec()]);
+/// assert_eq!(used, 22);
+/// ```
+pub fn decode(buffer: &[u8]) -> Result<Option<(Command, usize)>, String> {
+    let decoded = match buffer.first() {
+        None => return Ok(None),
+        Some(b'*') => decode_multibulk(buffer)?,
+        Some(_) => decode_inline(buffer)?,
+    };
+
+    Ok(decoded.map(|(mut command, used)| {
+        if let Some(name) = command.first_mut() {
+            name.make_ascii_lowercase();
+        }
+        (command, used)
+    }))
+}
+
+#[doc(hidden)]
+fn decode_inline(buffer: &[u8]) -> Result<Option<(Command, usize)>, String> {
+    let end = match buffer.iter().position(|&b| b == b'\n') {
+        Some(end) => end,
+        None if buffer.len() > MAX_INLINE_LEN => {
+            return Err("Protocol error: too big inline request".to_string())
+        }
+        None => return Ok(None),
+    };
+
+    let command = buffer[..end]
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_vec())
+        .collect();
+
+    Ok(Some((command, end + 1)))
+}
+
+#[doc(hidden)]
+fn decode_multibulk(buffer: &[u8]) -> Result<Option<(Command, usize)>, String> {
+    let (len, mut pos) = match read_line(buffer, 1)? {
+        Some((line, next)) => (parse_len(line, "multibulk")?, next),
+        None => return Ok(None),
+    };
+
+    if len > MAX_MULTIBULK_LEN {
+        return Err("Protocol error: invalid multibulk length".to_string());
+    }
+
+    let mut command = Vec::new();
+    for _ in 0..len.max(0) {
+        match buffer.get(pos) {
+            None => return Ok(None),
+            Some(b'$') => {}
+            Some(&other) => {
+                return Err(format!(
+                    "Protocol error: expected '$', got '{}'",
+                    other as char
+                ))
+            }
+        }
+
+        let (bulk_len, start) = match read_line(buffer, pos + 1)? {
+            Some((line, next)) => (parse_len(line, "bulk")?, next),
+            None => return Ok(None),
+        };
+
+        if !(0..=MAX_BULK_LEN).contains(&bulk_len) {
+            return Err("Protocol error: invalid bulk length".to_string());
+        }
+
+        let end = start + bulk_len as usize;
+        if buffer.len() < end + CRLF.len() {
+            return Ok(None);
+        }
+
+        if &buffer[end..end + CRLF.len()] != CRLF {
+            return Err("Protocol error: bulk string not terminated by CRLF".to_string());
+        }
+
+        command.push(buffer[start..end].to_vec());
+        pos = end + CRLF.len();
+    }
+
+    Ok(Some((command, pos)))
+}
+
+#[doc(hidden)]
+fn read_line(buffer: &[u8], from: usize) -> Result<Option<(&[u8], usize)>, String> {
+    let tail = match buffer.get(from..) {
+        Some(tail) => tail,
+        None => return Ok(None),
+    };
+
+    match tail.windows(CRLF.len()).position(|w| w == CRLF) {
+        Some(end) => Ok(Some((&tail[..end], from + end + CRLF.len()))),
+        None if tail.len() > MAX_INLINE_LEN => {
+            Err("Protocol error: too big length line".to_string())
+        }
+        None => Ok(None),
+    }
+}
+
+#[doc(hidden)]
+fn parse_len(line: &[u8], kind: &str) -> Result<i64, String> {
+    match std::str::from_utf8(line).map(|s| s.parse::<i64>()) {
+        Ok(Ok(len)) => Ok(len),
+        _ => Err(format!("Protocol error: invalid {} length", kind)),
+    }
+}
+
+#[doc(hidden)]
+fn sanitize(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod resp_test {
+    use super::*;
+
+    const GET_KEY: &[u8] = b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n";
+
+    mod decode_test {
+        use super::*;
+
+        #[test]
+        fn decode_an_array_of_bulk_strings() {
+            let (command, used) = decode(GET_KEY).unwrap().unwrap();
+
+            assert_eq!(command, vec![b"get".to_vec(), b"key".to_vec()]);
+            assert_eq!(used, GET_KEY.len());
+        }
+
+        #[test]
+        fn decode_keeps_spaces_inside_bulk_strings() {
+            let request = b"*3\r\n$3\r\nset\r\n$3\r\nkey\r\n$11\r\nhello world\r\n";
+            let (command, _) = decode(request).unwrap().unwrap();
+
+            assert_eq!(command[2], b"hello world");
+        }
+
+        #[test]
+        fn decode_an_inline_command() {
+            let (command, used) = decode(b"SET key 1\r\n").unwrap().unwrap();
+
+            assert_eq!(
+                command,
+                vec![b"set".to_vec(), b"key".to_vec(), b"1".to_vec()]
+            );
+            assert_eq!(used, 11);
+        }
+
+        #[test]
+        fn decode_keeps_binary_bulk_strings() {
+            let request = b"*3\r\n$3\r\nset\r\n$3\r\nkey\r\n$4\r\n\x00\xff\r\n\r\n";
+            let (command, used) = decode(request).unwrap().unwrap();
+
+            assert_eq!(command[2], b"\x00\xff\r\n");
+            assert_eq!(used, request.len());
+        }
+
+        #[test]
+        fn decode_an_incomplete_command_needs_more_bytes() {
+            for end in 0..GET_KEY.len() {
+                assert!(decode(&GET_KEY[..end]).unwrap().is_none());
+            }
+        }
+
+        #[test]
+        fn decode_only_uses_the_first_command() {
+            let mut request = GET_KEY.to_vec();
+            request.extend_from_slice(GET_KEY);
+
+            let (_, used) = decode(&request).unwrap().unwrap();
+            assert_eq!(used, GET_KEY.len());
+        }
+
+        #[test]
+        fn decode_an_array_without_bulk_strings_fails() {
+            assert!(decode(b"*1\r\n:1\r\n").is_err());
+        }
+
+        #[test]
+        fn decode_an_invalid_length_fails() {
+            assert!(decode(b"*a\r\n").is_err());
+            assert!(decode(b"*1\r\n$-5\r\n").is_err());
+        }
+    }
+
+    mod encode_test {
+        use super::*;
+
+        #[test]
+        fn encode_simple_values() {
+            assert_eq!(
+                RespValue::SimpleString("OK".to_string()).encode(),
+                b"+OK\r\n"
+            );
+            assert_eq!(RespValue::Integer(-2).encode(), b":-2\r\n");
+            assert_eq!(
+                RespValue::BulkString(b"hello".to_vec()).encode(),
+                b"$5\r\nhello\r\n"
+            );
+            assert_eq!(RespValue::Nil.encode(), b"$-1\r\n");
+        }
+
+        #[test]
+        fn encode_a_binary_bulk_string() {
+            assert_eq!(
+                RespValue::BulkString(b"\x00\r\n".to_vec()).encode(),
+                b"$3\r\n\x00\r\n\r\n"
+            );
+        }
+
+        #[test]
+        fn encode_an_array() {
+            let value = RespValue::Array(vec![
+                RespValue::BulkString(b"a".to_vec()),
+                RespValue::Nil,
+                RespValue::Integer(1),
+            ]);
+
+            assert_eq!(value.encode(), b"*3\r\n$1\r\na\r\n$-1\r\n:1\r\n");
+        }
+
+        #[test]
+        fn encode_resp3_maps_and_pushes() {
+            let map = RespValue::Map(vec![(
+                RespValue::BulkString(b"proto".to_vec()),
+                RespValue::Integer(3),
+            )]);
+            let push = RespValue::Array(vec![RespValue::BulkString(b"a".to_vec())]).into_push();
+
+            assert_eq!(map.encode(), b"%1\r\n$5\r\nproto\r\n:3\r\n");
+            assert_eq!(push.encode(), b">1\r\n$1\r\na\r\n");
+        }
+
+        #[test]
+        fn encode_an_error_adds_prefix_once() {
+            assert_eq!(
+                RespValue::error("Value isn't a String").encode(),
+                b"-ERR Value isn't a String\r\n"
+            );
+            assert_eq!(
+                RespValue::error("ERR Unsupported CONFIG parameter: port").encode(),
+                b"-ERR Unsupported CONFIG parameter: port\r\n"
+            );
+            assert_eq!(
+                RespValue::error("NOPERM No permissions to access a key").encode(),
+                b"-NOPERM No permissions to access a key\r\n"
+            );
+            assert_eq!(
+                RespValue::error("DB index is out of range").encode(),
+                b"-ERR DB index is out of range\r\n"
+            );
+        }
+
+        #[test]
+        fn success_query_maps_to_resp_values() {
+            let list =
+                SuccessQuery::List(vec![SuccessQuery::String(b"a".to_vec()), SuccessQuery::Nil]);
+
+            assert_eq!(
+                RespValue::from(list),
+                RespValue::Array(vec![RespValue::BulkString(b"a".to_vec()), RespValue::Nil])
+            );
+            assert_eq!(
+                RespValue::from(SuccessQuery::Boolean(true)),
+                RespValue::Integer(1)
+            );
+            assert_eq!(
+                RespValue::from(SuccessQuery::Success),
+                RespValue::SimpleString("OK".to_string())
+            );
+        }
+    }
+}
@@ -1,22 +1,62 @@
-use crate::channels::Channels;
+use crate::aof::Aof;
+use crate::channels::{message_channel, Channels, MessageSender};
 use crate::client::Client;
+use crate::connection::{ClientClass, Connection, Stream};
 use crate::database::Database;
 use crate::logger::Logger;
+use crate::resp::Command;
 use crate::server_conf::ServerConf;
-use std::net::{TcpListener, TcpStream};
+use crate::tls::{self, TlsStream};
+use crate::worker_pool::WorkerPool;
+use mio::net::TcpListener;
+use mio::{Events, Interest, Poll, Registry, Token, Waker};
+use rustls::ServerConfig;
+use std::collections::{HashMap, HashSet};
+use std::io::{ErrorKind, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
+
+#[doc(hidden)]
+const LISTENER: Token = Token(0);
+#[doc(hidden)]
+const TLS_LISTENER: Token = Token(usize::MAX - 1);
+#[doc(hidden)]
+const WAKER: Token = Token(usize::MAX);
+#[doc(hidden)]
+const EVENTS_CAPACITY: usize = 1024;
+/// How often the connections are checked for the idle timeout.
+#[doc(hidden)]
+const TICK: Duration = Duration::from_millis(250);
+#[doc(hidden)]
+const MAX_CLIENTS_ERROR: &[u8] = b"-ERR max number of clients reached\r\n";
+#[doc(hidden)]
+const PROTECTED_MODE_ERROR: &[u8] = b"-DENIED Running in protected mode because no password is set: only loopback connections are accepted. Set requirepass or disable protected-mode.\r\n";
+
+/// What a worker gives back after executing the commands of a connection: the
+/// client, its replies and if it's still alive, or None if it panicked.
+#[doc(hidden)]
+type Outcome = (Token, Option<(Client, Vec<u8>, bool)>);
 
 /// Server is the one in charge of distribute and share the resources for
 /// each client.
 ///
+/// A single thread waits for the events of every connection and does all the reading
+/// and writing, without blocking; the commands read are executed by a fixed pool of
+/// workers, so idle clients only cost their buffers.
 pub struct Server {
     #[doc(hidden)]
     database: Database,
     #[doc(hidden)]
     listener: TcpListener,
     #[doc(hidden)]
+    tls_listener: Option<(TcpListener, Arc<ServerConfig>)>,
+    #[doc(hidden)]
+    poll: Poll,
+    #[doc(hidden)]
+    waker: Arc<Waker>,
+    #[doc(hidden)]
     config: ServerConf,
     #[doc(hidden)]
     next_id: Arc<Mutex<u32>>,
@@ -37,7 +77,51 @@ impl Server {
     /// timeout = 0 -> a number
     /// dbfilename = dump.txt -> an existing file
     /// logfile = lf.log -> it can be a non-existing file
+    /// databases = 16 -> a number
+    /// shards = 16 -> a number
+    /// maxclients = 10000 -> a number
+    /// requirepass = -> the password clients give with AUTH, it can be empty
+    /// protected-mode = yes -> yes or no
+    /// aclfile = -> the file the users are saved to, it can be empty
+    /// tls-port = 0 -> a number, 0 for no TLS
+    /// tls-cert-file = -> the certificate of the server, in PEM
+    /// tls-key-file = -> the private key of tls-cert-file, in PEM
+    /// tls-ca-cert-file = -> the certificates of the CAs of the clients, in PEM
+    /// tls-auth-clients = yes -> yes, optional or no
+    /// worker-threads = 4 -> a number
+    /// appendonly = no -> yes or no
+    /// appendfilename = appendonly.aof -> it can be a non-existing file
+    /// appendfsync = everysec -> always, everysec or no
+    /// save = 3600 1 300 100 -> pairs of seconds and changes, it can be empty
+    /// dbformat = text -> text or rdb
+    /// maxmemory = 0 -> bytes, it can have a unit: kb, mb or gb
+    /// maxmemory-policy = noeviction -> noeviction, allkeys-lru, volatile-lru,
+    ///     allkeys-lfu, volatile-lfu, allkeys-random, volatile-random or volatile-ttl
+    /// notify-keyspace-events = -> the letters of the events to publish, it can be empty
+    /// client-output-buffer-limit = pubsub 32mb 8mb 60 -> groups of normal, replica
+    ///     or pubsub, a hard limit, a soft limit and its seconds
     /// ```
+    /// With appendonly = yes every write is logged in appendfilename, and on start the
+    /// data is loaded from there instead of from dbfilename.
+    /// With dbformat = rdb dbfilename is saved in the RDB format of Redis; it's loaded
+    /// in whatever format it has.
+    /// Every database divides its keys in shards pieces, each one with its own lock.
+    /// With requirepass clients can't execute any command until they AUTH; without it,
+    /// protected-mode only accepts clients from the loopback interface.
+    /// The users created with ACL SETUSER are loaded from aclfile, if it exists, and
+    /// saved to it with ACL SAVE.
+    /// With tls-port clients can also connect with TLS to that port, where the server
+    /// presents tls-cert-file. With tls-auth-clients = yes they need a certificate
+    /// signed by tls-ca-cert-file; with optional, only if they send one.
+    /// At most maxclients clients are connected at the same time, and the commands of
+    /// all of them are executed by worker-threads threads.
+    /// With maxmemory keys are evicted following maxmemory-policy once the data takes
+    /// that many bytes, or writes fail if the policy is noeviction; 0 means no limit.
+    /// Clients with more output waiting to be written than client-output-buffer-limit
+    /// allows for their class, like subscribers that don't read fast enough, are
+    /// disconnected.
+    /// With notify-keyspace-events the changes of the keys are published, as Redis
+    /// does, in the `__keyspace@<db>__` and `__keyevent@<db>__` channels.
     /// If it happens returns Ok(server), an Err otherwise.
     /// # Examples
     /// Basic Usage:
@@ -46,16 +130,48 @@ impl Server {
     /// ```
     pub fn new(config_file: &str) -> Result<Server, String> {
         let config = ServerConf::new(config_file)?;
-        let listener = TcpListener::bind(config.addr()).expect("Could not bind");
-        let database = Database::new(config.dbfilename());
+        let listener = std::net::TcpListener::bind(config.addr()).expect("Could not bind");
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let mut listener = TcpListener::from_std(listener);
+        let poll = Poll::new().map_err(|e| e.to_string())?;
+        poll.registry()
+            .register(&mut listener, LISTENER, Interest::READABLE)
+            .map_err(|e| e.to_string())?;
+        let tls_listener = if config.tls_port() != 0 {
+            Some(bind_tls(&config, poll.registry())?)
+        } else {
+            None
+        };
+        let waker = Arc::new(Waker::new(poll.registry(), WAKER).map_err(|e| e.to_string())?);
+        let database = if config.appendonly() {
+            let aof = Aof::open(&config.appendfilename(), config.appendfsync())?;
+            Database::new_with_aof(
+                config.dbfilename(),
+                config.databases(),
+                config.shards(),
+                aof,
+            )
+        } else {
+            Database::new(config.dbfilename(), config.databases(), config.shards())
+        }
+        .map_err(|e| e.to_string())?;
+        database.set_dump_format(config.dbformat());
+        database.set_maxmemory(config.maxmemory(), config.maxmemory_policy());
+        database.run_serializer(config.clone());
         let next_id = Arc::new(Mutex::new(1));
-        let channels = Channels::new();
+        let mut channels = Channels::new();
+        channels.set_waker(waker.clone());
+        database.set_notifications(channels.clone());
+        database.set_notify_keyspace_events(config.notify_keyspace_events());
         let uptime = SystemTime::now();
         let clients = Arc::new(Mutex::new(0));
 
         Ok(Server {
             database,
             listener,
+            tls_listener,
+            poll,
+            waker,
             config,
             next_id,
             channels,
@@ -65,13 +181,20 @@ impl Server {
     }
 
     #[doc(hidden)]
-    fn new_client(&self, stream: TcpStream, id: u32, logger_ref: Arc<Mutex<Logger>>) -> Client {
-        let total_clients = self.clients.clone();
+    fn new_client(&self, id: u32, messages: MessageSender) -> Client {
         let mut clients = self.clients.lock().unwrap();
         *clients += 1;
         drop(clients);
 
-        Client::new(stream, id, total_clients, logger_ref)
+        Client::new(
+            id,
+            self.database.clone(),
+            self.channels.clone(),
+            self.config.clone(),
+            self.uptime,
+            self.clients.clone(),
+            messages,
+        )
     }
 
     #[doc(hidden)]
@@ -83,45 +206,301 @@ impl Server {
         id
     }
 
-    /// Creates a client with all the resources that it needs for each connection listened
-    /// in the litener connected to the port passed in the config file and spawn a thread
-    /// with that client being handled.
+    /// Serves every client connected to the port passed in the config file, until the
+    /// process ends.
+    ///
+    /// Waits for the events of the listener and the connections: it accepts the new
+    /// connections, up to maxclients, reads the commands that arrive and gives them to
+    /// the workers, one batch per client at a time so they're executed in order, and
+    /// writes back the replies and the messages of the subscribed clients. Clients
+    /// that don't send anything for timeout seconds are disconnected.
     ///
     pub fn run(mut self) {
         let mut logger = Logger::new(&self.config.logfile(), self.config.verbose());
         let log_sender = logger.run();
         self.channels.add_logger(log_sender);
-        let logger_ref = Arc::new(Mutex::new(logger));
-
-        for stream in self.listener.incoming() {
-            let logger_ref = logger_ref.clone();
-            match stream {
-                Err(e) => eprintln!("failed: {}", e),
-                Ok(stream) => {
-                    let id = self.get_next_id();
-                    let database = self.database.clone();
-                    let channels = self.channels.clone();
-                    let uptime = self.uptime;
-                    let config = self.config.clone();
-                    let mut client = self.new_client(stream, id, logger_ref);
-
-                    thread::spawn(move || {
-                        client.handle_client(database, channels, uptime, config);
-                    });
+
+        let workers = WorkerPool::new(self.config.worker_threads());
+        let (done_sender, done_receiver) = channel();
+        let mut connections: HashMap<Token, Connection> = HashMap::new();
+        let mut subscribed: HashSet<Token> = HashSet::new();
+        let mut events = Events::with_capacity(EVENTS_CAPACITY);
+        let mut last_tick = Instant::now();
+
+        loop {
+            if let Err(e) = self.poll.poll(&mut events, Some(TICK)) {
+                if e.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                eprintln!("failed: {}", e);
+                return;
+            }
+            logger.set_verbose(self.config.verbose());
+
+            let mut touched = HashSet::new();
+            for event in events.iter() {
+                match event.token() {
+                    LISTENER => self.accept(false, &mut connections, &mut touched),
+                    TLS_LISTENER => self.accept(true, &mut connections, &mut touched),
+                    WAKER => touched.extend(subscribed.iter().copied()),
+                    token => {
+                        if let Some(connection) = connections.get_mut(&token) {
+                            if event.is_readable() {
+                                connection.read();
+                            }
+                            if event.is_writable() {
+                                connection.write();
+                            }
+                            touched.insert(token);
+                        }
+                    }
+                }
+            }
+
+            self.finish_jobs(
+                &done_receiver,
+                &mut connections,
+                &mut subscribed,
+                &mut touched,
+            );
+
+            if last_tick.elapsed() >= TICK {
+                last_tick = Instant::now();
+                self.expire_idle(&mut connections, &mut touched);
+            }
+
+            for token in touched {
+                self.update(
+                    token,
+                    &mut connections,
+                    &mut subscribed,
+                    &workers,
+                    &done_sender,
+                );
+            }
+        }
+    }
+
+    /// Accepts every pending connection of the plain or the TLS listener, or rejects
+    /// it if there are already maxclients clients or protected mode doesn't let it in.
+    /// Both kinds of connections are served the same way once accepted.
+    #[doc(hidden)]
+    fn accept(
+        &self,
+        tls: bool,
+        connections: &mut HashMap<Token, Connection>,
+        touched: &mut HashSet<Token>,
+    ) {
+        let (listener, tls_config) = match (tls, &self.tls_listener) {
+            (false, _) => (&self.listener, None),
+            (true, Some((listener, tls_config))) => (listener, Some(tls_config)),
+            (true, None) => return,
+        };
+
+        loop {
+            let (socket, addr) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) => {
+                    eprintln!("failed: {}", e);
+                    return;
+                }
+            };
+
+            let mut stream = match tls_config {
+                None => Stream::Plain(socket),
+                Some(tls_config) => match TlsStream::new(socket, tls_config.clone()) {
+                    Ok(stream) => Stream::Tls(Box::new(stream)),
+                    Err(e) => {
+                        eprintln!("failed: {}", e);
+                        continue;
+                    }
+                },
+            };
+
+            if !self.config.accepts_from(addr.ip()) {
+                let _ = stream.write(PROTECTED_MODE_ERROR);
+                continue;
+            }
+
+            if connections.len() >= self.config.maxclients() {
+                let _ = stream.write(MAX_CLIENTS_ERROR);
+                continue;
+            }
+
+            let id = self.get_next_id();
+            let token = Token(id as usize);
+            let (messages, receiver) = message_channel();
+            let client = self.new_client(id, messages);
+            let mut connection = Connection::new(stream, client, receiver);
+            if let Err(e) = connection.register(self.poll.registry(), token) {
+                eprintln!("failed: {}", e);
+                self.remove_client(connection);
+                continue;
+            }
+            connections.insert(token, connection);
+            touched.insert(token);
+        }
+    }
+
+    /// Gives back to their connections the clients whose commands the workers
+    /// finished executing.
+    #[doc(hidden)]
+    fn finish_jobs(
+        &self,
+        done_receiver: &Receiver<Outcome>,
+        connections: &mut HashMap<Token, Connection>,
+        subscribed: &mut HashSet<Token>,
+        touched: &mut HashSet<Token>,
+    ) {
+        for (token, outcome) in done_receiver.try_iter() {
+            if let Some(connection) = connections.get_mut(&token) {
+                connection.finish(outcome);
+                if connection.is_subscribed() {
+                    subscribed.insert(token);
+                } else {
+                    subscribed.remove(&token);
                 }
+                touched.insert(token);
+            }
+        }
+    }
+
+    /// Disconnects the clients that didn't send anything for the timeout of the
+    /// config file, if there's one.
+    #[doc(hidden)]
+    fn expire_idle(
+        &self,
+        connections: &mut HashMap<Token, Connection>,
+        touched: &mut HashSet<Token>,
+    ) {
+        let time_out = self.config.time_out();
+        if time_out == 0 {
+            return;
+        }
+
+        let time_out = Duration::from_secs(time_out);
+        for (token, connection) in connections.iter_mut() {
+            if connection.is_idle_for(time_out) {
+                connection.respond_error("Time Out".to_string());
+                touched.insert(*token);
+            }
+        }
+    }
+
+    /// Brings a connection up to date after something happened to it: gives its new
+    /// commands to the workers, writes what it has to write and closes it if it's done.
+    #[doc(hidden)]
+    fn update(
+        &self,
+        token: Token,
+        connections: &mut HashMap<Token, Connection>,
+        subscribed: &mut HashSet<Token>,
+        workers: &WorkerPool,
+        done_sender: &Sender<Outcome>,
+    ) {
+        let connection = match connections.get_mut(&token) {
+            Some(connection) => connection,
+            None => return,
+        };
+
+        if subscribed.contains(&token) {
+            connection.receive_messages();
+        }
+        if let Some((client, commands)) = connection.take_commands() {
+            self.dispatch(token, client, commands, workers, done_sender.clone());
+        }
+        connection.write();
+        if connection.pending_output() > 0 {
+            let class = match subscribed.contains(&token) {
+                true => ClientClass::PubSub,
+                false => ClientClass::Normal,
+            };
+            let limit = self.config.client_output_buffer_limit(class);
+            if connection.enforce_output_limit(limit) {
+                let msg = format!(
+                    "Client closed for overcoming of {} output buffer limits",
+                    class
+                );
+                self.channels.clone().send_logger(token.0 as u32, &msg);
+            }
+        }
+
+        if connection.is_done() {
+            subscribed.remove(&token);
+            if let Some(connection) = connections.remove(&token) {
+                self.remove_client(connection);
             }
+        } else if let Err(e) = connection.update_interest(self.poll.registry(), token) {
+            eprintln!("failed: {}", e);
         }
     }
+
+    /// Executes the commands of client in a worker, that gives the client back when
+    /// it's done and wakes the event loop.
+    #[doc(hidden)]
+    fn dispatch(
+        &self,
+        token: Token,
+        mut client: Client,
+        commands: Vec<Command>,
+        workers: &WorkerPool,
+        done_sender: Sender<Outcome>,
+    ) {
+        let waker = self.waker.clone();
+        workers.execute(move || {
+            let outcome = panic::catch_unwind(AssertUnwindSafe(move || {
+                let alive = client.handle_commands(&commands);
+                let output = client.take_output();
+                (client, output, alive)
+            }));
+
+            let _ = done_sender.send((token, outcome.ok()));
+            if let Err(e) = waker.wake() {
+                eprintln!("Couldn't wake the event loop: {}", e);
+            }
+        });
+    }
+
+    #[doc(hidden)]
+    fn remove_client(&self, connection: Connection) {
+        connection.close(self.poll.registry());
+        let mut clients = self.clients.lock().unwrap();
+        *clients -= 1;
+    }
+}
+
+/// Binds the TLS port and registers it in registry, with the configuration of its
+/// connections.
+#[doc(hidden)]
+fn bind_tls(
+    config: &ServerConf,
+    registry: &Registry,
+) -> Result<(TcpListener, Arc<ServerConfig>), String> {
+    let tls_config = tls::server_config(config)?;
+    let listener = std::net::TcpListener::bind(config.tls_addr()).map_err(|e| e.to_string())?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let mut listener = TcpListener::from_std(listener);
+    registry
+        .register(&mut listener, TLS_LISTENER, Interest::READABLE)
+        .map_err(|e| e.to_string())?;
+
+    Ok((listener, tls_config))
 }
 
 #[cfg(test)]
 mod server_test {
-    use std::io::{BufRead, BufReader, Write};
-    use std::str;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+    use std::convert::TryFrom;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+    use std::{str, thread};
 
     use super::*;
 
-    const ANS_SUCCESS: &str = "Ok\n";
+    const ANS_SUCCESS: &str = "+OK\r\n";
 
     const SET_KEY_1: &str = "set key 1\n";
     const GET_KEY: &str = "get key\n";
@@ -138,42 +517,351 @@ mod server_test {
     }
 
     fn integer_ans(integer: i32) -> String {
-        format!("(integer) {}\n", integer)
+        format!(":{}\r\n", integer)
     }
 
-    fn test_command(client: &mut TcpStream, command: &str, expect: &str) {
-        client
-            .write(command.as_bytes())
-            .expect("Failed to write to server");
+    fn bulk_ans(value: &str) -> String {
+        format!("${}\r\n{}\r\n", value.len(), value)
+    }
+
+    fn resp_command(command: &[&str]) -> String {
+        let mut resp = format!("*{}\r\n", command.len());
+        for arg in command {
+            resp.push_str(&bulk_ans(arg));
+        }
+        resp
+    }
 
+    fn read_reply<R: BufRead>(reader: &mut R) -> String {
         let mut buffer: Vec<u8> = Vec::new();
-        let mut reader = BufReader::new(client);
         reader
             .read_until(b'\n', &mut buffer)
             .expect("Could not read into buffer");
 
-        assert_eq!(str::from_utf8(&buffer).unwrap(), expect);
+        let line = str::from_utf8(&buffer).unwrap().to_string();
+        match line.as_bytes()[0] {
+            b'$' => {
+                let len = line[1..].trim().parse::<i64>().unwrap();
+                if len >= 0 {
+                    let mut bulk = vec![0; len as usize + 2];
+                    reader.read_exact(&mut bulk).unwrap();
+                    return line + str::from_utf8(&bulk).unwrap();
+                }
+                line
+            }
+            b'*' | b'>' | b'%' => {
+                let mut len = line[1..].trim().parse::<i64>().unwrap();
+                if line.starts_with('%') {
+                    len *= 2;
+                }
+                let mut reply = line;
+                for _ in 0..len {
+                    reply.push_str(&read_reply(reader));
+                }
+                reply
+            }
+            _ => line,
+        }
+    }
+
+    fn test_command<S: Read + Write>(client: &mut S, command: &str, expect: &str) {
+        client
+            .write_all(command.as_bytes())
+            .expect("Failed to write to server");
+
+        let mut reader = BufReader::new(client);
+        assert_eq!(read_reply(&mut reader), expect);
+    }
+
+    fn test_command_bytes(client: &mut TcpStream, command: &[u8], expect: &[u8]) {
+        client
+            .write_all(command)
+            .expect("Failed to write to server");
+
+        let mut reply = vec![0; expect.len()];
+        client.read_exact(&mut reply).unwrap();
+        assert_eq!(reply, expect);
+    }
+
+    fn tls_client(port: u16, with_certificate: bool) -> StreamOwned<ClientConnection, TcpStream> {
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter("tls/ca.crt").unwrap() {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let builder = ClientConfig::builder().with_root_certificates(roots);
+        let config = if with_certificate {
+            let certs = CertificateDer::pem_file_iter("tls/client.crt")
+                .unwrap()
+                .map(|cert| cert.unwrap())
+                .collect();
+            let key = PrivateKeyDer::from_pem_file("tls/client.key").unwrap();
+            builder.with_client_auth_cert(certs, key).unwrap()
+        } else {
+            builder.with_no_client_auth()
+        };
+
+        let name = ServerName::try_from("localhost").unwrap();
+        let connection = ClientConnection::new(Arc::new(config), name).unwrap();
+        let socket = TcpStream::connect(("127.0.0.1", port)).expect("Could not connect to server");
+        StreamOwned::new(connection, socket)
+    }
+
+    #[test]
+    fn test_tls() {
+        let config_file = "tls_server_test.conf";
+        let options = "port = 8889\n\
+                       tls-port = 8890\n\
+                       dbfilename = db_dump_tls.txt\n\
+                       save = \n\
+                       tls-cert-file = tls/redis.crt\n\
+                       tls-key-file = tls/redis.key\n\
+                       tls-ca-cert-file = tls/ca.crt";
+        std::fs::write(config_file, options).unwrap();
+        let server = Server::new(config_file);
+        std::fs::remove_file(config_file).unwrap();
+        let server = server.unwrap();
+        thread::spawn(move || server.run());
+
+        let mut client = tls_client(8890, true);
+        test_command(&mut client, "ping\r\n", "+PONG\r\n");
+        let value = "v".repeat(100_000);
+        test_command(
+            &mut client,
+            &resp_command(&["SET", "key", &value]),
+            ANS_SUCCESS,
+        );
+        test_command(&mut client, "get key\r\n", &bulk_ans(&value));
+
+        let mut plain = TcpStream::connect("0.0.0.0:8889").expect("Could not connect to server");
+        test_command(&mut plain, "strlen key\r\n", &integer_ans(100_000));
+
+        let mut anonymous = tls_client(8890, false);
+        let _ = anonymous.write_all(b"ping\r\n");
+        let mut reply = [0; 16];
+        assert!(!matches!(anonymous.read(&mut reply), Ok(read) if read > 0));
     }
 
     #[test]
     fn test() {
         run_server();
         test_strings_commands();
+        test_resp_commands();
+        test_binary_values();
+        test_hash_commands();
+        test_sorted_set_commands();
+        test_transactions();
+        test_databases();
+        test_pipelined_commands();
+        test_big_commands();
         test_two_clients();
         test_multiple_clients();
+        test_publish_subscribe();
+        test_pattern_subscribe();
+        test_keyspace_notifications();
+        test_slow_subscribers_are_disconnected();
+        test_subscription_mode();
+        test_shard_channels();
+        test_monitor();
+        test_idle_clients_are_disconnected();
+        test_maxclients();
+        test_auth();
+        test_acl();
     }
 
     fn test_strings_commands() {
         let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
         test_command(&mut client, APPEND_KEY_HOLA, &integer_ans(4));
         test_command(&mut client, SET_KEY_1, ANS_SUCCESS);
-        test_command(&mut client, GET_KEY, "1\n");
+        test_command(&mut client, GET_KEY, &bulk_ans("1"));
         test_command(&mut client, APPEND_KEY_ADIOS, &integer_ans(6));
-        test_command(&mut client, GET_KEY, "1adios\n");
+        test_command(&mut client, GET_KEY, &bulk_ans("1adios"));
         test_command(&mut client, DEL_KEY, ANS_SUCCESS);
         test_command(&mut client, APPEND_KEY_HOLA, &integer_ans(4));
         test_command(&mut client, APPEND_KEY_ADIOS, &integer_ans(9));
-        test_command(&mut client, GET_KEY, "holaadios\n");
+        test_command(&mut client, GET_KEY, &bulk_ans("holaadios"));
+        test_command(&mut client, FLUSHDB, ANS_SUCCESS);
+    }
+
+    fn test_resp_commands() {
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let set = resp_command(&["SET", "resp_key", "hello world"]);
+        let get = resp_command(&["GET", "resp_key"]);
+        let mget = resp_command(&["MGET", "resp_key", "non_existent"]);
+        let wrong_type = resp_command(&["LLEN", "resp_key"]);
+
+        test_command(&mut client, &resp_command(&["PING"]), "+PONG\r\n");
+        test_command(&mut client, &set, ANS_SUCCESS);
+        test_command(&mut client, &get, &bulk_ans("hello world"));
+        test_command(
+            &mut client,
+            &mget,
+            &format!("*2\r\n{}$-1\r\n", bulk_ans("hello world")),
+        );
+        test_command(&mut client, &wrong_type, "-ERR Value isn't a List\r\n");
+        test_command(
+            &mut client,
+            &resp_command(&["DEL", "resp_key"]),
+            ANS_SUCCESS,
+        );
+    }
+
+    fn test_binary_values() {
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let value: &[u8] = b"\x00\xff\r\n binary value";
+
+        let mut set = b"*3\r\n$3\r\nSET\r\n$10\r\nbinary_key\r\n".to_vec();
+        set.extend_from_slice(format!("${}\r\n", value.len()).as_bytes());
+        set.extend_from_slice(value);
+        set.extend_from_slice(b"\r\n");
+        test_command_bytes(&mut client, &set, ANS_SUCCESS.as_bytes());
+
+        let mut expect = format!("${}\r\n", value.len()).into_bytes();
+        expect.extend_from_slice(value);
+        expect.extend_from_slice(b"\r\n");
+        let get = b"*2\r\n$3\r\nGET\r\n$10\r\nbinary_key\r\n";
+        test_command_bytes(&mut client, get, &expect);
+
+        test_command(&mut client, "del binary_key\n", ANS_SUCCESS);
+    }
+
+    fn test_hash_commands() {
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let hset = resp_command(&["HSET", "hash_key", "field", "value", "count", "1"]);
+        test_command(&mut client, &hset, &integer_ans(2));
+        test_command(&mut client, "hget hash_key field\r\n", &bulk_ans("value"));
+        test_command(&mut client, "hincrby hash_key count 5\r\n", &integer_ans(6));
+        test_command(&mut client, "hget hash_key nofield\r\n", "$-1\r\n");
+        test_command(
+            &mut client,
+            "hdel hash_key field count\r\n",
+            &integer_ans(2),
+        );
+        test_command(&mut client, "hlen hash_key\r\n", &integer_ans(0));
+    }
+
+    fn test_sorted_set_commands() {
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let zadd = resp_command(&["ZADD", "zset_key", "1", "one", "2.5", "two", "3", "three"]);
+        test_command(&mut client, &zadd, &integer_ans(3));
+        test_command(&mut client, "zscore zset_key two\r\n", &bulk_ans("2.5"));
+        test_command(&mut client, "zrevrank zset_key one\r\n", &integer_ans(2));
+        let zrange = "zrange zset_key +inf (1 BYSCORE REV LIMIT 0 1 WITHSCORES\r\n";
+        let reply = format!("*2\r\n{}{}", bulk_ans("three"), bulk_ans("3"));
+        test_command(&mut client, zrange, &reply);
+        test_command(
+            &mut client,
+            "zadd zset_key NX XX 1 one\r\n",
+            "-ERR Syntax error On: zadd zset_key NX XX 1 one\r\n",
+        );
+        test_command(
+            &mut client,
+            "zremrangebyrank zset_key 0 -1\r\n",
+            &integer_ans(3),
+        );
+    }
+
+    fn test_transactions() {
+        let mut client1 = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut client2 = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(&mut client1, "multi\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "set tx_key 1\r\n", "+QUEUED\r\n");
+        test_command(&mut client1, "incrby tx_key 2\r\n", "+QUEUED\r\n");
+        test_command(&mut client2, "get tx_key\r\n", "$-1\r\n");
+        let reply = format!("*2\r\n{}{}", ANS_SUCCESS, integer_ans(3));
+        test_command(&mut client1, "exec\r\n", &reply);
+
+        test_command(&mut client1, "watch tx_key\r\n", ANS_SUCCESS);
+        test_command(&mut client2, "set tx_key 10\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "multi\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "set tx_key 20\r\n", "+QUEUED\r\n");
+        test_command(&mut client1, "exec\r\n", "$-1\r\n");
+        test_command(&mut client1, "get tx_key\r\n", &bulk_ans("10"));
+
+        test_command(&mut client1, "multi\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "set tx_key 30\r\n", "+QUEUED\r\n");
+        test_command(&mut client1, "discard\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "exec\r\n", "-ERR EXEC without MULTI\r\n");
+
+        // Commands that change nothing don't abort the transactions watching the key.
+        test_command(&mut client1, "watch tx_key missing_key\r\n", ANS_SUCCESS);
+        test_command(&mut client2, "set tx_key 40 nx\r\n", "$-1\r\n");
+        test_command(&mut client2, "del missing_key\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "multi\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "set tx_key 50\r\n", "+QUEUED\r\n");
+        test_command(&mut client1, "exec\r\n", &format!("*1\r\n{}", ANS_SUCCESS));
+
+        test_command(&mut client1, "debug reload\r\n", ANS_SUCCESS);
+        test_command(&mut client2, "get tx_key\r\n", &bulk_ans("50"));
+        test_command(&mut client1, "multi\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "debug reload\r\n", "+QUEUED\r\n");
+        test_command(&mut client1, "exec\r\n", &format!("*1\r\n{}", ANS_SUCCESS));
+        std::fs::remove_file("dump.txt").unwrap();
+        test_command(&mut client1, "del tx_key\r\n", ANS_SUCCESS);
+    }
+
+    fn test_databases() {
+        let mut client1 = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut client2 = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(&mut client1, "set db_key 0\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "select 1\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "get db_key\r\n", "$-1\r\n");
+        test_command(
+            &mut client1,
+            "select 16\r\n",
+            "-ERR DB index is out of range\r\n",
+        );
+
+        test_command(&mut client2, "move db_key 1\r\n", &integer_ans(1));
+        test_command(&mut client1, "get db_key\r\n", &bulk_ans("0"));
+        test_command(&mut client2, "swapdb 0 1\r\n", ANS_SUCCESS);
+        test_command(&mut client2, "get db_key\r\n", &bulk_ans("0"));
+        test_command(&mut client1, "get db_key\r\n", "$-1\r\n");
+
+        test_command(&mut client1, "set db_key 1\r\n", ANS_SUCCESS);
+        test_command(&mut client2, "flushall\r\n", ANS_SUCCESS);
+        test_command(&mut client1, "dbsize\r\n", &integer_ans(0));
+        test_command(&mut client2, "dbsize\r\n", &integer_ans(0));
+    }
+
+    fn test_pipelined_commands() {
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut commands = String::new();
+        let mut replies = String::new();
+        for i in 0..10 {
+            let key = format!("pipelined_{}", i);
+            commands.push_str(&resp_command(&["SET", &key, &i.to_string()]));
+            replies.push_str(ANS_SUCCESS);
+        }
+        commands.push_str("get pipelined_9\r\n");
+        replies.push_str(&bulk_ans("9"));
+
+        test_command_bytes(&mut client, commands.as_bytes(), replies.as_bytes());
+
+        let get = resp_command(&["GET", "pipelined_3"]);
+        let (first_half, second_half) = get.split_at(get.len() / 2);
+        client.write_all(first_half.as_bytes()).unwrap();
+        thread::sleep(std::time::Duration::from_millis(100));
+        test_command(&mut client, second_half, &bulk_ans("3"));
+
+        test_command(&mut client, FLUSHDB, ANS_SUCCESS);
+    }
+
+    fn test_big_commands() {
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let value = "v".repeat(100_000);
+        let mut mset = vec!["MSET"];
+        let keys: Vec<String> = (0..50).map(|i| format!("big_{}", i)).collect();
+        for key in keys.iter() {
+            mset.push(key);
+            mset.push(&value);
+        }
+
+        test_command(&mut client, &resp_command(&mset), ANS_SUCCESS);
+        test_command(
+            &mut client,
+            &resp_command(&["GET", "big_49"]),
+            &bulk_ans(&value),
+        );
         test_command(&mut client, FLUSHDB, ANS_SUCCESS);
     }
 
@@ -182,13 +870,13 @@ mod server_test {
         let mut client2 = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
         test_command(&mut client1, APPEND_KEY_HOLA, &integer_ans(4));
         test_command(&mut client1, SET_KEY_1, ANS_SUCCESS);
-        test_command(&mut client1, GET_KEY, "1\n");
+        test_command(&mut client1, GET_KEY, &bulk_ans("1"));
         test_command(&mut client2, APPEND_KEY_ADIOS, &integer_ans(6));
-        test_command(&mut client2, GET_KEY, "1adios\n");
+        test_command(&mut client2, GET_KEY, &bulk_ans("1adios"));
         test_command(&mut client1, DEL_KEY, ANS_SUCCESS);
         test_command(&mut client1, APPEND_KEY_HOLA, &integer_ans(4));
         test_command(&mut client1, APPEND_KEY_ADIOS, &integer_ans(9));
-        test_command(&mut client2, GET_KEY, "holaadios\n");
+        test_command(&mut client2, GET_KEY, &bulk_ans("holaadios"));
         test_command(&mut client1, FLUSHDB, ANS_SUCCESS);
     }
 
@@ -201,22 +889,22 @@ mod server_test {
         let mut i = -1;
         for client in &mut clients {
             i += 1;
-            let command = format!("set key1 {}", i);
+            let command = format!("set key1 {}\n", i);
             test_command(client, &command, ANS_SUCCESS);
         }
 
         for client in &mut clients {
-            test_command(client, "get key1", &(i.to_string() + "\n"));
+            test_command(client, "get key1\n", &bulk_ans(&i.to_string()));
         }
 
         let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
         test_command(&mut client, APPEND_KEY_HOLA, &integer_ans(4));
         test_command(&mut client, SET_KEY_1, ANS_SUCCESS);
-        test_command(&mut client, GET_KEY, "1\n");
+        test_command(&mut client, GET_KEY, &bulk_ans("1"));
         test_command(&mut client, APPEND_KEY_ADIOS, &integer_ans(6));
 
         for client in &mut clients {
-            test_command(client, "get key", "1adios\n");
+            test_command(client, "get key\n", &bulk_ans("1adios"));
         }
 
         test_command(&mut client, DEL_KEY, ANS_SUCCESS);
@@ -224,7 +912,413 @@ mod server_test {
         test_command(&mut client, APPEND_KEY_ADIOS, &integer_ans(9));
 
         for client in &mut clients {
-            test_command(client, "get key", "holaadios\n");
+            test_command(client, "get key\n", &bulk_ans("holaadios"));
+        }
+    }
+
+    fn test_publish_subscribe() {
+        let mut subscriber =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut publisher =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let subscribed = format!(
+            "*3\r\n{}{}{}",
+            bulk_ans("subscribe"),
+            bulk_ans("news"),
+            integer_ans(1)
+        );
+        test_command(&mut subscriber, "subscribe news\r\n", &subscribed);
+        test_command(&mut publisher, "publish news hello\r\n", &integer_ans(1));
+
+        let message = format!(
+            "*3\r\n{}{}{}",
+            bulk_ans("message"),
+            bulk_ans("news"),
+            bulk_ans("hello")
+        );
+        let mut reader = BufReader::new(&mut subscriber);
+        assert_eq!(read_reply(&mut reader), message);
+
+        drop(subscriber);
+        thread::sleep(std::time::Duration::from_millis(100));
+        test_command(&mut publisher, "publish news bye\r\n", &integer_ans(0));
+    }
+
+    fn test_pattern_subscribe() {
+        let mut subscriber =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut publisher =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let subscribed = format!(
+            "*3\r\n{}{}{}*3\r\n{}{}{}",
+            bulk_ans("subscribe"),
+            bulk_ans("news.tech"),
+            integer_ans(1),
+            bulk_ans("psubscribe"),
+            bulk_ans("news.*"),
+            integer_ans(2)
+        );
+        let commands = "subscribe news.tech\r\npsubscribe news.*\r\n";
+        test_command_bytes(&mut subscriber, commands.as_bytes(), subscribed.as_bytes());
+        test_command(&mut publisher, "pubsub numpat\r\n", &integer_ans(1));
+        test_command(&mut publisher, "publish news.tech hi\r\n", &integer_ans(2));
+
+        let message = format!(
+            "*3\r\n{}{}{}",
+            bulk_ans("message"),
+            bulk_ans("news.tech"),
+            bulk_ans("hi")
+        );
+        let pmessage = format!(
+            "*4\r\n{}{}{}{}",
+            bulk_ans("pmessage"),
+            bulk_ans("news.*"),
+            bulk_ans("news.tech"),
+            bulk_ans("hi")
+        );
+        let mut reader = BufReader::new(&mut subscriber);
+        assert_eq!(read_reply(&mut reader), message);
+        assert_eq!(read_reply(&mut reader), pmessage);
+        drop(reader);
+
+        let unsubscribed = format!(
+            "*3\r\n{}{}{}",
+            bulk_ans("punsubscribe"),
+            bulk_ans("news.*"),
+            integer_ans(1)
+        );
+        test_command(&mut subscriber, "punsubscribe news.*\r\n", &unsubscribed);
+        test_command(&mut publisher, "pubsub numpat\r\n", &integer_ans(0));
+        test_command(
+            &mut publisher,
+            "publish news.sports hi\r\n",
+            &integer_ans(0),
+        );
+    }
+
+    fn test_keyspace_notifications() {
+        let mut subscriber =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let config = "config set notify-keyspace-events KEA\r\n";
+        test_command(&mut client, config, ANS_SUCCESS);
+        let subscribed = format!(
+            "*3\r\n{}{}{}",
+            bulk_ans("psubscribe"),
+            bulk_ans("__key*@0__:*"),
+            integer_ans(1)
+        );
+        test_command(&mut subscriber, "psubscribe __key*@0__:*\r\n", &subscribed);
+
+        test_command(&mut client, "set event_key 1\r\n", ANS_SUCCESS);
+        test_command(&mut client, "set event_key 2 nx\r\n", "$-1\r\n");
+        test_command(&mut client, "pexpire event_key 10\r\n", &integer_ans(1));
+
+        let mut reader = BufReader::new(&mut subscriber);
+        for (channel, message) in [
+            ("__keyspace@0__:event_key", "set"),
+            ("__keyevent@0__:set", "event_key"),
+            ("__keyspace@0__:event_key", "expire"),
+            ("__keyevent@0__:expire", "event_key"),
+            ("__keyspace@0__:event_key", "expired"),
+            ("__keyevent@0__:expired", "event_key"),
+        ] {
+            let pmessage = format!(
+                "*4\r\n{}{}{}{}",
+                bulk_ans("pmessage"),
+                bulk_ans("__key*@0__:*"),
+                bulk_ans(channel),
+                bulk_ans(message)
+            );
+            assert_eq!(read_reply(&mut reader), pmessage);
         }
+
+        let config = resp_command(&["config", "set", "notify-keyspace-events", ""]);
+        test_command(&mut client, &config, ANS_SUCCESS);
+    }
+
+    fn test_slow_subscribers_are_disconnected() {
+        let mut subscriber =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut publisher =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let config = resp_command(&[
+            "config",
+            "set",
+            "client-output-buffer-limit",
+            "pubsub 1mb 0 0",
+        ]);
+        test_command(&mut publisher, &config, ANS_SUCCESS);
+        let subscribed = format!(
+            "*3\r\n{}{}{}",
+            bulk_ans("subscribe"),
+            bulk_ans("slow"),
+            integer_ans(1)
+        );
+        test_command(&mut subscriber, "subscribe slow\r\n", &subscribed);
+
+        // The subscriber never reads, so once the socket is full the messages wait in
+        // its output buffer until they go over the limit.
+        let publish = resp_command(&["publish", "slow", &"x".repeat(256 * 1024)]);
+        let mut publisher = BufReader::new(publisher);
+        let mut receivers = 1;
+        for _ in 0..400 {
+            publisher.get_mut().write_all(publish.as_bytes()).unwrap();
+            receivers = match read_reply(&mut publisher).as_str() {
+                ":1\r\n" => 1,
+                _ => 0,
+            };
+            if receivers == 0 {
+                break;
+            }
+        }
+        assert_eq!(receivers, 0);
+
+        let config = resp_command(&[
+            "config",
+            "set",
+            "client-output-buffer-limit",
+            "pubsub 32mb 8mb 60",
+        ]);
+        test_command(publisher.get_mut(), &config, ANS_SUCCESS);
+    }
+
+    fn test_subscription_mode() {
+        let mut subscriber =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut publisher =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let subscribed = |kind: &str, count| {
+            format!(
+                "3\r\n{}{}{}",
+                bulk_ans(kind),
+                bulk_ans("room"),
+                integer_ans(count)
+            )
+        };
+        let pong = format!("*2\r\n{}{}", bulk_ans("pong"), bulk_ans(""));
+        test_command(
+            &mut subscriber,
+            "subscribe room\r\n",
+            &format!("*{}", subscribed("subscribe", 1)),
+        );
+        test_command(&mut subscriber, "ping\r\n", &pong);
+        let mut reader = BufReader::new(&mut subscriber);
+        reader.get_mut().write_all(b"get key\r\n").unwrap();
+        assert!(read_reply(&mut reader).starts_with('-'));
+        drop(reader);
+        test_command(
+            &mut subscriber,
+            "unsubscribe\r\n",
+            &format!("*{}", subscribed("unsubscribe", 0)),
+        );
+        test_command(&mut subscriber, "ping\r\n", "+PONG\r\n");
+
+        test_command(
+            &mut subscriber,
+            "subscribe room\r\n",
+            &format!("*{}", subscribed("subscribe", 1)),
+        );
+        test_command(&mut subscriber, "reset\r\n", "+RESET\r\n");
+        test_command(&mut publisher, "publish room hi\r\n", &integer_ans(0));
+
+        let mut reader = BufReader::new(&mut subscriber);
+        reader.get_mut().write_all(b"hello 3\r\n").unwrap();
+        let hello = read_reply(&mut reader);
+        assert!(hello.starts_with("%7\r\n"));
+        assert!(hello.contains(&format!("{}:3\r\n", bulk_ans("proto"))));
+        drop(reader);
+        test_command(
+            &mut subscriber,
+            "subscribe room\r\n",
+            &format!(">{}", subscribed("subscribe", 1)),
+        );
+        test_command(&mut subscriber, "ping\r\n", "+PONG\r\n");
+        test_command(&mut subscriber, "set room 1\r\n", ANS_SUCCESS);
+        test_command(&mut publisher, "publish room hi\r\n", &integer_ans(1));
+
+        let message = format!(
+            ">3\r\n{}{}{}",
+            bulk_ans("message"),
+            bulk_ans("room"),
+            bulk_ans("hi")
+        );
+        let mut reader = BufReader::new(&mut subscriber);
+        assert_eq!(read_reply(&mut reader), message);
+        drop(reader);
+        test_command(
+            &mut subscriber,
+            "hello 4\r\n",
+            "-NOPROTO unsupported protocol version\r\n",
+        );
+        test_command(&mut subscriber, "del room\r\n", ANS_SUCCESS);
+    }
+
+    fn test_shard_channels() {
+        let mut subscriber =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut publisher =
+            TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let reply = |kind: &str, channel: &str, count| {
+            format!(
+                "*3\r\n{}{}{}",
+                bulk_ans(kind),
+                bulk_ans(channel),
+                integer_ans(count)
+            )
+        };
+        test_command(
+            &mut subscriber,
+            "ssubscribe orders\r\n",
+            &reply("ssubscribe", "orders", 1),
+        );
+        test_command(
+            &mut publisher,
+            "pubsub shardchannels\r\n",
+            &format!("*1\r\n{}", bulk_ans("orders")),
+        );
+        test_command(
+            &mut publisher,
+            "pubsub shardnumsub orders\r\n",
+            &format!("*2\r\n{}{}", bulk_ans("orders"), integer_ans(1)),
+        );
+        test_command(&mut publisher, "pubsub channels\r\n", "*0\r\n");
+        test_command(&mut publisher, "publish orders hi\r\n", &integer_ans(0));
+        test_command(&mut publisher, "spublish orders hi\r\n", &integer_ans(1));
+
+        let message = format!(
+            "*3\r\n{}{}{}",
+            bulk_ans("smessage"),
+            bulk_ans("orders"),
+            bulk_ans("hi")
+        );
+        let mut reader = BufReader::new(&mut subscriber);
+        assert_eq!(read_reply(&mut reader), message);
+        drop(reader);
+
+        test_command(
+            &mut subscriber,
+            "sunsubscribe\r\n",
+            &reply("sunsubscribe", "orders", 0),
+        );
+        test_command(&mut subscriber, "ping\r\n", "+PONG\r\n");
+        test_command(&mut publisher, "spublish orders hi\r\n", &integer_ans(0));
+    }
+
+    fn test_monitor() {
+        let mut monitor = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(&mut monitor, "monitor\r\n", ANS_SUCCESS);
+        test_command(&mut client, "ping\r\n", "+PONG\r\n");
+
+        let mut reader = BufReader::new(&mut monitor);
+        assert!(read_reply(&mut reader).starts_with("+Client: "));
+    }
+
+    fn test_idle_clients_are_disconnected() {
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(&mut client, "config set timeout 1\r\n", ANS_SUCCESS);
+
+        let mut idle = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut reply = String::new();
+        idle.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "-ERR Time Out\r\n");
+
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(&mut client, "config set timeout 0\r\n", ANS_SUCCESS);
+    }
+
+    fn test_maxclients() {
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(&mut client, "config set maxclients 1\r\n", ANS_SUCCESS);
+
+        let mut rejected = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let mut reply = String::new();
+        rejected.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "-ERR max number of clients reached\r\n");
+
+        test_command(&mut client, "config set maxclients 10000\r\n", ANS_SUCCESS);
+        let mut accepted = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(&mut accepted, "ping\r\n", "+PONG\r\n");
+    }
+
+    fn test_auth() {
+        let mut admin = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(
+            &mut admin,
+            "auth secret\r\n",
+            "-ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?\r\n",
+        );
+        test_command(&mut admin, "config set requirepass secret\r\n", ANS_SUCCESS);
+
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let noauth = "-NOAUTH Authentication required.\r\n";
+        test_command(&mut client, "get key\r\n", noauth);
+        test_command(&mut client, "multi\r\n", noauth);
+        test_command(
+            &mut client,
+            "auth wrong\r\n",
+            "-WRONGPASS invalid username-password pair or user is disabled.\r\n",
+        );
+        test_command(&mut client, "flushdb\r\n", noauth);
+        test_command(&mut client, "auth secret\r\n", ANS_SUCCESS);
+        test_command(&mut client, "ping\r\n", "+PONG\r\n");
+
+        let unset = resp_command(&["CONFIG", "SET", "requirepass", ""]);
+        test_command(&mut admin, &unset, ANS_SUCCESS);
+        let mut client = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(&mut client, "ping\r\n", "+PONG\r\n");
+    }
+
+    fn test_acl() {
+        let mut admin = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        let setuser = resp_command(&[
+            "ACL", "SETUSER", "alice", "on", ">pass", "~cache:*", "&news", "+@read", "+publish",
+        ]);
+        test_command(&mut admin, &setuser, ANS_SUCCESS);
+        test_command(&mut admin, "acl whoami\r\n", &bulk_ans("default"));
+
+        let mut alice = TcpStream::connect("0.0.0.0:8888").expect("Could not connect to server");
+        test_command(
+            &mut alice,
+            "auth alice wrong\r\n",
+            "-WRONGPASS invalid username-password pair or user is disabled.\r\n",
+        );
+        test_command(&mut alice, "auth alice pass\r\n", ANS_SUCCESS);
+        test_command(&mut alice, "acl whoami\r\n", &bulk_ans("alice"));
+        test_command(&mut alice, "get cache:a\r\n", "$-1\r\n");
+        test_command(
+            &mut alice,
+            "set cache:a 1\r\n",
+            "-NOPERM User alice has no permissions to run the 'set' command\r\n",
+        );
+        test_command(
+            &mut alice,
+            "get secret\r\n",
+            "-NOPERM No permissions to access a key\r\n",
+        );
+        test_command(&mut alice, "publish news hi\r\n", &integer_ans(0));
+        test_command(
+            &mut alice,
+            "publish sports hi\r\n",
+            "-NOPERM No permissions to access a channel\r\n",
+        );
+        test_command(
+            &mut alice,
+            "acl list\r\n",
+            "-NOPERM User alice has no permissions to run the 'acl' command\r\n",
+        );
+
+        test_command(&mut admin, "acl deluser alice\r\n", &integer_ans(1));
+        test_command(
+            &mut alice,
+            "get cache:a\r\n",
+            "-NOAUTH Authentication required.\r\n",
+        );
+        test_command(
+            &mut admin,
+            "acl deluser default\r\n",
+            "-ERR The 'default' user cannot be removed\r\n",
+        );
     }
 }
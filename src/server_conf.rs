@@ -1,9 +1,19 @@
+use crate::acl::{Acl, DEFAULT_USER};
+use crate::aof::AppendFsync;
+use crate::connection::{ClientClass, OutputBufferLimit};
+use crate::eviction::EvictionPolicy;
+use crate::hash_shard::DEFAULT_SHARDS;
 use crate::matcher::matcher;
+use crate::notify::KeyspaceEvents;
+use crate::rdb::DumpFormat;
+use crate::tls::TlsAuthClients;
 use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::net::IpAddr;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -12,18 +22,86 @@ const PORT: &str = "port";
 const TIMEOUT: &str = "timeout";
 const DBFILENAME: &str = "dbfilename";
 const LOGFILE: &str = "logfile";
+const DATABASES: &str = "databases";
+const SHARDS: &str = "shards";
+const APPENDONLY: &str = "appendonly";
+const APPENDFILENAME: &str = "appendfilename";
+const APPENDFSYNC: &str = "appendfsync";
+const SAVE: &str = "save";
+const DBFORMAT: &str = "dbformat";
+const MAXMEMORY: &str = "maxmemory";
+const MAXMEMORY_POLICY: &str = "maxmemory-policy";
+const MAXCLIENTS: &str = "maxclients";
+const WORKER_THREADS: &str = "worker-threads";
+const REQUIREPASS: &str = "requirepass";
+const PROTECTED_MODE: &str = "protected-mode";
+const ACLFILE: &str = "aclfile";
+const TLS_PORT: &str = "tls-port";
+const TLS_CERT_FILE: &str = "tls-cert-file";
+const TLS_KEY_FILE: &str = "tls-key-file";
+const TLS_CA_CERT_FILE: &str = "tls-ca-cert-file";
+const TLS_AUTH_CLIENTS: &str = "tls-auth-clients";
+const NOTIFY_KEYSPACE_EVENTS: &str = "notify-keyspace-events";
+const CLIENT_OUTPUT_BUFFER_LIMIT: &str = "client-output-buffer-limit";
 const DEFAULT_VERBOSE: u64 = 0;
 const DEFAULT_PORT: u64 = 8888;
 const DEFAULT_TIMEOUT: u64 = 0;
 const DEFAULT_DBFILENAME: &str = "dump.txt";
 const DEFAULT_LOGFILE: &str = "lf.log";
-const NUMERIC_KEYS: [&str; 2] = [VERBOSE, TIMEOUT];
-const INVALID_SETEABLE: [&str; 3] = [LOGFILE, PORT, DBFILENAME];
+const DEFAULT_DATABASES: u64 = 16;
+const DEFAULT_APPENDONLY: &str = "no";
+const DEFAULT_APPENDFILENAME: &str = "appendonly.aof";
+const DEFAULT_APPENDFSYNC: &str = "everysec";
+const DEFAULT_SAVE: &str = "3600 1 300 100 60 10000";
+const DEFAULT_DBFORMAT: &str = "text";
+const DEFAULT_MAXMEMORY: &str = "0";
+const DEFAULT_MAXMEMORY_POLICY: &str = "noeviction";
+const DEFAULT_MAXCLIENTS: u64 = 10000;
+const DEFAULT_WORKER_THREADS: u64 = 4;
+const DEFAULT_REQUIREPASS: &str = "";
+const DEFAULT_PROTECTED_MODE: &str = "yes";
+const DEFAULT_ACLFILE: &str = "";
+const DEFAULT_TLS_PORT: u64 = 0;
+const DEFAULT_TLS_CERT_FILE: &str = "";
+const DEFAULT_TLS_KEY_FILE: &str = "";
+const DEFAULT_TLS_CA_CERT_FILE: &str = "";
+const DEFAULT_TLS_AUTH_CLIENTS: &str = "yes";
+const DEFAULT_NOTIFY_KEYSPACE_EVENTS: &str = "";
+const DEFAULT_CLIENT_OUTPUT_BUFFER_LIMIT: &str =
+    "normal 0 0 0 replica 256mb 64mb 60 pubsub 32mb 8mb 60";
+const NUMERIC_KEYS: [&str; 7] = [
+    VERBOSE,
+    TIMEOUT,
+    DATABASES,
+    SHARDS,
+    MAXCLIENTS,
+    WORKER_THREADS,
+    TLS_PORT,
+];
+const INVALID_SETEABLE: [&str; 16] = [
+    LOGFILE,
+    PORT,
+    DBFILENAME,
+    DATABASES,
+    SHARDS,
+    APPENDONLY,
+    APPENDFILENAME,
+    APPENDFSYNC,
+    DBFORMAT,
+    WORKER_THREADS,
+    ACLFILE,
+    TLS_PORT,
+    TLS_CERT_FILE,
+    TLS_KEY_FILE,
+    TLS_CA_CERT_FILE,
+    TLS_AUTH_CLIENTS,
+];
 const MIN_PORT: i64 = 1024;
 const MAX_PORT: i64 = 49151;
 
 pub struct ServerConf {
     conf: Arc<Mutex<HashMap<String, String>>>,
+    acl: Acl,
 }
 
 #[derive(Debug, PartialEq)]
@@ -39,17 +117,24 @@ pub enum ServerError {
     NotAnInteger,
     InvalidPortValue,
     NoSeteableOption(String),
+    InvalidSaveParams,
+    InvalidMaxmemory,
+    InvalidMaxmemoryPolicy,
+    InvalidProtectedMode,
+    InvalidNotifyKeyspaceEvents,
+    InvalidClientOutputBufferLimit,
+    ShutdownFailed,
 }
 
 impl Clone for ServerConf {
     fn clone(&self) -> Self {
-        ServerConf::new_from_conf(self.conf.clone())
+        ServerConf::new_from_conf(self.conf.clone(), self.acl.clone())
     }
 }
 
 impl ServerConf {
-    pub fn new_from_conf(conf: Arc<Mutex<HashMap<String, String>>>) -> ServerConf {
-        ServerConf { conf }
+    pub fn new_from_conf(conf: Arc<Mutex<HashMap<String, String>>>, acl: Acl) -> ServerConf {
+        ServerConf { conf, acl }
     }
 
     pub fn new(config_file: &str) -> Result<ServerConf, String> {
@@ -77,7 +162,18 @@ impl ServerConf {
 
         drop(guard);
 
-        Ok(ServerConf { conf })
+        let config = ServerConf {
+            conf,
+            acl: Acl::new(),
+        };
+        config.acl.set_requirepass(config.requirepass().as_deref());
+        if let Some(aclfile) = config.aclfile() {
+            if Path::new(&aclfile).exists() {
+                config.acl.load(&aclfile).map_err(|e| e.to_string())?;
+            }
+        }
+
+        Ok(config)
     }
 
     pub fn port(&self) -> u64 {
@@ -97,6 +193,51 @@ impl ServerConf {
         "0.0.0.0:".to_owned() + &port.to_string()
     }
 
+    /// Returns the port of the TLS connections, 0 if there aren't.
+    pub fn tls_port(&self) -> u64 {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(TLS_PORT) {
+            if let Ok(v) = value.parse::<u64>() {
+                return v;
+            }
+        }
+
+        DEFAULT_TLS_PORT
+    }
+
+    pub fn tls_addr(&self) -> String {
+        "0.0.0.0:".to_owned() + &self.tls_port().to_string()
+    }
+
+    /// Returns the certificate the server presents on the TLS port.
+    pub fn tls_cert_file(&self) -> Option<String> {
+        self.non_empty(TLS_CERT_FILE)
+    }
+
+    /// Returns the private key of tls-cert-file.
+    pub fn tls_key_file(&self) -> Option<String> {
+        self.non_empty(TLS_KEY_FILE)
+    }
+
+    /// Returns the certificates of the CAs that sign the certificates of the clients.
+    pub fn tls_ca_cert_file(&self) -> Option<String> {
+        self.non_empty(TLS_CA_CERT_FILE)
+    }
+
+    /// Returns whether the clients of the TLS port need a certificate, yes by default.
+    pub fn tls_auth_clients(&self) -> TlsAuthClients {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(TLS_AUTH_CLIENTS) {
+            if let Some(auth_clients) = TlsAuthClients::parse(value) {
+                return auth_clients;
+            }
+        }
+
+        TlsAuthClients::Yes
+    }
+
     pub fn logfile(&self) -> String {
         let conf = self.conf.lock().unwrap();
 
@@ -110,11 +251,12 @@ impl ServerConf {
         let conf = self.conf.lock().unwrap();
         let mut list = Vec::new();
         for (k, v) in conf.iter() {
-            if !matcher(k, pattern) {
+            if !matcher(k.as_bytes(), pattern.as_bytes()) {
                 continue;
             }
 
-            list.push(SuccessServerRequest::String(format!("{}: {}", k, v)));
+            list.push(SuccessServerRequest::String(k.to_string()));
+            list.push(SuccessServerRequest::String(v.to_string()));
         }
 
         if list.is_empty() {
@@ -139,6 +281,37 @@ impl ServerConf {
             return Err(ServerError::NoSeteableOption(option.to_string()));
         }
 
+        if option == SAVE && parse_save_points(new_value).is_none() {
+            return Err(ServerError::InvalidSaveParams);
+        }
+
+        if option == MAXMEMORY && parse_memory(new_value).is_none() {
+            return Err(ServerError::InvalidMaxmemory);
+        }
+
+        if option == MAXMEMORY_POLICY && EvictionPolicy::parse(new_value).is_none() {
+            return Err(ServerError::InvalidMaxmemoryPolicy);
+        }
+
+        if option == PROTECTED_MODE && new_value != "yes" && new_value != "no" {
+            return Err(ServerError::InvalidProtectedMode);
+        }
+
+        // Kept as Redis writes it back, so CONFIG GET gives the same for KEA or AKE.
+        // Only the classes given change their limits, as in Redis.
+        let new_value = match option {
+            NOTIFY_KEYSPACE_EVENTS => KeyspaceEvents::parse(new_value)
+                .ok_or(ServerError::InvalidNotifyKeyspaceEvents)?
+                .to_string(),
+            CLIENT_OUTPUT_BUFFER_LIMIT => {
+                let current = conf.get(option).map_or("", |value| value.as_str());
+                merge_output_buffer_limits(current, new_value)
+                    .ok_or(ServerError::InvalidClientOutputBufferLimit)?
+            }
+            _ => new_value.to_string(),
+        };
+        let new_value = new_value.as_str();
+
         if conf.contains_key(option) {
             if option == PORT {
                 let value = new_value.parse::<i64>().unwrap();
@@ -149,6 +322,10 @@ impl ServerConf {
                     Err(ServerError::InvalidPortValue)
                 }
             } else {
+                if option == REQUIREPASS {
+                    let password = Some(new_value).filter(|password| !password.is_empty());
+                    self.acl.set_requirepass(password);
+                }
                 conf.insert(option.to_string(), new_value.to_string());
                 Ok(SuccessServerRequest::Success)
             }
@@ -177,6 +354,232 @@ impl ServerConf {
         }
     }
 
+    pub fn databases(&self) -> usize {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(DATABASES) {
+            if let Ok(v) = value.parse::<usize>() {
+                if v > 0 {
+                    return v;
+                }
+            }
+        }
+
+        DEFAULT_DATABASES as usize
+    }
+
+    pub fn shards(&self) -> usize {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(SHARDS) {
+            if let Ok(v) = value.parse::<usize>() {
+                if v > 0 {
+                    return v;
+                }
+            }
+        }
+
+        DEFAULT_SHARDS
+    }
+
+    pub fn maxclients(&self) -> usize {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(MAXCLIENTS) {
+            if let Ok(v) = value.parse::<usize>() {
+                return v;
+            }
+        }
+
+        DEFAULT_MAXCLIENTS as usize
+    }
+
+    pub fn worker_threads(&self) -> usize {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(WORKER_THREADS) {
+            if let Ok(v) = value.parse::<usize>() {
+                if v > 0 {
+                    return v;
+                }
+            }
+        }
+
+        DEFAULT_WORKER_THREADS as usize
+    }
+
+    /// Returns the password clients have to give with AUTH, or None if they don't
+    /// need one.
+    pub fn requirepass(&self) -> Option<String> {
+        self.non_empty(REQUIREPASS)
+    }
+
+    pub fn protected_mode(&self) -> bool {
+        let conf = self.conf.lock().unwrap();
+
+        match conf.get(PROTECTED_MODE) {
+            Some(value) => value == "yes",
+            None => DEFAULT_PROTECTED_MODE == "yes",
+        }
+    }
+
+    /// Returns the file the users are saved to with ACL SAVE and loaded from at start
+    /// and with ACL LOAD, or None if there's none. A file that doesn't exist yet is
+    /// created by ACL SAVE.
+    pub fn aclfile(&self) -> Option<String> {
+        self.non_empty(ACLFILE)
+    }
+
+    /// Returns the users of the server.
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    }
+
+    /// Returns true if a client connecting from addr can be accepted: in protected mode,
+    /// while the default user has no password, only clients from the loopback
+    /// interface are.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let config = ServerConf::new("redis.conf")?;
+    ///
+    /// assert!(config.accepts_from("127.0.0.1".parse().unwrap()));
+    /// assert!(!config.accepts_from("10.0.0.1".parse().unwrap()));
+    /// ```
+    pub fn accepts_from(&self, addr: IpAddr) -> bool {
+        let loopback = match addr {
+            IpAddr::V4(addr) => addr.is_loopback(),
+            IpAddr::V6(addr) => {
+                addr.is_loopback() || addr.to_ipv4_mapped().is_some_and(|addr| addr.is_loopback())
+            }
+        };
+
+        loopback || !self.protected_mode() || !self.acl.is_open(DEFAULT_USER)
+    }
+
+    pub fn appendonly(&self) -> bool {
+        let conf = self.conf.lock().unwrap();
+
+        match conf.get(APPENDONLY) {
+            Some(value) => value == "yes",
+            None => DEFAULT_APPENDONLY == "yes",
+        }
+    }
+
+    pub fn appendfilename(&self) -> String {
+        let conf = self.conf.lock().unwrap();
+        match conf.get(APPENDFILENAME) {
+            Some(value) => value.to_string(),
+            None => DEFAULT_APPENDFILENAME.to_string(),
+        }
+    }
+
+    pub fn appendfsync(&self) -> AppendFsync {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(APPENDFSYNC) {
+            if let Some(fsync) = AppendFsync::parse(value) {
+                return fsync;
+            }
+        }
+
+        AppendFsync::Everysec
+    }
+
+    /// Returns the format the dump is saved in: text, the default, or rdb to save it
+    /// in the format of Redis.
+    pub fn dbformat(&self) -> DumpFormat {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(DBFORMAT) {
+            if let Some(format) = DumpFormat::parse(value) {
+                return format;
+            }
+        }
+
+        DumpFormat::Text
+    }
+
+    /// Returns the save points, pairs of seconds and changes: the data is saved when
+    /// there were at least that many changes and that many seconds passed since the
+    /// last save. An empty list disables saving.
+    pub fn save_points(&self) -> Vec<(u64, u64)> {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(SAVE) {
+            if let Some(points) = parse_save_points(value) {
+                return points;
+            }
+        }
+
+        parse_save_points(DEFAULT_SAVE).unwrap()
+    }
+
+    /// Returns the memory limit of the data in bytes, 0 for no limit. It can be given
+    /// with a unit: kb, mb or gb.
+    pub fn maxmemory(&self) -> usize {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(MAXMEMORY) {
+            if let Some(bytes) = parse_memory(value) {
+                return bytes;
+            }
+        }
+
+        parse_memory(DEFAULT_MAXMEMORY).unwrap()
+    }
+
+    /// Returns how keys are evicted once maxmemory is reached, noeviction by default.
+    pub fn maxmemory_policy(&self) -> EvictionPolicy {
+        let conf = self.conf.lock().unwrap();
+
+        if let Some(value) = conf.get(MAXMEMORY_POLICY) {
+            if let Some(policy) = EvictionPolicy::parse(value) {
+                return policy;
+            }
+        }
+
+        EvictionPolicy::NoEviction
+    }
+
+    /// Returns the keyspace events published in the channels, none by default.
+    pub fn notify_keyspace_events(&self) -> KeyspaceEvents {
+        let conf = self.conf.lock().unwrap();
+
+        conf.get(NOTIFY_KEYSPACE_EVENTS)
+            .and_then(|value| KeyspaceEvents::parse(value))
+            .unwrap_or_default()
+    }
+
+    /// Returns how many bytes can wait to be written to the clients of class before
+    /// they are disconnected, from client-output-buffer-limit: 32mb, or 8mb for a
+    /// minute, for the subscribed clients and no limit for the normal ones by default.
+    pub fn client_output_buffer_limit(&self, class: ClientClass) -> OutputBufferLimit {
+        let conf = self.conf.lock().unwrap();
+        let value = conf
+            .get(CLIENT_OUTPUT_BUFFER_LIMIT)
+            .map_or("", |v| v.as_str());
+
+        parse_output_buffer_limits(value)
+            .into_iter()
+            .flatten()
+            .chain(parse_output_buffer_limits(DEFAULT_CLIENT_OUTPUT_BUFFER_LIMIT).unwrap())
+            .find(|(limit_class, _)| *limit_class == class)
+            .map(|(_, limit)| limit)
+            .unwrap_or_default()
+    }
+
+    /// Returns the value of option, or None if it's empty.
+    #[doc(hidden)]
+    fn non_empty(&self, option: &str) -> Option<String> {
+        let conf = self.conf.lock().unwrap();
+
+        match conf.get(option) {
+            Some(value) if !value.is_empty() => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
     pub fn verbose(&self) -> bool {
         if let Some(value) = self.conf.lock().unwrap().get(VERBOSE) {
             if let Ok(v) = value.parse::<u64>() {
@@ -224,10 +627,111 @@ impl fmt::Display for ServerError {
             ServerError::NoSeteableOption(option) => {
                 write!(f, "ERR Unsupported CONFIG parameter: {}", option)
             }
+            ServerError::InvalidSaveParams => write!(f, "Invalid save parameters"),
+            ServerError::InvalidMaxmemory => write!(f, "Invalid maxmemory value"),
+            ServerError::InvalidMaxmemoryPolicy => write!(f, "Invalid maxmemory-policy value"),
+            ServerError::InvalidProtectedMode => write!(f, "protected-mode must be yes or no"),
+            ServerError::InvalidNotifyKeyspaceEvents => {
+                write!(f, "Invalid event class character. Use 'Ag$lshzxeKE'")
+            }
+            ServerError::InvalidClientOutputBufferLimit => write!(
+                f,
+                "Invalid client-output-buffer-limit value, it has to be groups of a class, \
+                 a hard limit, a soft limit and the seconds of the soft limit"
+            ),
+            ServerError::ShutdownFailed => write!(f, "Errors trying to SHUTDOWN. Check logs."),
         }
     }
 }
 
+fn parse_save_points(value: &str) -> Option<Vec<(u64, u64)>> {
+    let numbers: Vec<&str> = value.split_whitespace().collect();
+    if !numbers.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let mut points = Vec::new();
+    for pair in numbers.chunks(2) {
+        let seconds = pair[0].parse::<u64>().ok()?;
+        let changes = pair[1].parse::<u64>().ok()?;
+        points.push((seconds, changes));
+    }
+
+    Some(points)
+}
+
+fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_ascii_lowercase();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(position) => value.split_at(position),
+        None => (&value[..], ""),
+    };
+    let unit = match unit {
+        "" | "b" => 1,
+        "kb" => 1024,
+        "mb" => 1024 * 1024,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    number.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+/// Parses the value of client-output-buffer-limit: groups of a class, a hard limit, a
+/// soft limit, both in bytes with an optional unit, and the seconds of the soft limit.
+fn parse_output_buffer_limits(value: &str) -> Option<Vec<(ClientClass, OutputBufferLimit)>> {
+    let words: Vec<&str> = value.split_whitespace().collect();
+    if !words.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut limits = Vec::new();
+    for group in words.chunks(4) {
+        let class = ClientClass::parse(group[0])?;
+        let limit = OutputBufferLimit {
+            hard: parse_memory(group[1])?,
+            soft: parse_memory(group[2])?,
+            soft_seconds: group[3].parse::<u64>().ok()?,
+        };
+        // The last limits of a class are the ones that count.
+        limits.retain(|(c, _)| *c != class);
+        limits.push((class, limit));
+    }
+
+    Some(limits)
+}
+
+/// Returns the limits of current with the ones of new_value instead, for the classes
+/// new_value has, written with every class in bytes as Redis does.
+fn merge_output_buffer_limits(current: &str, new_value: &str) -> Option<String> {
+    let new_limits = parse_output_buffer_limits(new_value)?;
+    let current_limits = parse_output_buffer_limits(current).unwrap_or_default();
+    let default_limits = parse_output_buffer_limits(DEFAULT_CLIENT_OUTPUT_BUFFER_LIMIT).unwrap();
+
+    let merged: Vec<String> = [
+        ClientClass::Normal,
+        ClientClass::Replica,
+        ClientClass::PubSub,
+    ]
+    .iter()
+    .filter_map(|class| {
+        new_limits
+            .iter()
+            .chain(current_limits.iter())
+            .chain(default_limits.iter())
+            .find(|(limit_class, _)| limit_class == class)
+            .map(|(class, limit)| {
+                format!(
+                    "{} {} {} {}",
+                    class, limit.hard, limit.soft, limit.soft_seconds
+                )
+            })
+    })
+    .collect();
+
+    Some(merged.join(" "))
+}
+
 fn default_values() -> Arc<Mutex<HashMap<String, String>>> {
     let d = Arc::new(Mutex::new(HashMap::new()));
 
@@ -239,6 +743,51 @@ fn default_values() -> Arc<Mutex<HashMap<String, String>>> {
     guard.insert(TIMEOUT.to_string(), DEFAULT_TIMEOUT.to_string());
     guard.insert(DBFILENAME.to_string(), DEFAULT_DBFILENAME.to_string());
     guard.insert(LOGFILE.to_string(), DEFAULT_LOGFILE.to_string());
+    guard.insert(DATABASES.to_string(), DEFAULT_DATABASES.to_string());
+    guard.insert(SHARDS.to_string(), DEFAULT_SHARDS.to_string());
+    guard.insert(MAXCLIENTS.to_string(), DEFAULT_MAXCLIENTS.to_string());
+    guard.insert(REQUIREPASS.to_string(), DEFAULT_REQUIREPASS.to_string());
+    guard.insert(ACLFILE.to_string(), DEFAULT_ACLFILE.to_string());
+    guard.insert(TLS_PORT.to_string(), DEFAULT_TLS_PORT.to_string());
+    guard.insert(TLS_CERT_FILE.to_string(), DEFAULT_TLS_CERT_FILE.to_string());
+    guard.insert(TLS_KEY_FILE.to_string(), DEFAULT_TLS_KEY_FILE.to_string());
+    guard.insert(
+        TLS_CA_CERT_FILE.to_string(),
+        DEFAULT_TLS_CA_CERT_FILE.to_string(),
+    );
+    guard.insert(
+        TLS_AUTH_CLIENTS.to_string(),
+        DEFAULT_TLS_AUTH_CLIENTS.to_string(),
+    );
+    guard.insert(
+        PROTECTED_MODE.to_string(),
+        DEFAULT_PROTECTED_MODE.to_string(),
+    );
+    guard.insert(
+        WORKER_THREADS.to_string(),
+        DEFAULT_WORKER_THREADS.to_string(),
+    );
+    guard.insert(APPENDONLY.to_string(), DEFAULT_APPENDONLY.to_string());
+    guard.insert(
+        APPENDFILENAME.to_string(),
+        DEFAULT_APPENDFILENAME.to_string(),
+    );
+    guard.insert(APPENDFSYNC.to_string(), DEFAULT_APPENDFSYNC.to_string());
+    guard.insert(SAVE.to_string(), DEFAULT_SAVE.to_string());
+    guard.insert(DBFORMAT.to_string(), DEFAULT_DBFORMAT.to_string());
+    guard.insert(MAXMEMORY.to_string(), DEFAULT_MAXMEMORY.to_string());
+    guard.insert(
+        NOTIFY_KEYSPACE_EVENTS.to_string(),
+        DEFAULT_NOTIFY_KEYSPACE_EVENTS.to_string(),
+    );
+    guard.insert(
+        CLIENT_OUTPUT_BUFFER_LIMIT.to_string(),
+        DEFAULT_CLIENT_OUTPUT_BUFFER_LIMIT.to_string(),
+    );
+    guard.insert(
+        MAXMEMORY_POLICY.to_string(),
+        DEFAULT_MAXMEMORY_POLICY.to_string(),
+    );
 
     drop(guard);
 
@@ -262,6 +811,24 @@ mod config_parser_tests {
         ServerConf::new("reds.conf").unwrap();
     }
 
+    #[test]
+    fn loads_the_users_of_the_aclfile() {
+        let (conf_file, aclfile) = ("acl_test.conf", "acl_test_conf_users.acl");
+        std::fs::write(conf_file, format!("aclfile = {}", aclfile)).unwrap();
+        std::fs::write(aclfile, "user alice on >secret ~* +@read\n").unwrap();
+        let cp = ServerConf::new(conf_file);
+        std::fs::write(aclfile, "user alice on +nothing\n").unwrap();
+        let invalid = ServerConf::new(conf_file);
+        std::fs::remove_file(conf_file).unwrap();
+        std::fs::remove_file(aclfile).unwrap();
+
+        let cp = cp.unwrap();
+        assert_eq!(cp.aclfile(), Some(aclfile.to_string()));
+        assert!(cp.acl().authenticate("alice", b"secret"));
+        assert!(cp.acl().is_open(DEFAULT_USER));
+        assert!(invalid.is_err());
+    }
+
     mod get_tests {
         use super::*;
 
@@ -274,23 +841,46 @@ mod config_parser_tests {
             assert_eq!(cp.time_out(), DEFAULT_TIMEOUT);
             assert_eq!(cp.dbfilename(), DEFAULT_DBFILENAME);
             assert_eq!(cp.logfile(), DEFAULT_LOGFILE);
+            assert_eq!(cp.databases(), DEFAULT_DATABASES as usize);
+            assert_eq!(cp.shards(), DEFAULT_SHARDS);
+            assert_eq!(cp.maxclients(), DEFAULT_MAXCLIENTS as usize);
+            assert_eq!(cp.worker_threads(), DEFAULT_WORKER_THREADS as usize);
+            assert_eq!(cp.requirepass(), None);
+            assert!(cp.protected_mode());
+            assert_eq!(cp.aclfile(), None);
+            assert_eq!(cp.tls_port(), DEFAULT_TLS_PORT);
+            assert_eq!(cp.tls_cert_file(), None);
+            assert_eq!(cp.tls_key_file(), None);
+            assert_eq!(cp.tls_ca_cert_file(), None);
+            assert_eq!(cp.tls_auth_clients(), TlsAuthClients::Yes);
+            assert!(!cp.appendonly());
+            assert_eq!(cp.appendfilename(), DEFAULT_APPENDFILENAME);
+            assert_eq!(cp.appendfsync(), AppendFsync::Everysec);
+            assert_eq!(cp.save_points(), vec![(3600, 1), (300, 100), (60, 10000)]);
+            assert_eq!(cp.dbformat(), DumpFormat::Text);
+            assert_eq!(cp.maxmemory(), 0);
+            assert_eq!(cp.maxmemory_policy(), EvictionPolicy::NoEviction);
         }
     }
 
     mod get_config_tests {
         use super::*;
 
+        fn contains_pair(list: &[String], key: &str, value: &str) -> bool {
+            list.chunks(2).any(|pair| pair == [key, value])
+        }
+
         #[test]
         fn get_all_config() {
             let cp = create_config_parser();
             if let Ok(SuccessServerRequest::List(list)) = cp.get_config("*") {
                 let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
 
-                assert!(list.contains(&format!("{}: {}", VERBOSE, DEFAULT_VERBOSE)));
-                assert!(list.contains(&format!("{}: {}", PORT, DEFAULT_PORT)));
-                assert!(list.contains(&format!("{}: {}", TIMEOUT, DEFAULT_TIMEOUT)));
-                assert!(list.contains(&format!("{}: {}", DBFILENAME, DEFAULT_DBFILENAME)));
-                assert!(list.contains(&format!("{}: {}", LOGFILE, DEFAULT_LOGFILE)));
+                assert!(contains_pair(&list, VERBOSE, &DEFAULT_VERBOSE.to_string()));
+                assert!(contains_pair(&list, PORT, &DEFAULT_PORT.to_string()));
+                assert!(contains_pair(&list, TIMEOUT, &DEFAULT_TIMEOUT.to_string()));
+                assert!(contains_pair(&list, DBFILENAME, DEFAULT_DBFILENAME));
+                assert!(contains_pair(&list, LOGFILE, DEFAULT_LOGFILE));
             }
         }
 
@@ -300,7 +890,7 @@ mod config_parser_tests {
             if let Ok(SuccessServerRequest::List(list)) = cp.get_config(VERBOSE) {
                 let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
 
-                assert!(list.contains(&format!("{}: {}", VERBOSE, DEFAULT_VERBOSE)));
+                assert!(contains_pair(&list, VERBOSE, &DEFAULT_VERBOSE.to_string()));
             }
         }
 
@@ -310,7 +900,7 @@ mod config_parser_tests {
             if let Ok(SuccessServerRequest::List(list)) = cp.get_config(DBFILENAME) {
                 let list: Vec<String> = list.iter().map(|x| x.to_string()).collect();
 
-                assert!(list.contains(&format!("{}: {}", DBFILENAME, DEFAULT_DBFILENAME)));
+                assert!(contains_pair(&list, DBFILENAME, DEFAULT_DBFILENAME));
             }
         }
     }
@@ -326,7 +916,7 @@ mod config_parser_tests {
             let r = cp.set_config(VERBOSE, "1").unwrap();
             assert_eq!(r, SuccessServerRequest::Success);
 
-            assert_eq!(cp.verbose(), true);
+            assert!(cp.verbose());
         }
 
         #[test]
@@ -340,6 +930,204 @@ mod config_parser_tests {
             assert_eq!(cp.verbose(), DEFAULT_VERBOSE_TO_BOOLEAN);
         }
 
+        #[test]
+        fn set_databases_is_not_allowed() {
+            let mut cp = create_config_parser();
+
+            let r = cp.set_config(DATABASES, "4").unwrap_err();
+            assert_eq!(r, ServerError::NoSeteableOption(DATABASES.to_string()));
+
+            assert_eq!(cp.databases(), DEFAULT_DATABASES as usize);
+        }
+
+        #[test]
+        fn set_maxclients_works_properly() {
+            let mut cp = create_config_parser();
+
+            cp.set_config(MAXCLIENTS, "2").unwrap();
+            assert_eq!(cp.maxclients(), 2);
+
+            let r = cp.set_config(MAXCLIENTS, "many").unwrap_err();
+            assert_eq!(r, ServerError::NotAnInteger);
+            assert_eq!(cp.maxclients(), 2);
+        }
+
+        #[test]
+        fn set_worker_threads_is_not_allowed() {
+            let mut cp = create_config_parser();
+
+            let r = cp.set_config(WORKER_THREADS, "8").unwrap_err();
+            assert_eq!(r, ServerError::NoSeteableOption(WORKER_THREADS.to_string()));
+
+            assert_eq!(cp.worker_threads(), DEFAULT_WORKER_THREADS as usize);
+        }
+
+        #[test]
+        fn set_shards_is_not_allowed() {
+            let mut cp = create_config_parser();
+
+            let r = cp.set_config(SHARDS, "64").unwrap_err();
+            assert_eq!(r, ServerError::NoSeteableOption(SHARDS.to_string()));
+
+            assert_eq!(cp.shards(), DEFAULT_SHARDS);
+        }
+
+        #[test]
+        fn set_save_points() {
+            let mut cp = create_config_parser();
+
+            let r = cp.set_config(SAVE, "10 2").unwrap();
+            assert_eq!(r, SuccessServerRequest::Success);
+            assert_eq!(cp.save_points(), vec![(10, 2)]);
+
+            cp.set_config(SAVE, "").unwrap();
+            assert!(cp.save_points().is_empty());
+        }
+
+        #[test]
+        fn set_save_points_with_a_missing_number() {
+            let mut cp = create_config_parser();
+
+            let r = cp.set_config(SAVE, "10 2 30").unwrap_err();
+            assert_eq!(r, ServerError::InvalidSaveParams);
+
+            assert_eq!(cp.save_points(), vec![(3600, 1), (300, 100), (60, 10000)]);
+        }
+
+        #[test]
+        fn set_maxmemory_with_a_unit() {
+            let mut cp = create_config_parser();
+
+            cp.set_config(MAXMEMORY, "100mb").unwrap();
+            assert_eq!(cp.maxmemory(), 100 * 1024 * 1024);
+
+            let r = cp.set_config(MAXMEMORY, "100xb").unwrap_err();
+            assert_eq!(r, ServerError::InvalidMaxmemory);
+            assert_eq!(cp.maxmemory(), 100 * 1024 * 1024);
+        }
+
+        #[test]
+        fn set_notify_keyspace_events() {
+            let mut cp = create_config_parser();
+            assert_eq!(cp.notify_keyspace_events(), KeyspaceEvents::default());
+
+            cp.set_config(NOTIFY_KEYSPACE_EVENTS, "KEA").unwrap();
+            let r = cp.get_config(NOTIFY_KEYSPACE_EVENTS).unwrap();
+            assert_eq!(r.to_string(), "notify-keyspace-events AKE ");
+            assert_eq!(
+                cp.notify_keyspace_events(),
+                KeyspaceEvents::parse("AKE").unwrap()
+            );
+
+            let r = cp.set_config(NOTIFY_KEYSPACE_EVENTS, "Kq").unwrap_err();
+            assert_eq!(r, ServerError::InvalidNotifyKeyspaceEvents);
+        }
+
+        #[test]
+        fn set_client_output_buffer_limit_of_a_class() {
+            let mut cp = create_config_parser();
+            let pubsub = cp.client_output_buffer_limit(ClientClass::PubSub);
+            assert_eq!(pubsub.hard, 32 * 1024 * 1024);
+            assert_eq!(pubsub.soft_seconds, 60);
+
+            cp.set_config(CLIENT_OUTPUT_BUFFER_LIMIT, "pubsub 1kb 512 5")
+                .unwrap();
+            let pubsub = OutputBufferLimit {
+                hard: 1024,
+                soft: 512,
+                soft_seconds: 5,
+            };
+            assert_eq!(cp.client_output_buffer_limit(ClientClass::PubSub), pubsub);
+            let r = cp.get_config(CLIENT_OUTPUT_BUFFER_LIMIT).unwrap();
+            assert_eq!(
+                r.to_string(),
+                "client-output-buffer-limit normal 0 0 0 replica 268435456 67108864 60 \
+                 pubsub 1024 512 5 "
+            );
+
+            let r = cp
+                .set_config(CLIENT_OUTPUT_BUFFER_LIMIT, "pubsub 1kb 512")
+                .unwrap_err();
+            assert_eq!(r, ServerError::InvalidClientOutputBufferLimit);
+            let r = cp
+                .set_config(CLIENT_OUTPUT_BUFFER_LIMIT, "monitor 0 0 0")
+                .unwrap_err();
+            assert_eq!(r, ServerError::InvalidClientOutputBufferLimit);
+        }
+
+        #[test]
+        fn set_maxmemory_policy() {
+            let mut cp = create_config_parser();
+
+            cp.set_config(MAXMEMORY_POLICY, "allkeys-lfu").unwrap();
+            assert_eq!(cp.maxmemory_policy(), EvictionPolicy::AllkeysLfu);
+
+            let r = cp.set_config(MAXMEMORY_POLICY, "lfu").unwrap_err();
+            assert_eq!(r, ServerError::InvalidMaxmemoryPolicy);
+        }
+
+        #[test]
+        fn set_requirepass() {
+            let mut cp = create_config_parser();
+
+            cp.set_config(REQUIREPASS, "secret").unwrap();
+            assert_eq!(cp.requirepass(), Some("secret".to_string()));
+            assert!(cp.acl().authenticate(DEFAULT_USER, b"secret"));
+            assert!(!cp.acl().is_open(DEFAULT_USER));
+
+            cp.set_config(REQUIREPASS, "").unwrap();
+            assert_eq!(cp.requirepass(), None);
+            assert!(cp.acl().is_open(DEFAULT_USER));
+        }
+
+        #[test]
+        fn tls_options_can_not_be_set() {
+            let mut cp = create_config_parser();
+
+            let r = cp.set_config(TLS_CERT_FILE, "redis.crt").unwrap_err();
+            assert_eq!(r, ServerError::NoSeteableOption(TLS_CERT_FILE.to_string()));
+            let r = cp.set_config(TLS_PORT, "no").unwrap_err();
+            assert_eq!(r, ServerError::NotAnInteger);
+        }
+
+        #[test]
+        fn set_aclfile() {
+            let mut cp = create_config_parser();
+
+            let r = cp.set_config(ACLFILE, "users.acl").unwrap_err();
+            assert_eq!(r, ServerError::NoSeteableOption(ACLFILE.to_string()));
+        }
+
+        #[test]
+        fn set_protected_mode() {
+            let mut cp = create_config_parser();
+
+            cp.set_config(PROTECTED_MODE, "no").unwrap();
+            assert!(!cp.protected_mode());
+
+            let r = cp.set_config(PROTECTED_MODE, "maybe").unwrap_err();
+            assert_eq!(r, ServerError::InvalidProtectedMode);
+            assert!(!cp.protected_mode());
+        }
+
+        #[test]
+        fn protected_mode_only_accepts_loopback_clients_without_a_password() {
+            let mut cp = create_config_parser();
+            let remote: IpAddr = "10.0.0.1".parse().unwrap();
+
+            assert!(cp.accepts_from("127.0.0.1".parse().unwrap()));
+            assert!(cp.accepts_from("::1".parse().unwrap()));
+            assert!(cp.accepts_from("::ffff:127.0.0.1".parse().unwrap()));
+            assert!(!cp.accepts_from(remote));
+
+            cp.set_config(REQUIREPASS, "secret").unwrap();
+            assert!(cp.accepts_from(remote));
+
+            cp.set_config(REQUIREPASS, "").unwrap();
+            cp.set_config(PROTECTED_MODE, "no").unwrap();
+            assert!(cp.accepts_from(remote));
+        }
+
         #[test]
         fn set_non_existent_option() {
             let mut cp = create_config_parser();
@@ -0,0 +1,495 @@
+use crate::databasehelper::random_number;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+#[doc(hidden)]
+type Link = Option<Box<Node>>;
+
+#[doc(hidden)]
+#[derive(Clone, Debug)]
+struct Node {
+    score: f64,
+    member: Vec<u8>,
+    priority: u64,
+    size: usize,
+    left: Link,
+    right: Link,
+}
+
+/// A set of members ordered by score, and by member when scores are equal.
+///
+/// SortedSet keeps a HashMap from member to score, for constant time score lookups,
+/// and a treap ordered by (score, member) that knows the size of every subtree,
+/// for logarithmic rank lookups and range queries.
+///
+#[derive(Clone, Debug)]
+pub struct SortedSet {
+    #[doc(hidden)]
+    scores: HashMap<Vec<u8>, f64>,
+    #[doc(hidden)]
+    root: Link,
+    #[doc(hidden)]
+    seed: u64,
+}
+
+/// Bytes taken by each member besides its contents: its score, twice, and the node
+/// of the tree with its links, size and priority.
+#[doc(hidden)]
+const NODE_OVERHEAD: usize = 64;
+
+/// One end of a score range. The infinities are inclusive bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScoreBound {
+    #[doc(hidden)]
+    Inclusive(f64),
+    #[doc(hidden)]
+    Exclusive(f64),
+}
+
+/// One end of a lexicographical range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LexBound<'a> {
+    #[doc(hidden)]
+    Inclusive(&'a [u8]),
+    #[doc(hidden)]
+    Exclusive(&'a [u8]),
+    #[doc(hidden)]
+    Min,
+    #[doc(hidden)]
+    Max,
+}
+
+impl ScoreBound {
+    /// Parses a bound in the format used by ZRANGEBYSCORE: a number, or a number
+    /// prefixed by `(` to exclude it. `-inf` and `+inf` are accepted.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// assert_eq!(ScoreBound::parse(b"(1.5"), Some(ScoreBound::Exclusive(1.5)));
+    /// ```
+    pub fn parse(bound: &[u8]) -> Option<ScoreBound> {
+        let (exclusive, number) = match bound.split_first() {
+            Some((b'(', number)) => (true, number),
+            _ => (false, bound),
+        };
+
+        let number = parse_score(number)?;
+        if exclusive {
+            Some(ScoreBound::Exclusive(number))
+        } else {
+            Some(ScoreBound::Inclusive(number))
+        }
+    }
+}
+
+impl<'a> LexBound<'a> {
+    /// Parses a bound in the format used by ZRANGEBYLEX: a member prefixed by `[`
+    /// to include it or by `(` to exclude it, `-` for the minimum or `+` for the maximum.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// assert_eq!(LexBound::parse(b"[a"), Some(LexBound::Inclusive(b"a")));
+    /// ```
+    pub fn parse(bound: &'a [u8]) -> Option<LexBound<'a>> {
+        match bound.split_first() {
+            Some((b'[', member)) => Some(LexBound::Inclusive(member)),
+            Some((b'(', member)) => Some(LexBound::Exclusive(member)),
+            Some((b'-', [])) => Some(LexBound::Min),
+            Some((b'+', [])) => Some(LexBound::Max),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a score, which must be a number and can't be NaN.
+pub fn parse_score(bytes: &[u8]) -> Option<f64> {
+    let score = std::str::from_utf8(bytes).ok()?.parse::<f64>().ok()?;
+    if score.is_nan() {
+        None
+    } else {
+        // Adding zero turns -0 into 0, so both sort as the same score.
+        Some(score + 0.0)
+    }
+}
+
+#[doc(hidden)]
+fn compare(score: f64, member: &[u8], other_score: f64, other_member: &[u8]) -> Ordering {
+    score
+        .partial_cmp(&other_score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| member.cmp(other_member))
+}
+
+#[doc(hidden)]
+fn size(link: &Link) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+#[doc(hidden)]
+fn update(node: &mut Node) {
+    node.size = 1 + size(&node.left) + size(&node.right);
+}
+
+#[doc(hidden)]
+fn merge(left: Link, right: Link) -> Link {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                update(&mut left);
+                Some(left)
+            } else {
+                right.left = merge(Some(left), right.left.take());
+                update(&mut right);
+                Some(right)
+            }
+        }
+    }
+}
+
+/// Splits the tree in the nodes that go before the split point, those for which
+/// goes_left is true, and the rest of them.
+#[doc(hidden)]
+fn split<F>(link: Link, goes_left: &F) -> (Link, Link)
+where
+    F: Fn(f64, &[u8]) -> bool,
+{
+    match link {
+        None => (None, None),
+        Some(mut node) => {
+            if goes_left(node.score, &node.member) {
+                let (left, right) = split(node.right.take(), goes_left);
+                node.right = left;
+                update(&mut node);
+                (Some(node), right)
+            } else {
+                let (left, right) = split(node.left.take(), goes_left);
+                node.left = right;
+                update(&mut node);
+                (left, Some(node))
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+fn collect(link: &Link, start: usize, end: usize, result: &mut Vec<(Vec<u8>, f64)>) {
+    if let Some(node) = link {
+        if start >= end {
+            return;
+        }
+        let left_size = size(&node.left);
+        if start < left_size {
+            collect(&node.left, start, end.min(left_size), result);
+        }
+        if start <= left_size && left_size < end {
+            result.push((node.member.clone(), node.score));
+        }
+        if end > left_size + 1 {
+            collect(
+                &node.right,
+                start.saturating_sub(left_size + 1),
+                end - left_size - 1,
+                result,
+            );
+        }
+    }
+}
+
+impl SortedSet {
+    /// Creates a new empty SortedSet.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let sorted_set = SortedSet::new();
+    /// ```
+    pub fn new() -> SortedSet {
+        SortedSet {
+            scores: HashMap::new(),
+            root: None,
+            seed: random_number() | 1,
+        }
+    }
+
+    /// Returns the number of members in the set.
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Returns true if the set has no members.
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Returns the score of member, if it is in the set.
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds member with score, or updates its score if it was already in the set,
+    /// and returns its old score.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut sorted_set = SortedSet::new();
+    /// assert_eq!(sorted_set.insert(b"member", 1.0), None);
+    /// assert_eq!(sorted_set.insert(b"member", 2.0), Some(1.0));
+    /// ```
+    pub fn insert(&mut self, member: &[u8], score: f64) -> Option<f64> {
+        let old_score = self.remove(member);
+
+        let priority = self.next_priority();
+        let node = Box::new(Node {
+            score,
+            member: member.to_vec(),
+            priority,
+            size: 1,
+            left: None,
+            right: None,
+        });
+        let (left, right) = split(self.root.take(), &|other_score, other_member| {
+            compare(other_score, other_member, score, member) == Ordering::Less
+        });
+        self.root = merge(merge(left, Some(node)), right);
+        self.scores.insert(member.to_vec(), score);
+
+        old_score
+    }
+
+    /// Removes member from the set and returns its score, if it was in the set.
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+
+        let (left, right) = split(self.root.take(), &|other_score, other_member| {
+            compare(other_score, other_member, score, member) == Ordering::Less
+        });
+        let (_, right) = split(right, &|other_score, other_member| {
+            compare(other_score, other_member, score, member) != Ordering::Greater
+        });
+        self.root = merge(left, right);
+
+        Some(score)
+    }
+
+    /// Returns the position of member in the set, starting from 0 for the member
+    /// with the lowest score.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut sorted_set = SortedSet::new();
+    /// sorted_set.insert(b"b", 2.0);
+    /// sorted_set.insert(b"a", 1.0);
+    /// assert_eq!(sorted_set.rank(b"b"), Some(1));
+    /// ```
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.count_before(|other_score, other_member| {
+            compare(other_score, other_member, score, member) == Ordering::Less
+        }))
+    }
+
+    /// Returns how many members go before the first member for which is_before is
+    /// false. is_before has to be true for a prefix of the set.
+    pub fn count_before<F>(&self, is_before: F) -> usize
+    where
+        F: Fn(f64, &[u8]) -> bool,
+    {
+        let mut count = 0;
+        let mut link = &self.root;
+        while let Some(node) = link {
+            if is_before(node.score, &node.member) {
+                count += size(&node.left) + 1;
+                link = &node.right;
+            } else {
+                link = &node.left;
+            }
+        }
+        count
+    }
+
+    /// Returns the position of the first member inside the score bound taken as
+    /// a lower bound.
+    pub fn rank_of_min_score(&self, min: ScoreBound) -> usize {
+        match min {
+            ScoreBound::Inclusive(min) => self.count_before(|score, _| score < min),
+            ScoreBound::Exclusive(min) => self.count_before(|score, _| score <= min),
+        }
+    }
+
+    /// Returns the position after the last member inside the score bound taken as
+    /// an upper bound.
+    pub fn rank_of_max_score(&self, max: ScoreBound) -> usize {
+        match max {
+            ScoreBound::Inclusive(max) => self.count_before(|score, _| score <= max),
+            ScoreBound::Exclusive(max) => self.count_before(|score, _| score < max),
+        }
+    }
+
+    /// Returns the position of the first member inside the lexicographical bound
+    /// taken as a lower bound.
+    pub fn rank_of_min_lex(&self, min: LexBound) -> usize {
+        match min {
+            LexBound::Inclusive(min) => self.count_before(|_, member| member < min),
+            LexBound::Exclusive(min) => self.count_before(|_, member| member <= min),
+            LexBound::Min => 0,
+            LexBound::Max => self.len(),
+        }
+    }
+
+    /// Returns the position after the last member inside the lexicographical bound
+    /// taken as an upper bound.
+    pub fn rank_of_max_lex(&self, max: LexBound) -> usize {
+        match max {
+            LexBound::Inclusive(max) => self.count_before(|_, member| member <= max),
+            LexBound::Exclusive(max) => self.count_before(|_, member| member < max),
+            LexBound::Min => 0,
+            LexBound::Max => self.len(),
+        }
+    }
+
+    /// Returns the members, with their scores, whose positions are between start,
+    /// included, and end, excluded.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let mut sorted_set = SortedSet::new();
+    /// sorted_set.insert(b"b", 2.0);
+    /// sorted_set.insert(b"a", 1.0);
+    /// assert_eq!(sorted_set.range(1, 2), vec![(b"b".to_vec(), 2.0)]);
+    /// ```
+    pub fn range(&self, start: usize, end: usize) -> Vec<(Vec<u8>, f64)> {
+        let mut result = Vec::new();
+        collect(&self.root, start, end.min(self.len()), &mut result);
+        result
+    }
+
+    /// Returns an approximation of the bytes the members and scores take: every
+    /// member is kept both in the map of scores and in a node of the tree.
+    pub fn memory_usage(&self) -> usize {
+        self.scores
+            .keys()
+            .map(|member| 2 * member.len() + NODE_OVERHEAD)
+            .sum()
+    }
+
+    /// Returns every member, with its score, in order.
+    pub fn members(&self) -> Vec<(Vec<u8>, f64)> {
+        self.range(0, self.len())
+    }
+
+    #[doc(hidden)]
+    fn next_priority(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+}
+
+impl Default for SortedSet {
+    fn default() -> Self {
+        SortedSet::new()
+    }
+}
+
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+#[cfg(test)]
+mod sorted_set_test {
+    use super::*;
+
+    fn create_sorted_set(elements: &[(&[u8], f64)]) -> SortedSet {
+        let mut sorted_set = SortedSet::new();
+        for (member, score) in elements {
+            sorted_set.insert(member, *score);
+        }
+        sorted_set
+    }
+
+    #[test]
+    fn members_are_ordered_by_score_then_by_member() {
+        let sorted_set = create_sorted_set(&[(b"c", 1.0), (b"a", 2.0), (b"b", 1.0)]);
+
+        let expected = vec![
+            (b"b".to_vec(), 1.0),
+            (b"c".to_vec(), 1.0),
+            (b"a".to_vec(), 2.0),
+        ];
+        assert_eq!(sorted_set.members(), expected);
+    }
+
+    #[test]
+    fn insert_an_existing_member_updates_its_score_and_position() {
+        let mut sorted_set = create_sorted_set(&[(b"a", 1.0), (b"b", 2.0)]);
+
+        assert_eq!(sorted_set.insert(b"a", 3.0), Some(1.0));
+        assert_eq!(sorted_set.len(), 2);
+        assert_eq!(sorted_set.rank(b"a"), Some(1));
+        assert_eq!(sorted_set.rank(b"b"), Some(0));
+    }
+
+    #[test]
+    fn remove_takes_the_member_out_of_the_order() {
+        let mut sorted_set = create_sorted_set(&[(b"a", 1.0), (b"b", 2.0), (b"c", 3.0)]);
+
+        assert_eq!(sorted_set.remove(b"b"), Some(2.0));
+        assert_eq!(sorted_set.remove(b"b"), None);
+        assert_eq!(sorted_set.rank(b"c"), Some(1));
+        assert_eq!(sorted_set.members().len(), 2);
+    }
+
+    #[test]
+    fn rank_and_range_work_with_many_members() {
+        let mut sorted_set = SortedSet::new();
+        for i in (0..1000).rev() {
+            sorted_set.insert(format!("member{}", i).as_bytes(), i as f64);
+        }
+
+        assert_eq!(sorted_set.rank(b"member0"), Some(0));
+        assert_eq!(sorted_set.rank(b"member999"), Some(999));
+        assert_eq!(sorted_set.rank(b"member500"), Some(500));
+
+        let range = sorted_set.range(10, 13);
+        let scores: Vec<f64> = range.iter().map(|(_, score)| *score).collect();
+        assert_eq!(scores, vec![10.0, 11.0, 12.0]);
+    }
+
+    #[test]
+    fn score_bounds_give_the_ranks_of_the_range() {
+        let sorted_set = create_sorted_set(&[(b"a", 1.0), (b"b", 2.0), (b"c", 2.0), (b"d", 3.0)]);
+
+        assert_eq!(sorted_set.rank_of_min_score(ScoreBound::Inclusive(2.0)), 1);
+        assert_eq!(sorted_set.rank_of_min_score(ScoreBound::Exclusive(2.0)), 3);
+        assert_eq!(sorted_set.rank_of_max_score(ScoreBound::Inclusive(2.0)), 3);
+        assert_eq!(sorted_set.rank_of_max_score(ScoreBound::Exclusive(2.0)), 1);
+    }
+
+    #[test]
+    fn lex_bounds_give_the_ranks_of_the_range() {
+        let sorted_set = create_sorted_set(&[(b"a", 0.0), (b"b", 0.0), (b"c", 0.0)]);
+
+        assert_eq!(sorted_set.rank_of_min_lex(LexBound::Inclusive(b"b")), 1);
+        assert_eq!(sorted_set.rank_of_min_lex(LexBound::Exclusive(b"b")), 2);
+        assert_eq!(sorted_set.rank_of_max_lex(LexBound::Inclusive(b"b")), 2);
+        assert_eq!(sorted_set.rank_of_max_lex(LexBound::Max), 3);
+    }
+
+    #[test]
+    fn parse_bounds() {
+        assert_eq!(ScoreBound::parse(b"(1.5"), Some(ScoreBound::Exclusive(1.5)));
+        assert_eq!(
+            ScoreBound::parse(b"-inf"),
+            Some(ScoreBound::Inclusive(f64::NEG_INFINITY))
+        );
+        assert_eq!(ScoreBound::parse(b"nan"), None);
+        assert_eq!(LexBound::parse(b"[a"), Some(LexBound::Inclusive(b"a")));
+        assert_eq!(LexBound::parse(b"+"), Some(LexBound::Max));
+        assert_eq!(LexBound::parse(b"a"), None);
+    }
+}
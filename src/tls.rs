@@ -0,0 +1,236 @@
+use crate::server_conf::ServerConf;
+use mio::net::TcpStream;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig, ServerConnection};
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::Arc;
+
+/// Most bytes the TLS session of a connection keeps waiting to be sent. What the
+/// client doesn't take beyond that stays in the output buffer of the connection,
+/// where it counts towards client-output-buffer-limit.
+const TLS_BUFFER_LIMIT: usize = 64 * 1024;
+
+/// Whether the clients of the TLS port have to send a certificate signed by
+/// tls-ca-cert-file, named like the tls-auth-clients option of Redis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TlsAuthClients {
+    #[doc(hidden)]
+    No,
+    #[doc(hidden)]
+    Optional,
+    #[doc(hidden)]
+    Yes,
+}
+
+impl TlsAuthClients {
+    /// Parses the value of the tls-auth-clients option.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// assert_eq!(TlsAuthClients::parse("optional"), Some(TlsAuthClients::Optional));
+    /// assert_eq!(TlsAuthClients::parse("maybe"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<TlsAuthClients> {
+        match value {
+            "no" => Some(TlsAuthClients::No),
+            "optional" => Some(TlsAuthClients::Optional),
+            "yes" => Some(TlsAuthClients::Yes),
+            _ => None,
+        }
+    }
+}
+
+/// Creates the configuration of the TLS connections from the tls options of config:
+/// the certificate of the server and its key, and the certificates of the CAs the
+/// certificates of the clients have to be signed by, if they're asked for one.
+/// # Examples
+/// Basic Usage:
+/// ```
+/// let config = ServerConf::new("redis.conf")?;
+/// let tls_config = tls::server_config(&config)?;
+/// ```
+pub fn server_config(config: &ServerConf) -> Result<Arc<ServerConfig>, String> {
+    let (cert_file, key_file) = match (config.tls_cert_file(), config.tls_key_file()) {
+        (Some(cert_file), Some(key_file)) => (cert_file, key_file),
+        _ => return Err("tls-port needs tls-cert-file and tls-key-file".to_string()),
+    };
+    let certs = load_certs(&cert_file)?;
+    let key = PrivateKeyDer::from_pem_file(&key_file)
+        .map_err(|e| format!("Couldn't load the key of {}: {}", key_file, e))?;
+
+    let builder = ServerConfig::builder();
+    let builder = match (config.tls_auth_clients(), config.tls_ca_cert_file()) {
+        (TlsAuthClients::No, _) => builder.with_no_client_auth(),
+        (_, None) => {
+            return Err("tls-auth-clients needs tls-ca-cert-file to verify the clients".to_string())
+        }
+        (auth_clients, Some(ca_cert_file)) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(&ca_cert_file)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| format!("Invalid CA certificate in {}: {}", ca_cert_file, e))?;
+            }
+            let mut verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+            if auth_clients == TlsAuthClients::Optional {
+                verifier = verifier.allow_unauthenticated();
+            }
+            let verifier = verifier.build().map_err(|e| e.to_string())?;
+            builder.with_client_cert_verifier(verifier)
+        }
+    };
+
+    let config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Invalid certificate in {}: {}", cert_file, e))?;
+    Ok(Arc::new(config))
+}
+
+/// A connection of the TLS port: the socket and the TLS session over it.
+///
+/// Reading gives the bytes the client sent already decrypted, and writing encrypts
+/// them. As the socket is non blocking, what can't be sent yet waits in the session
+/// until wants_write is false, up to TLS_BUFFER_LIMIT bytes: once the session holds
+/// that much, writing takes nothing more until the socket takes some of it.
+pub struct TlsStream {
+    #[doc(hidden)]
+    socket: TcpStream,
+    #[doc(hidden)]
+    session: ServerConnection,
+}
+
+impl TlsStream {
+    /// Creates a new TlsStream over socket, which starts with the handshake.
+    pub fn new(socket: TcpStream, config: Arc<ServerConfig>) -> io::Result<TlsStream> {
+        let mut session = ServerConnection::new(config).map_err(io::Error::other)?;
+        session.set_buffer_limit(Some(TLS_BUFFER_LIMIT));
+        Ok(TlsStream { socket, session })
+    }
+
+    pub fn socket(&mut self) -> &mut TcpStream {
+        &mut self.socket
+    }
+
+    /// Returns true if there's something waiting to be sent to the client.
+    pub fn wants_write(&self) -> bool {
+        self.session.wants_write()
+    }
+
+    /// Tells the client the connection is being closed, if the socket takes it.
+    pub fn close(&mut self) {
+        self.session.send_close_notify();
+        let _ = self.flush();
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.session.reader().read(buf) {
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(0),
+                result => return result,
+            }
+
+            if self.session.read_tls(&mut self.socket)? == 0 {
+                return Ok(0);
+            }
+            if let Err(e) = self.session.process_new_packets() {
+                // Lets the client know why, if the socket takes it.
+                let _ = self.flush();
+                return Err(io::Error::new(ErrorKind::InvalidData, e));
+            }
+            self.flush()?;
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Makes room in the session for buf with what the socket takes now.
+        self.flush()?;
+        let written = self.session.writer().write(buf)?;
+        self.flush()?;
+        if written == 0 && !buf.is_empty() {
+            return Err(ErrorKind::WouldBlock.into());
+        }
+        Ok(written)
+    }
+
+    /// Sends as much as the socket takes of what is waiting to be sent.
+    fn flush(&mut self) -> io::Result<()> {
+        while self.session.wants_write() {
+            match self.session.write_tls(&mut self.socket) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Couldn't load the certificates of {}: {}", path, e))?;
+
+    if certs.is_empty() {
+        return Err(format!("There are no certificates in {}", path));
+    }
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tls_test {
+    use super::*;
+    use std::fs;
+
+    fn config_with(name: &str, options: &str) -> Result<Arc<ServerConfig>, String> {
+        let file = format!("tls_test_{}.conf", name);
+        fs::write(&file, options).unwrap();
+        let config = ServerConf::new(&file);
+        fs::remove_file(&file).unwrap();
+
+        server_config(&config.unwrap())
+    }
+
+    #[test]
+    fn loads_the_test_certificates() {
+        let options = "tls-cert-file = tls/redis.crt\n\
+                       tls-key-file = tls/redis.key\n\
+                       tls-ca-cert-file = tls/ca.crt";
+
+        assert!(config_with("certificates", options).is_ok());
+    }
+
+    #[test]
+    fn the_certificate_and_the_key_are_needed() {
+        let options = "tls-cert-file = tls/redis.crt\ntls-auth-clients = no";
+
+        assert!(config_with("no_key", options).is_err());
+    }
+
+    #[test]
+    fn verifying_the_clients_needs_a_ca() {
+        let options = "tls-cert-file = tls/redis.crt\n\
+                       tls-key-file = tls/redis.key\n\
+                       tls-auth-clients = optional";
+
+        assert!(config_with("no_ca", options).is_err());
+    }
+
+    #[test]
+    fn a_missing_certificate_fails() {
+        let options = "tls-cert-file = tls/missing.crt\n\
+                       tls-key-file = tls/redis.key\n\
+                       tls-auth-clients = no";
+
+        assert!(config_with("missing", options).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[doc(hidden)]
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed number of threads that run the jobs given to them in the order they
+/// arrive.
+///
+/// However many clients are connected, no more than that many commands are executed
+/// at the same time; the rest of the jobs wait in the queue.
+pub struct WorkerPool {
+    #[doc(hidden)]
+    sender: Sender<Job>,
+}
+
+impl WorkerPool {
+    /// Creates a new WorkerPool with the given number of threads, at least one.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let workers = WorkerPool::new(4);
+    /// ```
+    pub fn new(size: usize) -> WorkerPool {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..size.max(1) {
+            let receiver = receiver.clone();
+            thread::spawn(move || work(receiver));
+        }
+
+        WorkerPool { sender }
+    }
+
+    /// Queues job to be run by the first thread that is free.
+    /// # Examples
+    /// Basic Usage:
+    /// ```
+    /// let workers = WorkerPool::new(4);
+    /// let (s, r) = channel();
+    /// workers.execute(move || s.send(1).unwrap());
+    ///
+    /// assert_eq!(r.recv().unwrap(), 1);
+    /// ```
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if self.sender.send(Box::new(job)).is_err() {
+            eprintln!("Every worker of the pool is gone");
+        }
+    }
+}
+
+/// Runs the jobs of receiver until the pool is dropped. A job that panics doesn't
+/// take its thread with it, so the pool keeps its size.
+#[doc(hidden)]
+fn work(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+
+        match job {
+            Ok(job) => {
+                if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                    eprintln!("A job of the worker pool panicked");
+                }
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod worker_pool_test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn every_job_is_run() {
+        let workers = WorkerPool::new(3);
+        let (s, r) = channel();
+        for i in 0..20 {
+            let s = s.clone();
+            workers.execute(move || s.send(i).unwrap());
+        }
+
+        let mut results: Vec<i32> = r.iter().take(20).collect();
+        results.sort_unstable();
+        assert_eq!(results, (0..20).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn no_more_jobs_than_threads_run_at_the_same_time() {
+        let workers = WorkerPool::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let most_running = Arc::new(AtomicUsize::new(0));
+        let (s, r) = channel();
+        for _ in 0..8 {
+            let (running, most_running, s) = (running.clone(), most_running.clone(), s.clone());
+            workers.execute(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most_running.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                s.send(()).unwrap();
+            });
+        }
+
+        r.iter().take(8).for_each(drop);
+        assert_eq!(most_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn a_job_that_panics_does_not_stop_the_pool() {
+        let workers = WorkerPool::new(1);
+        workers.execute(|| panic!("job failed"));
+        let (s, r) = channel();
+        workers.execute(move || s.send(1).unwrap());
+
+        assert_eq!(r.recv_timeout(Duration::from_secs(1)).unwrap(), 1);
+    }
+}
@@ -0,0 +1,26 @@
+#!/bin/sh
+# Generates the self-signed certificates the TLS tests use: a CA, a certificate for
+# the server, valid for localhost and 127.0.0.1, and one for a client, both signed
+# by the CA.
+set -e
+cd "$(dirname "$0")"
+
+openssl genrsa -out ca.key 2048
+openssl req -x509 -new -nodes -sha256 -key ca.key -days 3650 \
+    -subj "/O=Proyecto Taller 1/CN=Test CA" -out ca.crt
+
+generate() {
+    name=$1
+    extensions=$2
+    openssl genrsa -out "$name.key" 2048
+    openssl req -new -sha256 -key "$name.key" -subj "/O=Proyecto Taller 1/CN=$name" \
+        -out "$name.csr"
+    printf '%b' "$extensions" > "$name.ext"
+    openssl x509 -req -sha256 -in "$name.csr" -CA ca.crt -CAkey ca.key -CAcreateserial \
+        -days 3650 -extfile "$name.ext" -out "$name.crt"
+    rm "$name.csr" "$name.ext"
+}
+
+generate redis "subjectAltName = DNS:localhost, IP:127.0.0.1\nextendedKeyUsage = serverAuth\n"
+generate client "extendedKeyUsage = clientAuth\n"
+rm -f ca.srl ca.key